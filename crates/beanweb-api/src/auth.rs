@@ -0,0 +1,476 @@
+//! Login sessions
+//!
+//! When `server.auth` is set every route requires a session. Logging in at
+//! `/login` creates a server-side session per device; the browser only keeps
+//! a `beanweb_session` cookie signed with HMAC-SHA256 (key from `keys`),
+//! never the credentials:
+//! - `/api/sessions` lists active sessions, `DELETE /api/sessions/:id` revokes one
+//! - `POST /logout` ends the current session
+//! - Sessions expire after `server.auth.session_days` without use; expired
+//!   ones are pruned every [`PRUNE_INTERVAL`]
+//! - With a second factor enrolled (see `two_factor`), the password only
+//!   leads to a code prompt; the session starts once the code checks out
+//!
+//! Scripts and API clients can skip the login and send the same credentials
+//! as HTTP Basic auth on every request instead. Unauthenticated `/api/`
+//! requests get a 401 with a `WWW-Authenticate: Basic` challenge; pages
+//! still redirect to `/login`. Prometheus scrapers can read `/metrics`, and
+//! nothing else, with `server.auth.metrics_token` as a bearer token.
+//!
+//! Against cross-site requests the cookie is `SameSite=Strict`, and
+//! [`check_origin`] refuses POST, PUT, PATCH and DELETE whose `Origin` (or
+//! `Referer`) names another host. Requests without either header are only
+//! let through when they don't rely on the cookie.
+//!
+//! Sessions live in memory, so a restart logs every device out.
+
+use crate::keys::{HmacSha256, ServerKey};
+use crate::AppState;
+use beanweb_config::AuthConfig;
+use axum::extract::{Path, Request};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use chrono::{DateTime, Duration, Utc};
+use hmac::Mac;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Cookie holding the signed session token
+pub const SESSION_COOKIE: &str = "beanweb_session";
+
+/// Paths reachable without a session
+const PUBLIC_PATHS: [&str; 3] = ["/login", "/login/verify", "/api/health"];
+
+/// Minutes to enter the second factor after the password
+const PENDING_LOGIN_MINUTES: i64 = 5;
+/// Wrong codes before a pending login has to start over
+const PENDING_LOGIN_ATTEMPTS: u8 = 5;
+
+/// `WWW-Authenticate` value of the 401 challenge
+const BASIC_CHALLENGE: &str = r#"Basic realm="beanweb", charset="UTF-8""#;
+
+/// How often expired sessions and pending logins are dropped
+pub const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// One logged-in device
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// Public ID used to revoke the session (not the cookie token)
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub user_agent: String,
+    /// Client address as reported by a proxy, when available
+    pub address: Option<String>,
+}
+
+/// Server-side session store shared by all requests
+pub type SessionStore = Arc<RwLock<Sessions>>;
+
+/// A login past the password, waiting for its second factor
+#[derive(Debug)]
+struct PendingLogin {
+    created_at: DateTime<Utc>,
+    attempts: u8,
+}
+
+/// Sessions by token, plus the key that signs the cookies
+#[derive(Debug)]
+pub struct Sessions {
+    by_token: HashMap<String, Session>,
+    /// Logins waiting for a second factor, by the token in the code form
+    pending: HashMap<String, PendingLogin>,
+    signing_key: [u8; 32],
+}
+
+/// Session store whose cookies are signed with a key derived from `key`
+pub fn open(key: &ServerKey) -> SessionStore {
+    Arc::new(RwLock::new(Sessions::new(key)))
+}
+
+impl Default for Sessions {
+    /// Sessions signed with a key for this process only
+    fn default() -> Self {
+        Self::new(&ServerKey::ephemeral())
+    }
+}
+
+impl Sessions {
+    pub fn new(key: &ServerKey) -> Self {
+        Self { by_token: HashMap::new(), pending: HashMap::new(), signing_key: key.derive("session-cookie") }
+    }
+
+    fn mac(&self, token: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+        mac.update(token.as_bytes());
+        mac
+    }
+
+    /// Cookie value: token plus its HMAC, so forged tokens are rejected up front
+    fn cookie_value(&self, token: &str) -> String {
+        let signature = self.mac(token).finalize().into_bytes();
+        format!("{}.{}", token, data_encoding::BASE64URL_NOPAD.encode(&signature))
+    }
+
+    /// Token of a cookie value whose signature matches
+    fn verify(&self, value: &str) -> Option<String> {
+        let (token, signature) = value.rsplit_once('.')?;
+        let signature = data_encoding::BASE64URL_NOPAD.decode(signature.as_bytes()).ok()?;
+        self.mac(token).verify_slice(&signature).ok().map(|_| token.to_string())
+    }
+
+    /// Create a session and return its cookie value
+    fn create(&mut self, user_agent: String, address: Option<String>) -> String {
+        let token = random_hex(32);
+        let now = Utc::now();
+        self.by_token.insert(token.clone(), Session {
+            id: random_hex(8),
+            created_at: now,
+            last_seen: now,
+            user_agent,
+            address,
+        });
+        self.cookie_value(&token)
+    }
+
+    /// Look up a session by cookie value, refreshing `last_seen`; an
+    /// expired one is dropped
+    fn touch(&mut self, value: &str, max_idle: Duration) -> Option<Session> {
+        let token = self.verify(value)?;
+        let now = Utc::now();
+        let session = self.by_token.get_mut(&token)?;
+        if now - session.last_seen >= max_idle {
+            self.by_token.remove(&token);
+            return None;
+        }
+        session.last_seen = now;
+        Some(session.clone())
+    }
+
+    /// Drop sessions idle for `max_idle` and pending logins past their time
+    fn prune(&mut self, max_idle: Duration) {
+        let now = Utc::now();
+        self.by_token.retain(|_, s| now - s.last_seen < max_idle);
+        self.pending.retain(|_, p| now - p.created_at < Duration::minutes(PENDING_LOGIN_MINUTES));
+    }
+
+    /// Start a login that still needs its second factor; returns its token
+    fn begin_pending(&mut self) -> String {
+        let token = random_hex(16);
+        self.pending.insert(token.clone(), PendingLogin { created_at: Utc::now(), attempts: 0 });
+        token
+    }
+
+    /// Count an attempt at the pending login `token`; false once it expired
+    /// or ran out of attempts
+    fn attempt_pending(&mut self, token: &str) -> bool {
+        let now = Utc::now();
+        let Some(pending) = self.pending.get_mut(token) else {
+            return false;
+        };
+        if now - pending.created_at >= Duration::minutes(PENDING_LOGIN_MINUTES) || pending.attempts >= PENDING_LOGIN_ATTEMPTS {
+            self.pending.remove(token);
+            return false;
+        }
+        pending.attempts += 1;
+        true
+    }
+
+    /// Remove the session with this public ID
+    fn revoke(&mut self, id: &str) -> bool {
+        let before = self.by_token.len();
+        self.by_token.retain(|_, s| s.id != id);
+        self.by_token.len() != before
+    }
+}
+
+/// Compare secrets without exiting early on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Whether the request carries the configured credentials as HTTP Basic auth;
+/// the encoded forms are compared, so nothing is decoded from the request
+fn basic_auth_ok(auth: &AuthConfig, headers: &HeaderMap) -> bool {
+    let Some((scheme, token)) = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().split_once(' '))
+    else {
+        return false;
+    };
+    let expected = data_encoding::BASE64.encode(format!("{}:{}", auth.username, auth.password).as_bytes());
+    scheme.eq_ignore_ascii_case("basic") && constant_time_eq(token.trim().as_bytes(), expected.as_bytes())
+}
+
+/// Whether the request carries `server.auth.metrics_token` as a bearer token
+fn metrics_token_ok(auth: &AuthConfig, headers: &HeaderMap) -> bool {
+    let Some(token) = auth.metrics_token.as_deref().filter(|t| !t.is_empty()) else {
+        return false;
+    };
+    headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+/// Session cookie of the request, if any
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Session of the request; `None` when auth is off or the cookie is invalid
+pub(crate) async fn current_session(state: &AppState, headers: &HeaderMap) -> Option<Session> {
+    let auth = state.config.server.auth.as_ref()?;
+    let cookie = session_cookie(headers)?;
+    let max_idle = Duration::days(auth.session_days.into());
+    state.sessions.write().await.touch(&cookie, max_idle)
+}
+
+/// Drop expired sessions every [`PRUNE_INTERVAL`], instead of on each request
+pub fn spawn_pruning(state: AppState) {
+    let Some(auth) = state.config.server.auth.clone() else {
+        return;
+    };
+    let max_idle = Duration::days(auth.session_days.into());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            state.sessions.write().await.prune(max_idle);
+        }
+    });
+}
+
+/// Host part (`host[:port]`) of an `Origin` or `Referer` value
+fn origin_host(value: &str) -> Option<&str> {
+    let rest = value.split_once("://")?.1;
+    Some(rest.split(['/', '?', '#']).next().unwrap_or(rest))
+}
+
+/// Whether the request names its own host as origin: `Some(false)` for
+/// another host, `None` when it carries neither `Origin` nor `Referer`
+fn same_origin(headers: &HeaderMap) -> Option<bool> {
+    let value = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok());
+    let origin = value(header::ORIGIN).filter(|o| *o != "null").or_else(|| value(header::REFERER))?;
+    let Some(host) = origin_host(origin) else {
+        return Some(false);
+    };
+    let forwarded = value(header::HeaderName::from_static("x-forwarded-host"));
+    Some([value(header::HOST), forwarded].into_iter().flatten().any(|own| own.eq_ignore_ascii_case(host)))
+}
+
+/// Middleware: refuse state-changing requests from other sites (see the module docs)
+pub async fn check_origin(
+    state: axum::extract::State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let safe = matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let allowed = safe || match same_origin(request.headers()) {
+        Some(same) => same,
+        // Nothing to compare: fine unless a browser's cookie does the authenticating
+        None => state.config.server.auth.is_none()
+            || request.headers().contains_key(header::AUTHORIZATION)
+            || session_cookie(request.headers()).is_none(),
+    };
+    if allowed {
+        next.run(request).await
+    } else {
+        tracing::warn!(method = %request.method(), path = request.uri().path(), "Cross-site request refused");
+        (StatusCode::FORBIDDEN, r#"{"success": false, "message": "跨站请求已拒绝"}"#).into_response()
+    }
+}
+
+/// Middleware: require a valid session or Basic credentials for everything
+/// but the login page
+pub async fn require_session(
+    state: axum::extract::State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let Some(auth) = &state.config.server.auth else {
+        return next.run(request).await;
+    };
+    if PUBLIC_PATHS.contains(&path) {
+        return next.run(request).await;
+    }
+    if path == "/metrics" && metrics_token_ok(auth, request.headers()) {
+        return next.run(request).await;
+    }
+    // Basic credentials can't carry a second factor
+    if basic_auth_ok(auth, request.headers()) && !state.two_factor.read().await.enrolled() {
+        return next.run(request).await;
+    }
+    if current_session(&state, request.headers()).await.is_some() {
+        return next.run(request).await;
+    }
+    let tried_basic = request.headers().contains_key(header::AUTHORIZATION);
+    if tried_basic {
+        // Same delay as a failed login, against guessing
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+    // Feed readers can't follow a redirect to the login page either
+    if path.starts_with("/api/") || path.starts_with("/feed/") || tried_basic {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, BASIC_CHALLENGE)],
+            r#"{"success": false, "message": "未登录"}"#,
+        )
+            .into_response();
+    }
+    if crate::is_htmx_request(request.headers()) {
+        // Let htmx navigate the whole page instead of swapping the login form into a fragment
+        return (StatusCode::UNAUTHORIZED, [(header::HeaderName::from_static("hx-redirect"), "/login")]).into_response();
+    }
+    Redirect::to("/login").into_response()
+}
+
+/// GET: Login page
+pub async fn page_login() -> axum::response::Html<String> {
+    axum::response::Html(beanweb_ui::auth::login_page(None))
+}
+
+/// POST: Check credentials and start a session
+pub async fn htmx_login(
+    state: axum::extract::State<AppState>,
+    headers: HeaderMap,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let Some(auth) = &state.config.server.auth else {
+        return Redirect::to("/").into_response();
+    };
+    let username = form.get("username").map(|s| s.as_str()).unwrap_or("");
+    let password = form.get("password").map(|s| s.as_str()).unwrap_or("");
+    // Evaluate both comparisons so timing doesn't reveal which one failed
+    let user_ok = constant_time_eq(username.as_bytes(), auth.username.as_bytes());
+    let password_ok = constant_time_eq(password.as_bytes(), auth.password.as_bytes());
+    if !(user_ok & password_ok) {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        return (StatusCode::UNAUTHORIZED, axum::response::Html(beanweb_ui::auth::login_page(Some("用户名或密码错误")))).into_response();
+    }
+    if state.two_factor.read().await.enrolled() {
+        let token = state.sessions.write().await.begin_pending();
+        return axum::response::Html(beanweb_ui::auth::code_page(&token, None)).into_response();
+    }
+    start_session(&state, auth, &headers).await
+}
+
+/// POST: Check the second factor of a pending login and start the session
+pub async fn htmx_login_verify(
+    state: axum::extract::State<AppState>,
+    headers: HeaderMap,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let Some(auth) = &state.config.server.auth else {
+        return Redirect::to("/").into_response();
+    };
+    let token = form.get("token").map(|s| s.as_str()).unwrap_or("");
+    if !state.sessions.write().await.attempt_pending(token) {
+        return (StatusCode::UNAUTHORIZED, axum::response::Html(beanweb_ui::auth::login_page(Some("验证已过期，请重新登录")))).into_response();
+    }
+    let code = form.get("code").map(|s| s.as_str()).unwrap_or("");
+    if !state.two_factor.write().await.verify(code) {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        return (StatusCode::UNAUTHORIZED, axum::response::Html(beanweb_ui::auth::code_page(token, Some("动态码不正确")))).into_response();
+    }
+    state.sessions.write().await.pending.remove(token);
+    start_session(&state, auth, &headers).await
+}
+
+/// Create the session and set its cookie
+async fn start_session(state: &AppState, auth: &AuthConfig, headers: &HeaderMap) -> Response {
+    let header_value = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    let user_agent = header_value(header::USER_AGENT).unwrap_or_default();
+    let address = header_value(header::HeaderName::from_static("x-forwarded-for"))
+        .and_then(|v| v.split(',').next().map(|a| a.trim().to_string()));
+    let value = state.sessions.write().await.create(user_agent, address);
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        SESSION_COOKIE, value, u64::from(auth.session_days) * 86400
+    );
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/")).into_response()
+}
+
+/// POST: End the current session
+pub async fn htmx_logout(state: axum::extract::State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(session) = current_session(&state, &headers).await {
+        state.sessions.write().await.revoke(&session.id);
+    }
+    let cookie = format!("{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Strict", SESSION_COOKIE);
+    let location = if state.config.server.auth.is_some() { "/login" } else { "/" };
+    (
+        [(header::SET_COOKIE, cookie), (header::HeaderName::from_static("hx-redirect"), location.to_string())],
+        Redirect::to(location),
+    )
+        .into_response()
+}
+
+/// All sessions, most recently used first
+async fn sorted_sessions(state: &AppState) -> Vec<Session> {
+    let mut sessions: Vec<Session> = state.sessions.read().await.by_token.values().cloned().collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_seen));
+    sessions
+}
+
+/// GET: Active sessions, newest activity first (JSON API)
+pub async fn api_sessions(state: axum::extract::State<AppState>, headers: HeaderMap) -> String {
+    let current = current_session(&state, &headers).await.map(|s| s.id);
+    let sessions = sorted_sessions(&state).await;
+    let items: Vec<serde_json::Value> = sessions.iter()
+        .map(|s| {
+            let mut item = serde_json::to_value(s).unwrap_or_default();
+            item["current"] = serde_json::Value::Bool(current.as_deref() == Some(s.id.as_str()));
+            item
+        })
+        .collect();
+    serde_json::json!({
+        "enabled": state.config.server.auth.is_some(),
+        "sessions": items,
+    })
+    .to_string()
+}
+
+/// DELETE: Revoke one session by its public ID (JSON API)
+pub async fn api_revoke_session(state: axum::extract::State<AppState>, id: Path<String>) -> Response {
+    if state.sessions.write().await.revoke(&id.0) {
+        r#"{"success": true, "message": "会话已注销"}"#.into_response()
+    } else {
+        (StatusCode::NOT_FOUND, r#"{"success": false, "message": "会话不存在"}"#).into_response()
+    }
+}
+
+/// HTMX: Session list for the settings page
+pub async fn htmx_sessions(state: axum::extract::State<AppState>, headers: HeaderMap) -> String {
+    if state.config.server.auth.is_none() {
+        return beanweb_ui::auth::auth_disabled("按设备管理会话");
+    }
+    let current = current_session(&state, &headers).await.map(|s| s.id);
+    let sessions = sorted_sessions(&state).await;
+    let rows: Vec<_> = sessions.iter()
+        .map(|s| beanweb_ui::auth::SessionRow {
+            id: &s.id,
+            user_agent: &s.user_agent,
+            address: s.address.as_deref(),
+            created_at: s.created_at,
+            last_seen: s.last_seen,
+            current: current.as_deref() == Some(s.id.as_str()),
+        })
+        .collect();
+    beanweb_ui::auth::sessions(&rows)
+}
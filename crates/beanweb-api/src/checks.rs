@@ -0,0 +1,248 @@
+//! Background integrity checks
+//!
+//! After every successful (re)load a background task runs the core integrity
+//! check and caches the result. Alerts only fire when the error set changes:
+//! - `/api/check/latest` returns the cached report with added/resolved issues
+//! - The sidebar badge shows until the current error set is acknowledged
+//!   (the acknowledged fingerprint lives in the `beanweb_check_seen` cookie)
+//! - `checks.webhook_url` receives a JSON POST on every change
+//!
+//! The banner on every page shows failing balance assertions and negative
+//! asset balances; they are computed once per load and kept in the cache.
+//!
+//! `/api/checks/balances` runs the balance assertion pass on demand and
+//! lists every `balance` directive with its outcome, file and line;
+//! `/api/checks/negative-balances` lists asset accounts below zero;
+//! `/api/insights/stale-balances` lists accounts not reconciled lately.
+
+use crate::AppState;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use beanweb_core::{BalanceCheck, IntegrityIssue, IntegrityReport, Ledger, NegativeBalance};
+use beanweb_ui::banners;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Cookie holding the fingerprint of the acknowledged error set
+pub const CHECK_SEEN_COOKIE: &str = "beanweb_check_seen";
+
+/// Shared cache of the latest check
+pub type CheckCache = Arc<RwLock<CheckState>>;
+
+/// Latest check result and how it differs from the run before
+#[derive(Debug, Clone, Default)]
+pub struct CheckState {
+    /// Most recent report, `None` until the first run finished
+    pub latest: Option<IntegrityReport>,
+    /// When the error set last changed (RFC 3339)
+    pub changed_at: Option<String>,
+    /// Issues that appeared with the last change
+    pub added: Vec<IntegrityIssue>,
+    /// Issues that disappeared with the last change
+    pub resolved: Vec<IntegrityIssue>,
+    /// What the banner shows, for the load it was computed from
+    pub banner: Option<BannerChecks>,
+}
+
+/// Failing balance assertions and negative asset balances of one load
+#[derive(Debug, Clone, Default)]
+pub struct BannerChecks {
+    /// Load count of [`Ledger::subscribe_reloads`] the lists belong to
+    pub generation: u64,
+    pub failed_balances: Vec<BalanceCheck>,
+    pub negative_balances: Vec<NegativeBalance>,
+}
+
+/// Banner lists of the loaded ledger: from the cache, computed on the first
+/// call after each load
+pub async fn banner_checks(state: &AppState, ledger: &Ledger) -> BannerChecks {
+    let generation = *ledger.subscribe_reloads().borrow();
+    if let Some(banner) = state.checks.read().await.banner.as_ref().filter(|b| b.generation == generation) {
+        return banner.clone();
+    }
+    let banner = BannerChecks {
+        generation,
+        failed_balances: ledger.check_balances().into_iter().filter(|c| !c.passed).collect(),
+        negative_balances: ledger.negative_balances(),
+    };
+    state.checks.write().await.banner = Some(banner.clone());
+    banner
+}
+
+impl CheckState {
+    /// Store a new report; returns true when the error set changed
+    fn update(&mut self, report: IntegrityReport) -> bool {
+        let changed = match &self.latest {
+            Some(previous) if previous.fingerprint == report.fingerprint => false,
+            Some(previous) => {
+                self.added = report.added_since(previous);
+                self.resolved = report.resolved_since(previous);
+                true
+            }
+            // First run: existing errors count as new, a clean ledger is no news
+            None => {
+                self.added = report.issues.clone();
+                !report.issues.is_empty()
+            }
+        };
+        if changed {
+            self.changed_at = Some(report.checked_at.clone());
+        }
+        self.latest = Some(report);
+        changed
+    }
+}
+
+/// Start the background task; it checks once now and again after every reload
+pub fn spawn_checks(state: AppState) {
+    if !state.config.checks.enabled {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut reloads = state.ledger.read().await.subscribe_reloads();
+        loop {
+            let report = state.ledger.read().await.integrity_check();
+            let count = report.issues.len();
+            let mut cache = state.checks.write().await;
+            if cache.update(report) {
+                tracing::info!("Integrity check: error set changed, {} issue(s)", count);
+                if let Some(url) = state.config.checks.webhook_url.clone() {
+                    let payload = webhook_payload(&cache);
+                    tokio::spawn(send_webhook(url, payload));
+                }
+            }
+            drop(cache);
+            if reloads.changed().await.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// JSON body for `/api/check/latest` and the webhook
+fn webhook_payload(cache: &CheckState) -> serde_json::Value {
+    let issues = cache.latest.as_ref().map(|r| r.issues.as_slice()).unwrap_or_default();
+    serde_json::json!({
+        "status": match &cache.latest {
+            None => "pending",
+            Some(report) if report.issues.is_empty() => "ok",
+            Some(_) => "error",
+        },
+        "checked_at": cache.latest.as_ref().map(|r| r.checked_at.clone()),
+        "fingerprint": cache.latest.as_ref().map(|r| r.fingerprint.clone()),
+        "changed_at": cache.changed_at,
+        "issue_count": issues.len(),
+        "issues": issues,
+        "added": cache.added,
+        "resolved": cache.resolved,
+    })
+}
+
+/// POST the payload to the configured webhook (plain http, 10s timeout)
+async fn send_webhook(url: String, payload: serde_json::Value) {
+    let request = hyper::Request::post(&url)
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(payload.to_string()));
+    let request = match request {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::warn!("Invalid integrity webhook URL {}: {}", url, e);
+            return;
+        }
+    };
+    let client = hyper::Client::new();
+    match tokio::time::timeout(std::time::Duration::from_secs(10), client.request(request)).await {
+        Ok(Ok(response)) if response.status().is_success() => {}
+        Ok(Ok(response)) => tracing::warn!("Integrity webhook returned {}", response.status()),
+        Ok(Err(e)) => tracing::warn!("Integrity webhook failed: {}", e),
+        Err(_) => tracing::warn!("Integrity webhook timed out"),
+    }
+}
+
+/// GET: Latest integrity check result (JSON API)
+pub async fn api_check_latest(state: axum::extract::State<AppState>) -> String {
+    if !state.config.checks.enabled {
+        return r#"{"status": "disabled"}"#.to_string();
+    }
+    webhook_payload(&*state.checks.read().await).to_string()
+}
+
+/// GET: Every balance assertion checked against the running balance;
+/// `?failed=true` lists only the failing ones (JSON API)
+pub async fn api_check_balances(
+    state: axum::extract::State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> String {
+    let checks = state.ledger.read().await.check_balances();
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    let only_failed = params.get("failed").is_some_and(|v| v == "true" || v == "1");
+    let assertions: Vec<_> = checks.iter().filter(|c| !only_failed || !c.passed).collect();
+    serde_json::json!({
+        "status": if failed == 0 { "ok" } else { "error" },
+        "total": checks.len(),
+        "failed": failed,
+        "assertions": assertions,
+    })
+    .to_string()
+}
+
+/// GET: Asset accounts with a negative balance and the transactions that
+/// took them there (JSON API)
+pub async fn api_negative_balances(state: axum::extract::State<AppState>) -> String {
+    let negative = state.ledger.read().await.negative_balances();
+    serde_json::json!({
+        "status": if negative.is_empty() { "ok" } else { "error" },
+        "count": negative.len(),
+        "accounts": negative,
+    })
+    .to_string()
+}
+
+/// GET: Accounts whose latest balance assertion is older than
+/// `checks.stale_balance_days` (JSON API)
+pub async fn api_stale_balances(state: axum::extract::State<AppState>) -> String {
+    let stale = state.ledger.read().await.stale_balances();
+    serde_json::json!({
+        "thresholds": state.config.checks.stale_balance_days,
+        "count": stale.len(),
+        "accounts": stale,
+    })
+    .to_string()
+}
+
+/// Fingerprint the user last acknowledged
+fn seen_fingerprint(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == CHECK_SEEN_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// HTMX: Sidebar badge, shown while the current error set is unacknowledged
+pub async fn htmx_check_badge(state: axum::extract::State<AppState>, headers: HeaderMap) -> String {
+    let cache = state.checks.read().await;
+    let Some(report) = &cache.latest else {
+        return String::new();
+    };
+    let seen = seen_fingerprint(&headers);
+    // Nothing new, or a clean ledger the user never saw broken
+    if seen.as_deref() == Some(report.fingerprint.as_str()) || (seen.is_none() && report.issues.is_empty()) {
+        return String::new();
+    }
+
+    banners::check_badge(report.issues.len(), &cache.added, cache.resolved.len())
+}
+
+/// POST: Acknowledge the current error set and hide the badge
+pub async fn htmx_check_seen(state: axum::extract::State<AppState>) -> impl IntoResponse {
+    let fingerprint = state.checks.read().await
+        .latest
+        .as_ref()
+        .map(|r| r.fingerprint.clone())
+        .unwrap_or_default();
+    let cookie = format!("{}={}; Path=/; Max-Age=31536000; SameSite=Lax", CHECK_SEEN_COOKIE, fingerprint);
+    ([(header::SET_COOKIE, cookie)], "")
+}
@@ -0,0 +1,263 @@
+//! Pagination, filtering and sorting shared by the JSON collection endpoints
+//!
+//! Every list endpoint (`/api/transactions`, `/api/accounts`, `/api/files`)
+//! takes the same JSON:API-style query parameters:
+//! - `page[size]` and `page[number]` (1-based); the size is capped at [`MAX_PAGE_SIZE`]
+//! - or `page[after]=<id>` on collections with [`Collection::CURSOR`]: the
+//!   page after that item in the default sort, empty for the first page.
+//!   The response has no totals then, and `links.next` carries the cursor
+//! - `filter[<field>]=<value>` for the fields the endpoint lists in [`Collection::FILTERS`]
+//! - `sort=<field>,-<field>`, a leading `-` sorting that field descending
+//!
+//! Unknown parameters, filters or sort fields are rejected with 400 rather
+//! than ignored, so clients still sending `limit`/`offset`/`q` notice. The
+//! response is a [`CollectionResponse`]: `{"data": [...], "meta": {...}, "links": {...}}`.
+
+use crate::ApiError;
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+
+/// Largest accepted `page[size]`
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Filters, sort fields and defaults of one collection endpoint
+pub trait Collection {
+    /// Accepted `filter[...]` fields
+    const FILTERS: &'static [&'static str];
+    /// Accepted `sort` fields
+    const SORTS: &'static [&'static str];
+    /// `sort` when the request has none
+    const DEFAULT_SORT: &'static str;
+    /// `page[size]` when the request has none
+    const DEFAULT_PAGE_SIZE: usize;
+    /// Whether `page[after]` is accepted
+    const CURSOR: bool = false;
+}
+
+/// One `sort` field
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// Validated collection parameters of a request to the collection `C`
+#[derive(Debug, Clone)]
+pub struct CollectionQuery<C> {
+    pub page_size: usize,
+    pub page_number: usize,
+    /// `page[after]`; `Some("")` asks for the first page by cursor
+    pub after: Option<String>,
+    pub filters: BTreeMap<String, String>,
+    pub sort: Vec<SortKey>,
+    /// Request path, for the pagination links
+    path: String,
+    collection: PhantomData<C>,
+}
+
+impl<C: Collection> CollectionQuery<C> {
+    /// Parse and validate the decoded query parameters
+    pub fn parse(path: &str, params: &HashMap<String, String>) -> Result<Self, ApiError> {
+        let bad_request = |message: String| ApiError::BadRequest { message };
+        let mut query = Self {
+            page_size: C::DEFAULT_PAGE_SIZE,
+            page_number: 1,
+            after: None,
+            filters: BTreeMap::new(),
+            sort: parse_sort(C::DEFAULT_SORT),
+            path: path.to_string(),
+            collection: PhantomData,
+        };
+
+        for (name, value) in params {
+            let field = name.strip_suffix(']').and_then(|n| n.split_once('['));
+            match (name.as_str(), field) {
+                ("page[size]", _) => {
+                    query.page_size = value.parse().ok().filter(|size| (1..=MAX_PAGE_SIZE).contains(size))
+                        .ok_or_else(|| bad_request(format!("page[size] must be between 1 and {}", MAX_PAGE_SIZE)))?;
+                }
+                ("page[number]", _) => {
+                    query.page_number = value.parse().ok().filter(|number| *number >= 1)
+                        .ok_or_else(|| bad_request("page[number] must be 1 or more".to_string()))?;
+                }
+                ("page[after]", _) if C::CURSOR => query.after = Some(value.trim().to_string()),
+                ("sort", _) => query.sort = parse_sort(value),
+                (_, Some(("filter", field))) if C::FILTERS.contains(&field) => {
+                    if !value.trim().is_empty() {
+                        query.filters.insert(field.to_string(), value.trim().to_string());
+                    }
+                }
+                (_, Some(("filter", field))) => {
+                    return Err(bad_request(format!("Unknown filter `{}`; supported: {}", field, C::FILTERS.join(", "))));
+                }
+                _ => {
+                    return Err(bad_request(format!("Unsupported query parameter `{}`; use page[size], page[number], filter[...] and sort", name)));
+                }
+            }
+        }
+
+        if let Some(key) = query.sort.iter().find(|key| !C::SORTS.contains(&key.field.as_str())) {
+            return Err(bad_request(format!("Unknown sort field `{}`; supported: {}", key.field, C::SORTS.join(", "))));
+        }
+        if query.after.is_some() {
+            if params.contains_key("page[number]") {
+                return Err(bad_request("page[after] and page[number] can't be combined".to_string()));
+            }
+            if query.sort != parse_sort(C::DEFAULT_SORT) {
+                return Err(bad_request(format!("page[after] only works with sort={}", C::DEFAULT_SORT)));
+            }
+        }
+        Ok(query)
+    }
+
+    /// The cursor of `page[after]`, None for the first page or offset paging
+    pub fn cursor(&self) -> Option<&str> {
+        self.after.as_deref().filter(|after| !after.is_empty())
+    }
+
+    /// Value of `filter[field]`, if given and not blank
+    pub fn filter(&self, field: &str) -> Option<&str> {
+        self.filters.get(field).map(|s| s.as_str())
+    }
+
+    /// Sort by the requested fields in order; `compare` orders two items
+    /// ascending by one field. Stable, so ties keep their order
+    pub fn sort<T>(&self, items: &mut [T], compare: impl Fn(&str, &T, &T) -> Ordering) {
+        items.sort_by(|a, b| {
+            self.sort.iter()
+                .map(|key| {
+                    let ordering = compare(&key.field, a, b);
+                    if key.descending { ordering.reverse() } else { ordering }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    /// The requested page of the already filtered and sorted `items`
+    pub fn paginate<T>(&self, items: Vec<T>) -> CollectionResponse<T> {
+        let total = items.len();
+        let total_pages = total.div_ceil(self.page_size).max(1);
+        let data = items.into_iter()
+            .skip((self.page_number - 1).saturating_mul(self.page_size))
+            .take(self.page_size)
+            .collect();
+        CollectionResponse {
+            data,
+            meta: CollectionMeta {
+                total: Some(total),
+                page: PageMeta { number: Some(self.page_number), size: self.page_size, total_pages: Some(total_pages) },
+            },
+            links: CollectionLinks {
+                self_link: self.link(self.page_number),
+                first: self.link(1),
+                last: Some(self.link(total_pages)),
+                prev: (self.page_number > 1).then(|| self.link((self.page_number - 1).min(total_pages))),
+                next: (self.page_number < total_pages).then(|| self.link(self.page_number + 1)),
+            },
+        }
+    }
+
+    /// A page fetched by `page[after]`; `next` is the cursor of the following
+    /// page, None on the last one
+    pub fn paginate_after<T>(&self, data: Vec<T>, next: Option<String>) -> CollectionResponse<T> {
+        let after = self.after.clone().unwrap_or_default();
+        CollectionResponse {
+            data,
+            meta: CollectionMeta {
+                total: None,
+                page: PageMeta { number: None, size: self.page_size, total_pages: None },
+            },
+            links: CollectionLinks {
+                self_link: self.link_with(&format!("page[after]={}", urlencoding::encode(&after))),
+                first: self.link_with("page[after]="),
+                last: None,
+                prev: None,
+                next: next.map(|next| self.link_with(&format!("page[after]={}", urlencoding::encode(&next)))),
+            },
+        }
+    }
+
+    /// This request's URL with another page number
+    fn link(&self, page_number: usize) -> String {
+        self.link_with(&format!("page[number]={}", page_number))
+    }
+
+    /// This request's URL with another page parameter
+    fn link_with(&self, page: &str) -> String {
+        let mut params: Vec<String> = self.filters.iter()
+            .map(|(field, value)| format!("filter[{}]={}", field, urlencoding::encode(value)))
+            .collect();
+        let sort: Vec<String> = self.sort.iter()
+            .map(|key| format!("{}{}", if key.descending { "-" } else { "" }, key.field))
+            .collect();
+        params.push(format!("sort={}", urlencoding::encode(&sort.join(","))));
+        params.push(format!("page[size]={}", self.page_size));
+        params.push(page.to_string());
+        format!("{}?{}", self.path, params.join("&"))
+    }
+}
+
+/// `-date,payee` → date descending, then payee ascending
+fn parse_sort(value: &str) -> Vec<SortKey> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| match field.strip_prefix('-') {
+            Some(field) => SortKey { field: field.to_string(), descending: true },
+            None => SortKey { field: field.to_string(), descending: false },
+        })
+        .collect()
+}
+
+#[axum::async_trait]
+impl<C: Collection, S: Send + Sync> FromRequestParts<S> for CollectionQuery<C> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let params = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map_err(|e| ApiError::BadRequest { message: e.body_text() })?;
+        Self::parse(parts.uri.path(), &params)
+    }
+}
+
+/// Response envelope of a collection endpoint
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionResponse<T> {
+    pub data: Vec<T>,
+    pub meta: CollectionMeta,
+    pub links: CollectionLinks,
+}
+
+/// Totals of the whole filtered collection
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionMeta {
+    /// Items across all pages, left out for `page[after]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    pub page: PageMeta,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PageMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<usize>,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<usize>,
+}
+
+/// URLs of this and the neighbouring pages, with the same filters and sort
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionLinks {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    pub first: String,
+    /// None for `page[after]`
+    pub last: Option<String>,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+}
@@ -10,9 +10,27 @@ pub enum ApiError {
     #[error("Bad request: {message}")]
     BadRequest { message: String },
 
+    #[error("Conflict: {message}")]
+    Conflict { message: String },
+
     #[error("Unauthorized")]
     Unauthorized,
 
     #[error("Internal server error")]
     InternalError,
 }
+
+impl axum::response::IntoResponse for ApiError {
+    /// `{"error": message}` with the matching status code
+    fn into_response(self) -> axum::response::Response {
+        use axum::http::StatusCode;
+        let status = match &self {
+            ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+            ApiError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, axum::Json(serde_json::json!({"error": self.to_string()}))).into_response()
+    }
+}
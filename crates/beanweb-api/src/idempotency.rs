@@ -0,0 +1,118 @@
+//! Idempotent transaction creation
+//!
+//! The create form carries a key generated when it is rendered (API clients
+//! send an `Idempotency-Key` header instead). The first successful response
+//! for a key is remembered, and a repeated submit with the same key gets that
+//! response back without appending a second copy:
+//! - Keys belong to the login session that sent them, so another device
+//!   can't collide with (or read back) them
+//! - Each key has its own lock, held for the create: a duplicate arriving
+//!   mid-request waits for the first one's response, other creates don't
+//! - Only successes are remembered, so a failed submit can be retried
+//! - Keys expire after [`KEY_TTL`]; at most [`MAX_KEYS`] are kept
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::http::HeaderMap;
+use rand::Rng;
+use tokio::sync::Mutex;
+
+/// Request header carrying the key
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// Form field carrying the key
+pub const IDEMPOTENCY_FIELD: &str = "idempotency_key";
+
+/// How long a key is remembered
+pub const KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Keys kept before the oldest are dropped
+pub const MAX_KEYS: usize = 1000;
+
+/// Longest accepted key
+const MAX_KEY_LEN: usize = 255;
+
+/// Remembered keys shared by all requests
+pub type IdempotencyStore = Arc<IdempotencyKeys>;
+
+/// The successful response of one key, once there is one
+#[derive(Debug, Default)]
+pub struct Remembered(Option<(Instant, String)>);
+
+impl Remembered {
+    /// The response, if it hasn't expired
+    pub fn get(&self) -> Option<String> {
+        self.0.as_ref()
+            .filter(|(seen, _)| seen.elapsed() < KEY_TTL)
+            .map(|(_, response)| response.clone())
+    }
+
+    /// Remember the successful response
+    pub fn remember(&mut self, response: String) {
+        self.0 = Some((Instant::now(), response));
+    }
+}
+
+/// One key; locked for the duration of a create with it
+pub type Slot = Arc<Mutex<Remembered>>;
+
+/// Slots by session scope and key. The map's own lock is only held to look
+/// a slot up, never across a create
+#[derive(Debug, Default)]
+pub struct IdempotencyKeys {
+    slots: std::sync::Mutex<HashMap<(String, String), Slot>>,
+}
+
+impl IdempotencyKeys {
+    /// Slot of `key` within `scope` (see [`request_scope`]), new if unseen
+    pub fn slot(&self, scope: &str, key: &str) -> Slot {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let id = (scope.to_string(), key.to_string());
+        if !slots.contains_key(&id) {
+            prune(&mut slots);
+        }
+        slots.entry(id).or_default().clone()
+    }
+}
+
+/// Drop expired and unused slots, then the oldest responses past [`MAX_KEYS`];
+/// slots in use by a request are kept
+fn prune(slots: &mut HashMap<(String, String), Slot>) {
+    slots.retain(|_, slot| {
+        Arc::strong_count(slot) > 1 || slot.try_lock().map_or(true, |remembered| remembered.get().is_some())
+    });
+    while slots.len() >= MAX_KEYS {
+        let oldest = slots.iter()
+            .filter(|(_, slot)| Arc::strong_count(slot) == 1)
+            .filter_map(|(id, slot)| slot.try_lock().ok().and_then(|r| r.0.as_ref().map(|(seen, _)| *seen)).map(|seen| (id.clone(), seen)))
+            .min_by_key(|(_, seen)| *seen);
+        match oldest {
+            Some((id, _)) => slots.remove(&id),
+            None => break,
+        };
+    }
+}
+
+/// Scope of a request's keys: its login session, empty without one (auth
+/// off or Basic credentials)
+pub async fn request_scope(state: &crate::AppState, headers: &HeaderMap) -> String {
+    crate::auth::current_session(state, headers).await.map(|s| s.id).unwrap_or_default()
+}
+
+/// Key of a request: the header, else the form field; blank or oversized keys are ignored
+pub fn request_key(headers: &HeaderMap, params: &HashMap<String, String>) -> Option<String> {
+    headers.get(IDEMPOTENCY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| params.get(IDEMPOTENCY_FIELD).map(|s| s.as_str()))
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+        .map(str::to_string)
+}
+
+/// Hidden form field with a fresh key
+pub fn form_field() -> String {
+    let mut rng = rand::thread_rng();
+    let key: String = (0..16).map(|_| format!("{:02x}", rng.gen::<u8>())).collect();
+    format!("<input type='hidden' name='{}' value='{}'>", IDEMPOTENCY_FIELD, key)
+}
@@ -0,0 +1,312 @@
+//! Background report jobs
+//!
+//! Reports over many years (All Time on a ledger going back a decade) take
+//! seconds to build. Instead of holding the ledger read lock for a whole
+//! request, a job builds the report on a blocking task one step at a time:
+//! - One step per year with transactions in the range, keeping that year's
+//!   income/expense totals as partial results while the job runs; a year
+//!   only walks its own transactions, so the steps add up to one pass
+//! - A last step building the full report, as JSON and as the HTMX fragment
+//! - The read lock is taken per step, so writes and other requests get in
+//!   between
+//!
+//! Starting a job already running or done for the same report, conversion
+//! mode and time range since the last reload joins that one, so a finished
+//! report is built once per reload. The last step fails when the time range
+//! was changed meanwhile. Failed jobs, and finished ones of earlier loads, are
+//! kept for [`JOB_TTL`], at most [`MAX_JOBS`] jobs in all.
+
+use crate::AppState;
+use beanweb_core::trends::TREND_MONTHS;
+use beanweb_core::{ConversionMode, Ledger, TimeContext};
+use beanweb_ui::reports;
+use chrono::{Datelike, NaiveDate};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a finished job can still be polled
+pub const JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Jobs kept before the oldest finished ones are dropped
+pub const MAX_JOBS: usize = 32;
+
+/// Jobs shared by all requests; only locked briefly, never across an await
+pub type ReportJobs = Arc<Mutex<JobTable>>;
+
+/// A report that can be built in a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportKind {
+    Overview,
+    Balance,
+    IncomeExpense,
+}
+
+impl ReportKind {
+    /// `overview`, `balance` or `income-expense`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "overview" => Some(Self::Overview),
+            "balance" => Some(Self::Balance),
+            "income-expense" => Some(Self::IncomeExpense),
+            _ => None,
+        }
+    }
+}
+
+/// What a job builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportRequest {
+    pub kind: ReportKind,
+    pub mode: ConversionMode,
+    /// Income/expense rolled up by report groups
+    pub grouped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+/// Income/expense totals of one year, in the report currency
+#[derive(Debug, Clone, Serialize)]
+pub struct YearTotals {
+    pub year: i32,
+    pub total_income: String,
+    pub total_expenses: String,
+    pub net_income: String,
+    pub currency: String,
+}
+
+/// A job and what it has built so far
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportJob {
+    pub id: String,
+    pub report: ReportKind,
+    pub status: JobStatus,
+    /// Steps finished
+    pub done: usize,
+    /// Steps in all
+    pub total: usize,
+    /// Totals of the years finished so far
+    pub partial: Vec<YearTotals>,
+    /// The full report once done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// The full report as the HTMX fragment once done
+    #[serde(skip)]
+    pub html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Report, mode, time range and ledger load, for joining a job
+    #[serde(skip)]
+    key: String,
+    /// Ledger load the job was started on
+    #[serde(skip)]
+    generation: u64,
+    /// Started, or finished once done
+    #[serde(skip)]
+    updated: Instant,
+}
+
+/// Jobs by id
+#[derive(Debug, Default)]
+pub struct JobTable {
+    jobs: HashMap<String, ReportJob>,
+    /// Latest ledger load a job was started on
+    generation: u64,
+}
+
+impl JobTable {
+    /// The job with `id`, if it hasn't expired
+    pub fn get(&self, id: &str) -> Option<ReportJob> {
+        self.jobs.get(id).filter(|job| self.keep(job)).cloned()
+    }
+
+    /// Running, done on the current load, or finished less than [`JOB_TTL`] ago
+    fn keep(&self, job: &ReportJob) -> bool {
+        match job.status {
+            JobStatus::Running => true,
+            JobStatus::Done if job.generation == self.generation => true,
+            _ => job.updated.elapsed() < JOB_TTL,
+        }
+    }
+
+    /// A running or done job for `key`
+    fn reusable(&self, key: &str) -> Option<String> {
+        self.jobs.values()
+            .find(|job| job.status != JobStatus::Failed && job.key == key)
+            .map(|job| job.id.clone())
+    }
+
+    fn insert(&mut self, job: ReportJob) {
+        self.generation = self.generation.max(job.generation);
+        let expired: Vec<String> = self.jobs.values().filter(|job| !self.keep(job)).map(|job| job.id.clone()).collect();
+        for id in expired {
+            self.jobs.remove(&id);
+        }
+        while self.jobs.len() >= MAX_JOBS {
+            let oldest = self.jobs.values()
+                .filter(|job| job.status != JobStatus::Running)
+                .min_by_key(|job| job.updated)
+                .map(|job| job.id.clone());
+            let Some(oldest) = oldest else { break };
+            self.jobs.remove(&oldest);
+        }
+        self.jobs.insert(job.id.clone(), job);
+    }
+
+    fn update(&mut self, id: &str, change: impl FnOnce(&mut ReportJob)) {
+        if let Some(job) = self.jobs.get_mut(id) {
+            change(job);
+        }
+    }
+}
+
+/// Years with transactions within `context`, oldest first
+pub fn years_in_range(ledger: &Ledger, context: &TimeContext) -> Vec<i32> {
+    let first = context.start_date().map(|d| d.year()).unwrap_or(i32::MIN);
+    let last = context.end_date().or(context.as_of()).map(|d| d.year()).unwrap_or(i32::MAX);
+    ledger.transaction_years().into_iter().filter(|year| (first..=last).contains(year)).collect()
+}
+
+/// `year` within the range of `context`
+fn year_context(context: &TimeContext, year: i32) -> Option<TimeContext> {
+    let mut start = NaiveDate::from_ymd_opt(year, 1, 1)?;
+    let mut end = NaiveDate::from_ymd_opt(year, 12, 31)?;
+    if let Some(range_start) = context.start_date() {
+        start = start.max(range_start);
+    }
+    if let Some(range_end) = context.end_date() {
+        end = end.min(range_end);
+    }
+    let mut year_context = TimeContext::custom(start, end);
+    year_context.include_future = context.include_future;
+    Some(year_context)
+}
+
+fn new_id() -> String {
+    let mut rng = rand::thread_rng();
+    (0..8).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+/// Start a job building `request` over the current time range, or join the
+/// one running or done since the last reload; returns the job id
+pub async fn start(state: &AppState, request: ReportRequest) -> String {
+    let (context, years, generation) = {
+        let ledger = state.ledger.read().await;
+        let context = ledger.time_context();
+        let years = years_in_range(&ledger, &context);
+        let generation = *ledger.subscribe_reloads().borrow();
+        (context, years, generation)
+    };
+    let years = if request.kind == ReportKind::Balance { Vec::new() } else { years };
+    let key = format!("{:?} {:?} {} {:?} {}", request.kind, request.mode, request.grouped, context, generation);
+
+    let id = {
+        let mut jobs = state.jobs.lock().unwrap();
+        if let Some(id) = jobs.reusable(&key) {
+            return id;
+        }
+        let id = new_id();
+        jobs.insert(ReportJob {
+            id: id.clone(),
+            report: request.kind,
+            status: JobStatus::Running,
+            done: 0,
+            total: years.len() + 1,
+            partial: Vec::new(),
+            result: None,
+            html: None,
+            error: None,
+            key,
+            generation,
+            updated: Instant::now(),
+        });
+        id
+    };
+    tokio::spawn(run(state.clone(), id.clone(), request, context, years));
+    id
+}
+
+async fn run(state: AppState, id: String, request: ReportRequest, context: TimeContext, years: Vec<i32>) {
+    for year in years {
+        let Some(year_context) = year_context(&context, year) else { continue };
+        let ledger = state.ledger.clone();
+        let step = tokio::task::spawn_blocking(move || {
+            let report = ledger.blocking_read().income_expense_report_in(&year_context);
+            YearTotals {
+                year,
+                total_income: report.total_income,
+                total_expenses: report.total_expenses,
+                net_income: report.net_income,
+                currency: report.currency,
+            }
+        }).await;
+        match step {
+            Ok(totals) => state.jobs.lock().unwrap().update(&id, |job| {
+                job.partial.push(totals);
+                job.done += 1;
+            }),
+            Err(e) => return fail(&state, &id, e.to_string()),
+        }
+    }
+
+    let ledger = state.ledger.clone();
+    let step = tokio::task::spawn_blocking(move || {
+        let ledger = ledger.blocking_read();
+        if ledger.time_context() != context {
+            return Err("时间范围已改变，请重新生成报表".to_string());
+        }
+        Ok(build(&ledger, request))
+    }).await;
+    match step {
+        Ok(Ok((result, html))) => state.jobs.lock().unwrap().update(&id, |job| {
+            job.done = job.total;
+            job.status = JobStatus::Done;
+            job.result = Some(result);
+            job.html = Some(html);
+            job.updated = Instant::now();
+        }),
+        Ok(Err(message)) => fail(&state, &id, message),
+        Err(e) => fail(&state, &id, e.to_string()),
+    }
+}
+
+fn fail(state: &AppState, id: &str, message: String) {
+    tracing::error!("Report job {} failed: {}", id, message);
+    state.jobs.lock().unwrap().update(id, |job| {
+        job.status = JobStatus::Failed;
+        job.error = Some(message);
+        job.updated = Instant::now();
+    });
+}
+
+/// The full report over the current time range, as JSON and as HTML
+fn build(ledger: &Ledger, request: ReportRequest) -> (serde_json::Value, String) {
+    let ReportRequest { kind, mode, grouped } = request;
+    match kind {
+        ReportKind::Overview => {
+            let balance = ledger.balance_report_with(mode);
+            let income_expense = ledger.income_expense_report_with(mode);
+            let html = reports::overview(&balance, &income_expense, &ledger.category_trends(TREND_MONTHS, false), mode);
+            (serde_json::json!({ "balance": balance, "income_expense": income_expense }), html)
+        }
+        ReportKind::Balance => {
+            let balance = ledger.balance_report_with(mode);
+            let html = reports::balance_sheet(&balance, mode);
+            (serde_json::to_value(&balance).unwrap_or_default(), html)
+        }
+        ReportKind::IncomeExpense => {
+            let report = if grouped { ledger.grouped_income_expense_report_with(mode) } else { ledger.income_expense_report_with(mode) };
+            let html = reports::income_expense(&report, &ledger.category_trends(TREND_MONTHS, grouped), grouped, mode);
+            (serde_json::to_value(&report).unwrap_or_default(), html)
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Server key
+//!
+//! One random 32-byte key, kept in `server.auth.key_file`, from which the
+//! keys for each purpose are derived with HMAC-SHA256:
+//! - The file is created on first start, readable by its owner only
+//! - It belongs outside `data.path`, so a copy of the ledger directory
+//!   doesn't carry what it takes to forge a session cookie or to read
+//!   the second-factor secret
+//! - Without `server.auth` nothing is written; a random key lives as long
+//!   as the process
+//!
+//! Replacing the file logs every device out, and the enrolled authenticator
+//! stops working until it is enrolled again; recovery codes still do.
+
+use beanweb_config::Config;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::path::Path;
+
+/// HMAC-SHA256, as used for everything keyed here
+pub type HmacSha256 = Hmac<Sha256>;
+
+/// Key length in bytes
+const KEY_LEN: usize = 32;
+
+/// Root key of the server; derive a key per purpose instead of using it directly
+#[derive(Clone)]
+pub struct ServerKey([u8; KEY_LEN]);
+
+impl std::fmt::Debug for ServerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ServerKey(..)")
+    }
+}
+
+impl ServerKey {
+    /// Random key for this process only
+    pub fn ephemeral() -> Self {
+        Self(rand::thread_rng().gen())
+    }
+
+    /// Key of `config`: read from `server.auth.key_file`, created there when
+    /// missing; ephemeral when auth is off
+    pub fn open(config: &Config) -> std::io::Result<Self> {
+        match &config.server.auth {
+            Some(auth) => Self::load_or_create(&auth.key_file),
+            None => Ok(Self::ephemeral()),
+        }
+    }
+
+    fn load_or_create(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                let bytes = data_encoding::HEXLOWER_PERMISSIVE.decode(content.trim().as_bytes())
+                    .ok()
+                    .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+                    .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} is not a {}-byte hex key", path.display(), KEY_LEN)))?;
+                Ok(Self(bytes))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = Self::ephemeral();
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut options = std::fs::OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                let content = format!("{}\n", data_encoding::HEXLOWER.encode(&key.0));
+                std::io::Write::write_all(&mut options.open(path)?, content.as_bytes())?;
+                tracing::info!("Created server key {}", path.display());
+                Ok(key)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Key for `purpose`, independent of the keys for other purposes
+    pub fn derive(&self, purpose: &str) -> [u8; KEY_LEN] {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts any key length");
+        mac.update(purpose.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+}
@@ -1,16 +1,46 @@
 //! HTTP API server with HTMX support
 //!
 //! Routes are organized into modules:
-//! - routes::transactions: Transaction list, search, pagination
+//! - routes::transactions: Transaction list, search, pagination, create, edit and delete
 //! - routes::accounts: Account list, tree view
-//! - routes::reports: Balance and income-expense reports
+//! - routes::reports: Balance sheet, income-expense, category and payee reports
+//! - routes::budgets: Spent vs budget per account
+//! - routes::tags: Tag list with counts and amounts
+//! - routes::templates: Transaction templates for the create form
+//! - routes::rules: Categorization rules and their settings page
+//! - routes::timeline: Events timeline and stored queries
+//! - routes::import: Bank statement (CSV) import
 //! - routes::settings: Configuration display
+//! - privacy: Amount masking for screen-sharing
+//! - checks: Background integrity checks and alerts
+//! - auth: Login sessions when `server.auth` is set, and the cross-site request check
+//! - keys: The server key that signs session cookies
+//! - two_factor: Optional authenticator-app codes and recovery codes at login
+//! - timing: Slow request logging and the slow operations list
+//! - metrics: Request log and Prometheus metrics at `/metrics`
+//! - idempotency: Remembered keys against double-submitted creates
+//! - jobs: Reports over long ranges built in the background
+//! - collection: Shared page/filter/sort parameters and envelope of JSON list endpoints
+//!
+//! The page shell, dashboard and report HTML come from beanweb-ui; handlers
+//! fetch the data from the ledger and pass it on.
 
+pub mod auth;
+pub mod checks;
+pub mod collection;
 pub mod error;
+pub mod idempotency;
+pub mod jobs;
+pub mod keys;
+pub mod metrics;
+pub mod privacy;
 pub mod routes;
+pub mod timing;
+pub mod two_factor;
+pub mod watch;
 
 use axum::{
-    routing::{get, put, post},
+    routing::{delete, get, put, post},
     Router,
 };
 use tokio::net::TcpListener;
@@ -20,37 +50,94 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 pub use error::ApiError;
+pub use beanweb_ui::layout::{base_html, header_bar, nav_sidebar, page_time_selector};
+
+use beanweb_ui::dashboard::{self, TopCard};
+use beanweb_ui::{banners, html_escape, layout};
 
 /// Application state
 #[derive(Clone)]
 pub struct AppState {
     pub ledger: Arc<RwLock<Ledger>>,
     pub config: Config,
+    pub checks: checks::CheckCache,
+    pub sessions: auth::SessionStore,
+    pub two_factor: two_factor::TwoFactorStore,
+    pub idempotency: idempotency::IdempotencyStore,
+    pub jobs: jobs::ReportJobs,
+    pub metrics: metrics::RequestMetrics,
+}
+
+impl AppState {
+    /// The ledger through its stable facade, which handlers that write
+    /// transactions go through
+    pub fn service(&self) -> beanweb_core::LedgerService {
+        beanweb_core::LedgerService::from_shared(self.ledger.clone())
+    }
 }
 
 /// Create the application router
 pub fn create_router(state: AppState) -> Router {
     // Import route handlers
-    use routes::transactions::{api_transactions, api_transaction_detail, htmx_transactions_list, htmx_transactions_filter, htmx_transaction_detail, page_transactions, page_transaction_create, htmx_transaction_create_form, htmx_transaction_store};
-    use routes::accounts::{api_accounts, htmx_accounts_list, htmx_account_suggest, page_accounts, page_account_detail, htmx_account_transactions_list};
-    // NOTE: 报表功能已禁用
-    // use routes::reports::{api_balance_report, api_income_expense, page_reports, htmx_reports_overview, htmx_reports_balance, htmx_reports_income_expense, htmx_reports_category};
+    use routes::transactions::{api_transactions, api_transactions_export, api_transaction_detail, api_transaction_breakdown, api_transaction_delete, api_link_group, api_evaluate_amount, htmx_transactions_list, htmx_transactions_filter, htmx_transaction_detail, htmx_transaction_breakdown, htmx_transactions_upcoming, htmx_transactions_review_banner, htmx_transactions_mark_reviewed, page_transactions, page_transaction, page_transaction_create, page_transaction_edit, htmx_transaction_create_form, htmx_transaction_edit_form, htmx_transaction_update, htmx_transaction_delete, htmx_transaction_store, api_suspense, api_recategorize, page_suspense, htmx_suspense_list, htmx_recategorize, htmx_suspense_badge};
+    use routes::accounts::{api_accounts, api_account_changes, api_account_monthly, api_account_pause, api_account_resume, htmx_account_monthly, htmx_account_paused, api_currencies, htmx_accounts_list, htmx_account_suggest, htmx_account_picker, page_accounts, page_account_detail, htmx_account_transactions_list};
+    use routes::reports::{api_balance_report, api_income_expense, api_monthly_summary, api_allocation_report, api_holdings_report, api_payees, api_report_digest, api_start_report_job, api_report_job, htmx_report_job, page_reports, htmx_reports_overview, htmx_reports_balance, htmx_reports_income_expense, htmx_reports_category, htmx_reports_allocation, htmx_reports_holdings, htmx_reports_monthly, htmx_reports_payees};
     use routes::settings::{api_settings, api_settings_metadata, page_settings};
     use routes::time::{api_time_range, api_set_time_range, api_time_range_options, api_time_range_months, api_time_range_years};
-    use routes::files::{api_files_list, api_file_content, api_file_save, page_files, page_file_edit};
-    // NOTE: 货币功能已禁用
-    // use crate::routes::commodities::page_commodities;
+    use routes::files::{api_files_list, api_file_content, api_file_save, api_document, api_orphaned_files, api_include_orphan, htmx_orphaned_files, htmx_include_orphan, page_files, page_file_edit};
+    use routes::export::{api_export_anonymized, api_export_beancount};
+    use routes::events::api_events;
+    use routes::tools::{api_account_import, api_account_import_preview, api_account_templates, api_balance_import, api_balance_import_preview, api_bootstrap_accounts, api_opening_balances, api_opening_balances_preview, htmx_account_import_preview, htmx_balance_import_preview, htmx_opening_balances_preview, api_transaction_import, api_transaction_import_preview, htmx_transaction_import_preview, page_account_import, page_balance_import, page_bootstrap_accounts, page_opening_balances};
+    use crate::routes::commodities::{api_commodities, page_commodities};
+    use routes::budgets::{api_budgets, htmx_budgets_list, page_budgets};
+    use routes::tags::{api_tags, htmx_tags_list, page_tags};
+    use routes::templates::{api_template, api_template_create, api_template_delete, api_template_update, api_templates};
+    use routes::rules::{api_rule_match, api_rules, htmx_rule_test, page_rules};
 
     Router::new()
         // API endpoints
         .route("/api/health", get(health_check))
         .route("/api/accounts", get(api_accounts))
+        .route("/api/accounts/changes", get(api_account_changes))
+        .route("/api/accounts/:name/monthly", get(api_account_monthly))
+        .route("/api/accounts/:name/pause", post(api_account_pause))
+        .route("/api/accounts/:name/resume", post(api_account_resume))
+        .route("/api/currencies", get(api_currencies))
         .route("/api/transactions", get(api_transactions))
-        .route("/api/transactions/:id", get(api_transaction_detail))
+        .route("/api/transactions/evaluate-amount", get(api_evaluate_amount))
+        .route("/api/transactions/export", get(api_transactions_export))
+        .route("/api/transactions/:id", get(api_transaction_detail).delete(api_transaction_delete))
+        .route("/api/transactions/:id/breakdown", get(api_transaction_breakdown))
+        .route("/api/suspense", get(api_suspense))
+        .route("/api/suspense/recategorize", post(api_recategorize))
+        .route("/api/links/:link", get(api_link_group))
         .route("/api/summary", get(api_summary))
-        // NOTE: 报表API已禁用
-        // .route("/api/reports/balance", get(api_balance_report))
-        // .route("/api/reports/income-expense", get(api_income_expense))
+        .route("/api/status", get(api_status))
+        .route("/api/errors", get(api_errors))
+        .route("/api/options", get(api_options))
+        .route("/api/admin/slow", get(timing::api_slow))
+        .route("/metrics", get(metrics::metrics))
+        .route("/api/events", get(api_events))
+        .route("/feed/transactions.xml", get(routes::feed::feed_transactions))
+        .route("/api/reports/balance", get(api_balance_report))
+        .route("/api/commodities", get(api_commodities))
+        .route("/api/budgets", get(api_budgets))
+        .route("/api/tags", get(api_tags))
+        .route("/api/ledger-events", get(routes::timeline::api_ledger_events))
+        .route("/api/queries", get(routes::timeline::api_queries))
+        .route("/api/templates", get(api_templates).post(api_template_create))
+        .route("/api/templates/:name", get(api_template).put(api_template_update).delete(api_template_delete))
+        .route("/api/rules", get(api_rules))
+        .route("/api/rules/match", get(api_rule_match))
+        .route("/api/reports/income-expense", get(api_income_expense))
+        .route("/api/reports/monthly", get(api_monthly_summary))
+        .route("/api/reports/allocation", get(api_allocation_report))
+        .route("/api/reports/holdings", get(api_holdings_report))
+        .route("/api/reports/digest.html", get(api_report_digest))
+        .route("/api/reports/jobs", post(api_start_report_job))
+        .route("/api/reports/jobs/:id", get(api_report_job))
+        .route("/api/payees", get(api_payees))
+        .route("/api/recurring", get(api_recurring))
         .route("/api/settings", get(api_settings))
         .route("/api/settings/metadata", get(api_settings_metadata))
         .route("/api/time-range", get(api_time_range))
@@ -61,40 +148,113 @@ pub fn create_router(state: AppState) -> Router {
         .route("/api/files", get(api_files_list))
         .route("/api/files/*path", get(api_file_content))
         .route("/api/files/*path", put(api_file_save))
+        .route("/api/documents/*path", get(api_document))
+        .route("/api/includes", post(api_include_orphan))
+        .route("/api/includes/orphans", get(api_orphaned_files))
         .route("/api/reload", post(api_reload))
+        .route("/api/check/latest", get(checks::api_check_latest))
+        .route("/api/checks/balances", get(checks::api_check_balances))
+        .route("/api/checks/negative-balances", get(checks::api_negative_balances))
+        .route("/api/insights/stale-balances", get(checks::api_stale_balances))
+        .route("/api/sessions", get(auth::api_sessions))
+        .route("/api/sessions/:id", delete(auth::api_revoke_session))
+        .route("/api/export/anonymized", get(api_export_anonymized))
+        .route("/api/export/beancount", get(api_export_beancount))
+        .route("/api/tools/account-templates", get(api_account_templates))
+        .route("/api/tools/bootstrap-accounts", post(api_bootstrap_accounts))
+        .route("/api/tools/accounts/preview", post(api_account_import_preview))
+        .route("/api/tools/accounts/import", post(api_account_import))
+        .route("/api/tools/balances/preview", post(api_balance_import_preview))
+        .route("/api/tools/balances/import", post(api_balance_import))
+        .route("/api/tools/transactions/preview", post(api_transaction_import_preview))
+        .route("/api/tools/transactions/import", post(api_transaction_import))
+        .route("/api/tools/opening-balances/preview", post(api_opening_balances_preview))
+        .route("/api/tools/opening-balances", post(api_opening_balances))
+        .route("/api/import/csv", post(routes::import::api_import_csv))
         // HTMX page routes
+        .route("/status/banner", get(htmx_status_banner))
+        .route("/status/title", get(htmx_status_title))
+        .route("/login", get(auth::page_login).post(auth::htmx_login))
+        .route("/login/verify", post(auth::htmx_login_verify))
+        .route("/logout", post(auth::htmx_logout))
+        .route("/settings/sessions", get(auth::htmx_sessions))
+        .route("/settings/two-factor", get(two_factor::htmx_two_factor))
+        .route("/settings/two-factor/setup", post(two_factor::htmx_two_factor_setup))
+        .route("/settings/two-factor/enable", post(two_factor::htmx_two_factor_enable))
+        .route("/settings/two-factor/recovery", post(two_factor::htmx_two_factor_recovery))
+        .route("/settings/two-factor/disable", post(two_factor::htmx_two_factor_disable))
+        .route("/check/badge", get(checks::htmx_check_badge))
+        .route("/check/seen", post(checks::htmx_check_seen))
         .route("/", get(index_page))
         .route("/dashboard", get(page_dashboard))
+        .route("/dashboard/top/:card", get(htmx_dashboard_top))
         .route("/accounts", get(page_accounts))
         .route("/accounts/:name", get(page_account_detail))
         .route("/transactions", get(page_transactions))
-        // NOTE: 报表页面已禁用
-        // .route("/reports", get(page_reports))
-        // .route("/reports/overview", get(htmx_reports_overview))
-        // .route("/reports/balance", get(htmx_reports_balance))
-        // .route("/reports/income-expense", get(htmx_reports_income_expense))
-        // .route("/reports/category", get(htmx_reports_category))
+        .route("/reports", get(page_reports))
+        .route("/reports/overview", get(htmx_reports_overview))
+        .route("/reports/balance", get(htmx_reports_balance))
+        .route("/reports/income-expense", get(htmx_reports_income_expense))
+        .route("/reports/monthly", get(htmx_reports_monthly))
+        .route("/reports/category", get(htmx_reports_category))
+        .route("/reports/allocation", get(htmx_reports_allocation))
+        .route("/reports/holdings", get(htmx_reports_holdings))
+        .route("/reports/payees", get(htmx_reports_payees))
+        .route("/reports/jobs/:id", get(htmx_report_job))
+        .route("/tools/bootstrap", get(page_bootstrap_accounts))
+        .route("/tools/accounts", get(page_account_import))
+        .route("/tools/accounts/preview", post(htmx_account_import_preview))
+        .route("/tools/balances", get(page_balance_import))
+        .route("/tools/balances/preview", post(htmx_balance_import_preview))
+        .route("/tools/transactions/preview", post(htmx_transaction_import_preview))
+        .route("/tools/opening-balances", get(page_opening_balances))
+        .route("/tools/opening-balances/preview", post(htmx_opening_balances_preview))
+        .route("/includes", post(htmx_include_orphan))
+        .route("/includes/orphans", get(htmx_orphaned_files))
         .route("/files", get(page_files))
         .route("/files/*path", get(page_file_edit))
         // NOTE: 货币页面已禁用
-        // .route("/commodities", get(page_commodities))
+        .route("/commodities", get(page_commodities))
+        .route("/budgets", get(page_budgets))
+        .route("/budgets/list", get(htmx_budgets_list))
+        .route("/tags", get(page_tags))
+        .route("/timeline", get(routes::timeline::page_timeline))
+        .route("/tags/list", get(htmx_tags_list))
         .route("/settings", get(page_settings))
+        .route("/settings/rules", get(page_rules))
+        .route("/settings/rules/test", get(htmx_rule_test))
         // HTMX partial routes (for tab content)
         .route("/accounts/list", get(htmx_accounts_list))
         .route("/accounts/suggest", get(htmx_account_suggest))
+        .route("/accounts/picker", get(htmx_account_picker))
         .route("/accounts/:name/transactions/list", get(htmx_account_transactions_list))
+        .route("/accounts/:name/monthly", get(htmx_account_monthly))
+        .route("/accounts/:name/paused", post(htmx_account_paused))
         .route("/transactions/list", get(htmx_transactions_list))
         .route("/transactions/filter", get(htmx_transactions_filter))
+        .route("/transactions/upcoming", get(htmx_transactions_upcoming))
+        .route("/transactions/review", get(htmx_transactions_review_banner).post(htmx_transactions_mark_reviewed))
+        .route("/transactions/suspense", get(page_suspense))
+        .route("/transactions/suspense/list", get(htmx_suspense_list))
+        .route("/transactions/suspense/recategorize", post(htmx_recategorize))
+        .route("/transactions/suspense/badge", get(htmx_suspense_badge))
         .route("/transactions/:id/detail", get(htmx_transaction_detail))
-        // NOTE: 编辑功能已禁用
-        // .route("/transactions/:id/edit", get(page_transaction_edit))
-        // .route("/transactions/:id/edit/form", get(htmx_transaction_edit_form))
-        // .route("/transactions/:id", put(htmx_transaction_update))
+        .route("/transactions/:id/breakdown", get(htmx_transaction_breakdown))
+        .route("/transactions/:id/edit", get(page_transaction_edit))
+        .route("/transactions/:id/edit/form", get(htmx_transaction_edit_form))
+        .route("/transactions/:id", get(page_transaction).put(htmx_transaction_update).delete(htmx_transaction_delete))
         // Transaction create routes
         .route("/transactions/create", get(page_transaction_create))
         .route("/transactions/create/form", get(htmx_transaction_create_form))
-        .route("/transactions", post(htmx_transaction_store))
-        .with_state(state)
+        .route("/transactions", post(htmx_transaction_store).layer(axum::extract::DefaultBodyLimit::max(routes::transactions::api::MAX_DOCUMENT_UPLOAD_BYTES)))
+        // Privacy mode
+        .route("/privacy/toggle", post(privacy::htmx_privacy_toggle))
+        .with_state(state.clone())
+        .layer(axum::middleware::from_fn_with_state(state.clone(), timing::time_requests))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth::require_session))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth::check_origin))
+        .layer(axum::middleware::from_fn(privacy::mask_amounts_layer))
+        .layer(axum::middleware::from_fn_with_state(state, metrics::log_requests))
 }
 
 /// Health check endpoint
@@ -109,431 +269,64 @@ async fn api_summary(state: axum::extract::State<AppState>) -> String {
     serde_json::to_string(&summary).unwrap_or_default()
 }
 
-// ==================== Template Functions ====================
-
-/// Base HTML template
-pub fn base_html(title: &str, content: &str) -> String {
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="zh-CN">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{} - Beanweb</title>
-    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
-    <script src="https://cdn.tailwindcss.com"></script>
-    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/tailwindcss@2.2.19/dist/tailwind.min.css">
-    <style>
-        .htmx-indicator {{ opacity: 0; transition: opacity 0.3s; }}
-        .htmx-request .htmx-indicator {{ opacity: 1; }}
-        .htmx-request.htmx-indicator {{ opacity: 1; }}
-    </style>
-</head>
-<body class="bg-gray-50 text-gray-900">
-    {}
-</body>
-</html>"#,
-        title, content
-    )
+/// Recurring payments with their predicted next dates (JSON API)
+async fn api_recurring(state: axum::extract::State<AppState>) -> String {
+    let payments = state.ledger.read().await.recurring_payments();
+    serde_json::json!({
+        "count": payments.len(),
+        "payments": payments,
+    })
+    .to_string()
 }
 
-/// Navigation sidebar (without time selector - for backward compatibility)
-pub fn nav_sidebar(current_path: &str) -> String {
-    let links = [
-        ("/", "仪表盘", "dashboard"),
-        ("/accounts", "账户", "accounts"),
-        ("/transactions", "流水", "transactions"),
-        // NOTE: 货币功能已禁用
-        // ("/commodities", "货币", "commodities"),
-        // NOTE: 报表功能已禁用
-        // ("/reports", "报表", "reports"),
-        ("/files", "文件", "files"),
-        ("/settings", "设置", "settings"),
-    ];
-
-    let mut nav = String::from("<div class='bg-white border-r h-screen flex flex-col'><div class='p-4 border-b'><h1 class='text-xl font-bold text-indigo-600'>Beanweb</h1></div><ul class='flex-1 py-2 space-y-1 px-2'>");
-
-    for (path, label, id) in &links {
-        let is_active = if *path == "/" {
-            current_path == "/"
-        } else {
-            current_path.starts_with(path)
-        };
-        let active_class = if is_active { "bg-indigo-50 text-indigo-600" } else { "text-gray-600 hover:bg-gray-50" };
-        let icon = match *id {
-            "dashboard" => "📊",
-            "accounts" => "💰",
-            "transactions" => "📋",
-            "reports" => "📈",
-            "files" => "📄",
-            "settings" => "⚙️",
-            _ => "📄",
-        };
-        nav.push_str(&format!(
-            r#"<li><a href='{}' class='flex items-center gap-2 px-3 py-2 rounded-lg {}'>{}<span>{}</span></a></li>"#,
-            path, active_class, icon, label
-        ));
-    }
-    nav.push_str("</ul></div>");
-    nav
+/// Server and ledger load status (JSON API)
+async fn api_status(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    serde_json::json!({
+        "status": if ledger.load_status().error.is_some() { "error" } else { "ok" },
+        "startup_mode": state.config.server.startup_mode.to_string(),
+        "load": ledger.load_status(),
+        "parse_errors": ledger.parse_errors(),
+        "summary": ledger.summary(),
+    })
+    .to_string()
 }
 
-/// Page-level time selector - For placing at top of pages
-/// With year/month dropdowns that can be combined, plus custom date picker with drill-down
-pub fn page_time_selector(current_range: &str, _start_date: &str, _end_date: &str) -> String {
-    // Parse current year and month from range string
-    let (selected_year, selected_month) = if current_range.starts_with("year:") {
-        let year = current_range.strip_prefix("year:").unwrap_or("");
-        if year.ends_with("-01") || year.ends_with("-02") || year.ends_with("-03") ||
-           year.ends_with("-04") || year.ends_with("-05") || year.ends_with("-06") ||
-           year.ends_with("-07") || year.ends_with("-08") || year.ends_with("-09") ||
-           year.ends_with("-10") || year.ends_with("-11") || year.ends_with("-12") {
-            // Format: year:2026-01 (year + month)
-            let parts: Vec<&str> = year.split('-').collect();
-            if parts.len() == 2 {
-                (parts[0], parts[1])
-            } else {
-                (year, "")
-            }
-        } else {
-            (year, "")
-        }
-    } else if current_range.starts_with("month:") {
-        let month = current_range.strip_prefix("month:").unwrap_or("");
-        ("", month)
-    } else {
-        ("", "")
-    };
-
-    // Parse custom range
-    let (custom_start, custom_end) = if current_range.starts_with("custom:") {
-        if let Some(custom_str) = current_range.strip_prefix("custom:") {
-            let parts: Vec<&str> = custom_str.split(',').collect();
-            if parts.len() == 2 {
-                (parts[0].to_string(), parts[1].to_string())
-            } else {
-                ("".to_string(), "".to_string())
-            }
-        } else {
-            ("".to_string(), "".to_string())
-        }
-    } else {
-        ("".to_string(), "".to_string())
-    };
-
-    let show_custom = if current_range.starts_with("custom:") { "display: block" } else { "display: none" };
-
-    format!(r#"
-        <div class='flex items-center gap-3 mb-4 p-3 bg-white rounded-lg border shadow-sm' id='page-time-selector'>
-            <span class='text-sm font-medium text-gray-600 flex-shrink-0'>时间:</span>
-            <!-- Year selector: 全部 or specific year -->
-            <select id='year-select' class='px-2 py-1.5 text-sm border rounded-lg bg-white min-w-[100px]' onchange='handleYearChange()'>
-                <option value='' {}>年份</option>
-                <option value='all' {}>全部</option>
-                <!-- Years loaded dynamically -->
-                <optgroup label='选择年份' id='year-options'>
-                    <option value='' disabled>加载中...</option>
-                </optgroup>
-            </select>
-            <span class='text-gray-400' id='month-separator' style='display: none;'>-</span>
-            <!-- Month selector: cascading, enabled only when year selected -->
-            <select id='month-select' class='px-2 py-1.5 text-sm border rounded-lg bg-white min-w-[90px]' onchange='handleMonthChange()' disabled style='background-color: #f9fafb;'>
-                <option value='all' {}>月份</option>
-                <option value='' {}>全年</option>
-                <option value='01' {}>1月</option>
-                <option value='02' {}>2月</option>
-                <option value='03' {}>3月</option>
-                <option value='04' {}>4月</option>
-                <option value='05' {}>5月</option>
-                <option value='06' {}>6月</option>
-                <option value='07' {}>7月</option>
-                <option value='08' {}>8月</option>
-                <option value='09' {}>9月</option>
-                <option value='10' {}>10月</option>
-                <option value='11' {}>11月</option>
-                <option value='12' {}>12月</option>
-            </select>
-            <div class='flex-1'></div>
-            <button onclick='toggleTimeSelector()' class='px-3 py-1.5 text-sm border rounded-lg hover:bg-gray-50 flex items-center gap-1'>
-                <svg xmlns='http://www.w3.org/2000/svg' class='h-4 w-4' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
-                    <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M8 7V3m8 4V3m-9 8h10M5 21h14a2 2 0 002-2V7a2 2 0 00-2-2H5a2 2 0 00-2 2v12a2 2 0 002 2z'/>
-                </svg>
-                自定义
-            </button>
-        </div>
-        <!-- Time selector dropdown (hidden by default) -->
-        <div id='time-selector-dropdown' class='hidden mb-4 p-4 bg-white rounded-lg border shadow-lg' style='position: absolute; z-index: 50; min-width: 360px;'>
-            <div class='flex items-center justify-between mb-3'>
-                <h4 class='font-medium'>自定义日期范围</h4>
-                <button onclick='toggleTimeSelector()' class='text-gray-400 hover:text-gray-600'>
-                    <svg xmlns='http://www.w3.org/2000/svg' class='h-5 w-5' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
-                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M6 18L18 6M6 6l12 12'/>
-                    </svg>
-                </button>
-            </div>
-            <!-- Step 1: Select Year -->
-            <div id='custom-step1' class='space-y-3'>
-                <label class='text-sm font-medium text-gray-700'>选择年份</label>
-                <select id='custom-year-select' class='w-full px-3 py-2 border rounded-lg bg-white' onchange='customYearChanged()'>
-                    <option value=''>请选择年份</option>
-                    <optgroup label='可用年份' id='custom-year-options'>
-                        <option value='' disabled>加载中...</option>
-                    </optgroup>
-                </select>
-            </div>
-            <!-- Step 2: Select Month (hidden initially) -->
-            <div id='custom-step2' class='space-y-3' style='display: none;'>
-                <div class='flex items-center justify-between'>
-                    <label class='text-sm font-medium text-gray-700'>选择月份(从当年1月到所选月份)</label>
-                    <button onclick='backToYearSelect()' class='text-xs text-indigo-600 hover:underline'>返回年份</button>
-                </div>
-                <div class='grid grid-cols-4 gap-2'>
-                    <button onclick='selectCustomMonth("01")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>1月</button>
-                    <button onclick='selectCustomMonth("02")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>2月</button>
-                    <button onclick='selectCustomMonth("03")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>3月</button>
-                    <button onclick='selectCustomMonth("04")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>4月</button>
-                    <button onclick='selectCustomMonth("05")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>5月</button>
-                    <button onclick='selectCustomMonth("06")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>6月</button>
-                    <button onclick='selectCustomMonth("07")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>7月</button>
-                    <button onclick='selectCustomMonth("08")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>8月</button>
-                    <button onclick='selectCustomMonth("09")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>9月</button>
-                    <button onclick='selectCustomMonth("10")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>10月</button>
-                    <button onclick='selectCustomMonth("11")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>11月</button>
-                    <button onclick='selectCustomMonth("12")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>12月</button>
-                </div>
-                <p id='custom-selected-date' class='text-sm text-gray-500 text-center'></p>
-            </div>
-            <!-- Direct date input (alternative) -->
-            <div class='mt-3 pt-3 border-t'>
-                <label class='text-xs text-gray-500 mb-1 block'>或者直接输入日期范围</label>
-                <div class='flex items-center gap-2 mb-2'>
-                    <input type='date' id='custom-start' value='{}' class='flex-1 px-2 py-1.5 text-sm border rounded'>
-                    <span class='text-gray-400'>至</span>
-                    <input type='date' id='custom-end' value='{}' class='flex-1 px-2 py-1.5 text-sm border rounded'>
-                </div>
-                <button onclick='applyCustomRange()' class='w-full px-3 py-2 bg-indigo-600 text-white text-sm rounded-lg hover:bg-indigo-700'>
-                    应用
-                </button>
-            </div>
-        </div>
-        <script>
-        let customSelectedYear = '';
-
-        // Fetch available years for custom picker
-        function loadCustomYearOptions() {{
-            fetch('/api/time-range/years')
-                .then(r => r.json())
-                .then(years => {{
-                    const yearGroup = document.getElementById('custom-year-options');
-                    if (yearGroup && years.length > 0) {{
-                        yearGroup.innerHTML = years.map(y =>
-                            `<option value="${{y}}">${{y}} 年</option>`
-                        ).join('');
-                    }}
-                }})
-                .catch(err => console.error('Failed to load years:', err));
-        }}
-
-        // Fetch available years and populate main selector
-        function loadYearOptions() {{
-            fetch('/api/time-range/years')
-                .then(r => r.json())
-                .then(years => {{
-                    const yearGroup = document.getElementById('year-options');
-                    if (yearGroup && years.length > 0) {{
-                        const currentYear = document.getElementById('year-select').value.split('-')[0];
-                        yearGroup.innerHTML = years.map(y =>
-                            `<option value="${{y}}" ${{y === currentYear ? 'selected' : ''}}>${{y}} 年</option>`
-                        ).join('');
-                        // If a year is already selected, trigger change to update month selector
-                        if (document.getElementById('year-select').value && document.getElementById('year-select').value !== 'all') {{
-                            handleYearChange();
-                        }}
-                    }}
-                }})
-                .catch(err => console.error('Failed to load years:', err));
-        }}
-
-        // Handle year selection change - enables month selector
-        function handleYearChange() {{
-            const yearSelect = document.getElementById('year-select');
-            const monthSelect = document.getElementById('month-select');
-            const separator = document.getElementById('month-separator');
-            const year = yearSelect.value;
-
-            if (year === '' || year === 'all') {{
-                // Reset to all - no time filter
-                monthSelect.value = '';
-                monthSelect.disabled = true;
-                monthSelect.style.backgroundColor = '#f9fafb';
-                separator.style.display = 'none';
-                setTimeRange('all');
-            }} else {{
-                // Year selected - enable month selector
-                monthSelect.disabled = false;
-                monthSelect.style.backgroundColor = '';
-                separator.style.display = 'inline';
-                // Default to full year, user can select specific month
-                monthSelect.value = 'all';
-            }}
-        }}
-
-        // Handle month selection change
-        function handleMonthChange() {{
-            const yearSelect = document.getElementById('year-select');
-            const monthSelect = document.getElementById('month-select');
-            const year = yearSelect.value;
-            const month = monthSelect.value;
-
-            if (!year || year === 'all') {{
-                // Should not happen due to disabled state, but handle gracefully
-                return;
-            }}
-
-            if (month === '' || month === 'all') {{
-                // Full year selected
-                setTimeRange('year:' + year);
-            }} else {{
-                // Specific month selected
-                setTimeRange('year:' + year + '-' + month);
-            }}
-        }}
-
-        function setTimeRange(range) {{
-            let apiRange = range;
-            if (range === 'all') {{
-                apiRange = 'all';
-            }} else if (range.match(/^\d{4}$/)) {{
-                // 4-digit year -> add year: prefix
-                apiRange = 'year:' + range;
-            }} else if (range.includes('-') && range.length === 7) {{
-                // year-month format (like 2025-03)
-                apiRange = 'year:' + range;
-            }}
-
-            console.log('[DEBUG] setTimeRange: original=' + range + ', api=' + apiRange);
-            htmx.ajax('POST', '/api/time-range?range=' + encodeURIComponent(apiRange),
-                {{ target: 'body', swap: 'none' }}).then(() => {{
-                    window.location.reload();
-                }}).catch(err => {{
-                    console.error('Failed to set time range:', err);
-                    window.location.reload();
-                }});
-        }}
-
-        function toggleTimeSelector() {{
-            const dropdown = document.getElementById('time-selector-dropdown');
-            if (dropdown) {{
-                dropdown.classList.toggle('hidden');
-                if (!dropdown.classList.contains('hidden')) {{
-                    // Reset to step 1 when opening
-                    document.getElementById('custom-step1').style.display = 'block';
-                    document.getElementById('custom-step2').style.display = 'none';
-                    loadCustomYearOptions();
-                }}
-            }}
-        }}
-
-        // Custom date picker drill-down functions
-        function customYearChanged() {{
-            const yearSelect = document.getElementById('custom-year-select');
-            customSelectedYear = yearSelect.value;
-            if (customSelectedYear) {{
-                document.getElementById('custom-step1').style.display = 'none';
-                document.getElementById('custom-step2').style.display = 'block';
-                document.getElementById('custom-selected-date').textContent = '已选择: ' + customSelectedYear + ' 年';
-            }}
-        }}
-
-        function backToYearSelect() {{
-            document.getElementById('custom-step1').style.display = 'block';
-            document.getElementById('custom-step2').style.display = 'none';
-            customSelectedYear = '';
-        }}
-
-        function selectCustomMonth(month) {{
-            if (customSelectedYear) {{
-                const range = customSelectedYear + '-' + month;
-                setTimeRange(range);
-                toggleTimeSelector(); // Close dropdown
-            }}
-        }}
-
-        function applyCustomRange() {{
-            const start = document.getElementById('custom-start').value;
-            const end = document.getElementById('custom-end').value;
-            if (start && end) {{
-                htmx.ajax('POST', '/api/time-range?range=custom:' + start + ',' + end,
-                    {{ target: 'body', swap: 'none' }}).then(() => {{
-                        window.location.reload();
-                    }}).catch(err => {{
-                        console.error('Failed to set custom range:', err);
-                        window.location.reload();
-                    }});
-            }}
-        }}
-
-        // Initialize on load
-        document.addEventListener('DOMContentLoaded', function() {{
-            // Load year options first, then sync state
-            loadYearOptions();
+/// Every problem the parser found during the last load (JSON API)
+async fn api_errors(state: axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
+    let errors = state.ledger.read().await.parse_errors();
+    let count = |severity: beanweb_core::Severity| errors.iter().filter(|e| e.severity == severity).count();
+    axum::Json(serde_json::json!({
+        "errors": count(beanweb_core::Severity::Error),
+        "warnings": count(beanweb_core::Severity::Warning),
+        "diagnostics": errors,
+    }))
+}
 
-            // If a year is already selected in the dropdown, enable month selector
-            const yearSelect = document.getElementById('year-select');
-            if (yearSelect && yearSelect.value && yearSelect.value !== 'all') {{
-                handleYearChange();
-            }} else {{
-                // Ensure month selector is disabled when no year selected
-                const monthSelect = document.getElementById('month-select');
-                const separator = document.getElementById('month-separator');
-                if (monthSelect) {{
-                    monthSelect.disabled = true;
-                    monthSelect.style.backgroundColor = '#f9fafb';
-                }}
-                if (separator) {{
-                    separator.style.display = 'none';
-                }}
-            }}
-        }});
+/// GET /api/options - the ledger's `option` directives
+async fn api_options(state: axum::extract::State<AppState>) -> axum::Json<beanweb_core::LedgerOptions> {
+    axum::Json(state.ledger.read().await.options())
+}
 
-        // Close dropdown when clicking outside
-        document.addEventListener('click', function(e) {{
-            const dropdown = document.getElementById('time-selector-dropdown');
-            const toggle = document.querySelector('button[onclick="toggleTimeSelector()"]');
-            if (dropdown && !dropdown.classList.contains('hidden') && !dropdown.contains(e.target) && (!toggle || !toggle.contains(e.target))) {{
-                dropdown.classList.add('hidden');
-            }}
-        }});
-        </script>
-    "#,
-        if selected_year.is_empty() { "selected" } else { "" },
-        if selected_year == "all" { "selected" } else { "" },
-        if selected_month.is_empty() { "selected" } else { "" },
-        if selected_month == "all" { "selected" } else { "" },
-        if selected_month == "01" { "selected" } else { "" },
-        if selected_month == "02" { "selected" } else { "" },
-        if selected_month == "03" { "selected" } else { "" },
-        if selected_month == "04" { "selected" } else { "" },
-        if selected_month == "05" { "selected" } else { "" },
-        if selected_month == "06" { "selected" } else { "" },
-        if selected_month == "07" { "selected" } else { "" },
-        if selected_month == "08" { "selected" } else { "" },
-        if selected_month == "09" { "selected" } else { "" },
-        if selected_month == "10" { "selected" } else { "" },
-        if selected_month == "11" { "selected" } else { "" },
-        if selected_month == "12" { "selected" } else { "" },
-        custom_start,
-        custom_end
-    )
+/// HTMX: Sidebar heading, the `title` option or "Beanweb"
+async fn htmx_status_title(state: axum::extract::State<AppState>) -> String {
+    let title = state.ledger.read().await.title();
+    html_escape(title.as_deref().unwrap_or("Beanweb"))
 }
 
-/// Header bar - Wrapper for main content area
-pub fn header_bar(current_path: &str) -> String {
-    format!(r#"<div class='flex flex-col h-screen'>
-    <div class='flex flex-1 overflow-hidden'>
-        <aside class='w-64 flex-shrink-0'>{}</aside>
-        <main class='flex-1 overflow-auto bg-gray-50 p-6'>"#,
-        nav_sidebar(current_path))
+/// HTMX: Load error banner shown on every page (empty when the last load succeeded
+/// and every directive parsed)
+async fn htmx_status_banner(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    let status = ledger.load_status();
+    let Some(error) = status.error else {
+        let checks = checks::banner_checks(&state, &ledger).await;
+        return banners::parse_errors(&ledger.parse_errors(), &state.config.data.path)
+            + &banners::balance_failures(&checks.failed_balances, &state.config.data.path)
+            + &banners::negative_balances(&checks.negative_balances);
+    };
+    let retrying = state.config.server.startup_mode == beanweb_config::StartupMode::Retry && !status.loaded;
+    banners::load_error(&error, status.loaded, retrying.then_some(status.attempts))
 }
 
 /// Check if request is from HTMX (partial page update)
@@ -542,30 +335,13 @@ fn is_htmx_request(headers: &axum::http::HeaderMap) -> bool {
 }
 
 /// Wrap content for full page or HTMX partial (with time range)
-pub fn page_response(headers: &axum::http::HeaderMap, title: &str, current_path: &str, inner_content: &str) -> String {
-    page_response_with_time(headers, title, current_path, inner_content, "month")
+pub fn page_response(headers: &axum::http::HeaderMap, config: &Config, title: &str, current_path: &str, inner_content: &str) -> String {
+    page_response_with_time(headers, config, title, current_path, inner_content, "month")
 }
 
 /// Wrap content for full page or HTMX partial with time range
-pub fn page_response_with_time(headers: &axum::http::HeaderMap, title: &str, current_path: &str, inner_content: &str, time_range: &str) -> String {
-    if is_htmx_request(headers) {
-        // HTMX partial - just the content area (no sidebar for partial updates)
-        format!(r#"<div class='flex flex-col h-screen'>
-    <div class='flex flex-1 overflow-hidden'>
-        <main class='flex-1 overflow-auto bg-gray-50 p-6'>{}</main>
-    </div>
-</div>"#,
-            inner_content)
-    } else {
-        // Full page - wrap with base HTML and sidebar (without time selector)
-        base_html(title, &format!(r#"<div class='flex flex-col h-screen'>
-    <div class='flex flex-1 overflow-hidden'>
-        <aside class='w-64 flex-shrink-0'>{}</aside>
-        <main class='flex-1 overflow-auto bg-gray-50 p-6'>{}</main>
-    </div>
-</div>"#,
-            nav_sidebar(current_path), inner_content))
-    }
+pub fn page_response_with_time(headers: &axum::http::HeaderMap, config: &Config, title: &str, current_path: &str, inner_content: &str, time_range: &str) -> String {
+    layout::page(title, current_path, inner_content, is_htmx_request(headers), &config.features)
 }
 
 /// Index page with navigation
@@ -574,53 +350,49 @@ async fn index_page(
     headers: axum::http::HeaderMap,
 ) -> axum::response::Html<String> {
     let ledger = state.ledger.read().await;
-    let stats = ledger.transaction_stats();
-    let balance_report = ledger.balance_report();
-    let income_expense = ledger.income_expense_report();
     let time_range = ledger.time_context().range.to_string();
+    let inner_content = dashboard::page(&dashboard::Dashboard {
+        balance_report: ledger.balance_report(),
+        income_expense: ledger.income_expense_report(),
+        stats: ledger.transaction_stats(),
+        top_assets: top_rows(&ledger, TopCard::Assets),
+        top_expenses: top_rows(&ledger, TopCard::Expenses),
+        top_n: state.config.charts.top_items_count,
+        velocity: ledger.spending_velocity(&ledger.time_context()),
+        recurring: ledger.recurring_payments(),
+        stale_balances: ledger.stale_balances(),
+    });
+
+    axum::response::Html(page_response_with_time(&headers, &state.config, "仪表盘", "/dashboard", &inner_content, &time_range))
+}
 
-    let top_assets: Vec<String> = balance_report.entries.iter().filter(|e| e.account_type == beanweb_core::AccountType::Assets).take(5).map(|e| {
-        format!("<div class='flex justify-between py-2 border-b'><span>{}</span><span class='font-medium'>{}</span></div>", e.account, e.balance)
-    }).collect();
-
-    let net_income_value: f64 = income_expense.net_income.parse().unwrap_or(0.0);
-
-    let inner_content = format!(
-        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>仪表盘</h2></div>
-        <div class='grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-4 mb-6'>
-            <div class='bg-green-50 p-4 rounded-lg border border-green-200'><p class='text-sm text-green-600'>总资产</p><p class='text-2xl font-bold text-green-700'>{}</p></div>
-            <div class='bg-red-50 p-4 rounded-lg border border-red-200'><p class='text-sm text-red-600'>总负债</p><p class='text-2xl font-bold text-red-700'>{}</p></div>
-            <div class='bg-blue-50 p-4 rounded-lg border border-blue-200'><p class='text-sm text-blue-600'>总收入</p><p class='text-2xl font-bold text-blue-700'>{}</p></div>
-            <div class='bg-yellow-50 p-4 rounded-lg border border-yellow-200'><p class='text-sm text-yellow-600'>总支出</p><p class='text-2xl font-bold text-yellow-700'>{}</p></div>
-        </div>
-        <div class='grid grid-cols-1 lg:grid-cols-2 gap-6'>
-            <div class='bg-white rounded-xl shadow-sm p-6'>
-                <h3 class='text-lg font-semibold mb-4'>资产排名</h3>
-                <div class='space-y-1'>{}</div>
-            </div>
-            <div class='bg-white rounded-xl shadow-sm p-6'>
-                <h3 class='text-lg font-semibold mb-4'>本月统计</h3>
-                <div class='grid grid-cols-2 gap-4'>
-                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>交易数</p><p class='text-xl font-bold'>{}</p></div>
-                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>条目数</p><p class='text-xl font-bold'>{}</p></div>
-                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>净资产</p><p class='text-xl font-bold text-indigo-600'>{}</p></div>
-                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>收支结余</p><p class='text-xl font-bold {}'>{}</p></div>
-                </div>
-            </div>
-        </div>"#,
-        balance_report.total_assets,
-        balance_report.total_liabilities,
-        income_expense.total_income,
-        income_expense.total_expenses,
-        top_assets.join(""),
-        stats.total_transactions,
-        stats.total_postings,
-        balance_report.net_worth,
-        if net_income_value < 0.0 { "text-red-600" } else { "text-green-600" },
-        income_expense.net_income
-    );
+/// Rows of a ranked dashboard card, in no particular order
+fn top_rows(ledger: &Ledger, card: TopCard) -> Vec<dashboard::TopRow> {
+    match card {
+        TopCard::Assets => ledger.balance_report().entries.into_iter()
+            .filter(|e| e.account_type == beanweb_core::AccountType::Assets)
+            .map(|e| dashboard::TopRow { label: e.account, amount: e.balance.parse().unwrap_or(0.0), detail: e.currency })
+            .collect(),
+        TopCard::Expenses => ledger.expense_category_report().breakdowns.into_iter()
+            .map(|b| dashboard::TopRow { label: b.category, amount: b.amount.parse().unwrap_or(0.0), detail: format!("{:.1}%", b.percentage) })
+            .collect(),
+    }
+}
 
-    axum::response::Html(page_response_with_time(&headers, "仪表盘", "/dashboard", &inner_content, &time_range))
+/// HTMX: Ranked dashboard card body; `?all=1` returns the full list
+async fn htmx_dashboard_top(
+    state: axum::extract::State<AppState>,
+    card: axum::extract::Path<String>,
+    query: axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> String {
+    let card = match card.0.as_str() {
+        "assets" => TopCard::Assets,
+        "expenses" => TopCard::Expenses,
+        _ => return String::new(),
+    };
+    let ledger = state.ledger.read().await;
+    let expanded = query.get("all").is_some_and(|v| v == "1");
+    dashboard::top_card(card, &top_rows(&ledger, card), state.config.charts.top_items_count, expanded)
 }
 
 /// Dashboard page (alias for index)
@@ -642,24 +414,22 @@ async fn page_dashboard(
 /// * `ledger` - The shared ledger state
 pub async fn start_server(config: Config, ledger: Arc<RwLock<beanweb_core::Ledger>>) {
     let addr = format!("{}:{}", config.server.host, config.server.port);
-    let state = AppState { ledger, config };
+    let key = keys::ServerKey::open(&config).expect("Failed to open the server key (server.auth.key_file)");
+    let two_factor = two_factor::open(&config, &key);
+    let state = AppState { ledger, config, checks: checks::CheckCache::default(), sessions: auth::open(&key), two_factor, idempotency: idempotency::IdempotencyStore::default(), jobs: jobs::ReportJobs::default(), metrics: metrics::RequestMetrics::default() };
+    checks::spawn_checks(state.clone());
+    watch::spawn_watcher(state.clone());
+    auth::spawn_pruning(state.clone());
 
     let router = create_router(state);
 
     let listener = TcpListener::bind(&addr).await.unwrap();
-    eprintln!("[INFO] Starting Beanweb server on http://{}", addr);
-    eprintln!("[INFO] Available routes:");
-    eprintln!("[INFO]   - / (Dashboard)");
-    eprintln!("[INFO]   - /accounts (Account management)");
-    eprintln!("[INFO]   - /transactions (Transaction list)");
-    eprintln!("[INFO]   - /reports (Financial reports)");
-    eprintln!("[INFO]   - /settings (Configuration)");
-    eprintln!("[INFO]   - /api/* (JSON API endpoints)");
+    tracing::info!("Starting Beanweb server on http://{}", addr);
 
     match axum::serve(listener, router).await
     {
-        Ok(_) => eprintln!("[INFO] Server stopped gracefully"),
-        Err(e) => eprintln!("[ERROR] Server error: {}", e),
+        Ok(_) => tracing::info!("Server stopped gracefully"),
+        Err(e) => tracing::error!("Server error: {}", e),
     }
 }
 
@@ -0,0 +1,129 @@
+//! Request logging and Prometheus metrics
+//!
+//! Every request is logged at info level with its method, path, status and
+//! latency, and counted by method and status. Methods outside the standard
+//! ones count as `other`, so however the method and status vary the counts
+//! stay a small fixed set, even for requests that never logged in.
+//! `/metrics` serves the counts
+//! in the Prometheus text format together with the size of the ledger and
+//! its last load:
+//! - `beanweb_http_requests_total` and `beanweb_http_request_duration_seconds_sum`
+//!   by `method` and `status`, since startup
+//! - `beanweb_ledger_*`: accounts, transactions, commodities and files of the
+//!   loaded ledger, load attempts, whether the last one failed, and how long
+//!   the last successful load or reload took
+//!
+//! With `server.auth` set, `/metrics` needs credentials like any other page.
+//! Scrapers send `server.auth.metrics_token` as a bearer token, which opens
+//! `/metrics` only.
+
+use crate::AppState;
+use axum::extract::Request;
+use axum::http::{header, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use beanweb_core::LedgerOperations;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Content type of the Prometheus text format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Request counts shared by all requests
+pub type RequestMetrics = Arc<Mutex<RequestCounts>>;
+
+/// Requests and their total latency by method and status
+#[derive(Debug, Default)]
+pub struct RequestCounts {
+    by_method_status: BTreeMap<(String, u16), (u64, Duration)>,
+}
+
+impl RequestCounts {
+    /// Count a request
+    pub fn record(&mut self, method: &str, status: u16, latency: Duration) {
+        let (count, total) = self.by_method_status.entry((method.to_string(), status)).or_default();
+        *count += 1;
+        *total += latency;
+    }
+
+    /// Requests with `method` and `status` so far
+    pub fn count(&self, method: &str, status: u16) -> u64 {
+        self.by_method_status.get(&(method.to_string(), status)).map_or(0, |(count, _)| *count)
+    }
+}
+
+/// Label of a method: the standard ones by name, anything else `other`
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => "other",
+    }
+}
+
+/// Middleware: log and count every request
+pub async fn log_requests(
+    state: axum::extract::State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let label = method_label(request.method());
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let latency = started.elapsed();
+    let status = response.status().as_u16();
+    tracing::info!(method = %method, path = %path, status, latency_ms = latency.as_secs_f64() * 1000.0, "request");
+    state.metrics.lock().unwrap().record(label, status, latency);
+    response
+}
+
+/// Append one metric family with its samples
+fn family(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, String)]) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+/// GET /metrics - request counts and ledger size, Prometheus text format
+pub async fn metrics(state: axum::extract::State<AppState>) -> Response {
+    let mut requests = Vec::new();
+    let mut durations = Vec::new();
+    for ((method, status), (count, total)) in &state.metrics.lock().unwrap().by_method_status {
+        let labels = format!("{{method=\"{}\",status=\"{}\"}}", method, status);
+        requests.push((labels.clone(), count.to_string()));
+        durations.push((labels, total.as_secs_f64().to_string()));
+    }
+
+    let ledger = state.ledger.read().await;
+    let summary = ledger.summary();
+    let status = ledger.load_status();
+    let gauge = |value: String| vec![(String::new(), value)];
+
+    let mut out = String::new();
+    family(&mut out, "beanweb_http_requests_total", "counter", "HTTP requests by method and status.", &requests);
+    family(&mut out, "beanweb_http_request_duration_seconds_sum", "counter", "Total time spent on HTTP requests by method and status.", &durations);
+    family(&mut out, "beanweb_ledger_accounts", "gauge", "Accounts in the loaded ledger.", &gauge(summary.total_accounts.to_string()));
+    family(&mut out, "beanweb_ledger_transactions", "gauge", "Transactions in the loaded ledger.", &gauge(summary.total_transactions.to_string()));
+    family(&mut out, "beanweb_ledger_commodities", "gauge", "Commodities in the loaded ledger.", &gauge(summary.total_commodities.to_string()));
+    family(&mut out, "beanweb_ledger_files", "gauge", "Files parsed by the last load.", &gauge(ledger.file_stats().len().to_string()));
+    family(&mut out, "beanweb_ledger_load_attempts_total", "counter", "Load and reload attempts.", &gauge(status.attempts.to_string()));
+    family(&mut out, "beanweb_ledger_load_failed", "gauge", "1 if the last load attempt failed.", &gauge(u8::from(status.error.is_some()).to_string()));
+    if let Some(ms) = status.duration_ms {
+        family(&mut out, "beanweb_ledger_last_load_duration_seconds", "gauge", "Duration of the last successful load or reload.", &gauge((ms as f64 / 1000.0).to_string()));
+    }
+
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], out).into_response()
+}
@@ -0,0 +1,217 @@
+//! Privacy mode - mask amounts in rendered pages (e.g. while screen-sharing)
+//!
+//! The preference lives in the `beanweb_privacy` cookie. When it is on, the
+//! middleware rewrites every HTML page and fragment before it leaves the
+//! server, so masked responses never contain the real numbers:
+//! - Text nodes, `title` and `data-*` attribute values are masked, except
+//!   the data attributes scripts read ([`FUNCTIONAL_DATA_ATTRIBUTES`])
+//! - So are the values of amount inputs (`name` ending in `amount`); saving
+//!   a form with a masked amount fails instead of writing it
+//! - Dates, times, counters ("12 笔", "第 3 页") and account names are kept
+//! - `<script>`/`<style>` content is left as-is
+//!
+//! HTML is masked wherever it is served, `/api/` fragments included. Data
+//! for programs is not: JSON, and plain text under `/api/` (ledger files
+//! for the editor, exports). Scripts parse it and the editor writes it
+//! back, so masked values would break them or end up in the ledger; those
+//! responses are exempt by design.
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Cookie holding the privacy preference ("1" = on)
+pub const PRIVACY_COOKIE: &str = "beanweb_privacy";
+
+/// Replacement shown instead of an amount
+const MASK: &str = "•••";
+
+/// `data-*` attributes holding names, paths or JSON for scripts; masking
+/// them would break the page (template prefill, the account picker)
+const FUNCTIONAL_DATA_ATTRIBUTES: [&str; 8] = [
+    "data-account", "data-accounts", "data-field", "data-path",
+    "data-secret", "data-selectable", "data-target", "data-template",
+];
+
+/// Whether the request has privacy mode enabled
+pub fn privacy_enabled(headers: &HeaderMap) -> bool {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .any(|(name, value)| name == PRIVACY_COOKIE && value == "1")
+}
+
+/// Middleware: mask amounts in displayed responses when privacy mode is on
+pub async fn mask_amounts_layer(request: Request, next: Next) -> Response {
+    let enabled = privacy_enabled(request.headers());
+    let api = request.uri().path().starts_with("/api/");
+    let response = next.run(request).await;
+    if !enabled {
+        return response;
+    }
+    let content_type = response.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let displayed = content_type.starts_with("text/html") || (content_type.starts_with("text/plain") && !api);
+    if !displayed {
+        return response;
+    }
+
+    // Masking needs the whole document, so streamed bodies are buffered here
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read response").into_response();
+    };
+    let masked = mask_amounts(&String::from_utf8_lossy(&bytes));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Response::from_parts(parts, Body::from(masked))
+}
+
+/// POST: Toggle privacy mode and reload the page
+pub async fn htmx_privacy_toggle(headers: HeaderMap) -> impl IntoResponse {
+    let value = if privacy_enabled(&headers) { "0" } else { "1" };
+    let cookie = format!("{}={}; Path=/; Max-Age=31536000; SameSite=Lax", PRIVACY_COOKIE, value);
+    ([(header::SET_COOKIE, cookie), (header::HeaderName::from_static("hx-refresh"), "true".to_string())], "")
+}
+
+/// Mask amounts in an HTML document
+pub fn mask_amounts(html: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"(?s)<!--.*?-->|<[^>]*>").unwrap());
+
+    let mut out = String::with_capacity(html.len());
+    let mut last = 0;
+    // Raw text element we are inside of (script/style), if any
+    let mut raw_until: Option<&str> = None;
+    for m in tag.find_iter(html) {
+        let text = &html[last..m.start()];
+        if raw_until.is_some() { out.push_str(text) } else { out.push_str(&mask_text(text)) }
+        last = m.end();
+
+        let tag_text = m.as_str();
+        let lower = tag_text.to_ascii_lowercase();
+        if let Some(close) = raw_until {
+            if lower.starts_with(close) {
+                raw_until = None;
+            }
+            out.push_str(tag_text);
+            continue;
+        }
+        if lower.starts_with("<script") && !lower.ends_with("/>") {
+            raw_until = Some("</script");
+        } else if lower.starts_with("<style") {
+            raw_until = Some("</style");
+        }
+        out.push_str(&mask_attributes(tag_text));
+    }
+    let rest = &html[last..];
+    if raw_until.is_some() { out.push_str(rest) } else { out.push_str(&mask_text(rest)) }
+    out
+}
+
+/// Mask `title`, non-functional `data-*` and amount input `value` attribute
+/// values inside a tag
+fn mask_attributes(tag: &str) -> String {
+    static ATTR: OnceLock<Regex> = OnceLock::new();
+    static AMOUNT_INPUT: OnceLock<Regex> = OnceLock::new();
+    let attr = ATTR.get_or_init(|| Regex::new(r#"(\s([\w-]+)=)('[^']*'|"[^"]*")"#).unwrap());
+    let amount_input = AMOUNT_INPUT.get_or_init(|| Regex::new(r#"(?i)^<input\s[^>]*\bname=(?:'[^']*amount'|"[^"]*amount")"#).unwrap());
+
+    let is_amount_input = amount_input.is_match(tag);
+    attr.replace_all(tag, |caps: &regex::Captures| {
+        let name = caps[2].to_ascii_lowercase();
+        let masked = name == "title"
+            || (name.starts_with("data-") && !FUNCTIONAL_DATA_ATTRIBUTES.contains(&name.as_str()))
+            || (is_amount_input && name == "value");
+        if masked { format!("{}{}", &caps[1], mask_text(&caps[3])) } else { caps[0].to_string() }
+    })
+    .into_owned()
+}
+
+/// Mask numbers in plain text, keeping dates, times, counters and identifiers
+fn mask_text(text: &str) -> String {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let number = NUMBER.get_or_init(|| {
+        Regex::new(r"(\d{4}-\d{2}(?:-\d{2})?)|(\d{1,2}:\d{2}(?::\d{2})?)|([-+]?[¥$€£]?\d[\d,]*(?:\.\d+)?)").unwrap()
+    });
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in number.captures_iter(text) {
+        let Some(m) = caps.get(3) else { continue };
+        let before = text[..m.start()].chars().next_back();
+        let after = text[m.end()..].trim_start().chars().next();
+        // Part of a name (Bank:6222, #tag1, &#39;) or a counter (12 笔, 第 3 页)
+        let identifier = before.is_some_and(|c| c.is_ascii_alphanumeric() || "_:/#&^.@".contains(c));
+        let counter = after.is_some_and(|c| "条笔页个项天次".contains(c))
+            || text[..m.start()].trim_end().ends_with('第');
+        if identifier || counter {
+            continue;
+        }
+        out.push_str(&text[last..m.start()]);
+        out.push_str(MASK);
+        last = m.end();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_text() {
+        assert_eq!(mask_text("余额 1,234.56 CNY"), "余额 ••• CNY");
+        assert_eq!(mask_text("-¥20 / +3.5"), "••• / •••");
+        // Dates, times, counters and identifiers stay
+        assert_eq!(mask_text("2024-01-05 12:30 共 12 笔 第 3 页"), "2024-01-05 12:30 共 12 笔 第 3 页");
+        assert_eq!(mask_text("Assets:Bank:6222 #trip2024 &#39;"), "Assets:Bank:6222 #trip2024 &#39;");
+    }
+
+    #[test]
+    fn test_mask_amounts() {
+        let html = "<td title='1000.00 CNY' data-amount=\"1000\" class='w-2'>1000.00</td><script>let x = 42;</script><style>.a{width:10px}</style><!-- 7 -->5";
+        assert_eq!(
+            mask_amounts(html),
+            "<td title='••• CNY' data-amount=\"•••\" class='w-2'>•••</td><script>let x = 42;</script><style>.a{width:10px}</style><!-- 7 -->•••"
+        );
+    }
+
+    #[test]
+    fn test_mask_amount_inputs() {
+        let html = "<input type='text' name='posting_0_amount' value='1000.00 CNY' placeholder='100.00 CNY'><input name='date' value='2024-01-05'><input name='count' value='12'>";
+        assert_eq!(
+            mask_amounts(html),
+            "<input type='text' name='posting_0_amount' value='••• CNY' placeholder='100.00 CNY'><input name='date' value='2024-01-05'><input name='count' value='12'>"
+        );
+    }
+
+    #[test]
+    fn test_functional_data_attributes() {
+        // Template JSON and picker names stay intact for the scripts reading them
+        let html = r#"<option value='Rent' data-template='{"postings":[{"amount":"3000.00 CNY"}]}'>Rent</option><div data-account='Assets:Bank' data-selectable='1' data-total='3000'>"#;
+        assert_eq!(
+            mask_amounts(html),
+            r#"<option value='Rent' data-template='{"postings":[{"amount":"3000.00 CNY"}]}'>Rent</option><div data-account='Assets:Bank' data-selectable='1' data-total='•••'>"#
+        );
+    }
+
+    #[test]
+    fn test_privacy_enabled() {
+        let mut headers = HeaderMap::new();
+        assert!(!privacy_enabled(&headers));
+        headers.insert(header::COOKIE, HeaderValue::from_static("a=b; beanweb_privacy=1"));
+        assert!(privacy_enabled(&headers));
+        headers.insert(header::COOKIE, HeaderValue::from_static("beanweb_privacy=0"));
+        assert!(!privacy_enabled(&headers));
+    }
+}
@@ -1,23 +1,13 @@
 //! Accounts API endpoints - JSON API and HTMX partial responses
 
-use crate::AppState;
+use crate::{ApiError, AppState};
+use crate::collection::{Collection, CollectionQuery, CollectionResponse};
+use beanweb_core::account_filter::AccountFilter;
 use axum::extract::{Query, Path};
+use beanweb_ui::accounts;
 use std::collections::HashMap;
 
-/// Account amount structure with calculated total and currency detail
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct AccountAmount {
-    /// The calculated total amount (converted to operating currency)
-    pub calculated: CalculatedAmount,
-    /// Detailed amounts per currency
-    pub detail: HashMap<String, String>,
-}
-
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct CalculatedAmount {
-    pub number: String,
-    pub currency: String,
-}
+pub use beanweb_core::account_tree::{AccountAmount, AccountTreeNode, CalculatedAmount};
 
 /// Account list item for API response
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -28,37 +18,63 @@ pub struct AccountListItem {
     #[serde(rename = "status")]
     pub account_status: String,
     pub amount: AccountAmount,
+    /// Number of direct child accounts
+    pub children_count: usize,
+    pub has_transactions_in_period: bool,
+    pub last_transaction_date: Option<String>,
 }
 
-/// Account with full tree information for frontend
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct AccountTreeNode {
-    pub name: String,
-    pub short_name: String,
-    pub path: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub alias: Option<String>,
-    #[serde(rename = "status")]
-    pub account_status: String,
-    pub amount: AccountAmount,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub children: Option<Vec<AccountTreeNode>>,
-    pub has_children: bool,
-    pub is_leaf: bool,
-    pub is_real: bool,  // true if this node exists in accounts table (can click to view detail)
-    pub depth: usize,
+/// `/api/accounts` collection parameters
+pub struct AccountCollection;
+
+impl Collection for AccountCollection {
+    const FILTERS: &'static [&'static str] = &["account", "status"];
+    const SORTS: &'static [&'static str] = &["name", "last_transaction_date", "children_count"];
+    const DEFAULT_SORT: &'static str = "name";
+    const DEFAULT_PAGE_SIZE: usize = 100;
 }
 
-pub async fn api_accounts(state: axum::extract::State<AppState>) -> String {
+/// Accounts with balance and activity (JSON API)
+/// `filter[account]` takes an account, a glob such as `Expenses:*` or a
+/// `~regex`; `filter[status]` is open, closed or paused
+pub async fn api_accounts(
+    state: axum::extract::State<AppState>,
+    query: CollectionQuery<AccountCollection>,
+) -> Result<axum::Json<CollectionResponse<AccountListItem>>, ApiError> {
+    let account_filter = query.filter("account")
+        .map(AccountFilter::parse)
+        .transpose()
+        .map_err(|e| ApiError::BadRequest { message: e.to_string() })?;
+    let status = query.filter("status")
+        .map(|s| s.parse::<beanweb_core::AccountStatus>())
+        .transpose()
+        .map_err(|message| ApiError::BadRequest { message })?;
+
     let ledger = state.ledger.read().await;
     let accounts = ledger.accounts();
     let account_balances = ledger.calculate_account_balances();
+    let activity = ledger.account_activity();
+    let report_currency = ledger.report_currency();
+
+    // Count direct children (including implicit intermediate accounts)
+    let mut children: HashMap<String, std::collections::HashSet<String>> = HashMap::new();
+    for acc in &accounts {
+        let mut path = acc.name.as_str();
+        while let Some(pos) = path.rfind(':') {
+            let parent = &path[..pos];
+            children.entry(parent.to_string()).or_default().insert(path.to_string());
+            path = parent;
+        }
+    }
 
     // Build account list items with proper amount structure
     let mut items: Vec<AccountListItem> = accounts.iter()
+        .filter(|acc| account_filter.as_ref().is_none_or(|f| f.matches(&acc.name)))
+        .filter(|acc| status.is_none_or(|s| acc.status == s))
         .map(|acc| {
-            let balance = account_balances.get(&acc.name).copied().unwrap_or(0.0);
-            let currency = acc.currency.clone().unwrap_or_else(|| "CNY".to_string());
+            let balance = ledger.display_amount(&acc.name, account_balances.get(&acc.name).copied().unwrap_or(0.0));
+            let currency = acc.currency.clone().unwrap_or_else(|| report_currency.clone());
+            let acc_activity = activity.get(&acc.name).cloned().unwrap_or_default();
 
             AccountListItem {
                 name: acc.name.clone(),
@@ -75,11 +91,20 @@ pub async fn api_accounts(state: axum::extract::State<AppState>) -> String {
                         detail
                     },
                 },
+                children_count: children.get(&acc.name).map_or(0, |c| c.len()),
+                has_transactions_in_period: acc_activity.has_transactions_in_period,
+                last_transaction_date: acc_activity.last_transaction_date,
             }
         })
         .collect();
-
-    serde_json::to_string(&items).unwrap_or_default()
+    drop(ledger);
+
+    query.sort(&mut items, |field, a, b| match field {
+        "last_transaction_date" => a.last_transaction_date.cmp(&b.last_transaction_date),
+        "children_count" => a.children_count.cmp(&b.children_count),
+        _ => a.name.cmp(&b.name),
+    });
+    Ok(axum::Json(query.paginate(items)))
 }
 
 /// Format balance number for display
@@ -91,66 +116,39 @@ fn format_balance_number(value: f64) -> String {
     }
 }
 
-/// Calculate account balances with currency detail
-pub(crate) fn calculate_balances_with_detail(
-    accounts: &[beanweb_core::Account],
-    transactions: &[beanweb_core::Transaction],
-) -> HashMap<String, AccountAmount> {
-    let mut balances: HashMap<String, HashMap<String, f64>> = HashMap::new();
-    let mut currencies: HashMap<String, String> = HashMap::new();
-
-    // Initialize with account currencies
-    for account in accounts {
-        currencies.insert(account.name.clone(), account.currency.clone().unwrap_or_else(|| "CNY".to_string()));
-    }
-
-    // Calculate balances from transactions
-    for transaction in transactions {
-        for posting in &transaction.postings {
-            let account_name = &posting.account;
-            let currency = if posting.currency.is_empty() {
-                currencies.get(account_name).cloned().unwrap_or_else(|| "CNY".to_string())
-            } else {
-                posting.currency.clone()
+/// Rolled-up balance of every account and tree node (see [`beanweb_core::rollup`]),
+/// display-signed; `calculated` is the operating currency part
+pub(crate) fn rolled_up_amounts(ledger: &beanweb_core::Ledger, operating_currency: &str) -> HashMap<String, AccountAmount> {
+    ledger.rolled_up_balances()
+        .iter()
+        .map(|(name, amounts)| {
+            let detail: HashMap<String, String> = amounts.iter()
+                .map(|(currency, amount)| (currency.clone(), format!("{:.2}", ledger.display_amount(name, *amount))))
+                .collect();
+            let amount = AccountAmount {
+                calculated: CalculatedAmount {
+                    number: detail.get(operating_currency).cloned().unwrap_or_else(|| "0.00".to_string()),
+                    currency: operating_currency.to_string(),
+                },
+                detail,
             };
+            (name.clone(), amount)
+        })
+        .collect()
+}
 
-            let amount = posting.amount_value().unwrap_or(0.0);
-            let account_balances = balances.entry(account_name.clone()).or_default();
-            *account_balances.entry(currency.clone()).or_insert(0.0) += amount;
-        }
-    }
-
-    // Convert to AccountAmount structure
-    let mut result: HashMap<String, AccountAmount> = HashMap::new();
-    for (account_name, currency_balances) in balances {
-        let operating_currency = currencies.get(&account_name).cloned().unwrap_or_else(|| "CNY".to_string());
-
-        // Calculate total in operating currency (simplified - just use the currency if matches)
-        let mut total = 0.0;
-        let mut detail = HashMap::new();
-
-        for (currency, balance) in currency_balances {
-            let formatted = format_balance_number(balance);
-            detail.insert(currency.clone(), formatted);
-            if currency == operating_currency {
-                total += balance;
-            }
-        }
-
-        result.insert(account_name, AccountAmount {
-            calculated: CalculatedAmount {
-                number: format_balance_number(total),
-                currency: operating_currency,
-            },
-            detail,
-        });
+/// Amount of a node without any balance
+fn zero_amount(operating_currency: &str) -> AccountAmount {
+    AccountAmount {
+        calculated: CalculatedAmount { number: "0.00".to_string(), currency: operating_currency.to_string() },
+        detail: HashMap::new(),
     }
-
-    result
 }
 
 /// Build account tree with hierarchy
-pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &HashMap<String, AccountAmount>) -> Vec<AccountTreeNode> {
+/// `balances` are rolled up (see [`rolled_up_amounts`]), so each node shows its subtree;
+/// nodes without a balance show zero in `currency`
+pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &HashMap<String, AccountAmount>, currency: &str) -> Vec<AccountTreeNode> {
     // Collect all account names
     let account_names: Vec<String> = accounts.iter().map(|a| a.name.clone()).collect();
     let account_set: std::collections::HashSet<String> = account_names.iter().cloned().collect();
@@ -177,6 +175,7 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
         name: &str,
         accounts: &[beanweb_core::Account],
         balances: &HashMap<String, AccountAmount>,
+        currency: &str,
         children_map: &HashMap<String, std::collections::HashSet<String>>,
         account_set: &std::collections::HashSet<String>,
         visited: &mut std::collections::HashSet<String>,
@@ -204,7 +203,7 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
                 let mut sorted_children: Vec<&String> = children.iter().collect();
                 sorted_children.sort();
                 for child in sorted_children {
-                    if let Some(node) = build_node(child, accounts, balances, children_map, account_set, visited) {
+                    if let Some(node) = build_node(child, accounts, balances, currency, children_map, account_set, visited) {
                         child_nodes.push(node);
                     }
                 }
@@ -218,53 +217,8 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
             None
         };
 
-        // Calculate amount: own balance + children's total
-        let combined_amount = if let Some(child_list) = &children {
-            // Start with own balance
-            let mut detail: HashMap<String, String> = HashMap::new();
-            let operating_currency = balances.get(name)
-                .map(|a| a.calculated.currency.clone())
-                .unwrap_or_else(|| "CNY".to_string());
-
-            // Add own balance first
-            if let Some(own_balance) = balances.get(name) {
-                for (currency, amount) in &own_balance.detail {
-                    detail.insert(currency.clone(), amount.clone());
-                }
-            }
-
-            // Add children's amounts
-            for child in child_list {
-                for (currency, amount) in &child.amount.detail {
-                    let child_num = amount.parse::<f64>().unwrap_or(0.0);
-                    if child_num != 0.0 {
-                        detail.entry(currency.clone()).and_modify(|current| {
-                            let curr_num = current.parse::<f64>().unwrap_or(0.0);
-                            *current = format_balance_number(curr_num + child_num);
-                        }).or_insert_with(|| amount.clone());
-                    }
-                }
-            }
-
-            // Get the total for operating currency
-            let total = detail.get(&operating_currency)
-                .map(|s| s.clone())
-                .unwrap_or_else(|| "0.00".to_string());
-
-            AccountAmount {
-                calculated: CalculatedAmount {
-                    number: total,
-                    currency: operating_currency,
-                },
-                detail,
-            }
-        } else {
-            // Leaf node - use own balance
-            balances.get(name).cloned().unwrap_or_else(|| AccountAmount {
-                calculated: CalculatedAmount { number: "0.00".to_string(), currency: "CNY".to_string() },
-                detail: HashMap::new(),
-            })
-        };
+        // Amounts are rolled up already
+        let amount = balances.get(name).cloned().unwrap_or_else(|| zero_amount(currency));
 
         Some(AccountTreeNode {
             name: name.to_string(),
@@ -272,12 +226,13 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
             path: name.to_string(),
             alias: account.and_then(|a| a.alias.clone()),
             account_status: account.map(|a| format!("{}", a.status)).unwrap_or_else(|| "Open".to_string()),
-            amount: combined_amount,
+            amount,
             children,
             has_children,
             is_leaf,
             is_real: account.is_some(),  // true if this node exists in accounts table
             depth: name.chars().filter(|&c| c == ':').count(),
+            change: None,
         })
     }
 
@@ -306,7 +261,7 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
     // Build tree for each root
     for root_name in all_roots {
         if !visited.contains(&root_name) {
-            if let Some(node) = build_node(&root_name, accounts, balances, &children_map, &account_set, &mut visited) {
+            if let Some(node) = build_node(&root_name, accounts, balances, currency, &children_map, &account_set, &mut visited) {
                 roots.push(node);
             }
         }
@@ -318,14 +273,137 @@ pub(crate) fn build_account_tree(accounts: &[beanweb_core::Account], balances: &
     roots
 }
 
+/// One level of the account tree: the direct children of `parent` (the roots when None)
+/// `balances` are rolled up (see [`rolled_up_amounts`]); `children` is left unloaded
+/// and `has_children` tells the tree whether the node can be expanded
+pub(crate) fn build_account_level(accounts: &[beanweb_core::Account], balances: &HashMap<String, AccountAmount>, currency: &str, parent: Option<&str>) -> Vec<AccountTreeNode> {
+    let prefix = parent.map(|p| format!("{}:", p));
+    let by_name: HashMap<&str, &beanweb_core::Account> = accounts.iter().map(|a| (a.name.as_str(), a)).collect();
+
+    // child name -> has children
+    let mut level: std::collections::BTreeMap<String, bool> = std::collections::BTreeMap::new();
+    for account in accounts {
+        let rest = match &prefix {
+            Some(prefix) => match account.name.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest,
+                None => continue,
+            },
+            None => account.name.as_str(),
+        };
+        let (segment, deeper) = match rest.split_once(':') {
+            Some((segment, _)) => (segment, true),
+            None => (rest, false),
+        };
+        let child = match parent {
+            Some(parent) => format!("{}:{}", parent, segment),
+            None => segment.to_string(),
+        };
+        *level.entry(child).or_default() |= deeper;
+    }
+
+    level.into_iter()
+        .map(|(name, has_children)| {
+            let account = by_name.get(name.as_str()).copied();
+            AccountTreeNode {
+                short_name: name.rsplit(':').next().unwrap_or(&name).to_string(),
+                path: name.clone(),
+                alias: account.and_then(|a| a.alias.clone()),
+                account_status: account.map(|a| format!("{}", a.status)).unwrap_or_else(|| "Open".to_string()),
+                amount: balances.get(&name).cloned().unwrap_or_else(|| zero_amount(currency)),
+                children: None,
+                has_children,
+                is_leaf: !has_children,
+                is_real: account.is_some(),
+                depth: name.matches(':').count(),
+                change: None,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Net change of every account over the active time range in `currency`, display-signed
+pub(crate) fn period_changes_in(ledger: &beanweb_core::Ledger, currency: &str) -> HashMap<String, f64> {
+    ledger.period_changes()
+        .into_iter()
+        .filter_map(|(account, amounts)| {
+            let change = ledger.display_amount(&account, *amounts.get(currency)?);
+            Some((account, change))
+        })
+        .collect()
+}
+
+/// Fill in `change` for the nodes and their loaded children, summed over each subtree
+/// Nodes without postings in the period keep None
+pub(crate) fn apply_period_changes(nodes: &mut [AccountTreeNode], changes: &HashMap<String, f64>) {
+    for node in nodes {
+        let prefix = format!("{}:", node.path);
+        let moved: Vec<f64> = changes.iter()
+            .filter(|(account, _)| **account == node.path || account.starts_with(&prefix))
+            .map(|(_, change)| *change)
+            .collect();
+        node.change = (!moved.is_empty()).then(|| moved.iter().sum());
+        if let Some(children) = node.children.as_mut() {
+            apply_period_changes(children, changes);
+        }
+    }
+}
+
+/// API: Net change per account over the active time range, largest movement first
+/// Amounts follow the display sign convention, one entry per account and currency
+pub async fn api_account_changes(state: axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
+    let ledger = state.ledger.read().await;
+    let context = ledger.time_context();
+
+    let mut changes: Vec<(String, String, f64)> = ledger.period_changes()
+        .into_iter()
+        .flat_map(|(account, amounts)| {
+            amounts.into_iter()
+                .filter(|(_, amount)| *amount != 0.0)
+                .map(move |(currency, amount)| (account.clone(), currency, amount))
+        })
+        .map(|(account, currency, amount)| {
+            let change = ledger.display_amount(&account, amount);
+            (account, currency, change)
+        })
+        .collect();
+    changes.sort_by(|a, b| b.2.abs().total_cmp(&a.2.abs()).then_with(|| a.0.cmp(&b.0)));
+
+    axum::Json(serde_json::json!({
+        "range": context.range.to_string(),
+        "start": context.start_date().map(|d| d.to_string()),
+        "end": context.end_date().map(|d| d.to_string()),
+        "changes": changes.into_iter()
+            .map(|(account, currency, change)| serde_json::json!({ "account": account, "currency": currency, "change": change }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// API: Currencies used in postings, with the accounts and account types they occur in
+/// `suspicious` marks currencies used once and never declared, e.g. CNH typed for CNY
+pub async fn api_currencies(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    let currencies: Vec<serde_json::Value> = ledger.currencies_in_use()
+        .into_iter()
+        .map(|usage| {
+            let suspicious = usage.is_suspicious();
+            let mut value = serde_json::to_value(usage).unwrap_or_default();
+            value["suspicious"] = serde_json::Value::Bool(suspicious);
+            value
+        })
+        .collect();
+    serde_json::to_string(&currencies).unwrap_or_default()
+}
+
+/// HTMX: Account tree (`?search=food&hide_closed=true&show_paused=true`)
+/// With `?parent=Assets:Brokerage` only that node's direct children are rendered
 pub async fn htmx_accounts_list(
     state: axum::extract::State<AppState>,
     query: Option<Query<HashMap<String, String>>>,
 ) -> axum::response::Response<String> {
     let ledger = state.ledger.read().await;
     let accounts = ledger.accounts();
-    let transactions = ledger.all_transactions();
-    let account_balances = calculate_balances_with_detail(&accounts, &transactions);
+    let currency = ledger.report_currency();
 
     let search_term = query
         .as_ref()
@@ -333,15 +411,25 @@ pub async fn htmx_accounts_list(
         .map(|s| s.to_lowercase())
         .unwrap_or_default();
 
-    let hide_closed = query
-        .as_ref()
-        .and_then(|q| q.0.get("hide_closed"))
-        .map(|s| s == "true")
-        .unwrap_or(false);
-
-    let tree = build_account_tree(&accounts, &account_balances);
-
-    let body = super::page::render_accounts_tree(&tree, search_term, hide_closed);
+    let filter = accounts::TreeFilter::from_query(query.as_ref().map(|q| &q.0));
+
+    // Lazy tree: one level below `parent`, fetched when a node is first expanded
+    let parent = query.as_ref().and_then(|q| q.0.get("parent")).filter(|p| !p.is_empty());
+    let body = if let Some(parent) = parent {
+        let prefix = format!("{}:", parent);
+        let subtree: Vec<beanweb_core::Account> = accounts.into_iter()
+            .filter(|a| a.name == *parent || a.name.starts_with(&prefix))
+            .collect();
+        let amounts = rolled_up_amounts(&ledger, &currency);
+        let mut level = build_account_level(&subtree, &amounts, &currency, Some(parent));
+        apply_period_changes(&mut level, &period_changes_in(&ledger, &currency));
+        accounts::level(&level, filter)
+    } else {
+        let amounts = rolled_up_amounts(&ledger, &currency);
+        let mut tree = build_account_tree(&accounts, &amounts, &currency);
+        apply_period_changes(&mut tree, &period_changes_in(&ledger, &currency));
+        accounts::tree(&tree, &search_term, filter)
+    };
 
     axum::response::Response::builder()
         .header(axum::http::header::CONTENT_TYPE, "text/html; charset=utf-8")
@@ -349,38 +437,29 @@ pub async fn htmx_accounts_list(
         .unwrap()
 }
 
+/// Suggestions shown when the request doesn't ask for a limit, and the most it may ask for
+const SUGGEST_LIMIT: usize = 10;
+const SUGGEST_MAX_LIMIT: usize = 50;
+
+/// HTMX: Account autocomplete (`?search=food&target=<id>&limit=10`); with
+/// `date=YYYY-MM-DD` only accounts open on that day are offered.
+/// Served from the ledger's cached suggestion index; hosts should still debounce
+/// keystrokes (`hx-trigger="keyup changed delay:150ms"`, `hx-sync="this:replace"`)
 pub async fn htmx_account_suggest(
     state: axum::extract::State<AppState>,
     query: Query<HashMap<String, String>>,
 ) -> String {
-    let ledger = state.ledger.read().await;
-    let accounts = ledger.accounts();
-    let q = query.get("search").map(|s| s.to_lowercase()).unwrap_or_default();
+    // Only the Arc is taken under the ledger lock
+    let suggestions = state.ledger.read().await.account_suggestions();
+    let q = query.get("search").map(|s| s.as_str()).unwrap_or_default();
     let target = query.get("target").map(|s| s.as_str()).unwrap_or("account-suggest");
+    let limit = query.get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(SUGGEST_LIMIT)
+        .min(SUGGEST_MAX_LIMIT);
 
-    if q.is_empty() {
-        return format!(r#"<div id='{}' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-40 overflow-auto hidden'></div>"#, target);
-    }
-
-    let filtered: Vec<&beanweb_core::Account> = accounts.iter()
-        .filter(|a| a.name.to_lowercase().contains(&q))
-        .take(10)
-        .collect();
-
-    if filtered.is_empty() {
-        return format!(r#"<div id='{}' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-40 overflow-auto hidden'></div>"#, target);
-    }
-
-    let options: Vec<String> = filtered.iter().map(|a| {
-        format!(r#"<div class='px-3 py-2 hover:bg-indigo-50 cursor-pointer text-sm border-b last:border-0' data-account='{}' onclick="selectAccount(this, '{}')"><div class='font-medium'>{}</div></div>"#,
-            a.name, target, a.name)
-    }).collect();
-
-    format!(
-        r#"<div id='{}' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-40 overflow-auto'>{}</div>"#,
-        target,
-        options.join("")
-    )
+    let date = query.get("date").map(|d| d.as_str()).filter(|d| !d.is_empty());
+    accounts::suggestions(target, &suggestions.suggest_on(q, limit, date))
 }
 
 pub async fn htmx_account_transactions_list(
@@ -393,17 +472,25 @@ pub async fn htmx_account_transactions_list(
     let limit = params.get("limit").and_then(|s| s.parse().ok()).unwrap_or(50);
     let offset = params.get("offset").and_then(|s| s.parse().ok()).unwrap_or(0);
     let query = params.get("q").map(|s| s.to_lowercase()).unwrap_or_default();
+    // YYYY-MM from the monthly table
+    let month = params.get("month")
+        .filter(|m| chrono::NaiveDate::parse_from_str(&format!("{}-01", m), "%Y-%m-%d").is_ok())
+        .map(|m| m.as_str());
 
     // Get all records for balance calculation
-    let transactions = ledger.transactions_by_account(&account_name);
-    let balances = ledger.balances_by_account(&account_name);
+    let mut transactions = ledger.transactions_by_account(&account_name);
+    let mut balances = ledger.balances_by_account(&account_name);
+    if let Some(month) = month {
+        transactions.retain(|tx| tx.date.starts_with(month));
+        balances.retain(|b| b.date.starts_with(month));
+    }
     let _pads = ledger.pads_by_account(&account_name);
 
     // Initial balance is always 0 for new accounts
     // Balance directives only affect transactions AFTER them (at start of day)
     let initial_balance = 0.0;
 
-    eprintln!("[DEBUG htmx_account_transactions_list] account={}, initial_balance={}, tx_count={}, balance_count={}",
+    tracing::debug!("htmx_account_transactions_list: account={}, initial_balance={}, tx_count={}, balance_count={}",
         account_name, initial_balance, transactions.len(), balances.len());
 
     // Apply search filter if query provided
@@ -418,5 +505,138 @@ pub async fn htmx_account_transactions_list(
         });
     }
 
-    super::page::render_account_transactions_paginated(&filtered_transactions, &balances, &account_name, limit, offset, initial_balance)
+    let filtered_transactions: Vec<_> = filtered_transactions.iter().collect();
+    let list = super::page::render_account_transactions_paginated(&filtered_transactions, &balances, &account_name, limit, offset, initial_balance, &ledger, month);
+    match month {
+        Some(month) => accounts::month_filter(&account_name, month, limit, &list),
+        None => list,
+    }
+}
+
+/// API: Money in and out, net and ending balance of an account for the last
+/// twelve months of the time range
+pub async fn api_account_monthly(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> Result<axum::Json<beanweb_core::trends::AccountMonthly>, ApiError> {
+    let ledger = state.ledger.read().await;
+    if ledger.account(&path.0).is_none() {
+        return Err(ApiError::NotFound { resource: format!("account {}", path.0) });
+    }
+    Ok(axum::Json(ledger.account_monthly(&path.0, beanweb_core::trends::ACCOUNT_MONTHS)))
+}
+
+/// HTMX: Monthly table on the account detail page, newest month first; a
+/// month shows just its transactions in the list below
+pub async fn htmx_account_monthly(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> String {
+    let ledger = state.ledger.read().await;
+    let monthly = ledger.account_monthly(&path.0, beanweb_core::trends::ACCOUNT_MONTHS);
+    drop(ledger);
+    accounts::monthly(&monthly)
+}
+
+/// Pause or resume an account and reload; see [`beanweb_core::pause`]
+async fn set_paused(state: &AppState, account: &str, paused: bool) -> Result<beanweb_core::Account, ApiError> {
+    let mut ledger = state.ledger.write().await;
+    let file = ledger.set_account_paused(account, paused).map_err(|e| match e {
+        beanweb_core::CoreError::AccountNotFound { .. } => ApiError::NotFound { resource: format!("account {}", account) },
+        e => ApiError::BadRequest { message: e.to_string() },
+    })?;
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after updating account status: {}", e);
+    }
+    tracing::info!("{} {} in {}", if paused { "Paused" } else { "Resumed" }, account, file.display());
+    ledger.account(account).ok_or(ApiError::InternalError)
+}
+
+/// API: Pause an account, hiding it from autocompletion and default views without closing it
+pub async fn api_account_pause(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> Result<axum::Json<beanweb_core::Account>, ApiError> {
+    set_paused(&state, &path.0, true).await.map(axum::Json)
+}
+
+/// API: Resume a paused account
+pub async fn api_account_resume(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> Result<axum::Json<beanweb_core::Account>, ApiError> {
+    set_paused(&state, &path.0, false).await.map(axum::Json)
+}
+
+/// HTMX: Pause/resume button on the account detail page; refreshes the page
+pub async fn htmx_account_paused(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+    query: Query<HashMap<String, String>>,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    let paused = query.get("paused").is_some_and(|p| p == "true");
+    match set_paused(&state, &path.0, paused).await {
+        Ok(_) => ([("HX-Refresh", "true")], String::new()).into_response(),
+        Err(e) => accounts::action_error(&e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(name: &str, status: beanweb_core::AccountStatus) -> beanweb_core::Account {
+        beanweb_core::Account {
+            name: name.to_string(),
+            account_type: beanweb_core::AccountType::Assets,
+            status,
+            balance: serde_json::Value::Null,
+            currency: None,
+            open_date: None,
+            close_date: None,
+            alias: None,
+            note: None,
+            tags: Vec::new(),
+            source: None,
+            line: None,
+        }
+    }
+
+    #[test]
+    fn test_build_account_level() {
+        use beanweb_core::AccountStatus::{Closed, Open};
+        let accounts = vec![
+            account("Assets:Bank:Checking", Open),
+            account("Assets:Bank:Savings", Closed),
+            account("Assets:Cash", Open),
+            account("Assets", Open),
+            account("Expenses:Food", Open),
+        ];
+        let mut balances = HashMap::new();
+        balances.insert("Assets:Bank".to_string(), AccountAmount {
+            calculated: CalculatedAmount { number: "150.00".to_string(), currency: "CNY".to_string() },
+            detail: HashMap::from([("CNY".to_string(), "150.00".to_string())]),
+        });
+
+        // Roots, once each, sorted
+        let roots = build_account_level(&accounts, &balances, "CNY", None);
+        let names: Vec<(&str, bool, bool)> = roots.iter().map(|n| (n.name.as_str(), n.has_children, n.is_real)).collect();
+        assert_eq!(names, vec![("Assets", true, true), ("Expenses", true, false)]);
+
+        // One level down: a parent without its own `open` is implicit and
+        // carries its rolled-up balance; children are left to load
+        let level = build_account_level(&accounts, &balances, "CNY", Some("Assets"));
+        let names: Vec<(&str, bool, bool)> = level.iter().map(|n| (n.name.as_str(), n.has_children, n.is_real)).collect();
+        assert_eq!(names, vec![("Assets:Bank", true, false), ("Assets:Cash", false, true)]);
+        assert_eq!((level[0].short_name.as_str(), level[0].depth, level[0].children.is_none()), ("Bank", 1, true));
+        assert_eq!(level[0].amount.calculated.number, "150.00");
+        assert_eq!((level[1].amount.calculated.number.as_str(), level[1].amount.calculated.currency.as_str()), ("0.00", "CNY"));
+
+        let leaves = build_account_level(&accounts, &balances, "CNY", Some("Assets:Bank"));
+        let statuses: Vec<(&str, &str, bool)> = leaves.iter().map(|n| (n.short_name.as_str(), n.account_status.as_str(), n.is_leaf)).collect();
+        assert_eq!(statuses, vec![("Checking", "open", true), ("Savings", "closed", true)]);
+        // A prefix that is not a whole component matches nothing
+        assert!(build_account_level(&accounts, &balances, "CNY", Some("Assets:Ba")).is_empty());
+    }
 }
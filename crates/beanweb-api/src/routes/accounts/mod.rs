@@ -4,29 +4,41 @@
 //! - List all accounts with hierarchical tree structure
 //! - Tree view with expandable/collapsible nodes
 //! - Multi-currency balance display with detail tooltips
-//! - Show/hide closed accounts toggle
+//! - Net change over the selected time range next to each balance
+//! - Show/hide closed accounts toggle; paused accounts hidden unless asked for
 //! - Account search and filtering
-//! - Account detail page with transactions
+//! - Account detail page with transactions and a monthly totals table
+//! - Pausing and resuming seasonal accounts without closing them
+//! - Reusable tree-based account picker for forms and filters
 //!
 //! Structure:
 //! - api.rs: JSON API and HTMX endpoints
 //! - page.rs: Full page rendering
+//! - picker.rs: Account picker fragment
 
 pub mod api;
 pub mod page;
+pub mod picker;
 
 pub use api::{
     api_accounts,
+    api_account_changes,
+    api_account_monthly,
+    api_account_pause,
+    api_account_resume,
+    api_currencies,
     htmx_accounts_list,
     htmx_account_suggest,
     htmx_account_transactions_list,
+    htmx_account_monthly,
+    htmx_account_paused,
     AccountAmount,
     AccountListItem,
     AccountTreeNode,
 };
+pub use picker::htmx_account_picker;
 pub use page::{
     page_accounts,
     page_account_detail,
-    render_account_transactions_paginated,
     get_posting_amount_for_account,
 };
@@ -0,0 +1,56 @@
+//! Account picker - Reusable tree-based account selector (HTML fragment)
+//!
+//! `/accounts/picker?target=<input name>&type=expenses` renders a collapsible
+//! account tree that fills the named input on selection; the markup and its
+//! script are in `beanweb_ui::picker`.
+
+use crate::AppState;
+use axum::extract::Query;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Map `type` values ("expenses", "assets,liabilities") to root account names
+fn requested_roots(types: Option<&str>) -> Vec<&'static str> {
+    let Some(types) = types.filter(|t| !t.is_empty()) else {
+        return Vec::new();
+    };
+    types.split(',')
+        .filter_map(|t| match t.trim().to_lowercase().as_str() {
+            "assets" => Some("Assets"),
+            "liabilities" => Some("Liabilities"),
+            "equity" => Some("Equity"),
+            "income" => Some("Income"),
+            "expenses" => Some("Expenses"),
+            _ => None,
+        })
+        .collect()
+}
+
+/// HTMX: Account picker tree
+pub async fn htmx_account_picker(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> String {
+    let ledger = state.ledger.read().await;
+    let target = query.get("target").map(|s| s.as_str()).unwrap_or("account");
+    let roots = requested_roots(query.get("type").map(|s| s.as_str()));
+
+    // Open accounts only, paused ones left out; parents of nested accounts are added as implicit nodes
+    let real: HashSet<String> = ledger.accounts()
+        .into_iter()
+        .filter(|a| a.status == beanweb_core::AccountStatus::Open)
+        .map(|a| a.name)
+        .filter(|name| roots.is_empty() || roots.iter().any(|r| name.split(':').next() == Some(*r)))
+        .collect();
+
+    let mut children: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for name in &real {
+        let mut parent = String::new();
+        for part in name.split(':') {
+            let path = if parent.is_empty() { part.to_string() } else { format!("{}:{}", parent, part) };
+            children.entry(parent.clone()).or_default().insert(path.clone());
+            parent = path;
+        }
+    }
+
+    beanweb_ui::picker::tree(target, &children, &real)
+}
@@ -0,0 +1,22 @@
+//! Budgets API endpoints
+//!
+//! JSON API for budget reports
+
+use crate::AppState;
+
+/// GET /api/budgets - spent vs budget per account for the active time range
+pub async fn api_budgets(state: axum::extract::State<AppState>) -> String {
+    if !state.config.is_feature_enabled("budget") {
+        return r#"{"status": "disabled"}"#.to_string();
+    }
+    let ledger = state.ledger.read().await;
+    let report = ledger.budget_report();
+    serde_json::json!({
+        "status": "ok",
+        "time_range": ledger.time_context().range.to_string(),
+        "start": report.start,
+        "end": report.end,
+        "budgets": report.items,
+    })
+    .to_string()
+}
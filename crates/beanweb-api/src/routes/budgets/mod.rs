@@ -0,0 +1,19 @@
+//! Budget routes
+//!
+//! Features:
+//! - Spent vs budget per account for the active time range
+//! - Progress bars, amber from 80% and red once over budget
+//! - Weekly to yearly budgets, marked when prorated to a range that isn't
+//!   made of whole periods
+//!
+//! Everything here is off unless `features.budget_enable` is set.
+//!
+//! Structure:
+//! - api.rs: JSON API endpoints
+//! - page.rs: HTMX page rendering
+
+pub mod api;
+pub mod page;
+
+pub use api::api_budgets;
+pub use page::{htmx_budgets_list, page_budgets};
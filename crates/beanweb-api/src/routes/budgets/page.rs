@@ -0,0 +1,29 @@
+//! Budgets page rendering
+//!
+//! HTMX page endpoints for budget progress
+
+use crate::AppState;
+use beanweb_ui::budgets;
+
+/// Budgets page - Spent vs budget per account, reloaded with the time range
+pub async fn page_budgets(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let ledger = state.ledger.read().await;
+    let time_range = ledger.time_context().range.to_string();
+    let start_date = ledger.time_context().start_date().map(|d| d.to_string()).unwrap_or_else(|| "-".to_string());
+    let end_date = ledger.time_context().end_date().map(|d| d.to_string()).unwrap_or_else(|| "-".to_string());
+
+    let inner_content = budgets::page(&crate::page_time_selector(&time_range, &start_date, &end_date));
+
+    axum::response::Html(crate::page_response_with_time(&headers, &state.config, "预算", "/budgets", &inner_content, &time_range))
+}
+
+/// HTMX: Budget table for the active time range
+pub async fn htmx_budgets_list(state: axum::extract::State<AppState>) -> String {
+    if !state.config.is_feature_enabled("budget") {
+        return budgets::disabled();
+    }
+    budgets::list(&state.ledger.read().await.budget_report())
+}
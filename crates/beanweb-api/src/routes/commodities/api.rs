@@ -1,22 +1,15 @@
 //! Commodities API endpoints
 //!
-//! JSON API for commodities data (if needed in the future)
+//! JSON API for commodity totals
 
 use crate::AppState;
 
-/// Placeholder for future JSON API endpoint
-/// Example: GET /api/commodities - returns commodity balances as JSON
+/// GET /api/commodities - every commodity with its per-account totals
 pub async fn api_commodities(state: axum::extract::State<AppState>) -> String {
     let ledger = state.ledger.read().await;
-
-    // Calculate balances
-    let balances = calculate_all_commodity_balances(&ledger);
-
-    // Return as JSON
-    serde_json::to_string(&balances).unwrap_or_default()
+    serde_json::json!({
+        "currency": ledger.report_currency(),
+        "commodities": ledger.commodity_totals(),
+    })
+    .to_string()
 }
-
-// Re-export shared calculation logic
-pub(crate) use super::page::calculate_all_commodity_balances;
-
-use super::page::CommodityBalance;
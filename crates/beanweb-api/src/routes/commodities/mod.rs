@@ -1,13 +1,15 @@
 //! Commodities/Multi-currency routes
 //!
 //! Features:
-//! - Display all commodity/currency total balances
+//! - Every declared or used commodity with its total units, latest price and value
+//! - Per-account breakdown of each commodity
 //!
 //! Structure:
-//! - api.rs: JSON API endpoints (if needed)
+//! - api.rs: JSON API endpoints
 //! - page.rs: HTMX page rendering
 
 pub mod api;
 pub mod page;
 
+pub use api::api_commodities;
 pub use page::page_commodities;
@@ -0,0 +1,29 @@
+//! Server-Sent Events for live ledger updates
+//!
+//! `/api/events` pushes a `ledger-reloaded` event after every successful load
+//! (file watcher, saves from the UI, `/api/reload`). The layout script turns
+//! it into an htmx event on `body`, so fragments subscribed with
+//! `hx-trigger='... ledger-reloaded from:body'` refetch themselves.
+
+use crate::AppState;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use std::convert::Infallible;
+
+/// Name of the event sent after a reload, also fired on `body` by the layout
+pub const LEDGER_RELOADED_EVENT: &str = "ledger-reloaded";
+
+/// SSE stream of ledger reloads; the data carries the number of loads so far
+pub async fn api_events(state: axum::extract::State<AppState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let reloads = state.ledger.read().await.subscribe_reloads();
+    let stream = futures_util::stream::unfold(reloads, |mut reloads| async move {
+        // Ends the stream when the ledger is dropped (server shutdown)
+        reloads.changed().await.ok()?;
+        let count = *reloads.borrow_and_update();
+        let event = Event::default()
+            .event(LEDGER_RELOADED_EVENT)
+            .data(serde_json::json!({ "reloads": count }).to_string());
+        Some((Ok(event), reloads))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,45 @@
+//! Export routes
+//!
+//! Provides downloadable copies of the ledger
+//! - api_export_beancount: The loaded ledger as one canonical Beancount file
+//! - api_export_anonymized: Anonymized copy for bug reports
+
+use crate::AppState;
+use beanweb_core::AnonymizeOptions;
+use std::collections::HashMap;
+
+/// Download an anonymized copy of the ledger for bug reports
+/// Query params:
+/// - scale: multiply all amounts by this factor (e.g. 0.37)
+/// - seed: fixed salt so repeated exports produce the same names
+pub async fn api_export_anonymized(
+    state: axum::extract::State<AppState>,
+    query: axum::extract::Query<HashMap<String, String>>,
+) -> impl axum::response::IntoResponse {
+    let ledger = state.ledger.read().await;
+    let options = AnonymizeOptions {
+        scale: query.get("scale").and_then(|s| s.parse().ok()),
+        seed: query.get("seed").filter(|s| !s.is_empty()).cloned(),
+    };
+
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"anonymized.bean\""),
+        ],
+        ledger.anonymized_export(&options),
+    )
+}
+
+/// Download the loaded ledger as a single canonical Beancount file
+/// Comments and layout of the source files are not kept
+pub async fn api_export_beancount(state: axum::extract::State<AppState>) -> impl axum::response::IntoResponse {
+    let ledger = state.ledger.read().await;
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"ledger.bean\""),
+        ],
+        ledger.render_ledger(),
+    )
+}
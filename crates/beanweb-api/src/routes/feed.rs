@@ -0,0 +1,120 @@
+//! Atom feed of ledger activity
+//!
+//! `/feed/transactions.xml` lists the latest transactions, newest first, so
+//! a feed reader can follow the ledger without polling the API:
+//! - `?account=` takes an account filter (`Assets:Bank`, globs, `~regex`)
+//!   and `?tag=` a tag without `#`; both may be combined
+//! - Upcoming transactions are left out until their date
+//! - Entry ids use the content hash of the transaction, so an entry keeps its
+//!   id when lines are inserted above it in the file
+//! - Each entry links to the transaction's page; links are absolute, built
+//!   from `server.base_url` rather than the request's `Host` header
+//!
+//! With `server.auth` set, feed readers log in with HTTP Basic credentials.
+
+use beanweb_ui::transactions::headline_amount;
+use crate::{html_escape, ApiError, AppState};
+use axum::http::header;
+use axum::response::IntoResponse;
+use beanweb_core::{AccountFilter, Transaction, TransactionHeadline};
+use std::collections::HashMap;
+
+/// Entries per feed
+const FEED_ENTRIES: usize = 50;
+
+/// RFC 3339 timestamp of a transaction, at midnight without a time
+fn timestamp(tx: &Transaction, offset: &str) -> String {
+    let time = match tx.time.len() {
+        0 => "00:00:00".to_string(),
+        5 => format!("{}:00", tx.time),
+        _ => tx.time.clone(),
+    };
+    format!("{}T{}{}", tx.date, time, offset)
+}
+
+fn render_entry(tx: &Transaction, headline: &TransactionHeadline, base: &str, offset: &str) -> String {
+    let (amount, _, currency) = headline_amount(headline);
+    let title = [tx.payee.as_str(), tx.narration.as_str()].iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" · ");
+    let postings: String = tx.postings.iter()
+        .map(|p| format!("<li>{} {}</li>", html_escape(&p.account), html_escape(&p.amount)))
+        .collect();
+    let hash = tx.id.rsplit(':').next().unwrap_or(&tx.id);
+    format!(
+        r#"  <entry>
+    <id>urn:beanweb:transaction:{hash}</id>
+    <title>{title} {amount} {currency}</title>
+    <link href="{base}/transactions/{path}"/>
+    <updated>{updated}</updated>
+    <content type="html">{content}</content>
+  </entry>
+"#,
+        hash = html_escape(hash),
+        title = html_escape(&title),
+        amount = html_escape(&amount),
+        currency = html_escape(&currency),
+        base = html_escape(base),
+        path = urlencoding::encode(&tx.id),
+        updated = timestamp(tx, offset),
+        content = html_escape(&format!("<p>{}</p><ul>{}</ul>", tx.date, postings)),
+    )
+}
+
+/// Atom feed of the latest transactions (`?account=`, `?tag=`)
+pub async fn feed_transactions(
+    state: axum::extract::State<AppState>,
+    query: axum::extract::Query<HashMap<String, String>>,
+    uri: axum::http::Uri,
+) -> Result<impl IntoResponse, ApiError> {
+    let param = |name: &str| query.get(name).map(|s| s.trim()).filter(|s| !s.is_empty());
+    let filters = match param("account") {
+        Some(account) => vec![AccountFilter::parse(account).map_err(|e| ApiError::BadRequest { message: e.to_string() })?],
+        None => Vec::new(),
+    };
+    let tag = param("tag").map(|t| t.trim_start_matches('#').to_string());
+
+    let ledger = state.ledger.read().await;
+    let as_of = ledger.as_of_date();
+    let page = ledger
+        .transactions_after(None, FEED_ENTRIES, &filters, "", |tx| {
+            !tx.is_upcoming(as_of) && tag.as_ref().is_none_or(|tag| tx.tags.contains(tag))
+        })
+        .map_err(|e| ApiError::BadRequest { message: e.to_string() })?;
+
+    let base = state.config.server.base_url();
+    let offset = chrono::Local::now().format("%:z").to_string();
+    let mut title = "Beanweb 交易".to_string();
+    for filter in [param("account"), param("tag")].into_iter().flatten() {
+        title.push_str(" · ");
+        title.push_str(filter);
+    }
+    let updated = page.transactions.iter()
+        .map(|tx| timestamp(tx, &offset))
+        .max()
+        .unwrap_or_else(|| chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false));
+    let entries: String = page.transactions.iter()
+        .map(|tx| render_entry(tx, &ledger.headline(tx), &base, &offset))
+        .collect();
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/feed/transactions.xml");
+
+    let feed = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:beanweb:feed:{path}</id>
+  <title>{title}</title>
+  <updated>{updated}</updated>
+  <link rel="self" href="{base}{path}"/>
+  <link href="{base}/transactions"/>
+{entries}</feed>
+"#,
+        title = html_escape(&title),
+        updated = updated,
+        path = html_escape(path),
+        base = html_escape(&base),
+        entries = entries,
+    );
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], feed))
+}
@@ -2,19 +2,24 @@
 //!
 //! Features:
 //! - File listing with include recursion
-//! - File content read/write
+//! - Per-file parse statistics and large-file warnings
+//! - File content read/write, in line windows for large files
 //! - Account validation on save
+//! - Ledger files no include reaches, with a button adding the include
 
-use crate::AppState;
+use crate::{ApiError, AppState};
+use crate::collection::{Collection, CollectionQuery};
 use axum::extract::Path;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// File info structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 struct FileInfo {
     name: String,
     modified: String,
     size: String,
+    bytes: u64,
     referenced: bool,
 }
 
@@ -150,6 +155,7 @@ fn scan_directory_iterative(base_path: &PathBuf) -> Vec<FileInfo> {
                             name: relative_path,
                             modified: get_file_modified(&path),
                             size: get_file_size(&path),
+                            bytes: path.metadata().map(|m| m.len()).unwrap_or(0),
                             referenced: false,
                         });
                     }
@@ -185,8 +191,36 @@ fn get_file_size(path: &PathBuf) -> String {
     }
 }
 
-/// HTMX: Get file list (with optional search filter)
-pub async fn api_files_list(state: axum::extract::State<AppState>, query: Option<axum::extract::Query<std::collections::HashMap<String, String>>>) -> String {
+/// Index parse stats by path relative to the data directory
+fn stats_by_relative_path(base_path: &PathBuf, stats: Vec<beanweb_core::FileParseStats>) -> std::collections::HashMap<String, beanweb_core::FileParseStats> {
+    stats.into_iter()
+        .map(|s| {
+            let path = PathBuf::from(&s.path);
+            let relative = path.strip_prefix(base_path).unwrap_or(&path).to_string_lossy().into_owned();
+            (relative, s)
+        })
+        .collect()
+}
+
+/// `/api/files` collection parameters
+pub struct FileCollection;
+
+impl Collection for FileCollection {
+    const FILTERS: &'static [&'static str] = &["name"];
+    const SORTS: &'static [&'static str] = &["name", "modified", "size"];
+    const DEFAULT_SORT: &'static str = "name";
+    const DEFAULT_PAGE_SIZE: usize = 100;
+}
+
+/// File list; `filter[name]` keeps names containing it (case-insensitive)
+/// HTMX requests get the files page table with every matching file, other
+/// requests the paginated JSON collection
+pub async fn api_files_list(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+    query: CollectionQuery<FileCollection>,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
     let config = &state.config;
     let base_path = &config.data.path;
 
@@ -206,49 +240,142 @@ pub async fn api_files_list(state: axum::extract::State<AppState>, query: Option
     }
 
     // Apply search filter
-    let search_term = query.as_ref().and_then(|q| q.0.get("search").map(|s| s.to_lowercase())).unwrap_or_default();
-    if !search_term.is_empty() {
+    if let Some(search_term) = query.filter("name").map(str::to_lowercase) {
         files.retain(|f| f.name.to_lowercase().contains(&search_term));
     }
 
-    files.sort_by(|a, b| a.name.cmp(&b.name));
+    query.sort(&mut files, |field, a, b| match field {
+        "modified" => a.modified.cmp(&b.modified),
+        "size" => a.bytes.cmp(&b.bytes),
+        _ => a.name.cmp(&b.name),
+    });
 
-    // Render HTML
-    if files.is_empty() {
-        return r#"<div class='text-center py-12 text-gray-500'>没有找到 Beancount 文件</div>"#.to_string();
+    if crate::is_htmx_request(&headers) {
+        render_files_table(&state, &files).await.into_response()
+    } else {
+        axum::Json(query.paginate(files)).into_response()
     }
+}
 
-    let mut html = r#"<table class='w-full'><thead><tr><th class='text-left p-4 bg-gray-50'>文件名</th><th class='text-left p-4 bg-gray-50'>修改时间</th><th class='text-left p-4 bg-gray-50'>大小</th><th class='text-left p-4 bg-gray-50'>操作</th></tr></thead><tbody>"#.to_string();
-
-    for file in &files {
-        // NOTE: 引用状态显示已禁用（不准确）
-        // let status = if file.referenced {
-        //     r#"<span class='text-xs bg-green-100 text-green-800 px-2 py-1 rounded'>已引用</span>"#
-        // } else {
-        //     r#"<span class='text-xs bg-yellow-100 text-yellow-800 px-2 py-1 rounded'>未引用</span>"#
-        // };
-
-        html.push_str(&format!(
-            r#"<tr class='border-b hover:bg-gray-50'><td class='p-4'><a href='/files/{}' class='text-indigo-600 hover:text-indigo-800 hover:underline'>{}</a></td><td class='p-4 text-gray-500'>{}</td><td class='p-4 text-gray-500'>{}</td><td class='p-4'><a href='/files/{}' class='px-3 py-1 bg-indigo-100 text-indigo-700 rounded hover:bg-indigo-200'>编辑</a></td></tr>"#,
-            urlencoding::encode(&file.name),
-            file.name,
-            file.modified,
-            file.size,
-            urlencoding::encode(&file.name)
-        ));
-    }
+/// Files page table with size warnings and parse statistics
+async fn render_files_table(state: &AppState, files: &[FileInfo]) -> String {
+    let config = &state.config;
+    let stats = stats_by_relative_path(&config.data.path, state.ledger.read().await.file_stats());
+    let rows: Vec<_> = files.iter()
+        .map(|file| beanweb_ui::files::FileRow {
+            name: &file.name,
+            modified: &file.modified,
+            size: &file.size,
+            bytes: file.bytes,
+            stats: stats.get(&file.name),
+        })
+        .collect();
+    beanweb_ui::files::files_table(&rows, config.data.file_size_warning_kb)
+}
+
+/// Lines per window when a range is requested without an end
+const WINDOW_LINES: usize = 500;
+
+/// Saved while the file was changed elsewhere
+const STALE_FILE: &str = "文件已在别处修改，请重新加载后再编辑";
+
+/// `ETag` of file content: the start of its SHA-256, quoted
+pub(crate) fn content_etag(content: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    etag(&Sha256::digest(content))
+}
 
-    html.push_str("</tbody></table>");
-    html
+/// [`content_etag`] of the file at `path`, hashed in chunks
+fn file_etag(path: &std::path::Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(etag(&hasher.finalize()))
 }
 
-pub async fn api_file_content(state: axum::extract::State<AppState>, path: Path<String>) -> String {
+fn etag(digest: &[u8]) -> String {
+    format!("\"{}\"", data_encoding::HEXLOWER.encode(&digest[..16]))
+}
+
+/// File content; a `Range: lines=a-b` header or `?offset=&limit=` (in lines)
+/// returns only that window as 206 Partial Content with a `Content-Range`,
+/// reading no more of the file than the window. A `Range` that is not a
+/// line range is ignored, as HTTP allows. The `ETag` is the version of the
+/// whole file, which saves send back in `If-Match`
+pub async fn api_file_content(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use beanweb_utils::lines::{parse_line_range, read_line_window};
+
     let config = &state.config;
     let file_path = config.data.path.join(&path.0);
 
-    match std::fs::read_to_string(&file_path) {
-        Ok(content) => content,
-        Err(_) => String::new(),
+    let range = headers.get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_line_range)
+        .or_else(|| params.get("offset").map(|offset| (
+            offset.parse().unwrap_or(0),
+            params.get("limit").and_then(|l| l.parse().ok()),
+        )));
+    let Some((offset, limit)) = range else {
+        return match std::fs::read_to_string(&file_path) {
+            Ok(content) => ([(header::ETAG, content_etag(content.as_bytes()))], content).into_response(),
+            Err(_) => String::new().into_response(),
+        };
+    };
+
+    let read = read_line_window(&file_path, offset, limit.unwrap_or(WINDOW_LINES).max(1))
+        .and_then(|window| Ok((window, file_etag(&file_path)?)));
+    let (window, etag) = match read {
+        Ok(read) => read,
+        Err(_) => return String::new().into_response(),
+    };
+    let status = if window.is_complete() { StatusCode::OK } else { StatusCode::PARTIAL_CONTENT };
+    (
+        status,
+        [
+            (header::CONTENT_RANGE, window.content_range()),
+            (header::ACCEPT_RANGES, "lines".to_string()),
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (header::ETAG, etag),
+        ],
+        window.text,
+    )
+        .into_response()
+}
+
+/// Serve an attached document (receipt image/PDF) from the documents folder
+pub async fn api_document(state: axum::extract::State<AppState>, path: Path<String>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let config = &state.config;
+    let relative = std::path::Path::new(&path.0);
+    let documents_dir = std::path::Path::new(&config.data.documents_dir);
+    // Only plain paths inside the documents folder; no `..` or absolute paths
+    let inside = relative.components().all(|c| matches!(c, std::path::Component::Normal(_)))
+        && relative.starts_with(documents_dir);
+    if !inside {
+        return (axum::http::StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+
+    let content_type = match relative.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("heic") => "image/heic",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    };
+
+    match std::fs::read(config.data.path.join(relative)) {
+        Ok(bytes) => ([(axum::http::header::CONTENT_TYPE, content_type)], bytes).into_response(),
+        Err(_) => (axum::http::StatusCode::NOT_FOUND, "Not found").into_response(),
     }
 }
 
@@ -315,33 +442,115 @@ fn validate_accounts(content: &str, ledger: &beanweb_core::Ledger) -> Validation
     }
 }
 
-pub async fn api_file_save(state: axum::extract::State<AppState>, path: Path<String>, body: String) -> String {
-    let config = &state.config;
-    let file_path = config.data.path.join(&path.0);
+/// Save a file; with a `Content-Range: lines a-b/total` header the body
+/// replaces only those lines, as loaded by the windowed editor of large files.
+/// With `If-Match` (required for a window) the file must still be the version
+/// the editor loaded, else 409 Conflict; the response carries the new `ETag`.
+/// Reading, checking, writing and reloading all happen under the ledger's
+/// write lock, so concurrent saves can't interleave
+pub async fn api_file_save(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use beanweb_utils::lines::{parse_content_range, splice_lines};
+
+    let file_path = state.config.data.path.join(&path.0);
+    let header_value = |name| headers.get(name).and_then(|v: &axum::http::HeaderValue| v.to_str().ok());
+    let if_match = header_value(header::IF_MATCH).map(str::trim);
+    let refused = |status: StatusCode, reason: &str| (status, beanweb_ui::files::save_result(Err(reason), &[])).into_response();
+
+    let mut ledger = state.ledger.write().await;
+    let current = std::fs::read(&file_path).ok();
+    if let Some(expected) = if_match {
+        if current.as_deref().map(content_etag).as_deref() != Some(expected) {
+            return refused(StatusCode::CONFLICT, STALE_FILE);
+        }
+    }
+
+    let body = match header_value(header::CONTENT_RANGE) {
+        None => body,
+        Some(value) => {
+            let Some((offset, count, total)) = parse_content_range(value) else {
+                return refused(StatusCode::BAD_REQUEST, "无效的行范围");
+            };
+            let current = String::from_utf8_lossy(current.as_deref().unwrap_or_default());
+            if if_match.is_none() || current.split_inclusive('\n').count() != total {
+                return refused(StatusCode::CONFLICT, STALE_FILE);
+            }
+            splice_lines(&current, offset, count, &body)
+        }
+    };
 
     // Validate accounts before saving
-    let ledger = state.ledger.read().await;
     let validation = validate_accounts(&body, &ledger);
+    let saved = std::fs::write(&file_path, &body).map_err(|e| e.to_string());
+    if saved.is_ok() {
+        // Trigger ledger reload directly (not through HTTP)
+        if let Err(e) = ledger.reload().await {
+            tracing::error!("Failed to reload ledger after file save: {}", e);
+        }
+    }
+    drop(ledger);
 
-    // Show warnings even if valid
-    let warning_html = if !validation.warnings.is_empty() {
-        let warnings: Vec<String> = validation.warnings.iter().map(|w| format!("<li class='ml-4'>{}</li>", w)).collect();
-        format!(r#"<div class='bg-yellow-50 border border-yellow-200 rounded-lg p-4 mt-2'><div class='flex items-center gap-2'><span class='text-yellow-600'>⚠</span><span class='font-medium text-yellow-800'>警告</span></div><ul class='mt-2 text-sm text-yellow-700'>{}</ul></div>"#, warnings.join(""))
-    } else {
-        String::new()
-    };
+    let html = beanweb_ui::files::save_result(saved.as_ref().copied().map_err(|e| e.as_str()), &validation.warnings);
+    match saved {
+        Ok(()) => ([(header::ETAG, content_etag(body.as_bytes()))], html).into_response(),
+        Err(_) => html.into_response(),
+    }
+}
 
-    // Save file
-    match std::fs::write(&file_path, &body) {
-        Ok(_) => {
-            // Trigger ledger reload directly (not through HTTP)
-            let mut ledger = state.ledger.write().await;
-            if let Err(e) = ledger.reload().await {
-                eprintln!("[ERROR] Failed to reload ledger after file save: {}", e);
-            }
+/// Ledger files no include reaches; empty when `data.detect_orphans` is off
+async fn orphaned_files(state: &AppState) -> Vec<beanweb_core::OrphanedFile> {
+    if !state.config.data.detect_orphans {
+        return Vec::new();
+    }
+    state.ledger.read().await.orphaned_files()
+}
 
-            format!(r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>保存成功！账本已重新加载</span></div>{}</div>"#, warning_html)
-        }
-        Err(e) => format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败: {}</span></div>{}</div>"#, e, warning_html),
+/// Add the `include` field (an orphan's path or glob) to the main file and
+/// reload; returns the orphans left
+async fn include_orphan(state: &AppState, form: &HashMap<String, String>) -> Result<Vec<beanweb_core::OrphanedFile>, ApiError> {
+    let include = form.get("include").map(|s| s.trim()).filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::BadRequest { message: "缺少 include".to_string() })?;
+    let mut ledger = state.ledger.write().await;
+    ledger.include_orphan(include).map_err(|e| ApiError::BadRequest { message: e.to_string() })?;
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after adding include: {}", e);
+    }
+    tracing::info!("Included {} from {}", include, state.config.data.main_file);
+    drop(ledger);
+    Ok(orphaned_files(state).await)
+}
+
+/// API: Ledger files under the data directory that the main file does not include
+pub async fn api_orphaned_files(state: axum::extract::State<AppState>) -> axum::Json<Vec<beanweb_core::OrphanedFile>> {
+    axum::Json(orphaned_files(&state).await)
+}
+
+/// API: Include an orphaned file, or a suggested glob, from the main file
+pub async fn api_include_orphan(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Result<axum::Json<Vec<beanweb_core::OrphanedFile>>, ApiError> {
+    include_orphan(&state, &form).await.map(axum::Json)
+}
+
+/// HTMX: Orphaned files panel on the files page
+pub async fn htmx_orphaned_files(state: axum::extract::State<AppState>) -> String {
+    beanweb_ui::files::orphaned_files(&state.config.data.main_file, &orphaned_files(&state).await, None)
+}
+
+/// HTMX: Include button of the orphaned files panel; re-renders the panel
+pub async fn htmx_include_orphan(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> String {
+    match include_orphan(&state, &form).await {
+        Ok(orphans) => beanweb_ui::files::orphaned_files(&state.config.data.main_file, &orphans, None),
+        Err(e) => beanweb_ui::files::orphaned_files(&state.config.data.main_file, &orphaned_files(&state).await, Some(&e.to_string())),
     }
 }
@@ -3,5 +3,5 @@
 pub mod api;
 pub mod page;
 
-pub use api::{api_files_list, api_file_content, api_file_save};
+pub use api::{api_files_list, api_file_content, api_file_save, api_document, api_orphaned_files, api_include_orphan, htmx_orphaned_files, htmx_include_orphan};
 pub use page::{page_files, page_file_edit};
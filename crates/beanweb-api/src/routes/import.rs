@@ -0,0 +1,112 @@
+//! Import routes
+//!
+//! Bank statements uploaded as CSV, checked and written by
+//! [`beanweb_core::transaction_import`]. One endpoint serves each step of the
+//! import: the same upload is posted again with the choices made on the
+//! preview until it is committed.
+//! - api_import_csv: preview the parsed rows, or append the accepted ones to
+//!   `data.new_transaction_file`
+
+use crate::error::ApiError;
+use crate::AppState;
+use beanweb_core::transaction_import::{CsvColumns, TransactionImportOptions, TransactionRowStatus};
+use std::collections::HashMap;
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    ApiError::BadRequest { message: message.into() }
+}
+
+/// Multipart form of [`api_import_csv`]: the CSV text and the other fields
+async fn read_import_form(request: axum::extract::Request) -> Result<(String, HashMap<String, String>), ApiError> {
+    let mut multipart = <axum::extract::Multipart as axum::extract::FromRequest<()>>::from_request(request, &())
+        .await
+        .map_err(|e| bad_request(e.to_string()))?;
+    let mut csv = None;
+    let mut fields = HashMap::new();
+    while let Some(field) = multipart.next_field().await.map_err(|e| bad_request(e.to_string()))? {
+        let name = field.name().unwrap_or_default().to_string();
+        let bytes = field.bytes().await.map_err(|e| bad_request(e.to_string()))?;
+        let text = String::from_utf8(bytes.to_vec()).map_err(|_| bad_request(format!("{} is not UTF-8", name)))?;
+        if name == "file" {
+            csv = Some(text);
+        } else {
+            fields.insert(name, text);
+        }
+    }
+    let csv = csv.ok_or_else(|| bad_request("No CSV file uploaded"))?;
+    Ok((csv, fields))
+}
+
+/// Import options from the form fields
+fn import_options(fields: &HashMap<String, String>) -> Result<TransactionImportOptions, ApiError> {
+    let columns = match fields.get("mapping").map(|m| m.trim()).filter(|m| !m.is_empty()) {
+        Some(mapping) => Some(serde_json::from_str::<CsvColumns>(mapping).map_err(|e| bad_request(format!("Invalid mapping: {}", e)))?),
+        None => None,
+    };
+    let counter_accounts = fields.iter()
+        .filter_map(|(name, account)| Some((name.strip_prefix("counter_")?.parse::<usize>().ok()?, account.clone())))
+        .collect();
+    let lines = match fields.get("accept").filter(|a| !a.trim().is_empty()) {
+        Some(accept) => Some(
+            accept.split(',')
+                .map(|line| line.trim().parse::<usize>().map_err(|_| bad_request(format!("Invalid line: {}", line))))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        None => None,
+    };
+    Ok(TransactionImportOptions {
+        account: fields.get("account").cloned().unwrap_or_default(),
+        counter_account: fields.get("counter_account").cloned(),
+        counter_accounts,
+        columns,
+        lines,
+        skip_rules: fields.get("skip_rules").is_some_and(|s| s == "true" || s == "1"),
+    })
+}
+
+/// POST /api/import/csv - bank statement import (multipart)
+/// Fields:
+/// - file: the CSV (UTF-8, comma or tab separated)
+/// - account: the account the statement is for
+/// - counter_account: balancing account of every row (default `Expenses:Uncategorized`)
+/// - mapping: JSON column mapping, e.g. `{"date": "Buchungstag", "amount": 3, "payee": "Empfänger", "date_format": "%d.%m.%Y"}`
+/// - counter_<line>: balancing account of the row on that CSV line
+/// - accept: comma-separated CSV lines to write (default: all new rows)
+/// - skip_rules: `true` to leave the categorization rules out
+/// - commit: `true` to write; otherwise only the preview is returned
+pub async fn api_import_csv(
+    state: axum::extract::State<AppState>,
+    request: axum::extract::Request,
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    let (csv, fields) = read_import_form(request).await?;
+    let options = import_options(&fields)?;
+    let commit = fields.get("commit").is_some_and(|c| c == "true" || c == "1");
+    let invalid = |e: beanweb_core::CoreError| bad_request(e.to_string());
+
+    if !commit {
+        let preview = state.ledger.read().await.preview_transaction_import(&csv, &options).map_err(invalid)?;
+        return Ok(axum::Json(serde_json::json!({
+            "committed": false,
+            "new": preview.count(TransactionRowStatus::Ok),
+            "duplicates": preview.count(TransactionRowStatus::Duplicate),
+            "invalid": preview.count(TransactionRowStatus::Invalid),
+            "rows": preview.rows,
+        })));
+    }
+
+    let mut ledger = state.ledger.write().await;
+    let file = state.config.data.new_transaction_file.clone();
+    let outcome = ledger.import_transactions(&csv, &file, &options).map_err(invalid)?;
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after importing transactions: {}", e);
+    }
+    tracing::info!("Imported {} transactions of {} into {}", outcome.written, options.account, outcome.file);
+    Ok(axum::Json(serde_json::json!({
+        "committed": true,
+        "file": outcome.file,
+        "written": outcome.written,
+        "skipped": outcome.skipped,
+        "include_added": outcome.include_added,
+        "rows": outcome.preview.rows,
+    })))
+}
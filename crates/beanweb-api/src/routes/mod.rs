@@ -3,11 +3,22 @@
 //! All routes are organized into modules for better maintainability:
 //! - transactions: Transaction list, search, pagination
 //! - accounts: Account list, tree view
+//! - reports: Balance sheet, income-expense, category and allocation reports
 //! - settings: Settings page
 //! - time: Time range control
 //! - files: File editor
-//!
-//! NOTE: Reports and commodities modules have been disabled (incomplete features)
+//! - export: Ledger export
+//! - import: Bank statement (CSV) import
+//! - tools: Ledger setup helpers (account bootstrap, opening balances, balance import)
+//! - stream: Streamed responses for large HTML fragments
+//! - events: Server-Sent Events for live ledger updates
+//! - commodities: Per-currency totals across accounts
+//! - budgets: Spent vs budget per account
+//! - feed: Atom feed of the latest transactions
+//! - tags: Tags with their counts and amounts
+//! - templates: Transaction templates for quick entry
+//! - rules: Categorization rules for imports and the create form
+//! - timeline: Events of the ledger by date, stored queries
 //!
 //! Each module follows a consistent structure:
 //! - mod.rs: Module declaration and exports
@@ -16,10 +27,19 @@
 
 pub mod transactions;
 pub mod accounts;
-// NOTE: 报表功能已禁用
-// pub mod reports;
+pub mod reports;
 pub mod settings;
 pub mod time;
 pub mod files;
-// NOTE: 货币功能已禁用
-// pub mod commodities;
+pub mod export;
+pub mod import;
+pub mod tools;
+pub mod stream;
+pub mod events;
+pub mod commodities;
+pub mod budgets;
+pub mod feed;
+pub mod tags;
+pub mod templates;
+pub mod rules;
+pub mod timeline;
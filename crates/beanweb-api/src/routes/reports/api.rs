@@ -1,37 +1,178 @@
 //! Reports API endpoints - JSON API and HTMX partial responses
 
-use crate::AppState;
+use super::{conversion, jobs};
+use crate::jobs::{ReportKind, ReportRequest};
+use crate::{ApiError, AppState};
 use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use beanweb_core::trends::TREND_MONTHS;
+use beanweb_ui::reports;
 
 // Re-export all API functions from the original module
 
-pub async fn api_balance_report(state: axum::extract::State<AppState>) -> String {
+/// Balance sheet (JSON API), `?conversion=original` keeps the booked currencies
+pub async fn api_balance_report(state: axum::extract::State<AppState>, query: Query<std::collections::HashMap<String, String>>) -> Result<String, ApiError> {
+    let mode = conversion::requested_mode(&query.0)?.unwrap_or_default();
     let ledger = state.ledger.read().await;
-    serde_json::to_string(&ledger.balance_report()).unwrap_or_default()
+    Ok(serde_json::to_string(&ledger.balance_report_with(mode)).unwrap_or_default())
 }
 
-pub async fn api_income_expense(state: axum::extract::State<AppState>) -> String {
+/// Whether `?group_by=groups` asks for the configured report groups
+fn wants_groups(query: &std::collections::HashMap<String, String>) -> bool {
+    query.get("group_by").map(|s| s == "groups").unwrap_or(false)
+}
+
+/// Income vs expenses (JSON API), `?group_by=groups` rolls up by report groups
+/// and `?conversion=original` keeps the booked currencies
+pub async fn api_income_expense(state: axum::extract::State<AppState>, query: Query<std::collections::HashMap<String, String>>) -> Result<String, ApiError> {
+    let mode = conversion::requested_mode(&query.0)?.unwrap_or_default();
+    let ledger = state.ledger.read().await;
+    let report = if wants_groups(&query.0) {
+        ledger.grouped_income_expense_report_with(mode)
+    } else {
+        ledger.income_expense_report_with(mode)
+    };
+    Ok(serde_json::to_string(&report).unwrap_or_default())
+}
+
+/// `?year=` of the monthly summary; defaults to the year the current time
+/// range ends in, or this year for open-ended ranges
+fn summary_year(ledger: &beanweb_core::Ledger, query: &std::collections::HashMap<String, String>) -> Result<i32, ApiError> {
+    match query.get("year").filter(|y| !y.is_empty()) {
+        Some(year) => year.parse().ok().filter(|y| (1..=9999).contains(y))
+            .ok_or_else(|| ApiError::BadRequest { message: format!("Invalid year `{}`", year) }),
+        None => {
+            let end = ledger.time_context().end_date().unwrap_or_else(|| chrono::Utc::now().date_naive());
+            Ok(chrono::Datelike::year(&end))
+        }
+    }
+}
+
+/// Income and expenses per month of `?year=` (JSON API)
+pub async fn api_monthly_summary(
+    state: axum::extract::State<AppState>,
+    query: Query<std::collections::HashMap<String, String>>,
+) -> Result<axum::Json<beanweb_core::MonthlySummaryReport>, ApiError> {
+    let ledger = state.ledger.read().await;
+    let year = summary_year(&ledger, &query.0)?;
+    Ok(axum::Json(ledger.monthly_summary_report(year)))
+}
+
+/// Net income allocation report (JSON API)
+pub async fn api_allocation_report(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    serde_json::to_string(&ledger.allocation_report()).unwrap_or_default()
+}
+
+/// Holdings grouped by asset class (JSON API)
+pub async fn api_holdings_report(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    serde_json::to_string(&ledger.holdings_by_asset_class()).unwrap_or_default()
+}
+
+/// Spending per payee in the current time range, biggest first (JSON API)
+pub async fn api_payees(state: axum::extract::State<AppState>) -> axum::Json<Vec<beanweb_core::PayeeSummary>> {
     let ledger = state.ledger.read().await;
-    serde_json::to_string(&ledger.income_expense_report()).unwrap_or_default()
+    axum::Json(ledger.payee_summary(&ledger.time_context()))
 }
 
-pub async fn htmx_reports_overview(state: axum::extract::State<AppState>) -> String {
+/// HTMX: Overview; the conversion mode is remembered per report (see [`conversion`]),
+/// long ranges are built in a job (see [`jobs`])
+pub async fn htmx_reports_overview(state: axum::extract::State<AppState>, headers: HeaderMap, query: Query<std::collections::HashMap<String, String>>) -> Result<Response, ApiError> {
+    let mode = conversion::report_mode(conversion::OVERVIEW, &headers, &query.0)?;
+    if let Some(html) = jobs::start_if_long(&state, ReportRequest { kind: ReportKind::Overview, mode, grouped: false }).await {
+        return conversion::remember_mode(conversion::OVERVIEW, &headers, &query.0, html);
+    }
     let ledger = state.ledger.read().await;
-    super::page::render_reports_overview(&ledger)
+    conversion::remember_mode(conversion::OVERVIEW, &headers, &query.0, reports::overview(
+        &ledger.balance_report_with(mode),
+        &ledger.income_expense_report_with(mode),
+        &ledger.category_trends(TREND_MONTHS, false),
+        mode,
+    ))
 }
 
-pub async fn htmx_reports_balance(state: axum::extract::State<AppState>) -> String {
+pub async fn htmx_reports_balance(state: axum::extract::State<AppState>, headers: HeaderMap, query: Query<std::collections::HashMap<String, String>>) -> Result<Response, ApiError> {
+    let mode = conversion::report_mode(conversion::BALANCE, &headers, &query.0)?;
+    if let Some(html) = jobs::start_if_long(&state, ReportRequest { kind: ReportKind::Balance, mode, grouped: false }).await {
+        return conversion::remember_mode(conversion::BALANCE, &headers, &query.0, html);
+    }
     let ledger = state.ledger.read().await;
-    super::page::render_balance_report(&ledger)
+    conversion::remember_mode(conversion::BALANCE, &headers, &query.0, reports::balance_sheet(&ledger.balance_report_with(mode), mode))
 }
 
-pub async fn htmx_reports_income_expense(state: axum::extract::State<AppState>) -> String {
+pub async fn htmx_reports_income_expense(state: axum::extract::State<AppState>, headers: HeaderMap, query: Query<std::collections::HashMap<String, String>>) -> Result<Response, ApiError> {
+    let mode = conversion::report_mode(conversion::INCOME_EXPENSE, &headers, &query.0)?;
+    let grouped = wants_groups(&query.0);
+    if let Some(html) = jobs::start_if_long(&state, ReportRequest { kind: ReportKind::IncomeExpense, mode, grouped }).await {
+        return conversion::remember_mode(conversion::INCOME_EXPENSE, &headers, &query.0, html);
+    }
     let ledger = state.ledger.read().await;
-    super::page::render_income_expense_report(&ledger)
+    let report = if grouped { ledger.grouped_income_expense_report_with(mode) } else { ledger.income_expense_report_with(mode) };
+    let html = reports::income_expense(&report, &ledger.category_trends(TREND_MONTHS, grouped), grouped, mode);
+    conversion::remember_mode(conversion::INCOME_EXPENSE, &headers, &query.0, html)
 }
 
+/// Months in the category detail sparkline
+const CATEGORY_TREND_MONTHS: usize = 12;
+
+/// HTMX: Transactions of `?category=` in the current time range
 pub async fn htmx_reports_category(state: axum::extract::State<AppState>, query: Query<std::collections::HashMap<String, String>>) -> String {
     let ledger = state.ledger.read().await;
     let category = query.0.get("category").map(|s| s.as_str()).unwrap_or("");
-    super::page::render_category_details(&ledger, category)
+    let (transactions, total) = category_transactions(&ledger, category);
+    reports::category(category, &transactions, total, &ledger.category_trends(CATEGORY_TREND_MONTHS, false))
+}
+
+/// Transactions booking to `category` or its sub-accounts, newest first, and
+/// their total, in the display sign
+fn category_transactions(ledger: &beanweb_core::Ledger, category: &str) -> (Vec<reports::CategoryTransaction>, f64) {
+    if category.is_empty() {
+        return (Vec::new(), 0.0);
+    }
+    let prefix = format!("{}:", category);
+    let in_category = |p: &beanweb_core::Posting| p.account == category || p.account.starts_with(&prefix);
+    let mut transactions: Vec<reports::CategoryTransaction> = ledger.filtered_transactions(usize::MAX, 0).into_iter()
+        .filter(|tx| tx.postings.iter().any(in_category))
+        .map(|tx| {
+            let amount: f64 = tx.postings.iter().filter(|p| in_category(p)).filter_map(|p| p.amount_value()).sum();
+            reports::CategoryTransaction {
+                title: if tx.payee.is_empty() { tx.narration } else { tx.payee },
+                date: tx.date,
+                amount: ledger.display_amount(category, amount),
+            }
+        })
+        .collect();
+    transactions.sort_by(|a, b| b.date.cmp(&a.date));
+    let total = transactions.iter().map(|tx| tx.amount).sum();
+    (transactions, total)
+}
+
+/// HTMX: Net income allocation waterfall chart
+pub async fn htmx_reports_allocation(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    reports::allocation(&ledger.allocation_report())
+}
+
+/// HTMX: Holdings grouped by asset class
+pub async fn htmx_reports_holdings(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    reports::holdings(&ledger.holdings_by_asset_class())
+}
+
+/// HTMX: Top payees with their monthly trends
+pub async fn htmx_reports_payees(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    reports::payees(&ledger.payee_summary(&ledger.time_context()), &ledger.payee_trends(TREND_MONTHS))
+}
+
+/// HTMX: Monthly income/expense bar chart of `?year=`
+pub async fn htmx_reports_monthly(
+    state: axum::extract::State<AppState>,
+    query: Query<std::collections::HashMap<String, String>>,
+) -> Result<String, ApiError> {
+    let ledger = state.ledger.read().await;
+    let year = summary_year(&ledger, &query.0)?;
+    Ok(reports::monthly_summary(&ledger.monthly_summary_report(year)))
 }
@@ -0,0 +1,65 @@
+//! Per-report currency conversion toggle
+//!
+//! Reports convert every amount into the operating currency by default. The
+//! balance sheet, income-expense report and overview each have a toggle to
+//! show the original currencies instead:
+//! - `?conversion=original|converted` picks the mode for one request; on the
+//!   HTMX fragments it is also remembered for that report
+//! - The reports shown in original currencies are kept in the
+//!   `beanweb_conversion` cookie, e.g. `balance.overview`
+//! - JSON APIs only follow the query parameter
+
+use crate::ApiError;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use beanweb_core::ConversionMode;
+use std::collections::HashMap;
+
+/// Cookie listing the reports shown in original currencies, `.`-separated
+pub const CONVERSION_COOKIE: &str = "beanweb_conversion";
+
+/// Reports with a toggle, as named in the cookie
+pub const OVERVIEW: &str = "overview";
+pub const BALANCE: &str = "balance";
+pub const INCOME_EXPENSE: &str = "income-expense";
+
+/// `?conversion=` of the request, if any
+pub fn requested_mode(query: &HashMap<String, String>) -> Result<Option<ConversionMode>, ApiError> {
+    query.get("conversion")
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(|message| ApiError::BadRequest { message })
+}
+
+/// Reports listed in the cookie
+fn original_reports(headers: &HeaderMap) -> Vec<String> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == CONVERSION_COOKIE)
+        .map(|(_, value)| value.split('.').filter(|r| !r.is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Mode of `report`: the query parameter, else the remembered one
+pub fn report_mode(report: &str, headers: &HeaderMap, query: &HashMap<String, String>) -> Result<ConversionMode, ApiError> {
+    Ok(requested_mode(query)?.unwrap_or_else(|| {
+        if original_reports(headers).iter().any(|r| r == report) { ConversionMode::Original } else { ConversionMode::Converted }
+    }))
+}
+
+/// `html` as a response, remembering the mode when the request chose one
+pub fn remember_mode(report: &str, headers: &HeaderMap, query: &HashMap<String, String>, html: String) -> Result<Response, ApiError> {
+    let Some(mode) = requested_mode(query)? else {
+        return Ok(html.into_response());
+    };
+    let mut reports: Vec<String> = original_reports(headers).into_iter().filter(|r| r != report).collect();
+    if mode == ConversionMode::Original {
+        reports.push(report.to_string());
+    }
+    let cookie = format!("{}={}; Path=/; Max-Age=31536000; SameSite=Lax", CONVERSION_COOKIE, reports.join("."));
+    Ok(([(header::SET_COOKIE, cookie)], html).into_response())
+}
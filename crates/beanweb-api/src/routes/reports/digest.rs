@@ -0,0 +1,114 @@
+//! Period digest - standalone HTML summary for email or archiving
+//!
+//! `GET /api/reports/digest.html?period=2024-06` (or `2024-W23` for a week)
+//! renders the same income/expense structs as the report pages into a single
+//! document; the markup is in `beanweb_ui::digest`.
+
+use crate::AppState;
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use beanweb_core::{AccountType, Ledger, TimeContext};
+use beanweb_ui::digest::{self, Digest, LargestExpense};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+/// A digest period: its bounds and how it is titled
+struct Period {
+    start: NaiveDate,
+    end: NaiveDate,
+    title: String,
+}
+
+/// Parse `YYYY-MM` or ISO week `YYYY-Www`; empty means the current month
+fn parse_period(value: Option<&str>) -> Result<Period, String> {
+    let value = value.map(str::trim).filter(|v| !v.is_empty());
+    let value = value.map(str::to_string).unwrap_or_else(|| chrono::Local::now().format("%Y-%m").to_string());
+    if let Some((year, week)) = value.split_once("-W") {
+        let (year, week) = (year.parse::<i32>(), week.parse::<u32>());
+        if let (Ok(year), Ok(week)) = (year, week) {
+            if let Some(start) = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon) {
+                return Ok(Period { start, end: start + Duration::days(6), title: format!("{} 年第 {} 周", year, week) });
+            }
+        }
+        return Err(format!("无效的周: {}", value));
+    }
+    let start = NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+        .map_err(|_| format!("无效的期间: {}（格式 2024-06 或 2024-W23）", value))?;
+    let next = if start.month() == 12 {
+        NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+    };
+    let end = next.and_then(|d| d.pred_opt()).unwrap_or(start);
+    Ok(Period { start, end, title: format!("{} 年 {} 月", start.year(), start.month()) })
+}
+
+/// The period right before `period`, of the same length kind
+fn previous_period(period: &Period) -> Period {
+    let end = period.start.pred_opt().unwrap_or(period.start);
+    let start = if period.end - period.start == Duration::days(6) {
+        period.start - Duration::days(7)
+    } else {
+        end.with_day(1).unwrap_or(end)
+    };
+    Period { start, end, title: String::new() }
+}
+
+/// GET: Standalone HTML digest of one period
+pub async fn api_report_digest(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> Response {
+    let period = match parse_period(query.get("period").map(|s| s.as_str())) {
+        Ok(period) => period,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let ledger = state.ledger.read().await;
+    let html = render_digest(&ledger, &period, state.config.charts.top_items_count);
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response()
+}
+
+fn render_digest(ledger: &Ledger, period: &Period, top_n: usize) -> String {
+    let context = TimeContext::custom(period.start, period.end);
+    let previous = previous_period(period);
+    let report = ledger.income_expense_report_in(&context);
+    let previous_report = ledger.income_expense_report_in(&TimeContext::custom(previous.start, previous.end));
+    let categories = ledger.expense_category_report_in(&context);
+    let largest = largest_expenses(ledger, &context);
+    digest::document(&Digest {
+        title: &period.title,
+        start: period.start,
+        end: period.end,
+        report: &report,
+        previous: &previous_report,
+        categories: &categories,
+        largest: &largest,
+        top_n,
+        generated: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+    })
+}
+
+/// Transactions with the largest expense postings in the period
+fn largest_expenses(ledger: &Ledger, context: &TimeContext) -> Vec<LargestExpense> {
+    let (Some(start), Some(end)) = (context.start_date(), context.end_date()) else {
+        return Vec::new();
+    };
+    let options = ledger.options();
+    let mut expenses: Vec<LargestExpense> = ledger.transactions_by_date_range(start, end)
+        .iter()
+        .filter_map(|tx| {
+            let postings = tx.postings.iter().filter(|p| options.is(&p.account, AccountType::Expenses));
+            let total: f64 = postings.clone().filter_map(|p| p.amount_value()).sum();
+            let currency = postings.map(|p| p.currency.clone()).find(|c| !c.is_empty()).unwrap_or_default();
+            let title = [tx.payee.as_str(), tx.narration.as_str()].iter()
+                .filter(|s| !s.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" · ");
+            (total > 0.0).then(|| LargestExpense { date: tx.date.clone(), title, total, currency })
+        })
+        .collect();
+    expenses.sort_by(|a, b| b.total.partial_cmp(&a.total).unwrap_or(std::cmp::Ordering::Equal));
+    expenses
+}
@@ -0,0 +1,85 @@
+//! Report jobs - reports over long ranges built in the background (see [`crate::jobs`])
+
+use super::conversion;
+use crate::jobs::{self, JobStatus, ReportJob, ReportKind, ReportRequest};
+use crate::{ApiError, AppState};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use beanweb_ui::reports;
+use std::collections::HashMap;
+
+fn not_found(id: &str) -> ApiError {
+    ApiError::NotFound { resource: format!("Report job {}", id) }
+}
+
+/// POST /api/reports/jobs - start building `?report=` (overview, balance or
+/// income-expense) over the current time range, with `?conversion=` and
+/// `?group_by=groups` as on the report endpoints; returns the job, or the one
+/// already running for the same report
+pub async fn api_start_report_job(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> Result<(StatusCode, axum::Json<ReportJob>), ApiError> {
+    let report = query.get("report").map(String::as_str).unwrap_or_default();
+    let kind = ReportKind::parse(report)
+        .ok_or_else(|| ApiError::BadRequest { message: format!("Unknown report `{}`", report) })?;
+    let request = ReportRequest {
+        kind,
+        mode: conversion::requested_mode(&query.0)?.unwrap_or_default(),
+        grouped: query.get("group_by").is_some_and(|g| g == "groups"),
+    };
+    let id = jobs::start(&state, request).await;
+    let job = state.jobs.lock().unwrap().get(&id).ok_or_else(|| not_found(&id))?;
+    Ok((StatusCode::ACCEPTED, axum::Json(job)))
+}
+
+/// GET /api/reports/jobs/:id - progress and the years built so far; the
+/// report in `result` once done
+pub async fn api_report_job(state: axum::extract::State<AppState>, Path(id): Path<String>) -> Result<axum::Json<ReportJob>, ApiError> {
+    let job = state.jobs.lock().unwrap().get(&id).ok_or_else(|| not_found(&id))?;
+    Ok(axum::Json(job))
+}
+
+/// HTMX: the report once its job is done, else the progress polling again
+pub async fn htmx_report_job(state: axum::extract::State<AppState>, Path(id): Path<String>) -> Result<String, ApiError> {
+    let job = state.jobs.lock().unwrap().get(&id).ok_or_else(|| not_found(&id))?;
+    Ok(match job.status {
+        JobStatus::Done => job.html.unwrap_or_default(),
+        JobStatus::Failed => reports::job_failed(job.error.as_deref().unwrap_or_default()),
+        JobStatus::Running => progress(&job),
+    })
+}
+
+/// For the report pages: when the current range has at least
+/// `reports.async_min_transactions` transactions, start a job (or join the
+/// one already built since the last reload) and return its progress, or the
+/// cached report, instead of building it in the request
+pub(super) async fn start_if_long(state: &AppState, request: ReportRequest) -> Option<String> {
+    let count = {
+        let ledger = state.ledger.read().await;
+        ledger.transactions_count_in(&ledger.time_context())
+    };
+    if count < state.config.reports.async_min_transactions {
+        return None;
+    }
+    let id = jobs::start(state, request).await;
+    let job = state.jobs.lock().unwrap().get(&id)?;
+    match (job.status, job.html.as_ref()) {
+        (JobStatus::Done, Some(html)) => Some(html.clone()),
+        _ => Some(progress(&job)),
+    }
+}
+
+/// Spinner with the years built so far, polling the job until it is done
+fn progress(job: &ReportJob) -> String {
+    let years: Vec<_> = job.partial.iter()
+        .map(|year| reports::JobYear {
+            year: year.year,
+            income: &year.total_income,
+            expenses: &year.total_expenses,
+            net: &year.net_income,
+            currency: &year.currency,
+        })
+        .collect();
+    reports::job_progress(&job.id, job.done, job.total, &years)
+}
@@ -1,19 +1,35 @@
-//! Report routes - Balance and income-expense reports
+//! Report routes - Balance, income-expense, monthly summary and payee reports
 //!
 //! Structure:
 //! - api.rs: JSON API and HTMX endpoints
+//! - conversion.rs: Original currencies / converted toggle
+//! - jobs.rs: Reports over long ranges built in the background
 //! - page.rs: Full page rendering
+//! - digest.rs: Standalone HTML digest of one period
 
 pub mod api;
+pub mod conversion;
+pub mod digest;
+pub mod jobs;
 pub mod page;
 
 pub use api::{
     api_balance_report,
     api_income_expense,
+    api_monthly_summary,
+    api_allocation_report,
+    api_holdings_report,
+    api_payees,
     htmx_reports_overview,
     htmx_reports_balance,
     htmx_reports_income_expense,
     htmx_reports_category,
+    htmx_reports_allocation,
+    htmx_reports_holdings,
+    htmx_reports_monthly,
+    htmx_reports_payees,
 };
 
+pub use digest::api_report_digest;
+pub use jobs::{api_start_report_job, api_report_job, htmx_report_job};
 pub use page::page_reports;
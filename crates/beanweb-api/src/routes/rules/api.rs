@@ -0,0 +1,52 @@
+//! Categorization rules API endpoints
+
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::Query;
+use beanweb_core::{CategoryRule, CoreError, RuleSubject};
+use std::collections::HashMap;
+
+pub(super) fn api_error(e: CoreError) -> ApiError {
+    match e {
+        CoreError::ValidationError { message } | CoreError::InvalidFormat { message } => ApiError::BadRequest { message },
+        e => {
+            tracing::error!("Categorization rules: {}", e);
+            ApiError::InternalError
+        }
+    }
+}
+
+/// Payment described by `?payee=&narration=&amount=`, metadata as
+/// `?meta_<key>=<value>` or as `key: value` lines in `?metadata=`
+pub(super) fn subject(query: &HashMap<String, String>) -> Result<RuleSubject, ApiError> {
+    let text = |name: &str| query.get(name).map(|s| s.trim().to_string()).unwrap_or_default();
+    let amount = match query.get("amount").map(|a| a.trim()).filter(|a| !a.is_empty()) {
+        Some(amount) => Some(amount.parse().map_err(|_| ApiError::BadRequest { message: format!("Invalid amount `{}`", amount) })?),
+        None => None,
+    };
+    let mut metadata: HashMap<String, String> = query.iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix("meta_")?.to_string(), value.trim().to_string())))
+        .collect();
+    for line in query.get("metadata").map(|m| m.lines()).into_iter().flatten() {
+        if let Some((key, value)) = line.split_once(':') {
+            metadata.insert(key.trim().to_string(), value.trim().trim_matches('"').to_string());
+        }
+    }
+    Ok(RuleSubject { payee: text("payee"), narration: text("narration"), amount, metadata })
+}
+
+/// GET /api/rules - the rules in file order
+pub async fn api_rules(state: axum::extract::State<AppState>) -> Result<axum::Json<Vec<CategoryRule>>, ApiError> {
+    state.ledger.read().await.categorization_rules().map(axum::Json).map_err(api_error)
+}
+
+/// GET /api/rules/match?payee=&narration=&amount= - the first rule matching
+/// the payment (see [`subject`]); `{"rule": null}` when none does
+pub async fn api_rule_match(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    let subject = subject(&query.0)?;
+    let rules = state.ledger.read().await.rule_set().map_err(api_error)?;
+    Ok(axum::Json(serde_json::json!({ "rule": rules.matching(&subject) })))
+}
@@ -0,0 +1,17 @@
+//! Categorization rule routes
+//!
+//! Features:
+//! - The rules kept in `rules.yaml` (see [`beanweb_core::rules`]), as JSON
+//!   and on a settings page
+//! - Matching a payment against them, for the create form and for trying
+//!   rules out on sample text
+//!
+//! Structure:
+//! - api.rs: JSON API endpoints
+//! - page.rs: Settings page and the rule tester
+
+pub mod api;
+pub mod page;
+
+pub use api::{api_rule_match, api_rules};
+pub use page::{htmx_rule_test, page_rules};
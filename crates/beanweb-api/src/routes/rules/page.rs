@@ -0,0 +1,27 @@
+//! Categorization rules page and the rule tester
+
+use super::api::{api_error, subject};
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::Query;
+use std::collections::HashMap;
+
+/// GET /settings/rules - the rules with a form to try them on sample text
+pub async fn page_rules(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let rules = state.ledger.read().await.categorization_rules().map_err(|e| e.to_string());
+    let inner_content = beanweb_ui::rules::page(rules.as_deref().map_err(String::as_str));
+    axum::response::Html(crate::page_response(&headers, &state.config, "分类规则", "/settings", &inner_content))
+}
+
+/// HTMX: which rule the sample payment matches, and what it would set
+pub async fn htmx_rule_test(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> Result<String, ApiError> {
+    let subject = subject(&query.0)?;
+    let rules = state.ledger.read().await.rule_set().map_err(api_error)?;
+    Ok(beanweb_ui::rules::test_result(rules.matching(&subject)))
+}
@@ -0,0 +1,44 @@
+//! Streamed HTML responses for large fragments
+//!
+//! Fragments such as the transactions list can reach several MB. Instead of
+//! building the whole string first, handlers pass an iterator of chunks that
+//! is rendered lazily while hyper writes the body.
+
+use axum::body::Body;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use std::convert::Infallible;
+
+/// Stream HTML chunks as the response body
+/// Marked `no-cache` so the browser revalidates instead of reusing a stale list
+pub fn html_stream<I>(chunks: I) -> Response
+where
+    I: Iterator<Item = String> + Send + 'static,
+{
+    let stream = futures_util::stream::iter(chunks.map(Ok::<_, Infallible>));
+    (
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+/// Stream a file download, e.g. a CSV export, as `filename`
+pub fn download_stream<I>(content_type: &'static str, filename: &str, chunks: I) -> Response
+where
+    I: Iterator<Item = String> + Send + 'static,
+{
+    let stream = futures_util::stream::iter(chunks.map(Ok::<_, Infallible>));
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
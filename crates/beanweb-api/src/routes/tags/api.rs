@@ -0,0 +1,10 @@
+//! Tags API endpoints
+//!
+//! JSON API for tag summaries
+
+use crate::AppState;
+
+/// GET /api/tags - every tag, most used first
+pub async fn api_tags(state: axum::extract::State<AppState>) -> axum::Json<Vec<beanweb_core::TagSummary>> {
+    axum::Json(state.ledger.read().await.all_tags())
+}
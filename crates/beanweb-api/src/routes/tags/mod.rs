@@ -0,0 +1,15 @@
+//! Tag routes
+//!
+//! Features:
+//! - Every tag with its transaction count and summed expenses and income
+//! - Each tag links to the transactions list filtered by it (`?tag=`)
+//!
+//! Structure:
+//! - api.rs: JSON API endpoints
+//! - page.rs: HTMX page rendering
+
+pub mod api;
+pub mod page;
+
+pub use api::api_tags;
+pub use page::{htmx_tags_list, page_tags};
@@ -0,0 +1,19 @@
+//! Tags page rendering
+//!
+//! HTMX page endpoints for browsing tags
+
+use crate::AppState;
+use beanweb_ui::tags;
+
+/// Tags page - the list is reloaded with the ledger
+pub async fn page_tags(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    axum::response::Html(crate::page_response(&headers, &state.config, "标签", "/tags", &tags::page()))
+}
+
+/// HTMX: Tag table
+pub async fn htmx_tags_list(state: axum::extract::State<AppState>) -> String {
+    tags::list(&state.ledger.read().await.all_tags())
+}
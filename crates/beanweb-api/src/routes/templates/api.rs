@@ -0,0 +1,74 @@
+//! Transaction templates API endpoints
+//!
+//! JSON CRUD at `/api/templates`; a template is addressed by its name
+
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use beanweb_core::{CoreError, TransactionTemplate};
+
+fn api_error(e: CoreError) -> ApiError {
+    match e {
+        CoreError::ValidationError { message } => ApiError::BadRequest { message },
+        CoreError::DuplicateEntry { entry } => ApiError::Conflict { message: format!("{} already exists", entry) },
+        e => {
+            tracing::error!("Transaction templates: {}", e);
+            ApiError::InternalError
+        }
+    }
+}
+
+fn not_found(name: &str) -> ApiError {
+    ApiError::NotFound { resource: format!("template {}", name) }
+}
+
+/// GET /api/templates - all templates, sorted by name
+pub async fn api_templates(state: axum::extract::State<AppState>) -> Result<axum::Json<Vec<TransactionTemplate>>, ApiError> {
+    state.ledger.read().await.transaction_templates().map(axum::Json).map_err(api_error)
+}
+
+/// GET /api/templates/:name
+pub async fn api_template(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> Result<axum::Json<TransactionTemplate>, ApiError> {
+    state.ledger.read().await.transaction_template(&path.0)
+        .map_err(api_error)?
+        .map(axum::Json)
+        .ok_or_else(|| not_found(&path.0))
+}
+
+/// POST /api/templates - add a template, replacing one with the same name
+pub async fn api_template_create(
+    state: axum::extract::State<AppState>,
+    axum::Json(template): axum::Json<TransactionTemplate>,
+) -> Result<(StatusCode, axum::Json<TransactionTemplate>), ApiError> {
+    let saved = state.ledger.write().await.save_transaction_template(template).map_err(api_error)?;
+    Ok((StatusCode::CREATED, axum::Json(saved)))
+}
+
+/// PUT /api/templates/:name - replace a template; a different name in the
+/// body renames it, 409 when that name is taken
+pub async fn api_template_update(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+    axum::Json(template): axum::Json<TransactionTemplate>,
+) -> Result<axum::Json<TransactionTemplate>, ApiError> {
+    // The write lock keeps concurrent edits of the file apart
+    state.ledger.write().await.replace_transaction_template(&path.0, template)
+        .map_err(api_error)?
+        .map(axum::Json)
+        .ok_or_else(|| not_found(&path.0))
+}
+
+/// DELETE /api/templates/:name
+pub async fn api_template_delete(
+    state: axum::extract::State<AppState>,
+    path: Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.ledger.write().await.delete_transaction_template(&path.0).map_err(api_error)? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(not_found(&path.0)),
+    }
+}
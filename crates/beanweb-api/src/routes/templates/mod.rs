@@ -0,0 +1,13 @@
+//! Transaction template routes
+//!
+//! Features:
+//! - CRUD for the templates kept in `templates.yaml` (see
+//!   [`beanweb_core::templates`]); the picker of the create-transaction
+//!   form is rendered by [`beanweb_ui::templates`]
+//!
+//! Structure:
+//! - api.rs: JSON API endpoints
+
+pub mod api;
+
+pub use api::{api_template, api_template_create, api_template_delete, api_template_update, api_templates};
@@ -6,6 +6,9 @@ use crate::AppState;
 use chrono::Datelike;
 use std::collections::HashMap;
 
+/// Client-side event fired (via HX-Trigger) after the time range changes
+pub const TIME_RANGE_CHANGED_EVENT: &str = "time-range-changed";
+
 /// Get current time range (JSON API)
 pub async fn api_time_range(state: axum::extract::State<AppState>) -> String {
     let ledger = state.ledger.read().await;
@@ -14,7 +17,8 @@ pub async fn api_time_range(state: axum::extract::State<AppState>) -> String {
     serde_json::to_string(&serde_json::json!({
         "range": context.range.to_string(),
         "start_date": context.start_date().map(|d| d.to_string()),
-        "end_date": context.end_date().map(|d| d.to_string())
+        "end_date": context.end_date().map(|d| d.to_string()),
+        "include_future": context.include_future
     })).unwrap_or_default()
 }
 
@@ -61,11 +65,13 @@ pub async fn api_time_range_years(state: axum::extract::State<AppState>) -> Stri
 }
 
 /// Set time range (POST) - supports query params and form body
+/// Responds with `HX-Trigger: time-range-changed` so period-dependent
+/// fragments can refetch themselves instead of reloading the whole page
 pub async fn api_set_time_range(
     state: axum::extract::State<AppState>,
     query: axum::extract::Query<HashMap<String, String>>,
     body: String,
-) -> String {
+) -> impl axum::response::IntoResponse {
     let query_params = &query.0;
 
     // First try to get from query params
@@ -89,12 +95,17 @@ pub async fn api_set_time_range(
         ""
     };
 
-    eprintln!("[DEBUG] api_set_time_range called with range: '{}'", range_str);
+    tracing::debug!("api_set_time_range called with range: '{}'", range_str);
 
     // Set time range on ledger (need write lock for set_custom_range/set_time_range)
     {
         let ledger = state.ledger.write().await;
 
+        // Today horizon toggle: include_future=true|false (may be sent without a range)
+        if let Some(include_future) = query_params.get("include_future") {
+            ledger.set_include_future(include_future == "true");
+        }
+
         if range_str.starts_with("month:") {
             // Format: month:01 (current year)
             if let Some(month_str) = range_str.strip_prefix("month:") {
@@ -108,7 +119,7 @@ pub async fn api_set_time_range(
                         .pred_opt()
                         .unwrap_or(start);
                     ledger.set_custom_range(start, end);
-                    eprintln!("[DEBUG] Set month range: {} to {}", start, end);
+                    tracing::debug!("Set month range: {} to {}", start, end);
                 }
             }
         } else if range_str.starts_with("year:") {
@@ -128,14 +139,14 @@ pub async fn api_set_time_range(
                                 .pred_opt()
                                 .unwrap_or(start);
                             ledger.set_custom_range(start, end);
-                            eprintln!("[DEBUG] Set month range: {} to {}", start, end);
+                            tracing::debug!("Set month range: {} to {}", start, end);
                         }
                     } else {
                         // year only - full year from Jan 1 to Dec 31
                         let start = chrono::NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
                         let end = chrono::NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
                         ledger.set_custom_range(start, end);
-                        eprintln!("[DEBUG] Set year range: {} to {}", start, end);
+                        tracing::debug!("Set year range: {} to {}", start, end);
                     }
                 }
             }
@@ -149,7 +160,7 @@ pub async fn api_set_time_range(
                         chrono::NaiveDate::parse_from_str(parts[1], "%Y-%m-%d")
                     ) {
                         ledger.set_custom_range(start, end);
-                        eprintln!("[DEBUG] Set custom range: {} to {}", start, end);
+                        tracing::debug!("Set custom range: {} to {}", start, end);
                     }
                 }
             }
@@ -158,28 +169,31 @@ pub async fn api_set_time_range(
             match range_str {
                 "month" => {
                     ledger.set_time_range(beanweb_config::TimeRange::Month);
-                    eprintln!("[DEBUG] Set time range: month");
+                    tracing::debug!("Set time range: month");
                 }
                 "quarter" => {
                     ledger.set_time_range(beanweb_config::TimeRange::Quarter);
-                    eprintln!("[DEBUG] Set time range: quarter");
+                    tracing::debug!("Set time range: quarter");
                 }
                 "year" => {
                     ledger.set_time_range(beanweb_config::TimeRange::Year);
-                    eprintln!("[DEBUG] Set time range: year");
+                    tracing::debug!("Set time range: year");
                 }
                 "all" => {
                     ledger.set_time_range(beanweb_config::TimeRange::All);
-                    eprintln!("[DEBUG] Set time range: all");
+                    tracing::debug!("Set time range: all");
                 }
                 _ => {
-                    eprintln!("[DEBUG] Unknown time range: '{}'", range_str);
+                    tracing::debug!("Unknown time range: '{}'", range_str);
                 }
             }
         }
     }
 
-    format!(r#"{{"success": true, "message": "时间范围已更新"}}"#)
+    (
+        [("HX-Trigger", TIME_RANGE_CHANGED_EVENT)],
+        r#"{"success": true, "message": "时间范围已更新"}"#.to_string(),
+    )
 }
 
 /// Available time range options (for UI)
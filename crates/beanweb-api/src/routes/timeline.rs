@@ -0,0 +1,43 @@
+//! Timeline of `event` directives
+//!
+//! `/timeline` lists events newest first, grouped under their year, so
+//! changes of `location`, `employer` and the like read as a history;
+//! `?type=` keeps one event type. `/api/ledger-events` returns the same list
+//! as JSON (`/api/events` is the live update stream), and `/api/queries` the
+//! stored `query` directives.
+
+use crate::AppState;
+use axum::extract::Query;
+use beanweb_core::{LedgerEvent, StoredQuery};
+use std::collections::HashMap;
+
+fn event_type(query: &HashMap<String, String>) -> Option<&str> {
+    query.get("type").map(|t| t.trim()).filter(|t| !t.is_empty())
+}
+
+/// GET /api/ledger-events?type= - events, newest first
+pub async fn api_ledger_events(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> axum::Json<Vec<LedgerEvent>> {
+    axum::Json(state.ledger.read().await.events(event_type(&query.0)))
+}
+
+/// GET /api/queries - stored queries in file order
+pub async fn api_queries(state: axum::extract::State<AppState>) -> axum::Json<Vec<StoredQuery>> {
+    axum::Json(state.ledger.read().await.queries())
+}
+
+/// GET /timeline?type= - events grouped by year, with a filter by type
+pub async fn page_timeline(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+    query: Query<HashMap<String, String>>,
+) -> axum::response::Html<String> {
+    let ledger = state.ledger.read().await;
+    let selected = event_type(&query.0);
+    let events = ledger.events(selected);
+
+    let inner_content = beanweb_ui::timeline::page(&events, &ledger.event_types(), selected);
+    axum::response::Html(crate::page_response(&headers, &state.config, "时间线", "/timeline", &inner_content))
+}
@@ -0,0 +1,360 @@
+//! Tool routes
+//!
+//! One-off helpers that write to the ledger files, e.g. bootstrapping the
+//! account tree of a new ledger from a template or a CSV export, recording
+//! its opening balances, importing historical balance assertions from CSV or
+//! importing a bank's transaction export without duplicating earlier imports
+
+use crate::AppState;
+use beanweb_core::account_import::AccountImportOptions;
+use beanweb_core::AccountType;
+use beanweb_core::balance_import::{BalanceImportOptions, DEFAULT_BALANCES_FILE};
+use beanweb_core::bootstrap::{account_templates, ACCOUNTS_FILE};
+use beanweb_core::opening::{OpeningBalance, DEFAULT_OPENING_FILE};
+use beanweb_core::transaction_import::{TransactionImportOptions, DEFAULT_IMPORT_FILE};
+use beanweb_ui::tools;
+use std::collections::HashMap;
+
+/// List the shipped account-tree templates (JSON API)
+pub async fn api_account_templates() -> String {
+    serde_json::to_string(&account_templates()).unwrap_or_default()
+}
+
+/// Open all accounts of a template and wire the include (JSON API)
+/// Body (JSON): `{"template": "personal", "currency": "CNY", "start_date": "2024-01-01"}`
+/// Every field is optional: personal, the default currency and today
+pub async fn api_bootstrap_accounts(state: axum::extract::State<AppState>, body: String) -> String {
+    let params: HashMap<String, String> = if body.trim().is_empty() {
+        HashMap::new()
+    } else {
+        match serde_json::from_str(&body) {
+            Ok(params) => params,
+            Err(e) => return serde_json::json!({"success": false, "message": format!("Invalid JSON: {}", e)}).to_string(),
+        }
+    };
+    let template = params.get("template").map(|s| s.as_str()).unwrap_or("personal");
+    let currency = params.get("currency").cloned().unwrap_or_else(|| state.config.currency.default_currency.clone());
+    let date = params.get("start_date").cloned().unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+
+    let mut ledger = state.ledger.write().await;
+    let outcome = match ledger.bootstrap_accounts(template, &currency, &date) {
+        Ok(outcome) => outcome,
+        Err(e) => return serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    };
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after bootstrapping accounts: {}", e);
+    }
+
+    serde_json::json!({
+        "success": true,
+        "message": format!("已开立 {} 个账户", outcome.opened.len()),
+        "result": outcome,
+    })
+    .to_string()
+}
+
+/// GET /tools/bootstrap - pick a template to open the accounts of a new ledger
+pub async fn page_bootstrap_accounts(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let inner_content = tools::bootstrap_accounts_page(&account_templates(), &state.config.currency.default_currency, &today);
+    axum::response::Html(crate::page_response(&headers, &state.config, "账户模板", "/settings", &inner_content))
+}
+
+/// Body of the account import endpoints
+#[derive(Debug, serde::Deserialize)]
+struct AccountImportRequest {
+    csv: String,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(flatten)]
+    options: AccountImportOptions,
+}
+
+fn parse_account_import(body: &str) -> Result<AccountImportRequest, String> {
+    serde_json::from_str(body).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Check a chart of accounts from CSV without writing (JSON API)
+/// Body (JSON): `{"csv": "account,type,currency,date\n...", "start_date": "2024-01-01"}`
+pub async fn api_account_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_account_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let ledger = state.ledger.read().await;
+    let preview = ledger.preview_account_import(&request.csv, &request.options);
+    serde_json::json!({"success": true, "result": preview}).to_string()
+}
+
+/// Write `open` directives for a chart of accounts from CSV (JSON API)
+/// Body (JSON): as the preview, plus an optional `"file"` (default `accounts.bean`)
+pub async fn api_account_import(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_account_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+
+    let mut ledger = state.ledger.write().await;
+    let outcome = match ledger.import_accounts(&request.csv, request.file.as_deref(), &request.options) {
+        Ok(outcome) => outcome,
+        Err(e) => return serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    };
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after importing accounts: {}", e);
+    }
+
+    serde_json::json!({
+        "success": true,
+        "message": format!("已开立 {} 个账户到 {}", outcome.opened.len(), outcome.file),
+        "result": outcome,
+    })
+    .to_string()
+}
+
+/// GET /tools/accounts - form to preview and import a chart of accounts
+pub async fn page_account_import(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let inner_content = tools::account_import_page(ACCOUNTS_FILE);
+    axum::response::Html(crate::page_response(&headers, &state.config, "导入账户", "/settings", &inner_content))
+}
+
+/// HTMX: preview table of an account import
+pub async fn htmx_account_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_account_import(&body) {
+        Ok(request) => request,
+        Err(message) => return tools::error(&message),
+    };
+    let ledger = state.ledger.read().await;
+    tools::account_import_preview(&ledger.preview_account_import(&request.csv, &request.options))
+}
+
+/// Body of the balance import endpoints
+#[derive(Debug, serde::Deserialize)]
+struct BalanceImportRequest {
+    csv: String,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(flatten)]
+    options: BalanceImportOptions,
+}
+
+fn parse_balance_import(body: &str) -> Result<BalanceImportRequest, String> {
+    serde_json::from_str(body).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Check balance assertions from CSV against the ledger without writing (JSON API)
+/// Body (JSON): `{"csv": "date,account,amount,currency\n...", "end_of_day": true}`
+pub async fn api_balance_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_balance_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let ledger = state.ledger.read().await;
+    let preview = ledger.preview_balance_import(&request.csv, &request.options);
+    serde_json::json!({"success": true, "result": preview}).to_string()
+}
+
+/// Write balance assertions from CSV into a ledger file (JSON API)
+/// Body (JSON): `{"csv": "...", "file": "balances.bean", "end_of_day": true, "skip_failing": false}`;
+/// failing rows are skipped unless `skip_failing` is false
+pub async fn api_balance_import(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_balance_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let file = request.file.as_deref().unwrap_or(DEFAULT_BALANCES_FILE);
+
+    let mut ledger = state.ledger.write().await;
+    let outcome = match ledger.import_balances(&request.csv, file, &request.options) {
+        Ok(outcome) => outcome,
+        Err(e) => return serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    };
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after importing balances: {}", e);
+    }
+
+    serde_json::json!({
+        "success": true,
+        "message": format!("已写入 {} 条余额断言到 {}", outcome.written, outcome.file),
+        "result": outcome,
+    })
+    .to_string()
+}
+
+/// GET /tools/balances - form to preview and import balance assertions
+pub async fn page_balance_import(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let inner_content = tools::balance_import_page(DEFAULT_BALANCES_FILE);
+    axum::response::Html(crate::page_response(&headers, &state.config, "导入余额断言", "/settings", &inner_content))
+}
+
+/// HTMX: preview table of a balance import, failing months highlighted
+pub async fn htmx_balance_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_balance_import(&body) {
+        Ok(request) => request,
+        Err(message) => return tools::error(&message),
+    };
+    let ledger = state.ledger.read().await;
+    tools::balance_import_preview(&ledger.preview_balance_import(&request.csv, &request.options))
+}
+
+/// Body of the opening balance endpoints
+#[derive(Debug, serde::Deserialize)]
+struct OpeningBalancesRequest {
+    #[serde(default)]
+    date: Option<String>,
+    balances: Vec<OpeningBalance>,
+    #[serde(default)]
+    file: Option<String>,
+}
+
+fn parse_opening_balances(body: &str) -> Result<(OpeningBalancesRequest, String), String> {
+    let request: OpeningBalancesRequest = serde_json::from_str(body).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let date = request.date.clone().unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    Ok((request, date))
+}
+
+/// Generate the opening balance directives without writing (JSON API)
+/// Body (JSON): `{"date": "2024-01-01", "balances": [{"account": "Assets:Bank", "amount": "1000", "currency": "CNY"}]}`
+pub async fn api_opening_balances_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let (request, date) = match parse_opening_balances(&body) {
+        Ok(parsed) => parsed,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let ledger = state.ledger.read().await;
+    match ledger.plan_opening_balances(&date, &request.balances) {
+        Ok(plan) => serde_json::json!({"success": true, "result": plan}).to_string(),
+        Err(e) => serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    }
+}
+
+/// Write the opening balance transaction and open directives (JSON API)
+/// Body (JSON): as the preview, plus an optional `"file"` (default `opening.bean`)
+pub async fn api_opening_balances(state: axum::extract::State<AppState>, body: String) -> String {
+    let (request, date) = match parse_opening_balances(&body) {
+        Ok(parsed) => parsed,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let file = request.file.as_deref().unwrap_or(DEFAULT_OPENING_FILE);
+
+    let mut ledger = state.ledger.write().await;
+    let outcome = match ledger.write_opening_balances(&date, &request.balances, file) {
+        Ok(outcome) => outcome,
+        Err(e) => return serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    };
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after writing opening balances: {}", e);
+    }
+
+    serde_json::json!({
+        "success": true,
+        "message": format!("已写入 {} 笔期初余额到 {}", outcome.plan.postings.len(), outcome.file),
+        "result": outcome,
+    })
+    .to_string()
+}
+
+/// GET /tools/opening-balances - wizard for the opening balances, offering
+/// the asset and liability accounts already open
+pub async fn page_opening_balances(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    let ledger = state.ledger.read().await;
+    let options = ledger.options();
+    let accounts: Vec<String> = ledger.accounts().into_iter()
+        .map(|a| a.name)
+        .filter(|name| options.is(name, AccountType::Assets) || options.is(name, AccountType::Liabilities))
+        .collect();
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let inner_content = tools::opening_balances_page(&accounts, &ledger.report_currency(), &today, DEFAULT_OPENING_FILE);
+    axum::response::Html(crate::page_response(&headers, &state.config, "期初余额", "/settings", &inner_content))
+}
+
+/// HTMX: generated opening balance directives
+pub async fn htmx_opening_balances_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let (request, date) = match parse_opening_balances(&body) {
+        Ok(parsed) => parsed,
+        Err(message) => return tools::error(&message),
+    };
+    let ledger = state.ledger.read().await;
+    match ledger.plan_opening_balances(&date, &request.balances) {
+        Ok(plan) => tools::opening_balances_preview(&plan),
+        Err(e) => tools::error(&e.to_string()),
+    }
+}
+
+/// Body of the transaction import endpoints
+#[derive(Debug, serde::Deserialize)]
+struct TransactionImportRequest {
+    csv: String,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(flatten)]
+    options: TransactionImportOptions,
+}
+
+fn parse_transaction_import(body: &str) -> Result<TransactionImportRequest, String> {
+    serde_json::from_str(body).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Check a bank export against the ledger without writing (JSON API)
+/// Body (JSON): `{"csv": "date,amount,payee,narration,external_id,currency\n...", "account": "Assets:Bank", "counter_account": "Expenses:Uncategorized"}`
+pub async fn api_transaction_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_transaction_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let ledger = state.ledger.read().await;
+    match ledger.preview_transaction_import(&request.csv, &request.options) {
+        Ok(preview) => serde_json::json!({"success": true, "result": preview}).to_string(),
+        Err(e) => serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    }
+}
+
+/// Write the new rows of a bank export as transactions (JSON API)
+/// Body (JSON): as the preview, plus an optional `"file"` (default `imports.bean`)
+pub async fn api_transaction_import(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_transaction_import(&body) {
+        Ok(request) => request,
+        Err(message) => return serde_json::json!({"success": false, "message": message}).to_string(),
+    };
+    let file = request.file.as_deref().unwrap_or(DEFAULT_IMPORT_FILE);
+
+    let mut ledger = state.ledger.write().await;
+    let outcome = match ledger.import_transactions(&request.csv, file, &request.options) {
+        Ok(outcome) => outcome,
+        Err(e) => return serde_json::json!({"success": false, "message": e.to_string()}).to_string(),
+    };
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after importing transactions: {}", e);
+    }
+
+    serde_json::json!({
+        "success": true,
+        "message": format!("已导入 {} 笔交易到 {}，跳过 {} 行", outcome.written, outcome.file, outcome.skipped),
+        "result": outcome,
+    })
+    .to_string()
+}
+
+/// HTMX: preview table of a transaction import, duplicates greyed out
+pub async fn htmx_transaction_import_preview(state: axum::extract::State<AppState>, body: String) -> String {
+    let request = match parse_transaction_import(&body) {
+        Ok(request) => request,
+        Err(message) => return tools::error(&message),
+    };
+    let ledger = state.ledger.read().await;
+    match ledger.preview_transaction_import(&request.csv, &request.options) {
+        Ok(preview) => tools::transaction_import_preview(&preview),
+        Err(e) => tools::error(&e.to_string()),
+    }
+}
+
@@ -2,56 +2,208 @@
 //!
 //! Endpoints:
 //! - api_transactions: Get transactions list (JSON)
+//! - api_transactions_export: Filtered transactions as a CSV download
 //! - api_transaction_detail: Get single transaction (JSON)
+//! - api_transaction_delete: Delete a transaction from its file (JSON)
 //! - htmx_transactions_list: Transaction list (HTML fragment)
 //! - htmx_transactions_filter: Transaction filter (HTML fragment)
 //! - htmx_transaction_detail: Transaction detail (HTML fragment)
+//! - htmx_transactions_upcoming: Future-dated transactions (HTML fragment)
+//! - htmx_transactions_review_banner: "N new since last visit" banner (HTML fragment)
+//! - htmx_transactions_mark_reviewed: Move the reviewed watermark to the latest transaction (HTMX)
 //! - htmx_transaction_edit_form: Edit form (HTML fragment)
 //! - htmx_transaction_update: Update transaction (HTMX)
+//! - htmx_transaction_delete: Delete transaction (HTMX)
 //! - htmx_transaction_create_form: Create form (HTML fragment)
 //! - htmx_transaction_store: Store new transaction (HTMX)
 
-use crate::AppState;
-use beanweb_core::TransactionsResponse;
+use crate::{ApiError, AppState};
+use crate::collection::{Collection, CollectionQuery, CollectionResponse};
+use beanweb_core::account_filter::{retain_matching, split_query, AccountFilter};
 use axum::extract::Query;
+use beanweb_ui::transactions;
 use std::collections::HashMap;
-use std::io::Write;
-use std::path::PathBuf;
+
+/// Cookie holding the "reviewed up to" date watermark for this browser
+const REVIEWED_COOKIE: &str = "beanweb_reviewed_until";
+
+/// Event fired after the reviewed watermark moves
+pub const REVIEWED_UPDATED_EVENT: &str = "reviewed-updated";
+
+/// Read the reviewed watermark (YYYY-MM-DD) from the request cookies
+fn reviewed_until(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers.get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == REVIEWED_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok())
+}
+
+/// A transaction counts as reviewed once the watermark reaches its date
+fn is_reviewed(tx: &beanweb_core::Transaction, watermark: Option<&str>) -> bool {
+    watermark.is_some_and(|w| tx.date.as_str() <= w)
+}
+
+/// Tag or link of a `tag`/`link` parameter, without the `#` or `^`; None
+/// when blank
+fn label_param(value: Option<&str>) -> Option<&str> {
+    value.map(|t| t.trim().trim_start_matches(['#', '^'])).filter(|t| !t.is_empty())
+}
+
+/// Whether `tx` carries the tag and the link, when asked for
+fn has_labels(tx: &beanweb_core::Transaction, tag: Option<&str>, link: Option<&str>) -> bool {
+    tag.is_none_or(|tag| tx.tags.iter().any(|t| t == tag)) && link.is_none_or(|link| tx.links.iter().any(|l| l == link))
+}
+
+/// Account filters from the `account` parameter and `account:` terms in `q`,
+/// with the remaining search keywords
+fn account_filters(params: &HashMap<String, String>) -> Result<(Vec<AccountFilter>, String), beanweb_core::CoreError> {
+    let (mut filters, keywords) = split_query(params.get("q").map(|s| s.as_str()).unwrap_or(""))?;
+    if let Some(pattern) = params.get("account").filter(|s| !s.trim().is_empty()) {
+        filters.push(AccountFilter::parse(pattern)?);
+    }
+    Ok((filters, keywords))
+}
+
+/// Transactions the list shows for `q`, `account`, `tag`, `link` and `reviewed`
+/// in the active time range, newest first; future-dated ones are listed separately
+fn list_transactions<'a>(
+    ledger: &'a beanweb_core::Ledger,
+    params: &HashMap<String, String>,
+    headers: &axum::http::HeaderMap,
+) -> Result<beanweb_core::Transactions<'a>, beanweb_core::CoreError> {
+    let (filters, keywords) = account_filters(params)?;
+    let keep = list_filter(ledger, params, headers);
+    Ok(ledger.transactions_matching(&filters, &keywords, keep))
+}
+
+/// Up to `limit` transactions of the list after the transaction `after`
+fn list_page(
+    ledger: &beanweb_core::Ledger,
+    params: &HashMap<String, String>,
+    headers: &axum::http::HeaderMap,
+    after: Option<&str>,
+    limit: usize,
+) -> Result<beanweb_core::TransactionPage, beanweb_core::CoreError> {
+    let (filters, keywords) = account_filters(params)?;
+    let keep = list_filter(ledger, params, headers);
+    ledger.transactions_after(after, limit, &filters, &keywords, keep)
+}
+
+/// The list's time range, `tag`, `link` and `reviewed` filters
+fn list_filter(
+    ledger: &beanweb_core::Ledger,
+    params: &HashMap<String, String>,
+    headers: &axum::http::HeaderMap,
+) -> impl Fn(&beanweb_core::Transaction) -> bool {
+    use beanweb_core::TimeFilter;
+    let time_context = ledger.time_context();
+    let as_of = time_context.as_of();
+
+    // Reviewed quick filter: "yes" keeps reviewed, "no" keeps new transactions
+    let watermark = reviewed_until(headers);
+    let reviewed = params.get("reviewed").cloned();
+    let tag = label_param(params.get("tag").map(|s| s.as_str())).map(str::to_string);
+    let link = label_param(params.get("link").map(|s| s.as_str())).map(str::to_string);
+
+    move |t| {
+        // Future-dated transactions are listed in the upcoming section instead
+        !t.is_upcoming(as_of)
+            && t.filter_by_time(&time_context)
+            && has_labels(t, tag.as_deref(), link.as_deref())
+            && match reviewed.as_deref() {
+                Some("yes") => is_reviewed(t, watermark.as_deref()),
+                Some("no") => !is_reviewed(t, watermark.as_deref()),
+                _ => true,
+            }
+    }
+}
+
+/// `/api/transactions` collection parameters
+pub struct TransactionCollection;
+
+impl Collection for TransactionCollection {
+    const FILTERS: &'static [&'static str] = &["account", "link", "search", "tag"];
+    const SORTS: &'static [&'static str] = &["date", "payee", "narration"];
+    const DEFAULT_SORT: &'static str = "-date";
+    const DEFAULT_PAGE_SIZE: usize = 50;
+    const CURSOR: bool = true;
+}
 
 /// Get transactions with pagination and search (JSON API)
+/// `filter[account]` (or `account:` in `filter[search]`) takes an account, a
+/// glob such as `Expenses:Food:*` or a `~regex`; `filter[tag]` and
+/// `filter[link]` keep the transactions with that tag or link; `page[after]=<id>`
+/// pages by cursor instead of page number
 pub async fn api_transactions(
     state: axum::extract::State<AppState>,
-    params: Query<HashMap<String, String>>,
-) -> String {
-    let ledger = state.ledger.read().await;
-    let limit = params.get("limit").and_then(|s| s.parse().ok()).unwrap_or(50);
-    let offset = params.get("offset").and_then(|s| s.parse().ok()).unwrap_or(0);
-    let query = params.get("q").map(|s| s.as_str());
-
-    let transactions = if let Some(q) = query {
-        if q.is_empty() {
-            ledger.transactions(limit, offset)
-        } else {
-            let all = ledger.search_transactions(q);
-            all.into_iter().skip(offset).take(limit).collect()
-        }
-    } else {
-        ledger.transactions(limit, offset)
-    };
+    query: CollectionQuery<TransactionCollection>,
+) -> Result<axum::Json<CollectionResponse<beanweb_core::Transaction>>, ApiError> {
+    let bad_request = |e: beanweb_core::CoreError| ApiError::BadRequest { message: e.to_string() };
+    let (mut filters, keywords) = split_query(query.filter("search").unwrap_or("")).map_err(bad_request)?;
+    if let Some(pattern) = query.filter("account") {
+        filters.push(AccountFilter::parse(pattern).map_err(bad_request)?);
+    }
+
+    let (tag, link) = (label_param(query.filter("tag")), label_param(query.filter("link")));
+    let has_labels = |t: &beanweb_core::Transaction| has_labels(t, tag, link);
 
-    let total_count = if let Some(q) = query {
-        if q.is_empty() { ledger.transactions_count() } else { ledger.search_transactions(q).len() }
+    let ledger = state.ledger.read().await;
+    if query.after.is_some() {
+        // Cursor paging walks the date index and clones only the page
+        let page = ledger.transactions_after(query.cursor(), query.page_size, &filters, &keywords, has_labels)
+            .map_err(bad_request)?;
+        return Ok(axum::Json(query.paginate_after(page.transactions, page.next)));
+    }
+    let found = if keywords.is_empty() {
+        ledger.transaction_query(usize::MAX, 0, &filters, None)
     } else {
-        ledger.transactions_count()
+        ledger.search_transactions(&keywords)
     };
+    // Filtered and sorted in place; only the page is cloned
+    let mut transactions: Vec<&beanweb_core::Transaction> = found.iter().filter(|t| has_labels(t)).collect();
+    retain_matching(&mut transactions, &filters);
+
+    query.sort(&mut transactions, |field, a, b| match field {
+        "payee" => a.payee.cmp(&b.payee),
+        "narration" => a.narration.cmp(&b.narration),
+        _ => a.date.cmp(&b.date).then_with(|| a.time.cmp(&b.time)),
+    });
+    let page = query.paginate(transactions);
+    Ok(axum::Json(CollectionResponse { data: page.data.into_iter().cloned().collect(), meta: page.meta, links: page.links }))
+}
 
-    let response = TransactionsResponse {
-        transactions,
-        total_count,
-        page: offset / limit + 1,
-        page_size: limit,
+/// Download the transactions the list shows as CSV, one row per posting
+/// (`?format=csv`, the only format so far); honors `q`, `account`, `tag`, `link`, `reviewed`
+/// and the active time range
+pub async fn api_transactions_export(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+    params: Query<HashMap<String, String>>,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    let format = params.get("format").map(|s| s.as_str()).unwrap_or("csv");
+    if format != "csv" {
+        let error = serde_json::json!({"error": format!("Unsupported export format: {}", format)});
+        return (axum::http::StatusCode::BAD_REQUEST, axum::Json(error)).into_response();
+    }
+    let ledger = state.ledger.read().await;
+    let transactions = match list_transactions(&ledger, &params, &headers) {
+        Ok(transactions) => transactions,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, axum::Json(serde_json::json!({"error": e.to_string()}))).into_response(),
     };
-    serde_json::to_string(&response).unwrap_or_default()
+    // Rows are written from the borrowed transactions, then the lock is released
+    let rows: Vec<String> = std::iter::once(beanweb_core::export::CSV_HEADER.to_string())
+        .chain(transactions.iter().map(beanweb_core::export::transaction_csv_rows))
+        .collect();
+    drop(transactions);
+    drop(ledger);
+
+    let filename = format!("transactions-{}.csv", chrono::Local::now().format("%Y%m%d"));
+    crate::routes::stream::download_stream("text/csv; charset=utf-8", &filename, rows.into_iter())
 }
 
 /// Get single transaction detail (JSON API)
@@ -69,211 +221,232 @@ pub async fn api_transaction_detail(
     }
 }
 
+/// API: Headline amount and per-posting amounts of a transaction, elided
+/// postings inferred; `display_amount` follows the sign convention
+pub async fn api_transaction_breakdown(
+    state: axum::extract::State<AppState>,
+    path: axum::extract::Path<String>,
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    let ledger = state.ledger.read().await;
+    let tx = ledger.transaction(&path.0).ok_or_else(|| ApiError::NotFound { resource: format!("transaction {}", path.0) })?;
+    let postings: Vec<serde_json::Value> = tx.breakdown().into_iter()
+        .map(|p| {
+            let display = ledger.display_amount(&p.account, parse_decimal(&p.amount));
+            serde_json::json!({
+                "account": p.account,
+                "amount": p.amount,
+                "display_amount": display.to_string(),
+                "currency": p.currency,
+                "inferred": p.inferred,
+            })
+        })
+        .collect();
+    Ok(axum::Json(serde_json::json!({"headline": ledger.headline(&tx), "postings": postings})))
+}
+
+/// HTMX: Tooltip of a row with more than two postings, the amount of each
+pub async fn htmx_transaction_breakdown(
+    state: axum::extract::State<AppState>,
+    path: axum::extract::Path<String>,
+) -> String {
+    let ledger = state.ledger.read().await;
+    let Some(tx) = ledger.transaction(&path.0) else {
+        return String::new();
+    };
+    let postings: Vec<_> = tx.breakdown().into_iter()
+        .map(|p| {
+            let display = ledger.display_amount(&p.account, parse_decimal(&p.amount));
+            (p, display)
+        })
+        .collect();
+    transactions::breakdown(&postings)
+}
+
+fn parse_decimal(text: &str) -> beanweb_core::Decimal {
+    beanweb_core::Decimal::from_str_exact(text).unwrap_or_default()
+}
+
+/// API: Delete a transaction, removing its block from the source file
+/// (the previous file is kept as `<file>.bak`) and reloading the ledger
+pub async fn api_transaction_delete(
+    state: axum::extract::State<AppState>,
+    path: axum::extract::Path<String>,
+) -> String {
+    match delete_transaction(&state, &path.0).await {
+        Ok(deletion) => serde_json::json!({
+            "success": true,
+            "message": format!("已删除 {} 第 {} 行的交易", deletion.file.display(), deletion.line),
+            "result": {"file": deletion.file, "line": deletion.line, "transaction": deletion.transaction},
+        }),
+        Err(e) => serde_json::json!({"success": false, "message": e.to_string()}),
+    }
+    .to_string()
+}
+
+async fn delete_transaction(state: &AppState, id: &str) -> Result<beanweb_core::WrittenTransaction, beanweb_core::CoreError> {
+    let deletion = state.service().delete_transaction(id).await?;
+    tracing::info!("Deleted transaction at {}:{}", deletion.file.display(), deletion.line);
+    Ok(deletion)
+}
+
+/// API: All transactions sharing a `^link`, with the group's net balance per account
+pub async fn api_link_group(
+    state: axum::extract::State<AppState>,
+    path: axum::extract::Path<String>,
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    if path.0.trim().trim_start_matches('^').is_empty() {
+        return Err(ApiError::BadRequest { message: "link is empty".to_string() });
+    }
+    let ledger = state.ledger.read().await;
+    let group = ledger.link_group(&path.0);
+    if group.transactions.is_empty() {
+        return Err(ApiError::NotFound { resource: format!("link {}", path.0) });
+    }
+    let settled = group.is_settled();
+    let mut value = serde_json::to_value(group).map_err(|_| ApiError::InternalError)?;
+    value["settled"] = serde_json::Value::Bool(settled);
+    Ok(axum::Json(value))
+}
+
 /// HTMX: Transactions list - Partial page update
 /// Supports combined keyword and time filtering:
 /// - Keyword only: Search all transactions
 /// - Time only: Filter by time range
 /// - Both: Apply keyword filter to time-filtered results
+/// - Account filters (`account`, or `account:` in `q`) narrow any of these
 pub async fn htmx_transactions_list(
     state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
     params: Query<HashMap<String, String>>,
-) -> String {
+) -> axum::response::Response {
     let ledger = state.ledger.read().await;
-    let time_context = ledger.time_context();
     let limit = params.get("limit").and_then(|s| s.parse().ok()).unwrap_or(50);
     let offset = params.get("offset").and_then(|s| s.parse().ok()).unwrap_or(0);
-    let query = params.get("q").map(|s| s.as_str()).unwrap_or("");
-
-    // Check if time filter is active
-    let use_time_filter = !matches!(time_context.range, beanweb_config::TimeRange::All);
-
-    // Determine data source based on filters
-    let base_transactions: Vec<beanweb_core::Transaction> = if query.is_empty() {
-        // No keyword - use time filter if active
-        if use_time_filter {
-            ledger.filtered_transactions(10000, 0)
-        } else {
-            ledger.transactions(10000, 0)
-        }
-    } else if query.is_empty() && use_time_filter {
-        // Keyword empty, time filter active
-        ledger.filtered_transactions(10000, 0)
-    } else if query.is_empty() {
-        // No filters
-        ledger.transactions(10000, 0)
-    } else {
-        // Has keyword - need to check time filter too
-        if use_time_filter {
-            // Apply keyword filter to time-filtered results
-            let time_filtered = ledger.filtered_transactions(10000, 0);
-            let search_results = ledger.search_transactions(query);
-            let search_accounts: std::collections::HashSet<String> =
-                search_results.iter().flat_map(|t| t.postings.iter().map(|p| p.account.clone())).collect();
-            time_filtered.into_iter()
-                .filter(|t| {
-                    // Match if any posting account matches or transaction metadata matches
-                    t.postings.iter().any(|p| search_accounts.contains(&p.account)) ||
-                    t.payee.to_lowercase().contains(&query.to_lowercase()) ||
-                    t.narration.to_lowercase().contains(&query.to_lowercase()) ||
-                    t.tags.iter().any(|tag| tag.to_lowercase().contains(&query.to_lowercase()))
-                })
-                .collect()
-        } else {
-            // Only keyword filter
-            ledger.search_transactions(query)
+    // `after=<id>` pages by cursor, which new transactions don't shift;
+    // without it the numbered pages use `offset`
+    let listed = match params.get("after") {
+        Some(after) => {
+            let after = Some(after.as_str()).filter(|a| !a.is_empty());
+            list_page(&ledger, &params, &headers, after, limit).map(|page| (page.transactions, page.next, None))
         }
+        None => list_transactions(&ledger, &params, &headers).map(|transactions| {
+            // Counted in place; only the page is cloned for the stream
+            let total_count = transactions.len();
+            let page: Vec<_> = transactions.iter().skip(offset).take(limit).cloned().collect();
+            let next = page.last().filter(|_| offset + page.len() < total_count).map(|t| t.id.clone());
+            (page, next, Some(total_count))
+        }),
     };
-
-    let mut transactions = base_transactions;
-
-    transactions.sort_by(|a, b| {
-        match b.date.cmp(&a.date) {
-            std::cmp::Ordering::Equal => b.time.cmp(&a.time),
-            other => other
+    let (transactions, next, total_count) = match listed {
+        Ok(listed) => listed,
+        Err(e) => {
+            return crate::routes::stream::html_stream(std::iter::once(transactions::list_error(&e.to_string())));
         }
-    });
-
-    let total_count = transactions.len();
-    let transactions: Vec<_> = transactions.into_iter().skip(offset).take(limit).collect();
+    };
 
-    let current_page = offset / limit + 1;
-    let total_pages = (total_count + limit - 1) / limit;
+    // Rows render while the body streams; the ledger lock isn't needed for that
+    let transactions: Vec<_> = transactions.into_iter().map(|tx| {
+        let headline = ledger.headline(&tx);
+        (tx, headline)
+    }).collect();
+    drop(ledger);
 
     if transactions.is_empty() {
-        return r#"<div class='text-center py-12 text-gray-500'><p>暂无交易记录</p></div>"#.to_string();
+        return crate::routes::stream::html_stream(std::iter::once(transactions::list_empty()));
     }
 
-    let mut html = String::from("<div id='tx-list-container' class='space-y-2'>");
-    for tx in &transactions {
-        let flag = tx.flag.as_deref().unwrap_or("");
-        let flag_color = match flag {
-            "*" => "#10B981",
-            "!" => "#F59E0B",
-            _ => "#6B7280",
-        };
-
-        // Smart parsing of payee and narration
-        // Handle cases where user entered "payee: narration" without quotes
-        let (desc, narration): (String, String) = if tx.payee.is_empty() && tx.narration.contains(": ") {
-            // Likely entered as "工资: 2026-01-30" without quotes
-            let parts: Vec<&str> = tx.narration.split(": ").collect();
-            if parts.len() >= 2 {
-                (parts[0].to_string(), parts[1..].join(": "))
-            } else {
-                (tx.narration.clone(), String::new())
-            }
-        } else if tx.payee.is_empty() {
-            // Only narration, no payee
-            (tx.narration.clone(), String::new())
-        } else if tx.narration.is_empty() {
-            // Only payee, no narration
-            (tx.payee.clone(), String::new())
-        } else {
-            // Both payee and narration present
-            (tx.payee.clone(), tx.narration.clone())
-        };
-
-        let narration_display = if narration.is_empty() {
-            String::new()
-        } else {
-            format!(" - {}", narration)
-        };
-
-        let (amount_display, amount_color, display_currency) = calculate_tx_amount(tx);
-
-        let currency_suffix = if display_currency.is_empty() {
-            String::new()
-        } else {
-            format!(" {}", display_currency)
-        };
-
-        let tags_brief: Vec<String> = tx.tags.iter().take(2).map(|tag| format!("#{}", tag)).collect();
-        let tags_brief_str = if tags_brief.is_empty() {
-            String::new()
-        } else {
-            format!(" <span class='text-gray-400 text-sm'>{}</span>", tags_brief.join(" "))
-        };
-
-        let detail_id = format!("tx-detail-{}", tx.id);
-        let datetime = if tx.has_time() {
-            format!("{} <span class='text-gray-400'>{}</span>", tx.date, tx.time)
-        } else {
-            tx.date.clone()
-        };
-
-        html.push_str(&format!(
-            r#"<div class='border border-l-4 rounded-r-lg p-3 hover:bg-gray-50 transition cursor-pointer' onclick='toggleDetail("{}")'>
-                <div class='flex items-center justify-between gap-2'>
-                    <div class='flex items-center gap-3 flex-1 min-w-0'>
-                        <span class='w-1 h-8 rounded flex-shrink-0' style='background:{}'></span>
-                        <div class='flex-1 min-w-0'>
-                            <div class='text-sm text-gray-500'>{}</div>
-                            <div class='font-medium truncate'>{}{}</div>
-                        </div>
-                    </div>
-                    <div class='flex items-center gap-2 flex-shrink-0'>
-                        {}
-                        <span class='font-medium {}'>{}{}</span>
-                    </div>
-                </div>
-            </div>
-            <div id='{}' class='tx-detail-container' style='display:none'></div>"#,
-            detail_id, flag_color, datetime, desc, narration_display, tags_brief_str, amount_color, amount_display, currency_suffix, detail_id
-        ));
-    }
-    html.push_str("</div>");
-
-    html.push_str(r#"<script>
-    function toggleDetail(id) {
-        var el = document.getElementById(id);
-        if (el.style.display === 'none') {
-            el.style.display = 'block';
-            if (el.innerHTML === '') {
-                htmx.ajax('GET', '/transactions/' + id.replace('tx-detail-', '') + '/detail', {target: el});
-            }
-        } else {
-            el.style.display = 'none';
-            el.innerHTML = '';
-        }
-    }
-    </script>"#);
-
-    let target = "#transactions-content";
-    html.push_str(&format!(
-        r#"<div class='mt-6 flex items-center justify-between flex-wrap gap-4'>
-            <span class='text-sm text-gray-500'>共 {} 条记录，第 {} / {} 页</span>
-            <div class='flex items-center gap-2'>
-                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset=0&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>首页</button>
-                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>上一页</button>
-                <span class='text-sm text-gray-600'>第 <input type='number' id='page-jump-input' min='1' max='{}' value='{}' class='w-16 text-center border rounded px-2 py-1'> 页</span>
-                <button onclick='const p=document.getElementById("page-jump-input").value; htmx.ajax("GET", "/transactions/list?limit={}&offset=" + (p-1)*{} + "&q={}", "{}")' class='px-3 py-1 border rounded bg-blue-50 hover:bg-blue-100 text-blue-600'>跳转</button>
-                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>下一页</button>
-                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>末页</button>
-            </div>
-        </div>"#,
-        total_count, current_page, total_pages,
-        if current_page == 1 { "disabled" } else { "" },
-        limit, query, target,
-        if current_page == 1 { "disabled" } else { "" },
-        limit, offset.saturating_sub(limit), query, target,
-        total_pages, current_page,
-        limit, limit, query, target,
-        if current_page >= total_pages { "disabled" } else { "" },
-        limit, offset + limit, query, target,
-        if current_page >= total_pages { "disabled" } else { "" },
-        limit, (total_pages.saturating_sub(1)) * limit, query, target
-    ));
-
-    html.push_str(r#"<style>.disabled{cursor:not-allowed;opacity:0.5;pointer-events:none}</style>"#);
-    html
+    // Keep the account, tag, link and reviewed filters when paging
+    let query_param = format!(
+        "{}&account={}&tag={}&link={}&reviewed={}",
+        urlencoding::encode(params.get("q").map(|s| s.as_str()).unwrap_or("")),
+        urlencoding::encode(params.get("account").map(|s| s.as_str()).unwrap_or("")),
+        urlencoding::encode(params.get("tag").map(|s| s.as_str()).unwrap_or("")),
+        urlencoding::encode(params.get("link").map(|s| s.as_str()).unwrap_or("")),
+        urlencoding::encode(params.get("reviewed").map(|s| s.as_str()).unwrap_or(""))
+    );
+    let footer = transactions::list_footer(limit, offset, total_count, next.as_deref(), &query_param);
+
+    // Header, row chunks and footer go out as separate body frames; rows are
+    // rendered lazily as the client reads
+    let mut rows = transactions.into_iter().peekable();
+    let chunks = std::iter::once(transactions::LIST_START.to_string())
+        .chain(std::iter::from_fn(move || {
+            rows.peek()?;
+            Some(rows.by_ref().take(STREAM_CHUNK_ROWS).map(|(tx, headline)| transactions::row(&tx, &headline)).collect::<String>())
+        }))
+        .chain(std::iter::once(footer));
+    crate::routes::stream::html_stream(chunks)
 }
 
+/// Transactions rendered per streamed chunk
+const STREAM_CHUNK_ROWS: usize = 50;
+
 /// Transactions filter - Alias for list (used by page size selector)
 pub async fn htmx_transactions_filter(
     state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
     params: Query<HashMap<String, String>>,
+) -> axum::response::Response {
+    htmx_transactions_list(state, headers, params).await
+}
+
+/// HTMX: Upcoming (future-dated) transactions section
+/// Empty when there are none or when future transactions are already included
+pub async fn htmx_transactions_upcoming(state: axum::extract::State<AppState>) -> String {
+    let ledger = state.ledger.read().await;
+    if ledger.as_of_date().is_none() {
+        return String::new();
+    }
+    let upcoming = ledger.upcoming_transactions();
+    if upcoming.is_empty() {
+        return String::new();
+    }
+
+    let upcoming: Vec<_> = upcoming.into_iter().map(|tx| {
+        let headline = ledger.headline(&tx);
+        (tx, headline)
+    }).collect();
+    transactions::upcoming(&upcoming)
+}
+
+/// HTMX: Banner with the number of transactions added since the reviewed watermark
+/// Counts the whole ledger (not the selected time range), upcoming transactions excluded
+pub async fn htmx_transactions_review_banner(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
 ) -> String {
-    htmx_transactions_list(state, params).await
+    let ledger = state.ledger.read().await;
+    let as_of = ledger.as_of_date();
+    let watermark = reviewed_until(&headers);
+    let new_count = ledger.transactions(10000, 0)
+        .iter()
+        .filter(|t| !t.is_upcoming(as_of) && !is_reviewed(t, watermark.as_deref()))
+        .count();
+    if new_count == 0 {
+        return String::new();
+    }
+
+    transactions::review_banner(watermark.as_deref(), new_count)
+}
+
+/// HTMX: Mark everything up to the latest (non-upcoming) transaction as reviewed
+pub async fn htmx_transactions_mark_reviewed(state: axum::extract::State<AppState>) -> impl axum::response::IntoResponse {
+    let ledger = state.ledger.read().await;
+    let as_of = ledger.as_of_date();
+    let latest = ledger.transactions(10000, 0)
+        .into_iter()
+        .filter(|t| !t.is_upcoming(as_of))
+        .map(|t| t.date)
+        .max()
+        .unwrap_or_else(|| chrono::Local::now().date_naive().to_string());
+    let cookie = format!("{}={}; Path=/; Max-Age=31536000; SameSite=Lax", REVIEWED_COOKIE, latest);
+    (
+        [
+            (axum::http::header::SET_COOKIE, cookie),
+            (axum::http::HeaderName::from_static("hx-trigger"), REVIEWED_UPDATED_EVENT.to_string()),
+        ],
+        String::new(),
+    )
 }
 
 /// HTMX: Transaction detail - Returns expanded detail view
@@ -286,505 +459,214 @@ pub async fn htmx_transaction_detail(
     let transaction = ledger.transaction(&transaction_id);
 
     match transaction {
-        Some(tx) => super::page::render_transaction_detail(&tx),
-        None => r#"<div class='text-center py-8 text-red-500'>未找到交易记录</div>"#.to_string(),
+        Some(tx) => {
+            let linked: Vec<beanweb_core::LinkGroup> = tx.links.iter().map(|link| ledger.link_group(link)).collect();
+            transactions::detail(&tx, &linked)
+        }
+        None => transactions::not_found(),
     }
 }
 
-// NOTE: 编辑功能已禁用
-// /// HTMX: Get edit form (supports mode switching via query param)
-// pub async fn htmx_transaction_edit_form(
-//     state: axum::extract::State<AppState>,
-//     path: axum::extract::Path<String>,
-//     query: axum::extract::Query<std::collections::HashMap<String, String>>,
-// ) -> String {
-//     let ledger = state.ledger.read().await;
-//     let transaction_id = path.0;
-//     let transaction = ledger.transaction(&transaction_id);
-//
-//     match transaction {
-//         Some(tx) => {
-//             let mode = query.0.get("mode").map(|s| s.as_str()).unwrap_or("form");
-//             let text_content = super::page::generate_transaction_text(&tx);
-//
-//             match mode {
-//                 "text" => {
-//                     format!(
-//                         r#"<form hx-put='/transactions/{}' hx-target='#edit-result' hx-swap='innerHTML'>
-//                             <div class='mb-4'>
-//                                 <label class='block text-sm font-medium text-gray-700 mb-2'>交易内容 (Beancount 格式)</label>
-//                                 <textarea name="content" class="w-full h-64 font-mono text-sm p-4 border rounded-lg focus:ring-2 focus:ring-indigo-500" placeholder="输入交易记录...">{}</textarea>
-//                             </div>
-//                             <div class='flex items-center gap-4'>
-//                                 <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存更改</button>
-//                                 <button type='button' onclick="switchEditMode('form', '{}')" class='px-4 py-2 border rounded-lg hover:bg-gray-50'>切换到 UI表单模式</button>
-//                                 <button type='button' onclick='closeEditModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
-//                             </div>
-//                         </form>
-//                         <div id='edit-result' class='mt-4'></div>"#,
-//                         transaction_id, text_content, transaction_id
-//                     )
-//                 }
-//                 _ => {
-//                     let form_html = super::page::render_edit_form(&tx);
-//                     format!(
-//                         r#"<form hx-put='/transactions/{}' hx-target='#edit-result' hx-swap='innerHTML'>
-//                             {}
-//                             <div class='flex items-center gap-4 mt-6 pt-4 border-t'>
-//                                 <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存更改</button>
-//                                 <button type='button' onclick='closeEditModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
-//                             </div>
-//                         </form>
-//                         <div id='edit-result' class='mt-4'></div>"#,
-//                         transaction_id, form_html
-//                     )
-//                 }
-//             }
-//         }
-//         None => r#"<div class='text-red-500 p-4'>未找到交易记录</div>"#.to_string(),
-//     }
-// }
-
-/// Parse transaction ID to extract line number
-/// Format: txn-{source}:{line}:{hash} or legacy txn-{line}
-fn parse_txn_id(transaction_id: &str) -> (Option<String>, usize) {
-    // Try new format: txn-{source}:{line}:{hash}
-    if let Some(after_txn) = transaction_id.strip_prefix("txn-") {
-        let parts: Vec<&str> = after_txn.rsplitn(2, ':').collect();
-        if parts.len() >= 2 {
-            // parts[0] = hash, parts[1] = line:source (need to split by : again)
-            if let Some((source_part, line_str)) = parts[1].rsplit_once(':') {
-                if let Ok(line) = line_str.parse::<usize>() {
-                    // Restore source from parts[2..]
-                    let source = if parts.len() > 2 {
-                        let source_rest: String = parts[2..].iter().rev().map(|s| *s).collect::<Vec<&str>>().join(":");
-                        Some(source_rest.replace("-", "/"))
-                    } else {
-                        None
-                    };
-                    return (source, line);
-                }
-            }
+/// Red "保存失败" box of the edit form
+fn update_error(message: &str) -> String {
+    transactions::failure("保存失败", message)
+}
+
+/// HTMX: Get edit form (supports mode switching via query param)
+/// Text mode edits the directive as written in the file, metadata and comments included
+pub async fn htmx_transaction_edit_form(
+    state: axum::extract::State<AppState>,
+    path: axum::extract::Path<String>,
+    query: Query<HashMap<String, String>>,
+) -> String {
+    let ledger = state.ledger.read().await;
+    let transaction_id = path.0;
+    let Some(tx) = ledger.transaction(&transaction_id) else {
+        return transactions::edit_not_found();
+    };
+    let source = match ledger.transaction_source(&transaction_id).await {
+        Ok(source) => source,
+        Err(e) => return update_error(&e.to_string()),
+    };
+    match query.0.get("mode").map(|s| s.as_str()).unwrap_or("form") {
+        "text" => transactions::edit_text_form(&transaction_id, &source),
+        _ => {
+            let accounts: Vec<String> = ledger.accounts().into_iter().map(|a| a.name).collect();
+            transactions::edit_form(&tx, &accounts)
         }
     }
+}
 
-    // Legacy format: txn-{line}
-    let line_number: usize = transaction_id
-        .strip_prefix("txn-")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(0);
+/// Beancount text of a submitted edit form; metadata lines of the original are kept
+fn transaction_text_from_form(params: &HashMap<String, String>, original: &str) -> Result<String, String> {
+    let field = |name: &str| params.get(name).map(|s| s.trim().to_string()).unwrap_or_default();
+    let quote = |text: String| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
 
-    (None, line_number)
-}
+    let date = field("date");
+    if date.is_empty() {
+        return Err("日期不能为空".to_string());
+    }
+    let mut header = vec![date];
+    let flag = field("flag");
+    if !flag.is_empty() {
+        header.push(flag);
+    }
+    let (payee, narration) = (field("payee"), field("narration"));
+    if !payee.is_empty() {
+        header.push(quote(payee));
+    }
+    // Beancount reads a single string as the narration
+    header.push(quote(narration));
+    for tag in field("tags").split_whitespace() {
+        header.push(format!("#{}", tag.trim_start_matches('#')));
+    }
+    for link in field("links").split_whitespace() {
+        header.push(format!("^{}", link.trim_start_matches('^')));
+    }
 
-/// NOTE: 编辑功能已禁用
-// /// Handle transaction update (save)
-// pub async fn htmx_transaction_update(
-//     state: axum::extract::State<AppState>,
-//     path: axum::extract::Path<String>,
-//     body: String,
-// ) -> String {
-//     let transaction_id = path.0;
-//
-//     // Parse transaction ID to get source file and line number
-//     let (source_from_id, line_number) = parse_txn_id(&transaction_id);
-//
-//     // Get transaction to find its source file
-//     let ledger = state.ledger.read().await;
-//     let transaction = ledger.transaction(&transaction_id);
-//
-//     if transaction.is_none() {
-//         return format!(
-//             r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>未找到交易记录: {}</p></div>"#,
-//             transaction_id
-//         );
-//     }
-//     let transaction = transaction.unwrap();
-//
-//     // Determine which file to modify - use transaction.source first, fall back to ID-based extraction
-//     let source_file = transaction.source.clone()
-//         .or(source_from_id)
-//         .unwrap_or_else(|| state.config.data.main_file.clone());
-//     let data_path = &state.config.data.path;
-//     let target_file_path = data_path.join(&source_file);
-//
-//     drop(ledger);
-//
-//     // Parse the form body - handle URL-encoded form data
-//     let mut new_content = String::new();
-//     let mut found_content = false;
-//
-//     // Debug: log first part of body
-//     let body_preview = if body.len() > 200 { &body[..200] } else { &body };
-//     eprintln!("[DEBUG] Transaction update - tx_id: {}, line: {}, source: {}", transaction_id, line_number, source_file);
-//
-//     // Parse form fields
-//     let mut params: HashMap<String, String> = HashMap::new();
-//     let mut has_content_field = false;
-//
-//     // Check if it's JSON format
-//     if body.starts_with('{') {
-//         // Try to parse as JSON
-//         if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
-//             for (key, value) in json.as_object().unwrap_or(&serde_json::Map::new()).iter() {
-//                 if let Some(s) = value.as_str() {
-//                     params.insert(key.clone(), s.to_string());
-//                     if key == "content" {
-//                         has_content_field = true;
-//                     }
-//                 }
-//             }
-//         }
-//     } else {
-//         // Parse as form-urlencoded
-//         for pair in body.split('&') {
-//             let parts: Vec<&str> = pair.split('=').collect();
-//             if parts.len() >= 2 {
-//                 let key = urlencoding::decode(parts[0]).unwrap_or_default().into_owned();
-//                 let value_raw = parts[1..].join("=");
-//                 let value = urlencoding::decode(&value_raw).unwrap_or_default().into_owned();
-//                 params.insert(key.clone(), value.clone());
-//                 if key == "content" {
-//                     has_content_field = true;
-//                 }
-//                 eprintln!("[DEBUG] Form field: key={}, value_len={}", key, value.len());
-//             }
-//         }
-//     }
-//
-//     // Build content from form fields
-//     if has_content_field {
-//         // Text mode - use content field directly
-//         if let Some(content) = params.get("content") {
-//             new_content = content.clone();
-//             found_content = true;
-//         }
-//     } else {
-//         // Form mode - build Beancount text from fields
-//         eprintln!("[DEBUG] Form mode detected, building text from fields...");
-//
-//         let date = params.get("date").cloned().unwrap_or_default();
-//         let flag = params.get("flag").cloned().unwrap_or_default();
-//         let payee = params.get("payee").cloned().unwrap_or_default();
-//         let narration = params.get("narration").cloned().unwrap_or_default();
-//         let tags_str = params.get("tags").cloned().unwrap_or_default();
-//         let links_str = params.get("links").cloned().unwrap_or_default();
-//
-//         // Build header: DATE FLAG "payee" "narration" #tags ^links
-//         let mut header_parts = Vec::new();
-//         header_parts.push(date.clone());
-//         if !flag.is_empty() {
-//             header_parts.push(flag);
-//         }
-//         if !payee.is_empty() {
-//             header_parts.push(format!("\"{}\"", payee));
-//         }
-//         if !narration.is_empty() {
-//             header_parts.push(format!("\"{}\"", narration));
-//         }
-//         // Add tags
-//         for tag in tags_str.split_whitespace().filter(|t| t.starts_with('#')) {
-//             header_parts.push(tag.to_string());
-//         }
-//         // Add links
-//         for link in links_str.split_whitespace().filter(|l| l.starts_with('^')) {
-//             header_parts.push(link.to_string());
-//         }
-//
-//         let header = header_parts.join(" ");
-//         let mut lines = Vec::new();
-//         lines.push(header);
-//
-//         // Parse postings
-//         let mut posting_accounts: Vec<String> = Vec::new();
-//         let mut posting_amounts: Vec<String> = Vec::new();
-//
-//         for (key, value) in &params {
-//             if key.starts_with("posting_") && key.ends_with("_account") {
-//                 if !value.is_empty() {
-//                     posting_accounts.push(value.clone());
-//                 }
-//             } else if key.starts_with("posting_") && key.ends_with("_amount") {
-//                 if !value.is_empty() {
-//                     posting_amounts.push(value.clone());
-//                 }
-//             }
-//         }
-//
-//         // Build posting lines
-//         for (i, account) in posting_accounts.iter().enumerate() {
-//             let amount = posting_amounts.get(i).cloned().unwrap_or_default();
-//             let posting_line = if amount.is_empty() {
-//                 format!("    {}", account)
-//             } else {
-//                 format!("    {} {}", account, amount)
-//             };
-//             lines.push(posting_line);
-//         }
-//
-//         if !lines.is_empty() && lines.iter().any(|l| !l.trim().is_empty()) {
-//             new_content = lines.join("\n");
-//             found_content = true;
-//             eprintln!("[DEBUG] Built content from form, {} lines", lines.len());
-//         }
-//     }
-//
-//     if !found_content || new_content.trim().is_empty() {
-//         return format!(
-//             r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>交易内容不能为空</p></div>"#,
-//             has_content_field,
-//             new_content.len()
-//         );
-//     }
-//
-//     // Read the original file (use the source file)
-//     match std::fs::read_to_string(&target_file_path) {
-//         Ok(original_content) => {
-//             // Split into lines and find the transaction
-//             let mut lines: Vec<&str> = original_content.lines().collect();
-//             let total_lines = lines.len();
-//
-//             // Find the transaction by line number
-//             let line_index = line_number.saturating_sub(1);
-//
-//             if line_index >= total_lines {
-//                 return format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>行号超出范围</p></div>"#);
-//             }
-//
-//             // Find and replace transaction
-//             let new_lines: Vec<&str> = new_content.lines().collect();
-//             lines.splice(line_index..=line_index, new_lines.iter().copied());
-//
-//             let new_file_content = lines.join("\n");
-//             match std::fs::write(&target_file_path, new_file_content) {
-//                 Ok(_) => {
-//                     let mut ledger = state.ledger.write().await;
-//                     let _ = ledger.reload();
-//
-//                     format!(r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>交易已更新</span></div></div>"#)
-//                 }
-//                 Err(e) => {
-//                     format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>写入文件失败: {}</p></div>"#, e)
-//                 }
-//             }
-//         }
-//         Err(e) => {
-//             format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>无法读取文件: {}</p></div>"#, e)
-//         }
-//     }
-// }
+    // Rows keep the order they were added in, whatever the field order of the body
+    let mut rows: Vec<(usize, String, String)> = params.iter()
+        .filter_map(|(key, account)| {
+            let index = key.strip_prefix("posting_")?.strip_suffix("_account")?.parse().ok()?;
+            let amount = params.get(&format!("posting_{}_amount", index)).cloned().unwrap_or_default();
+            Some((index, account.trim().to_string(), amount.trim().to_string()))
+        })
+        .filter(|(_, account, _)| !account.is_empty())
+        .collect();
+    rows.sort_by_key(|(index, _, _)| *index);
+    if rows.is_empty() {
+        return Err("请至少添加一个分录".to_string());
+    }
 
-/// Render create form (text or form mode)
-pub async fn htmx_transaction_create_form(
+    let mut lines = vec![header.join(" ")];
+    for (_, account, amount) in rows {
+        lines.push(if amount.is_empty() { format!("    {}", account) } else { format!("    {}  {}", account, amount) });
+    }
+    Ok(beanweb_core::edit::keep_metadata(original, &lines.join("\n")))
+}
+
+/// Handle transaction update (save): the directive's lines are replaced in its
+/// file once the new text balances, then the ledger is reloaded
+pub async fn htmx_transaction_update(
     state: axum::extract::State<AppState>,
-    query: Query<HashMap<String, String>>,
+    path: axum::extract::Path<String>,
+    body: String,
 ) -> String {
-    let mode = query.0.get("mode").map(|s| s.as_str()).unwrap_or("form");
-
-    match mode {
-        "text" => {
-            let today = chrono::Local::today().format("%Y-%m-%d").to_string();
-            format!(
-                r#"<form hx-post='/transactions' hx-target='#create-result' hx-swap='innerHTML'>
-                    <div class='mb-4'>
-                        <label class='block text-sm font-medium text-gray-700 mb-2'>交易内容 (Beancount 格式)</label>
-                        <textarea name="content" class="w-full h-64 font-mono text-sm p-4 border rounded-lg focus:ring-2 focus:ring-indigo-500" placeholder="输入交易记录...">{}  * "交易对象" "摘要"
-    Assets:账户    -100.00 CNY
-    Expenses:类别    100.00 CNY</textarea>
-                    </div>
-                    <div class='flex items-center gap-4'>
-                        <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存</button>
-                        <button type='button' onclick='closeCreateModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
-                    </div>
-                </form>
-                <div id='create-result' class='mt-4'></div>"#,
-                today
-            )
-        }
-        _ => {
-            let today = chrono::Local::today().format("%Y-%m-%d").to_string();
-            let accounts_json = {
-                let ledger = state.ledger.read().await;
-                let accounts = ledger.accounts();
-                let json = serde_json::to_string(&accounts).unwrap_or_default();
-                json.replace("\"", "&quot;").replace("'", "&#39;")
-            };
-            format!(
-                r#"<form hx-post='/transactions' hx-target='#create-result' hx-swap='innerHTML'>
-                    <div class='space-y-6'>
-                        <div class='grid grid-cols-3 gap-4'>
-                            <div>
-                                <label class='block text-sm font-medium text-gray-700 mb-1'>日期</label>
-                                <input type='date' name='date' value='{}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
-                            </div>
-                            <div>
-                                <label class='block text-sm font-medium text-gray-700 mb-1'>标记</label>
-                                <select name='flag' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
-                                    <option value='*'>已确认 (*)</option>
-                                    <option value='!'>待确认 (!)</option>
-                                    <option value=''>无标记</option>
-                                </select>
-                            </div>
-                        </div>
-                        <div>
-                            <label class='block text-sm font-medium text-gray-700 mb-1'>交易对象</label>
-                            <input type='text' name='payee' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易对象'>
-                        </div>
-                        <div>
-                            <label class='block text-sm font-medium text-gray-700 mb-1'>摘要</label>
-                            <input type='text' name='narration' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易摘要'>
-                        </div>
-                        <div>
-                            <label class='block text-sm font-medium text-gray-700 mb-2'>分录</label>
-                            <div class='border rounded-lg p-3 bg-gray-50' id='postings-container'>
-                                <div class='flex items-center gap-2 mb-2'>
-                                    <div class='relative flex-1'>
-                                        <input type='text' name='posting_0_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8' placeholder='搜索账户...'
-                                            oninput='filterAccount(this, "account-list-0")' onfocus='showAccountList(this, "account-list-0")' onblur='hideAccountListDelayed(this, "account-list-0")'>
-                                        <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
-                                            <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
-                                        </svg>
-                                        <div id='account-list-0' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
-                                    </div>
-                                    <input type='text' name='posting_0_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额'>
-                                </div>
-                                <div class='flex items-center gap-2 mb-2'>
-                                    <div class='relative flex-1'>
-                                        <input type='text' name='posting_1_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8' placeholder='搜索账户...'
-                                            oninput='filterAccount(this, "account-list-1")' onfocus='showAccountList(this, "account-list-1")' onblur='hideAccountListDelayed(this, "account-list-1")'>
-                                        <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
-                                            <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
-                                        </svg>
-                                        <div id='account-list-1' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
-                                    </div>
-                                    <input type='text' name='posting_1_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额'>
-                                </div>
-                            </div>
-                            <button type='button' onclick='addPosting()' class='mt-2 px-4 py-2 text-sm text-indigo-600 hover:text-indigo-800 hover:bg-indigo-50 rounded-lg border border-indigo-200'>+ 添加分录</button>
-                        </div>
-                        <div class='grid grid-cols-2 gap-4'>
-                            <div>
-                                <label class='block text-sm font-medium text-gray-700 mb-1'>标签 (Tags)</label>
-                                <input type='text' name='tags' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='#tag1 #tag2' oninput='updatePreview()'>
-                            </div>
-                            <div>
-                                <label class='block text-sm font-medium text-gray-700 mb-1'>链接 (Links)</label>
-                                <input type='text' name='links' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='^link1 ^link2' oninput='updatePreview()'>
-                            </div>
-                        </div>
-                    </div>
-                    <div class='border-t pt-4'>
-                        <label class='block text-sm font-medium text-gray-700 mb-2'>交易预览</label>
-                        <pre id='tx-preview' class='bg-gray-900 text-green-400 p-4 rounded-lg text-sm font-mono overflow-x-auto'>2026-01-31 *
-    Assets:Account
-    Expenses:Category</pre>
-                    </div>
-                    <div class='flex items-center gap-4 mt-6 pt-4 border-t'>
-                        <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存</button>
-                        <button type='button' onclick='closeCreateModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
-                    </div>
-                </form>
-                <div id='create-result' class='mt-4'></div>
-                <div id='accounts-data' data-accounts='{}' class='hidden'></div>
-                <script>
-                    let postingCount = 2;
-                    function addPosting() {{
-                        const container = document.getElementById('postings-container');
-                        if (container) {{
-                            const html = `<div class='flex items-center gap-2 mb-2'>
-                                <div class='relative flex-1'>
-                                    <input type='text' name='posting_` + postingCount + `_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8' placeholder='搜索账户...'
-                                        oninput='filterAccount(this, "account-list-` + postingCount + `")' onfocus='showAccountList(this, "account-list-` + postingCount + `")' onblur='hideAccountListDelayed(this, "account-list-` + postingCount + `")'>
-                                    <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
-                                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
-                                    </svg>
-                                    <div id='account-list-` + postingCount + `' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
-                                </div>
-                                <input type='text' name='posting_` + postingCount + `_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额'>
-                                <button type='button' onclick='this.parentElement.remove()' class='px-3 py-2 text-red-500 hover:text-red-700 hover:bg-red-50 rounded-lg'>删除</button>
-                            </div>`;
-                            container.insertAdjacentHTML('beforeend', html);
-                            postingCount++;
-                        }}
-                    }}
-                    function showAccountList(input, listId) {{ filterAccount(input, listId); }}
-                    function hideAccountListDelayed(input, listId) {{
-                        setTimeout(() => {{
-                            const list = document.getElementById(listId);
-                            if (list) {{ list.classList.add('hidden'); }}
-                        }}, 200);
-                    }}
-                    function filterAccount(input, listId) {{
-                        const q = input.value.toLowerCase();
-                        const list = document.getElementById(listId);
-                        if (!list) return;
-                        const dataDiv = document.getElementById('accounts-data');
-                        let accounts = [];
-                        if (dataDiv && dataDiv.dataset.accounts) {{
-                            try {{ accounts = JSON.parse(dataDiv.dataset.accounts); }} catch(e) {{ accounts = []; }}
-                        }}
-                        const filtered = accounts.filter(a => a.name.toLowerCase().includes(q));
-                        if (filtered.length === 0 || q.length === 0) {{ list.classList.add('hidden'); return; }}
-                        let html = '';
-                        filtered.slice(0, 10).forEach(a => {{
-                            const parts = a.name.split(':');
-                            const shortName = parts[parts.length - 1];
-                            html += `<div class='px-3 py-2 hover:bg-indigo-50 cursor-pointer text-sm border-b last:border-0' data-account='${{a.name}}' onclick='selectAccount(this, "` + listId + `")'>
-                                <div class='font-medium'>${{shortName}}</div>
-                                <div class='text-xs text-gray-400'>${{a.name}}</div>
-                            </div>`;
-                        }});
-                        list.innerHTML = html;
-                        list.classList.remove('hidden');
-                    }}
-                    function selectAccount(item, listId) {{
-                        const list = document.getElementById(listId);
-                        const parent = list.parentElement;
-                        const input = parent.querySelector('input[type="text"]');
-                        const fullAccount = item.getAttribute('data-account');
-                        if (input && fullAccount) {{ input.value = fullAccount; }}
-                        list.classList.add('hidden');
-                        updatePreview();
-                    }}
-                    function updatePreview() {{
-                        const date = document.querySelector('input[name="date"]')?.value || new Date().toISOString().split('T')[0];
-                        const flag = document.querySelector('select[name="flag"]')?.value || '*';
-                        const flagStr = flag ? flag + ' ' : '';
-                        const payee = document.querySelector('input[name="payee"]')?.value || '';
-                        const narration = document.querySelector('input[name="narration"]')?.value || '';
-                        const tags = document.querySelector('input[name="tags"]')?.value || '';
-                        const links = document.querySelector('input[name="links"]')?.value || '';
-                        let firstLine = date + ' ' + flagStr;
-                        if (payee && narration) {{ firstLine += '"' + payee + '" "' + narration + '"'; }}
-                        else if (payee) {{ firstLine += '"' + payee + '"'; }}
-                        else if (narration) {{ firstLine += '"' + narration + '"'; }}
-                        const extras = [];
-                        tags.split(/\s+/).filter(t => t.starts_with('#')).forEach(t => extras.push(t));
-                        links.split(/\s+/).filter(l => l.starts_with('^')).forEach(l => extras.push(l));
-                        if (extras.length > 0) {{ firstLine += ' ' + extras.join(' '); }}
-                        let postings = [];
-                        document.querySelectorAll('[name^="posting_"][name$="_account"]').forEach(input => {{
-                            const name = input.name;
-                            const amountInput = document.querySelector('input[name="' + name.replace('_account', '_amount') + '"]');
-                            const account = input.value;
-                            const amount = amountInput?.value || '';
-                            if (account) {{ postings.push('    ' + account + (amount ? ' ' + amount : '')); }}
-                        }});
-                        const preview = document.getElementById('tx-preview');
-                        if (preview) {{ preview.textContent = firstLine + '\\n' + (postings.length > 0 ? postings.join('\\n') : '    ...'); }}
-                    }}
-                </script>"#,
-                today, accounts_json
-            )
+    let transaction_id = path.0;
+    let params = parse_form_body(&body);
+
+    let text = match params.get("content") {
+        Some(content) if params.get("mode").is_none_or(|m| m == "text") => Ok(content.trim_end().to_string()),
+        _ => match state.ledger.read().await.transaction_source(&transaction_id).await {
+            Ok(original) => transaction_text_from_form(&params, &original),
+            Err(e) => Err(e.to_string()),
+        },
+    };
+    let result = match text {
+        Ok(text) if text.trim().is_empty() => Err("交易内容不能为空".to_string()),
+        Ok(text) => state.service().update_transaction(&transaction_id, &text).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(update) => {
+            tracing::info!("Updated transaction at {}:{}", update.file.display(), update.line);
+            transactions::updated()
         }
+        Err(e) => update_error(&e),
     }
 }
 
-/// Store new transaction (write to file)
-pub async fn htmx_transaction_store(
+/// HTMX: Delete a transaction (confirmed in the browser) and refresh the list
+pub async fn htmx_transaction_delete(
     state: axum::extract::State<AppState>,
-    body: String,
+    path: axum::extract::Path<String>,
 ) -> String {
+    match delete_transaction(&state, &path.0).await {
+        Ok(_) => transactions::deleted(),
+        Err(e) => transactions::failure("删除失败", &e.to_string()),
+    }
+}
+
+/// Receipt file uploaded together with a new transaction
+struct UploadedDocument {
+    file_name: String,
+    bytes: bytes::Bytes,
+}
+
+/// File types accepted as transaction documents
+pub(crate) const DOCUMENT_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "heic", "pdf"];
+
+/// Largest accepted receipt upload
+pub const MAX_DOCUMENT_UPLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+/// Split an amount field into (value, currency); the number part may be an
+/// expression such as `12.5*3` (evaluated as the parser does amounts in the
+/// ledger), rounded to cents. Empty input yields 0.
+fn parse_amount_input(input: &str) -> Result<(beanweb_core::Decimal, String), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok((beanweb_core::Decimal::ZERO, String::new()));
+    }
+    let (expr, currency) = match input.rsplit_once(char::is_whitespace) {
+        Some((expr, last)) if last.starts_with(|c: char| c.is_ascii_uppercase()) => (expr.trim(), last.to_string()),
+        _ => (input, String::new()),
+    };
+    if let Ok(value) = expr.parse::<beanweb_core::Decimal>() {
+        return Ok((value, currency));
+    }
+    let value = beanweb_core::SimpleBeancountParser::parse_number(expr)
+        .ok_or_else(|| format!("无法计算 '{}'", expr))?;
+    Ok((value.round_dp(2), currency))
+}
+
+/// Currencies offered in the create form: the default currency first, then the ones
+/// in use (most used first, likely typos left out), then the remaining declared commodities
+fn currency_choices(ledger: &beanweb_core::Ledger, default_currency: &str) -> Vec<String> {
+    let mut choices = vec![default_currency.to_string()];
+    for usage in ledger.currencies_in_use() {
+        if !usage.is_suspicious() && !choices.contains(&usage.currency) {
+            choices.push(usage.currency);
+        }
+    }
+    let mut declared: Vec<String> = ledger.commodities().into_iter().map(|c| c.name).collect();
+    declared.sort();
+    for name in declared {
+        if !choices.contains(&name) {
+            choices.push(name);
+        }
+    }
+    choices
+}
+
+/// API: Evaluate an amount field (`?input=12.5*3 CNY`) for the create form
+pub async fn api_evaluate_amount(query: Query<HashMap<String, String>>) -> String {
+    let input = query.get("input").map(|s| s.as_str()).unwrap_or("");
+    let result = match parse_amount_input(input) {
+        Ok((amount, currency)) => {
+            let value = if currency.is_empty() { amount.to_string() } else { format!("{} {}", amount, currency) };
+            serde_json::json!({ "ok": true, "value": value, "amount": amount.to_string(), "currency": currency })
+        }
+        Err(error) => serde_json::json!({ "ok": false, "error": error }),
+    };
+    serde_json::to_string(&result).unwrap_or_default()
+}
+
+/// Normalize the optional time field (`HH:MM` or `HH:MM:SS`, as sent by a
+/// time input) to `HH:MM:SS`; None when left empty
+fn parse_time_input(input: &str) -> Result<Option<String>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    chrono::NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| chrono::NaiveTime::parse_from_str(input, "%H:%M"))
+        .map(|time| Some(time.format("%H:%M:%S").to_string()))
+        .map_err(|_| format!("无法识别的时间: {}（应为 HH:MM 或 HH:MM:SS）", input))
+}
+
+/// Parse an application/x-www-form-urlencoded body
+fn parse_form_body(body: &str) -> HashMap<String, String> {
     let mut params: HashMap<String, String> = HashMap::new();
     for pair in body.split('&') {
         let parts: Vec<&str> = pair.split('=').collect();
@@ -794,6 +676,138 @@ pub async fn htmx_transaction_store(
             params.insert(key, value);
         }
     }
+    params
+}
+
+/// Read a multipart form: text fields plus an optional `document` file
+async fn read_multipart_form(request: axum::extract::Request) -> Result<(HashMap<String, String>, Option<UploadedDocument>), String> {
+    let mut multipart = <axum::extract::Multipart as axum::extract::FromRequest<()>>::from_request(request, &())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut params = HashMap::new();
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await.map_err(|e| e.to_string())? {
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field.file_name().map(|s| s.to_string());
+        match file_name {
+            Some(file_name) if name == "document" => {
+                let bytes = field.bytes().await.map_err(|e| e.to_string())?;
+                if !file_name.is_empty() && !bytes.is_empty() {
+                    upload = Some(UploadedDocument { file_name, bytes });
+                }
+            }
+            _ => {
+                let value = field.text().await.map_err(|e| e.to_string())?;
+                params.insert(name, value);
+            }
+        }
+    }
+    Ok((params, upload))
+}
+
+/// Where a receipt goes: `<documents_dir>/<date>-<payee>.<ext>`, numbered
+/// rather than overwriting an existing one. Returns the file to write and
+/// its path relative to the data directory (the value of `document:` metadata)
+fn document_target(config: &beanweb_config::Config, date: &str, name: &str, upload: &UploadedDocument) -> Result<(std::path::PathBuf, String), String> {
+    let ext = std::path::Path::new(&upload.file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .filter(|e| DOCUMENT_EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| format!("不支持的文件类型: {}（支持 {}）", upload.file_name, DOCUMENT_EXTENSIONS.join(", ")))?;
+
+    let slug: String = name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let stem = if slug.is_empty() { date.to_string() } else { format!("{}-{}", date, slug) };
+
+    let dir = config.data.path.join(&config.data.documents_dir);
+    let mut file_name = format!("{}.{}", stem, ext);
+    let mut counter = 2;
+    while dir.join(&file_name).exists() {
+        file_name = format!("{}-{}.{}", stem, counter, ext);
+        counter += 1;
+    }
+    Ok((dir.join(&file_name), format!("{}/{}", config.data.documents_dir.trim_end_matches('/'), file_name)))
+}
+
+/// Write a receipt to the file picked by [`document_target`], never
+/// replacing one that appeared since
+fn save_document(target: &std::path::Path, upload: &UploadedDocument) -> Result<(), String> {
+    use std::io::Write;
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("无法创建目录 {}: {}", dir.display(), e))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)
+        .map_err(|e| format!("无法写入文件: {}", e))?;
+    file.write_all(&upload.bytes).map_err(|e| format!("无法写入文件: {}", e))
+}
+
+/// Render create form (text or form mode)
+pub async fn htmx_transaction_create_form(
+    state: axum::extract::State<AppState>,
+    query: Query<HashMap<String, String>>,
+) -> String {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    match query.0.get("mode").map(|s| s.as_str()).unwrap_or("form") {
+        "text" => transactions::create_text_form(&crate::idempotency::form_field(), &today),
+        _ => {
+            let ledger = state.ledger.read().await;
+            let templates = ledger.transaction_templates().unwrap_or_else(|e| {
+                tracing::error!("Failed to load transaction templates: {}", e);
+                Vec::new()
+            });
+            let currencies = currency_choices(&ledger, &ledger.report_currency());
+            transactions::create_form(&crate::idempotency::form_field(), &today, &ledger.accounts(), &currencies, &templates)
+        }
+    }
+}
+
+/// Store new transaction (write to file)
+/// Accepts a url-encoded form, or multipart when a receipt is attached
+pub async fn htmx_transaction_store(
+    state: axum::extract::State<AppState>,
+    request: axum::extract::Request,
+) -> String {
+    let headers = request.headers().clone();
+    let is_multipart = request.headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+
+    let (params, upload) = if is_multipart {
+        match read_multipart_form(request).await {
+            Ok(form) => form,
+            Err(e) => return transactions::failure("保存失败", &format!("无法读取上传内容: {}", e)),
+        }
+    } else {
+        let body = <String as axum::extract::FromRequest<()>>::from_request(request, &()).await.unwrap_or_default();
+        (parse_form_body(&body), None)
+    };
+
+    // A repeated key gets the first response back instead of a second copy
+    let slot = match crate::idempotency::request_key(&headers, &params) {
+        Some(key) => Some(state.idempotency.slot(&crate::idempotency::request_scope(&state, &headers).await, &key)),
+        None => None,
+    };
+    let mut remembered = match &slot {
+        Some(slot) => {
+            let remembered = slot.lock().await;
+            if let Some(response) = remembered.get() {
+                return response;
+            }
+            Some(remembered)
+        }
+        None => None,
+    };
 
     let default_date = chrono::Local::today().format("%Y-%m-%d").to_string();
     let date = params.get("date").unwrap_or(&default_date).clone();
@@ -802,247 +816,139 @@ pub async fn htmx_transaction_store(
     let narration = params.get("narration").unwrap_or(&String::new()).clone();
     let tags_str = params.get("tags").unwrap_or(&String::new()).clone();
     let links_str = params.get("links").unwrap_or(&String::new()).clone();
-
-    let flag_str = if flag.is_empty() { String::new() } else { format!("{} ", flag) };
-    let tags: Vec<&str> = tags_str.split_whitespace().filter(|s| s.starts_with('#')).collect();
-    let links: Vec<&str> = links_str.split_whitespace().filter(|s| s.starts_with('^')).collect();
-
-    let mut first_line = if payee.is_empty() && narration.is_empty() {
-        String::new()
-    } else if payee.is_empty() {
-        format!("\"{}\"", narration)
-    } else if narration.is_empty() {
-        format!("\"{}\"", payee)
-    } else {
-        format!("\"{}\" \"{}\"", payee, narration)
+    // Written as `time:` metadata, which orders the transaction within its day
+    let time = match parse_time_input(params.get("time").map(|s| s.as_str()).unwrap_or("")) {
+        Ok(time) => time,
+        Err(e) => return transactions::failure("时间无效", &e),
     };
 
-    let mut extras = Vec::new();
-    for tag in &tags { extras.push(*tag); }
-    for link in &links { extras.push(*link); }
-    if !extras.is_empty() {
-        first_line.push(' ');
-        first_line.push_str(&extras.join(" "));
-    }
-
-    fn parse_amount(amount_str: &str) -> (f64, String) {
-        let parts: Vec<&str> = amount_str.split_whitespace().collect();
-        let num_str = parts.first().map(|s| *s).unwrap_or("");
-        let currency = if parts.len() > 1 { parts[1].to_string() } else { String::new() };
-        let amount = num_str.parse::<f64>().unwrap_or(0.0);
-        (amount, currency)
-    }
+    let tags: Vec<String> = tags_str.split_whitespace().filter_map(|s| s.strip_prefix('#')).map(str::to_string).collect();
+    let links: Vec<String> = links_str.split_whitespace().filter_map(|s| s.strip_prefix('^')).map(str::to_string).collect();
 
-    struct PostingData { account: String, amount: f64, currency: String }
+    struct PostingData { account: String, amount: beanweb_core::Decimal, currency: String }
     let mut postings_data: Vec<PostingData> = Vec::new();
     for (key, value) in &params {
         if key.starts_with("posting_") && key.ends_with("_account") {
-            let amount_key = format!("{}_amount", key.strip_suffix("_account").unwrap());
-            let amount_str = params.get(&amount_key).unwrap_or(&String::new()).clone();
+            let prefix = key.strip_suffix("_account").unwrap();
+            let amount_str = params.get(&format!("{}_amount", prefix)).unwrap_or(&String::new()).clone();
             if !value.is_empty() {
-                let (amount, currency) = parse_amount(&amount_str);
+                let (amount, mut currency) = match parse_amount_input(&amount_str) {
+                    Ok(parsed) => parsed,
+                    Err(e) => return transactions::failure("金额无效", &format!("{}: {}", value, e)),
+                };
+                // The currency dropdown only applies when the amount doesn't name one
+                if currency.is_empty() && !amount.is_zero() {
+                    currency = params.get(&format!("{}_currency", prefix)).cloned().unwrap_or_default();
+                }
                 postings_data.push(PostingData { account: value.clone(), amount, currency });
             }
         }
     }
 
-    let known_amounts: Vec<f64> = postings_data.iter().filter(|p| p.amount != 0.0).map(|p| p.amount).collect();
-    let total: f64 = known_amounts.iter().sum();
-    let has_zero_amount_postings = postings_data.iter().any(|p| p.amount == 0.0);
-
-    let final_postings: Vec<String>;
+    let known_amounts: Vec<beanweb_core::Decimal> = postings_data.iter().filter(|p| !p.amount.is_zero()).map(|p| p.amount).collect();
+    let total: beanweb_core::Decimal = known_amounts.iter().sum();
+    let has_zero_amount_postings = postings_data.iter().any(|p| p.amount.is_zero());
+
+    // Written through `beanweb_core::render`, which quotes and escapes the strings
+    let amount_text = |amount: beanweb_core::Decimal, currency: &str| if currency.is_empty() { format!("{:.2}", amount) } else { format!("{} {}", amount, currency) };
+    let posting = |account: &str, amount: String| beanweb_core::Posting {
+        account: account.to_string(),
+        amount,
+        units: None,
+        currency: String::new(),
+        cost: None,
+        cost_spec: None,
+        price: None,
+        price_spec: None,
+        balance: None,
+        metadata: serde_json::Value::Null,
+    };
+    let final_postings: Vec<beanweb_core::Posting>;
     if postings_data.is_empty() {
-        return r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>请至少添加一个分录</p></div>"#.to_string();
-    } else if total.abs() < 0.001 {
+        return transactions::failure("保存失败", "请至少添加一个分录");
+    } else if total.is_zero() {
         final_postings = postings_data.iter().map(|p| {
-            let amount_str = if p.amount == 0.0 { String::new() } else if p.currency.is_empty() { format!("{:.2}", p.amount) } else { format!("{} {}", p.amount, p.currency) };
-            format!("    {}{}", p.account, if amount_str.is_empty() { String::new() } else { format!(" {}", amount_str) })
+            posting(&p.account, if p.amount.is_zero() { String::new() } else { amount_text(p.amount, &p.currency) })
         }).collect();
     } else if known_amounts.len() == postings_data.len() - 1 && has_zero_amount_postings {
         let missing_amount = -total;
-        let currency = postings_data.iter().find(|p| p.amount != 0.0).map(|p| p.currency.clone()).unwrap_or_default();
+        let currency = postings_data.iter().find(|p| !p.amount.is_zero()).map(|p| p.currency.clone()).unwrap_or_default();
         final_postings = postings_data.iter().map(|p| {
-            let amount_str = if p.amount == 0.0 {
-                if currency.is_empty() { format!("{:.2}", missing_amount) } else { format!("{} {}", missing_amount, currency) }
-            } else if p.currency.is_empty() { format!("{:.2}", p.amount) } else { format!("{} {}", p.amount, p.currency) };
-            format!("    {}{}", p.account, if amount_str.is_empty() { String::new() } else { format!(" {}", amount_str) })
+            let amount = if p.amount.is_zero() { amount_text(missing_amount, &currency) } else { amount_text(p.amount, &p.currency) };
+            posting(&p.account, amount)
         }).collect();
     } else if known_amounts.len() == postings_data.len() {
-        return format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>金额不平衡</span></div><p class='text-sm text-red-600 mt-1'>分录金额总和不为 0，当前: {:.2}</p></div>"#, total);
+        return transactions::failure("金额不平衡", &format!("分录金额总和不为 0，当前: {:.2}", total));
     } else {
-        return r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>无法自动计算</span></div><p class='text-sm text-red-600 mt-1'>多个分录金额为空，请填写足够的金额使总和为 0</p></div>"#.to_string();
+        return transactions::failure("无法自动计算", "多个分录金额为空，请填写足够的金额使总和为 0");
     }
 
-    let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let metadata_line = format!("    created_at: \"{}\"", created_at);
-    let mut transaction_text = String::new();
-    transaction_text.push_str(&format!("{} {} {}", date, flag_str, first_line));
-    transaction_text.push('\n');
-    transaction_text.push_str(&final_postings.join("\n"));
-    transaction_text.push('\n');
-    transaction_text.push_str(&metadata_line);
-    transaction_text.push('\n');
-    transaction_text.push('\n');
-
-    let new_tx_file = &state.config.data.new_transaction_file;
-    let file_path = if new_tx_file.is_empty() || new_tx_file == "main.bean" {
-        state.config.data.path.join(&state.config.data.main_file)
-    } else if new_tx_file.starts_with("/") || new_tx_file.contains(":/") {
-        PathBuf::from(new_tx_file)
-    } else {
-        state.config.data.path.join(new_tx_file)
-    };
-
-    match std::fs::OpenOptions::new().append(true).open(&file_path) {
-        Ok(mut file) => {
-            if let Err(e) = file.write_all(transaction_text.as_bytes()) {
-                return format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>错误: {}</p></div>"#, e);
-            }
-
-            // Trigger ledger reload directly
-            let mut ledger = state.ledger.write().await;
-            if let Err(e) = ledger.reload().await {
-                eprintln!("[ERROR] Failed to reload ledger after creating transaction: {}", e);
-            }
-
-            format!(r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>交易已创建</span></div><p class='text-sm text-green-600 mt-1'>账本已重新加载</p><script>closeCreateModal(); const txContent = document.getElementById('transactions-content'); if (txContent) {{ htmx.ajax('GET', '/transactions/list?limit=50', {{target: txContent}}); }} else {{ setTimeout(() => window.location.reload(), 300); }}</script></div>"#)
-        }
-        Err(e) => {
-            format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败</span></div><p class='text-sm text-red-600 mt-1'>无法打开文件: {}</p></div>"#, e)
-        }
+    // Postings to accounts not opened yet or already closed on that date would fail to load
+    let accounts: Vec<String> = postings_data.iter().map(|p| p.account.clone()).collect();
+    let inactive = state.ledger.read().await.inactive_accounts_on(&date, &accounts);
+    if !inactive.is_empty() {
+        return transactions::inactive_accounts(&date, &inactive);
     }
-}
 
-/// Calculate transaction amount for display
-fn calculate_tx_amount(tx: &beanweb_core::Transaction) -> (String, String, String) {
-    fn is_expenses_account(account: &str) -> bool {
-        account.starts_with("Expenses:") || account.starts_with("expenses:")
-    }
-    fn is_income_account(account: &str) -> bool {
-        account.starts_with("Income:") || account.starts_with("income:")
-    }
-    fn is_assets_account(account: &str) -> bool {
-        account.starts_with("Assets:") || account.starts_with("assets:")
-    }
-
-    let mut expenses_total: f64 = 0.0;
-    let mut income_total: f64 = 0.0;
-    let mut assets_total: f64 = 0.0;
-    let mut liabilities_total: f64 = 0.0;
-    let mut common_currency = String::new();
-    let mut has_unknown_amount = false;
-
-    // First pass: calculate known amounts
-    for p in &tx.postings {
-        if !p.amount.is_empty() {
-            // Use total_value to handle @ price syntax (e.g., 800 PI @ 1 CNY)
-            let (amount, currency) = p.total_value("CNY");
-            if common_currency.is_empty() && !currency.is_empty() {
-                common_currency = currency;
-            }
-            if is_expenses_account(&p.account) {
-                expenses_total += amount;
-            } else if is_income_account(&p.account) {
-                income_total += amount;
-            } else if is_assets_account(&p.account) {
-                assets_total += amount;
-            } else if p.account.starts_with("Liabilities:") || p.account.starts_with("liabilities:") {
-                liabilities_total += amount;
-            }
-        } else {
-            // Posting without amount - this is the balancing posting
-            has_unknown_amount = true;
+    // Same date, amount and accounts as an existing transaction: ask before saving
+    if params.get("allow_duplicate").is_none_or(|v| v != "1") {
+        let amount = postings_data.iter().map(|p| p.amount.abs()).max().unwrap_or_default().round_dp(2);
+        let similar = state.ledger.read().await.find_similar_transactions(&date, amount, &accounts);
+        if !similar.is_empty() {
+            return transactions::duplicate_warning(&similar);
         }
     }
 
-    // If there's a balancing posting (no amount), calculate the missing amount
-    let display_amount: f64;
-    let amount_color: String;
-    let display_currency = common_currency;
-
-    if has_unknown_amount {
-        // The transaction has a balancing posting without amount
-        // Calculate what the missing amount should be to balance the transaction
-        let known_total = expenses_total + income_total + assets_total + liabilities_total;
-        let balancing_amount = -known_total;
-
-        // Find the balancing posting's account
-        let balancing_account = tx.postings.iter()
-            .find(|p| p.amount.is_empty())
-            .map(|p| p.account.clone())
-            .unwrap_or_default();
-
-        // Determine which account type the balancing posting belongs to
-        if is_expenses_account(&balancing_account) {
-            expenses_total = balancing_amount;
-        } else if is_income_account(&balancing_account) {
-            income_total = balancing_amount;
-        } else if is_assets_account(&balancing_account) {
-            assets_total = balancing_amount;
-        } else if balancing_account.starts_with("Liabilities:") || balancing_account.starts_with("liabilities:") {
-            liabilities_total = balancing_amount;
-        }
+    // The receipt's name goes into the metadata now; the file is written
+    // once the transaction is, so a failed write leaves no stray receipt
+    let mut metadata = serde_json::Map::new();
+    let document = match &upload {
+        Some(upload) => match document_target(&state.config, &date, if payee.is_empty() { &narration } else { &payee }, upload) {
+            Ok((target, path)) => {
+                metadata.insert("document".to_string(), path.into());
+                Some((target, upload))
+            }
+            Err(e) => return transactions::failure("附件保存失败", &e),
+        },
+        None => None,
+    };
+    if let Some(time) = time {
+        metadata.insert("time".to_string(), time.to_string().into());
     }
-
-    // Determine display based on account type
-    // For income: negative means money in (green), positive means refund (red)
-    // For expenses: positive means money out (red), negative means refund (green)
-    // For assets: positive means increase (green), negative means decrease (red)
-
-    if income_total.abs() > 0.001 {
-        // Primary transaction is income-related
-        if income_total < 0.0 {
-            // Normal income (money in) - show positive in green
-            display_amount = income_total.abs();
-            amount_color = "text-green-600".to_string();
-        } else {
-            // Refund/return - show as red
-            display_amount = income_total.abs();
-            amount_color = "text-red-600".to_string();
-        }
-    } else if expenses_total.abs() > 0.001 {
-        // Primary transaction is expense-related
-        if expenses_total > 0.0 {
-            // Normal expense (money out) - show positive in red
-            display_amount = expenses_total.abs();
-            amount_color = "text-red-600".to_string();
-        } else {
-            // Refund - show as green
-            display_amount = expenses_total.abs();
-            amount_color = "text-green-600".to_string();
-        }
-    } else if assets_total.abs() > 0.001 {
-        // Primary transaction is asset-related
-        if assets_total > 0.0 {
-            // Asset increase - green
-            display_amount = assets_total.abs();
-            amount_color = "text-green-600".to_string();
-        } else {
-            // Asset decrease - red
-            display_amount = assets_total.abs();
-            amount_color = "text-red-600".to_string();
-        }
-    } else if liabilities_total.abs() > 0.001 {
-        // Primary transaction is liability-related
-        if liabilities_total > 0.0 {
-            // Liability increase - red
-            display_amount = liabilities_total.abs();
-            amount_color = "text-red-600".to_string();
-        } else {
-            // Liability decrease (payoff) - green
-            display_amount = liabilities_total.abs();
-            amount_color = "text-green-600".to_string();
-        }
-    } else {
-        // Fallback: use total
-        let total = expenses_total + income_total + assets_total + liabilities_total;
-        display_amount = total.abs();
-        if total < 0.0 {
-            amount_color = "text-green-600".to_string();
-        } else {
-            amount_color = "text-red-600".to_string();
+    metadata.insert("created_at".to_string(), chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string().into());
+
+    let transaction = beanweb_core::Transaction {
+        id: String::new(),
+        date: date.clone(),
+        time: String::new(),
+        payee,
+        narration,
+        postings: final_postings,
+        flag: Some(flag).filter(|f| !f.is_empty()),
+        tags,
+        links,
+        metadata: metadata.into(),
+        source: None,
+        line: None,
+    };
+    let transaction_text = beanweb_core::render::transaction(&transaction, &state.ledger.read().await.options());
+
+    match state.service().create_transaction(&transaction_text).await {
+        Ok(created) => {
+            tracing::info!("Created transaction at {}:{}", created.file.display(), created.line);
+            // The transaction is saved either way, so a retry gets this response
+            let response = match document.map(|(target, upload)| save_document(&target, upload).map_err(|e| (target, e))) {
+                Some(Err((target, e))) => {
+                    tracing::error!("Failed to save receipt {}: {}", target.display(), e);
+                    transactions::failure("附件保存失败", &format!("交易已保存，但附件未能写入: {}", e))
+                }
+                _ => transactions::created(),
+            };
+            if let Some(remembered) = remembered.as_mut() {
+                remembered.remember(response.clone());
+            }
+            response
         }
+        Err(e) => transactions::failure("保存失败", &e.to_string()),
     }
-
-    (format!("{:.2}", display_amount), amount_color, display_currency)
 }
@@ -4,32 +4,53 @@
 //! - List transactions with pagination
 //! - Search by keyword (payee, narration, account)
 //! - HTMX partial page updates
-//!
-//! NOTE: Transaction edit functionality has been disabled
+//! - Edit a transaction in place (text or form mode) or delete it
+//! - Export the filtered list as CSV
+//! - Review and recategorize postings on suspense accounts
 //!
 //! Structure:
 //! - api.rs: JSON API and HTMX endpoints
 //! - page.rs: Full page rendering
+//! - suspense.rs: Suspense account review
 
 pub mod api;
 pub mod page;
+pub mod suspense;
 
 pub use api::{
     api_transactions,
+    api_transactions_export,
     api_transaction_detail,
+    api_transaction_breakdown,
+    api_transaction_delete,
+    api_link_group,
+    api_evaluate_amount,
     htmx_transactions_list,
     htmx_transactions_filter,
     htmx_transaction_detail,
-    // NOTE: 编辑功能已禁用
-    // htmx_transaction_edit_form,
-    // htmx_transaction_update,
+    htmx_transaction_breakdown,
+    htmx_transactions_upcoming,
+    htmx_transactions_review_banner,
+    htmx_transactions_mark_reviewed,
+    htmx_transaction_edit_form,
+    htmx_transaction_update,
+    htmx_transaction_delete,
     htmx_transaction_create_form,
     htmx_transaction_store,
 };
 
 pub use page::{
     page_transactions,
-    // NOTE: 编辑功能已禁用
-    // page_transaction_edit,
+    page_transaction,
+    page_transaction_edit,
     page_transaction_create,
 };
+
+pub use suspense::{
+    api_suspense,
+    api_recategorize,
+    page_suspense,
+    htmx_suspense_list,
+    htmx_recategorize,
+    htmx_suspense_badge,
+};
@@ -0,0 +1,89 @@
+//! Review of postings on suspense accounts
+//!
+//! Endpoints:
+//! - api_suspense: Suspense postings with their count (JSON)
+//! - api_recategorize: Move a transaction's suspense postings to another account (JSON)
+//! - page_suspense: Review page, linked from the nav badge
+//! - htmx_suspense_list / htmx_recategorize: The review list and its per-row action
+//! - htmx_suspense_badge: Count shown next to 流水 in the nav
+
+use crate::{ApiError, AppState};
+use beanweb_core::SuspensePosting;
+use beanweb_ui::suspense;
+use std::collections::HashMap;
+
+/// The postings plus the open accounts they can move to
+async fn suspense(state: &AppState) -> (Vec<SuspensePosting>, Vec<String>) {
+    let ledger = state.ledger.read().await;
+    let postings = ledger.suspense_postings();
+    let targets = ledger.accounts().into_iter()
+        .filter(|a| a.status == beanweb_core::AccountStatus::Open && !ledger.is_suspense_account(&a.name))
+        .map(|a| a.name)
+        .collect();
+    (postings, targets)
+}
+
+/// Recategorize from the `id`, `from` and `to` fields and reload
+async fn recategorize(state: &AppState, form: &HashMap<String, String>) -> Result<(), ApiError> {
+    let field = |name: &str| form.get(name).map(|s| s.trim()).filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::BadRequest { message: format!("缺少 {}", name) });
+    let (id, from, to) = (field("id")?, field("from")?, field("to")?);
+    let mut ledger = state.ledger.write().await;
+    let update = ledger.recategorize_posting(id, from, to).await
+        .map_err(|e| ApiError::BadRequest { message: e.to_string() })?;
+    if let Err(e) = ledger.reload().await {
+        tracing::error!("Failed to reload ledger after recategorizing: {}", e);
+    }
+    tracing::info!("Recategorized {} to {} at {}:{}", from, to, update.file.display(), update.line);
+    Ok(())
+}
+
+/// API: Postings on suspense accounts, newest first
+pub async fn api_suspense(state: axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
+    let ledger = state.ledger.read().await;
+    axum::Json(serde_json::json!({
+        "count": ledger.suspense_count(),
+        "postings": ledger.suspense_postings(),
+    }))
+}
+
+/// API: Move the suspense postings of one transaction (`id`, `from`, `to`)
+pub async fn api_recategorize(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    recategorize(&state, &form).await?;
+    Ok(axum::Json(serde_json::json!({"success": true, "remaining": state.ledger.read().await.suspense_count()})))
+}
+
+/// HTMX: Nav badge with the number of transactions to recategorize
+pub async fn htmx_suspense_badge(state: axum::extract::State<AppState>) -> String {
+    suspense::badge(state.ledger.read().await.suspense_count())
+}
+
+/// Suspense review page
+pub async fn page_suspense(
+    state: axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Html<String> {
+    axum::response::Html(crate::page_response(&headers, &state.config, "待分类", "/transactions", &suspense::page()))
+}
+
+/// HTMX: Review list
+pub async fn htmx_suspense_list(state: axum::extract::State<AppState>) -> String {
+    let (postings, targets) = suspense(&state).await;
+    suspense::list(&postings, &targets, None)
+}
+
+/// HTMX: Recategorize button of one row; re-renders the list
+pub async fn htmx_recategorize(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> String {
+    let result = recategorize(&state, &form).await;
+    let (postings, targets) = suspense(&state).await;
+    match result {
+        Ok(()) => suspense::list(&postings, &targets, None),
+        Err(e) => suspense::list(&postings, &targets, Some(&e.to_string())),
+    }
+}
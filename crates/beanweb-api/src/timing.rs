@@ -0,0 +1,42 @@
+//! Slow requests and the slow operations log
+//!
+//! Core times its searches and reports itself; this layer adds whole
+//! requests (handler and rendering) to the same log, so a slow page can be
+//! told apart from the slow query behind it:
+//! - Requests over `logging.slow_query_ms` are recorded as `GET /path` with
+//!   the query string and the response size in bytes (0 when streamed)
+//! - `/api/admin/slow` lists the slowest operations, slowest first
+
+use crate::AppState;
+use axum::body::HttpBody;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::time::{Duration, Instant};
+
+/// Middleware: record requests slower than the threshold
+pub async fn time_requests(
+    state: axum::extract::State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let operation = format!("{} {}", request.method(), request.uri().path());
+    let params = request.uri().query().unwrap_or_default().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed();
+    if elapsed >= Duration::from_millis(state.config.logging.slow_query_ms) {
+        let bytes = response.body().size_hint().exact().unwrap_or(0) as usize;
+        state.ledger.read().await.record_timing(&operation, &params, elapsed, bytes);
+    }
+    response
+}
+
+/// API: Slowest operations since startup
+pub async fn api_slow(state: axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
+    let ledger = state.ledger.read().await;
+    axum::Json(serde_json::json!({
+        "threshold_ms": state.config.logging.slow_query_ms,
+        "operations": ledger.slow_queries(),
+    }))
+}
@@ -0,0 +1,339 @@
+//! Second login factor
+//!
+//! With `server.auth` set, an authenticator app (TOTP, RFC 6238: SHA-1, six
+//! digits, 30-second steps) can be enrolled on the settings page. From then
+//! on `/login` asks for a code after the password:
+//! - Codes one step either side of now are accepted; each step's code only
+//!   once, so a code can't be replayed while it is still valid
+//! - Ten recovery codes are issued at enrollment, each usable once instead
+//!   of a code; they can be regenerated with a current code
+//! - HTTP Basic credentials are no longer accepted, they can't carry a code
+//! - Enrolling, regenerating recovery codes and disabling all ask for the
+//!   password again
+//!
+//! The enrollment lives in `server.auth.state_file` (relative to
+//! `data.path`), written readable by its owner only. The TOTP secret is
+//! encrypted (XChaCha20-Poly1305) with a key derived from the server key,
+//! and recovery codes are stored only as Argon2 hashes, so the file alone
+//! gives away neither.
+
+use crate::keys::ServerKey;
+use crate::AppState;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use beanweb_config::Config;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use data_encoding::{BASE32_NOPAD, BASE64};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Seconds per TOTP step
+const STEP_SECONDS: u64 = 30;
+/// Recovery codes issued at a time
+const RECOVERY_CODES: usize = 10;
+/// Nonce length of XChaCha20-Poly1305
+const NONCE_LEN: usize = 24;
+
+/// Enrollment shared by all requests
+pub type TwoFactorStore = Arc<RwLock<TwoFactorState>>;
+
+/// Enrolled secret and recovery codes, see the module docs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TwoFactorState {
+    /// Base64 nonce and ciphertext of the enrolled authenticator's base32 secret
+    encrypted_secret: Option<String>,
+    /// Argon2 hashes (PHC strings) of the unused recovery codes
+    recovery_hashes: Vec<String>,
+    /// Steps whose code was accepted and that are still within the window
+    used_steps: Vec<u64>,
+    /// Secret shown during enrollment until a first code confirms it
+    #[serde(skip)]
+    pending_secret: Option<String>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// Key that encrypts the secret, derived from the server key
+    #[serde(skip)]
+    secret_key: [u8; 32],
+}
+
+/// Load the enrollment of `config`; none when auth is off or nothing is stored
+pub fn open(config: &Config, key: &ServerKey) -> TwoFactorStore {
+    let Some(auth) = &config.server.auth else {
+        return TwoFactorStore::default();
+    };
+    let path = config.data.path.join(&auth.state_file);
+    let mut state: TwoFactorState = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    state.path = path;
+    state.secret_key = key.derive("two-factor-secret");
+    Arc::new(RwLock::new(state))
+}
+
+impl TwoFactorState {
+    pub fn enrolled(&self) -> bool {
+        self.encrypted_secret.is_some()
+    }
+
+    pub fn recovery_codes_left(&self) -> usize {
+        self.recovery_hashes.len()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(&self.path)?, content.as_bytes())
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.secret_key))
+    }
+
+    /// Store `secret` encrypted as the enrolled one
+    fn set_secret(&mut self, secret: &str) {
+        let nonce: [u8; NONCE_LEN] = rand::thread_rng().gen();
+        let ciphertext = self.cipher()
+            .encrypt(XNonce::from_slice(&nonce), secret.as_bytes())
+            .expect("encrypting a short secret can't fail");
+        self.encrypted_secret = Some(BASE64.encode(&[nonce.as_slice(), &ciphertext].concat()));
+    }
+
+    /// The enrolled secret; None when there is none or the server key changed
+    fn secret(&self) -> Option<String> {
+        let sealed = BASE64.decode(self.encrypted_secret.as_ref()?.as_bytes()).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self.cipher().decrypt(XNonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    /// Accept a current TOTP code for `secret`, each step's code once
+    fn accept_code(&mut self, secret: &str, code: &str, now: u64) -> bool {
+        let code = normalize(code);
+        let step = now / STEP_SECONDS;
+        let matched = [step.saturating_sub(1), step, step + 1].into_iter()
+            .filter(|candidate| !self.used_steps.contains(candidate))
+            .find(|candidate| code_at_step(secret, *candidate).is_some_and(|expected| crate::auth::constant_time_eq(expected.as_bytes(), code.as_bytes())));
+        match matched {
+            Some(matched) => {
+                // Steps before the window can't match again
+                self.used_steps.retain(|used| *used + 1 >= step);
+                self.used_steps.push(matched);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Accept and use up a recovery code
+    fn accept_recovery_code(&mut self, code: &str) -> bool {
+        let code = normalize(code);
+        let matched = self.recovery_hashes.iter().position(|hash| {
+            PasswordHash::new(hash).is_ok_and(|hash| Argon2::default().verify_password(code.as_bytes(), &hash).is_ok())
+        });
+        matched.map(|index| self.recovery_hashes.remove(index)).is_some()
+    }
+
+    /// Second step of a login: a current code or an unused recovery code
+    pub fn verify(&mut self, code: &str) -> bool {
+        if !self.enrolled() {
+            return false;
+        }
+        let accepted = self.secret().is_some_and(|secret| self.accept_code(&secret, code, unix_now()))
+            || self.accept_recovery_code(code);
+        if accepted {
+            if let Err(e) = self.save() {
+                tracing::error!("Failed to save two-factor state: {}", e);
+            }
+        }
+        accepted
+    }
+
+    /// Replace the recovery codes; returns the new ones in clear, shown once
+    fn new_recovery_codes(&mut self) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        let codes: Vec<String> = (0..RECOVERY_CODES)
+            .map(|_| {
+                let chars = BASE32_NOPAD.encode(&rng.gen::<[u8; 5]>()).to_ascii_lowercase();
+                format!("{}-{}", &chars[..5], &chars[5..])
+            })
+            .collect();
+        self.recovery_hashes = codes.iter().map(|code| recovery_hash(code)).collect();
+        codes
+    }
+}
+
+/// Code as typed, without spaces or dashes, lowercased
+fn normalize(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase()
+}
+
+/// Argon2 hash of a recovery code with a fresh salt, as a PHC string
+fn recovery_hash(code: &str) -> String {
+    let salt = SaltString::encode_b64(&rand::thread_rng().gen::<[u8; 16]>()).expect("16 bytes make a valid salt");
+    Argon2::default()
+        .hash_password(normalize(code).as_bytes(), &salt)
+        .expect("default Argon2 parameters are valid")
+        .to_string()
+}
+
+fn unix_now() -> u64 {
+    u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default()
+}
+
+/// Six-digit code of the base32 `secret` at a Unix time; None for an invalid secret
+pub fn code_at(secret: &str, unix_time: u64) -> Option<String> {
+    code_at_step(secret, unix_time / STEP_SECONDS)
+}
+
+fn code_at_step(secret: &str, step: u64) -> Option<String> {
+    let cleaned: String = secret.chars().filter(|c| !c.is_whitespace() && *c != '=').collect();
+    let key = BASE32_NOPAD.decode(cleaned.to_ascii_uppercase().as_bytes()).ok().filter(|key| !key.is_empty())?;
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&key).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let mac = mac.finalize().into_bytes();
+    let offset = usize::from(mac[19] & 0x0f);
+    let value = u32::from_be_bytes([mac[offset] & 0x7f, mac[offset + 1], mac[offset + 2], mac[offset + 3]]);
+    Some(format!("{:06}", value % 1_000_000))
+}
+
+/// Whether the form carries the configured password
+fn password_ok(state: &AppState, form: &HashMap<String, String>) -> bool {
+    let password = form.get("password").map(|s| s.as_str()).unwrap_or("");
+    state.config.server.auth.as_ref()
+        .is_some_and(|auth| crate::auth::constant_time_eq(password.as_bytes(), auth.password.as_bytes()))
+}
+
+/// HTMX: Two-factor status and actions for the settings page
+pub async fn htmx_two_factor(state: axum::extract::State<AppState>) -> Html<String> {
+    if state.config.server.auth.is_none() {
+        return Html(beanweb_ui::auth::auth_disabled("可启用两步验证"));
+    }
+    Html(status_html(&*state.two_factor.read().await, None))
+}
+
+/// Enrolled or not, with the forms that change it
+fn status_html(two_factor: &TwoFactorState, error: Option<&str>) -> String {
+    beanweb_ui::auth::two_factor_status(two_factor.enrolled(), two_factor.recovery_codes_left(), error)
+}
+
+/// The status with an error, as a 400
+fn rejected(two_factor: &TwoFactorState, error: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Html(status_html(two_factor, Some(error)))).into_response()
+}
+
+/// HTMX POST: Start enrollment with a fresh secret, given the password
+pub async fn htmx_two_factor_setup(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let Some(auth) = &state.config.server.auth else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut two_factor = state.two_factor.write().await;
+    if two_factor.enrolled() {
+        return Html(status_html(&two_factor, None)).into_response();
+    }
+    if !password_ok(&state, &form) {
+        return rejected(&two_factor, "密码不正确");
+    }
+    let secret = BASE32_NOPAD.encode(&rand::thread_rng().gen::<[u8; 20]>());
+    two_factor.pending_secret = Some(secret.clone());
+    let uri = format!(
+        "otpauth://totp/Beanweb:{}?secret={}&issuer=Beanweb",
+        urlencoding::encode(&auth.username), secret
+    );
+    Html(beanweb_ui::auth::two_factor_setup(&uri, &secret))
+    .into_response()
+}
+
+fn form_code(form: &HashMap<String, String>) -> &str {
+    form.get("code").map(|s| s.as_str()).unwrap_or("")
+}
+
+/// Save the state, or render why not
+fn saved(two_factor: &TwoFactorState, html: String) -> Response {
+    match two_factor.save() {
+        Ok(()) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to save two-factor state: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Html(status_html(two_factor, Some("保存失败")))).into_response()
+        }
+    }
+}
+
+/// HTMX POST: Confirm enrollment with the password and a first code; issues
+/// the recovery codes
+pub async fn htmx_two_factor_enable(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let mut two_factor = state.two_factor.write().await;
+    let Some(secret) = two_factor.pending_secret.clone() else {
+        return Html(status_html(&two_factor, Some("请重新开始启用"))).into_response();
+    };
+    if !password_ok(&state, &form) {
+        return rejected(&two_factor, "密码不正确，请重新开始启用");
+    }
+    if !two_factor.accept_code(&secret, form_code(&form), unix_now()) {
+        return rejected(&two_factor, "动态码不正确，请重新开始启用");
+    }
+    two_factor.set_secret(&secret);
+    two_factor.pending_secret = None;
+    let codes = two_factor.new_recovery_codes();
+    saved(&two_factor, beanweb_ui::auth::recovery_codes(&codes))
+}
+
+/// HTMX POST: Replace the recovery codes, given the password and a current code
+pub async fn htmx_two_factor_recovery(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let mut two_factor = state.two_factor.write().await;
+    if !password_ok(&state, &form) {
+        return rejected(&two_factor, "密码不正确");
+    }
+    if !two_factor.verify(form_code(&form)) {
+        return rejected(&two_factor, "动态码不正确");
+    }
+    let codes = two_factor.new_recovery_codes();
+    saved(&two_factor, beanweb_ui::auth::recovery_codes(&codes))
+}
+
+/// HTMX POST: Turn the second factor off, given the password and a current
+/// or recovery code
+pub async fn htmx_two_factor_disable(
+    state: axum::extract::State<AppState>,
+    form: axum::Form<HashMap<String, String>>,
+) -> Response {
+    let mut two_factor = state.two_factor.write().await;
+    if !password_ok(&state, &form) {
+        return rejected(&two_factor, "密码不正确");
+    }
+    if !two_factor.verify(form_code(&form)) {
+        return rejected(&two_factor, "动态码不正确");
+    }
+    two_factor.encrypted_secret = None;
+    two_factor.recovery_hashes.clear();
+    two_factor.used_steps.clear();
+    let html = status_html(&two_factor, None);
+    saved(&two_factor, html)
+}
@@ -0,0 +1,93 @@
+//! Auto-reload when the ledger files change
+//!
+//! With `data.watch_enable`, a background task polls the ledger files
+//! (`.bean`, `.beancount`, `.bc`) under the data directory and reloads the
+//! ledger after edits made outside Beanweb (editor, sync tools, git pull):
+//! - Reloads wait until the files have been quiet for `data.watch_debounce_ms`,
+//!   so a bulk edit or a checkout touching many files causes a single reload
+//! - Reloads triggered elsewhere (saves from the UI, `/api/reload`) reset the
+//!   baseline, so Beanweb's own writes are not loaded twice
+
+use crate::AppState;
+use beanweb_core::watch::FileSnapshot;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often the data directory is scanned
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Changed files named in the log line before it is summarized
+const LOGGED_FILES: usize = 3;
+
+async fn scan(dir: &Path) -> FileSnapshot {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || FileSnapshot::scan(&dir)).await.unwrap_or_default()
+}
+
+fn describe(changed: &[PathBuf], dir: &Path) -> String {
+    let mut names: Vec<String> = changed.iter()
+        .take(LOGGED_FILES)
+        .map(|path| path.strip_prefix(dir).unwrap_or(path).display().to_string())
+        .collect();
+    if changed.len() > LOGGED_FILES {
+        names.push(format!("+{} more", changed.len() - LOGGED_FILES));
+    }
+    names.join(", ")
+}
+
+/// Start the watcher task; does nothing unless `data.watch_enable` is set
+pub fn spawn_watcher(state: AppState) {
+    if !state.config.data.watch_enable {
+        return;
+    }
+    let dir = state.config.data.path.clone();
+    let debounce = Duration::from_millis(state.config.data.watch_debounce_ms);
+    tokio::spawn(async move {
+        let mut reloads = state.ledger.read().await.subscribe_reloads();
+        let mut baseline = scan(&dir).await;
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        tracing::info!("Watching {} ({} ledger files)", dir.display(), baseline.len());
+
+        loop {
+            tokio::select! {
+                changed = reloads.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    baseline = scan(&dir).await;
+                    continue;
+                }
+                _ = interval.tick() => {}
+            }
+
+            let mut settled = scan(&dir).await;
+            if settled.changed_since(&baseline).is_empty() {
+                continue;
+            }
+            // Debounce: wait until a scan shows no further changes
+            loop {
+                tokio::time::sleep(debounce).await;
+                let next = scan(&dir).await;
+                if next == settled {
+                    break;
+                }
+                settled = next;
+            }
+            // Someone reloaded while we waited; their load already has the changes
+            if reloads.has_changed().unwrap_or(false) {
+                continue;
+            }
+
+            let changed = settled.changed_since(&baseline);
+            baseline = settled;
+            tracing::info!("Ledger files changed ({}), reloading", describe(&changed, &dir));
+            let result = state.ledger.write().await.reload().await;
+            // Our own reload needs no rescan
+            reloads.borrow_and_update();
+            if let Err(e) = result {
+                tracing::error!("Auto-reload failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,222 @@
+//! Route-level test harness
+//!
+//! [`TestServer::start`] writes a fixture ledger to a fresh temp dir, loads
+//! it and serves the real router on an ephemeral localhost port, so tests go
+//! through axum's extractors and the same handlers the browser hits:
+//! - `get`/`post`/`post_form` send plain requests; `get_htmx` adds
+//!   `HX-Request: true` to get the fragment instead of the full page
+//! - [`TestResponse`] has assertions for status, JSON and HTML content
+//! - The temp dir and server task go away when the server is dropped
+//!
+//! The time range is set to all time so fixtures with fixed dates show up.
+
+#![allow(dead_code)]
+
+use beanweb_api::{auth, checks, create_router, idempotency, jobs, keys, metrics, two_factor, AppState};
+use beanweb_config::{AuthConfig, Config, TimeRange};
+use beanweb_core::Ledger;
+use hyper::{Body, Client, Method, Request, StatusCode};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Temp dirs created by this test binary, to keep parallel tests apart
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// A running server over a fixture ledger
+pub struct TestServer {
+    pub addr: SocketAddr,
+    /// Data directory holding `main.bean`
+    pub dir: PathBuf,
+    pub state: AppState,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Status, headers and body of one response
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: hyper::HeaderMap,
+    pub body: String,
+}
+
+/// Require a login as `admin` / `secret!`; the server key goes into the temp dir
+pub fn with_auth(config: &mut Config) {
+    config.server.auth = Some(AuthConfig {
+        username: "admin".to_string(),
+        password: "secret!".to_string(),
+        session_days: 30,
+        state_file: ".beanweb/auth.json".into(),
+        key_file: config.data.path.join("beanweb.key"),
+        metrics_token: None,
+    });
+}
+
+impl TestServer {
+    /// Serve `main_bean` with the default configuration
+    pub async fn start(main_bean: &str) -> Self {
+        Self::start_with(main_bean, |_| {}).await
+    }
+
+    /// Serve `main_bean` after `configure` adjusted the configuration
+    pub async fn start_with(main_bean: &str, configure: impl FnOnce(&mut Config)) -> Self {
+        let id = SERVERS.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("beanweb-api-test-{}-{}", std::process::id(), id));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), main_bean).unwrap();
+
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        config.data.new_transaction_file = "main.bean".to_string();
+        // Background checks would race the assertions on reload
+        config.checks.enabled = false;
+        configure(&mut config);
+
+        let parser = Arc::new(beanweb_parser::DefaultBeancountParser);
+        let mut ledger = Ledger::new(config.clone(), parser);
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        ledger.set_time_range(TimeRange::All);
+
+        let key = keys::ServerKey::open(&config).unwrap();
+        let state = AppState {
+            ledger: Arc::new(RwLock::new(ledger)),
+            two_factor: two_factor::open(&config, &key),
+            config,
+            checks: checks::CheckCache::default(),
+            sessions: auth::open(&key),
+            idempotency: idempotency::IdempotencyStore::default(),
+            jobs: jobs::ReportJobs::default(),
+            metrics: metrics::RequestMetrics::default(),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = create_router(state.clone());
+        let task = tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        Self { addr, dir, state, task }
+    }
+
+    /// Send a request; `path` includes the query string
+    pub async fn request(&self, method: Method, path: &str, headers: &[(&str, &str)], body: String) -> TestResponse {
+        let mut request = Request::builder().method(method).uri(format!("http://{}{}", self.addr, path));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = Client::new().request(request.body(Body::from(body)).unwrap()).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        TestResponse { status, headers, body: String::from_utf8_lossy(&bytes).into_owned() }
+    }
+
+    pub async fn get(&self, path: &str) -> TestResponse {
+        self.request(Method::GET, path, &[], String::new()).await
+    }
+
+    /// GET as htmx does, for the fragment rather than the full page
+    pub async fn get_htmx(&self, path: &str) -> TestResponse {
+        self.request(Method::GET, path, &[("HX-Request", "true")], String::new()).await
+    }
+
+    /// `Origin` value of a same-site request
+    pub fn origin(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Log in as [`with_auth`]'s user; returns the `Cookie` header value
+    pub async fn login(&self) -> String {
+        let response = self.request(
+            Method::POST,
+            "/login",
+            &[("Content-Type", "application/x-www-form-urlencoded")],
+            "username=admin&password=secret%21".to_string(),
+        ).await;
+        assert_eq!(response.status, StatusCode::SEE_OTHER, "login failed: {}", response.body);
+        let cookie = response.headers["set-cookie"].to_str().unwrap();
+        cookie.split(';').next().unwrap().to_string()
+    }
+
+    pub async fn post(&self, path: &str) -> TestResponse {
+        self.request(Method::POST, path, &[], String::new()).await
+    }
+
+    /// POST `fields` as `application/x-www-form-urlencoded`
+    pub async fn post_form(&self, path: &str, fields: &[(&str, &str)]) -> TestResponse {
+        let body: Vec<String> = fields.iter()
+            .map(|(name, value)| format!("{}={}", urlencoding::encode(name), urlencoding::encode(value)))
+            .collect();
+        self.request(
+            Method::POST,
+            path,
+            &[("Content-Type", "application/x-www-form-urlencoded"), ("HX-Request", "true")],
+            body.join("&"),
+        )
+        .await
+    }
+
+    /// POST `fields` as `multipart/form-data`, `(name, file name, content)`;
+    /// fields without a file name are plain text fields
+    pub async fn post_multipart(&self, path: &str, fields: &[(&str, Option<&str>, &str)]) -> TestResponse {
+        const BOUNDARY: &str = "beanweb-test-boundary";
+        let mut body = String::new();
+        for (name, file_name, content) in fields {
+            let file = file_name.map(|f| format!("; filename=\"{}\"", f)).unwrap_or_default();
+            body.push_str(&format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"{}\r\n\r\n{}\r\n", BOUNDARY, name, file, content));
+        }
+        body.push_str(&format!("--{}--\r\n", BOUNDARY));
+        let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+        self.request(Method::POST, path, &[("Content-Type", content_type.as_str())], body).await
+    }
+
+    /// Contents of a file in the data directory
+    pub fn read_file(&self, name: &str) -> String {
+        std::fs::read_to_string(self.dir.join(name)).unwrap_or_default()
+    }
+
+    /// Replace a file in the data directory, as an outside editor would
+    pub fn write_file(&self, name: &str, content: &str) {
+        std::fs::write(self.dir.join(name), content).unwrap();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl TestResponse {
+    /// Assert a 2xx status
+    pub fn assert_ok(&self) -> &Self {
+        assert!(self.status.is_success(), "status {}: {}", self.status, self.body);
+        self
+    }
+
+    /// Assert `needle` appears in the body
+    pub fn assert_contains(&self, needle: &str) -> &Self {
+        assert!(self.body.contains(needle), "expected {:?} in:\n{}", needle, self.body);
+        self
+    }
+
+    pub fn assert_not_contains(&self, needle: &str) -> &Self {
+        assert!(!self.body.contains(needle), "unexpected {:?} in:\n{}", needle, self.body);
+        self
+    }
+
+    /// Assert an HTMX fragment: no page shell around it
+    pub fn assert_fragment(&self) -> &Self {
+        self.assert_ok().assert_not_contains("<html")
+    }
+
+    /// Body parsed as JSON
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or_else(|e| panic!("invalid JSON ({}): {}", e, self.body))
+    }
+}
@@ -6,6 +6,7 @@
 pub mod error;
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 pub use error::ConfigError;
@@ -24,6 +25,26 @@ pub struct ServerConfig {
     /// Basic authentication (optional)
     #[serde(default)]
     pub auth: Option<AuthConfig>,
+    /// What to do when the ledger fails to load at startup
+    #[serde(default)]
+    pub startup_mode: StartupMode,
+    /// Maximum load attempts in retry mode (0 = retry forever)
+    #[serde(default = "default_startup_retry_max_attempts")]
+    pub startup_retry_max_attempts: u32,
+    /// URL the server is reached at (`https://ledger.example`), used for
+    /// absolute links such as feed entries
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl ServerConfig {
+    /// `base_url` without a trailing `/`, else `http://localhost:<port>`
+    pub fn base_url(&self) -> String {
+        match &self.base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("http://localhost:{}", self.port),
+        }
+    }
 }
 
 fn default_host() -> String {
@@ -34,11 +55,65 @@ fn default_port() -> u16 {
     8081
 }
 
+fn default_startup_retry_max_attempts() -> u32 {
+    10
+}
+
+/// Startup behavior when the ledger fails to load
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupMode {
+    /// Exit with a non-zero status
+    FailFast,
+    /// Serve with empty data and show the error on every page
+    #[default]
+    ServeWithBanner,
+    /// Serve with the error banner and keep retrying the load with backoff
+    Retry,
+}
+
+impl std::fmt::Display for StartupMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupMode::FailFast => write!(f, "fail_fast"),
+            StartupMode::ServeWithBanner => write!(f, "serve_with_banner"),
+            StartupMode::Retry => write!(f, "retry"),
+        }
+    }
+}
+
 /// Basic authentication configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
     pub username: String,
     pub password: String,
+    /// Days a login session stays valid without being used
+    #[serde(default = "default_session_days")]
+    pub session_days: u32,
+    /// Second-factor enrollment and recovery codes; relative to `data.path`
+    #[serde(default = "default_auth_state_file")]
+    pub state_file: PathBuf,
+    /// Random key that signs session cookies and encrypts the second-factor
+    /// secret, created on first start;
+    /// relative to the working directory, keep it outside `data.path`
+    #[serde(default = "default_auth_key_file")]
+    pub key_file: PathBuf,
+    /// Bearer token that reads `/metrics` without logging in, for scrapers;
+    /// it opens nothing else
+    #[serde(default)]
+    pub metrics_token: Option<String>,
+}
+
+fn default_session_days() -> u32 {
+    30
+}
+
+fn default_auth_state_file() -> PathBuf {
+    PathBuf::from(".beanweb/auth.json")
+}
+
+fn default_auth_key_file() -> PathBuf {
+    PathBuf::from("beanweb.key")
 }
 
 /// Data directory configuration
@@ -53,9 +128,36 @@ pub struct DataConfig {
     /// Enable file watching for auto-reload
     #[serde(default = "default_true")]
     pub watch_enable: bool,
+    /// Quiet period after the last file change before reloading, in milliseconds
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
     /// Default file for new transactions (relative to data path)
     #[serde(default = "default_new_transaction_file")]
     pub new_transaction_file: String,
+    /// Warn on the files page when a single file exceeds this size in KB (0 disables)
+    #[serde(default = "default_file_size_warning_kb")]
+    pub file_size_warning_kb: u64,
+    /// Folder for receipts attached to transactions (relative to data path)
+    #[serde(default = "default_documents_dir")]
+    pub documents_dir: String,
+    /// Files larger than this (KB) open in an editor that loads and saves
+    /// 500 lines at a time instead of the whole file (0 disables)
+    #[serde(default = "default_editor_preview_kb")]
+    pub editor_preview_kb: u64,
+    /// List ledger files that no include reaches on the files page, with a
+    /// button adding the include to the main file
+    #[serde(default = "default_true")]
+    pub detect_orphans: bool,
+    /// Reloads parse only the files changed since the last load
+    #[serde(default = "default_true")]
+    pub incremental_reload: bool,
+    /// Parser reading the ledger, by the name it is registered under
+    #[serde(default = "default_parser")]
+    pub parser: String,
+}
+
+fn default_parser() -> String {
+    "simple".to_string()
 }
 
 fn default_data_path() -> PathBuf {
@@ -70,6 +172,22 @@ fn default_new_transaction_file() -> String {
     "transactions.bean".to_string()
 }
 
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+fn default_file_size_warning_kb() -> u64 {
+    512
+}
+
+fn default_editor_preview_kb() -> u64 {
+    2048
+}
+
+fn default_documents_dir() -> String {
+    "documents".to_string()
+}
+
 /// Feature toggles
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FeaturesConfig {
@@ -89,16 +207,92 @@ fn default_false() -> bool {
     false
 }
 
+/// Log line format
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One human-readable line per event
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {} (expected text or json)", s)),
+        }
+    }
+}
+
+/// Levels accepted by `logging.level`
+pub const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+/// Check a filter in RUST_LOG syntax: comma-separated directives, each a
+/// level or `target=level`, e.g. `info,beanweb_api=debug`. A bare target
+/// is rejected rather than read as `target=trace`, so a misspelt level
+/// does not pass
+pub fn parse_log_filter(filter: &str) -> Result<String, String> {
+    let is_level = |level: &str| level == "off" || LOG_LEVELS.contains(&level.to_lowercase().as_str());
+    let is_target = |target: &str| {
+        target.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':' || c == '-')
+    };
+    if filter.trim().is_empty() {
+        return Err("Log filter must not be empty".to_string());
+    }
+    for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let valid = match directive.split_once('=') {
+            Some((target, level)) => is_target(target) && is_level(level),
+            None => is_level(directive),
+        };
+        if !valid {
+            return Err(format!(
+                "Invalid log directive: {} (expected one of {}, or target=level)",
+                directive,
+                LOG_LEVELS.join(", ")
+            ));
+        }
+    }
+    Ok(filter.to_string())
+}
+
 /// Logging configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
-    /// Log level: debug, info, warn, error
+    /// Log level: trace, debug, info, warn, error, or directives as in
+    /// RUST_LOG (`info,beanweb_api=debug`)
     #[serde(default = "default_log_level")]
     pub level: String,
+    /// Log line format: text or json
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Queries, reports and requests slower than this (milliseconds) are
+    /// logged and kept for `/api/admin/slow`
+    #[serde(default = "default_slow_query_ms")]
+    pub slow_query_ms: u64,
 }
 
 fn default_log_level() -> String {
-    "debug".to_string()
+    "info".to_string()
+}
+
+fn default_slow_query_ms() -> u64 {
+    200
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            format: LogFormat::default(),
+            slow_query_ms: default_slow_query_ms(),
+        }
+    }
 }
 
 /// Pagination settings
@@ -294,6 +488,97 @@ impl Default for ChartType {
     }
 }
 
+/// Report settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportsConfig {
+    /// Virtual account groups used to roll up report entries
+    #[serde(default)]
+    pub groups: Vec<ReportGroup>,
+    /// Internal transfer detection
+    #[serde(default)]
+    pub transfers: TransferConfig,
+    /// Entries below this share of their total (in percent) are merged into
+    /// "Other" in reports and charts; 0 keeps every entry
+    #[serde(default)]
+    pub other_threshold: f64,
+    /// Report pages over ranges with at least this many transactions are
+    /// built in a background job, with a spinner polling it
+    #[serde(default = "default_async_min_transactions")]
+    pub async_min_transactions: usize,
+}
+
+fn default_async_min_transactions() -> usize {
+    20_000
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            groups: Vec::new(),
+            transfers: TransferConfig::default(),
+            other_threshold: 0.0,
+            async_min_transactions: default_async_min_transactions(),
+        }
+    }
+}
+
+/// Transfers between own accounts are kept out of income/expense totals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferConfig {
+    /// Detect transfers and report them in a separate section
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Extra accounts treated as transfer legs besides Assets/Liabilities
+    /// (e.g. a mis-booked "Expenses:Transfer"); sub-accounts are included
+    #[serde(default)]
+    pub accounts: Vec<String>,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            accounts: Vec::new(),
+        }
+    }
+}
+
+impl TransferConfig {
+    /// Whether a posting account counts as one side of an internal transfer
+    pub fn is_transfer_account(&self, account: &str) -> bool {
+        account.starts_with("Assets:")
+            || account.starts_with("Liabilities:")
+            || self.accounts.iter().any(|member| {
+                account == member || account.strip_prefix(member.as_str()).is_some_and(|rest| rest.starts_with(':'))
+            })
+    }
+}
+
+/// A named rollup of accounts, e.g. "Essential" = Expenses:Rent + Expenses:Utilities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportGroup {
+    /// Group name shown in reports
+    pub name: String,
+    /// Member accounts; each one also covers its sub-accounts
+    pub accounts: Vec<String>,
+}
+
+impl ReportsConfig {
+    /// Find the group an account rolls up into
+    /// When several members match, the longest (most specific) one wins
+    pub fn group_for(&self, account: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.accounts.iter().map(move |a| (g.name.as_str(), a.as_str())))
+            .filter(|(_, member)| {
+                account == *member
+                    || account.strip_prefix(member).is_some_and(|rest| rest.starts_with(':'))
+            })
+            .max_by_key(|(_, member)| member.len())
+            .map(|(name, _)| name)
+    }
+}
+
 /// Currency and number formatting
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CurrencyConfig {
@@ -312,6 +597,9 @@ pub struct CurrencyConfig {
     /// Currency symbol position ("before" or "after")
     #[serde(default = "default_symbol_position")]
     pub symbol_position: SymbolPosition,
+    /// Sign convention for Income/Liabilities/Equity amounts in the UI
+    #[serde(default)]
+    pub sign_convention: SignConvention,
 }
 
 impl Default for CurrencyConfig {
@@ -322,6 +610,7 @@ impl Default for CurrencyConfig {
             thousands_separator: ",".to_string(),
             decimal_separator: ".".to_string(),
             symbol_position: SymbolPosition::Before,
+            sign_convention: SignConvention::default(),
         }
     }
 }
@@ -360,6 +649,65 @@ impl Default for SymbolPosition {
     }
 }
 
+/// How amounts of credit-normal accounts (Income, Liabilities, Equity) are shown
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignConvention {
+    /// Earned income and owed liabilities are positive
+    #[default]
+    Natural,
+    /// Signs exactly as booked in beancount (income negative)
+    Raw,
+}
+
+impl std::fmt::Display for SignConvention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignConvention::Natural => write!(f, "natural"),
+            SignConvention::Raw => write!(f, "raw"),
+        }
+    }
+}
+
+/// Background integrity check settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecksConfig {
+    /// Re-run the integrity check after every (re)load
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// URL that receives a JSON POST whenever the set of errors changes
+    /// (plain http only, e.g. a local ntfy or chat relay)
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Days after which an account's latest balance assertion is stale, by
+    /// account type; types not listed are never reported
+    #[serde(default = "default_stale_balance_days")]
+    pub stale_balance_days: BTreeMap<String, u32>,
+    /// Placeholder accounts whose postings still need a real category; each
+    /// an account filter, so sub-accounts count too
+    #[serde(default = "default_suspense_accounts")]
+    pub suspense_accounts: Vec<String>,
+}
+
+fn default_stale_balance_days() -> BTreeMap<String, u32> {
+    BTreeMap::from([("Assets".to_string(), 30), ("Liabilities".to_string(), 30)])
+}
+
+fn default_suspense_accounts() -> Vec<String> {
+    vec!["Expenses:Uncategorized".to_string(), "Equity:TODO".to_string()]
+}
+
+impl Default for ChecksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            webhook_url: None,
+            stale_balance_days: default_stale_balance_days(),
+            suspense_accounts: default_suspense_accounts(),
+        }
+    }
+}
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
@@ -387,6 +735,12 @@ pub struct Config {
     /// Currency settings
     #[serde(default)]
     pub currency: CurrencyConfig,
+    /// Report settings
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    /// Integrity check settings
+    #[serde(default)]
+    pub checks: ChecksConfig,
     /// Logging settings
     #[serde(default)]
     pub logging: LoggingConfig,
@@ -434,6 +788,58 @@ impl Config {
             });
         }
 
+        if let Some(url) = &self.server.base_url {
+            let host = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://")).unwrap_or("");
+            if host.trim_end_matches('/').is_empty() || url.chars().any(|c| c.is_whitespace() || "\"'<>".contains(c)) {
+                return Err(ConfigError::InvalidValue {
+                    field: "server.base_url".to_string(),
+                    reason: "Base URL must be an http:// or https:// URL".to_string(),
+                });
+            }
+        }
+
+        // Validate the webhook URL
+        if let Some(url) = &self.checks.webhook_url {
+            if !url.starts_with("http://") {
+                return Err(ConfigError::InvalidValue {
+                    field: "checks.webhook_url".to_string(),
+                    reason: "Webhook URL must start with http://".to_string(),
+                });
+            }
+        }
+
+        for (account_type, days) in &self.checks.stale_balance_days {
+            if !["Assets", "Liabilities", "Equity", "Income", "Expenses"].contains(&account_type.as_str()) || *days == 0 {
+                return Err(ConfigError::InvalidValue {
+                    field: format!("checks.stale_balance_days.{}", account_type),
+                    reason: "Expected an account type (Assets, Liabilities, ...) and at least 1 day".to_string(),
+                });
+            }
+        }
+
+        if let Err(reason) = parse_log_filter(&self.logging.level) {
+            return Err(ConfigError::InvalidValue {
+                field: "logging.level".to_string(),
+                reason,
+            });
+        }
+
+        // Validate report groups
+        for group in &self.reports.groups {
+            if group.name.trim().is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    field: "reports.groups".to_string(),
+                    reason: "Group name must not be empty".to_string(),
+                });
+            }
+        }
+        if !(0.0..100.0).contains(&self.reports.other_threshold) {
+            return Err(ConfigError::InvalidValue {
+                field: "reports.other_threshold".to_string(),
+                reason: "Threshold must be a percentage from 0 to below 100".to_string(),
+            });
+        }
+
         Ok(())
     }
 
@@ -447,6 +853,20 @@ impl Config {
         self.data.path.join(&self.data.main_file)
     }
 
+    /// File new transactions are appended to: the main file when
+    /// `data.new_transaction_file` is empty or `main.bean`, else that file,
+    /// relative to `data.path` unless absolute
+    pub fn new_transaction_path(&self) -> PathBuf {
+        let file = &self.data.new_transaction_file;
+        if file.is_empty() || file == "main.bean" {
+            self.ledger_path()
+        } else if PathBuf::from(file).is_absolute() {
+            PathBuf::from(file)
+        } else {
+            self.data.path.join(file)
+        }
+    }
+
     /// Check if a feature is enabled
     pub fn is_feature_enabled(&self, feature: &str) -> bool {
         match feature {
@@ -456,3 +876,51 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_logging(logging: &str) -> Result<Config, ConfigError> {
+        let mut config: Config = serde_yaml::from_str(Config::generate_default()).unwrap();
+        config.logging = serde_yaml::from_str(logging).map_err(|_| ConfigError::InvalidYaml)?;
+        config.validate().map(|_| config)
+    }
+
+    #[test]
+    fn test_logging_level_validation() {
+        for level in ["info", "DEBUG", "off", "info,beanweb_api=debug", "beanweb_core::reports=trace", "tower_http=warn"] {
+            assert!(config_with_logging(&format!("level: \"{}\"", level)).is_ok(), "{}", level);
+        }
+        for level in ["", "verbose", "beanweb_api=loud", "=debug", "info,,beanweb api=debug"] {
+            let result = config_with_logging(&format!("level: \"{}\"", level));
+            assert!(matches!(result, Err(ConfigError::InvalidValue { ref field, .. }) if field == "logging.level"), "{}", level);
+        }
+        assert_eq!(config_with_logging("{}").unwrap().logging.level, "info");
+    }
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert!("JSON".parse::<LogFormat>().is_err());
+        assert_eq!(config_with_logging("format: json").unwrap().logging.format, LogFormat::Json);
+        assert_eq!(config_with_logging("{}").unwrap().logging.format, LogFormat::Text);
+        assert!(config_with_logging("format: xml").is_err());
+    }
+
+    #[test]
+    fn test_base_url() {
+        let mut config: Config = serde_yaml::from_str(Config::generate_default()).unwrap();
+        assert_eq!(config.server.base_url(), "http://localhost:8081");
+        for url in ["https://ledger.example/", "http://10.0.0.2:8081/beanweb"] {
+            config.server.base_url = Some(url.to_string());
+            assert!(config.validate().is_ok(), "{}", url);
+        }
+        assert_eq!(config.server.base_url(), "http://10.0.0.2:8081/beanweb");
+        for url in ["ledger.example", "https://", "https://ledger.example/\"><x"] {
+            config.server.base_url = Some(url.to_string());
+            assert!(matches!(config.validate(), Err(ConfigError::InvalidValue { ref field, .. }) if field == "server.base_url"), "{}", url);
+        }
+    }
+}
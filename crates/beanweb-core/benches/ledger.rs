@@ -0,0 +1,39 @@
+//! Ledger processing benchmarks on synthetic ledgers of 10k and 100k transactions
+//!
+//! `process_result` turns parsed directives into accounts, transactions,
+//! balances and prices; it runs on every load and reload. Parsing is done once
+//! outside the measurement (see the parser benchmarks for that part).
+//!
+//! `cargo bench -p beanweb-core -- --save-baseline main` on the base branch,
+//! then `-- --baseline main` on a change reports regressions against it.
+
+use beanweb_config::Config;
+use beanweb_core::Ledger;
+use beanweb_parser::{DefaultBeancountParser, SimpleBeancountParser};
+use beanweb_utils::synthetic::SyntheticLedger;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::sync::Arc;
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+
+fn bench_process_result(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("process_result");
+    group.sample_size(10);
+    for size in SIZES {
+        let text = SyntheticLedger::new(size).end_year(2024).render();
+        let directives = SimpleBeancountParser::parse(&text).unwrap();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &directives, |b, directives| {
+            b.iter_batched(
+                || (Ledger::new(Config::default(), Arc::new(DefaultBeancountParser)), directives.clone()),
+                |(mut ledger, directives)| runtime.block_on(ledger.load_directives(directives)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_process_result);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+//! Account filters for transaction queries
+//!
+//! Three forms, as accepted by `account=` and by `account:` terms in a search:
+//! - `Expenses:Food` matches the account and its sub-accounts
+//! - `Expenses:Food:*` is a glob; `*` matches any run of characters
+//!   (sub-accounts included) and `?` a single one
+//! - `~Taxi|Uber` is a regular expression searched anywhere in the name
+//!
+//! Matching is resolved once per distinct account name rather than once per
+//! posting, so a filter over a large ledger costs about as much as a plain
+//! account lookup.
+
+use crate::{CoreError, Ledger, Transaction};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Search terms that set an account filter, e.g. `account:Expenses:Food:*`
+const QUERY_PREFIX: &str = "account:";
+
+/// A parsed account filter
+#[derive(Debug, Clone)]
+pub enum AccountFilter {
+    /// An account and its sub-accounts
+    Account(String),
+    /// Glob pattern, kept with its compiled form
+    Glob(String, Regex),
+    /// Regular expression, searched anywhere in the name
+    Regex(Regex),
+}
+
+impl AccountFilter {
+    /// Parse `Expenses:Food`, `Expenses:Food:*` or `~Taxi|Uber`
+    pub fn parse(text: &str) -> Result<Self, CoreError> {
+        let text = text.trim();
+        let invalid = |message: String| CoreError::ValidationError { message };
+        if text.is_empty() {
+            return Err(invalid("Empty account filter".to_string()));
+        }
+        if let Some(pattern) = text.strip_prefix('~') {
+            return Regex::new(pattern)
+                .map(AccountFilter::Regex)
+                .map_err(|e| invalid(format!("Invalid account pattern {}: {}", pattern, e)));
+        }
+        if text.contains(['*', '?']) {
+            let mut pattern = String::from("^");
+            for c in text.chars() {
+                match c {
+                    '*' => pattern.push_str(".*"),
+                    '?' => pattern.push('.'),
+                    c => pattern.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            pattern.push('$');
+            let compiled = Regex::new(&pattern).map_err(|e| invalid(format!("Invalid account pattern {}: {}", text, e)))?;
+            return Ok(AccountFilter::Glob(text.to_string(), compiled));
+        }
+        Ok(AccountFilter::Account(text.to_string()))
+    }
+
+    /// Whether `account` passes the filter
+    pub fn matches(&self, account: &str) -> bool {
+        match self {
+            AccountFilter::Account(name) => {
+                account == name || account.strip_prefix(name.as_str()).is_some_and(|rest| rest.starts_with(':'))
+            }
+            AccountFilter::Glob(_, regex) | AccountFilter::Regex(regex) => regex.is_match(account),
+        }
+    }
+}
+
+impl std::fmt::Display for AccountFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountFilter::Account(name) => write!(f, "{}", name),
+            AccountFilter::Glob(pattern, _) => write!(f, "{}", pattern),
+            AccountFilter::Regex(regex) => write!(f, "~{}", regex.as_str()),
+        }
+    }
+}
+
+/// Split a search into its `account:` filters and the remaining keywords
+pub fn split_query(query: &str) -> Result<(Vec<AccountFilter>, String), CoreError> {
+    let mut filters = Vec::new();
+    let mut keywords = Vec::new();
+    for term in query.split_whitespace() {
+        match term.strip_prefix(QUERY_PREFIX) {
+            Some(pattern) if !pattern.is_empty() => filters.push(AccountFilter::parse(pattern)?),
+            _ => keywords.push(term),
+        }
+    }
+    Ok((filters, keywords.join(" ")))
+}
+
+/// Memoized filter results per account name, one flag per filter
+struct Matcher<'a> {
+    filters: &'a [AccountFilter],
+    seen: HashMap<String, Vec<bool>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(filters: &'a [AccountFilter]) -> Self {
+        Self { filters, seen: HashMap::new() }
+    }
+
+    /// Every filter is matched by at least one posting
+    fn matches(&mut self, tx: &Transaction) -> bool {
+        let mut hits = vec![false; self.filters.len()];
+        for posting in &tx.postings {
+            let account_hits = self.account_hits(&posting.account);
+            hits.iter_mut().zip(account_hits).for_each(|(hit, account_hit)| *hit |= account_hit);
+        }
+        hits.iter().all(|hit| *hit)
+    }
+
+    fn account_hits(&mut self, account: &str) -> &[bool] {
+        if !self.seen.contains_key(account) {
+            let hits = self.filters.iter().map(|f| f.matches(account)).collect();
+            self.seen.insert(account.to_string(), hits);
+        }
+        &self.seen[account]
+    }
+}
+
+/// Keep the transactions matching every filter
+pub fn retain_matching<T: std::borrow::Borrow<Transaction>>(transactions: &mut Vec<T>, filters: &[AccountFilter]) {
+    if filters.is_empty() {
+        return;
+    }
+    let mut matcher = Matcher::new(filters);
+    transactions.retain(|tx| matcher.matches(tx.borrow()));
+}
+
+impl Ledger {
+    /// Opened accounts matching `filter`, sorted
+    pub fn matching_accounts(&self, filter: &AccountFilter) -> Vec<String> {
+        let data = self.data.read().unwrap();
+        let mut names: Vec<String> = data.accounts.iter()
+            .filter(|a| filter.matches(&a.name))
+            .map(|a| a.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_filter_forms() {
+        let plain = AccountFilter::parse("Expenses:Food").unwrap();
+        assert!(plain.matches("Expenses:Food") && plain.matches("Expenses:Food:Dining"));
+        assert!(!plain.matches("Expenses:FoodTruck"));
+
+        let glob = AccountFilter::parse("Expenses:Food:*").unwrap();
+        assert!(glob.matches("Expenses:Food:Dining") && glob.matches("Expenses:Food:Dining:Lunch"));
+        assert!(!glob.matches("Expenses:Food"));
+        assert!(AccountFilter::parse("Assets:Bank:???").unwrap().matches("Assets:Bank:ICB"));
+
+        let regex = AccountFilter::parse("~Taxi|Uber").unwrap();
+        assert!(regex.matches("Expenses:Transport:Taxi") && regex.matches("Liabilities:Uber"));
+        assert_eq!(regex.to_string(), "~Taxi|Uber");
+        assert!(AccountFilter::parse("~(").is_err());
+
+        let (filters, keywords) = split_query("lunch account:Expenses:Food:* cafe").unwrap();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(keywords, "lunch cafe");
+    }
+}
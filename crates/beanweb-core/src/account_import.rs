@@ -0,0 +1,314 @@
+//! Chart of accounts imported from CSV
+//!
+//! People moving from other tools usually have an account list export. Each
+//! row (`account,type,currencies,date`; a header row is optional, as is every
+//! column after the account) becomes an `open` directive:
+//! - Names may use `:`, `/` or ` > ` between levels; without a root account
+//!   the type column decides it (`asset`, `bank`, `expense`, `负债`, ...)
+//! - Levels are made valid beancount names: spaces become `-` and the first
+//!   letter is capitalized, so "food > eating out" opens `Expenses:Food:Eating-out`
+//! - Currencies are separated by spaces, `;` or `|`; none means unconstrained
+//! - Accounts already open, or listed twice, are skipped as duplicates
+//! - A type disagreeing with the root, or an account opening before its
+//!   parent (in the CSV or the ledger), is invalid and never written
+//!
+//! Spreadsheets pasted from Excel are tab-separated, which is accepted too.
+
+use crate::balance_import::{split_csv_line, validate_target};
+use crate::bootstrap::{add_include, is_valid_currency, ACCOUNTS_FILE, ROOTS};
+use crate::{CoreError, Ledger};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Outcome of checking one CSV row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountRowStatus {
+    /// Gets an `open` directive
+    Ok,
+    /// Already open in the ledger, or an earlier row opens it
+    Duplicate,
+    /// Can't be turned into a directive
+    Invalid,
+}
+
+/// One checked CSV row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountImportRow {
+    /// 1-based line in the CSV
+    pub line: usize,
+    /// Name as written in the CSV
+    pub source: String,
+    /// Beancount account name; empty when it can't be derived
+    pub account: String,
+    pub currencies: Vec<String>,
+    pub date: String,
+    pub status: AccountRowStatus,
+    pub message: Option<String>,
+}
+
+/// Checked CSV, before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountImportPreview {
+    pub rows: Vec<AccountImportRow>,
+}
+
+impl AccountImportPreview {
+    pub fn count(&self, status: AccountRowStatus) -> usize {
+        self.rows.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Result of writing the directives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountImportOutcome {
+    /// Written file, relative to the data directory
+    pub file: String,
+    pub opened: Vec<String>,
+    /// Rows left out as invalid or duplicate
+    pub skipped: usize,
+    /// Whether an `include` was added to the main file
+    pub include_added: bool,
+    pub preview: AccountImportPreview,
+}
+
+/// Options of an import
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AccountImportOptions {
+    /// Open date of rows without one; today when unset
+    #[serde(default)]
+    pub start_date: Option<String>,
+}
+
+/// Root account named by an exported account type
+fn root_for_type(account_type: &str) -> Option<&'static str> {
+    let account_type = account_type.trim().to_lowercase();
+    let root = match account_type.as_str() {
+        "asset" | "assets" | "bank" | "cash" | "investment" | "资产" => "Assets",
+        "liability" | "liabilities" | "credit" | "credit card" | "loan" | "负债" => "Liabilities",
+        "equity" | "权益" | "所有者权益" => "Equity",
+        "income" | "revenue" | "收入" => "Income",
+        "expense" | "expenses" | "支出" | "费用" => "Expenses",
+        _ => return None,
+    };
+    Some(root)
+}
+
+/// One level of an account name as beancount accepts it, or None
+fn normalize_component(component: &str) -> Option<String> {
+    let joined = component.split_whitespace().collect::<Vec<_>>().join("-");
+    let mut chars = joined.chars();
+    let first = chars.next()?;
+    let name: String = first.to_uppercase().chain(chars).collect();
+    let valid_start = first.is_ascii_alphanumeric() || !first.is_ascii();
+    let valid_rest = name.chars().all(|c| c.is_alphanumeric() || c == '-');
+    (valid_start && valid_rest).then_some(name)
+}
+
+/// Beancount name of an exported account: levels normalized, the root taken
+/// from the name or else the type column
+fn normalize_account(name: &str, account_type: &str) -> Result<String, String> {
+    let levels: Vec<&str> = name.split([':', '/', '>']).map(str::trim).filter(|l| !l.is_empty()).collect();
+    let mut components = Vec::with_capacity(levels.len() + 1);
+    for level in &levels {
+        components.push(normalize_component(level).ok_or_else(|| format!("账户名无效：{}", level))?);
+    }
+    let typed_root = root_for_type(account_type);
+    if !account_type.trim().is_empty() && typed_root.is_none() {
+        return Err(format!("未知的账户类型：{}", account_type.trim()));
+    }
+    match components.first().map(String::as_str) {
+        Some(root) if ROOTS.contains(&root) => {
+            if typed_root.is_some_and(|typed| typed != root) {
+                return Err(format!("类型 {} 与根账户 {} 不符", account_type.trim(), root));
+            }
+        }
+        Some(_) => match typed_root {
+            Some(root) => components.insert(0, root.to_string()),
+            None => return Err("缺少根账户（Assets/Liabilities/Equity/Income/Expenses）或账户类型".to_string()),
+        },
+        None => return Err("账户名为空".to_string()),
+    }
+    if components.len() < 2 {
+        return Err(format!("不能直接开立根账户 {}", components[0]));
+    }
+    Ok(components.join(":"))
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y/%m/%d"))
+        .ok()
+}
+
+/// Header row: the first column names the account column
+fn is_header(first: &str) -> bool {
+    matches!(first.trim().to_lowercase().as_str(), "account" | "account name" | "name" | "账户" | "账户名" | "名称")
+}
+
+/// `open` directives grouped by root account
+fn render_rows(rows: &[&AccountImportRow]) -> String {
+    let mut out = String::new();
+    for root in ROOTS {
+        let mut members: Vec<&&AccountImportRow> = rows.iter()
+            .filter(|r| r.account.split(':').next() == Some(root))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        members.sort_by(|a, b| a.account.cmp(&b.account));
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!(";; ===== {} =====\n", root));
+        for row in members {
+            out.push_str(&format!("{} open {}", row.date, row.account));
+            if !row.currencies.is_empty() {
+                out.push_str(&format!(" {}", row.currencies.join(",")));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+impl Ledger {
+    /// Check CSV rows against the ledger without writing anything
+    pub fn preview_account_import(&self, csv: &str, options: &AccountImportOptions) -> AccountImportPreview {
+        let default_date = options.start_date.clone()
+            .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+        // Open dates of the ledger accounts, then of the accepted rows
+        let mut opened: HashMap<String, String> = self.accounts().into_iter()
+            .map(|a| (a.name, a.open_date.unwrap_or_default()))
+            .collect();
+        let existing: Vec<String> = opened.keys().cloned().collect();
+        let mut rows = Vec::new();
+
+        for (index, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<String> = if line.contains('\t') {
+                line.split('\t').map(|f| f.trim().to_string()).collect()
+            } else {
+                split_csv_line(line)
+            };
+            let field = |i: usize| fields.get(i).cloned().unwrap_or_default();
+            if rows.is_empty() && is_header(&field(0)) {
+                continue;
+            }
+            let mut row = AccountImportRow {
+                line: index + 1,
+                source: field(0),
+                account: String::new(),
+                currencies: field(2).split([' ', ';', '|', ',']).filter(|c| !c.is_empty()).map(str::to_string).collect(),
+                date: Some(field(3)).filter(|d| !d.is_empty()).unwrap_or_else(|| default_date.clone()),
+                status: AccountRowStatus::Invalid,
+                message: None,
+            };
+
+            match normalize_account(&row.source, &field(1)) {
+                Ok(account) => row.account = account,
+                Err(message) => {
+                    row.message = Some(message);
+                    rows.push(row);
+                    continue;
+                }
+            }
+            let Some(date) = parse_date(&row.date) else {
+                row.message = Some(format!("日期无效：{}", row.date));
+                rows.push(row);
+                continue;
+            };
+            row.date = date.format("%Y-%m-%d").to_string();
+            if let Some(currency) = row.currencies.iter().find(|c| !is_valid_currency(c)) {
+                row.message = Some(format!("货币无效：{}", currency));
+                rows.push(row);
+                continue;
+            }
+            if opened.contains_key(&row.account) {
+                row.status = AccountRowStatus::Duplicate;
+                row.message = Some(if existing.contains(&row.account) { "账本中已开立".to_string() } else { "CSV 中重复".to_string() });
+                rows.push(row);
+                continue;
+            }
+            // The nearest opened parent must not open after its child
+            let parent = row.account.match_indices(':').rev()
+                .map(|(i, _)| &row.account[..i])
+                .find_map(|parent| opened.get(parent).map(|date| (parent.to_string(), date.clone())));
+            if let Some((parent, date)) = parent.filter(|(_, date)| date.as_str() > row.date.as_str()) {
+                row.message = Some(format!("早于父账户 {} 的开立日期 {}", parent, date));
+                rows.push(row);
+                continue;
+            }
+
+            row.status = AccountRowStatus::Ok;
+            opened.insert(row.account.clone(), row.date.clone());
+            rows.push(row);
+        }
+
+        AccountImportPreview { rows }
+    }
+
+    /// Append `open` directives for the importable rows to `file` (relative to
+    /// the data directory, `accounts.bean` by default) and include it from the main file
+    pub fn import_accounts(&self, csv: &str, file: Option<&str>, options: &AccountImportOptions) -> Result<AccountImportOutcome, CoreError> {
+        let file = validate_target(file.unwrap_or(ACCOUNTS_FILE))?;
+        let preview = self.preview_account_import(csv, options);
+        let selected: Vec<&AccountImportRow> = preview.rows.iter().filter(|r| r.status == AccountRowStatus::Ok).collect();
+        if selected.is_empty() {
+            return Err(CoreError::ValidationError { message: "No accounts to import".to_string() });
+        }
+
+        let main_path = self.config.ledger_path();
+        let path = self.config.data.path.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| CoreError::IoError)?;
+        }
+        let body = format!(";; Imported accounts ({})\n{}", chrono::Local::now().format("%Y-%m-%d"), render_rows(&selected));
+        let content = match std::fs::read_to_string(&path) {
+            Ok(current) if !current.trim().is_empty() => format!("{}\n\n{}", current.trim_end(), body),
+            _ => body,
+        };
+        self.write_document(&path.to_string_lossy(), &content)?;
+
+        let include_added = if path == main_path {
+            false
+        } else {
+            let main = std::fs::read_to_string(&main_path).unwrap_or_default();
+            match add_include(&main, file) {
+                Some(updated) => {
+                    self.write_document(&main_path.to_string_lossy(), &updated)?;
+                    true
+                }
+                None => false,
+            }
+        };
+
+        Ok(AccountImportOutcome {
+            file: file.to_string(),
+            opened: selected.iter().map(|r| r.account.clone()).collect(),
+            skipped: preview.rows.len() - selected.len(),
+            include_added,
+            preview,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_account() {
+        assert_eq!(normalize_account("Assets:Bank:Checking", "").unwrap(), "Assets:Bank:Checking");
+        assert_eq!(normalize_account("food > eating out", "Expense").unwrap(), "Expenses:Food:Eating-out");
+        assert_eq!(normalize_account("信用卡/招行", "负债").unwrap(), "Liabilities:信用卡:招行");
+        assert!(normalize_account("Assets:Bank", "expense").is_err());
+        assert!(normalize_account("Bank", "").is_err());
+        assert!(normalize_account("Bank", "unknown").is_err());
+        assert!(normalize_account("Assets", "").is_err());
+        assert!(normalize_account("Assets:My_Bank", "").is_err());
+    }
+}
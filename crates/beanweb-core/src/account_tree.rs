@@ -0,0 +1,45 @@
+//! Nodes of the account tree as shown on the accounts page
+//!
+//! Every node carries its rolled-up balance (see [`crate::rollup`]) already
+//! display-signed and formatted, so the JSON API and the rendered tree show
+//! the same numbers.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Account amount structure with calculated total and currency detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountAmount {
+    /// The calculated total amount (converted to operating currency)
+    pub calculated: CalculatedAmount,
+    /// Detailed amounts per currency
+    pub detail: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalculatedAmount {
+    pub number: String,
+    pub currency: String,
+}
+
+/// Account with full tree information for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountTreeNode {
+    pub name: String,
+    pub short_name: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(rename = "status")]
+    pub account_status: String,
+    pub amount: AccountAmount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<AccountTreeNode>>,
+    pub has_children: bool,
+    pub is_leaf: bool,
+    pub is_real: bool,  // true if this node exists in accounts table (can click to view detail)
+    pub depth: usize,
+    /// Net change of the subtree over the active time range, operating currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+}
@@ -0,0 +1,89 @@
+//! Structured posting amounts
+//!
+//! [`Posting::amount`](crate::Posting::amount) is kept for display only
+//! ("800 PI {1 CNY} @ 1 CNY"); code that needs the numbers reads the parsed
+//! units, cost and price from these types instead of re-parsing that string.
+//! Postings built outside the parser (pads, tests) may leave them unset.
+
+use crate::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// A number in a currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount {
+    pub number: Decimal,
+    pub currency: String,
+}
+
+impl Amount {
+    pub fn new(number: Decimal, currency: impl Into<String>) -> Self {
+        Self { number, currency: currency.into() }
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.number, self.currency)
+    }
+}
+
+impl From<&beanweb_parser::Amount> for Amount {
+    fn from(amount: &beanweb_parser::Amount) -> Self {
+        Self::new(amount.amount, amount.currency.clone())
+    }
+}
+
+/// Per-unit cost of a lot: `{1.20 USD}` or `{1.20 USD, 2024-01-15}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostSpec {
+    pub per_unit: Amount,
+    /// Acquisition date (YYYY-MM-DD)
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+impl From<&beanweb_parser::Cost> for CostSpec {
+    fn from(cost: &beanweb_parser::Cost) -> Self {
+        Self {
+            per_unit: Amount::new(cost.amount, cost.currency.clone()),
+            date: cost.date.map(|d| d.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+/// Price annotation: `@ 7.10 CNY` per unit, or `@@ 710 CNY` in total
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceSpec {
+    pub amount: Amount,
+    /// `@@`: `amount` is the total for all units
+    #[serde(default)]
+    pub total: bool,
+}
+
+impl PriceSpec {
+    /// Price of one unit when `units` are priced
+    pub fn per_unit(&self, units: Decimal) -> Option<Decimal> {
+        if !self.total {
+            Some(self.amount.number)
+        } else if units.is_zero() {
+            None
+        } else {
+            Some(self.amount.number / units.abs())
+        }
+    }
+
+    /// Value of `units` in the price currency, signed like `units`
+    pub fn value_of(&self, units: Decimal) -> Decimal {
+        let value = if self.total { self.amount.number.abs() } else { (units * self.amount.number).abs() };
+        if units.is_sign_negative() { -value } else { value }
+    }
+}
+
+impl From<&beanweb_parser::Price> for PriceSpec {
+    fn from(price: &beanweb_parser::Price) -> Self {
+        match price {
+            beanweb_parser::Price::Single(amount) => Self { amount: amount.into(), total: false },
+            beanweb_parser::Price::Total(amount) => Self { amount: amount.into(), total: true },
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Anonymized ledger export
+//!
+//! Produces a Beancount copy of the loaded ledger that is safe to attach to
+//! bug reports:
+//! - Payees, narrations, tags and links are replaced by hashes
+//! - Account components below the root are replaced by random names
+//! - Amounts are optionally multiplied by a scale factor and rounded to
+//!   cents; the rounding left over in a currency goes to the last plain
+//!   posting in it, so scaled transactions still balance
+//!
+//! Every replacement is a salted hash, so within one export the same input
+//! always maps to the same output and the ledger structure is preserved.
+
+use crate::integrity::residuals;
+use crate::{render, Decimal, Ledger, Posting, Transaction};
+
+/// Options for anonymized export
+#[derive(Debug, Clone, Default)]
+pub struct AnonymizeOptions {
+    /// Multiply every amount by this factor (prices and costs stay per-unit)
+    pub scale: Option<Decimal>,
+    /// Salt for the mapping; a random one is used when absent
+    pub seed: Option<String>,
+}
+
+/// Consistent name mapping for one export
+struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    fn new(seed: Option<String>) -> Self {
+        Self {
+            salt: seed.unwrap_or_else(beanweb_utils::generate_id),
+        }
+    }
+
+    fn hash(&self, value: &str) -> String {
+        beanweb_parser::short_hash(&format!("{}:{}", self.salt, value))
+    }
+
+    /// Hash free text (payee, narration); empty stays empty
+    fn text(&self, value: &str) -> String {
+        if value.is_empty() {
+            String::new()
+        } else {
+            format!("t-{}", self.hash(value))
+        }
+    }
+
+    /// Hash a tag or link so it stays a valid identifier
+    fn label(&self, value: &str) -> String {
+        format!("l{}", self.hash(value))
+    }
+
+    /// Keep the root (Assets, Expenses, ...) and replace every other component
+    fn account(&self, name: &str) -> String {
+        let mut parts = name.split(':');
+        let mut result = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            result.push_str(&format!(":A{}", self.hash(&format!("component:{}", part))));
+        }
+        result
+    }
+}
+
+/// Scale the leading number of an amount string, keeping per-unit cost and price
+/// e.g. "10 STOCK {5 USD} @ 6 USD" with scale 2 -> "20.00 STOCK {5 USD} @ 6 USD"
+/// A total price (`@@`) is scaled as well so the transaction still balances
+fn scale_amount(amount: &str, scale: Option<Decimal>) -> String {
+    let Some(scale) = scale else {
+        return amount.to_string();
+    };
+    if let Some((units, total)) = amount.split_once("@@") {
+        return format!("{} @@ {}", scale_amount(units, Some(scale)), scale_amount(total, Some(scale)));
+    }
+    let trimmed = amount.trim();
+    let (number, rest) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+    match number.replace(',', "").parse::<Decimal>() {
+        Ok(value) if rest.is_empty() => format!("{:.2}", (value * scale).round_dp(2)),
+        Ok(value) => format!("{:.2} {}", (value * scale).round_dp(2), rest),
+        Err(_) => amount.to_string(),
+    }
+}
+
+/// Postings of `tx` with their units (and `@@` totals) scaled and rounded to
+/// cents. Unless a posting is elided and absorbs it anyway, the rounding
+/// residual of each currency is taken off the last posting in that currency
+/// without cost or price
+fn scale_postings(tx: &Transaction, scale: Option<Decimal>) -> Vec<Posting> {
+    let Some(scale) = scale else {
+        return tx.postings.clone();
+    };
+    let rounded = |number: Decimal| (number * scale).round_dp(2);
+    let mut scaled = tx.clone();
+    for posting in &mut scaled.postings {
+        match &mut posting.units {
+            Some(units) => units.number = rounded(units.number),
+            None => posting.amount = scale_amount(&posting.amount, Some(scale)),
+        }
+        if let Some(price) = posting.price_spec.as_mut().filter(|p| p.total) {
+            price.amount.number = rounded(price.amount.number);
+        }
+    }
+    if scaled.postings.iter().all(|p| !p.amount.is_empty()) {
+        for (currency, residual) in residuals(&scaled) {
+            let plain = scaled.postings.iter_mut().rev().find(|p| {
+                p.cost_spec.is_none() && p.price_spec.is_none() && p.units.as_ref().is_some_and(|u| u.currency == currency)
+            });
+            if let Some(units) = plain.and_then(|p| p.units.as_mut()) {
+                units.number -= residual;
+            }
+        }
+    }
+    scaled.postings
+}
+
+impl Ledger {
+    /// Export the ledger as anonymized Beancount text
+    pub fn anonymized_export(&self, options: &AnonymizeOptions) -> String {
+        let data = self.data.read().unwrap();
+        let anon = Anonymizer::new(options.seed.clone());
+        let mut out = String::from("; Anonymized export generated by beanweb\n\n");
+
+        for account in &data.accounts {
+            let date = account.open_date.clone().unwrap_or_else(|| "1970-01-01".to_string());
+            let currency = account.currency.clone().map(|c| format!(" {}", c)).unwrap_or_default();
+            out.push_str(&format!("{} open {}{}\n", date, anon.account(&account.name), currency));
+        }
+        out.push('\n');
+
+        // Synthesized pad transactions are re-created from the pad directives
+        let mut transactions: Vec<&Transaction> = data.transactions
+            .iter()
+            .filter(|t| !t.id.starts_with("pad-"))
+            .collect();
+        transactions.sort_by(|a, b| a.date.cmp(&b.date));
+
+        for tx in transactions {
+            let mut header = format!("{} {}", tx.date, tx.flag.as_deref().unwrap_or("*"));
+            let payee = anon.text(&tx.payee);
+            if !payee.is_empty() {
+                header.push_str(&format!(" \"{}\"", payee));
+            }
+            header.push_str(&format!(" \"{}\"", anon.text(&tx.narration)));
+            for tag in &tx.tags {
+                header.push_str(&format!(" #{}", anon.label(tag)));
+            }
+            for link in &tx.links {
+                header.push_str(&format!(" ^{}", anon.label(link)));
+            }
+            out.push_str(&header);
+            out.push('\n');
+            for mut posting in scale_postings(tx, options.scale) {
+                posting.account = anon.account(&posting.account);
+                out.push_str(&format!("    {}\n", render::posting(&posting)));
+            }
+            out.push('\n');
+        }
+
+        for pad in &data.pads {
+            out.push_str(&format!("{} pad {} {}\n", pad.date, anon.account(&pad.account), anon.account(&pad.source_account)));
+        }
+        for balance in &data.balances {
+            let amount = format!("{} {}", balance.amount, balance.currency);
+            out.push_str(&format!("{} balance {}    {}\n", balance.date, anon.account(&balance.account), scale_amount(&amount, options.scale)));
+        }
+        for account in data.accounts.iter().filter(|a| a.close_date.is_some()) {
+            out.push_str(&format!("{} close {}\n", account.close_date.as_deref().unwrap_or_default(), anon.account(&account.name)));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_mapping_is_consistent() {
+        let anon = Anonymizer::new(Some("seed".to_string()));
+        let a = anon.account("Assets:Bank:Checking");
+        let b = anon.account("Liabilities:Bank:Card");
+        assert!(a.starts_with("Assets:"));
+        assert!(b.starts_with("Liabilities:"));
+        assert_eq!(a.split(':').nth(1), b.split(':').nth(1));
+        assert_ne!(a, "Assets:Bank:Checking");
+        assert_eq!(a, anon.account("Assets:Bank:Checking"));
+    }
+
+    #[test]
+    fn test_scale_amount() {
+        let scale = |text: &str| Some(text.parse::<Decimal>().unwrap());
+        assert_eq!(scale_amount("100.00 CNY", scale("0.5")), "50.00 CNY");
+        assert_eq!(scale_amount("10 STOCK {5 USD}", scale("2")), "20.00 STOCK {5 USD}");
+        assert_eq!(scale_amount("2 USD @@ 14 CNY", scale("2")), "4.00 USD @@ 28.00 CNY");
+        assert_eq!(scale_amount("0.10 CNY", scale("3")), "0.30 CNY");
+        assert_eq!(scale_amount("100.00 CNY", None), "100.00 CNY");
+    }
+}
@@ -0,0 +1,342 @@
+//! Historical balance assertions imported from CSV
+//!
+//! Spreadsheets of month-end balances become `balance` directives. Each row
+//! (`date,account,amount,currency`; a header row is optional, an empty
+//! currency means the default one) is checked against the balance computed
+//! from the postings before its date, so the preview shows which assertions
+//! would fail before anything is written:
+//! - Rows for unknown or not yet open accounts are invalid and never written
+//! - Rows matching an existing `balance` directive are skipped as duplicates
+//! - Rows after a `pad` of their account, with no assertion in between, are
+//!   left out: the pad would fill the account up to whatever they assert
+//! - With `end_of_day`, balances are taken as of the end of the listed day
+//!   and asserted on the next one (beancount checks at the start of the day)
+
+use crate::bootstrap::{add_include, is_valid_currency};
+use crate::integrity::TOLERANCE;
+use crate::{CoreError, Decimal, Ledger};
+use rust_decimal::prelude::ToPrimitive;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+/// File the directives are written to unless another one is chosen
+pub const DEFAULT_BALANCES_FILE: &str = "balances.bean";
+
+/// Outcome of checking one CSV row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceRowStatus {
+    /// Matches the computed balance
+    Ok,
+    /// Will fail as an assertion
+    Fails,
+    /// Same assertion already in the ledger
+    Duplicate,
+    /// A `pad` before it would make it hold, whatever it asserts
+    Padded,
+    /// Can't be turned into a directive
+    Invalid,
+}
+
+/// One checked CSV row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceImportRow {
+    /// 1-based line in the CSV
+    pub line: usize,
+    /// Assertion date (the day after the CSV date with `end_of_day`)
+    pub date: String,
+    pub account: String,
+    pub amount: String,
+    pub currency: String,
+    /// Balance computed from the postings before `date`
+    pub computed: Option<f64>,
+    pub status: BalanceRowStatus,
+    pub message: Option<String>,
+}
+
+impl BalanceImportRow {
+    /// Whether the row becomes a directive when imported
+    pub fn is_importable(&self) -> bool {
+        matches!(self.status, BalanceRowStatus::Ok | BalanceRowStatus::Fails)
+    }
+}
+
+/// Rows of one month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceImportMonth {
+    /// YYYY-MM
+    pub month: String,
+    pub rows: usize,
+    pub failing: usize,
+}
+
+/// Checked CSV, before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceImportPreview {
+    pub rows: Vec<BalanceImportRow>,
+    /// Per-month counts, oldest first; months with `failing > 0` need attention
+    pub months: Vec<BalanceImportMonth>,
+}
+
+impl BalanceImportPreview {
+    pub fn count(&self, status: BalanceRowStatus) -> usize {
+        self.rows.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Result of writing the directives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceImportOutcome {
+    /// Written file, relative to the data directory
+    pub file: String,
+    pub written: usize,
+    /// Rows left out: invalid, duplicate, padded, or failing with
+    /// `skip_failing`
+    pub skipped: usize,
+    /// Whether an `include` was added to the main file
+    pub include_added: bool,
+    pub preview: BalanceImportPreview,
+}
+
+/// Options of an import
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceImportOptions {
+    /// Balances are as of the end of the listed day
+    #[serde(default)]
+    pub end_of_day: bool,
+    /// Leave out rows that would fail as assertions; on unless turned off,
+    /// so an import never breaks the ledger's checks
+    #[serde(default = "default_skip_failing")]
+    pub skip_failing: bool,
+}
+
+fn default_skip_failing() -> bool {
+    true
+}
+
+impl Default for BalanceImportOptions {
+    fn default() -> Self {
+        Self { end_of_day: false, skip_failing: default_skip_failing() }
+    }
+}
+
+/// Split a CSV line into fields; double quotes group commas ("1,234.00")
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y/%m/%d"))
+        .ok()
+}
+
+/// Normalize "1,234.50" / "-20" to a plain number with two decimals at least
+pub(crate) fn parse_number(text: &str) -> Option<String> {
+    let plain: String = text.chars().filter(|c| *c != ',' && *c != ' ').collect();
+    plain.parse::<f64>().ok()?;
+    Some(match plain.split_once('.') {
+        Some((_, decimals)) if decimals.len() >= 2 => plain,
+        Some((whole, decimals)) => format!("{}.{:0<2}", whole, decimals),
+        None => format!("{}.00", plain),
+    })
+}
+
+/// Relative `.bean` path inside the data directory
+pub(crate) fn validate_target(file: &str) -> Result<&str, CoreError> {
+    let path = Path::new(file);
+    let inside = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if file.is_empty() || !inside || path.extension().is_none_or(|ext| ext != "bean") {
+        return Err(CoreError::ValidationError { message: format!("Invalid target file: {}", file) });
+    }
+    Ok(file)
+}
+
+impl Ledger {
+    /// Check CSV rows against the ledger without writing anything
+    pub fn preview_balance_import(&self, csv: &str, options: &BalanceImportOptions) -> BalanceImportPreview {
+        let default_currency = self.report_currency();
+        let data = self.data.read().unwrap();
+        let mut rows = Vec::new();
+
+        for (index, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = split_csv_line(line);
+            let field = |i: usize| fields.get(i).cloned().unwrap_or_default();
+            // Header row: no digits where the first date would be
+            if index == 0 && !field(0).chars().any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let currency = Some(field(3)).filter(|c| !c.is_empty()).unwrap_or_else(|| default_currency.clone());
+            let mut row = BalanceImportRow {
+                line: index + 1,
+                date: field(0),
+                account: field(1),
+                amount: field(2),
+                currency,
+                computed: None,
+                status: BalanceRowStatus::Invalid,
+                message: None,
+            };
+
+            let Some(date) = parse_date(&row.date) else {
+                row.message = Some(format!("日期无效：{}", row.date));
+                rows.push(row);
+                continue;
+            };
+            let date = if options.end_of_day { date + Duration::days(1) } else { date };
+            row.date = date.format("%Y-%m-%d").to_string();
+            let Some(amount) = parse_number(&row.amount) else {
+                row.message = Some(format!("金额无效：{}", row.amount));
+                rows.push(row);
+                continue;
+            };
+            row.amount = amount;
+            if !is_valid_currency(&row.currency) {
+                row.message = Some(format!("货币无效：{}", row.currency));
+                rows.push(row);
+                continue;
+            }
+            let Some(account) = data.accounts.iter().find(|a| a.name == row.account) else {
+                row.message = Some(format!("账户不存在：{}", row.account));
+                rows.push(row);
+                continue;
+            };
+            if account.open_date.as_deref().is_some_and(|open| open > row.date.as_str()) {
+                row.message = Some(format!("账户于 {} 才开立", account.open_date.as_deref().unwrap_or("")));
+                rows.push(row);
+                continue;
+            }
+
+            let duplicate = data.balances.iter()
+                .any(|b| b.account == row.account && b.currency == row.currency && b.date == row.date);
+            // The first assertion after a pad is the one the pad fills up to
+            let padded = data.pads.iter().any(|pad| {
+                pad.account == row.account && pad.date < row.date && !data.balances.iter().any(|b| {
+                    b.account == row.account && b.currency == row.currency && b.date > pad.date && b.date < row.date
+                })
+            });
+            let computed = crate::integrity::computed_balance(
+                data.transactions.iter().filter(|tx| tx.date < row.date),
+                &row.account,
+                &row.currency,
+            );
+            let expected: Decimal = row.amount.parse().unwrap_or_default();
+            row.computed = computed.to_f64();
+            if duplicate {
+                row.status = BalanceRowStatus::Duplicate;
+                row.message = Some("账本中已有相同的余额断言".to_string());
+            } else if padded {
+                row.status = BalanceRowStatus::Padded;
+                row.message = Some("之前的 pad 会补齐到该断言，不导入".to_string());
+            } else if (computed - expected).abs() > TOLERANCE {
+                row.status = BalanceRowStatus::Fails;
+                row.message = Some(format!("实际 {:.2} {}（差额 {:.2}）", computed, row.currency, computed - expected));
+            } else {
+                row.status = BalanceRowStatus::Ok;
+            }
+            rows.push(row);
+        }
+        drop(data);
+
+        // Rows in file order within a month; months oldest first
+        let mut months: BTreeMap<String, BalanceImportMonth> = BTreeMap::new();
+        for row in rows.iter().filter(|r| r.status != BalanceRowStatus::Invalid) {
+            let month = row.date.get(..7).unwrap_or(&row.date).to_string();
+            let entry = months.entry(month.clone()).or_insert(BalanceImportMonth { month, rows: 0, failing: 0 });
+            entry.rows += 1;
+            if row.status == BalanceRowStatus::Fails {
+                entry.failing += 1;
+            }
+        }
+
+        BalanceImportPreview { rows, months: months.into_values().collect() }
+    }
+
+    /// Append the importable rows as `balance` directives to `file` (relative
+    /// to the data directory) and include it from the main file
+    pub fn import_balances(&self, csv: &str, file: &str, options: &BalanceImportOptions) -> Result<BalanceImportOutcome, CoreError> {
+        let file = validate_target(file)?;
+        let preview = self.preview_balance_import(csv, options);
+        let mut selected: Vec<&BalanceImportRow> = preview.rows.iter()
+            .filter(|r| r.is_importable() && !(options.skip_failing && r.status == BalanceRowStatus::Fails))
+            .collect();
+        selected.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.account.cmp(&b.account)));
+        if selected.is_empty() {
+            return Err(CoreError::ValidationError { message: "No balance assertions to import".to_string() });
+        }
+
+        let main_path = self.config.ledger_path();
+        let path = self.config.data.path.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| CoreError::IoError)?;
+        }
+        let mut body = format!(";; Imported balance assertions ({})\n", chrono::Local::now().format("%Y-%m-%d"));
+        for row in &selected {
+            body.push_str(&format!("{} balance {} {} {}\n", row.date, row.account, row.amount, row.currency));
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(current) if !current.trim().is_empty() => format!("{}\n\n{}", current.trim_end(), body),
+            _ => body,
+        };
+        self.write_document(&path.to_string_lossy(), &content)?;
+
+        let include_added = if path == main_path {
+            false
+        } else {
+            let main = std::fs::read_to_string(&main_path).unwrap_or_default();
+            match add_include(&main, file) {
+                Some(updated) => {
+                    self.write_document(&main_path.to_string_lossy(), &updated)?;
+                    true
+                }
+                None => false,
+            }
+        };
+
+        Ok(BalanceImportOutcome {
+            file: file.to_string(),
+            written: selected.len(),
+            skipped: preview.rows.len() - selected.len(),
+            include_added,
+            preview,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_fields() {
+        assert_eq!(split_csv_line(r#"2024-01-31, Assets:Bank ,"1,234.5",CNY"#), vec!["2024-01-31", "Assets:Bank", "1,234.5", "CNY"]);
+        assert_eq!(parse_number("1,234.5").as_deref(), Some("1234.50"));
+        assert_eq!(parse_number("-20").as_deref(), Some("-20.00"));
+        assert_eq!(parse_number("abc"), None);
+        assert!(validate_target("imports/balances.bean").is_ok());
+        assert!(validate_target("../outside.bean").is_err());
+        assert!(validate_target("/etc/balances.bean").is_err());
+        assert!(validate_target("balances.txt").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Account bootstrap from template hierarchies
+//!
+//! A new ledger needs dozens of `open` directives. The presets under
+//! `templates/accounts/` (one account per line, `;` starts a comment, the
+//! first comment is the label) are turned into an `accounts.bean` grouped by
+//! root account, and the main file gets an `include` for it:
+//! - Accounts that are already open in the ledger are skipped
+//! - An existing `accounts.bean` is kept; new accounts are appended to it,
+//!   with the previous version backed up as for any other write
+
+use crate::{CoreError, Ledger};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// File the opened accounts are written to, next to the main ledger file
+pub const ACCOUNTS_FILE: &str = "accounts.bean";
+
+/// Shipped presets: (name, file content)
+const PRESETS: &[(&str, &str)] = &[
+    ("personal", include_str!("../templates/accounts/personal.txt")),
+    ("freelancer", include_str!("../templates/accounts/freelancer.txt")),
+    ("family", include_str!("../templates/accounts/family.txt")),
+];
+
+/// Root accounts in the order they appear in the generated file
+pub(crate) const ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+/// An account tree preset
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountTemplate {
+    pub name: String,
+    pub label: String,
+    pub accounts: Vec<String>,
+}
+
+/// Result of applying a template
+#[derive(Debug, Clone, Serialize)]
+pub struct BootstrapOutcome {
+    /// Written file, relative to the data directory
+    pub file: String,
+    /// Accounts that got an `open` directive
+    pub opened: Vec<String>,
+    /// Accounts skipped because they are already open
+    pub skipped: Vec<String>,
+    /// Whether an `include` was added to the main file
+    pub include_added: bool,
+}
+
+/// All shipped presets
+pub fn account_templates() -> Vec<AccountTemplate> {
+    PRESETS.iter().map(|(name, content)| parse_template(name, content)).collect()
+}
+
+/// Look up a preset by name
+pub fn account_template(name: &str) -> Option<AccountTemplate> {
+    account_templates().into_iter().find(|t| t.name == name)
+}
+
+fn parse_template(name: &str, content: &str) -> AccountTemplate {
+    let label = content.lines()
+        .find_map(|line| line.trim().strip_prefix(';'))
+        .map(|label| label.trim().to_string())
+        .unwrap_or_else(|| name.to_string());
+    let accounts = content.lines()
+        .map(|line| line.split(';').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    AccountTemplate { name: name.to_string(), label, accounts }
+}
+
+/// Whether `currency` is a valid beancount commodity name (e.g. "CNY", "VT.US")
+pub(crate) fn is_valid_currency(currency: &str) -> bool {
+    let chars: Vec<char> = currency.chars().collect();
+    chars.len() >= 2
+        && chars.len() <= 24
+        && chars[0].is_ascii_uppercase()
+        && chars.last().is_some_and(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && chars.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(*c))
+}
+
+/// `open` directives grouped by root account, one section per root
+pub fn render_open_directives(accounts: &[String], currency: &str, date: &str) -> String {
+    let mut out = String::new();
+    for root in ROOTS {
+        let mut members: Vec<&String> = accounts.iter()
+            .filter(|a| a.split(':').next() == Some(root))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        members.sort();
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!(";; ===== {} =====\n", root));
+        for account in members {
+            out.push_str(&format!("{} open {} {}\n", date, account, currency));
+        }
+    }
+    out
+}
+
+/// Add `include "<file>"` to a ledger source unless present, after the
+/// leading option/include/plugin block; returns None when already included
+pub(crate) fn add_include(source: &str, file: &str) -> Option<String> {
+    let directive = format!("include \"{}\"", file);
+    if source.lines().any(|line| line.trim() == directive) {
+        return None;
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    let header_end = lines.iter()
+        .position(|line| {
+            let line = line.trim();
+            !(line.is_empty() || line.starts_with(';') || line.starts_with("option")
+                || line.starts_with("include") || line.starts_with("plugin"))
+        })
+        .unwrap_or(lines.len());
+    // Right after the last header directive, or at the very top
+    let at = lines[..header_end].iter()
+        .rposition(|line| {
+            let line = line.trim();
+            line.starts_with("option") || line.starts_with("include") || line.starts_with("plugin")
+        })
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let mut out: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    out.insert(at, directive);
+    if at == 0 && !lines.is_empty() {
+        out.insert(1, String::new());
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    Some(text)
+}
+
+impl Ledger {
+    /// Open every account of a preset in `accounts.bean` and include it from the main file
+    pub fn bootstrap_accounts(&self, template: &str, currency: &str, date: &str) -> Result<BootstrapOutcome, CoreError> {
+        let template = account_template(template).ok_or_else(|| CoreError::ValidationError {
+            message: format!("Unknown account template: {}", template),
+        })?;
+        if !is_valid_currency(currency) {
+            return Err(CoreError::ValidationError { message: format!("Invalid currency: {}", currency) });
+        }
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(CoreError::ValidationError { message: format!("Invalid date: {}", date) });
+        }
+
+        let existing: std::collections::HashSet<String> = self.accounts().into_iter().map(|a| a.name).collect();
+        let (skipped, opened): (Vec<String>, Vec<String>) = template.accounts
+            .into_iter()
+            .partition(|a| existing.contains(a));
+
+        let main_path = self.config.ledger_path();
+        let path = main_path.with_file_name(ACCOUNTS_FILE);
+        if !opened.is_empty() {
+            let body = render_open_directives(&opened, currency, date);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(current) if !current.trim().is_empty() => {
+                    format!("{}\n;; Added from template \"{}\"\n{}", current.trim_end(), template.name, body)
+                }
+                _ => format!(";; Accounts - generated from template \"{}\" ({})\n\n{}", template.name, template.label, body),
+            };
+            self.write_document(&path.to_string_lossy(), &content)?;
+        }
+
+        let main = std::fs::read_to_string(&main_path).unwrap_or_default();
+        let include_added = match add_include(&main, ACCOUNTS_FILE) {
+            Some(updated) if path.exists() => {
+                self.write_document(&main_path.to_string_lossy(), &updated)?;
+                true
+            }
+            _ => false,
+        };
+
+        Ok(BootstrapOutcome {
+            file: path.strip_prefix(&self.config.data.path).unwrap_or(&path).to_string_lossy().to_string(),
+            opened,
+            skipped,
+            include_added,
+        })
+    }
+}
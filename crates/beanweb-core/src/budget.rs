@@ -0,0 +1,298 @@
+//! Budgets from `custom "budget"` directives
+//!
+//! Budgets use Fava's syntax, one directive per account and period:
+//!
+//! ```text
+//! 2024-01-01 custom "budget" Expenses:Food "monthly" 1500.00 CNY
+//! ```
+//!
+//! Periods are `weekly`, `monthly`, `quarterly` and `yearly`. A later
+//! directive for the same account replaces the earlier one from its date on.
+//! The budget of a time range is built month by month: every month the range
+//! touches takes the directive in effect on its last day.
+//! - Monthly, quarterly and yearly budgets are spread over the months of
+//!   their period (a quarterly budget counts a third per month)
+//! - Weekly budgets count a seventh per day
+//! - A month the range only partly covers counts by day, and an item is
+//!   marked `prorated` when the range doesn't start and end on the bounds of
+//!   its period (a weekly budget over a month, a monthly one over a week)
+//!
+//! Spending is what was posted to the account and its sub-accounts in the
+//! budget's currency. With all time selected, the range runs from the first
+//! budget to today.
+
+use crate::{Decimal, Ledger};
+use beanweb_parser::CustomDirective;
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `custom` directive type holding a budget
+pub const BUDGET_TYPE: &str = "budget";
+
+/// How often a budget amount is available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl BudgetPeriod {
+    /// Length of the period in months, None for weekly
+    pub fn months(&self) -> Option<u32> {
+        match self {
+            BudgetPeriod::Weekly => None,
+            BudgetPeriod::Monthly => Some(1),
+            BudgetPeriod::Quarterly => Some(3),
+            BudgetPeriod::Yearly => Some(12),
+        }
+    }
+
+    /// First day of the period `date` is in; weeks start on Monday
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        let month = |m: u32| NaiveDate::from_ymd_opt(date.year(), m, 1).unwrap_or(date);
+        match self {
+            BudgetPeriod::Weekly => date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64),
+            BudgetPeriod::Monthly => month(date.month()),
+            BudgetPeriod::Quarterly => month((date.month() - 1) / 3 * 3 + 1),
+            BudgetPeriod::Yearly => month(1),
+        }
+    }
+
+    /// Last day of the period `date` is in
+    pub fn period_end(&self, date: NaiveDate) -> NaiveDate {
+        let start = self.period_start(date);
+        match self.months() {
+            None => start + chrono::Duration::days(6),
+            Some(months) => month_end(start.checked_add_months(chrono::Months::new(months - 1)).unwrap_or(start)),
+        }
+    }
+
+    /// Whether `start`..=`end` is made of whole periods
+    pub fn aligns_with(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.period_start(start) == start && self.period_end(end) == end
+    }
+}
+
+impl std::str::FromStr for BudgetPeriod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "weekly" | "week" => Ok(BudgetPeriod::Weekly),
+            "monthly" | "month" => Ok(BudgetPeriod::Monthly),
+            "quarterly" | "quarter" => Ok(BudgetPeriod::Quarterly),
+            "yearly" | "year" | "annual" => Ok(BudgetPeriod::Yearly),
+            _ => Err(format!("Invalid budget period: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for BudgetPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetPeriod::Weekly => write!(f, "weekly"),
+            BudgetPeriod::Monthly => write!(f, "monthly"),
+            BudgetPeriod::Quarterly => write!(f, "quarterly"),
+            BudgetPeriod::Yearly => write!(f, "yearly"),
+        }
+    }
+}
+
+/// One `custom "budget"` directive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    /// First day the budget applies (YYYY-MM-DD)
+    pub date: String,
+    pub account: String,
+    pub period: BudgetPeriod,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+/// Budget and spending of one account over the selected range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetItem {
+    pub account: String,
+    /// Period of the budget in effect at the end of the range
+    pub period: BudgetPeriod,
+    pub currency: String,
+    /// Amount of that budget per period
+    pub period_amount: f64,
+    /// Budget over the range, prorated from the period amounts
+    pub budget: f64,
+    pub spent: f64,
+    /// `budget - spent`, negative when over budget
+    pub remaining: f64,
+    /// Spent share of the budget, 0-100 or more when over budget
+    pub percent: f64,
+    /// The range isn't made of whole periods of the budget
+    pub prorated: bool,
+}
+
+/// Budgets of the selected range, by account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetReport {
+    /// First and last day of the range (YYYY-MM-DD)
+    pub start: String,
+    pub end: String,
+    pub items: Vec<BudgetItem>,
+}
+
+/// Read a budget from `custom "budget" Account "period" amount currency`
+pub(crate) fn parse_budget(custom: &CustomDirective) -> Option<Budget> {
+    if custom.custom_type.trim_matches('"') != BUDGET_TYPE {
+        return None;
+    }
+    let [account, period, amount, currency] = custom.values.as_slice() else {
+        return None;
+    };
+    let date = match &custom.date {
+        beanweb_parser::Date::Date(d) => *d,
+        beanweb_parser::Date::DateTime(dt) => dt.date(),
+    };
+    Some(Budget {
+        date: date.format("%Y-%m-%d").to_string(),
+        account: account.trim_matches('"').to_string(),
+        period: period.trim_matches('"').parse().ok()?,
+        amount: amount.replace(',', "").parse().ok()?,
+        currency: currency.to_string(),
+    })
+}
+
+/// Last day of the month `date` is in
+fn month_end(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1).and_then(|d| d.pred_opt()).unwrap_or(date)
+}
+
+impl Ledger {
+    /// Every budget directive, oldest first
+    pub fn budgets(&self) -> Vec<Budget> {
+        let mut budgets = self.data.read().unwrap().budgets.clone();
+        budgets.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.account.cmp(&b.account)));
+        budgets
+    }
+
+    /// Spent vs budget per account for the active time range
+    pub fn budget_report(&self) -> BudgetReport {
+        let context = self.time_context();
+        let budgets = self.budgets();
+        let today = context.as_of().unwrap_or_else(|| chrono::Local::now().date_naive());
+        let first_budget = budgets.first().and_then(|b| NaiveDate::parse_from_str(&b.date, "%Y-%m-%d").ok());
+        let start = context.start_date().or(first_budget).unwrap_or(today);
+        let end = context.end_date().unwrap_or(today).max(start);
+        self.budget_report_between(start, end)
+    }
+
+    /// Spent vs budget per account from `start` to `end` (both inclusive)
+    pub fn budget_report_between(&self, start: NaiveDate, end: NaiveDate) -> BudgetReport {
+        let budgets = self.budgets();
+
+        // Budget per account, month by month
+        let mut planned: BTreeMap<&str, (Decimal, &Budget)> = BTreeMap::new();
+        let mut month = start.with_day(1).unwrap_or(start);
+        while month <= end {
+            let last_day = month_end(month).format("%Y-%m-%d").to_string();
+            let mut in_effect: BTreeMap<&str, &Budget> = BTreeMap::new();
+            for budget in budgets.iter().filter(|b| b.date <= last_day) {
+                in_effect.insert(&budget.account, budget);
+            }
+            // Days of the month within the range
+            let month_days = Decimal::from(month_end(month).day());
+            let days = Decimal::from((month_end(month).min(end) - month.max(start)).num_days() + 1);
+            for (account, budget) in in_effect {
+                let share = match budget.period.months() {
+                    None => budget.amount * days / Decimal::from(7),
+                    Some(months) => budget.amount * days / month_days / Decimal::from(months),
+                };
+                let entry = planned.entry(account).or_insert((Decimal::ZERO, budget));
+                entry.0 += share;
+                entry.1 = budget;
+            }
+            let Some(next) = month_end(month).succ_opt() else { break };
+            month = next;
+        }
+
+        let start_str = start.format("%Y-%m-%d").to_string();
+        let end_str = end.format("%Y-%m-%d").to_string();
+        let data = self.data.read().unwrap();
+        let mut spent: BTreeMap<&str, Decimal> = BTreeMap::new();
+        for tx in data.transactions.iter().filter(|tx| tx.date >= start_str && tx.date <= end_str) {
+            for ((account, currency), amount) in crate::links::posting_units(tx) {
+                for (budget_account, (_, budget)) in &planned {
+                    let prefix = format!("{}:", budget_account);
+                    if currency == budget.currency && (account == *budget_account || account.starts_with(&prefix)) {
+                        *spent.entry(budget_account).or_default() += amount;
+                    }
+                }
+            }
+        }
+        drop(data);
+
+        let items = planned.into_iter()
+            .map(|(account, (planned, budget))| {
+                let used = spent.get(account).copied().unwrap_or_default();
+                BudgetItem {
+                    account: account.to_string(),
+                    period: budget.period,
+                    currency: budget.currency.clone(),
+                    period_amount: budget.amount.to_f64().unwrap_or(0.0),
+                    budget: planned.to_f64().unwrap_or(0.0),
+                    spent: used.to_f64().unwrap_or(0.0),
+                    remaining: (planned - used).to_f64().unwrap_or(0.0),
+                    percent: crate::percent_of(used, planned),
+                    prorated: !budget.period.aligns_with(start, end),
+                }
+            })
+            .collect();
+
+        BudgetReport { start: start_str, end: end_str, items }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_budget() {
+        let custom = CustomDirective {
+            date: beanweb_parser::Date::Date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+            custom_type: "\"budget\"".to_string(),
+            values: vec!["Expenses:Food".to_string(), "\"quarterly\"".to_string(), "1,500.00".to_string(), "CNY".to_string()],
+        };
+        let budget = parse_budget(&custom).unwrap();
+        assert_eq!(budget.account, "Expenses:Food");
+        assert_eq!(budget.period, BudgetPeriod::Quarterly);
+        assert_eq!(budget.amount, Decimal::new(150000, 2));
+
+        let other = CustomDirective { custom_type: "\"fava-option\"".to_string(), ..custom };
+        assert!(parse_budget(&other).is_none());
+        assert_eq!(month_end(NaiveDate::from_ymd_opt(2024, 2, 10).unwrap()), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+    }
+
+    #[test]
+    fn test_budget_periods() {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        assert_eq!("weekly".parse::<BudgetPeriod>().unwrap(), BudgetPeriod::Weekly);
+        // 2024-05-15 is a Wednesday
+        assert_eq!(BudgetPeriod::Weekly.period_start(date("2024-05-15")), date("2024-05-13"));
+        assert_eq!(BudgetPeriod::Weekly.period_end(date("2024-05-15")), date("2024-05-19"));
+        assert_eq!(BudgetPeriod::Quarterly.period_start(date("2024-05-15")), date("2024-04-01"));
+        assert_eq!(BudgetPeriod::Quarterly.period_end(date("2024-05-15")), date("2024-06-30"));
+        assert_eq!(BudgetPeriod::Yearly.period_end(date("2024-05-15")), date("2024-12-31"));
+
+        assert!(BudgetPeriod::Monthly.aligns_with(date("2024-04-01"), date("2024-05-31")));
+        assert!(!BudgetPeriod::Quarterly.aligns_with(date("2024-04-01"), date("2024-05-31")));
+        assert!(!BudgetPeriod::Weekly.aligns_with(date("2024-05-01"), date("2024-05-31")));
+        assert!(BudgetPeriod::Weekly.aligns_with(date("2024-05-13"), date("2024-05-26")));
+
+        // Weekly budgets have no month length to scale by
+        assert_eq!(BudgetPeriod::Weekly.months(), None);
+        assert_eq!(BudgetPeriod::Quarterly.months(), Some(3));
+    }
+}
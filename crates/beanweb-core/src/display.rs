@@ -0,0 +1,158 @@
+//! One amount per transaction for list rows
+//!
+//! With two postings the amount moved is obvious; split transactions (a
+//! salary with tax and pension, a receipt over several categories) need a
+//! rule for the headline amount:
+//! - Touching own accounts (Assets, Liabilities) and others: the net change
+//!   of the own accounts, so a salary shows the take-home pay and a split
+//!   purchase what left the bank
+//! - Only own accounts: a transfer, showing the amount moved
+//! - No own accounts: the income earned, else the expenses
+//!
+//! Account types follow the ledger's `name_*` options. Amounts are weights
+//! (units at their cost or price), elided postings count with their
+//! inferred amount. The headline is in the first currency of the postings
+//! deciding it; its direction is the [`Flow`], and its displayed sign
+//! follows the sign convention: money in reads like income, money out like
+//! expenses. [`Transaction::breakdown`] has the amount of every posting, for
+//! the row's tooltip.
+
+use crate::integrity::{posting_weight, residuals, TOLERANCE};
+use crate::options::LedgerOptions;
+use crate::{decimal_string, AccountType, Decimal, Ledger, Transaction};
+use serde::{Deserialize, Serialize};
+
+/// Which way a transaction moved money, seen from the own accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flow {
+    In,
+    Out,
+    Transfer,
+}
+
+/// Amount shown for a transaction in lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionHeadline {
+    /// Never negative, the direction is in `flow`
+    pub amount: String,
+    /// `amount` signed per the sign convention, as income for money in and
+    /// as expenses for money out; transfers stay positive
+    pub display_amount: String,
+    pub currency: String,
+    pub flow: Flow,
+}
+
+/// Units booked by one posting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostingAmount {
+    pub account: String,
+    /// As booked, credit-normal accounts negative
+    pub amount: String,
+    pub currency: String,
+    /// Elided in the file, inferred from the other postings
+    pub inferred: bool,
+}
+
+fn is_own(options: &LedgerOptions, account: &str) -> bool {
+    options.is(account, AccountType::Assets) || options.is(account, AccountType::Liabilities)
+}
+
+/// (account, weight, currency) of every posting, elided ones inferred
+fn weights(tx: &Transaction) -> Vec<(&str, Decimal, String)> {
+    let mut weights: Vec<(&str, Decimal, String)> = tx.postings.iter()
+        .filter(|p| !p.amount.is_empty())
+        .filter_map(|p| posting_weight(p).map(|(weight, currency)| (p.account.as_str(), weight, currency)))
+        .collect();
+    if let Some(elided) = tx.postings.iter().find(|p| p.amount.is_empty()) {
+        for (currency, residual) in residuals(tx) {
+            if residual.abs() >= TOLERANCE {
+                weights.push((elided.account.as_str(), -residual, currency));
+            }
+        }
+    }
+    weights
+}
+
+/// Sum of the weights of accounts matching `filter`, in the currency of the
+/// first of them; None when there are none or they cancel out
+fn net(weights: &[(&str, Decimal, String)], filter: impl Fn(&str) -> bool) -> Option<(Decimal, String)> {
+    let currency = &weights.iter().find(|(account, _, _)| filter(account))?.2;
+    let sum: Decimal = weights.iter()
+        .filter(|(account, _, c)| filter(account) && c == currency)
+        .map(|(_, weight, _)| *weight)
+        .sum();
+    (sum.abs() >= TOLERANCE).then(|| (sum, currency.clone()))
+}
+
+impl Ledger {
+    /// The amount to show for `tx` (see module docs)
+    pub fn headline(&self, tx: &Transaction) -> TransactionHeadline {
+        let (amount, currency, flow) = headline(tx, &self.options);
+        let display_amount = match flow {
+            Flow::In => self.display_amount(&self.options.name_income, -amount),
+            Flow::Out => self.display_amount(&self.options.name_expenses, amount),
+            Flow::Transfer => amount,
+        };
+        TransactionHeadline {
+            amount: decimal_string(amount),
+            display_amount: decimal_string(display_amount),
+            currency,
+            flow,
+        }
+    }
+}
+
+/// Unsigned headline amount of `tx`, rounded to cents, with its currency and direction
+fn headline(tx: &Transaction, options: &LedgerOptions) -> (Decimal, String, Flow) {
+    let weights = weights(tx);
+    let is_own = |account: &str| is_own(options, account);
+    let rounded = |amount: Decimal| amount.abs().round_dp(2);
+    let mixed = weights.iter().any(|(a, _, _)| is_own(a)) && weights.iter().any(|(a, _, _)| !is_own(a));
+    if let Some((sum, currency)) = net(&weights, is_own).filter(|_| mixed) {
+        return (rounded(sum), currency, if sum > Decimal::ZERO { Flow::In } else { Flow::Out });
+    }
+    if let Some((sum, currency)) = net(&weights, |a| options.is(a, AccountType::Income)) {
+        return (rounded(sum), currency, if sum < Decimal::ZERO { Flow::In } else { Flow::Out });
+    }
+    if let Some((sum, currency)) = net(&weights, |a| options.is(a, AccountType::Expenses)) {
+        return (rounded(sum), currency, if sum > Decimal::ZERO { Flow::Out } else { Flow::In });
+    }
+    let currency = weights.first().map(|(_, _, c)| c.clone()).unwrap_or_default();
+    let moved: Decimal = weights.iter()
+        .filter(|(_, weight, c)| *c == currency && *weight > Decimal::ZERO)
+        .map(|(_, weight, _)| *weight)
+        .sum();
+    (rounded(moved), currency, Flow::Transfer)
+}
+
+impl Transaction {
+    /// Units of every posting in file order, elided ones inferred
+    pub fn breakdown(&self) -> Vec<PostingAmount> {
+        let mut amounts = Vec::new();
+        for posting in &self.postings {
+            if !posting.amount.is_empty() {
+                if let Some(units) = posting.amount_decimal() {
+                    amounts.push(PostingAmount {
+                        account: posting.account.clone(),
+                        amount: decimal_string(units),
+                        currency: posting.currency.clone(),
+                        inferred: false,
+                    });
+                }
+                continue;
+            }
+            for (currency, residual) in residuals(self) {
+                if residual.abs() >= TOLERANCE {
+                    amounts.push(PostingAmount {
+                        account: posting.account.clone(),
+                        amount: decimal_string(-residual),
+                        currency,
+                        inferred: true,
+                    });
+                }
+            }
+        }
+        amounts
+    }
+}
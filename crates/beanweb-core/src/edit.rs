@@ -0,0 +1,262 @@
+//! Editing and deleting a transaction in place
+//!
+//! A transaction is located by its source file and header line, and only that
+//! directive's lines are replaced or removed (comments are kept on edits, see
+//! [`crate::rewrite`]; the previous file is saved as `<file>.bak`):
+//! - The lines on disk must still hold the loaded transaction; a file edited
+//!   since the last load is rejected instead of overwritten
+//! - The replacement must parse as exactly one transaction that balances
+//! - The caller reloads the ledger after a successful update
+//!
+//! Form edits only know the header and postings; [`keep_metadata`] carries
+//! the original metadata lines over so they are not lost.
+
+use crate::integrity::{residuals, TOLERANCE};
+use crate::rewrite::{directive_span, remove_directive, rewrite_directive, split_comment};
+use crate::{CoreError, Ledger, Transaction};
+use beanweb_parser::Directive;
+use std::path::{Path, PathBuf};
+
+/// Result of an update
+#[derive(Debug, Clone)]
+pub struct TransactionUpdate {
+    /// Rewritten file
+    pub file: PathBuf,
+    /// Header line of the directive (1-based)
+    pub line: usize,
+    /// The replacement as parsed
+    pub transaction: Transaction,
+}
+
+/// Result of a deletion
+#[derive(Debug, Clone)]
+pub struct TransactionDeletion {
+    /// Rewritten file; the previous content is in `<file>.bak`
+    pub file: PathBuf,
+    /// Header line the directive started at (1-based)
+    pub line: usize,
+    /// The removed transaction
+    pub transaction: Transaction,
+}
+
+/// A transaction's directive as found on disk
+struct Located {
+    file: PathBuf,
+    line: usize,
+    content: String,
+    span: std::ops::Range<usize>,
+}
+
+fn invalid(message: impl Into<String>) -> CoreError {
+    CoreError::ValidationError { message: message.into() }
+}
+
+fn stale() -> CoreError {
+    invalid("The file changed since it was loaded; reload and try again")
+}
+
+/// `key: value` metadata line (code part, without the comment)
+pub(crate) fn is_metadata(code: &str) -> bool {
+    let code = code.trim_start();
+    code.split_once(':').is_some_and(|(key, rest)| {
+        key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            && (rest.is_empty() || rest.starts_with([' ', '\t']))
+    })
+}
+
+fn metadata_key(code: &str) -> &str {
+    code.trim_start().split(':').next().unwrap_or("")
+}
+
+/// Copy the metadata lines of `original` into `replacement`: transaction
+/// metadata below the header, posting metadata below the posting with the
+/// same account. Keys already present in `replacement` are not copied.
+pub fn keep_metadata(original: &str, replacement: &str) -> String {
+    // Metadata of the transaction (None) and of each posting, in order
+    let mut groups: Vec<(Option<String>, Vec<&str>)> = vec![(None, Vec::new())];
+    for line in original.lines().skip(1) {
+        let (code, _, _) = split_comment(line);
+        if code.trim().is_empty() {
+            continue;
+        }
+        if is_metadata(code) {
+            if let Some((_, lines)) = groups.last_mut() {
+                lines.push(line);
+            }
+        } else {
+            groups.push((code.split_whitespace().next().map(str::to_string), Vec::new()));
+        }
+    }
+
+    let mut present: Vec<(Option<String>, String)> = Vec::new();
+    let mut owner: Option<String> = None;
+    for line in replacement.lines().skip(1) {
+        let (code, _, _) = split_comment(line);
+        if is_metadata(code) {
+            present.push((owner.clone(), metadata_key(code).to_string()));
+        } else if !code.trim().is_empty() {
+            owner = code.split_whitespace().next().map(str::to_string);
+        }
+    }
+    let carried = |group: &(Option<String>, Vec<&str>), out: &mut Vec<String>| {
+        for line in &group.1 {
+            let key = metadata_key(split_comment(line).0).to_string();
+            if !present.contains(&(group.0.clone(), key)) {
+                out.push(line.to_string());
+            }
+        }
+    };
+
+    let mut out = Vec::new();
+    let mut used = vec![false; groups.len()];
+    for (index, line) in replacement.lines().enumerate() {
+        out.push(line.to_string());
+        let (code, _, _) = split_comment(line);
+        if index == 0 {
+            carried(&groups[0], &mut out);
+            used[0] = true;
+            continue;
+        }
+        if code.trim().is_empty() || is_metadata(code) {
+            continue;
+        }
+        let account = code.split_whitespace().next().map(str::to_string);
+        if let Some(i) = (1..groups.len()).find(|&i| !used[i] && groups[i].0 == account) {
+            used[i] = true;
+            carried(&groups[i], &mut out);
+        }
+    }
+    out.join("\n")
+}
+
+impl Ledger {
+    /// Parse `text` as a single transaction and check that it balances
+    pub async fn validate_transaction_text(&self, text: &str) -> Result<Transaction, CoreError> {
+        let parsed = self.parser.parse(text).await
+            .map_err(|e| CoreError::ParseError { message: e.to_string() })?;
+        if let Some(error) = parsed.errors.first() {
+            return Err(CoreError::ParseError { message: format!("{}:{}: {}", error.line, error.column, error.message) });
+        }
+        let directives = parsed.directives;
+        let transactions: Vec<Transaction> = directives.iter()
+            .filter_map(|d| match &d.data {
+                Directive::Transaction(txn) => Some(Self::convert_transaction(txn, d.span.start, None)),
+                _ => None,
+            })
+            .collect();
+        let others = directives.iter()
+            .filter(|d| !matches!(d.data, Directive::Transaction(_) | Directive::Comment(_)))
+            .count();
+        let [tx] = <[Transaction; 1]>::try_from(transactions).map_err(|_| invalid("Expected exactly one transaction"))?;
+        if others > 0 {
+            return Err(invalid("Expected exactly one transaction"));
+        }
+        if tx.postings.len() < 2 {
+            return Err(invalid("A transaction needs at least two postings"));
+        }
+
+        match tx.postings.iter().filter(|p| p.amount.is_empty()).count() {
+            // An elided posting absorbs whatever is left over
+            1 => {}
+            0 => {
+                let off: Vec<String> = residuals(&tx).into_iter()
+                    .filter(|(_, sum)| sum.abs() > TOLERANCE)
+                    .map(|(currency, sum)| format!("{} {}", sum.normalize(), currency))
+                    .collect();
+                if !off.is_empty() {
+                    return Err(invalid(format!("Transaction does not balance: {}", off.join(", "))));
+                }
+            }
+            _ => return Err(invalid("Only one posting may leave its amount out")),
+        }
+        Ok(tx)
+    }
+
+    /// A transaction's `source`, relative to `data.path` unless absolute
+    pub(crate) fn source_path(&self, source: &str) -> PathBuf {
+        let path = PathBuf::from(source);
+        if path.is_absolute() { path } else { self.config.data.path.join(path) }
+    }
+
+    /// The loaded transaction whose directive starts at `line` of `file`
+    pub fn transaction_at(&self, file: &Path, line: usize) -> Option<Transaction> {
+        let data = self.data.read().unwrap();
+        data.transactions.iter()
+            .find(|tx| tx.line == Some(line as u32) && tx.source.as_deref().is_some_and(|s| self.source_path(s) == file))
+            .cloned()
+    }
+
+    /// Find the directive of `id` on disk, checking that it is still the loaded transaction
+    async fn locate_transaction(&self, id: &str) -> Result<Located, CoreError> {
+        let tx = self.transaction(id).ok_or_else(|| CoreError::TransactionNotFound { id: id.to_string() })?;
+        let (Some(source), Some(line)) = (tx.source.clone(), tx.line) else {
+            return Err(invalid(format!("Transaction {} has no source location", id)));
+        };
+        let line = line as usize;
+        let file = self.source_path(&source);
+
+        let content = std::fs::read_to_string(&file).map_err(|_| CoreError::FileNotFound { path: file.display().to_string() })?;
+        let lines: Vec<&str> = content.lines().collect();
+        if line == 0 || line > lines.len() {
+            return Err(stale());
+        }
+        // The span must parse back to the same transaction id
+        let span = directive_span(&lines, line - 1);
+        let current = lines[span.clone()].join("\n");
+        let on_disk = self.parser.parse(&current).await.ok()
+            .and_then(|parsed| parsed.directives.into_iter().find_map(|d| match d.data {
+                Directive::Transaction(txn) => Some(Self::convert_transaction(&txn, line, tx.source.as_deref()).id),
+                _ => None,
+            }));
+        if on_disk.as_deref() != Some(id) {
+            return Err(stale());
+        }
+        Ok(Located { file, line, content, span })
+    }
+
+    /// Source text of a transaction as written in its file, comments and metadata included
+    pub async fn transaction_source(&self, id: &str) -> Result<String, CoreError> {
+        let located = self.locate_transaction(id).await?;
+        let lines: Vec<&str> = located.content.lines().collect();
+        Ok(lines[located.span].join("\n"))
+    }
+
+    /// Replace the transaction `id` with `text`, after checking that the file
+    /// still holds it and the replacement balances; `&mut` so the file is only
+    /// written under the ledger's write lock
+    pub async fn update_transaction(&mut self, id: &str, text: &str) -> Result<TransactionUpdate, CoreError> {
+        let located = self.locate_transaction(id).await?;
+        let transaction = self.validate_transaction_text(text).await?;
+        let updated = rewrite_directive(&located.content, located.line, text)?;
+        self.write_document(&located.file.to_string_lossy(), &updated)?;
+        Ok(TransactionUpdate { file: located.file, line: located.line, transaction })
+    }
+
+    /// Remove the transaction `id` from its file, after checking that the file still holds it
+    pub async fn delete_transaction(&mut self, id: &str) -> Result<TransactionDeletion, CoreError> {
+        let located = self.locate_transaction(id).await?;
+        let transaction = self.transaction(id).ok_or_else(|| CoreError::TransactionNotFound { id: id.to_string() })?;
+        let updated = remove_directive(&located.content, located.line)?;
+        self.write_document(&located.file.to_string_lossy(), &updated)?;
+        Ok(TransactionDeletion { file: located.file, line: located.line, transaction })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_metadata() {
+        let original = "2024-01-05 * \"Shop\"\n  created_at: \"2024-01-05 10:00:00\"\n  Expenses:Food  30.00 CNY\n    receipt: \"r1.jpg\"\n  Assets:Bank";
+        let replacement = "2024-01-05 * \"Shop\" \"Lunch\"\n    Expenses:Food 35.00 CNY\n    Assets:Bank";
+        assert_eq!(
+            keep_metadata(original, replacement),
+            "2024-01-05 * \"Shop\" \"Lunch\"\n  created_at: \"2024-01-05 10:00:00\"\n    Expenses:Food 35.00 CNY\n    receipt: \"r1.jpg\"\n    Assets:Bank"
+        );
+        // Metadata in the replacement wins; removed postings lose theirs
+        let replacement = "2024-01-05 * \"Shop\"\n  created_at: \"edited\"\n  Expenses:Dining 30.00 CNY\n  Assets:Bank";
+        assert_eq!(keep_metadata(original, replacement), replacement);
+    }
+}
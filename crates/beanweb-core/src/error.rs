@@ -371,13 +371,13 @@ pub trait ErrorLogger {
     fn log_debug(&self, message: &str, context: &ErrorContext);
 }
 
-/// Default error logger using log crate
+/// Default error logger using tracing
 #[derive(Default)]
 pub struct DefaultErrorLogger;
 
 impl ErrorLogger for DefaultErrorLogger {
     fn log_error(&self, error: &CoreError, context: &ErrorContext) {
-        log::error!(
+        tracing::error!(
             target: "beanweb::error",
             "ERROR [{}] {} - Operation: {} - Request: {:?}",
             error.code(),
@@ -388,7 +388,7 @@ impl ErrorLogger for DefaultErrorLogger {
     }
 
     fn log_warning(&self, message: &str, context: &ErrorContext) {
-        log::warn!(
+        tracing::warn!(
             target: "beanweb::error",
             "WARNING: {} - Operation: {} - Request: {:?}",
             message,
@@ -398,7 +398,7 @@ impl ErrorLogger for DefaultErrorLogger {
     }
 
     fn log_debug(&self, message: &str, context: &ErrorContext) {
-        log::debug!(
+        tracing::debug!(
             target: "beanweb::error",
             "DEBUG: {} - Operation: {} - Request: {:?}",
             message,
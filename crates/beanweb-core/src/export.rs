@@ -0,0 +1,70 @@
+//! Transactions as CSV for spreadsheets
+//!
+//! One row per posting, with the transaction's date, payee and narration
+//! repeated on each row. An elided posting is written with the amount
+//! inferred from the other postings (one row per currency it balances), so
+//! every row carries a number and the amounts of a transaction sum to zero.
+
+use crate::integrity::TOLERANCE;
+use crate::{Decimal, Transaction};
+
+/// Header row, newline included
+pub const CSV_HEADER: &str = "date,time,flag,payee,narration,account,amount,currency,cost,price,tags,links\n";
+
+/// Quote a field when it holds a comma, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Rows of one transaction, each ending in a newline
+pub fn transaction_csv_rows(tx: &Transaction) -> String {
+    let tags = tx.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ");
+    let links = tx.links.iter().map(|l| format!("^{}", l)).collect::<Vec<_>>().join(" ");
+    let row = |account: &str, amount: &str, currency: &str, cost: &str, price: &str| {
+        let fields = [
+            tx.date.as_str(), tx.time.as_str(), tx.flag.as_deref().unwrap_or(""),
+            tx.payee.as_str(), tx.narration.as_str(), account, amount, currency, cost, price,
+            tags.as_str(), links.as_str(),
+        ];
+        let mut line = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+        line.push('\n');
+        line
+    };
+
+    let mut rows = String::new();
+    for posting in &tx.postings {
+        let cost = posting.cost.as_deref().unwrap_or("");
+        let price = posting.price.as_deref().unwrap_or("");
+        match posting.amount_decimal().filter(|_| !posting.amount.is_empty()) {
+            Some(amount) => rows.push_str(&row(&posting.account, &amount.to_string(), &posting.currency, cost, price)),
+            None => {
+                let residuals: Vec<(String, Decimal)> = crate::integrity::residuals(tx).into_iter()
+                    .filter(|(_, residual)| residual.abs() >= TOLERANCE)
+                    .collect();
+                if residuals.is_empty() {
+                    rows.push_str(&row(&posting.account, "", &posting.currency, cost, price));
+                }
+                for (currency, residual) in residuals {
+                    rows.push_str(&row(&posting.account, &(-residual).to_string(), &currency, cost, price));
+                }
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Lunch"), "Lunch");
+        assert_eq!(csv_field("Tea, cake"), "\"Tea, cake\"");
+        assert_eq!(csv_field("The \"Cafe\""), "\"The \"\"Cafe\"\"\"");
+    }
+}
@@ -0,0 +1,39 @@
+//! Which accounts a transaction moved money from and to
+//!
+//! The transactions list shows `Cash → Food` under each row instead of only
+//! the payee and amount:
+//! - Accounts whose units went down (assets spent, income earned, a card
+//!   charged) are the sources; those that went up are the destinations
+//! - Elided postings count with their inferred amount
+//! - Each account appears once, in posting order; one moving several
+//!   currencies is placed by its first one
+
+use crate::{links, Transaction};
+
+/// Sources and destinations of one transaction, full account names
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionFlow {
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+impl Transaction {
+    pub fn flow(&self) -> TransactionFlow {
+        let mut flow = TransactionFlow::default();
+        for ((account, _), amount) in links::posting_units(self) {
+            if amount.is_zero() || flow.from.contains(&account) || flow.to.contains(&account) {
+                continue;
+            }
+            if amount.is_sign_negative() {
+                flow.from.push(account);
+            } else {
+                flow.to.push(account);
+            }
+        }
+        // Inferred amounts come last from `posting_units`
+        let position = |account: &String| self.postings.iter().position(|p| &p.account == account);
+        flow.from.sort_by_key(position);
+        flow.to.sort_by_key(position);
+        flow
+    }
+}
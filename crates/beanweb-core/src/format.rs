@@ -0,0 +1,195 @@
+//! Layout normalization for `beanweb fmt`
+//!
+//! Rewrites the layout of a ledger file without changing what it says, in
+//! the spirit of bean-format:
+//! - Postings and transaction metadata are indented by two spaces, posting
+//!   metadata by four
+//! - The numbers of all postings in a file are right-aligned in one column,
+//!   so the currencies line up; account and number are at least two spaces
+//!   apart
+//! - Trailing whitespace is removed
+//!
+//! Comments keep their place and the spacing before them. Top-level lines,
+//! comment-only lines and the continuation lines of multi-line strings are
+//! left as written. [`format_verified`] parses the result again and refuses
+//! it if any directive reads differently.
+
+use crate::edit::is_metadata;
+use crate::rewrite::split_comment;
+use crate::CoreError;
+use beanweb_parser::{BeancountParserTrait, Directive};
+
+/// Indentation of postings and transaction metadata
+const INDENT: &str = "  ";
+
+/// A line of the file, as it will be written
+enum Line<'a> {
+    /// Written as is
+    Kept(&'a str),
+    /// Reindented, comment included
+    Indented(String),
+    /// A posting with an amount, aligned once all widths are known
+    Posting { account: String, number: &'a str, rest: &'a str, comment: String },
+}
+
+/// Whether a string is still open at the end of `line`, given whether one
+/// was open at its start; `;` outside strings starts a comment
+fn ends_in_string(line: &str, mut in_string: bool) -> bool {
+    let mut escaped = false;
+    for c in line.chars() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return false,
+            _ => {}
+        }
+    }
+    in_string
+}
+
+/// Optional flag and account of a posting, and the text after the account
+fn split_posting(code: &str) -> Option<(String, &str)> {
+    let (flag, code) = match code.split_once(char::is_whitespace) {
+        Some((flag, rest)) if flag == "!" || flag == "*" => (Some(flag), rest.trim_start()),
+        _ => (None, code),
+    };
+    let account = code.split_whitespace().next()?;
+    let is_account = account.contains(':')
+        && !account.starts_with(|c: char| c.is_ascii_lowercase() || "\";:!*".contains(c));
+    if !is_account {
+        return None;
+    }
+    let rest = code[account.len()..].trim_start();
+    Some((flag.map_or_else(|| account.to_string(), |flag| format!("{} {}", flag, account)), rest))
+}
+
+/// Leading number (or arithmetic expression) of a posting's amount, and what follows it
+fn split_number(rest: &str) -> (&str, &str) {
+    let end = rest.find(|c: char| !(c.is_ascii_digit() || " .,+-*/()".contains(c))).unwrap_or(rest.len());
+    (rest[..end].trim_end(), rest[end..].trim_start())
+}
+
+/// `source` with its postings aligned and indentation normalized
+pub fn format_source(source: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_string = false;
+    let mut after_posting = false;
+    for line in source.lines() {
+        let started_in_string = in_string;
+        in_string = ends_in_string(line, in_string);
+        // Whitespace at the end of an open string is part of the string
+        let trimmed = if in_string { line } else { line.trim_end() };
+        if started_in_string || trimmed.is_empty() || !trimmed.starts_with([' ', '\t']) {
+            if !started_in_string {
+                after_posting = false;
+            }
+            lines.push(Line::Kept(trimmed));
+            continue;
+        }
+
+        let (code, gap, comment) = split_comment(trimmed);
+        let code = code.trim_start();
+        let comment = comment.map(|c| format!("{}{}", gap, c)).unwrap_or_default();
+        if code.is_empty() {
+            lines.push(Line::Kept(trimmed));
+        } else if is_metadata(code) {
+            let indent = if after_posting { INDENT.repeat(2) } else { INDENT.to_string() };
+            lines.push(Line::Indented(format!("{}{}{}", indent, code, comment)));
+        } else if let Some((account, rest)) = split_posting(code) {
+            after_posting = true;
+            let (number, rest) = split_number(rest);
+            if number.is_empty() {
+                let rest = if rest.is_empty() { String::new() } else { format!(" {}", rest) };
+                lines.push(Line::Indented(format!("{}{}{}{}", INDENT, account, rest, comment)));
+            } else {
+                lines.push(Line::Posting { account, number, rest, comment });
+            }
+        } else {
+            lines.push(Line::Indented(format!("{}{}{}", INDENT, code, comment)));
+        }
+    }
+
+    let (account_width, number_width) = lines.iter().fold((0, 0), |(a, n), line| match line {
+        Line::Posting { account, number, .. } => (a.max(account.chars().count()), n.max(number.chars().count())),
+        _ => (a, n),
+    });
+    let mut out = String::with_capacity(source.len());
+    for line in lines {
+        match line {
+            Line::Kept(text) => out.push_str(text),
+            Line::Indented(text) => out.push_str(&text),
+            Line::Posting { account, number, rest, comment } => {
+                let posting = format!("{}{:<aw$}  {:>nw$} {}", INDENT, account, number, rest, aw = account_width, nw = number_width);
+                out.push_str(posting.trim_end());
+                out.push_str(&comment);
+            }
+        }
+        out.push('\n');
+    }
+    if !source.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+/// Directives of `source` without their positions, comments left out, and
+/// the number of parse errors
+async fn directives(parser: &dyn BeancountParserTrait, source: &str) -> Result<(Vec<serde_json::Value>, usize), CoreError> {
+    let parsed = parser.parse(source).await.map_err(|e| CoreError::ParseError { message: e.to_string() })?;
+    let directives = parsed.directives.iter()
+        .filter(|d| !matches!(d.data, Directive::Comment(_)))
+        .map(|d| serde_json::to_value(&d.data).map_err(|e| CoreError::InternalError { message: e.to_string() }))
+        .collect::<Result<_, _>>()?;
+    Ok((directives, parsed.errors.len()))
+}
+
+/// Whether `a` and `b` parse to the same directives
+async fn same_directives(parser: &dyn BeancountParserTrait, a: &str, b: &str) -> Result<bool, CoreError> {
+    Ok(directives(parser, a).await? == directives(parser, b).await?)
+}
+
+/// [`format_source`], refused when the result wouldn't parse to the same
+/// directives as `source`
+pub async fn format_verified(parser: &dyn BeancountParserTrait, source: &str) -> Result<String, CoreError> {
+    let formatted = format_source(source);
+    if formatted != source && !same_directives(parser, source, &formatted).await? {
+        return Err(CoreError::ValidationError { message: "formatting would change the directives, file left as is".to_string() });
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_source() {
+        let source = "option \"title\" \"Home\"  \n\n2024-01-05 * \"Shop\" \"Lunch; with tea\"\n    note: \"receipt\"\n\tExpenses:Food    20.00 CNY ; paid cash\n  ! Assets:Cash:Wallet -1,020.00 CNY @ 1 CNY\n        card: \"visa\"\n  Assets:Bank\n    ; a comment line\n";
+        let expected = "option \"title\" \"Home\"\n\n2024-01-05 * \"Shop\" \"Lunch; with tea\"\n  note: \"receipt\"\n  Expenses:Food             20.00 CNY ; paid cash\n  ! Assets:Cash:Wallet  -1,020.00 CNY @ 1 CNY\n    card: \"visa\"\n  Assets:Bank\n    ; a comment line\n";
+        assert_eq!(format_source(source), expected);
+        assert_eq!(format_source(expected), expected);
+    }
+
+    #[test]
+    fn test_format_keeps_multiline_strings() {
+        let source = "2024-01-05 * \"Shop\" \"Lunch\n    with   \n  tea\"\n    Expenses:Food  (10 + 2) CNY\n  Assets:Bank  -12 CNY";
+        let expected = "2024-01-05 * \"Shop\" \"Lunch\n    with   \n  tea\"\n  Expenses:Food  (10 + 2) CNY\n  Assets:Bank         -12 CNY";
+        assert_eq!(format_source(source), expected);
+    }
+
+    #[tokio::test]
+    async fn test_format_verified() {
+        let parser = beanweb_parser::DefaultBeancountParser;
+        let source = "2024-01-05 * \"Shop\" ; lunch\n    Expenses:Food    20.00 CNY\n  Assets:Bank\n";
+        let formatted = format_verified(&parser, source).await.unwrap();
+        assert_eq!(formatted, "2024-01-05 * \"Shop\" ; lunch\n  Expenses:Food  20.00 CNY\n  Assets:Bank\n");
+        // A changed amount or account is told apart, a changed layout is not
+        assert!(same_directives(&parser, source, &formatted).await.unwrap());
+        assert!(!same_directives(&parser, source, &source.replace("20.00", "2.00")).await.unwrap());
+        assert!(!same_directives(&parser, source, &source.replace("Assets:Bank", "Assets:Cash")).await.unwrap());
+    }
+}
@@ -0,0 +1,184 @@
+//! Portfolio holdings grouped by asset class
+//!
+//! Units held per commodity are summed over all `Assets:` postings, valued in
+//! the operating currency at the latest known price and grouped by the
+//! commodity's `asset-class:` metadata. Commodities without the metadata, or
+//! never declared, fall under [`UNCLASSIFIED`]; holdings without a price keep
+//! their units but add nothing to the totals.
+//!
+//! [`Ledger::commodity_totals`] is the per-currency view behind the
+//! commodities page: every declared or used commodity with the units held in
+//! each asset and liability account.
+
+use crate::{AccountType, Decimal, Ledger};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Asset class of commodities without `asset-class:` metadata
+pub const UNCLASSIFIED: &str = "unclassified";
+
+/// Units below this are treated as sold out
+const TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 6);
+
+/// Units of one commodity held across the asset accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    pub commodity: String,
+    /// Friendly name from the commodity's `name:` metadata, or the symbol
+    pub label: String,
+    pub units: f64,
+    /// Value in the operating currency; None when no price is known
+    pub value: Option<f64>,
+    /// Accounts holding the commodity
+    pub accounts: Vec<String>,
+}
+
+/// Holdings sharing one asset class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetClassGroup {
+    pub asset_class: String,
+    pub value: f64,
+    /// Share of the total valued holdings, 0-100
+    pub percentage: f64,
+    pub holdings: Vec<Holding>,
+}
+
+/// Holdings report, largest asset class first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingsReport {
+    pub groups: Vec<AssetClassGroup>,
+    pub total: f64,
+    pub currency: String,
+}
+
+/// Units of one commodity across the balance sheet accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommodityTotal {
+    pub commodity: String,
+    /// Friendly name from the commodity's `name:` metadata, or the symbol
+    pub label: String,
+    /// Whether a `commodity` directive declares it
+    pub declared: bool,
+    pub asset_class: Option<String>,
+    /// Net units over Assets and Liabilities
+    pub units: f64,
+    /// (account, units) with a non-zero balance, largest first
+    pub accounts: Vec<(String, f64)>,
+    /// Latest price in the operating currency
+    pub price: Option<f64>,
+    /// `units` valued at `price`
+    pub value: Option<f64>,
+}
+
+impl Ledger {
+    /// Every declared or used commodity with its totals, the operating
+    /// currency first, then by value
+    pub fn commodity_totals(&self) -> Vec<CommodityTotal> {
+        let currency = self.report_currency();
+        let data = self.data.read().unwrap();
+
+        let mut balances: BTreeMap<String, BTreeMap<String, Decimal>> = data.commodities.iter()
+            .map(|c| (c.name.clone(), BTreeMap::new()))
+            .collect();
+        for tx in &data.transactions {
+            for ((account, commodity), amount) in crate::links::posting_units(tx) {
+                if commodity.is_empty() {
+                    continue;
+                }
+                let accounts = balances.entry(commodity).or_default();
+                if self.options.is(&account, AccountType::Assets) || self.options.is(&account, AccountType::Liabilities) {
+                    *accounts.entry(account).or_default() += amount;
+                }
+            }
+        }
+
+        let mut totals: Vec<CommodityTotal> = balances.into_iter()
+            .map(|(commodity, accounts)| {
+                let mut accounts: Vec<(String, Decimal)> = accounts.into_iter()
+                    .filter(|(_, units)| units.abs() >= TOLERANCE)
+                    .collect();
+                accounts.sort_by_key(|(_, units)| std::cmp::Reverse(units.abs()));
+                let units: Decimal = accounts.iter().map(|(_, units)| units).sum();
+                let declared = data.commodities.iter().find(|c| c.name == commodity);
+                let price = data.prices.rate(&commodity, &currency, "9999-12-31");
+                CommodityTotal {
+                    label: declared.map(|c| c.label().to_string()).unwrap_or_else(|| commodity.clone()),
+                    declared: declared.is_some(),
+                    asset_class: declared.and_then(|c| c.asset_class.clone()),
+                    value: price.and_then(|p| (p * units).to_f64()),
+                    price: price.and_then(|p| p.to_f64()),
+                    units: units.to_f64().unwrap_or(0.0),
+                    accounts: accounts.into_iter().map(|(account, units)| (account, units.to_f64().unwrap_or(0.0))).collect(),
+                    commodity,
+                }
+            })
+            .collect();
+        drop(data);
+
+        totals.sort_by(|a, b| {
+            (b.commodity == currency).cmp(&(a.commodity == currency))
+                .then_with(|| b.value.unwrap_or(0.0).abs().total_cmp(&a.value.unwrap_or(0.0).abs()))
+                .then_with(|| a.commodity.cmp(&b.commodity))
+        });
+        totals
+    }
+
+    /// Current holdings of every commodity in `Assets:` accounts, grouped by asset class
+    pub fn holdings_by_asset_class(&self) -> HoldingsReport {
+        let currency = self.report_currency();
+        let data = self.data.read().unwrap();
+
+        let mut units: BTreeMap<String, (Decimal, Vec<String>)> = BTreeMap::new();
+        for tx in &data.transactions {
+            for ((account, commodity), amount) in crate::links::posting_units(tx) {
+                if !self.options.is(&account, AccountType::Assets) || commodity.is_empty() {
+                    continue;
+                }
+                let (total, accounts) = units.entry(commodity).or_insert_with(|| (Decimal::ZERO, Vec::new()));
+                *total += amount;
+                if !accounts.contains(&account) {
+                    accounts.push(account);
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<String, Vec<Holding>> = BTreeMap::new();
+        for (commodity, (units, mut accounts)) in units {
+            if units.abs() < TOLERANCE {
+                continue;
+            }
+            let declared = data.commodities.iter().find(|c| c.name == commodity);
+            let asset_class = declared
+                .and_then(|c| c.asset_class.clone())
+                .unwrap_or_else(|| UNCLASSIFIED.to_string());
+            accounts.sort();
+            groups.entry(asset_class).or_default().push(Holding {
+                label: declared.map(|c| c.label().to_string()).unwrap_or_else(|| commodity.clone()),
+                // Latest price regardless of date
+                value: data.prices.convert(units, &commodity, &currency, "9999-12-31").and_then(|v| v.to_f64()),
+                commodity,
+                units: units.to_f64().unwrap_or(0.0),
+                accounts,
+            });
+        }
+        drop(data);
+
+        let total: f64 = groups.values().flatten().filter_map(|h| h.value).sum();
+        let mut groups: Vec<AssetClassGroup> = groups.into_iter()
+            .map(|(asset_class, mut holdings)| {
+                holdings.sort_by(|a, b| b.value.unwrap_or(0.0).total_cmp(&a.value.unwrap_or(0.0)));
+                let value: f64 = holdings.iter().filter_map(|h| h.value).sum();
+                AssetClassGroup {
+                    asset_class,
+                    value,
+                    percentage: if total.abs() > 0.001 { value / total * 100.0 } else { 0.0 },
+                    holdings,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+        HoldingsReport { groups, total, currency }
+    }
+}
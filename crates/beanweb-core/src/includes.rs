@@ -0,0 +1,114 @@
+//! Ledger files that the main file does not include
+//!
+//! A new monthly file dropped into the data directory is silently ignored
+//! until an `include` reaches it. Orphans are found by comparing the files on
+//! disk with the files the last load actually parsed:
+//! - `.bean`, `.beancount` and `.bc` files anywhere under `data.path`; hidden
+//!   directories are skipped
+//! - Paths are relative to the main file's directory, as `include` takes them
+//! - When every ledger file of a directory is orphaned, `include "dir/*.bean"`
+//!   is offered as well, so next month's file is picked up on its own
+//!
+//! [`Ledger::include_orphan`] adds one of these includes to the main file.
+
+use crate::bootstrap::add_include;
+use crate::{CoreError, Ledger};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Extensions of ledger files
+pub(crate) const LEDGER_EXTENSIONS: [&str; 3] = ["bean", "beancount", "bc"];
+
+/// A ledger file no include reaches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrphanedFile {
+    /// Relative to the main file's directory, `/`-separated
+    pub path: String,
+    /// Glob covering this file and its orphaned siblings, e.g. `2024/*.bean`
+    pub glob: Option<String>,
+}
+
+fn is_ledger_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| LEDGER_EXTENSIONS.contains(&e))
+}
+
+/// Ledger files under `dir`, hidden directories skipped
+fn ledger_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            let hidden = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+            if path.is_dir() && !hidden {
+                dirs.push(path);
+            } else if path.is_file() && is_ledger_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Ledger {
+    /// Ledger files under the data directory that the last load did not parse
+    pub fn orphaned_files(&self) -> Vec<OrphanedFile> {
+        let main = self.config.ledger_path();
+        let Some(base) = main.parent().map(canonical) else { return Vec::new() };
+        let loaded: HashSet<PathBuf> = self.file_stats.read().unwrap().iter()
+            .map(|s| canonical(Path::new(&s.path)))
+            .chain(std::iter::once(canonical(&main)))
+            .collect();
+
+        // Every ledger file per (directory, extension), marking the loaded ones
+        let mut groups: BTreeMap<(String, String), Vec<(String, bool)>> = BTreeMap::new();
+        for file in ledger_files(&self.config.data.path) {
+            let file = canonical(&file);
+            let Ok(relative) = file.strip_prefix(&base) else { continue };
+            let path = relative.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let dir = path.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default();
+            let extension = file.extension().and_then(|e| e.to_str()).unwrap_or_default().to_string();
+            groups.entry((dir, extension)).or_default().push((path, loaded.contains(&file)));
+        }
+
+        let mut orphans = Vec::new();
+        for ((dir, extension), files) in groups {
+            let glob = (!dir.is_empty() && files.len() > 1 && files.iter().all(|(_, loaded)| !loaded))
+                .then(|| format!("{}/*.{}", dir, extension));
+            for (path, loaded) in files {
+                if !loaded {
+                    orphans.push(OrphanedFile { path, glob: glob.clone() });
+                }
+            }
+        }
+        orphans.sort_by(|a, b| a.path.cmp(&b.path));
+        orphans
+    }
+
+    /// Add `include "<include>"` to the main file, where `include` is the path
+    /// or glob of a current orphan; a backup of the main file is kept
+    /// The ledger still has to be reloaded to pick the file up
+    pub fn include_orphan(&self, include: &str) -> Result<(), CoreError> {
+        let known = self.orphaned_files().iter()
+            .any(|o| o.path == include || o.glob.as_deref() == Some(include));
+        if !known {
+            return Err(CoreError::ValidationError { message: format!("{} 不是未引用的文件", include) });
+        }
+        let main = self.config.ledger_path();
+        let source = std::fs::read_to_string(&main)
+            .map_err(|_| CoreError::FileNotFound { path: main.to_string_lossy().into_owned() })?;
+        let updated = add_include(&source, include)
+            .ok_or_else(|| CoreError::ValidationError { message: format!("主文件已包含 {}", include) })?;
+        self.write_document(&main.to_string_lossy(), &updated)
+    }
+}
@@ -0,0 +1,249 @@
+//! Lookup structures over the ledger's transactions
+//!
+//! Rebuilt at the end of every load, so queries no longer scan and clone the
+//! whole transaction list. Entries are positions in `LedgerData::transactions`:
+//! - By id, by account named in a posting, by tag and by link, each in file
+//!   order
+//! - All positions newest first (by date and time, file order within the
+//!   same minute), and oldest first for date ranges
+//! - Each position's rank in the newest-first order, so a page can start
+//!   right after any transaction (cursor pagination)
+//! - A lowercased search text per transaction, so keyword searches don't
+//!   lowercase every field on every request
+//! - By `external_id:` metadata, the bank's id of imported transactions
+//!
+//! Queries resolve to positions first. They return a [`Transactions`] view
+//! that reads the matches in place; callers clone only what must outlive it.
+//!
+//! Transaction ids carry the line number, which changes when something is
+//! inserted above. A cursor whose id is gone is therefore also looked up by
+//! its file and content hash before it is given up on.
+
+use crate::account_filter::AccountFilter;
+use crate::transaction_import::EXTERNAL_ID;
+use crate::{LedgerData, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLockReadGuard;
+
+/// See the module docs
+#[derive(Debug, Clone, Default)]
+pub struct TransactionIndex {
+    by_id: HashMap<String, usize>,
+    by_account: HashMap<String, Vec<usize>>,
+    by_tag: HashMap<String, Vec<usize>>,
+    by_link: HashMap<String, Vec<usize>>,
+    /// Positions by date, oldest first; file order within a day
+    by_date: Vec<usize>,
+    /// Positions by date and time, newest first; file order on ties
+    newest_first: Vec<usize>,
+    /// Rank of each position in `newest_first`
+    rank: Vec<usize>,
+    /// Id without its line number (see [`content_key`]) to the line and
+    /// position of every transaction with that content, in file order
+    by_content: HashMap<String, Vec<(usize, usize)>>,
+    /// Payee, narration, tags, links and posting accounts, lowercased
+    search_text: Vec<String>,
+    /// `external_id:` metadata to position, see [`crate::transaction_import`]
+    by_external_id: HashMap<String, usize>,
+}
+
+/// Transactions after a cursor, see [`crate::Ledger::transactions_after`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    /// Cursor of the following page: the id of the last transaction here,
+    /// None when nothing matches after it
+    pub next: Option<String>,
+}
+
+/// Transactions picked by a query, borrowed from the ledger
+///
+/// The view holds the ledger's read lock: drop it before awaiting, and
+/// clone what a response keeps after that.
+pub struct Transactions<'a> {
+    data: RwLockReadGuard<'a, LedgerData>,
+    positions: Vec<usize>,
+}
+
+impl<'a> Transactions<'a> {
+    pub(crate) fn new(data: RwLockReadGuard<'a, LedgerData>, positions: Vec<usize>) -> Self {
+        Self { data, positions }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&Transaction> {
+        self.positions.get(i).map(|p| &self.data.transactions[*p])
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Transaction> + ExactSizeIterator + '_ {
+        self.positions.iter().map(|p| &self.data.transactions[*p])
+    }
+
+    /// Keep the transactions for which `keep` returns true
+    pub fn retain(&mut self, mut keep: impl FnMut(&Transaction) -> bool) {
+        let data = &self.data;
+        self.positions.retain(|p| keep(&data.transactions[*p]));
+    }
+
+    /// Owned copies, for results that outlive the lock
+    pub fn to_vec(&self) -> Vec<Transaction> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl std::ops::Index<usize> for Transactions<'_> {
+    type Output = Transaction;
+    fn index(&self, i: usize) -> &Transaction {
+        &self.data.transactions[self.positions[i]]
+    }
+}
+
+impl std::fmt::Debug for Transactions<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// `txn-<source>:<line>:<hash>` split into the id without the line and the
+/// line, None for other ids
+fn content_key(id: &str) -> Option<(String, usize)> {
+    let (source, rest) = id.strip_prefix("txn-")?.split_once(':')?;
+    let (line, hash) = rest.split_once(':')?;
+    Some((format!("{}:{}", source, hash), line.parse().ok()?))
+}
+
+impl TransactionIndex {
+    pub fn build(transactions: &[Transaction]) -> Self {
+        let mut index = Self::default();
+        for (position, tx) in transactions.iter().enumerate() {
+            index.by_id.entry(tx.id.clone()).or_insert(position);
+            if let Some((key, line)) = content_key(&tx.id) {
+                index.by_content.entry(key).or_default().push((line, position));
+            }
+            let mut accounts: Vec<&str> = tx.postings.iter().map(|p| p.account.as_str()).collect();
+            accounts.sort_unstable();
+            accounts.dedup();
+            for account in accounts {
+                index.by_account.entry(account.to_string()).or_default().push(position);
+            }
+            for tag in &tx.tags {
+                let positions = index.by_tag.entry(tag.clone()).or_default();
+                if positions.last() != Some(&position) {
+                    positions.push(position);
+                }
+            }
+            for link in &tx.links {
+                let positions = index.by_link.entry(link.clone()).or_default();
+                if positions.last() != Some(&position) {
+                    positions.push(position);
+                }
+            }
+            let mut text = format!("{}\n{}", tx.payee, tx.narration);
+            for part in tx.tags.iter().chain(&tx.links).chain(tx.postings.iter().map(|p| &p.account)) {
+                text.push('\n');
+                text.push_str(part);
+            }
+            index.search_text.push(text.to_lowercase());
+            if let Some(external_id) = tx.metadata.get(EXTERNAL_ID).and_then(|v| v.as_str()) {
+                index.by_external_id.entry(external_id.to_string()).or_insert(position);
+            }
+        }
+
+        index.by_date = (0..transactions.len()).collect();
+        index.by_date.sort_by(|a, b| transactions[*a].date.cmp(&transactions[*b].date));
+        index.newest_first = (0..transactions.len()).collect();
+        index.newest_first.sort_by(|a, b| {
+            let (a, b) = (&transactions[*a], &transactions[*b]);
+            b.date.cmp(&a.date).then_with(|| b.time.cmp(&a.time))
+        });
+        index.rank = vec![0; transactions.len()];
+        for (rank, position) in index.newest_first.iter().enumerate() {
+            index.rank[*position] = rank;
+        }
+        index
+    }
+
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.by_id.get(id).copied()
+    }
+
+    /// Transactions with a posting on exactly `account`
+    pub fn by_account(&self, account: &str) -> &[usize] {
+        self.by_account.get(account).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn by_tag(&self, tag: &str) -> &[usize] {
+        self.by_tag.get(tag).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn by_link(&self, link: &str) -> &[usize] {
+        self.by_link.get(link).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Every tag with its transactions, in no particular order
+    pub fn tags(&self) -> impl Iterator<Item = (&str, &[usize])> {
+        self.by_tag.iter().map(|(tag, positions)| (tag.as_str(), positions.as_slice()))
+    }
+
+    /// Positions by date, oldest first
+    pub fn by_date(&self) -> &[usize] {
+        &self.by_date
+    }
+
+    pub fn newest_first(&self) -> &[usize] {
+        &self.newest_first
+    }
+
+    /// Transaction imported with the bank id `external_id`
+    pub fn by_external_id(&self, external_id: &str) -> Option<usize> {
+        self.by_external_id.get(external_id).copied()
+    }
+
+    /// Position of the cursor `id`: the transaction with that id or, when its
+    /// line moved, the same transaction in the same file; of identical
+    /// transactions the one nearest to the old line
+    pub fn locate(&self, id: &str) -> Option<usize> {
+        self.position(id).or_else(|| {
+            let (key, line) = content_key(id)?;
+            self.by_content.get(&key)?
+                .iter()
+                .min_by_key(|(candidate, _)| candidate.abs_diff(line))
+                .map(|&(_, position)| position)
+        })
+    }
+
+    /// Rank of `position` in [`Self::newest_first`]
+    pub fn rank(&self, position: usize) -> usize {
+        self.rank[position]
+    }
+
+    /// Whether the search text of `position` contains `query`, already lowercased
+    pub fn text_contains(&self, position: usize, query: &str) -> bool {
+        self.search_text.get(position).is_some_and(|text| text.contains(query))
+    }
+
+    /// Transactions matching every filter (each by at least one posting),
+    /// None when there are no filters
+    pub fn matching(&self, filters: &[AccountFilter]) -> Option<HashSet<usize>> {
+        let mut matched: Option<HashSet<usize>> = None;
+        for filter in filters {
+            let hits: HashSet<usize> = self.by_account.iter()
+                .filter(|(account, _)| filter.matches(account))
+                .flat_map(|(_, positions)| positions.iter().copied())
+                .collect();
+            matched = Some(match matched {
+                Some(previous) => previous.intersection(&hits).copied().collect(),
+                None => hits,
+            });
+        }
+        matched
+    }
+}
@@ -0,0 +1,301 @@
+//! Ledger integrity check
+//!
+//! Recomputes what beancount itself would reject, from the loaded data:
+//! - Balance assertions that don't match the postings before their date
+//! - Transactions whose postings don't sum to zero per currency
+//! - Postings to an account after it was closed
+//!
+//! Transactions synthesized from `pad` directives (see [`pad`](crate::pad))
+//! count towards the balances, so a padded assertion holds by construction
+//! and the ones after it are checked against the padded balance.
+//!
+//! [`Ledger::check_balances`] is the per-assertion view: one sweep over the
+//! transactions in date order keeps a running balance per account and
+//! currency, and every `balance` directive is compared against it (including
+//! sub-accounts) with its file and line.
+
+use crate::{Decimal, Ledger, Posting, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Amounts closer than this are considered equal
+pub(crate) const TOLERANCE: Decimal = Decimal::from_parts(5, 0, 0, false, 3);
+
+/// Kind of integrity issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityIssueKind {
+    /// A `balance` directive doesn't match the computed balance
+    BalanceAssertion,
+    /// Postings of a transaction don't sum to zero
+    Unbalanced,
+    /// Posting to an account after its `close` date
+    ClosedAccount,
+}
+
+impl std::fmt::Display for IntegrityIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityIssueKind::BalanceAssertion => write!(f, "balance_assertion"),
+            IntegrityIssueKind::Unbalanced => write!(f, "unbalanced"),
+            IntegrityIssueKind::ClosedAccount => write!(f, "closed_account"),
+        }
+    }
+}
+
+/// One problem found by the integrity check
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    pub date: String,
+    pub account: String,
+    /// Transaction ID, for unbalanced and closed-account issues
+    pub transaction_id: Option<String>,
+    pub message: String,
+}
+
+/// Result of one integrity check run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Time of the run (RFC 3339)
+    pub checked_at: String,
+    /// Issues, sorted by date
+    pub issues: Vec<IntegrityIssue>,
+    /// Stable hash of the issue set; equal fingerprints mean the same errors
+    pub fingerprint: String,
+}
+
+impl IntegrityReport {
+    /// Build a report from issues, sorting them and computing the fingerprint
+    pub fn new(mut issues: Vec<IntegrityIssue>) -> Self {
+        issues.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.cmp(b)));
+        issues.dedup();
+        let keys: Vec<String> = issues.iter()
+            .map(|i| format!("{}|{}|{}|{}", i.kind, i.date, i.account, i.message))
+            .collect();
+        Self {
+            checked_at: chrono::Utc::now().to_rfc3339(),
+            fingerprint: beanweb_parser::short_hash(&keys.join("\n")),
+            issues,
+        }
+    }
+
+    /// Issues present here but not in `previous`
+    pub fn added_since(&self, previous: &IntegrityReport) -> Vec<IntegrityIssue> {
+        self.issues.iter().filter(|i| !previous.issues.contains(i)).cloned().collect()
+    }
+
+    /// Issues present in `previous` but fixed here
+    pub fn resolved_since(&self, previous: &IntegrityReport) -> Vec<IntegrityIssue> {
+        previous.issues.iter().filter(|i| !self.issues.contains(i)).cloned().collect()
+    }
+}
+
+/// Outcome of one `balance` directive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceCheck {
+    pub date: String,
+    pub account: String,
+    pub currency: String,
+    pub expected: Decimal,
+    /// Balance of the written postings before `date`
+    pub computed: Decimal,
+    /// `computed - expected`
+    pub difference: Decimal,
+    /// A `pad` since the previous assertion makes it hold
+    pub padded: bool,
+    pub passed: bool,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// Units of a posting: amount and currency, `None` when the amount is elided
+fn posting_units(posting: &Posting) -> Option<(Decimal, String)> {
+    if posting.amount.is_empty() {
+        return None;
+    }
+    let amount = Ledger::posting_decimal(posting);
+    Some((amount, posting.currency.clone()))
+}
+
+/// Weight of a posting for balancing: units converted through cost or price
+pub(crate) fn posting_weight(posting: &Posting) -> Option<(Decimal, String)> {
+    let (units, currency) = posting_units(posting)?;
+    if let Some(cost) = &posting.cost_spec {
+        return Some((units * cost.per_unit.number, cost.per_unit.currency.clone()));
+    }
+    if let Some(price) = &posting.price_spec {
+        return Some((price.value_of(units), price.amount.currency.clone()));
+    }
+    // Not parsed: "{800 PI}" / "{1 CNY}" per-unit cost
+    if let Some((per_unit, cost_currency)) = posting.cost.as_deref().and_then(split_amount) {
+        return Some((units * per_unit, cost_currency));
+    }
+    match posting.price.as_deref() {
+        Some(price) if price.starts_with("@@") => split_amount(&price[2..])
+            .map(|(total, c)| (if units.is_sign_negative() { -total.abs() } else { total.abs() }, c)),
+        Some(price) if price.starts_with('@') => split_amount(&price[1..])
+            .map(|(per_unit, c)| (units * per_unit, c)),
+        _ => Some((units, currency)),
+    }
+}
+
+/// Parse "800 PI", "{1.5 CNY}" or " 7.1 USD" into number and currency
+fn split_amount(text: &str) -> Option<(Decimal, String)> {
+    let text = text.trim().trim_start_matches('{').trim_end_matches('}');
+    let mut parts = text.split_whitespace();
+    let number = parts.next()?.replace(',', "").parse::<Decimal>().ok()?;
+    let currency = parts.next()?.to_string();
+    Some((number, currency))
+}
+
+/// Per-currency residual of a transaction's known postings
+pub(crate) fn residuals(tx: &Transaction) -> BTreeMap<String, Decimal> {
+    let mut sums: BTreeMap<String, Decimal> = BTreeMap::new();
+    for weight in tx.postings.iter().filter_map(posting_weight) {
+        *sums.entry(weight.1).or_default() += weight.0;
+    }
+    sums
+}
+
+/// Units of `currency` posted to `account` and its sub-accounts by `transactions`
+pub(crate) fn computed_balance<'a>(transactions: impl Iterator<Item = &'a Transaction>, account: &str, currency: &str) -> Decimal {
+    let prefix = format!("{}:", account);
+    let mut computed = Decimal::ZERO;
+    for tx in transactions {
+        for posting in &tx.postings {
+            if posting.account != account && !posting.account.starts_with(&prefix) {
+                continue;
+            }
+            match posting_units(posting) {
+                Some((amount, posting_currency)) if posting_currency == currency => computed += amount,
+                Some(_) => {}
+                // Elided amount: the negated residual in the asserted currency
+                None => computed -= residuals(tx).get(currency).copied().unwrap_or_default(),
+            }
+        }
+    }
+    computed
+}
+
+/// Synthesized from a `pad` directive rather than written in the ledger
+pub(crate) fn is_pad_transaction(tx: &Transaction) -> bool {
+    tx.metadata.get("pad_source").is_some()
+}
+
+impl Ledger {
+    /// Run the full integrity check against the loaded data
+    pub fn integrity_check(&self) -> IntegrityReport {
+        let data = self.data.read().unwrap();
+        let mut issues = Vec::new();
+
+        // Unbalanced transactions and postings to closed accounts
+        let close_dates: HashMap<&str, &str> = data.accounts.iter()
+            .filter_map(|a| a.close_date.as_deref().map(|d| (a.name.as_str(), d)))
+            .collect();
+        for tx in data.transactions.iter().filter(|tx| !is_pad_transaction(tx)) {
+            // An elided posting absorbs whatever is left over
+            let has_elided = tx.postings.iter().any(|p| p.amount.is_empty());
+            if !has_elided {
+                let off: Vec<String> = residuals(tx).into_iter()
+                    .filter(|(_, sum)| sum.abs() > TOLERANCE)
+                    .map(|(currency, sum)| format!("{:.2} {}", sum, currency))
+                    .collect();
+                if !off.is_empty() {
+                    issues.push(IntegrityIssue {
+                        kind: IntegrityIssueKind::Unbalanced,
+                        date: tx.date.clone(),
+                        account: tx.postings.first().map(|p| p.account.clone()).unwrap_or_default(),
+                        transaction_id: Some(tx.id.clone()),
+                        message: format!("交易不平衡，差额 {}（{}）", off.join(", "), tx.narration),
+                    });
+                }
+            }
+            for posting in &tx.postings {
+                if let Some(close_date) = close_dates.get(posting.account.as_str()) {
+                    if tx.date.as_str() > *close_date {
+                        issues.push(IntegrityIssue {
+                            kind: IntegrityIssueKind::ClosedAccount,
+                            date: tx.date.clone(),
+                            account: posting.account.clone(),
+                            transaction_id: Some(tx.id.clone()),
+                            message: format!("账户已于 {} 关闭（{}）", close_date, tx.narration),
+                        });
+                    }
+                }
+            }
+        }
+
+        drop(data);
+
+        for check in self.check_balances().into_iter().filter(|c| !c.passed) {
+            issues.push(IntegrityIssue {
+                kind: IntegrityIssueKind::BalanceAssertion,
+                message: format!(
+                    "余额断言失败：断言 {:.2} {}，实际 {:.2} {}（差额 {:.2}）",
+                    check.expected, check.currency, check.computed, check.currency, check.difference
+                ),
+                date: check.date,
+                account: check.account,
+                transaction_id: None,
+            });
+        }
+
+        IntegrityReport::new(issues)
+    }
+
+    /// Check every `balance` directive against the running balance, oldest first
+    pub fn check_balances(&self) -> Vec<BalanceCheck> {
+        let data = self.data.read().unwrap();
+        let mut transactions: Vec<&Transaction> = data.transactions.iter().collect();
+        transactions.sort_by(|a, b| a.date.cmp(&b.date));
+        let mut assertions: Vec<_> = data.balances.iter().collect();
+        assertions.sort_by(|a, b| a.date.cmp(&b.date));
+
+        let mut running: HashMap<(&str, String), Decimal> = HashMap::new();
+        let mut pending = transactions.into_iter().peekable();
+        let mut previous_assertion: HashMap<(&str, &str), &str> = HashMap::new();
+        let mut checks = Vec::new();
+        for balance in assertions {
+            // Balances are asserted at the start of the day
+            while let Some(tx) = pending.next_if(|tx| tx.date < balance.date) {
+                for posting in &tx.postings {
+                    match posting_units(posting) {
+                        Some((amount, currency)) => *running.entry((posting.account.as_str(), currency)).or_default() += amount,
+                        // Elided amount: the negated residual in every currency
+                        None => for (currency, residual) in residuals(tx) {
+                            *running.entry((posting.account.as_str(), currency)).or_default() -= residual;
+                        },
+                    }
+                }
+            }
+
+            let key = (balance.account.as_str(), balance.currency.as_str());
+            let since = previous_assertion.insert(key, balance.date.as_str()).unwrap_or("");
+            let padded = data.pads.iter().any(|p| {
+                p.account == balance.account && p.date.as_str() >= since && p.date < balance.date
+            });
+            let prefix = format!("{}:", balance.account);
+            let computed: Decimal = running.iter()
+                .filter(|((account, currency), _)| {
+                    *currency == balance.currency && (*account == balance.account || account.starts_with(&prefix))
+                })
+                .map(|(_, amount)| amount)
+                .sum();
+            let difference = computed - balance.units;
+            checks.push(BalanceCheck {
+                date: balance.date.clone(),
+                account: balance.account.clone(),
+                currency: balance.currency.clone(),
+                expected: balance.units,
+                computed,
+                difference,
+                padded,
+                passed: difference.abs() <= TOLERANCE,
+                source: balance.source.clone(),
+                line: balance.line,
+            });
+        }
+        checks
+    }
+}
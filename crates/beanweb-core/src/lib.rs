@@ -1,18 +1,95 @@
 //! Core ledger processing and business logic
 
+pub mod account_filter;
+pub mod account_import;
+pub mod account_tree;
+pub mod amount;
+pub mod anonymize;
+pub mod balance_import;
+pub mod bootstrap;
+pub mod budget;
+pub mod display;
+pub mod edit;
 pub mod error;
+pub mod export;
+pub mod flow;
+pub mod format;
+pub mod holdings;
+pub mod includes;
+pub mod index;
+pub mod integrity;
+pub mod links;
+pub mod negative;
+pub mod notes;
+pub mod opening;
+pub mod operating;
+pub mod options;
+pub mod pad;
+pub mod other;
+pub mod pause;
+pub mod payees;
+pub mod prices;
+pub mod recurring;
+pub mod render;
+pub mod report_cache;
+pub mod rewrite;
+pub mod rollup;
+pub mod rules;
+pub mod service;
+pub mod similar;
+pub mod stale;
+pub mod sign;
+pub mod suggest;
+pub mod suspense;
+pub mod tags;
+pub mod templates;
+pub mod timing;
+pub mod transaction_import;
+pub mod trends;
+pub mod velocity;
+pub mod watch;
 
 use async_trait::async_trait;
 use beanweb_config::{Config, TimeRange};
-use beanweb_parser::{BeancountParserTrait, Directive, SpannedDirective, Transaction as ParserTransaction};
+use beanweb_parser::{BeancountParserTrait, Directive, ParseCache, SpannedDirective, Transaction as ParserTransaction};
 use chrono::{Datelike, DateTime, NaiveDate, Utc};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::path::PathBuf;
-
+use timing::QueryTimer;
+
+pub use account_filter::AccountFilter;
+pub use amount::{Amount, CostSpec, PriceSpec};
+pub use anonymize::AnonymizeOptions;
+pub use bootstrap::{AccountTemplate, BootstrapOutcome};
+pub use display::{Flow, PostingAmount, TransactionHeadline};
+pub use beanweb_parser::{DirectiveError, FileParseStats, Severity, SimpleBeancountParser};
+pub use rust_decimal::Decimal;
 pub use error::CoreError;
 pub use error::ErrorSeverity;
+pub use flow::TransactionFlow;
+pub use includes::OrphanedFile;
+pub use index::{TransactionPage, Transactions};
+pub use integrity::{BalanceCheck, IntegrityIssue, IntegrityIssueKind, IntegrityReport};
+pub use links::{LinkBalance, LinkGroup};
+pub use negative::{NegativeBalance, NegativeCause};
+pub use notes::{AccountDocument, AccountNote, LedgerEvent, StoredQuery};
+pub use operating::{BalanceTotals, IncomeExpenseTotals};
+pub use options::LedgerOptions;
+pub use payees::{PayeeSummary, PayeeTrends};
+pub use prices::{ConversionMode, PriceDatabase};
+pub use recurring::{RecurringInterval, RecurringPayment};
+pub use rules::{CategoryRule, RuleSet, RuleSubject};
+pub use service::{AccountSummary, BalanceSummary, IncomeExpenseSummary, LedgerService, PostingSummary, TransactionSummary, WrittenTransaction};
+pub use sign::SignConvention;
+pub use suggest::{AccountSuggestions, Suggestion};
+pub use suspense::SuspensePosting;
+pub use tags::TagSummary;
+pub use templates::{TemplatePosting, TransactionTemplate};
+pub use timing::QueryTiming;
+pub use velocity::SpendingVelocity;
 
 /// Parser reference type
 pub type ParserRef = Arc<dyn BeancountParserTrait>;
@@ -28,6 +105,9 @@ pub struct TimeContext {
     pub custom_start: Option<NaiveDate>,
     /// Custom end date (when range is Custom)
     pub custom_end: Option<NaiveDate>,
+    /// Include future-dated (scheduled) transactions; off by default so
+    /// balances and reports stop at today
+    pub include_future: bool,
 }
 
 impl Default for TimeContext {
@@ -36,6 +116,7 @@ impl Default for TimeContext {
             range: TimeRange::Month,
             custom_start: None,
             custom_end: None,
+            include_future: false,
         }
     }
 }
@@ -47,6 +128,7 @@ impl TimeContext {
             range,
             custom_start: None,
             custom_end: None,
+            include_future: false,
         }
     }
 
@@ -56,6 +138,17 @@ impl TimeContext {
             range: TimeRange::Custom,
             custom_start: Some(start),
             custom_end: Some(end),
+            include_future: false,
+        }
+    }
+
+    /// Get the "today horizon": postings after this date are upcoming
+    /// Returns None when future-dated transactions are included
+    pub fn as_of(&self) -> Option<NaiveDate> {
+        if self.include_future {
+            None
+        } else {
+            Some(Utc::now().date_naive())
         }
     }
 
@@ -98,6 +191,9 @@ impl TimeContext {
 
     /// Check if a date is within the current time context
     pub fn contains(&self, date: &NaiveDate) -> bool {
+        if self.as_of().is_some_and(|as_of| *date > as_of) {
+            return false;
+        }
         let start = self.start_date();
         let end = self.end_date();
 
@@ -159,6 +255,39 @@ pub struct Ledger {
     directives: RwLock<Vec<SpannedDirective>>,
     entry: (PathBuf, String),
     time_context: RwLock<TimeContext>,
+    file_stats: RwLock<Vec<FileParseStats>>,
+    /// Files parsed by earlier loads, for `data.incremental_reload`
+    parse_cache: ParseCache,
+    load_status: LoadStatus,
+    /// Bumped after every successful load, see [`Ledger::subscribe_reloads`]
+    reloads: tokio::sync::watch::Sender<u64>,
+    /// Autocomplete index over `data.accounts`, replaced after every load
+    suggestions: RwLock<Arc<AccountSuggestions>>,
+    /// Categorization rules compiled at the last load, or why they didn't
+    /// compile, see [`rules`]
+    rules: RwLock<Result<Arc<RuleSet>, String>>,
+    /// Slowest searches and reports, see [`timing`]
+    timings: std::sync::Mutex<timing::SlowLog>,
+    /// Reports computed since the last load, see [`report_cache`]
+    report_cache: std::sync::Mutex<report_cache::ReportCache>,
+    /// `option` directives of the last load, see [`options`]
+    options: options::LedgerOptions,
+}
+
+/// Outcome of the most recent load attempt
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadStatus {
+    /// Ledger data comes from a successful load
+    pub loaded: bool,
+    /// Error of the last attempt, if it failed
+    pub error: Option<String>,
+    /// Number of load attempts so far
+    pub attempts: u32,
+    /// Time of the last attempt (RFC 3339)
+    pub last_attempt: Option<String>,
+    /// How long the last successful load took, parsing and processing, in milliseconds
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
 }
 
 /// In-memory ledger data
@@ -169,6 +298,26 @@ pub struct LedgerData {
     pub commodities: Vec<Commodity>,
     pub balances: Vec<BalanceEntry>,
     pub pads: Vec<PadEntry>,
+    /// `price` directives (see [`prices`])
+    pub prices: PriceDatabase,
+    /// `custom "budget"` directives
+    #[serde(default)]
+    pub budgets: Vec<budget::Budget>,
+    /// `option "operating_currency"` values, in file order (see [`operating`])
+    #[serde(default)]
+    pub operating_currencies: Vec<String>,
+    /// `note`, `document`, `event` and `query` directives, in file order (see [`notes`])
+    #[serde(default)]
+    pub notes: Vec<notes::AccountNote>,
+    #[serde(default)]
+    pub documents: Vec<notes::AccountDocument>,
+    #[serde(default)]
+    pub events: Vec<notes::LedgerEvent>,
+    #[serde(default)]
+    pub queries: Vec<notes::StoredQuery>,
+    /// Lookups over `transactions`, rebuilt with them
+    #[serde(skip)]
+    pub index: index::TransactionIndex,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -289,6 +438,12 @@ pub struct Account {
     pub note: Option<String>,
     /// List of account tags
     pub tags: Vec<String>,
+    /// Source file of the `open` directive
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Line of the `open` directive in `source`
+    #[serde(default)]
+    pub line: Option<u32>,
 }
 
 impl Account {
@@ -324,6 +479,23 @@ impl Account {
     pub fn depth(&self) -> usize {
         self.name.chars().filter(|&c| c == ':').count()
     }
+
+    /// Whether the account takes postings on `date` (YYYY-MM-DD): opened on
+    /// or before it and not closed by then
+    pub fn is_open_on(&self, date: &str) -> bool {
+        self.inactive_on(date).is_none()
+    }
+
+    /// Why the account can't take a posting on `date`, if it can't
+    pub fn inactive_on(&self, date: &str) -> Option<String> {
+        if let Some(open) = self.open_date.as_deref().filter(|open| *open > date) {
+            return Some(format!("{} 于 {} 才开立", self.name, open));
+        }
+        if let Some(close) = self.close_date.as_deref().filter(|close| *close <= date) {
+            return Some(format!("{} 已于 {} 关闭", self.name, close));
+        }
+        None
+    }
 }
 
 /// Transaction information
@@ -361,6 +533,14 @@ impl Transaction {
         NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").ok()
     }
 
+    /// Check if transaction is dated after the given horizon (scheduled)
+    pub fn is_upcoming(&self, as_of: Option<NaiveDate>) -> bool {
+        match (as_of, self.date_naive()) {
+            (Some(as_of), Some(date)) => date > as_of,
+            _ => false,
+        }
+    }
+
     /// Get formatted datetime string (date + time)
     pub fn datetime(&self) -> String {
         if self.time.is_empty() || self.time == "00:00:00" {
@@ -412,72 +592,105 @@ impl Transaction {
 pub struct Posting {
     /// Account name
     pub account: String,
-    /// Amount (can be negative for expenses/liabilities)
+    /// Display form of the amount with cost and price, e.g. "800 PI @ 1 CNY";
+    /// read `units`, `cost_spec` and `price_spec` for the numbers
     pub amount: String,
+    /// Parsed units; None when elided or not parsed from the ledger
+    #[serde(default)]
+    pub units: Option<Amount>,
     /// Currency code
     pub currency: String,
-    /// Cost per unit (for investments)
+    /// Cost per unit (for investments), display form
     pub cost: Option<String>,
-    /// Price (for currency conversion)
+    /// Parsed `cost`
+    #[serde(default)]
+    pub cost_spec: Option<CostSpec>,
+    /// Price (for currency conversion), display form
     pub price: Option<String>,
+    /// Parsed `price`
+    #[serde(default)]
+    pub price_spec: Option<PriceSpec>,
     /// Balance assertion
     pub balance: Option<String>,
     /// Posting metadata
     pub metadata: serde_json::Value,
 }
 
-impl Posting {
-    /// Get amount as f64 (if parseable)
-    /// Handles @ price syntax: e.g., "800 PI @ 1 CNY" returns 800.0 (units)
-    /// For transactions with price, the caller should calculate total: units * price
-    pub fn amount_value(&self) -> Option<f64> {
-        // Extract the first number from the amount string
-        // Handle formats: "800 CNY", "-800.00 CNY", "800 PI @ 1 CNY"
-        let chars: Vec<char> = self.amount.chars().collect();
-        let mut i = 0;
-        let mut has_decimal = false;
-        let mut has_negative = false;
+/// Report amount string as a decimal; unparsable text counts as zero
+fn parse_decimal(text: &str) -> Decimal {
+    text.trim().parse().unwrap_or_default()
+}
 
-        // Handle negative sign
-        if !chars.is_empty() && chars[0] == '-' {
-            has_negative = true;
-            i = 1;
-        }
+/// Report amount string: trailing zeros dropped, never "-0"
+fn decimal_string(value: Decimal) -> String {
+    value.normalize().to_string()
+}
 
-        // Skip whitespace
-        while i < chars.len() && chars[i] == ' ' {
-            i += 1;
-        }
+/// `part` as a percentage of a positive `total`, 0 otherwise
+fn percent_of(part: Decimal, total: Decimal) -> f64 {
+    if total > Decimal::ZERO {
+        (part / total * Decimal::ONE_HUNDRED).to_f64().unwrap_or(0.0)
+    } else {
+        0.0
+    }
+}
 
-        // Parse the number (digits and optional decimal point)
-        let mut num_str = String::new();
-        while i < chars.len() {
-            let c = chars[i];
-            if c.is_ascii_digit() {
-                num_str.push(c);
-            } else if c == '.' && !has_decimal {
-                num_str.push(c);
-                has_decimal = true;
-            } else if c == ' ' || c == '\t' {
-                break;  // End of number
-            } else {
-                break;  // Non-numeric character (currency, @, etc.)
-            }
-            i += 1;
-        }
+/// Leading number of an amount string: "-800.00 CNY", "800 PI @ 1 CNY"
+fn leading_decimal(text: &str) -> Option<Decimal> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    let mut has_decimal = false;
+    let mut num_str = String::new();
 
-        if num_str.is_empty() {
-            return None;
+    // Handle negative sign
+    if !chars.is_empty() && chars[0] == '-' {
+        num_str.push('-');
+        i = 1;
+    }
+
+    // Skip whitespace
+    while i < chars.len() && chars[i] == ' ' {
+        i += 1;
+    }
+
+    // Parse the number (digits and optional decimal point); stops at whitespace, currency, @
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_digit() {
+            num_str.push(c);
+        } else if c == '.' && !has_decimal {
+            num_str.push(c);
+            has_decimal = true;
+        } else {
+            break;
         }
+        i += 1;
+    }
+
+    num_str.parse().ok()
+}
+
+impl Posting {
+    /// Exact units: `units` when parsed, otherwise the leading number of `amount`
+    /// Handles @ price syntax: e.g., "800 PI @ 1 CNY" returns 800 (units)
+    /// For transactions with price, the caller should calculate total: units * price
+    pub fn amount_decimal(&self) -> Option<Decimal> {
+        self.units.as_ref().map(|u| u.number).or_else(|| leading_decimal(&self.amount))
+    }
 
-        let value: f64 = num_str.parse().ok()?;
-        Some(if has_negative { -value } else { value })
+    /// Units as f64 for display and charts; sums should use `amount_decimal`
+    pub fn amount_value(&self) -> Option<f64> {
+        self.amount_decimal().and_then(|units| units.to_f64())
     }
 
     /// Get price per unit if specified (e.g., "@ 1 CNY")
     /// Returns Some((price_value, price_currency)) or None
     pub fn price_info(&self) -> Option<(f64, String)> {
-        // Look for @ in the amount string (single @ for price, @@ for total)
+        if let Some(price) = &self.price_spec {
+            let per_unit = price.per_unit(self.amount_decimal()?)?;
+            return Some((per_unit.to_f64()?, price.amount.currency.clone()));
+        }
+        // Not parsed: look for @ in the amount string (single @ for price, @@ for total)
         let at_pos = self.amount.find('@')?;
         let after_at = &self.amount[at_pos + 1..].trim();
 
@@ -550,6 +763,28 @@ impl Posting {
 pub struct Commodity {
     pub name: String,
     pub precision: u32,
+    /// Friendly name from the `name:` metadata
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Asset class from the `asset-class:` metadata (e.g. "stock", "bond")
+    #[serde(default)]
+    pub asset_class: Option<String>,
+}
+
+impl Commodity {
+    /// Display name when declared, otherwise the commodity symbol
+    pub fn label(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Price entry from a `price` directive: 1 `commodity` = `amount` `currency`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceEntry {
+    pub date: String,
+    pub commodity: String,
+    pub amount: Decimal,
+    pub currency: String,
 }
 
 /// Balance entry
@@ -557,8 +792,17 @@ pub struct Commodity {
 pub struct BalanceEntry {
     pub account: String,
     pub amount: String,
+    /// Exact value of `amount`
+    #[serde(default)]
+    pub units: Decimal,
     pub currency: String,
     pub date: String,
+    /// Source file of the directive
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Line of the directive in `source`
+    #[serde(default)]
+    pub line: Option<u32>,
 }
 
 impl Ledger {
@@ -572,28 +816,94 @@ impl Ledger {
             directives: RwLock::new(Vec::new()),
             entry: (PathBuf::new(), String::new()),
             time_context: RwLock::new(TimeContext::new(TimeRange::All)),
+            file_stats: RwLock::new(Vec::new()),
+            parse_cache: ParseCache::default(),
+            load_status: LoadStatus::default(),
+            reloads: tokio::sync::watch::channel(0).0,
+            suggestions: RwLock::new(Arc::new(AccountSuggestions::default())),
+            rules: RwLock::new(Ok(Arc::new(RuleSet::default()))),
+            timings: std::sync::Mutex::new(timing::SlowLog::default()),
+            report_cache: std::sync::Mutex::new(report_cache::ReportCache::default()),
+            options: options::LedgerOptions::default(),
         }
     }
 
     /// Load ledger from entry point
     pub async fn load(&mut self, entry: PathBuf) -> Result<(), CoreError> {
-        let directives = self.parser.parse_file(entry.clone()).await
-            .map_err(|e| CoreError::ParseError { message: e.to_string() })?;
+        self.entry = (entry.clone(), entry.to_string_lossy().to_string());
+        self.load_status.attempts += 1;
+        self.load_status.last_attempt = Some(Utc::now().to_rfc3339());
+        let started = std::time::Instant::now();
+
+        let parsed = if self.config.data.incremental_reload {
+            self.parser.parse_file_incremental(entry.clone(), &mut self.parse_cache).await
+        } else {
+            self.parser.parse_file_with_stats(entry.clone()).await
+        };
+        let (directives, file_stats) = match parsed {
+            Ok(result) => result,
+            Err(e) => {
+                let error = CoreError::ParseError { message: format!("{} ({})", e, entry.display()) };
+                self.load_status.error = Some(error.to_string());
+                return Err(error);
+            }
+        };
 
         // Store parsed directives
         {
             let mut dir_guard = self.directives.write().unwrap();
             *dir_guard = directives;
         }
-
-        self.entry = (entry.clone(), entry.to_string_lossy().to_string());
+        *self.file_stats.write().unwrap() = file_stats;
+        self.load_status.loaded = true;
+        self.load_status.error = None;
 
         // Process directives and populate data
         self.process_result().await;
+        self.load_status.duration_ms = Some(started.elapsed().as_millis() as u64);
+        self.reloads.send_modify(|generation| *generation += 1);
 
         Ok(())
     }
 
+    /// Build the ledger from directives parsed elsewhere, as [`Ledger::load`]
+    /// does after parsing; no file is read and reloads keep the old entry point
+    pub async fn load_directives(&mut self, directives: Vec<SpannedDirective>) {
+        *self.directives.write().unwrap() = directives;
+        self.process_result().await;
+    }
+
+    /// Outcome of the most recent load attempt
+    pub fn load_status(&self) -> LoadStatus {
+        self.load_status.clone()
+    }
+
+    /// Receiver notified after every successful load or reload; the value counts loads
+    pub fn subscribe_reloads(&self) -> tokio::sync::watch::Receiver<u64> {
+        self.reloads.subscribe()
+    }
+
+    /// Per-file statistics collected during the last load
+    pub fn file_stats(&self) -> Vec<FileParseStats> {
+        self.file_stats.read().unwrap().clone()
+    }
+
+    /// Files the last load parsed; with `data.incremental_reload` the
+    /// unchanged ones come from the cache and are not listed
+    pub fn reparsed_files(&self) -> Vec<PathBuf> {
+        if self.config.data.incremental_reload {
+            self.parse_cache.parsed().to_vec()
+        } else {
+            self.file_stats.read().unwrap().iter().map(|s| PathBuf::from(&s.path)).collect()
+        }
+    }
+
+    /// Problems the parser found during the last load, file by file: skipped
+    /// directives and includes of missing files
+    pub fn parse_errors(&self) -> Vec<DirectiveError> {
+        self.file_stats.read().unwrap().iter().flat_map(|f| f.errors.clone()).collect()
+    }
+
     /// Reload the ledger
     pub async fn reload(&mut self) -> Result<(), CoreError> {
         if self.entry.0.exists() {
@@ -717,9 +1027,12 @@ impl Ledger {
             Posting {
                 account: p.account.name.clone(),
                 amount: full_amount,
+                units: p.amount.as_ref().map(Amount::from),
                 currency,
                 cost: cost_str,
+                cost_spec: p.cost.as_ref().map(CostSpec::from),
                 price: price_str,
+                price_spec: p.price.as_ref().map(PriceSpec::from),
                 balance: None,
                 metadata: serde_json::Value::Object(serde_json::Map::new()),
             }
@@ -778,65 +1091,48 @@ impl Ledger {
 
     /// Process parse result into ledger data
     async fn process_result(&mut self) {
+        self.report_cache.get_mut().unwrap().clear();
         let mut data = self.data.write().unwrap();
         let directives = self.directives.read().unwrap();
 
-        // eprintln!("[DEBUG] Processing {} directives", directives.len());
-
-        // Count directive types for debugging
-        let mut open_count = 0;
-        let mut txn_count = 0;
-        let mut balance_count = 0;
-        let mut include_count = 0;
-        let mut other_count = 0;
-
-        for d in directives.iter() {
-            match &d.data {
-                Directive::Open(_) => open_count += 1,
-                Directive::Transaction(_) => txn_count += 1,
-                Directive::Balance(_) => balance_count += 1,
-                Directive::Include(_) => include_count += 1,
-                _ => other_count += 1,
-            }
-        }
-        // eprintln!("[DEBUG] Directive counts - Open: {}, Transaction: {}, Balance: {}, Include: {}, Other: {}",
-        //     open_count, txn_count, balance_count, include_count, other_count);
-
         // Clear existing data
         data.accounts.clear();
         data.transactions.clear();
         data.commodities.clear();
         data.balances.clear();
         data.pads.clear();
-
-        // Track seen accounts to avoid duplicates
-        let mut seen_accounts: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-        // First pass: collect all Pad directives (we'll process them after Balance directives)
-        let mut pad_directives: Vec<Directive> = Vec::new();
-
+        data.prices.clear();
+        data.budgets.clear();
+        data.operating_currencies.clear();
+        data.notes.clear();
+        data.documents.clear();
+        data.events.clear();
+        data.queries.clear();
+        // Options apply to the whole ledger wherever they appear
+        self.options = options::LedgerOptions::default();
         for directive in directives.iter() {
-            if let Directive::Pad(pad) = &directive.data {
-                pad_directives.push(directive.data.clone());
-                eprintln!("[DEBUG Pad collected] first_account={}, second_account={}, date={}",
-                    pad.account.name, pad.pad.name, Self::format_date(&pad.date));
+            if let Directive::Option(option) = &directive.data {
+                self.options.set(&option.key, &option.value);
             }
         }
 
-        eprintln!("[DEBUG] Total Pad directives collected: {}", pad_directives.len());
+        // Track seen accounts to avoid duplicates
+        let mut seen_accounts: std::collections::HashSet<String> = std::collections::HashSet::new();
 
-        // Second pass: process all directives
+        // Process all directives
         for directive in directives.iter() {
             match &directive.data {
                 Directive::Open(open) => {
                     let name = open.account.name.clone();
-                    // eprintln!("[DEBUG] Found account: {}", name);
                     if !seen_accounts.contains(&name) {
                         seen_accounts.insert(name.clone());
+                        // `status: "paused"` parks a seasonal account without closing it (see [`pause`])
+                        let paused = open.meta.get(pause::STATUS_KEY).is_some_and(|v| v.as_str().trim() == pause::PAUSED);
                         let account = Account {
                             name: name.clone(),
-                            account_type: Self::map_account_type(&open.account.account_type),
-                            status: AccountStatus::Open,
+                            account_type: self.options.account_type(&name)
+                                .unwrap_or_else(|| Self::map_account_type(&open.account.account_type)),
+                            status: if paused { AccountStatus::Paused } else { AccountStatus::Open },
                             balance: serde_json::Value::Null,
                             currency: open.currencies.first().cloned(),
                             open_date: Some(Self::format_date(&open.date)),
@@ -844,6 +1140,8 @@ impl Ledger {
                             alias: None,
                             note: None,
                             tags: Vec::new(),
+                            source: directive.source.clone(),
+                            line: Some(directive.span.start as u32),
                         };
                         data.accounts.push(account);
                     }
@@ -912,16 +1210,88 @@ impl Ledger {
                     let entry = BalanceEntry {
                         account: balance.account.name.clone(),
                         amount: balance.amount.amount.to_string(),
+                        units: balance.amount.amount,
                         currency: balance.amount.currency.clone(),
                         date: Self::format_date(&balance.date),
+                        source: directive.source.clone(),
+                        line: Some(directive.span.start as u32),
                     };
-                    eprintln!("[DEBUG Balance stored] account={}, date={}, amount={}",
-                        entry.account, entry.date, entry.amount);
                     data.balances.push(entry);
                 },
-                // Skip Pad here - we'll process them after all Balance directives
-                Directive::Pad(_) => {
-                    // Already collected in first pass
+                Directive::Price(price) => {
+                    data.prices.insert(PriceEntry {
+                        date: Self::format_date(&price.date),
+                        commodity: price.commodity.clone(),
+                        amount: price.amount.amount,
+                        currency: price.amount.currency.clone(),
+                    });
+                },
+                Directive::Commodity(commodity) if !data.commodities.iter().any(|c| c.name == commodity.name) => {
+                    let meta = |keys: &[&str]| keys.iter()
+                        .find_map(|key| commodity.meta.get(key))
+                        .map(|value| value.as_str().trim().to_string())
+                        .filter(|value| !value.is_empty());
+                    data.commodities.push(Commodity {
+                        name: commodity.name.clone(),
+                        precision: 2,
+                        display_name: meta(&["name"]),
+                        asset_class: meta(&["asset-class", "asset_class"]),
+                    });
+                },
+                // Turned into transactions once every balance is known, see below
+                Directive::Pad(pad) => {
+                    data.pads.push(PadEntry {
+                        account: pad.account.name.clone(),
+                        source_account: pad.pad.name.clone(),
+                        date: Self::format_date(&pad.date),
+                    });
+                },
+                Directive::Custom(custom) => {
+                    if let Some(budget) = budget::parse_budget(custom) {
+                        data.budgets.push(budget);
+                    }
+                },
+                Directive::Note(note) => {
+                    data.notes.push(notes::AccountNote {
+                        date: Self::format_date(&note.date),
+                        account: note.account.name.clone(),
+                        comment: note.comment.clone(),
+                        source: directive.source.clone(),
+                        line: Some(directive.span.start as u32),
+                    });
+                },
+                Directive::Document(document) => {
+                    data.documents.push(notes::AccountDocument {
+                        date: Self::format_date(&document.date),
+                        account: document.account.name.clone(),
+                        filename: document.filename.clone(),
+                        source: directive.source.clone(),
+                        line: Some(directive.span.start as u32),
+                    });
+                },
+                Directive::Event(event) => {
+                    data.events.push(notes::LedgerEvent {
+                        date: Self::format_date(&event.date),
+                        event_type: event.event_type.clone(),
+                        description: event.description.clone(),
+                        source: directive.source.clone(),
+                        line: Some(directive.span.start as u32),
+                    });
+                },
+                Directive::Query(query) => {
+                    data.queries.push(notes::StoredQuery {
+                        date: Self::format_date(&query.date),
+                        name: query.name.clone(),
+                        query: query.query.clone(),
+                        source: directive.source.clone(),
+                        line: Some(directive.span.start as u32),
+                    });
+                },
+                Directive::Option(option) if option.key == "operating_currency" => {
+                    let currency = option.value.trim().to_string();
+                    if !currency.is_empty() && !data.operating_currencies.contains(&currency) {
+                        data.operating_currencies.push(currency);
+                    }
                 },
                 _ => {
                     // Other directive types not yet processed
@@ -929,285 +1299,27 @@ impl Ledger {
             }
         }
 
-        // eprintln!("[DEBUG] Processed {} accounts, {} transactions, {} balances",
-        //     data.accounts.len(), data.transactions.len(), data.balances.len());
-
-        // Third pass: process Pad directives after all Balance directives have been processed
-        // This ensures we can find the correct balance amount for each Pad
-        eprintln!("[DEBUG] Available balances before Pad processing:");
-        for balance in &data.balances {
-            eprintln!("[DEBUG]   Balance: account={}, date={}, amount={}", balance.account, balance.date, balance.amount);
-        }
-
-        for pad_directive in pad_directives {
-            if let Directive::Pad(pad) = pad_directive {
-                // Store pad directive for timeline calculation
-                let entry = PadEntry {
-                    account: pad.account.name.clone(),
-                    source_account: pad.pad.name.clone(),
-                    date: Self::format_date(&pad.date),
-                };
-                data.pads.push(entry);
-
-                // Find the balance amount for the pad
-                // Beancount semantics: pad SOURCE TARGET
-                // - SOURCE: Assets/Liabilities account (has Balance directive)
-                // - TARGET: any account to be padded
-                // For Income/Expenses accounts (no Balance), we infer from SOURCE's balance change
-                let pad_date = NaiveDate::parse_from_str(&Self::format_date(&pad.date), "%Y-%m-%d")
-                    .unwrap_or_else(|_| chrono::Utc::now().date_naive());
-                let mut target_amount = String::new();
-                let mut target_currency = String::new();
-
-                // Determine if this is an Income/Expenses account (TARGET account)
-                // Income/Expenses accounts need the "difference" calculation
-                let is_income_or_expense = pad.pad.name.starts_with("Income:")
-                    || pad.pad.name.starts_with("Expenses:");
-
-                eprintln!("[DEBUG Pad calc] pad_date={}, source={}, target={}, is_income_expense={}",
-                    pad_date, pad.account.name, pad.pad.name, is_income_or_expense);
-
-                // For Income/Expenses accounts, we need to calculate the difference
-                // difference = Balance_change - Other_transactions_on_source
-                // This is because the Balance reflects ALL transactions, not just the Pad
-                if is_income_or_expense {
-                    eprintln!("[DEBUG Pad calc] Computing difference for Income/Expenses account");
-
-                    // Collect all balances for the source account (pad.account)
-                    let source_account = pad.account.name.clone();
-                    let mut source_balances: Vec<(NaiveDate, String, String)> = Vec::new();
-
-                    for balance in &data.balances {
-                        if balance.account == source_account {
-                            if let Ok(balance_date) = NaiveDate::parse_from_str(&balance.date, "%Y-%m-%d") {
-                                source_balances.push((balance_date, balance.amount.clone(), balance.currency.clone()));
-                            }
-                        }
-                    }
-
-                    // Sort by date (ascending)
-                    source_balances.sort_by(|a, b| a.0.cmp(&b.0));
-
-                    eprintln!("[DEBUG Pad calc] Found {} balances for {}", source_balances.len(), source_account);
-
-                    // Find the balance on or after the pad date
-                    let mut balance_on_pad_date = None;
-                    let mut prev_balance = None;
-
-                    for (idx, (balance_date, balance_amount, currency)) in source_balances.iter().enumerate() {
-                        if *balance_date >= pad_date {
-                            eprintln!("[DEBUG Pad calc] Found balance at {}: amount={}", balance_date, balance_amount);
-                            balance_on_pad_date = Some((balance_amount.clone(), currency.clone()));
-
-                            if idx > 0 {
-                                prev_balance = Some((source_balances[idx - 1].1.clone(), source_balances[idx - 1].2.clone()));
-                                eprintln!("[DEBUG Pad calc] Previous balance at {}: amount={}",
-                                    source_balances[idx - 1].0, source_balances[idx - 1].1);
-                            }
-                            break;
-                        }
-                    }
-
-                    if let Some((curr_amt, curr_curr)) = balance_on_pad_date {
-                        let curr_amount: f64 = curr_amt.parse().unwrap_or(0.0);
-                        target_currency = curr_curr;
-
-                        if let Some((prev_amt, _)) = prev_balance {
-                            let prev_amount: f64 = prev_amt.parse().unwrap_or(0.0);
-                            let balance_change = curr_amount - prev_amount;
-
-                            eprintln!("[DEBUG Pad calc] Balance change: {} - {} = {}",
-                                curr_amount, prev_amount, balance_change);
-
-                            // Get the actual dates for prev and current balance
-                            let prev_balance_date = source_balances.iter()
-                                .find(|(d, _, _)| *d < pad_date)
-                                .map(|(d, _, _)| *d);
-
-                            let curr_balance_date = source_balances.iter()
-                                .find(|(d, _, _)| *d >= pad_date)
-                                .map(|(d, _, _)| *d);
-
-                            eprintln!("[DEBUG Pad calc] prev_balance_date={:?}, curr_balance_date={:?}, pad_date={:?}",
-                                prev_balance_date, curr_balance_date, pad_date);
-
-                            // Calculate sum of all non-Pad transactions for the source account
-                            // We need to look at transactions BETWEEN the prev and curr balance dates
-                            // (exclusive of curr_balance_date since Balance shows balance at START of day)
-                            let mut other_tx_sum = 0.0f64;
-                            for tx in &data.transactions {
-                                let tx_date = match NaiveDate::parse_from_str(&tx.date, "%Y-%m-%d") {
-                                    Ok(d) => d,
-                                    Err(_) => continue,
-                                };
-
-                                // Skip transactions before or at prev_balance_date
-                                // (Balance shows balance at START of day, so transactions on that day count)
-                                if let Some(pbd) = prev_balance_date {
-                                    if tx_date <= pbd {
-                                        continue;
-                                    }
-                                }
-
-                                // Skip transactions on or after curr_balance_date
-                                // (Balance shows balance at START of day, so transactions on that day are after)
-                                if let Some(cbd) = curr_balance_date {
-                                    if tx_date >= cbd {
-                                        continue;
-                                    }
-                                }
-
-                                // Check if this is the Pad transaction we're processing
-                                // by looking for a posting to the target account (pad.pad.name = Income account)
-                                let is_this_pad = tx.postings.iter().any(|p| {
-                                    p.account == pad.pad.name
-                                });
-                                if is_this_pad {
-                                    continue;
-                                }
-
-                                // Sum up postings to the source account (excluding the Pad)
-                                for posting in &tx.postings {
-                                    if posting.account == source_account {
-                                        if let Some(amount_str) = posting.amount.split_whitespace().next() {
-                                            if let Ok(amount) = amount_str.parse::<f64>() {
-                                                other_tx_sum += amount;
-                                                eprintln!("[DEBUG Pad calc] Found tx {} on {}: amount={}, running_sum={}",
-                                                    tx.date, source_account, amount, other_tx_sum);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-
-                            eprintln!("[DEBUG Pad calc] Other transactions sum: {}", other_tx_sum);
-
-                            // The Pad difference = Balance change - Other transactions
-                            // This is the amount that goes to/from Income
-                            let pad_difference = balance_change - other_tx_sum;
-
-                            eprintln!("[DEBUG Pad calc] Pad difference: {} - {} = {}",
-                                balance_change, other_tx_sum, pad_difference);
-
-                            // Display as negative for Income received (credit)
-                            let display_amount = -pad_difference;
-                            target_amount = format!("{:.2}", display_amount);
-
-                            eprintln!("[DEBUG Pad calc] Final target_amount for {}: {}",
-                                pad.pad.name, target_amount);
-                        }
-                    }
-                } else {
-                    // For Assets/Liabilities accounts, use the balance amount directly
-                    let mut found_balance = false;
-                    for balance in &data.balances {
-                        if balance.account == pad.account.name {
-                            let balance_date = NaiveDate::parse_from_str(&balance.date, "%Y-%m-%d")
-                                .unwrap_or_else(|_| chrono::Utc::now().date_naive());
-                            if balance_date >= pad_date {
-                                target_amount = balance.amount.clone();
-                                target_currency = if balance.currency.is_empty() {
-                                    "CNY".to_string()
-                                } else {
-                                    balance.currency.clone()
-                                };
-                                eprintln!("[DEBUG Pad] account={}, pad_date={}, balance_date={}, amount={}",
-                                    pad.account.name, pad_date, balance_date, target_amount);
-                                found_balance = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                // If still empty, try account balance
-                if target_amount.is_empty() {
-                    if let Some(acc) = data.accounts.iter().find(|a| a.name == pad.account.name) {
-                        if let Some(balance_obj) = acc.balance.as_object() {
-                            if let Some(amount_str) = balance_obj.get("amount").and_then(|v| v.as_str()) {
-                                target_amount = amount_str.to_string();
-                            }
-                            if let Some(currency_str) = balance_obj.get("currency").and_then(|v| v.as_str()) {
-                                target_currency = currency_str.to_string();
-                            } else {
-                                target_currency = "CNY".to_string();
-                            }
-                            eprintln!("[DEBUG Pad fallback] account={}, amount={}", pad.account.name, target_amount);
-                        }
-                    }
-                }
-
-                if target_amount.is_empty() {
-                    target_amount = "0.00".to_string();
-                    target_currency = "CNY".to_string();
-                }
-
-                // Calculate amounts for the double-entry transaction
-                // target_amount is the "difference" for the TARGET account
-                // For Income:Income = negative (credit), For Assets = positive (debit)
-                let target_num: f64 = target_amount.parse().unwrap_or(0.0);
-                let source_num = -target_num;  // Opposite sign for double-entry
-
-                let target_posting_amount = format!("{:.2}", target_num);
-                let source_posting_amount = format!("{:.2}", source_num);
-
-                eprintln!("[DEBUG Generate Pad tx] target_amount={}, source_amount={}",
-                    target_posting_amount, source_posting_amount);
-
-                // Generate a synthetic transaction for the Pad directive
-                // Format: "TARGET from SOURCE" so page can correctly display description
-                let narration = format!("{} from {}", pad.account.name, pad.pad.name);
-                eprintln!("[DEBUG Generate Pad tx] narration={}", narration);
-
-                // For Assets account (pad.account.name): the difference is a decrease (negative)
-                // For Income account (pad.pad.name): the difference is income received (negative)
-                // Wait, let me reconsider:
-                // - Assets: 余利宝 balance increased from 966.05 to 10619.01
-                // - This means money came IN to 余利宝 from Income
-                // - So 余利宝 posting should be POSITIVE (money in)
-                // - Income posting should be NEGATIVE (money out / income received)
-
-                let tx = Transaction {
-                    id: format!("pad-{}", Self::format_date(&pad.date)),
-                    date: Self::format_date(&pad.date),
-                    time: String::new(),
-                    flag: None,
-                    payee: String::new(),
-                    narration: narration.clone(),
-                    tags: vec!["pad".to_string()],
-                    links: Vec::new(),
-                    postings: vec![
-                        Posting {
-                            account: pad.account.name.clone(),  // SOURCE = Assets/Liabilities
-                            amount: format!("{:.2}", -target_num),  // Negative for Assets decrease (if target was positive)
-                            currency: target_currency.clone(),
-                            cost: None,
-                            price: None,
-                            balance: None,
-                            metadata: serde_json::Value::Object(serde_json::Map::new()),
-                        },
-                        Posting {
-                            account: pad.pad.name.clone(),  // TARGET = Income/Expenses
-                            amount: target_posting_amount.clone(),  // The difference amount
-                            currency: target_currency.clone(),
-                            cost: None,
-                            price: None,
-                            balance: None,
-                            metadata: serde_json::Value::Object(serde_json::Map::new()),
-                        },
-                    ],
-                    metadata: serde_json::json!({
-                        "pad_source": pad.pad.name.clone(),
-                        "pad_date": Self::format_date(&pad.date),
-                    }),
-                    source: None,
-                    line: None,
-                };
-                data.transactions.push(tx);
-            }
-        }
+        // Pads need every balance assertion and transaction (see [`pad`])
+        let padding = pad::pad_transactions(&data.pads, &data.balances, &data.transactions);
+        data.transactions.extend(padding);
+        tracing::debug!(
+            directives = directives.len(),
+            accounts = data.accounts.len(),
+            transactions = data.transactions.len(),
+            balances = data.balances.len(),
+            pads = data.pads.len(),
+            "Ledger data rebuilt"
+        );
 
+        data.index = index::TransactionIndex::build(&data.transactions);
+        *self.suggestions.write().unwrap() = Arc::new(AccountSuggestions::new(&data.accounts));
         drop(data);
+        self.reload_rules();
+    }
+
+    /// Autocomplete index of all accounts; cheap to clone and usable without the ledger lock
+    pub fn account_suggestions(&self) -> Arc<AccountSuggestions> {
+        self.suggestions.read().unwrap().clone()
     }
 
     /// Get all accounts
@@ -1215,6 +1327,16 @@ impl Ledger {
         self.data.read().unwrap().accounts.clone()
     }
 
+    /// Get commodities declared with `commodity` directives
+    pub fn commodities(&self) -> Vec<Commodity> {
+        self.data.read().unwrap().commodities.clone()
+    }
+
+    /// Commodity declared as `name`, if any
+    pub fn commodity(&self, name: &str) -> Option<Commodity> {
+        self.data.read().unwrap().commodities.iter().find(|c| c.name == name).cloned()
+    }
+
     /// Get transactions with pagination
     pub fn transactions(&self, limit: usize, offset: usize) -> Vec<Transaction> {
         let data = self.data.read().unwrap();
@@ -1246,6 +1368,16 @@ impl Ledger {
         data.accounts.iter().find(|a| &a.name == name).cloned()
     }
 
+    /// Why postings to `names` on `date` would be rejected: accounts not
+    /// opened yet or already closed. Unknown accounts are not reported
+    pub fn inactive_accounts_on(&self, date: &str, names: &[String]) -> Vec<String> {
+        let data = self.data.read().unwrap();
+        names.iter()
+            .filter_map(|name| data.accounts.iter().find(|a| &a.name == name))
+            .filter_map(|account| account.inactive_on(date))
+            .collect()
+    }
+
     // ==================== Account Management Methods ====================
 
     /// Get all accounts
@@ -1373,6 +1505,103 @@ impl Ledger {
         }
     }
 
+    /// Get per-account posting activity, computed in a single pass over all postings
+    /// Only accounts that appear in at least one posting are included
+    pub fn account_activity(&self) -> HashMap<String, AccountActivity> {
+        let data = self.data.read().unwrap();
+        let context = self.time_context.read().unwrap().clone();
+        let mut activity: HashMap<String, AccountActivity> = HashMap::new();
+
+        for tx in &data.transactions {
+            let in_period = tx.filter_by_time(&context);
+            for posting in &tx.postings {
+                let entry = activity.entry(posting.account.clone()).or_default();
+                entry.has_transactions_in_period |= in_period;
+                // Dates are ISO formatted, so string comparison orders them
+                if entry.last_transaction_date.as_deref().is_none_or(|d| tx.date.as_str() > d) {
+                    entry.last_transaction_date = Some(tx.date.clone());
+                }
+            }
+        }
+
+        activity
+    }
+
+    /// Net change of every account over the active time range, per currency
+    /// Elided postings count with their inferred amounts; signs are the
+    /// ledger's own, so callers apply [`Ledger::display_amount`]
+    pub fn period_changes(&self) -> HashMap<String, HashMap<String, f64>> {
+        let data = self.data.read().unwrap();
+        let context = self.time_context.read().unwrap().clone();
+        let mut changes: HashMap<String, HashMap<String, Decimal>> = HashMap::new();
+
+        for tx in data.transactions.iter().filter(|tx| tx.filter_by_time(&context) && !tx.is_upcoming(context.as_of())) {
+            for ((account, currency), amount) in links::posting_units(tx) {
+                if currency.is_empty() {
+                    continue;
+                }
+                *changes.entry(account).or_default().entry(currency).or_default() += amount;
+            }
+        }
+
+        changes.into_iter()
+            .map(|(account, amounts)| {
+                let amounts = amounts.into_iter().map(|(currency, amount)| (currency, amount.to_f64().unwrap_or(0.0))).collect();
+                (account, amounts)
+            })
+            .collect()
+    }
+
+    /// Currencies actually used in postings, most used first
+    /// Ignores the time context: a typo currency matters whenever it was booked
+    pub fn currencies_in_use(&self) -> Vec<CurrencyUsage> {
+        let data = self.data.read().unwrap();
+        let declared: HashSet<&str> = data.commodities.iter().map(|c| c.name.as_str()).collect();
+        let mut usage: HashMap<String, CurrencyUsage> = HashMap::new();
+
+        for tx in &data.transactions {
+            let mut seen_in_tx: HashSet<&str> = HashSet::new();
+            for posting in tx.postings.iter().filter(|p| !p.currency.is_empty()) {
+                let entry = usage.entry(posting.currency.clone()).or_insert_with(|| CurrencyUsage {
+                    currency: posting.currency.clone(),
+                    postings: 0,
+                    transactions: 0,
+                    accounts: Vec::new(),
+                    account_types: BTreeMap::new(),
+                    first_seen: tx.date.clone(),
+                    last_seen: tx.date.clone(),
+                    declared: declared.contains(posting.currency.as_str()),
+                });
+                entry.postings += 1;
+                if seen_in_tx.insert(posting.currency.as_str()) {
+                    entry.transactions += 1;
+                }
+                if !entry.accounts.contains(&posting.account) {
+                    entry.accounts.push(posting.account.clone());
+                }
+                let account_type = posting.account.split(':').next().unwrap_or("")
+                    .parse::<AccountType>()
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|_| "other".to_string());
+                *entry.account_types.entry(account_type).or_insert(0) += 1;
+                // Dates are ISO formatted, so string comparison orders them
+                if tx.date < entry.first_seen {
+                    entry.first_seen = tx.date.clone();
+                }
+                if tx.date > entry.last_seen {
+                    entry.last_seen = tx.date.clone();
+                }
+            }
+        }
+
+        let mut currencies: Vec<CurrencyUsage> = usage.into_values().collect();
+        for usage in &mut currencies {
+            usage.accounts.sort();
+        }
+        currencies.sort_by(|a, b| b.postings.cmp(&a.postings).then_with(|| a.currency.cmp(&b.currency)));
+        currencies
+    }
+
     /// Get account count by type
     pub fn account_count_by_type(&self) -> serde_json::Value {
         let data = self.data.read().unwrap();
@@ -1385,21 +1614,36 @@ impl Ledger {
         })
     }
 
+    /// Calculate account balance from all transactions up to the today horizon
+    /// See `calculate_account_balances_as_of`
+    pub fn calculate_account_balances(&self) -> std::collections::HashMap<String, f64> {
+        self.calculate_account_balances_as_of(self.as_of_date())
+    }
+
+    /// `account_balances_as_of` as f64, for display and charts
+    pub fn calculate_account_balances_as_of(&self, as_of: Option<NaiveDate>) -> std::collections::HashMap<String, f64> {
+        self.account_balances_as_of(as_of)
+            .into_iter()
+            .map(|(account, balance)| (account, balance.to_f64().unwrap_or(0.0)))
+            .collect()
+    }
+
     /// Calculate account balance from all transactions
-    /// Returns a HashMap of account name to balance
+    /// Returns a HashMap of account name to exact balance
     /// This method correctly calculates balances by:
     /// 1. Starting with initial balances from the latest Balance directive (stored in Account.balance)
     /// 2. Adding only transactions that occur AFTER the latest Balance directive
-    pub fn calculate_account_balances(&self) -> std::collections::HashMap<String, f64> {
+    /// 3. Skipping transactions dated after `as_of` (None includes everything)
+    pub fn account_balances_as_of(&self, as_of: Option<NaiveDate>) -> std::collections::HashMap<String, Decimal> {
         let data = self.data.read().unwrap();
-        let mut balances: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
+        let mut balances: std::collections::HashMap<String, Decimal> = std::collections::HashMap::new();
 
         // Build a map of account -> (balance_date, balance_amount)
         // Account.balance stores the latest Balance directive value
-        let mut account_balance_dates: std::collections::HashMap<String, (chrono::NaiveDate, f64)> = std::collections::HashMap::new();
+        let mut account_balance_dates: std::collections::HashMap<String, (chrono::NaiveDate, Decimal)> = std::collections::HashMap::new();
 
         for account in &data.accounts {
-            if let Some(balance) = self.parse_balance(&account.balance) {
+            if let Some(balance) = Self::parse_balance(&account.balance) {
                 // Get the date from the balance JSON
                 if let Some(obj) = account.balance.as_object() {
                     if let Some(date_val) = obj.get("date") {
@@ -1416,7 +1660,7 @@ impl Ledger {
         }
 
         // Add transaction postings that occur after the balance date
-        for transaction in &data.transactions {
+        for transaction in data.transactions.iter().filter(|t| !t.is_upcoming(as_of)) {
             // Parse transaction date
             if let Some(txn_date) = transaction.date_naive() {
                 for posting in &transaction.postings {
@@ -1429,16 +1673,14 @@ impl Ledger {
                         if txn_date >= *balance_date {
                             // Use calculate_posting_amount to handle empty amounts (inferred from other postings)
                             let amount = Self::calculate_posting_amount(transaction, account_name);
-                            let entry = balances.entry(account_name.clone()).or_insert(0.0);
-                            *entry += amount;
+                            *balances.entry(account_name.clone()).or_default() += amount;
                         }
                         // If transaction is before balance date, skip it (already accounted for in balance)
                     } else {
                         // No balance directive for this account, add all transactions
                         // Use calculate_posting_amount to handle empty amounts (inferred from other postings)
                         let amount = Self::calculate_posting_amount(transaction, account_name);
-                        let entry = balances.entry(account_name.clone()).or_insert(0.0);
-                        *entry += amount;
+                        *balances.entry(account_name.clone()).or_default() += amount;
                     }
                 }
             } else {
@@ -1446,8 +1688,7 @@ impl Ledger {
                 for posting in &transaction.postings {
                     let account_name = &posting.account;
                     let amount = Self::calculate_posting_amount(transaction, account_name);
-                    let entry = balances.entry(account_name.clone()).or_insert(0.0);
-                    *entry += amount;
+                    *balances.entry(account_name.clone()).or_default() += amount;
                 }
             }
         }
@@ -1458,8 +1699,13 @@ impl Ledger {
     /// Parse amount string to f64, handling currency, signs, and commas
     /// Handles formats like "12,306.11 CNY", "-100.00 CNY", "100.00"
     fn parse_amount(amount_str: &str) -> f64 {
+        Self::parse_decimal_amount(amount_str).to_f64().unwrap_or(0.0)
+    }
+
+    /// Exact value of an amount string; see `parse_amount`
+    fn parse_decimal_amount(amount_str: &str) -> Decimal {
         if amount_str.is_empty() {
-            return 0.0;
+            return Decimal::ZERO;
         }
         // Remove commas and extract the first number
         let cleaned: String = amount_str.chars().filter(|&c| c != ',').collect();
@@ -1486,44 +1732,37 @@ impl Ledger {
                     }
                 }
                 if !num_str.is_empty() {
-                    return num_str.parse::<f64>().unwrap_or(0.0);
+                    return num_str.parse::<Decimal>().unwrap_or_default();
                 }
             }
             i += 1;
         }
-        0.0
+        Decimal::ZERO
+    }
+
+    /// Exact units of a posting: the parsed `units`, or its amount string
+    fn posting_decimal(posting: &Posting) -> Decimal {
+        posting.units.as_ref().map(|u| u.number).unwrap_or_else(|| Self::parse_decimal_amount(&posting.amount))
     }
 
     /// Calculate the posting amount for a specific account in a transaction
     /// Handles the case where posting amount is empty (inferred from other postings in the same transaction)
-    fn calculate_posting_amount(tx: &Transaction, account_name: &str) -> f64 {
+    fn calculate_posting_amount(tx: &Transaction, account_name: &str) -> Decimal {
         // First, check if this posting has an explicit amount
         if let Some(posting) = tx.postings.iter().find(|p| p.account == account_name) {
             if !posting.amount.is_empty() {
-                return Self::parse_amount(&posting.amount);
+                return Self::posting_decimal(posting);
             }
         }
 
         // If no explicit amount, calculate from other postings (Beancount double-entry)
-        let mut known_total: f64 = 0.0;
-        let mut has_known_amount = false;
-
-        for p in &tx.postings {
-            if !p.amount.is_empty() {
-                let amount = Self::parse_amount(&p.amount);
-                if amount != 0.0 {
-                    known_total += amount;
-                    has_known_amount = true;
-                }
-            }
-        }
+        let known_total: Decimal = tx.postings.iter()
+            .filter(|p| !p.amount.is_empty())
+            .map(Self::posting_decimal)
+            .sum();
 
         // For empty amount posting, the amount is the negative of known total
-        if has_known_amount {
-            -known_total
-        } else {
-            0.0
-        }
+        if known_total.is_zero() { Decimal::ZERO } else { -known_total }
     }
 
     // ==================== Transaction Management Methods ====================
@@ -1536,17 +1775,21 @@ impl Ledger {
     /// Get transaction by ID
     pub fn transaction(&self, id: &str) -> Option<Transaction> {
         let data = self.data.read().unwrap();
-        data.transactions.iter().find(|t| &t.id == id).cloned()
+        data.index.position(id).map(|i| data.transactions[i].clone())
     }
 
     /// Get transactions involving a specific account
-    pub fn transactions_by_account(&self, account_name: &str) -> Vec<Transaction> {
+    pub fn transactions_by_account(&self, account_name: &str) -> index::Transactions<'_> {
         let data = self.data.read().unwrap();
-        data.transactions
-            .iter()
-            .filter(|t| t.involves_account(account_name))
-            .cloned()
-            .collect()
+        let positions = data.index.by_account(account_name).to_vec();
+        index::Transactions::new(data, positions)
+    }
+
+    /// Transactions tagged `tag` (without the `#`), in file order
+    pub fn transactions_by_tag(&self, tag: &str) -> index::Transactions<'_> {
+        let data = self.data.read().unwrap();
+        let positions = data.index.by_tag(tag).to_vec();
+        index::Transactions::new(data, positions)
     }
 
     /// Get balances for a specific account
@@ -1594,36 +1837,67 @@ impl Ledger {
         &self,
         start_date: NaiveDate,
         end_date: NaiveDate,
-    ) -> Vec<Transaction> {
+    ) -> index::Transactions<'_> {
         let data = self.data.read().unwrap();
-        data.transactions
-            .iter()
-            .filter(|t| {
-                if let Some(date) = t.date_naive() {
-                    date >= start_date && date <= end_date
-                } else {
-                    false
-                }
-            })
-            .cloned()
+        let (start, end) = (start_date.format("%Y-%m-%d").to_string(), end_date.format("%Y-%m-%d").to_string());
+        let by_date = data.index.by_date();
+        let first = by_date.partition_point(|i| data.transactions[*i].date < start);
+        let last = by_date.partition_point(|i| data.transactions[*i].date <= end);
+        let positions: Vec<usize> = by_date[first..last.max(first)].iter().copied()
+            .filter(|i| data.transactions[*i].date_naive().is_some())
+            .collect();
+        index::Transactions::new(data, positions)
+    }
+
+    /// Transactions within `context`, by date; a bounded range only walks its
+    /// slice of the date index, so reports over one year of a long ledger
+    /// don't scan every transaction
+    fn transactions_within<'a>(data: &'a LedgerData, context: &TimeContext) -> Vec<&'a Transaction> {
+        let (start, end) = (context.start_date(), context.end_date());
+        if start.is_none() && end.is_none() {
+            return data.transactions.iter().filter(|t| t.filter_by_time(context)).collect();
+        }
+        let by_date = data.index.by_date();
+        let first = start
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .map(|start| by_date.partition_point(|i| data.transactions[*i].date < start))
+            .unwrap_or(0);
+        let last = end
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .map(|end| by_date.partition_point(|i| data.transactions[*i].date <= end))
+            .unwrap_or(by_date.len());
+        by_date[first..last.max(first)].iter()
+            .map(|i| &data.transactions[*i])
+            .filter(|t| t.filter_by_time(context))
             .collect()
     }
 
+    /// Number of transactions within `context`
+    pub fn transactions_count_in(&self, context: &TimeContext) -> usize {
+        Self::transactions_within(&self.data.read().unwrap(), context).len()
+    }
+
+    /// Years with at least one dated transaction, oldest first
+    pub fn transaction_years(&self) -> Vec<i32> {
+        let data = self.data.read().unwrap();
+        let mut years: Vec<i32> = data.index.by_date().iter()
+            .filter_map(|i| data.transactions[*i].date_naive())
+            .map(|date| date.year())
+            .collect();
+        years.dedup();
+        years
+    }
+
     /// Search transactions by payee, narration, tags, links, or account names
-    pub fn search_transactions(&self, query: &str) -> Vec<Transaction> {
+    pub fn search_transactions(&self, query: &str) -> index::Transactions<'_> {
+        let timer = QueryTimer::start("search_transactions", format!("query={:?}", query));
         let data = self.data.read().unwrap();
         let query_lower = query.to_lowercase();
-        data.transactions
-            .iter()
-            .filter(|t| {
-                t.payee.to_lowercase().contains(&query_lower)
-                    || t.narration.to_lowercase().contains(&query_lower)
-                    || t.tags.iter().any(|tag| tag.to_lowercase().contains(&query_lower))
-                    || t.links.iter().any(|link| link.to_lowercase().contains(&query_lower))
-                    || t.postings.iter().any(|p| p.account.to_lowercase().contains(&query_lower))
-            })
-            .cloned()
-            .collect()
+        let positions: Vec<usize> = (0..data.transactions.len())
+            .filter(|i| data.index.text_contains(*i, &query_lower))
+            .collect();
+        timer.finish(self, positions.len());
+        index::Transactions::new(data, positions)
     }
 
     /// Get transactions with pagination and optional filtering
@@ -1631,31 +1905,98 @@ impl Ledger {
         &self,
         limit: usize,
         offset: usize,
-        account_filter: Option<&str>,
+        account_filters: &[account_filter::AccountFilter],
         date_filter: Option<TimeContext>,
-    ) -> Vec<Transaction> {
+    ) -> index::Transactions<'_> {
+        let timer = QueryTimer::start("transaction_query", format!(
+            "limit={} offset={} accounts={} period={}",
+            limit, offset, timing::filters(account_filters),
+            date_filter.as_ref().map(timing::period).unwrap_or_default(),
+        ));
         let data = self.data.read().unwrap();
-        let mut transactions = data.transactions.iter().cloned().collect::<Vec<_>>();
-
-        // Apply account filter
-        if let Some(account) = account_filter {
-            transactions.retain(|t| t.involves_account(account));
-        }
-
-        // Apply date filter
-        if let Some(context) = date_filter {
-            transactions.retain(|t| {
-                t.date_naive()
-                    .map(|d| context.contains(&d))
-                    .unwrap_or(true)
-            });
-        }
+        // Account filters (see `account_filter`) resolve through the account index
+        let matching = data.index.matching(account_filters);
+
+        // Newest first
+        let positions: Vec<usize> = data.index.newest_first().iter().copied()
+            .filter(|i| matching.as_ref().is_none_or(|m| m.contains(i)))
+            .filter(|i| match &date_filter {
+                Some(context) => data.transactions[*i].date_naive().map(|d| context.contains(&d)).unwrap_or(true),
+                None => true,
+            })
+            .skip(offset)
+            .take(limit)
+            .collect();
+        timer.finish(self, positions.len());
+        index::Transactions::new(data, positions)
+    }
 
-        // Sort by date descending
-        transactions.sort_by(|a, b| b.date.cmp(&a.date));
+    /// Up to `limit` transactions newest first, starting right after the
+    /// transaction `after` (from the newest when None), that match the account
+    /// filters, `keywords` (as in `search_transactions`) and `keep`
+    /// Unlike an offset, the cursor stays put when transactions are added
+    /// before or after it between two pages
+    pub fn transactions_after(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        account_filters: &[account_filter::AccountFilter],
+        keywords: &str,
+        keep: impl Fn(&Transaction) -> bool,
+    ) -> Result<index::TransactionPage, CoreError> {
+        let timer = QueryTimer::start("transactions_after", format!(
+            "after={} limit={} accounts={} keywords={:?}",
+            after.unwrap_or_default(), limit, timing::filters(account_filters), keywords,
+        ));
+        let data = self.data.read().unwrap();
+        let start = match after {
+            Some(id) => {
+                let position = data.index.locate(id)
+                    .ok_or_else(|| CoreError::TransactionNotFound { id: id.to_string() })?;
+                data.index.rank(position) + 1
+            }
+            None => 0,
+        };
+        let matching = data.index.matching(account_filters);
+        let keywords = keywords.to_lowercase();
+
+        let mut positions = data.index.newest_first()[start..].iter().copied()
+            .filter(|i| matching.as_ref().is_none_or(|m| m.contains(i)))
+            .filter(|i| keywords.is_empty() || data.index.text_contains(*i, &keywords))
+            .filter(|i| keep(&data.transactions[*i]));
+        let transactions: Vec<Transaction> = positions.by_ref()
+            .take(limit)
+            .map(|i| data.transactions[i].clone())
+            .collect();
+        let next = match transactions.last() {
+            Some(last) if positions.next().is_some() => Some(last.id.clone()),
+            _ => None,
+        };
+        timer.finish(self, transactions.len());
+        Ok(index::TransactionPage { transactions, next })
+    }
 
-        // Apply pagination
-        transactions.into_iter().skip(offset).take(limit).collect()
+    /// Every transaction, newest first, that matches the account filters,
+    /// `keywords` (as in `search_transactions`) and `keep`
+    pub fn transactions_matching(
+        &self,
+        account_filters: &[account_filter::AccountFilter],
+        keywords: &str,
+        keep: impl Fn(&Transaction) -> bool,
+    ) -> index::Transactions<'_> {
+        let timer = QueryTimer::start("transactions_matching", format!(
+            "accounts={} keywords={:?}", timing::filters(account_filters), keywords,
+        ));
+        let data = self.data.read().unwrap();
+        let matching = data.index.matching(account_filters);
+        let keywords = keywords.to_lowercase();
+        let positions: Vec<usize> = data.index.newest_first().iter().copied()
+            .filter(|i| matching.as_ref().is_none_or(|m| m.contains(i)))
+            .filter(|i| keywords.is_empty() || data.index.text_contains(*i, &keywords))
+            .filter(|i| keep(&data.transactions[*i]))
+            .collect();
+        timer.finish(self, positions.len());
+        index::Transactions::new(data, positions)
     }
 
     /// Get transaction count
@@ -1665,11 +2006,7 @@ impl Ledger {
 
     /// Get transaction count for an account
     pub fn transaction_count_by_account(&self, account_name: &str) -> usize {
-        let data = self.data.read().unwrap();
-        data.transactions
-            .iter()
-            .filter(|t| t.involves_account(account_name))
-            .count()
+        self.data.read().unwrap().index.by_account(account_name).len()
     }
 
     /// Get transaction statistics
@@ -1726,6 +2063,29 @@ impl Ledger {
         ctx.custom_end = Some(end);
     }
 
+    /// Include or exclude future-dated transactions from balances and reports
+    pub fn set_include_future(&self, include_future: bool) {
+        self.time_context.write().unwrap().include_future = include_future;
+    }
+
+    /// Get the current today horizon (None when future transactions are included)
+    pub fn as_of_date(&self) -> Option<NaiveDate> {
+        self.time_context.read().unwrap().as_of()
+    }
+
+    /// Get transactions dated after today, earliest first
+    pub fn upcoming_transactions(&self) -> Vec<Transaction> {
+        let data = self.data.read().unwrap();
+        let today = Some(Utc::now().date_naive());
+        let mut upcoming: Vec<Transaction> = data.transactions
+            .iter()
+            .filter(|t| t.is_upcoming(today))
+            .cloned()
+            .collect();
+        upcoming.sort_by(|a, b| a.date.cmp(&b.date));
+        upcoming
+    }
+
     /// Get filtered transactions by current time context
     pub fn filtered_transactions(&self, limit: usize, offset: usize) -> Vec<Transaction> {
         let data = self.data.read().unwrap();
@@ -1765,156 +2125,533 @@ impl Ledger {
     // ==================== Report Generation Methods ====================
 
     /// Generate balance report
+    /// Each account's holdings are kept per currency and converted into the
+    /// operating currency at the report date through the price database.
+    /// Accounts holding a currency without a price are marked `unconverted`
+    /// and left out of the totals.
     pub fn balance_report(&self) -> BalanceReport {
-        let data = self.data.read().unwrap();
+        self.balance_report_with(ConversionMode::Converted)
+    }
+
+    /// Balance report in `mode`; with [`ConversionMode::Original`] each entry
+    /// shows the balance in the account's currency and the totals only cover
+    /// what is held in the operating currency
+    pub fn balance_report_with(&self, mode: ConversionMode) -> BalanceReport {
+        let context = self.time_context();
+        self.cached_report(format!("balance {:?} {:?}", mode, context), || {
+            let timer = QueryTimer::start("balance_report", format!("mode={:?} period={}", mode, timing::period(&context)));
+            let report = self.build_balance_report(mode);
+            timer.finish(self, report.entries.len());
+            report
+        })
+    }
+
+    fn build_balance_report(&self, mode: ConversionMode) -> BalanceReport {
         let context = self.time_context.read().unwrap().clone();
+        // Balance sheet at the end of the time range, never past today unless
+        // future transactions are included
+        let as_of = match (context.end_date(), context.as_of()) {
+            (Some(end), Some(today)) => Some(end.min(today)),
+            (end, today) => end.or(today),
+        };
+        let balances = self.account_balances_as_of(as_of);
+        // Per-currency holdings with natural signs
+        let mut holdings = self.account_holdings_as_of(as_of);
+        let as_of_str = as_of.map(|d| d.to_string());
+        let price_date = as_of_str.clone().unwrap_or_else(|| "9999-12-31".to_string());
+        let operating_currency = self.report_currency();
+        let data = self.data.read().unwrap();
 
-        // Filter accounts that have balances
+        // Accounts open at the report date
         let filtered_accounts: Vec<&Account> = data.accounts
             .iter()
-            .filter(|a| a.status == AccountStatus::Open)
+            .filter(|a| match &as_of_str {
+                Some(date) => a.open_date.as_deref().is_none_or(|open| open <= date.as_str())
+                    && a.close_date.as_deref().is_none_or(|close| close > date.as_str()),
+                None => a.status == AccountStatus::Open,
+            })
+            .filter(|a| matches!(a.account_type, AccountType::Assets | AccountType::Liabilities | AccountType::Equity))
             .collect();
 
-        // Calculate totals
-        let total_assets: f64 = filtered_accounts
-            .iter()
-            .filter(|a| a.account_type == AccountType::Assets)
-            .filter_map(|a| self.parse_balance(&a.balance))
-            .sum();
-
-        let total_liabilities: f64 = filtered_accounts
-            .iter()
-            .filter(|a| a.account_type == AccountType::Liabilities)
-            .filter_map(|a| self.parse_balance(&a.balance))
-            .sum();
+        let mut rows: Vec<(&Account, BTreeMap<String, Decimal>, Option<Decimal>)> = Vec::new();
+        for a in filtered_accounts {
+            let holdings = holdings.remove(&a.name).unwrap_or_default();
+            let converted = match mode {
+                ConversionMode::Converted => holdings.iter().try_fold(Decimal::ZERO, |sum, (currency, amount)| {
+                    data.prices.convert(*amount, currency, &operating_currency, &price_date).map(|value| sum + value)
+                }),
+                ConversionMode::Original => Some(holdings.get(&operating_currency).copied().unwrap_or_default()),
+            };
+            rows.push((a, holdings, converted));
+        }
 
-        let total_equity: f64 = filtered_accounts
-            .iter()
-            .filter(|a| a.account_type == AccountType::Equity)
-            .filter_map(|a| self.parse_balance(&a.balance))
-            .sum();
+        let total_of = |account_type: AccountType| -> Decimal {
+            rows.iter()
+                .filter(|(a, _, _)| a.account_type == account_type)
+                .filter_map(|(_, _, converted)| *converted)
+                .sum()
+        };
+        let total_assets = total_of(AccountType::Assets);
+        let total_liabilities = total_of(AccountType::Liabilities);
+        let total_equity = total_of(AccountType::Equity);
+
+        // Liabilities are booked negative, so they are added
+        let net_worth = total_assets + total_liabilities;
+
+        // Net worth per currency before conversion
+        let mut by_currency: BTreeMap<String, Decimal> = BTreeMap::new();
+        for (_, holdings, _) in rows.iter().filter(|(a, _, _)| a.account_type != AccountType::Equity) {
+            for (currency, amount) in holdings {
+                *by_currency.entry(currency.clone()).or_default() += amount;
+            }
+        }
 
-        let net_worth = total_assets - total_liabilities;
+        // Balances in the other operating currencies, None when unconvertible
+        let others = if mode == ConversionMode::Converted { operating::others(&operating_currency, &data.operating_currencies) } else { Vec::new() };
+        let in_others: Vec<Vec<Option<Decimal>>> = rows.iter()
+            .map(|(_, held, _)| others.iter()
+                .map(|other| held.iter().try_fold(Decimal::ZERO, |sum, (currency, amount)| {
+                    data.prices.convert(*amount, currency, other, &price_date).map(|value| sum + value)
+                }))
+                .collect())
+            .collect();
+        let operating_totals: Vec<BalanceTotals> = others.iter().enumerate()
+            .map(|(i, currency)| {
+                let total_of = |account_type: AccountType| -> Decimal {
+                    rows.iter().zip(&in_others)
+                        .filter(|((a, _, _), _)| a.account_type == account_type)
+                        .filter_map(|(_, values)| values[i])
+                        .sum()
+                };
+                let (assets, liabilities) = (total_of(AccountType::Assets), total_of(AccountType::Liabilities));
+                BalanceTotals {
+                    currency: currency.clone(),
+                    total_assets: decimal_string(assets.round_dp(2)),
+                    total_liabilities: decimal_string(self.display_amount("Liabilities", liabilities).round_dp(2)),
+                    net_worth: decimal_string((assets + liabilities).round_dp(2)),
+                }
+            })
+            .collect();
 
-        // Create entries
-        let entries: Vec<BalanceReportEntry> = filtered_accounts
-            .iter()
-            .map(|a| {
-                let balance = self.parse_balance(&a.balance).unwrap_or(0.0);
-                let percentage = if total_assets > 0.0 {
-                    (balance / total_assets) * 100.0
-                } else {
-                    0.0
+        let signed = |a: &Account, value: Decimal| if a.account_type == AccountType::Assets { value } else { self.display_amount(&a.name, value) };
+        let entries: Vec<BalanceReportEntry> = rows.iter().zip(&in_others)
+            .map(|((a, held, converted), in_others)| {
+                let holdings: Vec<Amount> = held.iter()
+                    .map(|(currency, amount)| Amount::new(signed(a, *amount), currency.clone()))
+                    .collect();
+                // Declared, else the only one held
+                let account_currency = || a.currency.clone()
+                    .or_else(|| (held.len() == 1).then(|| held.keys().next().cloned()).flatten())
+                    .unwrap_or_else(|| operating_currency.clone());
+                let (balance, currency) = match (mode, converted) {
+                    (ConversionMode::Original, _) => {
+                        let currency = account_currency();
+                        (signed(a, held.get(&currency).copied().unwrap_or_default()), currency)
+                    }
+                    (_, Some(value)) => (signed(a, *value), operating_currency.clone()),
+                    // Unconverted: the raw sum, in the account's currency
+                    (_, None) => (signed(a, balances.get(&a.name).copied().unwrap_or_default()), account_currency()),
                 };
                 BalanceReportEntry {
                     account: a.name.clone(),
                     account_type: a.account_type,
-                    balance: balance.to_string(),
-                    currency: a.currency.clone().unwrap_or_else(|| "USD".to_string()),
-                    percentage,
+                    balance: decimal_string(balance),
+                    percentage: if converted.is_some() && currency == operating_currency { percent_of(balance, total_assets) } else { 0.0 },
+                    currency,
+                    holdings,
+                    unconverted: converted.is_none(),
+                    operating: operating::operating_amounts(&others, &in_others.iter().map(|v| v.map(|v| signed(a, v))).collect::<Vec<_>>()),
                 }
             })
             .collect();
 
         BalanceReport {
             entries,
-            total_assets: total_assets.to_string(),
-            total_liabilities: total_liabilities.to_string(),
-            total_equity: total_equity.to_string(),
-            net_worth: net_worth.to_string(),
-            currency: "USD".to_string(),
-            as_of_date: Utc::now().date_naive().to_string(),
+            total_assets: decimal_string(total_assets),
+            total_liabilities: decimal_string(self.display_amount("Liabilities", total_liabilities)),
+            total_equity: decimal_string(self.display_amount("Equity", total_equity)),
+            net_worth: decimal_string(net_worth),
+            currency: operating_currency,
+            as_of_date: as_of_str.unwrap_or_else(|| Utc::now().date_naive().to_string()),
+            net_worth_by_currency: by_currency.into_iter()
+                .filter(|(_, amount)| !amount.is_zero())
+                .map(|(currency, amount)| Amount::new(amount, currency))
+                .collect(),
+            conversion: mode,
+            operating_totals,
         }
     }
 
     /// Generate income vs expenses report
+    /// Amounts are grouped per account and currency; foreign currencies are converted
+    /// into the operating currency through the price database at the transaction date.
+    /// Rows without a usable price are marked `unconverted` and left out of the totals.
+    /// Income follows the configured sign convention (see [`sign`]).
     pub fn income_expense_report(&self) -> IncomeExpenseReport {
+        self.income_expense_report_in(&self.time_context())
+    }
+
+    /// Income vs expenses report for an explicit period instead of the current time range
+    /// Entries below `reports.other_threshold` are merged into "Other" (see [`other`])
+    pub fn income_expense_report_in(&self, context: &TimeContext) -> IncomeExpenseReport {
+        self.with_display_signs(self.collapse_small_entries(self.natural_income_expense_report_in(context)))
+    }
+
+    /// Income vs expenses report of the current time range in `mode`; with
+    /// [`ConversionMode::Original`] entries keep their booked currency and the
+    /// totals only cover the operating currency
+    pub fn income_expense_report_with(&self, mode: ConversionMode) -> IncomeExpenseReport {
+        let report = self.natural_income_expense_report_with(&self.time_context(), mode);
+        self.with_display_signs(self.collapse_small_entries(report))
+    }
+
+    /// Income/expense report with natural signs: earned income and spending are
+    /// positive, refunds reduce them
+    fn natural_income_expense_report(&self) -> IncomeExpenseReport {
+        self.natural_income_expense_report_in(&self.time_context())
+    }
+
+    fn natural_income_expense_report_in(&self, context: &TimeContext) -> IncomeExpenseReport {
+        self.natural_income_expense_report_with(context, ConversionMode::Converted)
+    }
+
+    fn natural_income_expense_report_with(&self, context: &TimeContext, mode: ConversionMode) -> IncomeExpenseReport {
+        self.cached_report(format!("income_expense {:?} {:?}", mode, context), || {
+            let timer = QueryTimer::start("income_expense_report", format!("mode={:?} period={}", mode, timing::period(context)));
+            let report = self.build_income_expense_report(context, mode);
+            timer.finish(self, report.income_entries.len() + report.expense_entries.len());
+            report
+        })
+    }
+
+    fn build_income_expense_report(&self, context: &TimeContext, mode: ConversionMode) -> IncomeExpenseReport {
         let data = self.data.read().unwrap();
-        let context = self.time_context.read().unwrap().clone();
+        let operating_currency = data.report_currency(&self.config.currency.default_currency);
 
-        // Get filtered transactions
-        let filtered_txs: Vec<&Transaction> = data.transactions
-            .iter()
-            .filter(|t| t.filter_by_time(&context))
-            .collect();
+        let filtered_txs = Self::transactions_within(&data, context);
 
-        // Calculate income and expenses
-        let mut income_by_account: HashMap<String, f64> = HashMap::new();
-        let mut expense_by_account: HashMap<String, f64> = HashMap::new();
+        // (account, currency) -> (original amount, converted amount if every posting converted)
+        let mut income_by_account: HashMap<(String, String), (Decimal, Option<Decimal>)> = HashMap::new();
+        let mut expense_by_account: HashMap<(String, String), (Decimal, Option<Decimal>)> = HashMap::new();
+        let mut transfers = TransferSummary { volume: "0".to_string(), ..Default::default() };
+        // (account, currency) -> sum in each other operating currency, None once unconvertible
+        let others = if mode == ConversionMode::Converted { operating::others(&operating_currency, &data.operating_currencies) } else { Vec::new() };
+        let mut in_others: HashMap<(String, String), Vec<Option<Decimal>>> = HashMap::new();
 
         for tx in &filtered_txs {
+            // Internal transfers move money between own accounts; keep them out of the totals
+            if self.is_transfer(tx) {
+                Self::add_transfer(&mut transfers, tx, &data.prices, &operating_currency);
+                continue;
+            }
             for posting in &tx.postings {
-                let amount = posting.amount_value().unwrap_or(0.0);
-                if posting.account.starts_with("Income:") {
-                    *income_by_account.entry(posting.account.clone()).or_insert(0.0) += amount.abs();
-                } else if posting.account.starts_with("Expenses:") {
-                    *expense_by_account.entry(posting.account.clone()).or_insert(0.0) += amount.abs();
+                let target = if self.options.is(&posting.account, AccountType::Income) {
+                    &mut income_by_account
+                } else if self.options.is(&posting.account, AccountType::Expenses) {
+                    &mut expense_by_account
+                } else {
+                    continue;
+                };
+                let amount = sign::display_amount(self.options.account_type(&posting.account), posting.amount_decimal().unwrap_or_default(), SignConvention::Natural);
+                let converted = data.prices.convert(amount, &posting.currency, &operating_currency, &tx.date);
+                let entry = target.entry((posting.account.clone(), posting.currency.clone())).or_insert((Decimal::ZERO, Some(Decimal::ZERO)));
+                entry.0 += amount;
+                entry.1 = entry.1.zip(converted).map(|(sum, value)| sum + value);
+                if !others.is_empty() {
+                    let sums = in_others.entry((posting.account.clone(), posting.currency.clone()))
+                        .or_insert_with(|| vec![Some(Decimal::ZERO); others.len()]);
+                    for (sum, other) in sums.iter_mut().zip(&others) {
+                        *sum = sum.zip(data.prices.convert(amount, &posting.currency, other, &tx.date)).map(|(sum, value)| sum + value);
+                    }
                 }
             }
         }
 
-        let total_income: f64 = income_by_account.values().sum();
-        let total_expenses: f64 = expense_by_account.values().sum();
-        let net_income = total_income - total_expenses;
-
-        // Create income entries
-        let income_entries: Vec<IncomeExpenseEntry> = income_by_account
-            .into_iter()
-            .map(|(account, amount)| {
-                let percentage = if total_income > 0.0 { (amount / total_income) * 100.0 } else { 0.0 };
-                let category = account.split(':').nth(1).unwrap_or(&account).to_string();
-                IncomeExpenseEntry { account, amount: amount.to_string(), percentage, category }
+        let mut income_entries = Self::income_expense_entries(income_by_account, mode);
+        let mut expense_entries = Self::income_expense_entries(expense_by_account, mode);
+        for entry in income_entries.iter_mut().chain(expense_entries.iter_mut()) {
+            if let Some(sums) = in_others.get(&(entry.account.clone(), entry.currency.clone())) {
+                entry.operating = operating::operating_amounts(&others, sums);
+            }
+        }
+        let operating_totals: Vec<IncomeExpenseTotals> = others.iter()
+            .map(|currency| {
+                let income = operating::total_in(income_entries.iter().map(|e| &e.operating), currency);
+                let expenses = operating::total_in(expense_entries.iter().map(|e| &e.operating), currency);
+                IncomeExpenseTotals {
+                    currency: currency.clone(),
+                    total_income: decimal_string(income),
+                    total_expenses: decimal_string(expenses),
+                    net_income: decimal_string(income - expenses),
+                }
             })
             .collect();
 
-        // Create expense entries
-        let expense_entries: Vec<IncomeExpenseEntry> = expense_by_account
-            .into_iter()
-            .map(|(account, amount)| {
-                let percentage = if total_expenses > 0.0 { (amount / total_expenses) * 100.0 } else { 0.0 };
-                let category = account.split(':').nth(1).unwrap_or(&account).to_string();
-                IncomeExpenseEntry { account, amount: amount.to_string(), percentage, category }
-            })
-            .collect();
+        let total_of = |entries: &[IncomeExpenseEntry]| -> Decimal {
+            entries.iter()
+                .filter(|e| e.in_totals(&operating_currency, mode))
+                .map(|e| parse_decimal(&e.amount))
+                .sum()
+        };
+        let total_income = total_of(&income_entries);
+        let total_expenses = total_of(&expense_entries);
+        let net_income = total_income - total_expenses;
+        Self::fill_percentages(&mut income_entries, total_income, &operating_currency, mode);
+        Self::fill_percentages(&mut expense_entries, total_expenses, &operating_currency, mode);
+
+        let by_currency = |entries: &[IncomeExpenseEntry]| -> Vec<Amount> {
+            let mut sums: BTreeMap<String, Decimal> = BTreeMap::new();
+            for e in entries {
+                *sums.entry(e.currency.clone()).or_default() += parse_decimal(&e.original_amount);
+            }
+            sums.into_iter()
+                .filter(|(_, amount)| !amount.is_zero())
+                .map(|(currency, amount)| Amount::new(amount, currency))
+                .collect()
+        };
 
         let start_date = context.start_date().map(|d| d.to_string()).unwrap_or_default();
         let end_date = context.end_date().map(|d| d.to_string()).unwrap_or_default();
 
         IncomeExpenseReport {
+            income_by_currency: by_currency(&income_entries),
+            expenses_by_currency: by_currency(&expense_entries),
             income_entries,
             expense_entries,
-            total_income: total_income.to_string(),
-            total_expenses: total_expenses.to_string(),
-            net_income: net_income.to_string(),
-            currency: "USD".to_string(),
+            total_income: decimal_string(total_income),
+            total_expenses: decimal_string(total_expenses),
+            net_income: decimal_string(net_income),
+            currency: operating_currency,
             period_start: start_date,
             period_end: end_date,
+            transfers,
+            conversion: mode,
+            operating_totals,
         }
     }
 
-    /// Generate category report for expenses
-    pub fn expense_category_report(&self) -> CategoryReport {
-        let report = self.income_expense_report();
-        let total: f64 = report.total_expenses.parse().unwrap_or(0.0);
+    /// Share of `total` for the entries counted in it, 0 for the others
+    fn fill_percentages(entries: &mut [IncomeExpenseEntry], total: Decimal, report_currency: &str, mode: ConversionMode) {
+        for e in entries {
+            e.percentage = if e.in_totals(report_currency, mode) { percent_of(parse_decimal(&e.amount), total) } else { 0.0 };
+        }
+    }
+
+    /// Apply the configured sign convention to a natural-sign report
+    fn with_display_signs(&self, report: IncomeExpenseReport) -> IncomeExpenseReport {
+        if self.sign_convention() == SignConvention::Natural {
+            return report;
+        }
+        fn negate(value: &str) -> String {
+            decimal_string(-parse_decimal(value))
+        }
+        fn negate_entry(e: IncomeExpenseEntry) -> IncomeExpenseEntry {
+            IncomeExpenseEntry {
+                amount: negate(&e.amount),
+                original_amount: negate(&e.original_amount),
+                members: e.members.into_iter().map(negate_entry).collect(),
+                operating: e.operating.into_iter().map(|a| Amount::new(-a.number, a.currency)).collect(),
+                ..e
+            }
+        }
+        // Raw: income as booked, and net income as the booked Income + Expenses sum
+        IncomeExpenseReport {
+            income_entries: report.income_entries.into_iter().map(negate_entry).collect(),
+            income_by_currency: report.income_by_currency.into_iter().map(|a| Amount::new(-a.number, a.currency)).collect(),
+            total_income: negate(&report.total_income),
+            net_income: negate(&report.net_income),
+            operating_totals: report.operating_totals.into_iter()
+                .map(|t| IncomeExpenseTotals { total_income: negate(&t.total_income), net_income: negate(&t.net_income), ..t })
+                .collect(),
+            ..report
+        }
+    }
+
+    /// Booked amount converted to the configured display sign (see [`sign`]);
+    /// renamed roots count as the root they stand for
+    pub fn display_amount<T: sign::SignedAmount>(&self, account: &str, amount: T) -> T {
+        sign::display_amount(self.options.account_type(account), amount, self.sign_convention())
+    }
+
+    /// Configured display sign convention
+    pub fn sign_convention(&self) -> SignConvention {
+        self.config.currency.sign_convention
+    }
+
+    /// A transfer has every posting in a configured transfer account, at
+    /// least one of them in Assets/Liabilities
+    fn is_transfer(&self, tx: &Transaction) -> bool {
+        let config = &self.config.reports.transfers;
+        let balance_sheet = |account: &str| self.options.is(account, AccountType::Assets) || self.options.is(account, AccountType::Liabilities);
+        config.enabled
+            && tx.postings.len() >= 2
+            && tx.postings.iter().all(|p| config.is_transfer_account(&p.account))
+            && tx.postings.iter().any(|p| balance_sheet(&p.account))
+    }
+
+    /// Add one transfer to the summary, keyed by (from, to) account pair
+    fn add_transfer(summary: &mut TransferSummary, tx: &Transaction, prices: &PriceDatabase, operating_currency: &str) {
+        let mut inflow = Decimal::ZERO;
+        let mut outflow = Decimal::ZERO;
+        for posting in &tx.postings {
+            let Some(value) = posting.amount_decimal() else { continue };
+            let Some(converted) = prices.convert(value, &posting.currency, operating_currency, &tx.date) else { continue };
+            if converted.is_sign_positive() { inflow += converted } else { outflow -= converted }
+        }
+        let amount = inflow.max(outflow);
+
+        // The source leg is the first negative posting, the target the first positive;
+        // an auto-balanced (empty) posting fills whichever side is missing
+        let side = |positive: bool| {
+            tx.postings.iter()
+                .find(|p| p.amount_decimal().is_some_and(|v| !v.is_zero() && v.is_sign_positive() == positive))
+                .or_else(|| tx.postings.iter().find(|p| p.amount_decimal().is_none()))
+                .map(|p| p.account.clone())
+                .unwrap_or_default()
+        };
+        let (from_account, to_account) = (side(false), side(true));
+
+        summary.count += 1;
+        summary.volume = decimal_string(parse_decimal(&summary.volume) + amount);
+        match summary.entries.iter_mut().find(|e| e.from_account == from_account && e.to_account == to_account) {
+            Some(entry) => {
+                entry.amount = decimal_string(parse_decimal(&entry.amount) + amount);
+                entry.count += 1;
+            }
+            None => summary.entries.push(TransferEntry { from_account, to_account, amount: decimal_string(amount), count: 1 }),
+        }
+        summary.entries.sort_by_key(|e| std::cmp::Reverse(parse_decimal(&e.amount)));
+    }
 
-        let breakdowns: Vec<CategoryBreakdown> = report.expense_entries
+    /// Build report entries from per-(account, currency) sums; percentages are filled in by the caller
+    fn income_expense_entries(sums: HashMap<(String, String), (Decimal, Option<Decimal>)>, mode: ConversionMode) -> Vec<IncomeExpenseEntry> {
+        let mut entries: Vec<IncomeExpenseEntry> = sums
             .into_iter()
-            .map(|entry| {
-                let amount: f64 = entry.amount.parse().unwrap_or(0.0);
-                let percentage = if total > 0.0 { (amount / total) * 100.0 } else { 0.0 };
-                CategoryBreakdown {
-                    category: entry.category,
-                    amount: entry.amount,
-                    count: 1,
-                    percentage,
+            .map(|((account, currency), (original, converted))| {
+                let category = account.split(':').nth(1).unwrap_or(&account).to_string();
+                let (amount, unconverted) = match mode {
+                    ConversionMode::Converted => (converted.unwrap_or(original), converted.is_none()),
+                    ConversionMode::Original => (original, false),
+                };
+                IncomeExpenseEntry {
+                    account,
+                    amount: decimal_string(amount),
+                    percentage: 0.0,
+                    category,
+                    currency,
+                    original_amount: decimal_string(original),
+                    unconverted,
+                    members: Vec::new(),
+                    operating: Vec::new(),
                 }
             })
             .collect();
+        entries.sort_by(|a, b| a.account.cmp(&b.account).then_with(|| a.currency.cmp(&b.currency)));
+        entries
+    }
+
+    /// Generate income vs expenses report rolled up by the configured report groups
+    /// Accounts outside every group are kept as-is
+    pub fn grouped_income_expense_report(&self) -> IncomeExpenseReport {
+        self.grouped_income_expense_report_with(ConversionMode::Converted)
+    }
+
+    /// Grouped income vs expenses report in `mode` (see [`Self::income_expense_report_with`])
+    pub fn grouped_income_expense_report_with(&self, mode: ConversionMode) -> IncomeExpenseReport {
+        let report = self.natural_income_expense_report_with(&self.time_context(), mode);
+        let groups = &self.config.reports;
+        let total_income = parse_decimal(&report.total_income);
+        let total_expenses = parse_decimal(&report.total_expenses);
+
+        let mut income_entries = Self::group_report_entries(report.income_entries, total_income, groups);
+        let mut expense_entries = Self::group_report_entries(report.expense_entries, total_expenses, groups);
+        Self::fill_percentages(&mut income_entries, total_income, &report.currency, mode);
+        Self::fill_percentages(&mut expense_entries, total_expenses, &report.currency, mode);
+        self.with_display_signs(self.collapse_small_entries(IncomeExpenseReport {
+            income_entries,
+            expense_entries,
+            ..report
+        }))
+    }
+
+    /// Merge report entries that belong to the same report group
+    fn group_report_entries(entries: Vec<IncomeExpenseEntry>, total: Decimal, groups: &beanweb_config::ReportsConfig) -> Vec<IncomeExpenseEntry> {
+        let mut grouped: Vec<IncomeExpenseEntry> = Vec::new();
+        for entry in entries {
+            let Some(name) = groups.group_for(&entry.account) else {
+                grouped.push(entry);
+                continue;
+            };
+            let amount = parse_decimal(&entry.amount);
+            let original = parse_decimal(&entry.original_amount);
+            let existing = grouped.iter_mut()
+                .find(|e| e.account == name && e.currency == entry.currency && e.unconverted == entry.unconverted);
+            match existing {
+                Some(existing) => {
+                    existing.amount = decimal_string(parse_decimal(&existing.amount) + amount);
+                    existing.original_amount = decimal_string(parse_decimal(&existing.original_amount) + original);
+                    operating::add_amounts(&mut existing.operating, &entry.operating);
+                }
+                None => grouped.push(IncomeExpenseEntry {
+                    account: name.to_string(),
+                    amount: decimal_string(amount),
+                    percentage: 0.0,
+                    category: name.to_string(),
+                    currency: entry.currency,
+                    original_amount: decimal_string(original),
+                    unconverted: entry.unconverted,
+                    members: Vec::new(),
+                    operating: entry.operating,
+                }),
+            }
+        }
+        for entry in &mut grouped {
+            entry.percentage = if entry.unconverted { 0.0 } else { percent_of(parse_decimal(&entry.amount), total) };
+        }
+        grouped
+    }
+
+    /// Generate category report for expenses
+    /// Accounts are merged by category (the second account component), largest first;
+    /// `count` is the number of accounts in the category
+    pub fn expense_category_report(&self) -> CategoryReport {
+        self.expense_category_report_in(&self.time_context())
+    }
+
+    /// Expense category report for an explicit period
+    /// Categories below `reports.other_threshold` are merged into "Other" (see [`other`])
+    pub fn expense_category_report_in(&self, context: &TimeContext) -> CategoryReport {
+        // Merged per category below, not per account
+        let report = self.with_display_signs(self.natural_income_expense_report_in(context));
+        let total = parse_decimal(&report.total_expenses);
+
+        let mut breakdowns: Vec<CategoryBreakdown> = Vec::new();
+        for entry in report.expense_entries.into_iter().filter(|entry| !entry.unconverted) {
+            let amount = parse_decimal(&entry.amount);
+            match breakdowns.iter_mut().find(|b| b.category == entry.category) {
+                Some(breakdown) => {
+                    breakdown.amount = decimal_string(parse_decimal(&breakdown.amount) + amount);
+                    breakdown.count += 1;
+                }
+                None => breakdowns.push(CategoryBreakdown {
+                    category: entry.category,
+                    amount: decimal_string(amount),
+                    count: 1,
+                    percentage: 0.0,
+                    members: Vec::new(),
+                }),
+            }
+        }
+        for breakdown in &mut breakdowns {
+            breakdown.percentage = percent_of(parse_decimal(&breakdown.amount), total);
+        }
+        breakdowns.sort_by_key(|b| std::cmp::Reverse(parse_decimal(&b.amount)));
+        let breakdowns = other::collapse_breakdowns(breakdowns, self.config.reports.other_threshold);
 
         CategoryReport {
             category_type: "expenses".to_string(),
             breakdowns,
-            total: total.to_string(),
+            total: decimal_string(total),
             currency: report.currency,
         }
     }
@@ -1966,6 +2703,16 @@ impl Ledger {
 
     /// Generate net worth report
     pub fn net_worth_report(&self) -> NetWorthReport {
+        let context = self.time_context();
+        self.cached_report(format!("net_worth {:?}", context), || {
+            let timer = QueryTimer::start("net_worth_report", format!("period={}", timing::period(&context)));
+            let report = self.build_net_worth_report();
+            timer.finish(self, report.points.len());
+            report
+        })
+    }
+
+    fn build_net_worth_report(&self) -> NetWorthReport {
         let data = self.data.read().unwrap();
         let context = self.time_context.read().unwrap().clone();
 
@@ -1984,9 +2731,9 @@ impl Ledger {
         for tx in &transactions {
             for posting in &tx.postings {
                 let amount = posting.amount_value().unwrap_or(0.0);
-                if posting.account.starts_with("Assets:") {
+                if self.options.is(&posting.account, AccountType::Assets) {
                     running_assets += amount;
-                } else if posting.account.starts_with("Liabilities:") {
+                } else if self.options.is(&posting.account, AccountType::Liabilities) {
                     running_liabilities += amount;
                 }
             }
@@ -2078,29 +2825,91 @@ impl Ledger {
         }
     }
 
+    /// Generate net income allocation report (where did the surplus go)
+    /// Combines the income/expense report with the balance change of every
+    /// asset and liability account over the same period, per currency:
+    /// - Asset increases and liability decreases absorb net income
+    /// - Only changes in the report currency count toward the allocated total;
+    ///   whatever is left over is reported as the unexplained residual
+    pub fn allocation_report(&self) -> AllocationReport {
+        let income_expense = self.natural_income_expense_report();
+        let net_income = Self::parse_decimal_amount(&income_expense.net_income);
+
+        let data = self.data.read().unwrap();
+        let context = self.time_context.read().unwrap().clone();
+        let is_balance_sheet = |account: &str| self.options.is(account, AccountType::Assets) || self.options.is(account, AccountType::Liabilities);
+
+        // Balance diff over the period for balance sheet accounts
+        let mut change_by_account: HashMap<(String, String), Decimal> = HashMap::new();
+        for tx in data.transactions.iter().filter(|t| t.filter_by_time(&context)) {
+            for posting in tx.postings.iter().filter(|p| !p.amount.is_empty() && !p.currency.is_empty() && is_balance_sheet(&p.account)) {
+                *change_by_account.entry((posting.account.clone(), posting.currency.clone())).or_default() += Self::posting_decimal(posting);
+            }
+            // An elided posting takes the residual of every currency
+            if let Some(elided) = tx.postings.iter().find(|p| p.amount.is_empty() && is_balance_sheet(&p.account)) {
+                for (currency, residual) in integrity::residuals(tx) {
+                    *change_by_account.entry((elided.account.clone(), currency)).or_default() -= residual;
+                }
+            }
+        }
+
+        let mut entries: Vec<AllocationEntry> = change_by_account
+            .into_iter()
+            .filter(|(_, change)| !change.is_zero())
+            .map(|((account, currency), change)| {
+                let account_type = if self.options.is(&account, AccountType::Liabilities) {
+                    AccountType::Liabilities
+                } else {
+                    AccountType::Assets
+                };
+                AllocationEntry { account, account_type, currency, amount: change }
+            })
+            .collect();
+        // Report currency first, then largest allocations first
+        entries.sort_by(|a, b| {
+            (b.currency == income_expense.currency).cmp(&(a.currency == income_expense.currency))
+                .then_with(|| b.amount.cmp(&a.amount))
+                .then_with(|| a.account.cmp(&b.account))
+        });
+
+        let total_allocated: Decimal = entries.iter()
+            .filter(|e| e.currency == income_expense.currency)
+            .map(|e| e.amount)
+            .sum();
+
+        AllocationReport {
+            net_income,
+            entries,
+            total_allocated,
+            residual: net_income - total_allocated,
+            currency: income_expense.currency,
+            period_start: income_expense.period_start,
+            period_end: income_expense.period_end,
+        }
+    }
+
     /// Helper to parse balance from JSON value
-    fn parse_balance(&self, balance: &serde_json::Value) -> Option<f64> {
+    fn parse_balance(balance: &serde_json::Value) -> Option<Decimal> {
+        let number = |value: &serde_json::Value| value.as_f64().and_then(Decimal::from_f64);
         if balance.is_number() {
-            balance.as_f64()
+            number(balance)
         } else if let Some(s) = balance.as_str() {
             // Handle strings like "-6307.77 CNY"
-            Some(Self::parse_amount(s))
+            Some(Self::parse_decimal_amount(s))
         } else if let Some(obj) = balance.as_object() {
             // Try to get the "amount" field specifically
             if let Some(amount_val) = obj.get("amount") {
                 if let Some(s) = amount_val.as_str() {
-                    Some(Self::parse_amount(s))
-                } else if let Some(n) = amount_val.as_f64() {
-                    Some(n)
+                    Some(Self::parse_decimal_amount(s))
                 } else {
-                    Some(0.0)
+                    Some(number(amount_val).unwrap_or_default())
                 }
             } else {
                 // Fallback to first value
-                obj.values().next().and_then(|v| v.as_f64())
+                obj.values().next().and_then(number)
             }
         } else {
-            Some(0.0)
+            Some(Decimal::ZERO)
         }
     }
 
@@ -2213,7 +3022,8 @@ impl Ledger {
             .map_err(|e| CoreError::IoError)
     }
 
-    /// Write document content (with backup)
+    /// Write document content (with backup); the file is replaced in one
+    /// step, so a failed write leaves the previous content in place
     pub fn write_document(&self, path: &str, content: &str) -> Result<(), CoreError> {
         // Create backup
         let backup_path = format!("{}.bak", path);
@@ -2223,7 +3033,7 @@ impl Ledger {
         }
 
         // Write new content
-        std::fs::write(path, content)
+        beanweb_utils::write_atomic(std::path::Path::new(path), content.as_bytes())
             .map_err(|_| CoreError::IoError)?;
 
         Ok(())
@@ -2791,6 +3601,38 @@ pub struct AccountTreeNode {
     pub children: Vec<AccountTreeNode>,
 }
 
+/// Posting activity of a single account
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountActivity {
+    pub has_transactions_in_period: bool,
+    pub last_transaction_date: Option<String>,
+}
+
+/// Where a currency occurs in the ledger's postings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyUsage {
+    pub currency: String,
+    /// Number of postings in this currency
+    pub postings: usize,
+    /// Number of transactions with at least one such posting
+    pub transactions: usize,
+    /// Accounts posted to in this currency, sorted
+    pub accounts: Vec<String>,
+    /// Posting count per account type ("assets", "expenses", ...)
+    pub account_types: BTreeMap<String, usize>,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// Declared by a `commodity` directive
+    pub declared: bool,
+}
+
+impl CurrencyUsage {
+    /// Used once and never declared: most likely a typo (CNH for CNY)
+    pub fn is_suspicious(&self) -> bool {
+        self.postings == 1 && !self.declared
+    }
+}
+
 /// Account balance summary for reports
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AccountBalanceSummary {
@@ -2866,13 +3708,24 @@ pub struct JournalResponse {
 pub struct BalanceReportEntry {
     pub account: String,
     pub account_type: AccountType,
+    /// Balance in the report currency, or the raw sum in `currency` when `unconverted`
     pub balance: String,
     pub currency: String,
     pub percentage: f64,
+    /// Balance per currency held, before conversion
+    #[serde(default)]
+    pub holdings: Vec<Amount>,
+    /// A held currency has no price into the report currency
+    #[serde(default)]
+    pub unconverted: bool,
+    /// Balance in the other operating currencies (see [`operating`])
+    #[serde(default)]
+    pub operating: Vec<Amount>,
 }
 
 /// Balance report for all accounts
-#[derive(Debug, Serialize, Deserialize)]
+/// Totals are in `currency`, the operating currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BalanceReport {
     pub entries: Vec<BalanceReportEntry>,
     pub total_assets: String,
@@ -2881,10 +3734,18 @@ pub struct BalanceReport {
     pub net_worth: String,
     pub currency: String,
     pub as_of_date: String,
+    /// Assets and liabilities per currency before conversion
+    #[serde(default)]
+    pub net_worth_by_currency: Vec<Amount>,
+    #[serde(default)]
+    pub conversion: ConversionMode,
+    /// Totals in the other operating currencies (see [`operating`])
+    #[serde(default)]
+    pub operating_totals: Vec<BalanceTotals>,
 }
 
 /// Income vs Expenses report
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IncomeExpenseReport {
     pub income_entries: Vec<IncomeExpenseEntry>,
     pub expense_entries: Vec<IncomeExpenseEntry>,
@@ -2894,15 +3755,70 @@ pub struct IncomeExpenseReport {
     pub currency: String,
     pub period_start: String,
     pub period_end: String,
+    /// Internal transfers excluded from the totals above
+    #[serde(default)]
+    pub transfers: TransferSummary,
+    /// Income and expenses per currency before conversion
+    #[serde(default)]
+    pub income_by_currency: Vec<Amount>,
+    #[serde(default)]
+    pub expenses_by_currency: Vec<Amount>,
+    #[serde(default)]
+    pub conversion: ConversionMode,
+    /// Totals in the other operating currencies (see [`operating`])
+    #[serde(default)]
+    pub operating_totals: Vec<IncomeExpenseTotals>,
 }
 
-/// Income/Expense report entry
+/// Internal transfers between own accounts within the report period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferSummary {
+    pub count: usize,
+    /// Total amount moved, in the report currency
+    pub volume: String,
+    pub entries: Vec<TransferEntry>,
+}
+
+/// Transfers from one account to another, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferEntry {
+    pub from_account: String,
+    pub to_account: String,
+    pub amount: String,
+    pub count: usize,
+}
+
+/// Income/Expense report entry (one per account and currency)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IncomeExpenseEntry {
     pub account: String,
+    /// Amount in the report currency, or in `currency` when `unconverted`
     pub amount: String,
     pub percentage: f64,
     pub category: String,
+    /// Currency the postings were booked in
+    pub currency: String,
+    /// Amount in `currency`
+    pub original_amount: String,
+    /// No price was available to convert into the report currency
+    pub unconverted: bool,
+    /// Entries merged into this one (only for [`other::OTHER`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<IncomeExpenseEntry>,
+    /// Amount in the other operating currencies (see [`operating`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operating: Vec<Amount>,
+}
+
+impl IncomeExpenseEntry {
+    /// Whether the entry is part of the report totals: converted entries, or
+    /// with [`ConversionMode::Original`] the ones booked in the report currency
+    pub fn in_totals(&self, report_currency: &str, mode: ConversionMode) -> bool {
+        match mode {
+            ConversionMode::Converted => !self.unconverted,
+            ConversionMode::Original => self.currency == report_currency,
+        }
+    }
 }
 
 /// Net worth over time
@@ -2915,7 +3831,7 @@ pub struct NetWorthPoint {
 }
 
 /// Net worth history report
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetWorthReport {
     pub points: Vec<NetWorthPoint>,
     pub start_net_worth: String,
@@ -2925,6 +3841,30 @@ pub struct NetWorthReport {
     pub currency: String,
 }
 
+/// Net income allocation report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationReport {
+    pub net_income: Decimal,
+    pub entries: Vec<AllocationEntry>,
+    /// Sum of the entries in the report currency
+    pub total_allocated: Decimal,
+    pub residual: Decimal,
+    pub currency: String,
+    pub period_start: String,
+    pub period_end: String,
+}
+
+/// Net income allocation entry
+/// Positive amount means the account absorbed surplus
+/// (asset increase or liability paydown)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationEntry {
+    pub account: String,
+    pub account_type: AccountType,
+    pub currency: String,
+    pub amount: Decimal,
+}
+
 /// Monthly summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonthlySummary {
@@ -2938,6 +3878,7 @@ pub struct MonthlySummary {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MonthlySummaryReport {
     pub summaries: Vec<MonthlySummary>,
+    pub year: i32,
     pub total_income: String,
     pub total_expenses: String,
     pub total_net: String,
@@ -2951,6 +3892,9 @@ pub struct CategoryBreakdown {
     pub amount: String,
     pub count: usize,
     pub percentage: f64,
+    /// Categories merged into this one (only for [`other::OTHER`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<CategoryBreakdown>,
 }
 
 /// Category report
@@ -3201,6 +4145,2882 @@ impl LedgerOperations for Ledger {
 mod tests {
     use super::*;
 
+    /// Build a ledger from in-memory Beancount source
+    async fn ledger_from_source(source: &str) -> Ledger {
+        ledger_from_source_with_config(source, Config::default()).await
+    }
+
+    async fn ledger_from_source_with_config(source: &str, config: Config) -> Ledger {
+        let parser = Arc::new(beanweb_parser::DefaultBeancountParser);
+        let directives = parser.parse(source).await.unwrap().directives;
+        let mut ledger = Ledger::new(config, parser);
+        ledger.load_directives(directives).await;
+        ledger
+    }
+
+    #[tokio::test]
+    async fn test_paused_account() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Ski-Pass CNY
+  status: "paused"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Equity:Opening
+
+2024-01-02 * "Top up"
+  Assets:Ski-Pass  200.00 CNY
+  Equity:Opening
+"#).await;
+        let pass = ledger.account("Assets:Ski-Pass").unwrap();
+        assert_eq!(pass.status, AccountStatus::Paused);
+        assert_eq!(pass.line, Some(2));
+        assert_eq!(ledger.account("Assets:Bank").unwrap().line, Some(4));
+
+        // Still counted, but not suggested
+        assert!(ledger.balance_report().entries.iter().any(|e| e.account == "Assets:Ski-Pass" && e.balance == "200"));
+        let names: Vec<String> = ledger.account_suggestions().suggest("assets", 10).iter().map(|s| s.name.clone()).collect();
+        assert_eq!(names, ["Assets:Bank"]);
+        assert!(render::open(&pass).unwrap().ends_with("  status: \"paused\"\n"));
+    }
+
+    #[tokio::test]
+    async fn test_rolled_up_balances() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank:Checking CNY
+2024-01-01 open Assets:Bank:Savings CNY
+2024-01-01 open Assets:Broker USD
+2024-01-01 open Equity:Opening
+2024-01-01 open Expenses:Food:Lunch
+
+2024-01-02 * "Opening"
+  Assets:Bank:Checking  100.00 CNY
+  Equity:Opening
+2024-01-02 * "Opening"
+  Assets:Bank:Savings  50.00 CNY
+  Equity:Opening
+2024-01-02 * "Opening"
+  Assets:Broker  10.00 USD
+  Equity:Opening
+2024-01-03 * "Lunch"
+  Expenses:Food:Lunch  30.00 CNY
+  Assets:Bank:Checking
+"#).await;
+        let rolled = ledger.rolled_up_balances();
+        let amount = |account: &str, currency: &str| rolled.get(account).and_then(|a| a.get(currency)).map(|d| d.to_string());
+
+        assert_eq!(amount("Assets:Bank:Checking", "CNY").as_deref(), Some("70.00"));
+        assert_eq!(amount("Assets:Bank", "CNY").as_deref(), Some("120.00"));
+        assert_eq!(amount("Assets", "CNY").as_deref(), Some("120.00"));
+        assert_eq!(amount("Assets", "USD").as_deref(), Some("10.00"));
+        assert_eq!(amount("Expenses", "CNY").as_deref(), Some("30.00"));
+        // Per currency, never converted or mixed
+        assert!(!rolled["Assets:Bank"].contains_key("USD"));
+        assert_eq!(amount("Equity", "CNY").as_deref(), Some("-150.00"));
+        // Computed once per reload
+        assert!(Arc::ptr_eq(&rolled, &ledger.rolled_up_balances()));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_reload() {
+        let dir = std::env::temp_dir().join(format!("beanweb-incremental-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("2024")).unwrap();
+        let lunch = |date: &str, amount: u32| format!("{} * \"Lunch\"\n  Expenses:Food  {} CNY\n  Assets:Bank\n", date, amount);
+        std::fs::write(dir.join("main.bean"), "include \"accounts.bean\"\ninclude \"2024/*.bean\"\n").unwrap();
+        std::fs::write(dir.join("accounts.bean"), "2024-01-01 open Assets:Bank CNY\n2024-01-01 open Expenses:Food CNY\n").unwrap();
+        std::fs::write(dir.join("2024/01.bean"), lunch("2024-01-05", 10)).unwrap();
+        std::fs::write(dir.join("2024/02.bean"), lunch("2024-02-05", 20)).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        config.data.incremental_reload = true;
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        assert_eq!(ledger.reparsed_files().len(), 4);
+
+        // Nothing changed: nothing parsed, same ledger
+        ledger.reload().await.unwrap();
+        assert!(ledger.reparsed_files().is_empty());
+        assert_eq!(ledger.transactions(10, 0).len(), 2);
+        assert_eq!(ledger.file_stats().len(), 4);
+
+        // Only the edited file and a new one matched by the glob are parsed
+        std::fs::write(dir.join("2024/02.bean"), lunch("2024-02-05", 25)).unwrap();
+        std::fs::write(dir.join("2024/03.bean"), lunch("2024-03-05", 30)).unwrap();
+        ledger.reload().await.unwrap();
+        let mut reparsed = ledger.reparsed_files();
+        reparsed.sort();
+        assert_eq!(reparsed, vec![dir.join("2024/02.bean"), dir.join("2024/03.bean")]);
+        assert_eq!(ledger.account_balances_as_of(None)["Expenses:Food"], Decimal::from(65));
+
+        // A removed file drops out
+        std::fs::remove_file(dir.join("2024/01.bean")).unwrap();
+        ledger.reload().await.unwrap();
+        assert!(ledger.reparsed_files().is_empty());
+        assert_eq!(ledger.transactions(10, 0).len(), 2);
+        assert_eq!(ledger.account_balances_as_of(None)["Expenses:Food"], Decimal::from(55));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_files() {
+        let dir = std::env::temp_dir().join(format!("beanweb-orphans-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (name, content) in [
+            ("main.bean", "include \"accounts.bean\"\n"),
+            ("accounts.bean", "2024-01-01 open Assets:Bank CNY\n2024-01-01 open Expenses:Food CNY\n"),
+            ("notes.bean", ""),
+            ("2024/01.bean", "2024-01-05 * \"Lunch\"\n  Expenses:Food  10 CNY\n  Assets:Bank  -10 CNY\n"),
+            ("2024/02.bean", "2024-02-05 * \"Lunch\"\n  Expenses:Food  20 CNY\n  Assets:Bank  -20 CNY\n"),
+            (".git/stash.bean", ""),
+        ] {
+            std::fs::create_dir_all(dir.join(name).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        let orphans = ledger.orphaned_files();
+        let paths: Vec<&str> = orphans.iter().map(|o| o.path.as_str()).collect();
+        assert_eq!(paths, vec!["2024/01.bean", "2024/02.bean", "notes.bean"]);
+        assert_eq!(orphans[0].glob.as_deref(), Some("2024/*.bean"));
+        assert_eq!(orphans[2].glob, None);
+
+        // Only current orphans can be included
+        assert!(ledger.include_orphan("accounts.bean").is_err());
+        assert!(ledger.include_orphan("../elsewhere.bean").is_err());
+
+        ledger.include_orphan("2024/*.bean").unwrap();
+        ledger.reload().await.unwrap();
+        assert_eq!(ledger.transactions(10, 0).len(), 2);
+        let paths: Vec<String> = ledger.orphaned_files().into_iter().map(|o| o.path).collect();
+        assert_eq!(paths, vec!["notes.bean"]);
+        assert!(std::fs::read_to_string(dir.join("main.bean")).unwrap().contains("include \"2024/*.bean\""));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_ledger_is_consistent() {
+        let text = beanweb_utils::synthetic::SyntheticLedger::new(3_000).end_year(2024).render();
+        let ledger = ledger_from_source(&text).await;
+        assert_eq!(ledger.all_transactions().len(), 3_000);
+        assert!(ledger.integrity_check().issues.is_empty(), "{:?}", ledger.integrity_check().issues.first());
+        let checks = ledger.check_balances();
+        assert!(!checks.is_empty() && checks.iter().all(|c| c.passed), "{:?}", checks.iter().find(|c| !c.passed));
+        assert!(ledger.price("USD", "CNY", NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()).is_some());
+        let negative = ledger.negative_balances();
+        assert!(negative.is_empty(), "{:?}", negative.iter().map(|n| (&n.account, n.balance)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_price_lookup() {
+        let ledger = ledger_from_source(r#"
+2024-01-02 price USD  7.10 CNY
+2024-02-01 price USD  7.20 CNY
+2024-02-01 price AAPL  180.00 USD
+"#).await;
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(ledger.price("USD", "CNY", date("2024-01-01")), None);
+        assert_eq!(ledger.price("USD", "CNY", date("2024-01-31")), Some(Decimal::new(710, 2)));
+        assert_eq!(ledger.price("USD", "CNY", date("2024-06-30")), Some(Decimal::new(720, 2)));
+        assert_eq!(ledger.price("CNY", "USD", date("2024-06-30")), Decimal::ONE.checked_div(Decimal::new(720, 2)));
+        // No chaining through USD
+        assert_eq!(ledger.price("AAPL", "CNY", date("2024-06-30")), None);
+        assert_eq!(ledger.prices().history("USD", "CNY").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_allocation_report() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Savings
+2024-01-01 open Assets:Cash
+2024-01-01 open Assets:Wallet
+2024-01-01 open Liabilities:Card
+2024-01-01 open Income:Salary
+2024-01-01 open Expenses:Food
+
+2024-01-05 * "Employer" "Salary"
+    Assets:Bank    600.00 CNY
+    Assets:Bank    400.00 CNY
+    Income:Salary    -1000.00 CNY
+
+2024-01-06 * "Market" "Groceries"
+    Liabilities:Card    -200.00 CNY
+    Expenses:Food    200.00 CNY
+
+2024-01-10 * "Transfer"
+    Assets:Savings    500.00 CNY
+    Assets:Bank    -500.00 CNY
+
+2024-01-20 * "Card payment"
+    Liabilities:Card    150.00 CNY
+    Assets:Bank    -150.00 CNY
+
+2024-01-25 * "Wallet top-up"
+    Assets:Cash    30.00 CNY
+    Assets:Bank
+
+2024-01-28 * "Exchange"
+    Assets:Wallet    10.00 USD @ 7.00 CNY
+    Assets:Bank    -70.00 CNY
+"#).await;
+
+        let report = ledger.allocation_report();
+        assert_eq!(report.net_income, Decimal::from(800));
+        let amount_of = |account: &str, currency: &str| {
+            report.entries.iter().find(|e| e.account == account && e.currency == currency).map(|e| e.amount)
+        };
+        // Each posting counts once, the elided one with its inferred amount
+        assert_eq!(amount_of("Assets:Bank", "CNY"), Some(Decimal::from(250)));
+        assert_eq!(amount_of("Assets:Cash", "CNY"), Some(Decimal::from(30)));
+        assert_eq!(amount_of("Assets:Savings", "CNY"), Some(Decimal::from(500)));
+        assert_eq!(amount_of("Liabilities:Card", "CNY"), Some(Decimal::from(-50)));
+        // Other currencies are listed on their own and stay out of the total
+        assert_eq!(amount_of("Assets:Wallet", "USD"), Some(Decimal::from(10)));
+        assert_eq!(report.total_allocated, Decimal::from(730));
+        assert_eq!(report.residual, Decimal::from(70));
+    }
+
+    #[tokio::test]
+    async fn test_load_failure_recorded_in_status() {
+        let parser = Arc::new(beanweb_parser::DefaultBeancountParser);
+        let mut ledger = Ledger::new(Config::default(), parser);
+        assert!(ledger.load(PathBuf::from("/nonexistent/main.bean")).await.is_err());
+
+        let status = ledger.load_status();
+        assert!(!status.loaded);
+        assert_eq!(status.attempts, 1);
+        assert!(status.duration_ms.is_none());
+        assert!(status.error.unwrap().contains("/nonexistent/main.bean"));
+    }
+
+    #[tokio::test]
+    async fn test_grouped_income_expense_report() {
+        let mut config = Config::default();
+        config.reports.groups.push(beanweb_config::ReportGroup {
+            name: "Essential".to_string(),
+            accounts: vec!["Expenses:Rent".to_string(), "Expenses:Food".to_string()],
+        });
+        let ledger = ledger_from_source_with_config(r#"
+2024-01-05 * "Landlord" "Rent"
+    Expenses:Rent    300.00 CNY
+    Assets:Bank    -300.00 CNY
+
+2024-01-06 * "Market" "Groceries"
+    Expenses:Food:Groceries    100.00 CNY
+    Assets:Bank    -100.00 CNY
+
+2024-01-07 * "Cinema" "Movie"
+    Expenses:Fun    100.00 CNY
+    Assets:Bank    -100.00 CNY
+"#, config).await;
+
+        let report = ledger.grouped_income_expense_report();
+        assert_eq!(report.expense_entries.len(), 2);
+        let essential = report.expense_entries.iter().find(|e| e.account == "Essential").unwrap();
+        assert_eq!(essential.amount, "400");
+        assert!((essential.percentage - 80.0).abs() < 0.001);
+        assert!(report.expense_entries.iter().any(|e| e.account == "Expenses:Fun"));
+        assert_eq!(report.total_expenses, "500");
+    }
+
+    #[test]
+    fn test_display_amount_signs() {
+        use sign::display_amount;
+        let natural = SignConvention::Natural;
+        let options = LedgerOptions::default();
+        let shown = |account: &str, amount: f64, convention| display_amount(options.account_type(account), amount, convention);
+        // Booked signs: income and liabilities are negative, assets and expenses positive
+        assert_eq!(shown("Income:Salary", -5000.0, natural), 5000.0);
+        assert_eq!(shown("Liabilities:CreditCard", -1200.0, natural), 1200.0);
+        assert_eq!(shown("Equity:Opening-Balances", -100.0, natural), 100.0);
+        assert_eq!(shown("Assets:Bank", -70.0, natural), -70.0);
+        assert_eq!(shown("Expenses:Food", 70.0, natural), 70.0);
+        // A refund books income positive, which is shown as negative income
+        assert_eq!(shown("Income:Salary", 300.0, natural), -300.0);
+        // Raw keeps every amount as booked
+        assert_eq!(shown("Income:Salary", -5000.0, SignConvention::Raw), -5000.0);
+        assert_eq!(shown("Liabilities:CreditCard", -1200.0, SignConvention::Raw), -1200.0);
+        // Only the root decides; similar prefixes don't count
+        assert_eq!(shown("IncomeTax:Due", -10.0, natural), -10.0);
+    }
+
+    #[tokio::test]
+    async fn test_anonymized_export_scale() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Fees
+
+2024-01-05 * "Split"
+  Expenses:Food  10.01 CNY
+  Expenses:Fees  10.01 CNY
+  Assets:Bank  -20.02 CNY
+"#).await;
+        let options = AnonymizeOptions { scale: Some(Decimal::new(5, 1)), seed: Some("seed".to_string()) };
+        let export = ledger.anonymized_export(&options);
+        let amounts: Vec<Decimal> = export.lines()
+            .filter(|line| line.starts_with("    "))
+            .map(|line| line.split_whitespace().nth(1).unwrap().parse().unwrap())
+            .collect();
+        // 5.005 rounds to 5.00 twice; the cent left over goes to the bank posting
+        assert_eq!(amounts, vec![Decimal::new(500, 2), Decimal::new(500, 2), Decimal::new(-1000, 2)]);
+        let reparsed = ledger_from_source(&export).await;
+        assert!(reparsed.integrity_check().issues.is_empty(), "{}", export);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_accounts() {
+        let dir = std::env::temp_dir().join(format!("beanweb-bootstrap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), "option \"title\" \"Test\"\n\n2024-01-01 open Assets:Cash CNY\n").unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = ledger_from_source_with_config("2024-01-01 open Assets:Cash CNY\n", config).await;
+
+        assert!(ledger.bootstrap_accounts("unknown", "CNY", "2024-01-01").is_err());
+        assert!(ledger.bootstrap_accounts("personal", "cny", "2024-01-01").is_err());
+
+        let outcome = ledger.bootstrap_accounts("personal", "CNY", "2024-01-01").unwrap();
+        assert_eq!(outcome.file, "accounts.bean");
+        assert_eq!(outcome.skipped, vec!["Assets:Cash".to_string()]);
+        assert!(outcome.include_added);
+        let accounts = std::fs::read_to_string(dir.join("accounts.bean")).unwrap();
+        assert!(accounts.contains(";; ===== Liabilities =====\n2024-01-01 open Liabilities:CreditCard CNY"));
+        assert!(!accounts.contains("Assets:Cash"));
+        let main = std::fs::read_to_string(dir.join("main.bean")).unwrap();
+        assert!(main.starts_with("option \"title\" \"Test\"\ninclude \"accounts.bean\"\n"));
+
+        // The include is only added once and the new ledger parses
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        let again = ledger.bootstrap_accounts("personal", "CNY", "2024-01-01").unwrap();
+        assert!(again.opened.is_empty());
+        assert!(!again.include_added);
+
+        // Appending to the file backs up the previous version
+        let freelancer = ledger.bootstrap_accounts("freelancer", "CNY", "2024-01-01").unwrap();
+        assert!(!freelancer.opened.is_empty());
+        assert_eq!(std::fs::read_to_string(dir.join("accounts.bean.bak")).unwrap(), accounts);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expense_category_report() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Food:Groceries
+2024-01-01 open Expenses:Transport
+
+2024-01-05 * "Dinner"
+  Expenses:Food:Dining  30.00 CNY
+  Assets:Bank
+
+2024-01-06 * "Market"
+  Expenses:Food:Groceries  50.00 CNY
+  Assets:Bank
+
+2024-01-07 * "Bus"
+  Expenses:Transport  20.00 CNY
+  Assets:Bank
+"#).await;
+
+        let report = ledger.expense_category_report();
+        let categories: Vec<(&str, f64, usize)> = report.breakdowns.iter()
+            .map(|b| (b.category.as_str(), b.amount.parse().unwrap(), b.count))
+            .collect();
+        assert_eq!(categories, vec![("Food", 80.0, 2), ("Transport", 20.0, 1)]);
+        assert!((report.breakdowns[0].percentage - 80.0).abs() < 1e-9);
+
+        let january = TimeContext::custom(
+            NaiveDate::from_ymd_opt(2024, 1, 6).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+        );
+        let report = ledger.expense_category_report_in(&january);
+        assert_eq!(report.breakdowns.len(), 2);
+        assert_eq!(ledger.income_expense_report_in(&january).total_expenses.parse::<f64>().unwrap(), 70.0);
+    }
+
+    #[tokio::test]
+    async fn test_currencies_in_use() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 commodity CNY
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Income:Salary
+
+2024-01-05 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary  -1000.00 CNY
+
+2024-02-06 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+
+2024-03-07 * "Typo"
+  Expenses:Food  12.00 CNH
+  Assets:Bank  -12.00 CNY
+"#).await;
+
+        let currencies = ledger.currencies_in_use();
+        let names: Vec<&str> = currencies.iter().map(|c| c.currency.as_str()).collect();
+        assert_eq!(names, vec!["CNY", "CNH"]);
+        let cny = &currencies[0];
+        assert_eq!((cny.transactions, cny.first_seen.as_str(), cny.last_seen.as_str()), (3, "2024-01-05", "2024-03-07"));
+        assert_eq!(cny.accounts, vec!["Assets:Bank", "Expenses:Food", "Income:Salary"]);
+        assert_eq!(cny.account_types.get("assets"), Some(&2));
+        assert!(!cny.is_suspicious());
+        assert!(currencies[1].is_suspicious());
+    }
+
+    #[tokio::test]
+    async fn test_account_suggestions() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Transport:Fuel
+2024-01-01 open Assets:Food-Card
+2024-02-01 close Assets:Food-Card
+"#).await;
+
+        let suggestions = ledger.account_suggestions();
+        assert_eq!(suggestions.len(), 5);
+        let names = |q: &str, limit: usize| -> Vec<String> {
+            suggestions.suggest(q, limit).iter().map(|s| s.name.clone()).collect()
+        };
+        assert_eq!(names("FOOD", 10), vec!["Expenses:Food", "Expenses:Food:Dining", "Assets:Food-Card"]);
+        assert_eq!(names("exp", 2), vec!["Expenses:Food", "Expenses:Food:Dining"]);
+        assert_eq!(names("uel", 10), vec!["Expenses:Transport:Fuel"]);
+        assert!(names("  ", 10).is_empty());
+        // Memoized: the same query returns the same shared result
+        assert!(Arc::ptr_eq(&suggestions.suggest("food", 10), &suggestions.suggest("Food ", 10)));
+        // For a dated transaction, only accounts open that day
+        let on = |date| -> Vec<String> {
+            suggestions.suggest_on("food", 10, Some(date)).iter().map(|s| s.name.clone()).collect()
+        };
+        assert_eq!(on("2024-01-15"), vec!["Expenses:Food", "Expenses:Food:Dining", "Assets:Food-Card"]);
+        assert_eq!(on("2024-02-01"), vec!["Expenses:Food", "Expenses:Food:Dining"]);
+        assert!(on("2023-12-31").is_empty());
+
+        let inactive = ledger.inactive_accounts_on("2024-02-01", &["Assets:Food-Card".to_string(), "Assets:Bank".to_string(), "Assets:Unknown".to_string()]);
+        assert_eq!(inactive, vec!["Assets:Food-Card 已于 2024-02-01 关闭"]);
+        assert_eq!(ledger.inactive_accounts_on("2023-12-31", &["Assets:Bank".to_string()]), vec!["Assets:Bank 于 2024-01-01 才开立"]);
+    }
+
+    #[tokio::test]
+    async fn test_link_group() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Receivable
+2024-01-01 open Income:Consulting
+
+2024-03-01 * "Client" "Invoice 42" ^inv-42
+  Assets:Receivable  500.00 CNY
+  Income:Consulting
+
+2024-03-20 * "Client" "Partial payment" ^inv-42
+  Assets:Bank  300.00 CNY
+  Assets:Receivable  -300.00 CNY
+
+2024-03-21 * "Other"
+  Assets:Bank  1.00 CNY
+  Income:Consulting
+"#).await;
+
+        let group = ledger.link_group("^inv-42");
+        assert_eq!(group.transactions.len(), 2);
+        assert_eq!((group.first_date.as_deref(), group.last_date.as_deref()), (Some("2024-03-01"), Some("2024-03-20")));
+        let outstanding: Vec<(&str, &str)> = group.outstanding.iter().map(|b| (b.account.as_str(), b.amount.as_str())).collect();
+        assert_eq!(outstanding, vec![("Assets:Receivable", "200.00")]);
+        assert!(!group.is_settled());
+        // The elided income posting is inferred
+        assert!(group.balances.iter().any(|b| b.account == "Income:Consulting" && b.amount == "-500.00"));
+        assert!(ledger.link_group("missing").transactions.is_empty());
+
+        let linked = ledger.transactions_by_link("inv-42");
+        let narrations: Vec<&str> = linked.iter().map(|t| t.narration.as_str()).collect();
+        assert_eq!(narrations, vec!["Invoice 42", "Partial payment"]);
+    }
+
+    #[tokio::test]
+    async fn test_holdings_by_asset_class() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 commodity VTI
+  name: "Vanguard Total Stock Market"
+  asset-class: "stock"
+2024-01-01 commodity BND
+  name: "Vanguard Total Bond"
+  asset-class: bond
+2024-01-01 commodity GOLD
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Broker
+2024-01-01 open Assets:Vault
+2024-01-01 open Income:Salary
+
+2024-01-31 * "Salary"
+  Assets:Bank  5000.00 CNY
+  Income:Salary
+
+2024-02-01 * "Buy stocks"
+  Assets:Broker  10 VTI @ 100.00 CNY
+  Assets:Bank  -1000.00 CNY
+
+2024-02-02 * "Buy bonds"
+  Assets:Broker  20 BND @ 25.00 CNY
+  Assets:Bank  -500.00 CNY
+
+2024-02-03 * "Gold"
+  Assets:Vault  1 GOLD @ 400.00 CNY
+  Assets:Bank  -400.00 CNY
+
+2024-03-01 price VTI 150.00 CNY
+2024-03-01 price BND 24.00 CNY
+"#).await;
+
+        let vti = ledger.commodity("VTI").unwrap();
+        assert_eq!((vti.label(), vti.asset_class.as_deref()), ("Vanguard Total Stock Market", Some("stock")));
+        assert_eq!(ledger.commodity("BND").unwrap().asset_class.as_deref(), Some("bond"));
+        assert_eq!(ledger.commodity("GOLD").unwrap().label(), "GOLD");
+
+        let report = ledger.holdings_by_asset_class();
+        let classes: Vec<&str> = report.groups.iter().map(|g| g.asset_class.as_str()).collect();
+        assert_eq!(classes, vec![holdings::UNCLASSIFIED, "stock", "bond"]);
+        let stock = &report.groups[1];
+        assert_eq!(stock.holdings[0].label, "Vanguard Total Stock Market");
+        assert!((stock.value - 1500.0).abs() < 0.001);
+        assert!((stock.percentage - 1500.0 / 5080.0 * 100.0).abs() < 0.001);
+        // Bank cash is valued as is; GOLD has no price and adds nothing
+        let unclassified = &report.groups[0];
+        assert!(unclassified.holdings.iter().any(|h| h.commodity == "GOLD" && h.value.is_none()));
+        assert!((report.total - (3100.0 + 1500.0 + 480.0)).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_commodity_totals() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 commodity CNY
+2024-01-01 commodity VTI
+  name: "Vanguard Total Stock"
+  asset-class: "stock"
+2024-01-01 commodity EUR
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Broker
+2024-01-01 open Liabilities:Card
+2024-01-01 open Income:Salary
+2024-01-01 open Expenses:Travel
+
+2024-01-05 * "Salary"
+  Assets:Bank  5000.00 CNY
+  Income:Salary
+
+2024-01-10 * "Buy"
+  Assets:Broker  10 VTI @ 200.00 CNY
+  Assets:Bank  -2000.00 CNY
+
+2024-01-12 * "Trip"
+  Expenses:Travel  50.00 USD
+  Liabilities:Card  -50.00 USD
+
+2024-01-31 price VTI 210.00 CNY
+"#).await;
+
+        let totals = ledger.commodity_totals();
+        let names: Vec<&str> = totals.iter().map(|t| t.commodity.as_str()).collect();
+        assert_eq!(names, vec!["CNY", "VTI", "EUR", "USD"]);
+        assert_eq!(totals[0].units, 3000.0);
+        assert_eq!(totals[0].accounts, vec![("Assets:Bank".to_string(), 3000.0)]);
+        let vti = &totals[1];
+        assert_eq!(vti.label, "Vanguard Total Stock");
+        assert_eq!(vti.asset_class.as_deref(), Some("stock"));
+        assert_eq!(vti.value, Some(2100.0));
+        // Declared but unused, and used but undeclared
+        assert!(totals[2].declared && totals[2].accounts.is_empty());
+        assert!(!totals[3].declared);
+        assert_eq!(totals[3].units, -50.0);
+        assert_eq!(totals[3].price, None);
+    }
+
+    #[tokio::test]
+    async fn test_balance_import_preview() {
+        use balance_import::{BalanceImportOptions, BalanceRowStatus};
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Income:Salary
+2024-03-01 open Assets:Savings
+
+2024-01-15 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary
+
+2024-02-15 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary
+
+2024-02-01 balance Assets:Bank 1000.00 CNY
+"#).await;
+
+        let csv = "date,account,amount,currency\n\
+            2024-01-31,Assets:Bank,\"1,000\",CNY\n\
+            2024-02-29,Assets:Bank,1500,\n\
+            2024-02-29,Assets:Savings,0,CNY\n\
+            2024-13-01,Assets:Bank,1,CNY\n";
+        let preview = ledger.preview_balance_import(csv, &BalanceImportOptions { end_of_day: true, ..Default::default() });
+        let statuses: Vec<(usize, &str, BalanceRowStatus)> = preview.rows.iter().map(|r| (r.line, r.date.as_str(), r.status)).collect();
+        assert_eq!(statuses, vec![
+            (2, "2024-02-01", BalanceRowStatus::Duplicate),
+            (3, "2024-03-01", BalanceRowStatus::Fails),
+            (4, "2024-03-01", BalanceRowStatus::Ok),
+            (5, "2024-13-01", BalanceRowStatus::Invalid),
+        ]);
+        assert_eq!(preview.rows[1].computed, Some(2000.0));
+        assert_eq!(preview.rows[1].amount, "1500.00");
+        let failing: Vec<&str> = preview.months.iter().filter(|m| m.failing > 0).map(|m| m.month.as_str()).collect();
+        assert_eq!(failing, vec!["2024-03"]);
+
+        // Without end_of_day the savings account isn't open yet
+        let preview = ledger.preview_balance_import("2024-02-29,Assets:Savings,0,CNY", &BalanceImportOptions::default());
+        assert_eq!(preview.rows[0].status, BalanceRowStatus::Invalid);
+        assert!(BalanceImportOptions::default().skip_failing);
+
+        // A pad fills up to the next assertion of its account: rows between
+        // the pad and that assertion are left out, later ones are checked
+        let padded = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Equity:Opening
+2024-01-10 pad Assets:Bank Equity:Opening
+2024-03-01 balance Assets:Bank 500.00 CNY
+"#).await;
+        let preview = padded.preview_balance_import("2024-02-01,Assets:Bank,123,CNY
+2024-04-01,Assets:Bank,500,CNY
+", &BalanceImportOptions::default());
+        let statuses: Vec<BalanceRowStatus> = preview.rows.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![BalanceRowStatus::Padded, BalanceRowStatus::Ok]);
+        assert!(!preview.rows[0].is_importable());
+    }
+
+    #[tokio::test]
+    async fn test_opening_balances() {
+        use opening::OpeningBalance;
+        let dir = std::env::temp_dir().join(format!("beanweb-opening-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), "option \"title\" \"Test\"\n").unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        config.currency.default_currency = "CNY".to_string();
+        let mut ledger = ledger_from_source_with_config("2024-03-01 open Assets:Broker\n", config).await;
+        let balance = |account: &str, amount: &str, currency: Option<&str>| OpeningBalance {
+            account: account.to_string(),
+            amount: amount.to_string(),
+            currency: currency.map(|c| c.to_string()),
+        };
+
+        let balances = vec![
+            balance("Assets:Bank", "1,000.5", None),
+            balance("Assets:Bank", "200", Some("USD")),
+            balance("Liabilities:CreditCard", "-300", None),
+            balance("Assets:Wallet", "0", None),
+        ];
+        let plan = ledger.plan_opening_balances("2024-01-01", &balances).unwrap();
+        let opens: Vec<&str> = plan.opens.iter().map(|(a, _)| a.as_str()).collect();
+        assert_eq!(opens, vec!["Assets:Bank", "Assets:Wallet", "Equity:Opening-Balances", "Liabilities:CreditCard"]);
+        assert!(plan.source.contains("2024-01-01 open Assets:Bank CNY,USD\n"));
+        assert!(plan.source.contains("2024-01-01 open Equity:Opening-Balances CNY,USD\n"));
+        assert_eq!(plan.equity, vec![("-700.50".to_string(), "CNY".to_string()), ("-200.00".to_string(), "USD".to_string())]);
+        assert_eq!(plan.postings.len(), 3);
+        // Summed exactly, beyond what an f64 holds
+        let exact = ledger.plan_opening_balances("2024-01-01", &[
+            balance("Assets:Bank", "12345678901234567.89", None),
+            balance("Assets:Wallet", "0.1", None),
+            balance("Liabilities:CreditCard", "-0.2", None),
+        ]).unwrap();
+        assert_eq!(exact.equity, vec![("-12345678901234567.79".to_string(), "CNY".to_string())]);
+
+        // Rejected: income accounts, duplicates, accounts opening after the start date
+        assert!(ledger.plan_opening_balances("2024-01-01", &[balance("Income:Salary", "1", None)]).is_err());
+        assert!(ledger.plan_opening_balances("2024-01-01", &[balance("Assets:Bank", "1", None), balance("Assets:Bank", "2", Some("CNY"))]).is_err());
+        assert!(ledger.plan_opening_balances("2024-01-01", &[balance("Assets:Broker", "1", None)]).is_err());
+
+        // The written file balances and parses
+        let outcome = ledger.write_opening_balances("2024-01-01", &balances, "opening.bean").unwrap();
+        assert!(outcome.include_added);
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        assert!(ledger.accounts().iter().any(|a| a.name == "Equity:Opening-Balances"));
+        let tx = ledger.transactions(10, 0).into_iter()
+            .find(|t| t.postings.iter().any(|p| p.account == "Equity:Opening-Balances"))
+            .unwrap();
+        assert_eq!(tx.postings.len(), 5);
+        assert!(crate::integrity::residuals(&tx).values().all(|r| r.is_zero()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_decimal_aggregation() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Income:Salary
+
+2024-01-02 * "Salary"
+  Income:Salary  -0.30 CNY
+  Assets:Bank
+
+2024-01-03 * "Snack"
+  Expenses:Food  0.10 CNY
+  Assets:Bank
+
+2024-01-04 * "Snack"
+  Expenses:Food  0.20 CNY
+  Assets:Bank
+
+2024-01-05 balance Assets:Bank 0.00 CNY
+"#).await;
+
+        // 0.1 + 0.2 sums to 0.3 exactly, so the balance assertion holds
+        assert_eq!(ledger.account_balances_as_of(None)["Assets:Bank"], Decimal::ZERO);
+        assert_eq!(ledger.account_balances_as_of(None)["Expenses:Food"], "0.30".parse::<Decimal>().unwrap());
+        let report = ledger.income_expense_report_in(&TimeContext::new(TimeRange::All));
+        assert_eq!(report.total_expenses, "0.3");
+        assert_eq!(report.net_income, "0");
+        assert!(ledger.integrity_check().issues.is_empty());
+
+        let tx = ledger.transactions(10, 0).into_iter().find(|t| t.date == "2024-01-03").unwrap();
+        assert_eq!(tx.postings[0].units, Some(Amount::new("0.10".parse().unwrap(), "CNY")));
+        assert_eq!(tx.postings[1].units, None);
+    }
+
+    #[tokio::test]
+    async fn test_account_filter_query() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Food:Groceries
+2024-01-01 open Expenses:Transport:Taxi
+
+2024-01-02 * "Lunch"
+  Expenses:Food:Dining  30.00 CNY
+  Assets:Bank
+
+2024-01-03 * "Market"
+  Expenses:Food:Groceries  80.00 CNY
+  Assets:Bank
+
+2024-01-04 * "Ride"
+  Expenses:Transport:Taxi  25.00 CNY
+  Assets:Bank
+"#).await;
+
+        let food = AccountFilter::parse("Expenses:Food:*").unwrap();
+        assert_eq!(ledger.matching_accounts(&food), vec!["Expenses:Food:Dining", "Expenses:Food:Groceries"]);
+        assert_eq!(ledger.transaction_query(10, 0, std::slice::from_ref(&food), None).len(), 2);
+
+        let rides = AccountFilter::parse("~Taxi|Uber").unwrap();
+        let found = ledger.transaction_query(10, 0, &[rides], None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].date, "2024-01-04");
+
+        // Several filters must all match
+        let bank = AccountFilter::parse("Assets").unwrap();
+        assert_eq!(ledger.transaction_query(10, 0, &[food, bank], None).len(), 2);
+        assert_eq!(ledger.transaction_query(10, 0, &[], None).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_index() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Travel
+
+2024-01-05 * "Cafe" "Breakfast" #trip
+  Expenses:Food  10.00 CNY
+  Assets:Bank
+
+2024-03-01 * "Airline" "Flight" #trip ^booking
+  Expenses:Travel  800.00 CNY
+  Assets:Bank
+
+2024-02-01 * "Market" "Groceries"
+  Expenses:Food  50.00 CNY
+  Assets:Bank
+
+2024-02-01 * "Bakery" "Bread"
+  Expenses:Food  5.00 CNY
+  Assets:Bank
+"#).await;
+
+        // Newest first, file order within a day, only the page returned
+        let page: Vec<String> = ledger.transaction_query(2, 1, &[], None).iter().map(|t| t.payee.clone()).collect();
+        assert_eq!(page, vec!["Market", "Bakery"]);
+        let food = AccountFilter::parse("Expenses:Food").unwrap();
+        assert_eq!(ledger.transaction_query(10, 0, &[food], None).len(), 3);
+
+        let by_account: Vec<String> = ledger.transactions_by_account("Expenses:Food").iter().map(|t| t.payee.clone()).collect();
+        assert_eq!(by_account, vec!["Cafe", "Market", "Bakery"]);
+        assert_eq!(ledger.transaction_count_by_account("Assets:Bank"), 4);
+        assert!(ledger.transactions_by_account("Expenses").is_empty());
+
+        let trip: Vec<String> = ledger.transactions_by_tag("trip").iter().map(|t| t.payee.clone()).collect();
+        assert_eq!(trip, vec!["Cafe", "Airline"]);
+
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let february: Vec<String> = ledger.transactions_by_date_range(date("2024-02-01"), date("2024-02-29"))
+            .iter().map(|t| t.payee.clone()).collect();
+        assert_eq!(february, vec!["Market", "Bakery"]);
+        assert!(ledger.transactions_by_date_range(date("2024-04-01"), date("2024-03-01")).is_empty());
+
+        // Search covers payee, narration, tags, links and accounts, case-insensitively
+        assert_eq!(ledger.search_transactions("BREAD").len(), 1);
+        assert_eq!(ledger.search_transactions("booking").len(), 1);
+        assert_eq!(ledger.search_transactions("travel").len(), 1);
+
+        let id = ledger.transactions_by_tag("trip")[1].id.clone();
+        assert_eq!(ledger.transaction(&id).unwrap().payee, "Airline");
+        assert!(ledger.transaction("txn-missing").is_none());
+
+        // Views filter in place before anything is cloned
+        let mut matched = ledger.transactions_matching(&[], "bank", |t| t.date.as_str() >= "2024-02-01");
+        let payees: Vec<&str> = matched.iter().map(|t| t.payee.as_str()).collect();
+        assert_eq!(payees, vec!["Airline", "Market", "Bakery"]);
+        matched.retain(|t| t.tags.is_empty());
+        assert_eq!(matched.to_vec().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_flow() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Cash
+2024-01-01 open Liabilities:Card
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Home
+2024-01-01 open Expenses:Gifts
+
+2024-01-05 * "Market"
+  Expenses:Food  30.00 CNY
+  Expenses:Home  20.00 CNY
+  Expenses:Gifts  0.00 CNY
+  Assets:Cash  -10.00 CNY
+  Liabilities:Card
+"#).await;
+        let flow = ledger.transactions(1, 0)[0].flow();
+        assert_eq!(flow.from, vec!["Assets:Cash", "Liabilities:Card"]);
+        assert_eq!(flow.to, vec!["Expenses:Food", "Expenses:Home"]);
+    }
+
+    #[tokio::test]
+    async fn test_negative_balances() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Assets:Wallet CNY
+2024-01-01 open Liabilities:Card CNY
+2024-01-01 open Expenses:Food CNY
+2024-01-01 open Income:Salary CNY
+
+2024-01-02 * "Employer" "Salary"
+  Assets:Bank  100.00 CNY
+  Income:Salary
+
+2024-01-03 * "Cafe" "Coffee"
+  Expenses:Food  150.00 CNY
+  Assets:Bank
+
+2024-01-04 * "Employer" "Bonus"
+  Assets:Bank  100.00 CNY
+  Income:Salary
+
+2024-01-05 * "Market" "Groceries"
+  Expenses:Food  80.00 CNY
+  Assets:Bank
+
+2024-01-06 * "Bakery" "Bread"
+  Expenses:Food  90.00 CNY
+  Assets:Bank
+
+2024-01-07 * "Noodles" "Lunch"
+  Expenses:Food  30.00 CNY
+  Liabilities:Card
+"#).await;
+
+        // The card is a liability; only the bank is flagged
+        let negative = ledger.negative_balances();
+        assert_eq!(negative.len(), 1);
+        let bank = &negative[0];
+        assert_eq!((bank.account.as_str(), bank.currency.as_str(), bank.balance), ("Assets:Bank", "CNY", -120.0));
+        // It went negative again on the 5th; the earlier dip was paid back
+        assert_eq!(bank.since.as_deref(), Some("2024-01-05"));
+        let causes: Vec<&str> = bank.causes.iter().map(|c| c.payee.as_str()).collect();
+        assert_eq!(causes, vec!["Bakery", "Market"]);
+        assert_eq!(bank.causes[0].amount, -90.0);
+    }
+
+    #[tokio::test]
+    async fn test_transactions_after() {
+        let dir = std::env::temp_dir().join(format!("beanweb-cursor-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let txn = |date: &str, payee: &str| format!("{} * \"{}\"\n  Expenses:Food  10 CNY\n  Assets:Bank\n\n", date, payee);
+        let header = "2024-01-01 open Assets:Bank CNY\n2024-01-01 open Expenses:Food CNY\n\n";
+        let body: String = [("2024-01-01", "A"), ("2024-01-02", "B"), ("2024-01-03", "C"), ("2024-01-04", "D")]
+            .iter().map(|(date, payee)| txn(date, payee)).collect();
+        std::fs::write(dir.join("main.bean"), format!("{}{}", header, body)).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        let payees = |page: &TransactionPage| page.transactions.iter().map(|t| t.payee.clone()).collect::<Vec<_>>();
+        let first = ledger.transactions_after(None, 2, &[], "", |_| true).unwrap();
+        assert_eq!(payees(&first), vec!["D", "C"]);
+        let cursor = first.next.clone().unwrap();
+
+        // A transaction added at the top shifts every line, the cursor still holds
+        std::fs::write(dir.join("main.bean"), format!("{}{}{}", header, txn("2024-01-05", "E"), body)).unwrap();
+        ledger.reload().await.unwrap();
+        let second = ledger.transactions_after(Some(&cursor), 2, &[], "", |_| true).unwrap();
+        assert_eq!(payees(&second), vec!["B", "A"]);
+        assert!(second.next.is_none());
+
+        // Filters apply before the limit
+        let page = ledger.transactions_after(None, 1, &[], "", |t| t.payee != "E").unwrap();
+        assert_eq!(payees(&page), vec!["D"]);
+        assert!(matches!(
+            ledger.transactions_after(Some("txn-missing:1:0"), 2, &[], "", |_| true),
+            Err(CoreError::TransactionNotFound { .. })
+        ));
+
+        // Of two identical transactions the cursor keeps the one it was on
+        let twice = txn("2024-01-06", "F").repeat(2);
+        std::fs::write(dir.join("main.bean"), format!("{}{}{}", header, twice, body)).unwrap();
+        ledger.reload().await.unwrap();
+        let first = ledger.transactions_after(None, 2, &[], "", |_| true).unwrap();
+        assert_eq!(payees(&first), vec!["F", "F"]);
+        let cursor = first.next.clone().unwrap();
+        std::fs::write(dir.join("main.bean"), format!("{}; moved\n{}{}", header, twice, body)).unwrap();
+        ledger.reload().await.unwrap();
+        let second = ledger.transactions_after(Some(&cursor), 1, &[], "", |_| true).unwrap();
+        assert_eq!(payees(&second), vec!["D"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_suspense_postings() {
+        let dir = std::env::temp_dir().join(format!("beanweb-suspense-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), r#"2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Food CNY
+2024-01-01 open Expenses:Uncategorized CNY
+2024-01-01 open Equity:TODO CNY
+
+2024-01-05 * "Shop" "Card payment"
+  Expenses:Uncategorized  30 CNY ; imported
+  Assets:Bank
+
+2024-01-06 * "Cafe"
+  Expenses:Food  5 CNY
+  Assets:Bank
+
+2024-01-07 * "Transfer"
+  Assets:Bank  100 CNY
+  Equity:TODO
+"#).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        // Newest first, elided amounts inferred
+        let postings = ledger.suspense_postings();
+        let summary: Vec<(&str, &str, f64)> = postings.iter().map(|p| (p.payee.as_str(), p.account.as_str(), p.amount)).collect();
+        assert_eq!(summary, vec![("Transfer", "Equity:TODO", -100.0), ("Shop", "Expenses:Uncategorized", 30.0)]);
+        assert_eq!(ledger.suspense_count(), 2);
+
+        let shop = postings[1].transaction_id.clone();
+        assert!(ledger.recategorize_posting(&shop, "Expenses:Uncategorized", "Equity:TODO").await.is_err());
+        assert!(ledger.recategorize_posting(&shop, "Expenses:Uncategorized", "Expenses:Missing").await.is_err());
+        assert!(ledger.recategorize_posting(&shop, "Assets:Bank", "Expenses:Food").await.is_err());
+
+        ledger.recategorize_posting(&shop, "Expenses:Uncategorized", "Expenses:Food").await.unwrap();
+        let content = std::fs::read_to_string(dir.join("main.bean")).unwrap();
+        assert!(content.contains("  Expenses:Food  30 CNY ; imported\n"));
+        ledger.reload().await.unwrap();
+        assert_eq!(ledger.suspense_count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_slow_queries() {
+        let source = r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Food CNY
+
+2024-01-05 * "Cafe" "Coffee"
+  Expenses:Food  5 CNY
+  Assets:Bank
+"#;
+        // Nothing is recorded below the threshold
+        let ledger = ledger_from_source(source).await;
+        ledger.search_transactions("cafe");
+        assert!(ledger.slow_queries().is_empty());
+
+        let mut config = Config::default();
+        config.logging.slow_query_ms = 0;
+        let ledger = ledger_from_source_with_config(source, config).await;
+        ledger.search_transactions("cafe");
+        ledger.income_expense_report();
+        ledger.record_timing("GET /reports", "range=all", std::time::Duration::from_secs(60), 2048);
+
+        let slow = ledger.slow_queries();
+        assert_eq!(slow[0].operation, "GET /reports");
+        assert_eq!(slow[0].results, 2048);
+        assert!(slow.windows(2).all(|w| w[0].millis >= w[1].millis));
+        let search = slow.iter().find(|t| t.operation == "search_transactions").unwrap();
+        assert_eq!((search.params.as_str(), search.results), ("query=\"cafe\"", 1));
+        let report = slow.iter().find(|t| t.operation == "income_expense_report").unwrap();
+        assert_eq!(report.results, 1);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_import() {
+        use transaction_import::{TransactionImportOptions, TransactionRowStatus};
+        let dir = std::env::temp_dir().join(format!("beanweb-txn-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), r#"2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Uncategorized
+
+2024-01-03 * "Cafe"
+  external_id: "A1"
+  Expenses:Uncategorized  5 CNY
+  Assets:Bank
+"#).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        let csv = "date,amount,payee,narration,external_id\n\
+            2024-01-03,-5,Cafe,,A1\n\
+            2024-01-04,\"-1,200\",Rent,January flat,A2\n\
+            2024-01-04,-1200,Rent,,A2\n\
+            2024-01-05,-3,Kiosk,,\n\
+            2023-12-31,-3,Kiosk,,A3\n";
+        let options = TransactionImportOptions { account: "Assets:Bank".to_string(), ..Default::default() };
+        let preview = ledger.preview_transaction_import(csv, &options).unwrap();
+        let statuses: Vec<(usize, TransactionRowStatus)> = preview.rows.iter().map(|r| (r.line, r.status)).collect();
+        assert_eq!(statuses, vec![
+            (2, TransactionRowStatus::Duplicate),
+            (3, TransactionRowStatus::Ok),
+            (4, TransactionRowStatus::Duplicate),
+            (5, TransactionRowStatus::Ok),
+            (6, TransactionRowStatus::Invalid),
+        ]);
+        assert!(ledger.preview_transaction_import(csv, &TransactionImportOptions { account: "Assets:Cash".to_string(), ..Default::default() }).is_err());
+
+        let outcome = ledger.import_transactions(csv, "imports.bean", &options).unwrap();
+        assert_eq!((outcome.written, outcome.skipped, outcome.include_added), (2, 3, true));
+        ledger.reload().await.unwrap();
+        let rent = ledger.search_transactions("rent");
+        assert_eq!(rent.len(), 1);
+        assert_eq!(rent[0].narration, "January flat");
+        assert_eq!(rent[0].metadata["external_id"], "A2");
+
+        // Importing the same export again adds nothing; the row without an id
+        // is recognized by its date and amount
+        let preview = ledger.preview_transaction_import(csv, &options).unwrap();
+        assert_eq!(preview.count(TransactionRowStatus::Ok), 0);
+        assert_eq!(preview.rows[1].message.as_deref(), Some("账本中已有该交易"));
+        assert_eq!(preview.rows[3].message.as_deref(), Some("账本中已有当天相同金额的交易"));
+
+        // Another bank's layout, mapped by header; only the picked row is
+        // written, on its own counter account
+        let csv = "\u{feff}Buchungstag\tBetrag\tEmpfänger\n\
+            06.01.2024\t7.50\tBakery\n\
+            07.01.2024\t12\tBookshop\n";
+        let columns: transaction_import::CsvColumns = serde_json::from_str(
+            r#"{"date": "Buchungstag", "amount": "Betrag", "payee": "Empfänger", "date_format": "%d.%m.%Y", "negate": true}"#
+        ).unwrap();
+        let options = TransactionImportOptions {
+            account: "Assets:Bank".to_string(),
+            counter_accounts: HashMap::from([(2, "Expenses:Missing".to_string())]),
+            columns: Some(columns.clone()),
+            ..Default::default()
+        };
+        let preview = ledger.preview_transaction_import(csv, &options).unwrap();
+        assert_eq!((preview.rows[0].status, preview.rows[0].message.as_deref()), (TransactionRowStatus::Invalid, Some("未知账户：Expenses:Missing")));
+        assert_eq!((preview.rows[1].date.as_str(), preview.rows[1].amount.as_str(), preview.rows[1].payee.as_str()), ("2024-01-07", "-12.00", "Bookshop"));
+        let options = TransactionImportOptions { counter_accounts: HashMap::new(), lines: Some(vec![3]), ..options };
+        assert_eq!(ledger.import_transactions(csv, "imports.bean", &options).unwrap().written, 1);
+        assert!(std::fs::read_to_string(dir.join("imports.bean")).unwrap().contains("2024-01-07 * \"Bookshop\" \"\"\n  Assets:Bank  -12.00 CNY\n  Expenses:Uncategorized\n"));
+        let unknown = transaction_import::CsvColumns { date: transaction_import::CsvColumn::Header("Datum".to_string()), ..columns };
+        assert!(ledger.preview_transaction_import(csv, &TransactionImportOptions { columns: Some(unknown), ..options }).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_report_cache() {
+        let dir = std::env::temp_dir().join(format!("beanweb-report-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = "2024-01-01 open Assets:Bank CNY\n2024-01-01 open Expenses:Food CNY\n\n\
+            2024-01-05 * \"Cafe\"\n  Expenses:Food  5 CNY\n  Assets:Bank\n";
+        std::fs::write(dir.join("main.bean"), source).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = Ledger::new(config, Arc::new(beanweb_parser::DefaultBeancountParser));
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        // Same report, same time context: computed once
+        assert_eq!(ledger.income_expense_report().total_expenses, "5");
+        assert_eq!(ledger.income_expense_report().total_expenses, "5");
+        ledger.balance_report();
+        assert_eq!(ledger.report_cache.lock().unwrap().len(), 2);
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert_eq!(ledger.income_expense_report().total_expenses, "0");
+        assert_eq!(ledger.report_cache.lock().unwrap().len(), 3);
+
+        // A reload drops every cached report
+        ledger.set_time_range(TimeRange::All);
+        std::fs::write(dir.join("main.bean"), format!("{}\n2024-01-06 * \"Cafe\"\n  Expenses:Food  7 CNY\n  Assets:Bank\n", source)).unwrap();
+        ledger.reload().await.unwrap();
+        assert_eq!(ledger.report_cache.lock().unwrap().len(), 0);
+        assert_eq!(ledger.income_expense_report().total_expenses, "12");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_all_tags() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Travel
+2024-01-01 open Income:Refunds
+
+2024-03-01 * "Airline" "Flight" #trip #work
+  Expenses:Travel  800.00 CNY
+  Assets:Bank
+
+2024-03-02 * "Hotel" #trip
+  Expenses:Travel  120.00 EUR
+  Assets:Bank  -120.00 EUR
+
+2024-03-09 * "Airline" "Partial refund" #trip
+  Assets:Bank  100.00 CNY
+  Income:Refunds
+
+2999-01-01 * "Hotel" "Next trip" #trip #future
+  Expenses:Travel  50.00 CNY
+  Assets:Bank
+"#).await;
+
+        let tags = ledger.all_tags();
+        let names: Vec<(&str, usize)> = tags.iter().map(|t| (t.tag.as_str(), t.count)).collect();
+        assert_eq!(names, vec![("trip", 3), ("work", 1)]);
+        let amount = |a: &amount::Amount| format!("{} {}", a.number, a.currency);
+        assert_eq!(tags[0].expenses.iter().map(amount).collect::<Vec<_>>(), vec!["800.00 CNY", "120.00 EUR"]);
+        assert_eq!(tags[0].income.iter().map(amount).collect::<Vec<_>>(), vec!["100.00 CNY"]);
+    }
+
+    #[tokio::test]
+    async fn test_structured_amounts() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Broker
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:USD
+
+2024-01-02 * "Buy"
+  Assets:Broker  10 AAPL {150.00 USD}
+  Assets:USD  -1500.00 USD
+
+2024-01-03 * "Exchange"
+  Assets:USD  100.00 USD @ 7.10 CNY
+  Assets:Bank  -710.00 CNY
+
+2024-01-04 * "Exchange"
+  Assets:USD  50.00 USD @@ 360.00 CNY
+  Assets:Bank  -360.00 CNY
+"#).await;
+        let mut txs = ledger.transactions(10, 0);
+        txs.sort_by(|a, b| a.date.cmp(&b.date));
+
+        let buy = &txs[0].postings[0];
+        assert_eq!(buy.units, Some(Amount::new(Decimal::from(10), "AAPL")));
+        assert_eq!(buy.cost_spec.as_ref().map(|c| c.per_unit.to_string()).as_deref(), Some("150.00 USD"));
+        assert!(buy.price_spec.is_none());
+
+        let exchange = &txs[1].postings[0];
+        let price = exchange.price_spec.as_ref().unwrap();
+        assert!(!price.total);
+        assert_eq!(price.value_of("100.00".parse().unwrap()), "710.0000".parse::<Decimal>().unwrap());
+        assert_eq!(exchange.price_info(), Some((7.1, "CNY".to_string())));
+
+        // "@@" totals are spread over the units
+        let total = &txs[2].postings[0];
+        assert!(total.price_spec.as_ref().unwrap().total);
+        assert_eq!(total.price_info(), Some((7.2, "CNY".to_string())));
+        assert!(ledger.integrity_check().issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_category_trends() {
+        let this_month = Utc::now().date_naive().with_day(1).unwrap();
+        let last_month = this_month.checked_sub_months(chrono::Months::new(1)).unwrap();
+        let ledger = ledger_from_source(&format!(r#"
+2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Food:Dining
+2020-01-01 open Expenses:Food:Groceries
+2020-01-01 open Income:Salary
+
+{last} * "Lunch"
+  Expenses:Food:Dining  30.00 CNY
+  Assets:Bank
+
+{this} * "Market"
+  Expenses:Food:Groceries  80.00 CNY
+  Assets:Bank
+
+{this} * "Payday"
+  Income:Salary  -1000.00 CNY
+  Assets:Bank
+"#, last = last_month, this = this_month)).await;
+
+        let trends = ledger.category_trends(3, false);
+        assert_eq!(trends.months.len(), 3);
+        assert_eq!(trends.months[2], this_month.format("%Y-%m").to_string());
+        assert_eq!(trends.expenses["Expenses:Food:Dining"], vec![0.0, 30.0, 0.0]);
+        assert_eq!(trends.income["Income:Salary"], vec![0.0, 0.0, 1000.0]);
+        // Parent categories sum their sub-accounts
+        assert_eq!(trends.series("Expenses:Food"), Some(vec![0.0, 30.0, 80.0]));
+        assert_eq!(trends.series("Expenses:Rent"), None);
+    }
+
+    #[tokio::test]
+    async fn test_account_monthly() {
+        let ledger = ledger_from_source(r#"
+2023-01-01 open Assets:Bank CNY
+2023-01-01 open Expenses:Food
+2023-01-01 open Income:Salary
+
+2023-12-20 * "Payday"
+  Income:Salary  -1000.00 CNY
+  Assets:Bank
+
+2024-02-01 * "Payday"
+  Income:Salary  -1000.00 CNY
+  Assets:Bank
+
+2024-02-10 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+
+2024-03-05 * "Trip"
+  Expenses:Food  10.00 USD
+  Assets:Bank  -10.00 USD
+"#).await;
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+
+        let monthly = ledger.account_monthly("Assets:Bank", 3);
+        assert_eq!(monthly.currency, "CNY");
+        let months: Vec<&str> = monthly.months.iter().map(|m| m.month.as_str()).collect();
+        assert_eq!(months, ["2024-01", "2024-02", "2024-03"]);
+        // December's salary is carried in as the opening balance
+        assert_eq!(monthly.months[0].ending_balance, 1000.0);
+        assert_eq!((monthly.months[1].inflow, monthly.months[1].outflow, monthly.months[1].net), (1000.0, 30.0, 970.0));
+        assert_eq!(monthly.months[1].ending_balance, 1970.0);
+        // No USD price: left out and counted
+        assert_eq!(monthly.months[2].unconverted, 1);
+        assert_eq!(monthly.months[2].ending_balance, 1970.0);
+
+        let income = ledger.account_monthly("Income:Salary", 3);
+        assert_eq!(income.months[1].net, ledger.display_amount("Income:Salary", -1000.0));
+    }
+
+    #[tokio::test]
+    async fn test_monthly_summary_report() {
+        let ledger = ledger_from_source(r#"
+2023-01-01 open Assets:Bank CNY
+2023-01-01 open Expenses:Food
+2023-01-01 open Income:Salary
+2023-01-01 price USD  7.00 CNY
+
+2023-12-20 * "Payday"
+  Income:Salary  -1000.00 CNY
+  Assets:Bank
+
+2024-02-01 * "Payday"
+  Income:Salary  -1000.00 CNY
+  Assets:Bank
+
+2024-02-10 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+
+2024-03-05 * "Trip"
+  Expenses:Food  10.00 USD
+  Assets:Bank  -10.00 USD
+"#).await;
+
+        let report = ledger.monthly_summary_report(2024);
+        assert_eq!(report.year, 2024);
+        assert_eq!(report.summaries.len(), 12);
+        assert_eq!(report.summaries[0].month, "2024-01");
+        assert_eq!(report.summaries[0].income, "0");
+        let income = ledger.display_amount("Income:Salary", Decimal::from(-1000));
+        assert_eq!(report.summaries[1].income, income.to_string());
+        assert_eq!(report.summaries[1].expenses, "30");
+        assert_eq!(report.summaries[2].expenses, "70");
+        assert_eq!(report.total_expenses, "100");
+        assert_eq!(report.total_net, "900");
+        assert_eq!(report.currency, "CNY");
+
+        // The year is independent of the current time range
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        assert_eq!(ledger.monthly_summary_report(2023).summaries[11].income, income.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_update_transaction() {
+        let dir = std::env::temp_dir().join(format!("beanweb-edit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = "2024-01-01 open Assets:Bank\n2024-01-01 open Expenses:Food\n\n; lunch\n2024-01-05 * \"Shop\" \"Lunch\"\n  receipt: \"r1.jpg\"\n  Expenses:Food  30.00 CNY ; card\n  Assets:Bank  -30.00 CNY\n\n2024-01-06 * \"Shop\"\n  Expenses:Food  10.00 CNY\n  Assets:Bank\n";
+        std::fs::write(dir.join("main.bean"), source).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = ledger_from_source_with_config("", config).await;
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        let id = ledger.transactions(100, 0).into_iter().find(|t| t.date == "2024-01-05").unwrap().id;
+
+        let original = ledger.transaction_source(&id).await.unwrap();
+        assert!(original.starts_with("2024-01-05 * \"Shop\" \"Lunch\"\n  receipt: \"r1.jpg\""));
+        assert!(original.ends_with("Assets:Bank  -30.00 CNY"));
+
+        // Rejected without touching the file
+        let unbalanced = "2024-01-05 * \"Shop\" \"Lunch\"\n  Expenses:Food  35.00 CNY\n  Assets:Bank  -30.00 CNY";
+        assert!(ledger.update_transaction(&id, unbalanced).await.is_err());
+        assert!(ledger.update_transaction(&id, "2024-01-05 * \"Shop\"\n  Expenses:Food  35.00 CNY").await.is_err());
+        assert!(ledger.update_transaction(&id, "2024-01-05 * \"A\"\n  Expenses:Food  1 CNY\n  Assets:Bank\n2024-01-05 * \"B\"\n  Expenses:Food  1 CNY\n  Assets:Bank").await.is_err());
+        assert!(ledger.update_transaction("missing", unbalanced).await.is_err());
+        // Syntax errors come back with their position
+        let error = ledger.update_transaction(&id, "2024-01-05 * \"Shop\"\n  Expenses:Food  x CNY\n  Assets:Bank").await.unwrap_err();
+        assert!(error.to_string().contains("2:3: Invalid posting"), "{}", error);
+        assert_eq!(std::fs::read_to_string(dir.join("main.bean")).unwrap(), source);
+
+        let replacement = edit::keep_metadata(&original, "2024-01-05 * \"Shop\" \"Dinner\"\n  Expenses:Food  35.00 CNY\n  Assets:Bank  -35.00 CNY");
+        let update = ledger.update_transaction(&id, &replacement).await.unwrap();
+        assert_eq!(update.line, 5);
+        let written = std::fs::read_to_string(dir.join("main.bean")).unwrap();
+        assert!(written.contains("; lunch\n2024-01-05 * \"Shop\" \"Dinner\"\n  receipt: \"r1.jpg\"\n  Expenses:Food  35.00 CNY ; card\n  Assets:Bank  -35.00 CNY\n\n2024-01-06"));
+
+        // The loaded ledger no longer matches the file until it is reloaded
+        assert!(ledger.update_transaction(&id, &replacement).await.is_err());
+        ledger.reload().await.unwrap();
+        let updated = ledger.transactions(100, 0).into_iter().find(|t| t.date == "2024-01-05").unwrap();
+        assert_eq!(updated.narration, "Dinner");
+        assert!(ledger.transaction_source(&updated.id).await.is_ok());
+
+        // Deleting removes the block and keeps the previous file as a backup
+        let deletion = ledger.delete_transaction(&updated.id).await.unwrap();
+        assert_eq!(deletion.transaction.narration, "Dinner");
+        let written = std::fs::read_to_string(dir.join("main.bean")).unwrap();
+        assert!(written.contains("; lunch\n2024-01-06 * \"Shop\""));
+        assert!(std::fs::read_to_string(dir.join("main.bean.bak")).unwrap().contains("Dinner"));
+        assert!(ledger.delete_transaction(&updated.id).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_accounts() {
+        use account_import::{AccountImportOptions, AccountRowStatus};
+        let dir = std::env::temp_dir().join(format!("beanweb-account-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), "option \"title\" \"Test\"\n\n2024-01-01 open Assets:Cash CNY\n2024-06-01 open Assets:Broker\n").unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = ledger_from_source_with_config("", config).await;
+        ledger.load(dir.join("main.bean")).await.unwrap();
+
+        let csv = "Account,Type,Currency,Date\nChecking,Bank,\"CNY,USD\",\nfood > dining,expense,,\nAssets:Cash,,CNY,\nFood > Dining,Expense,,\nAssets:Loan,liability,,\nAssets:Broker:Cash,,,2024-03-01\nIncome:Salary,,cny,\n";
+        let options = AccountImportOptions { start_date: Some("2024-01-01".to_string()) };
+        let preview = ledger.preview_account_import(csv, &options);
+        let statuses: Vec<(&str, AccountRowStatus)> = preview.rows.iter().map(|r| (r.account.as_str(), r.status)).collect();
+        assert_eq!(statuses, vec![
+            ("Assets:Checking", AccountRowStatus::Ok),
+            ("Expenses:Food:Dining", AccountRowStatus::Ok),
+            ("Assets:Cash", AccountRowStatus::Duplicate),
+            ("Expenses:Food:Dining", AccountRowStatus::Duplicate),
+            ("", AccountRowStatus::Invalid),
+            ("Assets:Broker:Cash", AccountRowStatus::Invalid),
+            ("Income:Salary", AccountRowStatus::Invalid),
+        ]);
+        assert_eq!(preview.rows[0].line, 2);
+
+        let outcome = ledger.import_accounts(csv, None, &options).unwrap();
+        assert_eq!(outcome.opened, vec!["Assets:Checking".to_string(), "Expenses:Food:Dining".to_string()]);
+        assert_eq!(outcome.skipped, 5);
+        assert!(outcome.include_added);
+        let accounts = std::fs::read_to_string(dir.join("accounts.bean")).unwrap();
+        assert!(accounts.contains(";; ===== Assets =====\n2024-01-01 open Assets:Checking CNY,USD\n"));
+        assert!(accounts.contains("2024-01-01 open Expenses:Food:Dining\n"));
+
+        // Everything is a duplicate once loaded
+        ledger.load(dir.join("main.bean")).await.unwrap();
+        assert!(ledger.import_accounts(csv, None, &options).is_err());
+        assert!(ledger.import_accounts("Assets:New", Some("../out.bean"), &options).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_other_threshold() {
+        let source = r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Income:Salary
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Transport:Taxi
+2024-01-01 open Expenses:Books
+
+2024-01-05 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary
+2024-01-06 * "Dinner"
+  Expenses:Food:Dining  90.00 CNY
+  Assets:Bank
+2024-01-07 * "Taxi"
+  Expenses:Transport:Taxi  6.00 CNY
+  Assets:Bank
+2024-01-08 * "Book"
+  Expenses:Books  4.00 CNY
+  Assets:Bank
+"#;
+        let mut config = Config::default();
+        config.currency.default_currency = "CNY".to_string();
+        config.reports.other_threshold = 10.0;
+        let ledger = ledger_from_source_with_config(source, config).await;
+        ledger.set_time_range(TimeRange::All);
+
+        let report = ledger.income_expense_report();
+        let accounts: Vec<&str> = report.expense_entries.iter().map(|e| e.account.as_str()).collect();
+        assert_eq!(accounts, vec!["Expenses:Food:Dining", other::OTHER]);
+        let other = &report.expense_entries[1];
+        assert_eq!(parse_decimal(&other.amount), Decimal::from(10));
+        assert!((other.percentage - 10.0).abs() < 1e-9);
+        assert_eq!(other.members.len(), 2);
+        // A lone income entry is never merged; totals are unchanged
+        assert_eq!(report.income_entries.len(), 1);
+        assert_eq!(parse_decimal(&report.total_expenses), Decimal::from(100));
+
+        // Categories are merged on their own shares, not the accounts'
+        let categories = ledger.expense_category_report();
+        let names: Vec<&str> = categories.breakdowns.iter().map(|b| b.category.as_str()).collect();
+        assert_eq!(names, vec!["Food", other::OTHER]);
+        assert_eq!(categories.breakdowns[1].members.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_transactions() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Transport
+
+2024-01-05 * "Cafe" "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+
+2024-01-05 * "Metro"
+  Assets:Bank  -30.00 CNY
+  Expenses:Transport
+"#).await;
+        let accounts = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let similar = ledger.find_similar_transactions("2024-01-05", Decimal::new(-30, 0), &accounts(&["Assets:Bank", "Expenses:Food"]));
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].payee, "Cafe");
+        assert!(ledger.find_similar_transactions("2024-01-06", Decimal::new(30, 0), &accounts(&["Assets:Bank", "Expenses:Food"])).is_empty());
+        assert!(ledger.find_similar_transactions("2024-01-05", Decimal::new(31, 0), &accounts(&["Assets:Bank", "Expenses:Food"])).is_empty());
+        assert!(ledger.find_similar_transactions("2024-01-05", Decimal::new(30, 0), &accounts(&["Expenses:Food"])).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_balances() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Bank:Savings
+2024-01-01 open Expenses:Food
+
+2024-01-05 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+
+2024-01-06 * "Transfer"
+  Assets:Bank:Savings  100.00 CNY
+  Assets:Bank  -100.00 CNY
+
+2024-01-06 balance Assets:Bank  -30.00 CNY
+2024-01-07 balance Assets:Bank  -130.00 CNY
+2024-01-07 balance Assets:Bank:Savings  100.00 CNY
+"#).await;
+
+        let checks = ledger.check_balances();
+        assert_eq!(checks.len(), 3);
+        assert!(checks[0].passed);
+        let failed: Vec<_> = checks.iter().filter(|c| !c.passed).collect();
+        assert_eq!(failed.len(), 1);
+        // Sub-accounts count toward the parent
+        assert_eq!(failed[0].account, "Assets:Bank");
+        assert_eq!(failed[0].computed, Decimal::new(-30, 0));
+        assert_eq!(failed[0].difference, Decimal::new(100, 0));
+        assert_eq!(failed[0].line, Some(14));
+    }
+
+    #[tokio::test]
+    async fn test_stale_balances() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Cash
+2024-01-01 open Assets:Old
+2024-01-01 open Liabilities:Card
+2024-01-01 open Expenses:Food
+
+2024-01-10 balance Assets:Bank  0.00 CNY
+2024-03-01 balance Assets:Bank  0.00 CNY
+2024-01-10 balance Assets:Old  0.00 CNY
+2024-02-01 close Assets:Old
+2024-01-15 balance Liabilities:Card  0.00 CNY
+"#).await;
+
+        let today = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let stale = ledger.stale_balances_as_of(today);
+        // Bank was reconciled 19 days ago, Cash never, Old is closed
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].account, "Liabilities:Card");
+        assert_eq!(stale[0].last_assertion, "2024-01-15");
+        assert_eq!(stale[0].days, 65);
+        assert_eq!(stale[0].threshold, 30);
+    }
+
+    #[tokio::test]
+    async fn test_budget_report() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Travel
+
+2024-01-01 custom "budget" Expenses:Food "monthly" 1000.00 CNY
+2024-02-01 custom "budget" Expenses:Food "monthly" 1200.00 CNY
+2024-01-01 custom "budget" Expenses:Travel "quarterly" 3000.00 CNY
+
+2024-01-10 * "Market"
+  Expenses:Food  300.00 CNY
+  Assets:Bank
+
+2024-02-10 * "Restaurant"
+  Expenses:Food:Dining  1500.00 CNY
+  Assets:Bank
+
+2024-02-20 * "Train"
+  Expenses:Travel  500.00 CNY
+  Assets:Bank
+"#).await;
+
+        assert_eq!(ledger.budgets().len(), 3);
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let report = ledger.budget_report_between(date("2024-01-01"), date("2024-02-29"));
+        let food = report.items.iter().find(|i| i.account == "Expenses:Food").unwrap();
+        // January's 1000 plus February's 1200; sub-accounts count as spent
+        assert_eq!(food.budget, 2200.0);
+        assert_eq!(food.spent, 1800.0);
+        assert_eq!(food.remaining, 400.0);
+        let travel = report.items.iter().find(|i| i.account == "Expenses:Travel").unwrap();
+        assert_eq!(travel.budget, 2000.0);
+        assert_eq!(travel.percent, 25.0);
+
+        let february = ledger.budget_report_between(date("2024-02-01"), date("2024-02-29"));
+        let food = february.items.iter().find(|i| i.account == "Expenses:Food").unwrap();
+        assert_eq!((food.budget, food.spent), (1200.0, 1500.0));
+    }
+
+    #[tokio::test]
+    async fn test_weekly_budget() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Groceries
+2024-01-01 open Expenses:Rent
+
+2024-01-01 custom "budget" Expenses:Groceries "weekly" 70.00 CNY
+2024-01-01 custom "budget" Expenses:Rent "monthly" 3100.00 CNY
+
+2024-03-05 * "Market"
+  Expenses:Groceries  50.00 CNY
+  Assets:Bank
+"#).await;
+
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        // March has 31 days: 31 sevenths of the weekly budget
+        let march = ledger.budget_report_between(date("2024-03-01"), date("2024-03-31"));
+        let groceries = march.items.iter().find(|i| i.account == "Expenses:Groceries").unwrap();
+        assert_eq!((groceries.period, groceries.period_amount), (budget::BudgetPeriod::Weekly, 70.0));
+        assert_eq!(groceries.budget, 310.0);
+        assert!(groceries.prorated);
+        let rent = march.items.iter().find(|i| i.account == "Expenses:Rent").unwrap();
+        assert_eq!(rent.budget, 3100.0);
+        assert!(!rent.prorated);
+
+        // One week, Monday to Sunday: a whole weekly budget, 7 days of rent
+        let week = ledger.budget_report_between(date("2024-03-04"), date("2024-03-10"));
+        let groceries = week.items.iter().find(|i| i.account == "Expenses:Groceries").unwrap();
+        assert_eq!((groceries.budget, groceries.spent), (70.0, 50.0));
+        assert!(!groceries.prorated);
+        let rent = week.items.iter().find(|i| i.account == "Expenses:Rent").unwrap();
+        assert_eq!(rent.budget, 700.0);
+        assert!(rent.prorated);
+    }
+
+    #[tokio::test]
+    async fn test_spending_velocity() {
+        let ledger = ledger_from_source(r#"2023-12-01 open Assets:Bank
+2023-12-01 open Expenses:Food
+
+2023-12-31 * "Party"
+  Expenses:Food  310.00 CNY
+  Assets:Bank
+
+2024-01-01 * "Market"
+  Expenses:Food  40.00 CNY
+  Assets:Bank
+
+2024-01-31 * "Market"
+  Expenses:Food  22.00 CNY
+  Assets:Bank
+
+2024-02-29 * "Market"
+  Expenses:Food  58.00 CNY
+  Assets:Bank
+"#).await;
+
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        // First day of the month: one day elapsed, last month is December
+        let velocity = ledger.spending_velocity_on(date("2024-01-01"));
+        assert_eq!((velocity.days_elapsed, velocity.days_in_month), (1, 31));
+        assert_eq!(velocity.spent, "40");
+        assert_eq!(velocity.last_month_daily_average, "10");
+        assert_eq!(velocity.projected, "1240");
+        assert_eq!(velocity.change_percent, Some(300.0));
+
+        // Last day of the month: the projection is what was spent
+        let velocity = ledger.spending_velocity_on(date("2024-01-31"));
+        assert_eq!((velocity.spent.as_str(), velocity.daily_average.as_str()), ("62", "2"));
+        assert_eq!(velocity.projected, "62");
+        assert_eq!(velocity.change_percent, Some(-80.0));
+
+        // Leap February: 29 days, January's pace is its 62 over 31 days
+        let velocity = ledger.spending_velocity_on(date("2024-02-29"));
+        assert_eq!(velocity.days_in_month, 29);
+        assert_eq!((velocity.daily_average.as_str(), velocity.last_month_daily_average.as_str()), ("2", "2"));
+        assert_eq!(velocity.change_percent, Some(0.0));
+
+        // Nothing spent the month before
+        let velocity = ledger.spending_velocity_on(date("2023-12-15"));
+        assert_eq!((velocity.spent.as_str(), velocity.change_percent), ("0", None));
+
+        // A past custom range uses its last day
+        let velocity = ledger.spending_velocity(&TimeContext::custom(date("2024-01-01"), date("2024-01-31")));
+        assert_eq!(velocity.date, "2024-01-31");
+    }
+
+    #[tokio::test]
+    async fn test_payee_summary() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Travel
+2024-01-01 open Income:Salary
+2024-01-01 open Assets:Savings
+
+2024-01-01 price USD 7.00 CNY
+
+2024-03-02 * "Market" "Groceries"
+  Expenses:Food  120.00 CNY
+  Assets:Bank
+
+2024-03-09 * "Market" "Groceries"
+  Expenses:Food  80.00 CNY
+  Assets:Bank
+
+2024-03-12 * "Market" "Refund"
+  Expenses:Food  -20.00 CNY
+  Assets:Bank
+
+2024-03-15 * "Airline" "Ticket"
+  Expenses:Travel  100.00 USD
+  Assets:Bank  -700.00 CNY
+
+2024-03-20 * "Employer" "Salary"
+  Assets:Bank  5000.00 CNY
+  Income:Salary
+
+2024-03-21 * "Savings"
+  Assets:Savings  1000.00 CNY
+  Assets:Bank
+
+2024-03-22 * "" "Snacks"
+  Expenses:Food  5.00 CNY
+  Assets:Bank
+"#).await;
+
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let payees = ledger.payee_summary(&TimeContext::custom(date("2024-03-01"), date("2024-03-31")));
+        // Income, transfers and transactions without a payee are left out
+        let names: Vec<&str> = payees.iter().map(|p| p.payee.as_str()).collect();
+        assert_eq!(names, vec!["Airline", "Market"]);
+        assert_eq!((payees[0].amount.as_str(), payees[0].count), ("700", 1));
+        // The refund nets out
+        assert_eq!((payees[1].amount.as_str(), payees[1].count), ("180", 3));
+        assert_eq!(payees[1].last_date, "2024-03-12");
+        assert!((payees[0].percentage - 700.0 / 8.8).abs() < 0.01);
+
+        assert!(ledger.payee_summary(&TimeContext::custom(date("2024-04-01"), date("2024-04-30"))).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_operating_currencies() {
+        let ledger = ledger_from_source(r#"option "operating_currency" "CNY"
+option "operating_currency" "USD"
+
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Broker
+2024-01-01 open Liabilities:Card
+2024-01-01 open Expenses:Food
+2024-01-01 open Income:Salary
+
+2024-01-01 price USD 7.00 CNY
+
+2024-02-01 * "Employer" "Salary"
+  Assets:Bank  7000.00 CNY
+  Income:Salary  -7000.00 CNY
+
+2024-02-02 * "Broker" "Dividend"
+  Assets:Broker  100.00 USD
+  Income:Salary  -100.00 USD
+
+2024-02-03 * "Shop" "Lunch"
+  Expenses:Food  70.00 CNY
+  Liabilities:Card  -70.00 CNY
+"#).await;
+
+        assert_eq!(ledger.operating_currencies(), vec!["CNY", "USD"]);
+
+        let report = ledger.balance_report();
+        let usd = &report.operating_totals[0];
+        assert_eq!(usd.currency, "USD");
+        assert_eq!((usd.total_assets.as_str(), usd.total_liabilities.as_str(), usd.net_worth.as_str()), ("1100", "10", "1090"));
+        let bank = report.entries.iter().find(|e| e.account == "Assets:Bank").unwrap();
+        assert_eq!(bank.operating, vec![Amount::new(Decimal::new(100000, 2), "USD")]);
+
+        let report = ledger.income_expense_report();
+        let usd = &report.operating_totals[0];
+        assert_eq!((usd.total_income.as_str(), usd.total_expenses.as_str(), usd.net_income.as_str()), ("1100", "10", "1090"));
+        // The USD dividend keeps its units in the USD column
+        let dividend = report.income_entries.iter().find(|e| e.currency == "USD").unwrap();
+        assert_eq!(dividend.operating[0].to_string(), "100.00 USD");
+
+        // Original currencies have no converted columns
+        assert!(ledger.balance_report_with(ConversionMode::Original).operating_totals.is_empty());
+        let single = ledger_from_source("2024-01-01 open Assets:Bank\n").await;
+        assert!(single.balance_report().operating_totals.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recurring_payments() {
+        let ledger = ledger_from_source(r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Subscriptions
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Gym
+
+2024-01-15 * "Netflix" "Plan"
+  Expenses:Subscriptions  15.99 USD
+  Assets:Bank  -15.99 USD
+
+2024-02-14 * "Netflix" "Plan"
+  Expenses:Subscriptions  15.99 USD
+  Assets:Bank  -15.99 USD
+
+2024-03-15 * "Netflix" "Plan"
+  Expenses:Subscriptions  15.99 USD
+  Assets:Bank  -15.99 USD
+
+2024-01-03 * "Cafe" "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank  -30.00 CNY
+
+2024-01-20 * "Cafe" "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank  -30.00 CNY
+
+2024-03-01 * "Cafe" "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank  -30.00 CNY
+
+2023-01-10 * "Gym" "Membership"
+  Expenses:Gym  100.00 CNY
+  Assets:Bank  -100.00 CNY
+
+2023-02-10 * "Gym" "Membership"
+  Expenses:Gym  100.00 CNY
+  Assets:Bank  -100.00 CNY
+
+2023-03-10 * "Gym" "Membership"
+  Expenses:Gym  100.00 CNY
+  Assets:Bank  -100.00 CNY
+"#).await;
+
+        // Irregular lunches and the long-cancelled gym are left out
+        let payments = ledger.recurring_payments_as_of(NaiveDate::from_ymd_opt(2024, 3, 20).unwrap());
+        assert_eq!(payments.len(), 1);
+        let netflix = &payments[0];
+        assert_eq!((netflix.payee.as_str(), netflix.account.as_str()), ("Netflix", "Expenses:Subscriptions"));
+        assert_eq!((netflix.amount.as_str(), netflix.currency.as_str()), ("15.99", "USD"));
+        assert_eq!(netflix.interval, RecurringInterval::Monthly);
+        assert_eq!((netflix.occurrences, netflix.last_date.as_str(), netflix.next_date.as_str()), (3, "2024-03-15", "2024-04-15"));
+        assert_eq!(netflix.monthly_amount, "15.99");
+
+        // Later occurrences don't count before they happen
+        assert!(ledger.recurring_payments_as_of(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()).is_empty());
+        // Still listed while due, dropped once a whole interval has been missed
+        assert_eq!(ledger.recurring_payments_as_of(NaiveDate::from_ymd_opt(2024, 5, 10).unwrap()).len(), 1);
+        assert!(ledger.recurring_payments_as_of(NaiveDate::from_ymd_opt(2024, 5, 16).unwrap()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_templates() {
+        let dir = std::env::temp_dir().join(format!("beanweb-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let _ = std::fs::remove_file(dir.join(templates::TEMPLATES_FILE));
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        let ledger = ledger_from_source_with_config("2024-01-01 open Assets:Cash CNY\n", config).await;
+        let posting = |account: &str, amount: &str| TemplatePosting { account: account.to_string(), amount: amount.to_string() };
+        let coffee = TransactionTemplate {
+            name: " Coffee ".to_string(),
+            payee: "Cafe".to_string(),
+            narration: String::new(),
+            tags: vec!["#daily".to_string(), " ".to_string()],
+            postings: vec![posting("Expenses:Coffee", "35 CNY"), posting("Assets:Cash", ""), posting(" ", "1")],
+        };
+
+        assert!(ledger.transaction_templates().unwrap().is_empty());
+        let saved = ledger.save_transaction_template(coffee.clone()).unwrap();
+        assert_eq!(saved.name, "Coffee");
+        assert_eq!(saved.tags, vec!["daily"]);
+        assert_eq!(saved.postings, vec![posting("Expenses:Coffee", "35 CNY"), posting("Assets:Cash", "")]);
+        ledger.save_transaction_template(TransactionTemplate { name: "Rent".to_string(), ..coffee.clone() }).unwrap();
+        // Same name replaces, the file stays sorted by name
+        ledger.save_transaction_template(TransactionTemplate { name: "Coffee".to_string(), payee: "Kiosk".to_string(), ..coffee.clone() }).unwrap();
+        let templates = ledger.transaction_templates().unwrap();
+        assert_eq!(templates.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["Coffee", "Rent"]);
+        assert_eq!(ledger.transaction_template("Coffee").unwrap().unwrap().payee, "Kiosk");
+
+        assert!(ledger.save_transaction_template(TransactionTemplate { name: " ".to_string(), ..coffee.clone() }).is_err());
+        assert!(ledger.save_transaction_template(TransactionTemplate { postings: vec![], ..coffee }).is_err());
+        assert!(ledger.delete_transaction_template("Rent").unwrap());
+        assert!(!ledger.delete_transaction_template("Rent").unwrap());
+        assert_eq!(ledger.transaction_templates().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_headline() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Pension
+2024-01-01 open Assets:Broker
+2024-01-01 open Liabilities:Card
+2024-01-01 open Income:Salary
+2024-01-01 open Expenses:Tax
+2024-01-01 open Expenses:Insurance
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Home
+2024-01-01 open Expenses:Fees
+
+2024-01-25 * "Employer" "Salary"
+  Income:Salary  -10000 CNY
+  Expenses:Tax  1500 CNY
+  Expenses:Insurance  500 CNY
+  Assets:Pension  800 CNY
+  Assets:Bank
+
+2024-01-26 * "Market" "Weekly shop"
+  Expenses:Food  60 CNY
+  Expenses:Home  40 CNY
+  Liabilities:Card  -100 CNY
+
+2024-01-27 * "Market" "Refund"
+  Assets:Bank  15 CNY
+  Expenses:Home  -15 CNY
+
+2024-01-28 * "Card payment"
+  Assets:Bank  -100 CNY
+  Liabilities:Card  100 CNY
+
+2024-01-29 * "Buy ETF"
+  Assets:Broker  10 ETF {50 USD}
+  Expenses:Fees  2 USD
+  Assets:Bank  -502 USD
+"#).await;
+        let headline = |narration: &str| {
+            let tx = ledger.search_transactions(narration)[0].clone();
+            let headline = ledger.headline(&tx);
+            (headline.amount, headline.currency, headline.flow)
+        };
+        // Take-home pay plus the pension contribution, both own accounts
+        assert_eq!(headline("Salary"), ("8000".to_string(), "CNY".to_string(), Flow::In));
+        assert_eq!(headline("Weekly shop"), ("100".to_string(), "CNY".to_string(), Flow::Out));
+        assert_eq!(headline("Refund"), ("15".to_string(), "CNY".to_string(), Flow::In));
+        assert_eq!(headline("Card payment"), ("100".to_string(), "CNY".to_string(), Flow::Transfer));
+        // Weighed at cost: only the fee left own accounts
+        assert_eq!(headline("Buy ETF"), ("2".to_string(), "USD".to_string(), Flow::Out));
+
+        let salary = ledger.search_transactions("Salary")[0].breakdown();
+        assert_eq!(salary.len(), 5);
+        assert_eq!(salary[4], PostingAmount { account: "Assets:Bank".to_string(), amount: "7200".to_string(), currency: "CNY".to_string(), inferred: true });
+        assert!(!salary[0].inferred && salary[0].amount == "-10000");
+    }
+
+    #[tokio::test]
+    async fn test_transaction_headline_signs() {
+        let source = r#"
+option "name_assets" "Vermoegen"
+option "name_income" "Einnahmen"
+2024-01-01 open Vermoegen:Bank
+2024-01-01 open Einnahmen:Gehalt
+2024-01-01 open Expenses:Food
+
+2024-01-25 * "Employer" "Salary"
+  Einnahmen:Gehalt  -1000 CNY
+  Vermoegen:Bank
+
+2024-01-26 * "Market" "Lunch"
+  Expenses:Food  20 CNY
+  Vermoegen:Bank
+"#;
+        let signs = |ledger: &Ledger| -> Vec<(String, Flow)> {
+            ledger.search_transactions("").iter()
+                .map(|tx| ledger.headline(tx))
+                .map(|h| (h.display_amount, h.flow))
+                .collect()
+        };
+        // Renamed roots still count as own accounts and income
+        let natural = ledger_from_source(source).await;
+        let mut expected = vec![("1000".to_string(), Flow::In), ("20".to_string(), Flow::Out)];
+        let mut found = signs(&natural);
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(found, expected);
+
+        let mut config = Config::default();
+        config.currency.sign_convention = SignConvention::Raw;
+        let raw = ledger_from_source_with_config(source, config).await;
+        expected[0].0 = "-1000".to_string();
+        let mut found = signs(&raw);
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(found, expected);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_years() {
+        let ledger = ledger_from_source(
+            r#"2020-01-01 open Assets:Bank CNY
+2020-01-01 open Expenses:Food CNY
+
+2023-05-01 * "Shop" "Lunch"
+  Expenses:Food  20.00 CNY
+  Assets:Bank
+
+2020-02-01 * "Shop" "Lunch"
+  Expenses:Food  20.00 CNY
+  Assets:Bank
+
+2020-03-01 * "Shop" "Dinner"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+"#,
+        ).await;
+        assert_eq!(ledger.transaction_years(), vec![2020, 2023]);
+    }
+
+    #[tokio::test]
+    async fn test_ledger_service() {
+        let dir = std::env::temp_dir().join(format!("beanweb-service-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), "2024-01-01 open Assets:Bank CNY\n2024-01-01 open Expenses:Food CNY\n2024-06-01 close Expenses:Food\n").unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        config.data.new_transaction_file = "main.bean".to_string();
+        let service = LedgerService::open(config, Arc::new(beanweb_parser::DefaultBeancountParser)).await.unwrap();
+        service.set_time_range(TimeRange::All).await;
+
+        assert_eq!(service.accounts().await.len(), 2);
+        assert!(service.account("Assets:Bank").await.is_some());
+        assert_eq!(service.transaction_count().await, 0);
+
+        let created = service.create_transaction("2024-02-01 * \"Shop\" \"Lunch\"\n  Expenses:Food  20.00 CNY\n  Assets:Bank").await.unwrap();
+        // The entry as loaded back from the file, after the blank line
+        assert_eq!((created.file.clone(), created.line), (dir.join("main.bean"), 5));
+        assert_eq!(created.transaction.payee, "Shop");
+        assert_eq!(created.transaction.postings[1].units, None);
+        assert_eq!(Some(&created.transaction), service.transactions(10, 0).await.first());
+        assert_eq!(service.transaction_count().await, 1);
+        assert_eq!(service.income_expense_report(ConversionMode::Converted).await.expenses, Decimal::from(20));
+        // Closed account, unbalanced text: nothing is written
+        assert!(service.create_transaction("2024-07-01 * \"Shop\"\n  Expenses:Food  5.00 CNY\n  Assets:Bank").await.is_err());
+        assert!(service.create_transaction("2024-02-02 * \"Shop\"\n  Expenses:Food  5.00 CNY\n  Assets:Bank  -4.00 CNY").await.is_err());
+        assert_eq!(service.transaction_count().await, 1);
+
+        let id = service.search_transactions("Lunch").await[0].id.clone();
+        let updated = service.update_transaction(&id, "2024-02-01 * \"Shop\" \"Dinner\"\n  Expenses:Food  30.00 CNY\n  Assets:Bank").await.unwrap();
+        assert_eq!(updated.transaction.narration, "Dinner");
+        assert_eq!(updated.line, 5);
+        let id = updated.transaction.id.clone();
+        assert_eq!(service.transaction(&id).await.unwrap().narration, "Dinner");
+        assert_eq!(service.delete_transaction(&id).await.unwrap().transaction.narration, "Dinner");
+        assert_eq!(service.transaction_count().await, 0);
+
+        // Leading blank lines are trimmed before the header line is counted
+        let created = service.create_transaction("\n\n2024-02-03 * \"Shop\" \"Tea\"\n  Expenses:Food  5.00 CNY\n  Assets:Bank").await.unwrap();
+        assert_eq!(created.line, 5);
+        assert_eq!(created.transaction.narration, "Tea");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_categorization_rules() {
+        use transaction_import::TransactionImportOptions;
+        let dir = std::env::temp_dir().join(format!("beanweb-rules-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(rules::RULES_FILE), r##"
+- name: Coffee
+  payee: starbucks|costa
+  max_amount: 20
+  account: Expenses:Coffee
+  tags: ["#daily"]
+- name: Big coffee
+  payee: starbucks
+  account: Expenses:Treats
+- name: Tagged
+  metadata:
+    external_id: ^T-
+  account: Expenses:Tagged
+"##).unwrap();
+        let mut config = Config::default();
+        config.data.path = dir.clone();
+        config.data.main_file = "main.bean".to_string();
+        let mut ledger = ledger_from_source_with_config(r#"2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Coffee
+2024-01-01 open Expenses:Treats
+2024-01-01 open Expenses:Tagged
+2024-01-01 open Expenses:Uncategorized
+2024-01-01 open Expenses:Rent
+"#, config).await;
+
+        let rules = ledger.rule_set().unwrap();
+        let subject = |payee: &str, amount: i64| RuleSubject { payee: payee.to_string(), amount: Some(Decimal::from(amount)), ..Default::default() };
+        // Case-insensitive, the amount compared without its sign, first match wins
+        assert_eq!(rules.matching(&subject("STARBUCKS Berlin", -5)).map(|r| r.name.as_str()), Some("Coffee"));
+        assert_eq!(rules.matching(&subject("Starbucks", 35)).map(|r| r.name.as_str()), Some("Big coffee"));
+        assert_eq!(rules.matching(&subject("Costa", 35)), None);
+        assert_eq!(rules.matching(&subject("Costa", 5)).unwrap().tags, vec!["daily"]);
+        assert!(RuleSet::new(vec![CategoryRule { name: "Bad".to_string(), payee: Some("(".to_string()), narration: None, min_amount: None, max_amount: None, metadata: Default::default(), account: "Expenses:X".to_string(), tags: vec![] }]).is_err());
+        assert!(RuleSet::new(vec![CategoryRule { name: "Empty".to_string(), payee: None, narration: None, min_amount: None, max_amount: None, metadata: Default::default(), account: "Expenses:X".to_string(), tags: vec![] }]).is_err());
+        assert!(RuleSet::new(vec![CategoryRule { name: "Range".to_string(), payee: None, narration: None, min_amount: Some(Decimal::from(10)), max_amount: Some(Decimal::from(5)), metadata: Default::default(), account: "Expenses:X".to_string(), tags: vec![] }]).is_err());
+
+        // Import: the row's own counter account, else a rule, else the default
+        let csv = "2024-02-01,-4.50,Starbucks,,S-1\n2024-02-02,-900,Landlord,,T-2\n2024-02-03,-3,Kiosk,,K-3\n2024-02-04,-5,Costa,,K-4\n";
+        let options = TransactionImportOptions {
+            account: "Assets:Bank".to_string(),
+            counter_accounts: HashMap::from([(4, "Expenses:Rent".to_string())]),
+            ..Default::default()
+        };
+        let preview = ledger.preview_transaction_import(csv, &options).unwrap();
+        let counters: Vec<(&str, Option<&str>)> = preview.rows.iter().map(|r| (r.counter_account.as_str(), r.rule.as_deref())).collect();
+        assert_eq!(counters, vec![
+            ("Expenses:Coffee", Some("Coffee")),
+            ("Expenses:Tagged", Some("Tagged")),
+            ("Expenses:Uncategorized", None),
+            ("Expenses:Rent", None),
+        ]);
+        assert_eq!(preview.rows[0].tags, vec!["daily"]);
+        let skipped = ledger.preview_transaction_import(csv, &TransactionImportOptions { skip_rules: true, ..options.clone() }).unwrap();
+        assert_eq!(skipped.rows[0].counter_account, "Expenses:Uncategorized");
+
+        let outcome = ledger.import_transactions(csv, "rules-import.bean", &TransactionImportOptions { lines: Some(vec![1]), ..options }).unwrap();
+        assert_eq!(outcome.written, 1);
+        let written = std::fs::read_to_string(dir.join("rules-import.bean")).unwrap();
+        assert!(written.contains("2024-02-01 * \"Starbucks\" \"\" #daily\n"), "{}", written);
+        assert!(written.contains("  Expenses:Coffee\n"));
+
+        // The rules compiled at load are kept until the next one, which
+        // reports a broken file the same way for listing and matching
+        std::fs::write(dir.join(rules::RULES_FILE), "- name: Bad\n  payee: \"(\"\n  account: Expenses:X\n").unwrap();
+        assert!(Arc::ptr_eq(&ledger.rule_set().unwrap(), &rules));
+        ledger.load_directives(Vec::new()).await;
+        let listed = ledger.categorization_rules().unwrap_err().to_string();
+        assert!(listed.contains("rule Bad"), "{}", listed);
+        assert_eq!(ledger.rule_set().err().map(|e| e.to_string()), Some(listed));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_notes_documents_events_queries() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Assets:Bank:Savings CNY
+2024-01-01 open Assets:Banking CNY
+2024-01-10 note Assets:Bank "Called about the fee"
+2024-02-10 note Assets:Bank:Savings "Rate went up"
+2024-02-11 note Assets:Banking "Not a sub-account"
+2024-01-31 document Assets:Bank "documents/2024-01.pdf"
+2023-06-01 event "location" "Berlin"
+2024-02-01 event "location" "Shanghai"
+2024-03-01 event "employer" "ACME"
+2024-01-01 query "food" "SELECT * WHERE account ~ 'Food'"
+"#).await;
+
+        let notes = ledger.account_notes("Assets:Bank");
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].comment, "Rate went up");
+        assert_eq!(notes[1].comment, "Called about the fee");
+        assert!(notes[1].line.is_some());
+        assert!(ledger.account_notes("Assets:Bank:Savings").iter().all(|n| n.account == "Assets:Bank:Savings"));
+
+        let documents = ledger.account_documents("Assets:Bank");
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].filename, "documents/2024-01.pdf");
+        assert!(ledger.account_documents("Assets:Banking").is_empty());
+
+        let events = ledger.events(None);
+        assert_eq!(events.iter().map(|e| e.description.as_str()).collect::<Vec<_>>(), vec!["ACME", "Shanghai", "Berlin"]);
+        assert_eq!(ledger.events(Some("location")).len(), 2);
+        assert_eq!(ledger.event_types(), vec!["employer", "location"]);
+
+        let queries = ledger.queries();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].name, "food");
+        assert_eq!(queries[0].query, "SELECT * WHERE account ~ 'Food'");
+    }
+
+    #[tokio::test]
+    async fn test_ledger_options() {
+        let ledger = ledger_from_source(r#"
+option "title" "Haushalt"
+option "operating_currency" "EUR"
+option "name_assets" "Vermoegen"
+option "name_income" "Einnahmen"
+option "name_expenses" "Ausgaben"
+option "booking_method" "FIFO"
+2024-01-01 open Vermoegen:Bank EUR
+2024-01-01 open Einnahmen:Gehalt EUR
+2024-01-01 open Ausgaben:Essen EUR
+2024-01-05 * "Firma" "Gehalt"
+  Vermoegen:Bank  1000.00 EUR
+  Einnahmen:Gehalt  -1000.00 EUR
+2024-01-06 * "Markt" "Einkauf"
+  Ausgaben:Essen  40.00 EUR
+  Vermoegen:Bank  -40.00 EUR
+"#).await;
+        assert_eq!(ledger.title().as_deref(), Some("Haushalt"));
+        assert_eq!(ledger.report_currency(), "EUR");
+        let options = ledger.options();
+        assert_eq!(options.root(AccountType::Assets), "Vermoegen");
+        assert_eq!(options.root(AccountType::Liabilities), "Liabilities");
+        assert_eq!(options.values["booking_method"], vec!["FIFO".to_string()]);
+
+        let accounts = ledger.accounts();
+        let account_type = |name: &str| accounts.iter().find(|a| a.name == name).unwrap().account_type;
+        assert_eq!(account_type("Vermoegen:Bank"), AccountType::Assets);
+        assert_eq!(account_type("Einnahmen:Gehalt"), AccountType::Income);
+        assert_eq!(account_type("Ausgaben:Essen"), AccountType::Expenses);
+
+        let balances = ledger.balance_report();
+        assert_eq!(balances.currency, "EUR");
+        assert_eq!(balances.total_assets, "960");
+        let report = ledger.income_expense_report();
+        assert_eq!(report.currency, "EUR");
+        assert_eq!(report.total_income, "1000");
+        assert_eq!(report.total_expenses, "40");
+        // Natural signs follow the renamed income root
+        assert_eq!(ledger.display_amount("Einnahmen:Gehalt", -1000.0), 1000.0);
+
+        // Without options: standard roots and the configured currency
+        let plain = ledger_from_source("2024-01-01 open Assets:Cash\n").await;
+        assert_eq!(plain.title(), None);
+        assert_eq!(plain.report_currency(), Config::default().currency.default_currency);
+    }
+
+    /// (id, date, account, units) of every posting of the synthesized pad transactions
+    fn pad_postings(ledger: &Ledger) -> Vec<(String, String, String, String)> {
+        ledger.all_transactions().iter()
+            .filter(|tx| integrity::is_pad_transaction(tx))
+            .flat_map(|tx| tx.postings.iter().map(move |p| (tx.id.clone(), tx.date.clone(), p.account.clone(), format!("{} {}", p.amount, p.currency))))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pad_assets() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Bank:Savings
+2024-01-01 open Expenses:Food
+2024-01-01 open Equity:Opening
+2024-01-02 * "Cafe" "Lunch"
+  Expenses:Food  20.00 CNY
+  Assets:Bank  -20.00 CNY
+2024-01-03 * "Deposit"
+  Assets:Bank:Savings  100.00 CNY
+  Equity:Opening  -100.00 CNY
+2024-01-05 pad Assets:Bank Equity:Opening
+2024-01-05 pad Assets:Bank Equity:Opening
+2024-01-08 * "Cafe" "Dinner"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+2024-01-10 balance Assets:Bank  1000.00 CNY
+2024-01-10 balance Assets:Bank  50 USD
+2024-01-20 balance Assets:Bank  1000.00 CNY
+2024-02-01 pad Assets:Bank Equity:Opening
+"#).await;
+        // The second pad of the day takes over; the balance includes the
+        // sub-account and the dinner after the pad; one transaction per currency
+        assert_eq!(pad_postings(&ledger), vec![
+            ("pad-2024-01-05-1".to_string(), "2024-01-05".to_string(), "Assets:Bank".to_string(), "950 CNY".to_string()),
+            ("pad-2024-01-05-1".to_string(), "2024-01-05".to_string(), "Equity:Opening".to_string(), "-950 CNY".to_string()),
+            ("pad-2024-01-05-2".to_string(), "2024-01-05".to_string(), "Assets:Bank".to_string(), "50 USD".to_string()),
+            ("pad-2024-01-05-2".to_string(), "2024-01-05".to_string(), "Equity:Opening".to_string(), "-50 USD".to_string()),
+        ]);
+        // Later assertions are checked against the padded balance
+        let checks = ledger.check_balances();
+        assert!(checks.iter().all(|c| c.passed), "{:?}", checks);
+        assert_eq!((checks[2].date.as_str(), checks[2].computed, checks[2].padded), ("2024-01-20", Decimal::new(1000, 0), false));
+        // A pad without a following assertion inserts nothing
+        assert_eq!(ledger.all_pads().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_pad_liabilities() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Liabilities:CreditCard
+2024-01-01 open Expenses:Fees
+2024-01-01 open Expenses:Food
+2024-01-03 * "Market" "Groceries"
+  Expenses:Food  200.00 CNY
+  Liabilities:CreditCard  -200.00 CNY
+2024-01-31 pad Liabilities:CreditCard Expenses:Fees
+2024-02-01 balance Liabilities:CreditCard  -215.50 CNY
+2024-02-05 * "Market" "Groceries"
+  Expenses:Food  100.00 CNY
+  Liabilities:CreditCard  -100.00 CNY
+2024-02-10 pad Liabilities:CreditCard Expenses:Fees
+2024-03-01 balance Liabilities:CreditCard  -315.50 CNY
+"#).await;
+        // Fees owed grow the debt; the second pad finds nothing to fill
+        assert_eq!(pad_postings(&ledger), vec![
+            ("pad-2024-01-31-1".to_string(), "2024-01-31".to_string(), "Liabilities:CreditCard".to_string(), "-15.5 CNY".to_string()),
+            ("pad-2024-01-31-1".to_string(), "2024-01-31".to_string(), "Expenses:Fees".to_string(), "15.5 CNY".to_string()),
+        ]);
+        assert_eq!(ledger.calculate_account_balances()["Expenses:Fees"], 15.5);
+    }
+
+    #[tokio::test]
+    async fn test_pad_income() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:MoneyMarket
+2024-01-01 open Assets:Bank
+2024-01-01 open Income:Interest
+2024-01-01 balance Assets:MoneyMarket  0 CNY
+2024-01-02 * "Transfer in"
+  Assets:MoneyMarket  10000.00 CNY
+  Assets:Bank  -10000.00 CNY
+2024-01-31 pad Assets:MoneyMarket Income:Interest
+2024-02-01 balance Assets:MoneyMarket  10012.34 CNY
+2024-02-15 * "Transfer out"
+  Assets:MoneyMarket  -5000.00 CNY
+  Assets:Bank  5000.00 CNY
+2024-02-28 pad Assets:MoneyMarket Income:Interest
+2024-03-01 balance Assets:MoneyMarket  5020.01 CNY
+"#).await;
+        // Only the interest earned between assertions is inserted, not the transfers
+        let pads = pad_postings(&ledger);
+        assert_eq!(pads.len(), 4);
+        assert_eq!((pads[0].2.as_str(), pads[0].3.as_str()), ("Assets:MoneyMarket", "12.34 CNY"));
+        assert_eq!((pads[1].2.as_str(), pads[1].3.as_str()), ("Income:Interest", "-12.34 CNY"));
+        assert_eq!((pads[3].0.as_str(), pads[3].3.as_str()), ("pad-2024-02-28-1", "-7.67 CNY"));
+        assert_eq!(ledger.calculate_account_balances()["Income:Interest"], -20.01);
+        let report = ledger.income_expense_report();
+        assert_eq!(report.total_income, "20.01");
+    }
+
+    #[tokio::test]
+    async fn test_amount_expressions() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Cash USD
+2024-01-01 open Expenses:Fun USD
+2024-01-01 open Income:Gifts USD
+2024-01-02 * "Aunt" "Gift"
+  Assets:Cash  (10.00 + 2.50) * 8 USD
+  Income:Gifts
+2024-01-05 * "Cinema" "Two tickets"
+  Expenses:Fun  2 * 15.00 USD
+  Assets:Cash
+2024-01-06 balance Assets:Cash 100 - 2 * 15.00 USD
+"#).await;
+        let amounts: Vec<Option<Decimal>> = ledger.all_transactions().iter()
+            .map(|tx| tx.postings[0].amount_decimal())
+            .collect();
+        assert_eq!(amounts, vec![Some(Decimal::from(100)), Some(Decimal::from(30))]);
+        let checks = ledger.check_balances();
+        assert_eq!(checks.len(), 1);
+        assert!(checks[0].passed, "{:?}", checks[0]);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Wallet
+2024-01-01 open Expenses:Food
+2024-01-01 open Income:Salary
+2024-01-01 open Assets:Old
+2024-02-01 close Assets:Old
+
+2024-01-05 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary
+
+2024-01-10 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank  -20.00 CNY
+
+2024-01-20 * "Cash"
+  Assets:Wallet  50.00 CNY
+  Assets:Bank  -50.00 CNY
+
+2024-02-10 * "Late"
+  Assets:Old  5.00 CNY
+  Assets:Bank
+
+2024-02-01 balance Assets:Bank  930.00 CNY
+2024-02-01 balance Assets:Wallet  40.00 CNY
+"#).await;
+
+        let report = ledger.integrity_check();
+        let kinds: Vec<(IntegrityIssueKind, &str)> = report.issues.iter()
+            .map(|i| (i.kind, i.account.as_str()))
+            .collect();
+        assert_eq!(kinds, vec![
+            (IntegrityIssueKind::Unbalanced, "Expenses:Food"),
+            (IntegrityIssueKind::BalanceAssertion, "Assets:Wallet"),
+            (IntegrityIssueKind::ClosedAccount, "Assets:Old"),
+        ]);
+
+        // Same errors, same fingerprint; fixing one changes it
+        assert_eq!(ledger.integrity_check().fingerprint, report.fingerprint);
+        let fixed = IntegrityReport::new(report.issues[1..].to_vec());
+        assert_ne!(fixed.fingerprint, report.fingerprint);
+        assert_eq!(fixed.resolved_since(&report).len(), 1);
+        assert!(fixed.added_since(&report).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_balance_report_signs() {
+        let source = r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Liabilities:CreditCard CNY
+2024-02-01 balance Assets:Bank 1000.00 CNY
+2024-02-01 balance Liabilities:CreditCard -200.00 CNY
+"#;
+        let ledger = ledger_from_source(source).await;
+        let report = ledger.balance_report();
+        assert_eq!(report.total_liabilities.parse::<f64>().unwrap(), 200.0);
+        assert_eq!(report.net_worth.parse::<f64>().unwrap(), 800.0);
+        let card = report.entries.iter().find(|e| e.account == "Liabilities:CreditCard").unwrap();
+        assert_eq!(card.balance.parse::<f64>().unwrap(), 200.0);
+
+        let mut config = Config::default();
+        config.currency.sign_convention = SignConvention::Raw;
+        let ledger = ledger_from_source_with_config(source, config).await;
+        let report = ledger.balance_report();
+        assert_eq!(report.total_liabilities.parse::<f64>().unwrap(), -200.0);
+        assert_eq!(report.net_worth.parse::<f64>().unwrap(), 800.0);
+    }
+
+    #[tokio::test]
+    async fn test_balance_report_converts_currencies() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Assets:Wallet
+2024-01-01 open Assets:Crypto
+2024-01-01 open Liabilities:Card USD
+2024-01-01 open Equity:Opening
+2024-01-01 price USD  7.00 CNY
+2024-03-01 price USD  7.20 CNY
+
+2024-01-02 * "Opening"
+  Assets:Bank  1000.00 CNY
+  Equity:Opening
+
+2024-01-02 * "Opening"
+  Assets:Wallet  100.00 CNY
+  Equity:Opening
+
+2024-01-02 * "Opening"
+  Assets:Wallet  50.00 USD
+  Equity:Opening
+
+2024-01-02 * "Opening"
+  Assets:Crypto  1 BTC
+  Equity:Opening
+
+2024-01-05 * "Shop"
+  Expenses:Misc  10.00 USD
+  Liabilities:Card
+"#).await;
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        let report = ledger.balance_report();
+        assert_eq!(report.currency, "CNY");
+
+        let wallet = report.entries.iter().find(|e| e.account == "Assets:Wallet").unwrap();
+        assert!(!wallet.unconverted);
+        assert_eq!(wallet.balance.parse::<f64>().unwrap(), 450.0);
+        assert_eq!(wallet.holdings, vec![Amount::new(Decimal::new(10000, 2), "CNY"), Amount::new(Decimal::new(5000, 2), "USD")]);
+
+        // No BTC price: flagged and left out of the totals
+        let crypto = report.entries.iter().find(|e| e.account == "Assets:Crypto").unwrap();
+        assert!(crypto.unconverted);
+        assert_eq!(report.total_assets.parse::<f64>().unwrap(), 1450.0);
+        assert_eq!(report.total_liabilities.parse::<f64>().unwrap(), 70.0);
+        assert_eq!(report.net_worth.parse::<f64>().unwrap(), 1380.0);
+        assert!(report.net_worth_by_currency.contains(&Amount::new(Decimal::new(4000, 2), "USD")));
+        assert!(report.net_worth_by_currency.contains(&Amount::new(Decimal::ONE, "BTC")));
+
+        // The later price applies once the report date reaches it
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        let wallet = ledger.balance_report().entries.into_iter().find(|e| e.account == "Assets:Wallet").unwrap();
+        assert_eq!(wallet.balance.parse::<f64>().unwrap(), 460.0);
+    }
+
+    #[tokio::test]
+    async fn test_reports_in_original_currencies() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Assets:Wallet USD
+2024-01-01 open Equity:Opening
+2024-01-01 open Income:Salary
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Travel
+2024-01-01 price USD  7.00 CNY
+
+2024-01-02 * "Opening"
+  Assets:Bank  1000.00 CNY
+  Equity:Opening
+2024-01-03 * "Salary"
+  Assets:Wallet  100.00 USD
+  Income:Salary  -100.00 USD
+2024-01-04 * "Lunch"
+  Expenses:Food  30.00 CNY
+  Assets:Bank
+2024-01-05 * "Hotel"
+  Expenses:Travel  20.00 USD
+  Assets:Wallet
+"#).await;
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+
+        // Converted: the wallet counts at 7 CNY per USD
+        assert_eq!(ledger.balance_report().total_assets.parse::<f64>().unwrap(), 970.0 + 560.0);
+        let report = ledger.balance_report_with(ConversionMode::Original);
+        assert_eq!(report.conversion, ConversionMode::Original);
+        let wallet = report.entries.iter().find(|e| e.account == "Assets:Wallet").unwrap();
+        assert_eq!((wallet.balance.as_str(), wallet.currency.as_str()), ("80", "USD"));
+        assert!(!wallet.unconverted);
+        // Totals only cover the operating currency
+        assert_eq!(report.total_assets.parse::<f64>().unwrap(), 970.0);
+
+        let report = ledger.income_expense_report_with(ConversionMode::Original);
+        let travel = report.expense_entries.iter().find(|e| e.account == "Expenses:Travel").unwrap();
+        assert_eq!((travel.amount.as_str(), travel.currency.as_str()), ("20", "USD"));
+        assert_eq!(travel.percentage, 0.0);
+        assert_eq!(report.total_expenses.parse::<f64>().unwrap(), 30.0);
+        assert_eq!(report.total_income.parse::<f64>().unwrap(), 0.0);
+        assert_eq!(report.income_entries[0].currency, "USD");
+        assert_eq!(report.expenses_by_currency, vec![Amount::new(Decimal::new(3000, 2), "CNY"), Amount::new(Decimal::new(2000, 2), "USD")]);
+        assert_eq!(report.income_by_currency, vec![Amount::new(Decimal::new(10000, 2), "USD")]);
+
+        let converted = ledger.income_expense_report();
+        assert_eq!(converted.total_expenses.parse::<f64>().unwrap(), 170.0);
+        assert_eq!(converted.conversion, ConversionMode::Converted);
+    }
+
+    #[tokio::test]
+    async fn test_balance_report_follows_time_range() {
+        let ledger = ledger_from_source(r#"
+2023-01-01 open Assets:Bank CNY
+2023-01-01 open Income:Salary CNY
+2024-06-01 open Assets:Broker CNY
+
+2023-03-01 * "Salary"
+  Assets:Bank  1000.00 CNY
+  Income:Salary
+
+2024-06-15 * "Invest"
+  Assets:Broker  400.00 CNY
+  Assets:Bank  -400.00 CNY
+"#).await;
+
+        let report = ledger.balance_report();
+        assert_eq!(report.total_assets.parse::<f64>().unwrap(), 1000.0);
+        assert!(report.entries.iter().any(|e| e.account == "Assets:Broker"));
+        assert!(report.entries.iter().all(|e| e.account_type != AccountType::Income));
+
+        ledger.set_custom_range(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2023, 12, 31).unwrap());
+        let report = ledger.balance_report();
+        assert_eq!(report.as_of_date, "2023-12-31");
+        let bank = report.entries.iter().find(|e| e.account == "Assets:Bank").unwrap();
+        assert_eq!(bank.balance.parse::<f64>().unwrap(), 1000.0);
+        // Not open yet at the end of 2023
+        assert!(report.entries.iter().all(|e| e.account != "Assets:Broker"));
+    }
+
+    #[tokio::test]
+    async fn test_income_expense_report_signs() {
+        let source = r#"
+2024-01-05 * "Employer" "Salary"
+    Assets:Bank    5000.00 CNY
+    Income:Salary    -5000.00 CNY
+
+2024-01-08 * "Shop" "Shoes"
+    Expenses:Clothing    300.00 CNY
+    Assets:Bank    -300.00 CNY
+
+2024-01-09 * "Shop" "Shoes refund"
+    Expenses:Clothing    -100.00 CNY
+    Assets:Bank    100.00 CNY
+"#;
+        // Natural: income positive, refunds reduce spending
+        let ledger = ledger_from_source(source).await;
+        let report = ledger.income_expense_report();
+        assert_eq!(report.total_income.parse::<f64>().unwrap(), 5000.0);
+        assert_eq!(report.income_entries[0].amount.parse::<f64>().unwrap(), 5000.0);
+        assert_eq!(report.total_expenses.parse::<f64>().unwrap(), 200.0);
+        assert_eq!(report.net_income.parse::<f64>().unwrap(), 4800.0);
+
+        // Raw: income and net income as booked
+        let mut config = Config::default();
+        config.currency.sign_convention = SignConvention::Raw;
+        let ledger = ledger_from_source_with_config(source, config).await;
+        let report = ledger.income_expense_report();
+        assert_eq!(report.total_income.parse::<f64>().unwrap(), -5000.0);
+        assert_eq!(report.income_entries[0].amount.parse::<f64>().unwrap(), -5000.0);
+        assert_eq!(report.total_expenses.parse::<f64>().unwrap(), 200.0);
+        assert_eq!(report.net_income.parse::<f64>().unwrap(), -4800.0);
+        // Percentages don't depend on the convention
+        assert!((report.income_entries[0].percentage - 100.0).abs() < 0.001);
+        // The allocation report always works with natural signs
+        assert_eq!(ledger.allocation_report().net_income, Decimal::from(4800));
+    }
+
+    #[tokio::test]
+    async fn test_income_expense_report_separates_transfers() {
+        let source = r#"
+2024-01-05 * "Employer" "Salary"
+    Assets:Bank    5000.00 CNY
+    Income:Salary    -5000.00 CNY
+
+2024-01-10 * "Card" "Repayment"
+    Liabilities:CreditCard    1200.00 CNY
+    Assets:Bank
+
+2024-01-12 * "Self" "Top up wallet"
+    Assets:Wallet    300.00 CNY
+    Expenses:Transfer    -300.00 CNY
+
+2024-01-20 * "Self" "Savings"
+    Assets:Savings    800.00 CNY
+    Assets:Bank    -800.00 CNY
+"#;
+        let mut config = Config::default();
+        config.reports.transfers.accounts = vec!["Expenses:Transfer".to_string()];
+        let ledger = ledger_from_source_with_config(source, config).await;
+
+        let report = ledger.income_expense_report();
+        assert_eq!(report.total_income.parse::<f64>().unwrap(), 5000.0);
+        assert!(report.expense_entries.is_empty());
+        assert_eq!(report.transfers.count, 3);
+        assert_eq!(report.transfers.volume.parse::<f64>().unwrap(), 2300.0);
+        let top = &report.transfers.entries[0];
+        assert_eq!((top.from_account.as_str(), top.to_account.as_str()), ("Assets:Bank", "Liabilities:CreditCard"));
+
+        let mut config = Config::default();
+        config.reports.transfers.enabled = false;
+        let ledger = ledger_from_source_with_config(source, config).await;
+        let report = ledger.income_expense_report();
+        assert_eq!(report.transfers.count, 0);
+        assert_eq!(report.expense_entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_income_expense_report_mixed_currencies() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 price USD 7.00 CNY
+2024-01-20 price USD 7.20 CNY
+
+2024-01-05 * "Employer" "Salary"
+    Assets:Bank    10000.00 CNY
+    Income:Salary    -10000.00 CNY
+
+2024-01-15 * "Client" "Consulting"
+    Assets:Wise    100.00 USD
+    Income:Consulting    -100.00 USD
+
+2024-01-25 * "Client" "Consulting"
+    Assets:Wise    50.00 USD
+    Income:Consulting    -50.00 USD
+
+2024-01-16 * "Shop" "Lunch"
+    Expenses:Food    70.00 CNY
+    Assets:Bank    -70.00 CNY
+
+2024-01-18 * "Tokyo" "Ramen"
+    Expenses:Food    1000 JPY
+    Assets:Cash    -1000 JPY
+"#).await;
+
+        let report = ledger.income_expense_report();
+        assert_eq!(report.currency, "CNY");
+
+        // USD income converted at the price in effect on each transaction date
+        let consulting = report.income_entries.iter().find(|e| e.account == "Income:Consulting").unwrap();
+        assert_eq!(consulting.currency, "USD");
+        assert!(!consulting.unconverted);
+        assert!((consulting.amount.parse::<f64>().unwrap() - 1060.0).abs() < 0.001);
+        assert!((consulting.original_amount.parse::<f64>().unwrap() - 150.0).abs() < 0.001);
+        assert!((report.total_income.parse::<f64>().unwrap() - 11060.0).abs() < 0.001);
+
+        // No JPY price: kept as a separate, flagged row and left out of the totals
+        let food: Vec<_> = report.expense_entries.iter().filter(|e| e.account == "Expenses:Food").collect();
+        assert_eq!(food.len(), 2);
+        let jpy = food.iter().find(|e| e.currency == "JPY").unwrap();
+        assert!(jpy.unconverted);
+        assert_eq!(jpy.amount, "1000");
+        assert_eq!(jpy.percentage, 0.0);
+        assert!((report.total_expenses.parse::<f64>().unwrap() - 70.0).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_income_expense_report_inverse_price() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 price CNY 0.14 USD
+
+2024-01-15 * "Client" "Consulting"
+    Assets:Wise    14.00 USD
+    Income:Consulting    -14.00 USD
+"#).await;
+
+        let report = ledger.income_expense_report();
+        assert!((report.total_income.parse::<f64>().unwrap() - 100.0).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_future_transactions_excluded_from_balances() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Rent
+
+2024-02-01 * "Rent"
+    Assets:Bank    -100.00 CNY
+    Expenses:Rent    100.00 CNY
+
+2099-02-01 * "Scheduled rent"
+    Assets:Bank    -100.00 CNY
+    Expenses:Rent    100.00 CNY
+"#).await;
+
+        assert_eq!(ledger.calculate_account_balances()["Assets:Bank"], -100.0);
+        assert_eq!(ledger.calculate_account_balances_as_of(None)["Assets:Bank"], -200.0);
+        assert_eq!(ledger.filtered_transaction_count(), 1);
+
+        let upcoming = ledger.upcoming_transactions();
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(upcoming[0].date, "2099-02-01");
+
+        ledger.set_include_future(true);
+        assert_eq!(ledger.calculate_account_balances()["Assets:Bank"], -200.0);
+        assert_eq!(ledger.filtered_transaction_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_account_activity() {
+        let ledger = ledger_from_source(r#"
+2023-01-01 open Assets:Bank
+2023-01-01 open Assets:Cash
+2023-01-01 open Expenses:Food
+
+2023-03-01 * "Old"
+    Assets:Cash    -10.00 CNY
+    Expenses:Food    10.00 CNY
+
+2024-02-01 * "Lunch"
+    Assets:Bank    -20.00 CNY
+    Expenses:Food    20.00 CNY
+"#).await;
+        ledger.set_custom_range(
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        );
+
+        let activity = ledger.account_activity();
+        assert!(activity["Assets:Bank"].has_transactions_in_period);
+        assert!(!activity["Assets:Cash"].has_transactions_in_period);
+        assert_eq!(activity["Expenses:Food"].last_transaction_date.as_deref(), Some("2024-02-01"));
+        assert_eq!(activity["Assets:Cash"].last_transaction_date.as_deref(), Some("2023-03-01"));
+    }
+
+    /// Everything the parser keeps of a transaction, for round-trip comparisons
+    fn transaction_shape(tx: &Transaction) -> String {
+        let postings: Vec<String> = tx.postings.iter()
+            .map(|p| format!("{} {:?} {:?} {:?}", p.account, p.units, p.cost_spec, p.price_spec))
+            .collect();
+        format!("{} {:?} {:?} {:?} {:?} {:?} {} {}", tx.date, tx.flag, tx.payee, tx.narration, tx.tags, tx.links, tx.metadata, postings.join(" | "))
+    }
+
+    #[tokio::test]
+    async fn test_render_transaction_round_trip() {
+        let source = r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Assets:Broker
+2024-01-01 open Expenses:Food
+
+2024-02-01 * "Cafe" "Lunch" #work ^trip-1
+    receipt: "r1.jpg"
+    created_at: "2024-02-01 12:00:00"
+    Expenses:Food    20.00 CNY
+    Assets:Bank
+
+2024-02-02 ! "Coffee"
+    Expenses:Food  3.5 USD @ 7.10 CNY
+    Assets:Bank  -24.85 CNY
+
+2024-02-03 * "Broker" "Buy"
+    Assets:Broker  10 AAPL {150.00 USD, 2024-02-03}
+    Assets:Bank  -1500.00 USD
+"#;
+        let ledger = ledger_from_source(source).await;
+        let originals = ledger.all_transactions();
+        assert_eq!(originals.len(), 3);
+        for tx in &originals {
+            let text = render::transaction(tx, &ledger.options());
+            let reparsed = ledger_from_source(&format!("2024-01-01 open Assets:Bank CNY\n\n{}", text)).await;
+            let copies = reparsed.all_transactions();
+            assert_eq!(copies.len(), 1, "{}", text);
+            assert_eq!(transaction_shape(&copies[0]), transaction_shape(tx), "{}", text);
+            // Canonical: rendering the copy gives the same text
+            assert_eq!(render::transaction(&copies[0], &reparsed.options()), text);
+        }
+
+        let first = render::transaction(originals.iter().find(|t| t.payee == "Cafe").unwrap(), &ledger.options());
+        assert_eq!(first, "2024-02-01 * \"Cafe\" \"Lunch\" #work ^trip-1\n  created_at: \"2024-02-01 12:00:00\"\n  receipt: \"r1.jpg\"\n  Expenses:Food  20.00 CNY\n  Assets:Bank\n");
+    }
+
+    #[tokio::test]
+    async fn test_render_ledger_round_trip() {
+        let source = r#"
+2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Food
+2024-01-01 open Equity:Opening
+2024-01-01 custom "budget" Expenses:Food "monthly" 1500.00 CNY
+2024-01-02 price USD  7.10 CNY
+
+2024-01-05 pad Assets:Bank Equity:Opening
+2024-01-06 balance Assets:Bank  500.00 CNY
+
+2024-02-01 * "Cafe" "Lunch"
+    Expenses:Food    20.00 CNY
+    Assets:Bank
+
+2024-03-01 close Expenses:Food
+"#;
+        let ledger = ledger_from_source(source).await;
+        let text = ledger.render_ledger();
+        let reparsed = ledger_from_source(&text).await;
+
+        let shapes = |l: &Ledger| l.all_transactions().iter().map(transaction_shape).collect::<Vec<_>>();
+        assert_eq!(shapes(&reparsed), shapes(&ledger), "{}", text);
+        assert_eq!(reparsed.all_balances().len(), 1);
+        assert_eq!(reparsed.all_pads().len(), 1);
+        assert_eq!(reparsed.budgets().len(), 1);
+        assert_eq!(reparsed.calculate_account_balances()["Assets:Bank"], ledger.calculate_account_balances()["Assets:Bank"]);
+        assert!(reparsed.accounts().iter().any(|a| a.name == "Expenses:Food" && a.close_date.as_deref() == Some("2024-03-01")));
+        // The pad's transaction comes from the pad directive, not a copy of it
+        assert_eq!(text.matches("Equity:Opening").count(), 2, "{}", text);
+        assert_eq!(reparsed.render_ledger(), text);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_csv_rows() {
+        let ledger = ledger_from_source(r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+
+2024-02-01 * "Cafe, Ltd" "Lunch" #work
+    Expenses:Food    20.00 CNY
+    Assets:Bank
+"#).await;
+        let tx = &ledger.transactions(10, 0)[0];
+        let rows = export::transaction_csv_rows(tx);
+        let lines: Vec<&str> = rows.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"2024-02-01,,*,"Cafe, Ltd",Lunch,Expenses:Food,20.00,CNY"#), "{}", lines[0]);
+        assert!(lines[0].ends_with(",#work,"), "{}", lines[0]);
+        // The elided posting carries the inferred amount
+        assert!(lines[1].contains("Assets:Bank,-20.00,CNY"), "{}", lines[1]);
+        assert_eq!(export::CSV_HEADER.matches(',').count(), lines[0].matches(',').count() - 1);
+    }
+
+    #[tokio::test]
+    async fn test_period_changes() {
+        let ledger = ledger_from_source(r#"
+2023-01-01 open Assets:Bank
+2023-01-01 open Assets:Cash
+2023-01-01 open Expenses:Food
+
+2023-03-01 * "Old"
+    Assets:Cash    -10.00 CNY
+    Expenses:Food    10.00 CNY
+
+2024-02-01 * "Lunch"
+    Assets:Bank    -20.00 CNY
+    Expenses:Food
+
+2024-03-01 * "Dinner"
+    Assets:Bank    -5.50 CNY
+    Expenses:Food    5.50 CNY
+"#).await;
+        ledger.set_custom_range(
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        );
+
+        let changes = ledger.period_changes();
+        assert_eq!(changes["Assets:Bank"]["CNY"], -25.5);
+        // The elided posting counts with its inferred amount
+        assert_eq!(changes["Expenses:Food"]["CNY"], 25.5);
+        assert!(!changes.contains_key("Assets:Cash"));
+    }
+
     #[test]
     fn test_time_context_month() {
         let ctx = TimeContext::new(TimeRange::Month);
@@ -3258,6 +7078,20 @@ mod tests {
         assert!(!ctx.contains(&NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()));
     }
 
+    #[test]
+    fn test_time_context_excludes_future_by_default() {
+        let today = Utc::now().date_naive();
+        let tomorrow = today.succ_opt().unwrap();
+        let mut ctx = TimeContext::new(TimeRange::All);
+        assert_eq!(ctx.as_of(), Some(today));
+        assert!(ctx.contains(&today));
+        assert!(!ctx.contains(&tomorrow));
+
+        ctx.include_future = true;
+        assert_eq!(ctx.as_of(), None);
+        assert!(ctx.contains(&tomorrow));
+    }
+
     #[test]
     fn test_time_context_description() {
         let ctx_month = TimeContext::new(TimeRange::Month);
@@ -3317,18 +7151,24 @@ mod tests {
                 Posting {
                     account: "Expenses:Coffee".to_string(),
                     amount: "-5.00".to_string(),
+                    units: None,
                     currency: "USD".to_string(),
                     cost: None,
+                    cost_spec: None,
                     price: None,
+                    price_spec: None,
                     balance: None,
                     metadata: serde_json::json!({}),
                 },
                 Posting {
                     account: "Assets:Cash".to_string(),
                     amount: "5.00".to_string(),
+                    units: None,
                     currency: "USD".to_string(),
                     cost: None,
+                    cost_spec: None,
                     price: None,
+                    price_spec: None,
                     balance: None,
                     metadata: serde_json::json!({}),
                 },
@@ -3355,9 +7195,12 @@ mod tests {
         let posting = Posting {
             account: "Expenses:Food".to_string(),
             amount: "-25.50".to_string(),
+            units: None,
             currency: "USD".to_string(),
             cost: None,
+            cost_spec: None,
             price: None,
+            price_spec: None,
             balance: None,
             metadata: serde_json::json!({}),
         };
@@ -3380,6 +7223,8 @@ mod tests {
             alias: None,
             note: None,
             tags: vec![],
+            source: None,
+            line: None,
         };
 
         let ctx = TimeContext::new(TimeRange::Month);
@@ -3399,6 +7244,8 @@ mod tests {
             alias: None,
             note: None,
             tags: vec![],
+            source: None,
+            line: None,
         };
 
         assert_eq!(account.short_name(), "Checking:Chase");
@@ -3420,6 +7267,8 @@ mod tests {
             alias: None,
             note: None,
             tags: vec![],
+            source: None,
+            line: None,
         };
 
         assert!(root_account.is_root());
@@ -3440,6 +7289,8 @@ mod tests {
             alias: None,
             note: None,
             tags: vec![],
+            source: None,
+            line: None,
         };
 
         assert_eq!(account.parent_name(), Some("Assets:Checking".to_string()));
@@ -0,0 +1,117 @@
+//! Transactions tied together by `^links`
+//!
+//! A link follows a document through its lifecycle (invoice → payment →
+//! refund). The group's net balance per account shows where it stands: an
+//! account that several of the transactions pass through (the receivable,
+//! the clearing account) nets to zero once the lifecycle is complete, so
+//! anything left there is outstanding.
+
+use crate::integrity::TOLERANCE;
+use crate::{Decimal, Ledger, Transaction};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Net units an account received across a link group
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkBalance {
+    pub account: String,
+    pub currency: String,
+    pub amount: String,
+    /// Number of transactions in the group posting to this account
+    pub transactions: usize,
+}
+
+/// All transactions sharing one link
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkGroup {
+    /// Link name, without the `^`
+    pub link: String,
+    /// Transactions oldest first
+    pub transactions: Vec<Transaction>,
+    /// Non-zero net per account and currency
+    pub balances: Vec<LinkBalance>,
+    /// Balances of accounts shared by two or more transactions; empty when settled
+    pub outstanding: Vec<LinkBalance>,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+}
+
+impl LinkGroup {
+    /// Every account the transactions pass through nets to zero
+    pub fn is_settled(&self) -> bool {
+        self.outstanding.is_empty()
+    }
+}
+
+/// Units per (account, currency) of one transaction, elided postings inferred
+pub(crate) fn posting_units(tx: &Transaction) -> Vec<((String, String), Decimal)> {
+    let mut units = Vec::new();
+    for posting in &tx.postings {
+        if let Some(amount) = posting.amount_decimal().filter(|_| !posting.amount.is_empty()) {
+            units.push(((posting.account.clone(), posting.currency.clone()), amount));
+        }
+    }
+    if let Some(elided) = tx.postings.iter().find(|p| p.amount.is_empty()) {
+        for (currency, residual) in crate::integrity::residuals(tx) {
+            if residual.abs() >= TOLERANCE {
+                units.push(((elided.account.clone(), currency), -residual));
+            }
+        }
+    }
+    units
+}
+
+impl Ledger {
+    /// Transactions carrying `link` (with or without the leading `^`), oldest first
+    pub fn transactions_by_link(&self, link: &str) -> Vec<Transaction> {
+        let link = link.trim().trim_start_matches('^');
+        let data = self.data.read().unwrap();
+        let mut transactions: Vec<Transaction> = data.index.by_link(link).iter()
+            .map(|i| data.transactions[*i].clone())
+            .collect();
+        drop(data);
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.time.cmp(&b.time)));
+        transactions
+    }
+
+    /// Transactions carrying `link` (with or without the leading `^`), with their net balance
+    pub fn link_group(&self, link: &str) -> LinkGroup {
+        let link = link.trim().trim_start_matches('^').to_string();
+        let transactions = self.transactions_by_link(&link);
+
+        let mut totals: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+        let mut seen_in: HashMap<String, usize> = HashMap::new();
+        for tx in &transactions {
+            let units = posting_units(tx);
+            let mut accounts: Vec<&String> = units.iter().map(|((account, _), _)| account).collect();
+            accounts.sort();
+            accounts.dedup();
+            for account in accounts {
+                *seen_in.entry(account.clone()).or_insert(0) += 1;
+            }
+            for (key, amount) in units {
+                *totals.entry(key).or_default() += amount;
+            }
+        }
+
+        let balances: Vec<LinkBalance> = totals.into_iter()
+            .filter(|(_, amount)| amount.abs() >= TOLERANCE)
+            .map(|((account, currency), amount)| LinkBalance {
+                transactions: seen_in.get(&account).copied().unwrap_or(0),
+                account,
+                currency,
+                amount: format!("{:.2}", amount),
+            })
+            .collect();
+        let outstanding = balances.iter().filter(|b| b.transactions > 1).cloned().collect();
+
+        LinkGroup {
+            first_date: transactions.first().map(|tx| tx.date.clone()),
+            last_date: transactions.last().map(|tx| tx.date.clone()),
+            link,
+            transactions,
+            balances,
+            outstanding,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Asset accounts with a negative balance
+//!
+//! Money on hand can't go below zero, so a negative asset balance is almost
+//! always a sign error in data entry. Balances are the ones the accounts page
+//! shows (up to the today horizon, per currency); for each negative one the
+//! account's postings are replayed in date order through the transaction
+//! index to find when it last went below zero and which postings took it
+//! there, most recent first.
+
+use crate::integrity::TOLERANCE;
+use crate::{links, AccountType, Ledger};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// Postings listed per negative balance
+const CAUSES_SHOWN: usize = 3;
+
+/// An asset account below zero in one currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegativeBalance {
+    pub account: String,
+    pub currency: String,
+    pub balance: f64,
+    /// Date the running balance last went below zero, None when the postings
+    /// never did (the balance directives account for it)
+    pub since: Option<String>,
+    /// Postings that lowered the balance since then, newest first
+    pub causes: Vec<NegativeCause>,
+}
+
+/// A transaction that lowered a negative balance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegativeCause {
+    pub transaction_id: String,
+    pub date: String,
+    pub payee: String,
+    pub narration: String,
+    /// Units posted to the account, negative
+    pub amount: f64,
+}
+
+impl Ledger {
+    /// Negative asset balances, sorted by account and currency
+    pub fn negative_balances(&self) -> Vec<NegativeBalance> {
+        let as_of = self.as_of_date();
+        let holdings = self.account_holdings_as_of(as_of);
+        let data = self.data.read().unwrap();
+
+        let mut negative = Vec::new();
+        for account in data.accounts.iter().filter(|a| a.account_type == AccountType::Assets) {
+            let Some(amounts) = holdings.get(&account.name) else { continue };
+            for (currency, balance) in amounts.iter().filter(|(_, amount)| **amount <= -TOLERANCE) {
+                let mut transactions: Vec<_> = data.index.by_account(&account.name).iter()
+                    .map(|i| &data.transactions[*i])
+                    .filter(|tx| !tx.is_upcoming(as_of))
+                    .collect();
+                transactions.sort_by(|a, b| a.date.cmp(&b.date));
+
+                let mut running = crate::Decimal::ZERO;
+                let mut since = None;
+                let mut causes: Vec<NegativeCause> = Vec::new();
+                for tx in transactions {
+                    for ((posted, posted_currency), amount) in links::posting_units(tx) {
+                        if posted != account.name || &posted_currency != currency {
+                            continue;
+                        }
+                        let before = running;
+                        running += amount;
+                        if running > -TOLERANCE {
+                            since = None;
+                            causes.clear();
+                            continue;
+                        }
+                        if before > -TOLERANCE {
+                            since = Some(tx.date.clone());
+                        }
+                        if amount.is_sign_negative() {
+                            causes.push(NegativeCause {
+                                transaction_id: tx.id.clone(),
+                                date: tx.date.clone(),
+                                payee: tx.payee.clone(),
+                                narration: tx.narration.clone(),
+                                amount: amount.to_f64().unwrap_or(0.0),
+                            });
+                            if causes.len() > CAUSES_SHOWN {
+                                causes.remove(0);
+                            }
+                        }
+                    }
+                }
+                causes.reverse();
+                negative.push(NegativeBalance {
+                    account: account.name.clone(),
+                    currency: currency.clone(),
+                    balance: balance.to_f64().unwrap_or(0.0),
+                    since,
+                    causes,
+                });
+            }
+        }
+        negative.sort_by(|a, b| a.account.cmp(&b.account).then_with(|| a.currency.cmp(&b.currency)));
+        negative
+    }
+}
@@ -0,0 +1,105 @@
+//! Notes, documents, events and queries
+//!
+//! Directives that don't move money but are part of the record:
+//! - `note`: a dated remark on an account ("called the bank about the fee")
+//! - `document`: a file (statement, contract) belonging to an account; the
+//!   path is relative to the ledger file it appears in, or absolute
+//! - `event`: the value of a variable from a date on (`location`,
+//!   `employer`), shown on the timeline
+//! - `query`: a named BQL query, kept for listing; queries are not run
+//!
+//! Each keeps the file and line it was read from.
+
+use crate::Ledger;
+use serde::{Deserialize, Serialize};
+
+/// A `note` directive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountNote {
+    pub date: String,
+    pub account: String,
+    pub comment: String,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// A `document` directive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountDocument {
+    pub date: String,
+    pub account: String,
+    /// As written in the directive
+    pub filename: String,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// An `event` directive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEvent {
+    pub date: String,
+    pub event_type: String,
+    pub description: String,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// A `query` directive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredQuery {
+    pub date: String,
+    pub name: String,
+    pub query: String,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// `account` itself or one of its sub-accounts
+fn within(name: &str, account: &str) -> bool {
+    name == account || name.strip_prefix(account).is_some_and(|rest| rest.starts_with(':'))
+}
+
+impl Ledger {
+    /// Notes on `account` and its sub-accounts, newest first
+    pub fn account_notes(&self, account: &str) -> Vec<AccountNote> {
+        let mut notes: Vec<AccountNote> = self.data.read().unwrap().notes.iter()
+            .filter(|n| within(&n.account, account))
+            .cloned()
+            .collect();
+        notes.sort_by(|a, b| b.date.cmp(&a.date));
+        notes
+    }
+
+    /// Documents of `account` and its sub-accounts, newest first
+    pub fn account_documents(&self, account: &str) -> Vec<AccountDocument> {
+        let mut documents: Vec<AccountDocument> = self.data.read().unwrap().documents.iter()
+            .filter(|d| within(&d.account, account))
+            .cloned()
+            .collect();
+        documents.sort_by(|a, b| b.date.cmp(&a.date));
+        documents
+    }
+
+    /// Events, newest first; only those of `event_type` when given
+    pub fn events(&self, event_type: Option<&str>) -> Vec<LedgerEvent> {
+        let mut events: Vec<LedgerEvent> = self.data.read().unwrap().events.iter()
+            .filter(|e| event_type.is_none_or(|t| e.event_type == t))
+            .cloned()
+            .collect();
+        events.sort_by(|a, b| b.date.cmp(&a.date));
+        events
+    }
+
+    /// Event types in use, sorted
+    pub fn event_types(&self) -> Vec<String> {
+        let mut types: Vec<String> = self.data.read().unwrap().events.iter().map(|e| e.event_type.clone()).collect();
+        types.sort();
+        types.dedup();
+        types
+    }
+
+    /// Stored queries in file order
+    pub fn queries(&self) -> Vec<StoredQuery> {
+        self.data.read().unwrap().queries.clone()
+    }
+}
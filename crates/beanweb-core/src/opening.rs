@@ -0,0 +1,201 @@
+//! Opening balances of a new ledger
+//!
+//! Starting a ledger means recording today's real-world balances once: one
+//! transaction on the start date moves every asset and liability balance in
+//! from `Equity:Opening-Balances`, and every account it touches needs an
+//! `open` directive first. Given the balances, this writes both to
+//! `opening.bean` and includes it from the main file:
+//! - Accounts holding several currencies open with all of them, and the
+//!   equity side gets one posting per currency so the transaction balances
+//! - Accounts that are already open are reused, but must open on or before
+//!   the start date
+//! - Zero balances still open their account but add no posting
+
+use crate::balance_import::{parse_number, validate_target};
+use crate::bootstrap::{add_include, is_valid_currency};
+use crate::{AccountType, CoreError, Decimal, Ledger};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Equity account the opening balances are booked against
+pub const OPENING_BALANCES_ACCOUNT: &str = "Equity:Opening-Balances";
+
+/// File the opening entries are written to unless another one is chosen
+pub const DEFAULT_OPENING_FILE: &str = "opening.bean";
+
+/// Real-world balance of one account in one currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpeningBalance {
+    pub account: String,
+    /// "1,234.50" or "-800"; liabilities are usually negative
+    pub amount: String,
+    /// Default currency when omitted
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// Directives generating the opening balances, before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpeningBalancesPlan {
+    pub date: String,
+    /// Accounts that need an `open` directive, with their currencies
+    pub opens: Vec<(String, Vec<String>)>,
+    /// (account, amount, currency) of the non-zero balances
+    pub postings: Vec<(String, String, String)>,
+    /// Equity side per currency
+    pub equity: Vec<(String, String)>,
+    /// The generated beancount source
+    pub source: String,
+}
+
+/// Result of writing the opening balances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpeningBalancesOutcome {
+    /// Written file, relative to the data directory
+    pub file: String,
+    /// Whether an `include` was added to the main file
+    pub include_added: bool,
+    pub plan: OpeningBalancesPlan,
+}
+
+impl OpeningBalancesPlan {
+    fn render(&mut self) {
+        let mut out = format!(";; Opening balances ({})\n", self.date);
+        for (account, currencies) in &self.opens {
+            out.push_str(&format!("{} open {} {}\n", self.date, account, currencies.join(",")));
+        }
+        if !self.postings.is_empty() {
+            if !self.opens.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{} * \"Opening balances\"\n", self.date));
+            let width = self.postings.iter().map(|(a, _, _)| a.len())
+                .chain(std::iter::once(OPENING_BALANCES_ACCOUNT.len()))
+                .max()
+                .unwrap_or(0);
+            for (account, amount, currency) in &self.postings {
+                out.push_str(&format!("  {:width$}  {} {}\n", account, amount, currency, width = width));
+            }
+            for (amount, currency) in &self.equity {
+                out.push_str(&format!("  {:width$}  {} {}\n", OPENING_BALANCES_ACCOUNT, amount, currency, width = width));
+            }
+        }
+        self.source = out;
+    }
+}
+
+impl Ledger {
+    /// Validate the balances and build the directives without writing anything
+    pub fn plan_opening_balances(&self, date: &str, balances: &[OpeningBalance]) -> Result<OpeningBalancesPlan, CoreError> {
+        let invalid = |message: String| CoreError::ValidationError { message };
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(invalid(format!("Invalid date: {}", date)));
+        }
+        if balances.is_empty() {
+            return Err(invalid("No opening balances given".to_string()));
+        }
+
+        let default_currency = &self.report_currency();
+        let data = self.data.read().unwrap();
+        let mut currencies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut postings = Vec::new();
+        // Per currency: the negated sum, with as many decimals as the
+        // most precise amount
+        let mut equity: BTreeMap<String, Decimal> = BTreeMap::new();
+
+        for balance in balances {
+            let account = balance.account.trim();
+            if !(self.options.is(account, AccountType::Assets) || self.options.is(account, AccountType::Liabilities)) {
+                return Err(invalid(format!("Not an asset or liability account: {}", account)));
+            }
+            let currency = balance.currency.as_deref().map(str::trim).filter(|c| !c.is_empty()).unwrap_or(default_currency);
+            if !is_valid_currency(currency) {
+                return Err(invalid(format!("Invalid currency: {}", currency)));
+            }
+            let amount = parse_number(&balance.amount)
+                .ok_or_else(|| invalid(format!("Invalid amount for {}: {}", account, balance.amount)))?;
+            let held = currencies.entry(account.to_string()).or_default();
+            if held.iter().any(|c| c == currency) {
+                return Err(invalid(format!("Duplicate balance for {} in {}", account, currency)));
+            }
+            held.push(currency.to_string());
+
+            let value: Decimal = amount.parse()
+                .map_err(|_| invalid(format!("Invalid amount for {}: {}", account, balance.amount)))?;
+            if !value.is_zero() {
+                *equity.entry(currency.to_string()).or_default() -= value;
+                postings.push((account.to_string(), amount, currency.to_string()));
+            }
+        }
+        if !equity.is_empty() {
+            currencies.insert(OPENING_BALANCES_ACCOUNT.to_string(), equity.keys().cloned().collect());
+        }
+
+        let mut opens = Vec::new();
+        for (account, held) in currencies {
+            match data.accounts.iter().find(|a| a.name == account) {
+                Some(existing) => {
+                    if let Some(open) = existing.open_date.as_deref().filter(|open| *open > date) {
+                        return Err(invalid(format!("{} opens on {}, after {}", account, open, date)));
+                    }
+                    if existing.close_date.as_deref().is_some_and(|close| close <= date) {
+                        return Err(invalid(format!("{} is closed on {}", account, date)));
+                    }
+                }
+                None => opens.push((account, held)),
+            }
+        }
+        drop(data);
+
+        let mut plan = OpeningBalancesPlan {
+            date: date.to_string(),
+            opens,
+            postings,
+            equity: equity.into_iter()
+                .map(|(currency, mut value)| {
+                    if value.is_zero() {
+                        value.set_sign_positive(true);
+                    }
+                    (value.to_string(), currency)
+                })
+                .collect(),
+            source: String::new(),
+        };
+        plan.render();
+        Ok(plan)
+    }
+
+    /// Write the opening balances to `file` (relative to the data directory)
+    /// and include it from the main file
+    pub fn write_opening_balances(&self, date: &str, balances: &[OpeningBalance], file: &str) -> Result<OpeningBalancesOutcome, CoreError> {
+        let file = validate_target(file)?;
+        let plan = self.plan_opening_balances(date, balances)?;
+
+        let main_path = self.config.ledger_path();
+        let path = self.config.data.path.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| CoreError::IoError)?;
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(current) if !current.trim().is_empty() => format!("{}\n\n{}", current.trim_end(), plan.source),
+            _ => plan.source.clone(),
+        };
+        self.write_document(&path.to_string_lossy(), &content)?;
+
+        let include_added = if path == main_path {
+            false
+        } else {
+            let main = std::fs::read_to_string(&main_path).unwrap_or_default();
+            match add_include(&main, file) {
+                Some(updated) => {
+                    self.write_document(&main_path.to_string_lossy(), &updated)?;
+                    true
+                }
+                None => false,
+            }
+        };
+
+        Ok(OpeningBalancesOutcome { file: file.to_string(), include_added, plan })
+    }
+}
@@ -0,0 +1,75 @@
+//! Several operating currencies side by side
+//!
+//! `option "operating_currency"` may appear more than once; a household
+//! keeping CNY and USD wants both columns. Reports are in the first one (the
+//! report currency, `currency.default_currency` when there is none), and with
+//! converted amounts they carry every other operating currency as well:
+//! - Each balance and income/expense entry lists its amount in the other
+//!   operating currencies (`operating`), converted through the price database
+//!   at the report date or the transaction date like the main amount
+//! - `operating_totals` has the report totals in each of them
+//! - An amount without a price into one of them is left out of that column
+//!   and its total, as unconverted rows are left out of the main totals
+//!
+//! With a single operating currency, or original currencies selected, both
+//! stay empty.
+
+use crate::{Amount, Decimal, Ledger};
+use serde::{Deserialize, Serialize};
+
+/// Balance report totals in one operating currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceTotals {
+    pub currency: String,
+    pub total_assets: String,
+    pub total_liabilities: String,
+    pub net_worth: String,
+}
+
+/// Income/expense report totals in one operating currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomeExpenseTotals {
+    pub currency: String,
+    pub total_income: String,
+    pub total_expenses: String,
+    pub net_income: String,
+}
+
+/// Add `more` into `into`, currency by currency
+pub(crate) fn add_amounts(into: &mut Vec<Amount>, more: &[Amount]) {
+    for amount in more {
+        match into.iter_mut().find(|a| a.currency == amount.currency) {
+            Some(existing) => existing.number += amount.number,
+            None => into.push(amount.clone()),
+        }
+    }
+}
+
+/// `sums` (one per currency, None when unconvertible) as amounts, rounded
+/// to cents
+pub(crate) fn operating_amounts(currencies: &[String], sums: &[Option<Decimal>]) -> Vec<Amount> {
+    currencies.iter().zip(sums)
+        .filter_map(|(currency, sum)| sum.map(|sum| Amount::new(sum.round_dp(2), currency.clone())))
+        .collect()
+}
+
+/// Sum of the amounts in `currency` across `amounts`
+pub(crate) fn total_in<'a>(amounts: impl Iterator<Item = &'a Vec<Amount>>, currency: &str) -> Decimal {
+    amounts.flatten().filter(|a| a.currency == currency).map(|a| a.number).sum()
+}
+
+/// Operating currencies `declared` in the ledger besides `report_currency`,
+/// in file order
+pub(crate) fn others(report_currency: &str, declared: &[String]) -> Vec<String> {
+    declared.iter().filter(|c| *c != report_currency).cloned().collect()
+}
+
+impl Ledger {
+    /// The report currency, then the ledger's other `operating_currency` options
+    pub fn operating_currencies(&self) -> Vec<String> {
+        let report_currency = self.report_currency();
+        let mut currencies = others(&report_currency, &self.data.read().unwrap().operating_currencies);
+        currencies.insert(0, report_currency);
+        currencies
+    }
+}
@@ -0,0 +1,125 @@
+//! Ledger options
+//!
+//! `option "key" "value"` lines configure the ledger itself. Beanweb uses:
+//! - `title`: shown in the page header instead of "Beanweb"
+//! - `operating_currency`: the first one is the report currency, overriding
+//!   `currency.default_currency` of the config (see [`operating`](crate::operating)
+//!   for the others)
+//! - `name_assets`, `name_liabilities`, `name_equity`, `name_income`,
+//!   `name_expenses`: renamed root accounts (`Vermoegen` for `Assets`);
+//!   accounts are classified by their root
+//!
+//! Every option is kept, so `/api/options` lists the ones beanweb ignores too.
+
+use crate::{AccountType, Ledger, LedgerData};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Options of the loaded ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerOptions {
+    pub title: Option<String>,
+    pub name_assets: String,
+    pub name_liabilities: String,
+    pub name_equity: String,
+    pub name_income: String,
+    pub name_expenses: String,
+    /// Every option as written, values in file order
+    pub values: BTreeMap<String, Vec<String>>,
+}
+
+/// Beancount's name for the root of `account_type`
+pub(crate) fn standard_root(account_type: AccountType) -> &'static str {
+    match account_type {
+        AccountType::Assets => "Assets",
+        AccountType::Liabilities => "Liabilities",
+        AccountType::Equity => "Equity",
+        AccountType::Income => "Income",
+        AccountType::Expenses => "Expenses",
+    }
+}
+
+impl Default for LedgerOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            name_assets: standard_root(AccountType::Assets).to_string(),
+            name_liabilities: standard_root(AccountType::Liabilities).to_string(),
+            name_equity: standard_root(AccountType::Equity).to_string(),
+            name_income: standard_root(AccountType::Income).to_string(),
+            name_expenses: standard_root(AccountType::Expenses).to_string(),
+            values: BTreeMap::new(),
+        }
+    }
+}
+
+impl LedgerOptions {
+    /// Record `option "key" "value"`; for options given twice the last wins,
+    /// except in `values`
+    pub(crate) fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim().to_string();
+        self.values.entry(key.to_string()).or_default().push(value.clone());
+        if value.is_empty() {
+            return;
+        }
+        match key {
+            "title" => self.title = Some(value),
+            "name_assets" => self.name_assets = value,
+            "name_liabilities" => self.name_liabilities = value,
+            "name_equity" => self.name_equity = value,
+            "name_income" => self.name_income = value,
+            "name_expenses" => self.name_expenses = value,
+            _ => {}
+        }
+    }
+
+    /// Name of the root account of `account_type`
+    pub fn root(&self, account_type: AccountType) -> &str {
+        match account_type {
+            AccountType::Assets => &self.name_assets,
+            AccountType::Liabilities => &self.name_liabilities,
+            AccountType::Equity => &self.name_equity,
+            AccountType::Income => &self.name_income,
+            AccountType::Expenses => &self.name_expenses,
+        }
+    }
+
+    /// Type of `account` by its root; None for a root that isn't one of the five
+    pub fn account_type(&self, account: &str) -> Option<AccountType> {
+        let root = account.split(':').next().unwrap_or(account);
+        [AccountType::Assets, AccountType::Liabilities, AccountType::Equity, AccountType::Income, AccountType::Expenses]
+            .into_iter()
+            .find(|t| self.root(*t) == root)
+    }
+
+    /// Whether `account` is of `account_type`
+    pub fn is(&self, account: &str, account_type: AccountType) -> bool {
+        self.account_type(account) == Some(account_type)
+    }
+}
+
+impl LedgerData {
+    /// The first `operating_currency` option, else `fallback` (the configured
+    /// default currency)
+    pub(crate) fn report_currency(&self, fallback: &str) -> String {
+        self.operating_currencies.first().cloned().unwrap_or_else(|| fallback.to_string())
+    }
+}
+
+impl Ledger {
+    /// Options of the loaded ledger
+    pub fn options(&self) -> LedgerOptions {
+        self.options.clone()
+    }
+
+    /// `option "title"`
+    pub fn title(&self) -> Option<String> {
+        self.options.title.clone()
+    }
+
+    /// Currency reports convert into: the first `operating_currency` option,
+    /// else `currency.default_currency`
+    pub fn report_currency(&self) -> String {
+        self.data.read().unwrap().report_currency(&self.config.currency.default_currency)
+    }
+}
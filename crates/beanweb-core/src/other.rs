@@ -0,0 +1,116 @@
+//! Small categories collapsed into "Other"
+//!
+//! With `reports.other_threshold` set (a percentage), report entries that
+//! contribute less than that share of their total are merged into a single
+//! [`OTHER`] entry placed last. The merged entries are kept in its `members`,
+//! so charts and tables show one row that can be expanded:
+//! - Only entries counted in the totals are merged; unconverted ones, and with
+//!   [`ConversionMode::Original`] those in other currencies, have no share
+//! - A single small entry is left alone, "Other" always covers at least two
+
+use crate::{decimal_string, parse_decimal, CategoryBreakdown, ConversionMode, Decimal, IncomeExpenseEntry, IncomeExpenseReport, Ledger};
+
+/// Account and category name of the merged entry
+pub const OTHER: &str = "Other";
+
+/// Indices of the entries to merge: converted and below `threshold` percent
+fn small_indices(shares: &[(f64, bool)], threshold: f64) -> Vec<usize> {
+    if threshold <= 0.0 {
+        return Vec::new();
+    }
+    let small: Vec<usize> = shares.iter().enumerate()
+        .filter(|(_, (percentage, convertible))| *convertible && *percentage < threshold)
+        .map(|(i, _)| i)
+        .collect();
+    if small.len() < 2 { Vec::new() } else { small }
+}
+
+/// Split `entries` into the kept ones and the ones to merge
+fn partition<T>(entries: Vec<T>, small: &[usize]) -> (Vec<T>, Vec<T>) {
+    let (mut kept, mut members) = (Vec::new(), Vec::new());
+    for (i, entry) in entries.into_iter().enumerate() {
+        if small.contains(&i) { members.push(entry) } else { kept.push(entry) }
+    }
+    (kept, members)
+}
+
+/// Merge income/expense entries below `threshold` percent into one "Other" entry
+pub fn collapse_entries(entries: Vec<IncomeExpenseEntry>, threshold: f64, currency: &str, mode: ConversionMode) -> Vec<IncomeExpenseEntry> {
+    let shares: Vec<(f64, bool)> = entries.iter().map(|e| (e.percentage, e.in_totals(currency, mode))).collect();
+    let small = small_indices(&shares, threshold);
+    if small.is_empty() {
+        return entries;
+    }
+    let (mut kept, members) = partition(entries, &small);
+    let amount: Decimal = members.iter().map(|e| parse_decimal(&e.amount)).sum();
+    let mut operating = Vec::new();
+    for member in &members {
+        crate::operating::add_amounts(&mut operating, &member.operating);
+    }
+    kept.push(IncomeExpenseEntry {
+        account: OTHER.to_string(),
+        amount: decimal_string(amount),
+        percentage: members.iter().map(|e| e.percentage).sum(),
+        category: OTHER.to_string(),
+        currency: currency.to_string(),
+        original_amount: decimal_string(amount),
+        unconverted: false,
+        members,
+        operating,
+    });
+    kept
+}
+
+/// Merge category breakdowns below `threshold` percent into one "Other" breakdown
+pub fn collapse_breakdowns(breakdowns: Vec<CategoryBreakdown>, threshold: f64) -> Vec<CategoryBreakdown> {
+    let shares: Vec<(f64, bool)> = breakdowns.iter().map(|b| (b.percentage, true)).collect();
+    let small = small_indices(&shares, threshold);
+    if small.is_empty() {
+        return breakdowns;
+    }
+    let (mut kept, members) = partition(breakdowns, &small);
+    let amount: Decimal = members.iter().map(|b| parse_decimal(&b.amount)).sum();
+    kept.push(CategoryBreakdown {
+        category: OTHER.to_string(),
+        amount: decimal_string(amount),
+        count: members.iter().map(|b| b.count).sum(),
+        percentage: members.iter().map(|b| b.percentage).sum(),
+        members,
+    });
+    kept
+}
+
+impl Ledger {
+    /// Apply the configured "Other" threshold to both sides of a report
+    pub(crate) fn collapse_small_entries(&self, report: IncomeExpenseReport) -> IncomeExpenseReport {
+        let threshold = self.config.reports.other_threshold;
+        IncomeExpenseReport {
+            income_entries: collapse_entries(report.income_entries, threshold, &report.currency, report.conversion),
+            expense_entries: collapse_entries(report.expense_entries, threshold, &report.currency, report.conversion),
+            ..report
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakdown(category: &str, amount: &str, percentage: f64) -> CategoryBreakdown {
+        CategoryBreakdown { category: category.to_string(), amount: amount.to_string(), count: 1, percentage, members: Vec::new() }
+    }
+
+    #[test]
+    fn test_collapse_breakdowns() {
+        let breakdowns = vec![breakdown("Food", "90", 90.0), breakdown("Taxi", "6", 6.0), breakdown("Books", "4", 4.0)];
+        let collapsed = collapse_breakdowns(breakdowns.clone(), 10.0);
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed[1].category, OTHER);
+        assert_eq!(collapsed[1].amount, "10");
+        assert_eq!(collapsed[1].count, 2);
+        assert_eq!(collapsed[1].members.len(), 2);
+        // One small entry alone, or no threshold, changes nothing
+        assert_eq!(collapse_breakdowns(breakdowns.clone(), 5.0).len(), 3);
+        assert_eq!(collapse_breakdowns(breakdowns, 0.0).len(), 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! Padding pass
+//!
+//! `pad ACCOUNT SOURCE` inserts, on its date, the transfer from SOURCE that
+//! makes the next `balance` assertion of ACCOUNT hold. As in beancount:
+//! - a pad covers the first assertion after its date in each currency, up to
+//!   the next pad of the same account
+//! - the inserted amount is the asserted balance minus the balance at the
+//!   start of the assertion's day (sub-accounts and earlier pads included)
+//! - nothing is inserted when the assertion already holds or no assertion
+//!   follows the pad
+//!
+//! Each (pad, currency) pair becomes its own transaction with a unique
+//! `pad-{date}-{n}` id; `pad_source` in its metadata marks it as synthesized
+//! (see [`is_pad_transaction`](crate::integrity::is_pad_transaction)).
+
+use crate::integrity::residuals;
+use crate::{Amount, BalanceEntry, Decimal, PadEntry, Posting, Transaction};
+use std::collections::{HashMap, HashSet};
+
+/// Transactions inserted by `pads`, oldest pad first
+///
+/// One walk over the transactions in date order: before the assertions of a
+/// day are checked, the transactions of earlier days are added to running
+/// balances of every account and its parents.
+pub(crate) fn pad_transactions(pads: &[PadEntry], balances: &[BalanceEntry], transactions: &[Transaction]) -> Vec<Transaction> {
+    let mut pads: Vec<(usize, &PadEntry)> = pads.iter().enumerate().collect();
+    pads.sort_by(|a, b| a.1.date.cmp(&b.1.date));
+    let mut assertions: Vec<&BalanceEntry> = balances.iter().collect();
+    assertions.sort_by(|a, b| a.date.cmp(&b.date));
+    let mut ordered: Vec<&Transaction> = transactions.iter().collect();
+    ordered.sort_by(|a, b| a.date.cmp(&b.date));
+
+    let mut running = RunningBalances::default();
+    // The latest pad of each account, with the currencies it has covered
+    let mut active: HashMap<&str, (usize, &PadEntry, HashSet<&str>)> = HashMap::new();
+    let (mut next_tx, mut next_pad) = (0, 0);
+    let mut inserted: Vec<(usize, Transaction)> = Vec::new();
+    for balance in assertions {
+        // Balances are asserted at the start of the day
+        while next_tx < ordered.len() && ordered[next_tx].date < balance.date {
+            running.add(ordered[next_tx]);
+            next_tx += 1;
+        }
+        // The next pad of the account takes over from its date on
+        while next_pad < pads.len() && pads[next_pad].1.date < balance.date {
+            let (order, pad) = pads[next_pad];
+            active.insert(&pad.account, (order, pad, HashSet::new()));
+            next_pad += 1;
+        }
+
+        let Some((order, pad, covered)) = active.get_mut(balance.account.as_str()) else { continue };
+        if !covered.insert(&balance.currency) {
+            continue;
+        }
+        let difference = balance.units - running.get(&pad.account, &balance.currency);
+        if difference.is_zero() {
+            continue;
+        }
+        let tx = transaction(String::new(), pad, difference, &balance.currency);
+        running.add(&tx);
+        inserted.push((*order, tx));
+    }
+
+    // Ids count the pads' transactions per date, in pad order
+    inserted.sort_by(|a, b| a.1.date.cmp(&b.1.date).then(a.0.cmp(&b.0)));
+    let mut per_date: HashMap<String, usize> = HashMap::new();
+    inserted.into_iter().map(|(_, mut tx)| {
+        let n = per_date.entry(tx.date.clone()).or_default();
+        *n += 1;
+        tx.id = format!("pad-{}-{}", tx.date, n);
+        tx
+    }).collect()
+}
+
+/// Balance per account and currency, sub-accounts included
+#[derive(Default)]
+struct RunningBalances {
+    balances: HashMap<(String, String), Decimal>,
+}
+
+impl RunningBalances {
+    fn add(&mut self, tx: &Transaction) {
+        for posting in &tx.postings {
+            if posting.amount.is_empty() {
+                // Elided amount: the negated residual in each currency
+                for (currency, residual) in residuals(tx) {
+                    self.post(&posting.account, currency, -residual);
+                }
+            } else {
+                self.post(&posting.account, posting.currency.clone(), crate::Ledger::posting_decimal(posting));
+            }
+        }
+    }
+
+    fn post(&mut self, account: &str, currency: String, units: Decimal) {
+        let mut account = account;
+        loop {
+            *self.balances.entry((account.to_string(), currency.clone())).or_default() += units;
+            match account.rfind(':') {
+                Some(end) => account = &account[..end],
+                None => break,
+            }
+        }
+    }
+
+    fn get(&self, account: &str, currency: &str) -> Decimal {
+        self.balances.get(&(account.to_string(), currency.to_string())).copied().unwrap_or_default()
+    }
+}
+
+fn posting(account: &str, units: Decimal, currency: &str) -> Posting {
+    Posting {
+        account: account.to_string(),
+        amount: units.normalize().to_string(),
+        units: Some(Amount::new(units, currency.to_string())),
+        currency: currency.to_string(),
+        cost: None,
+        cost_spec: None,
+        price: None,
+        price_spec: None,
+        balance: None,
+        metadata: serde_json::Value::Object(serde_json::Map::new()),
+    }
+}
+
+/// `difference` moved from the pad's source into its account
+fn transaction(id: String, pad: &PadEntry, difference: Decimal, currency: &str) -> Transaction {
+    Transaction {
+        id,
+        date: pad.date.clone(),
+        time: String::new(),
+        flag: None,
+        payee: String::new(),
+        // "ACCOUNT from SOURCE", which the account pages read back
+        narration: format!("{} from {}", pad.account, pad.source_account),
+        tags: vec!["pad".to_string()],
+        links: Vec::new(),
+        postings: vec![
+            posting(&pad.account, difference, currency),
+            posting(&pad.source_account, -difference, currency),
+        ],
+        metadata: serde_json::json!({
+            "pad_source": pad.source_account,
+            "pad_date": pad.date,
+        }),
+        source: None,
+        line: None,
+    }
+}
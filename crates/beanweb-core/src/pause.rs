@@ -0,0 +1,104 @@
+//! Pausing accounts without closing them
+//!
+//! Seasonal accounts (a ski pass, a holiday card) sit idle for months but are
+//! not gone for good. A `close` directive would make later postings errors, so
+//! pausing is plain metadata on the `open` directive instead:
+//!
+//! ```text
+//! 2024-01-01 open Assets:Ski-Pass CNY
+//!   status: "paused"
+//! ```
+//!
+//! Paused accounts load as [`AccountStatus::Paused`](crate::AccountStatus):
+//! they keep their balance and history, and postings to them stay valid, but
+//! autocompletion and the default account views leave them out. Resuming
+//! removes the line again; the previous file is saved as `<file>.bak`.
+
+use crate::rewrite::{directive_span, split_comment};
+use crate::{AccountStatus, CoreError, Ledger};
+use std::path::PathBuf;
+
+/// Metadata key on the `open` directive
+pub const STATUS_KEY: &str = "status";
+
+/// Value of [`STATUS_KEY`] marking a paused account
+pub const PAUSED: &str = "paused";
+
+fn invalid(message: impl Into<String>) -> CoreError {
+    CoreError::ValidationError { message: message.into() }
+}
+
+/// The `open` directive starting at `line` (0-based) with the status line
+/// added or removed; comments on other lines are kept
+fn with_status(lines: &[&str], line: usize, paused: bool) -> Vec<String> {
+    let span = directive_span(lines, line);
+    let mut directive: Vec<String> = lines[span.clone()].iter()
+        .filter(|l| {
+            let (code, _, _) = split_comment(l);
+            code.trim_start().split_once(':').is_none_or(|(key, _)| key.trim() != STATUS_KEY)
+        })
+        .map(|l| l.to_string())
+        .collect();
+    if paused {
+        directive.insert(1, format!("  {}: \"{}\"", STATUS_KEY, PAUSED));
+    }
+    directive
+}
+
+impl Ledger {
+    /// Pause `account` or resume it, by rewriting the metadata of its `open`
+    /// directive. Closed accounts can't be paused. The caller reloads the
+    /// ledger afterwards; returns the rewritten file
+    pub fn set_account_paused(&self, account: &str, paused: bool) -> Result<PathBuf, CoreError> {
+        let found = self.account(account).ok_or_else(|| CoreError::AccountNotFound { name: account.to_string() })?;
+        if found.status == AccountStatus::Closed {
+            return Err(invalid(format!("{} is closed", account)));
+        }
+        let (Some(source), Some(line)) = (found.source, found.line) else {
+            return Err(invalid(format!("{} has no open directive on disk", account)));
+        };
+        let path = PathBuf::from(&source);
+        let file = if path.is_absolute() { path } else { self.config.data.path.join(path) };
+
+        let content = std::fs::read_to_string(&file).map_err(|_| CoreError::FileNotFound { path: file.display().to_string() })?;
+        let lines: Vec<&str> = content.lines().collect();
+        let line = line as usize;
+        // The line must still open this account
+        let opens = line >= 1 && line <= lines.len() && {
+            let (code, _, _) = split_comment(lines[line - 1]);
+            let mut words = code.split_whitespace().skip(1);
+            words.next() == Some("open") && words.next() == Some(account)
+        };
+        if !opens {
+            return Err(invalid("The file changed since it was loaded; reload and try again"));
+        }
+
+        let span = directive_span(&lines, line - 1);
+        let mut updated: Vec<String> = lines[..span.start].iter().map(|l| l.to_string()).collect();
+        updated.extend(with_status(&lines, line - 1, paused));
+        updated.extend(lines[span.end..].iter().map(|l| l.to_string()));
+        let mut text = updated.join("\n");
+        if content.ends_with('\n') {
+            text.push('\n');
+        }
+        self.write_document(&file.to_string_lossy(), &text)?;
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_status() {
+        let lines = ["2024-01-01 open Assets:Card CNY ; winter only", "  note: \"ski\"", "2024-01-02 open Assets:Bank"];
+        let paused = with_status(&lines, 0, true);
+        assert_eq!(paused, ["2024-01-01 open Assets:Card CNY ; winter only", "  status: \"paused\"", "  note: \"ski\""]);
+
+        let paused: Vec<&str> = paused.iter().map(|l| l.as_str()).collect();
+        assert_eq!(with_status(&paused, 0, false), ["2024-01-01 open Assets:Card CNY ; winter only", "  note: \"ski\""]);
+        // Pausing twice keeps one status line
+        assert_eq!(with_status(&paused, 0, true).len(), 3);
+    }
+}
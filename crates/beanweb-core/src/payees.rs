@@ -0,0 +1,112 @@
+//! Spending by payee
+//!
+//! Where the money goes by merchant rather than by category. A payee counts
+//! the transactions of the time range that post to an Expenses account:
+//! - `amount` is what those postings add up to in the operating currency,
+//!   converted at the transaction date; refunds from the payee net out
+//! - Postings without a price into the operating currency are left out and
+//!   counted in `unconverted`
+//! - Transactions without a payee, and those only moving money between own
+//!   accounts or earning income, are left out
+//!
+//! [`Ledger::payee_trends`] has the same amounts per month for the report's
+//! mini-charts, over the months up to the end of the time range.
+
+use crate::trends::month_starts;
+use crate::{decimal_string, links, percent_of, AccountType, Decimal, Ledger, TimeContext, TimeFilter};
+use chrono::{Months, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Spending at one payee over the time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayeeSummary {
+    pub payee: String,
+    pub count: usize,
+    pub amount: String,
+    pub currency: String,
+    /// Share of the spending at all payees
+    pub percentage: f64,
+    /// Latest transaction (YYYY-MM-DD)
+    pub last_date: String,
+    /// Postings left out for lack of a price
+    pub unconverted: usize,
+}
+
+/// Monthly spending per payee
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayeeTrends {
+    /// YYYY-MM, oldest first
+    pub months: Vec<String>,
+    /// Payee -> one value per month
+    pub payees: HashMap<String, Vec<f64>>,
+}
+
+impl Ledger {
+    /// Spending per payee in `context`, biggest first
+    pub fn payee_summary(&self, context: &TimeContext) -> Vec<PayeeSummary> {
+        let operating_currency = self.report_currency();
+        let data = self.data.read().unwrap();
+        let mut by_payee: HashMap<&str, (usize, Decimal, &str, usize)> = HashMap::new();
+        for tx in data.transactions.iter().filter(|tx| !tx.payee.trim().is_empty() && tx.filter_by_time(context)) {
+            let expenses: Vec<_> = links::posting_units(tx).into_iter()
+                .filter(|((account, _), _)| self.options.is(account, AccountType::Expenses))
+                .collect();
+            if expenses.is_empty() {
+                continue;
+            }
+            let entry = by_payee.entry(tx.payee.trim()).or_insert((0, Decimal::ZERO, "", 0));
+            entry.0 += 1;
+            entry.2 = entry.2.max(tx.date.as_str());
+            for ((_, currency), amount) in expenses {
+                match data.prices.convert(amount, &currency, &operating_currency, &tx.date) {
+                    Some(value) => entry.1 += value,
+                    None => entry.3 += 1,
+                }
+            }
+        }
+
+        let total: Decimal = by_payee.values().map(|(_, amount, _, _)| *amount).sum();
+        let mut payees: Vec<PayeeSummary> = by_payee.into_iter()
+            .map(|(payee, (count, amount, last_date, unconverted))| PayeeSummary {
+                payee: payee.to_string(),
+                count,
+                amount: decimal_string(amount),
+                currency: operating_currency.clone(),
+                percentage: percent_of(amount, total),
+                last_date: last_date.to_string(),
+                unconverted,
+            })
+            .collect();
+        drop(data);
+        payees.sort_by(|a, b| {
+            crate::parse_decimal(&b.amount).cmp(&crate::parse_decimal(&a.amount))
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.payee.cmp(&b.payee))
+        });
+        payees
+    }
+
+    /// Spending per payee in each of the last `months` months of the current
+    /// time range (never past today)
+    pub fn payee_trends(&self, months: usize) -> PayeeTrends {
+        let context = self.time_context();
+        let today = Utc::now().date_naive();
+        let end = context.end_date().map_or(today, |end| end.min(today));
+        let starts = month_starts(end, months);
+
+        let mut trends = PayeeTrends::default();
+        for (month, start) in starts.iter().enumerate() {
+            let month_end = start.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()).unwrap_or(*start);
+            let mut period = TimeContext::custom(*start, month_end);
+            period.include_future = context.include_future;
+            for summary in self.payee_summary(&period) {
+                let values = trends.payees.entry(summary.payee).or_insert_with(|| vec![0.0; starts.len()]);
+                values[month] = crate::parse_decimal(&summary.amount).to_f64().unwrap_or(0.0);
+            }
+            trends.months.push(start.format("%Y-%m").to_string());
+        }
+        trends
+    }
+}
@@ -0,0 +1,199 @@
+//! Price database from `price` directives
+//!
+//! Prices are kept per commodity pair in date order:
+//!
+//! ```text
+//! 2024-01-02 price USD  7.10 CNY
+//! ```
+//!
+//! records that 1 USD was worth 7.10 CNY from 2024-01-02 on. A lookup takes
+//! the latest price on or before the date; with several prices on one day the
+//! last one in the ledger wins. Conversion falls back to the inverse pair
+//! (CNY→USD from a USD/CNY price) but does not chain through a third currency.
+//!
+//! Reports convert into the operating currency by default; with
+//! [`ConversionMode::Original`] they keep every amount in the currency it was
+//! booked in instead, and their totals only cover the operating currency.
+
+use crate::{Decimal, Ledger, PriceEntry};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How reports show amounts booked in other currencies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversionMode {
+    /// Converted into the operating currency through the price database
+    #[default]
+    Converted,
+    /// As booked, per currency
+    Original,
+}
+
+impl std::str::FromStr for ConversionMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "converted" => Ok(ConversionMode::Converted),
+            "original" => Ok(ConversionMode::Original),
+            _ => Err(format!("Invalid conversion mode: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for ConversionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionMode::Converted => write!(f, "converted"),
+            ConversionMode::Original => write!(f, "original"),
+        }
+    }
+}
+
+/// One price of a commodity pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatedPrice {
+    pub date: String,
+    pub amount: Decimal,
+}
+
+/// Prices by commodity, then quote currency, each list sorted by date
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceDatabase {
+    pairs: BTreeMap<String, BTreeMap<String, Vec<DatedPrice>>>,
+}
+
+impl PriceDatabase {
+    /// Add a price, after any earlier price of the same pair and date
+    pub fn insert(&mut self, entry: PriceEntry) {
+        let prices = self.pairs.entry(entry.commodity).or_default().entry(entry.currency).or_default();
+        let at = prices.partition_point(|p| p.date <= entry.date);
+        prices.insert(at, DatedPrice { date: entry.date, amount: entry.amount });
+    }
+
+    pub fn clear(&mut self) {
+        self.pairs.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Number of price entries across all pairs
+    pub fn len(&self) -> usize {
+        self.pairs.values().flat_map(|quotes| quotes.values()).map(Vec::len).sum()
+    }
+
+    /// Every price as a directive entry, by commodity, currency and date
+    pub fn entries(&self) -> impl Iterator<Item = PriceEntry> + '_ {
+        self.pairs.iter().flat_map(|(commodity, quotes)| {
+            quotes.iter().flat_map(move |(currency, prices)| {
+                prices.iter().map(move |p| PriceEntry {
+                    date: p.date.clone(),
+                    commodity: commodity.clone(),
+                    amount: p.amount,
+                    currency: currency.clone(),
+                })
+            })
+        })
+    }
+
+    /// Price history of one pair, oldest first
+    pub fn history(&self, commodity: &str, currency: &str) -> &[DatedPrice] {
+        self.pairs.get(commodity)
+            .and_then(|quotes| quotes.get(currency))
+            .map_or(&[], |prices| prices.as_slice())
+    }
+
+    /// Latest price of 1 `commodity` in `currency` on or before `date`
+    /// (`YYYY-MM-DD`), from a price directive for exactly that pair
+    pub fn price(&self, commodity: &str, currency: &str, date: &str) -> Option<Decimal> {
+        let prices = self.history(commodity, currency);
+        let at = prices.partition_point(|p| p.date.as_str() <= date);
+        at.checked_sub(1).map(|i| prices[i].amount)
+    }
+
+    /// Value of 1 `from` in `to` on `date`, through the direct pair or its inverse
+    pub fn rate(&self, from: &str, to: &str, date: &str) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        self.price(from, to, date).or_else(|| {
+            self.price(to, from, date)
+                .filter(|rate| !rate.is_zero())
+                .and_then(|rate| Decimal::ONE.checked_div(rate))
+        })
+    }
+
+    /// `amount` of `from` in `to` on `date`; None when no price is known
+    /// An empty `from` is taken to be `to` already
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str, date: &str) -> Option<Decimal> {
+        if from == to || from.is_empty() {
+            return Some(amount);
+        }
+        if let Some(price) = self.price(from, to, date) {
+            return amount.checked_mul(price);
+        }
+        // Divide directly rather than through the rounded inverse rate
+        self.price(to, from, date)
+            .filter(|rate| !rate.is_zero())
+            .and_then(|rate| amount.checked_div(rate))
+    }
+}
+
+impl Ledger {
+    /// Price of 1 `commodity` in `currency` at `date`: the latest price directive
+    /// on or before it, or the inverse of the opposite pair's
+    pub fn price(&self, commodity: &str, currency: &str, date: NaiveDate) -> Option<Decimal> {
+        self.data.read().unwrap().prices.rate(commodity, currency, &date.to_string())
+    }
+
+    /// Copy of the price database
+    pub fn prices(&self) -> PriceDatabase {
+        self.data.read().unwrap().prices.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn entry(date: &str, commodity: &str, amount: &str, currency: &str) -> PriceEntry {
+        PriceEntry {
+            date: date.to_string(),
+            commodity: commodity.to_string(),
+            amount: Decimal::from_str(amount).unwrap(),
+            currency: currency.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_latest_price_at_or_before() {
+        let mut db = PriceDatabase::default();
+        db.insert(entry("2024-03-01", "USD", "7.20", "CNY"));
+        db.insert(entry("2024-01-01", "USD", "7.10", "CNY"));
+        db.insert(entry("2024-03-01", "USD", "7.25", "CNY"));
+
+        assert_eq!(db.price("USD", "CNY", "2023-12-31"), None);
+        assert_eq!(db.price("USD", "CNY", "2024-01-01"), Some(Decimal::from_str("7.10").unwrap()));
+        assert_eq!(db.price("USD", "CNY", "2024-02-15"), Some(Decimal::from_str("7.10").unwrap()));
+        // Same day: the later directive wins
+        assert_eq!(db.price("USD", "CNY", "2024-12-31"), Some(Decimal::from_str("7.25").unwrap()));
+        assert_eq!(db.len(), 3);
+        assert_eq!(db.entries().map(|e| e.date).collect::<Vec<_>>(), ["2024-01-01", "2024-03-01", "2024-03-01"]);
+    }
+
+    #[test]
+    fn test_convert_uses_inverse_pair() {
+        let mut db = PriceDatabase::default();
+        db.insert(entry("2024-01-01", "USD", "8", "CNY"));
+
+        assert_eq!(db.convert(Decimal::from(10), "USD", "CNY", "2024-06-01"), Some(Decimal::from(80)));
+        assert_eq!(db.convert(Decimal::from(80), "CNY", "USD", "2024-06-01"), Some(Decimal::from(10)));
+        assert_eq!(db.rate("CNY", "USD", "2024-06-01"), Some(Decimal::from_str("0.125").unwrap()));
+        assert_eq!(db.convert(Decimal::from(5), "EUR", "CNY", "2024-06-01"), None);
+        assert_eq!(db.convert(Decimal::from(5), "", "CNY", "2024-06-01"), Some(Decimal::from(5)));
+    }
+}
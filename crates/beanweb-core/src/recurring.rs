@@ -0,0 +1,141 @@
+//! Recurring payments: subscriptions, rent, insurance
+//!
+//! A payment recurs when the same payee (the narration when there is none)
+//! books the same amount to the same Expenses account at least
+//! [`MIN_OCCURRENCES`] times, at roughly regular intervals:
+//! - Every gap between two occurrences must fall within the tolerance of one
+//!   [`RecurringInterval`] (a week is 6-8 days, a month 26-35, a quarter
+//!   85-97, a year 350-380)
+//! - The next occurrence is predicted one interval after the last one
+//! - A payment more than one interval overdue is taken as cancelled and left
+//!   out
+//!
+//! The whole ledger up to the reference day is looked at, whatever the
+//! selected time range.
+
+use crate::{decimal_string, AccountType, Decimal, Ledger};
+use chrono::{Duration, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Occurrences needed before a payment counts as recurring
+pub const MIN_OCCURRENCES: usize = 3;
+
+/// How often a recurring payment comes back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurringInterval {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl RecurringInterval {
+    /// The interval a gap of `days` fits, if any
+    fn from_gap(days: i64) -> Option<Self> {
+        match days {
+            6..=8 => Some(Self::Weekly),
+            26..=35 => Some(Self::Monthly),
+            85..=97 => Some(Self::Quarterly),
+            350..=380 => Some(Self::Yearly),
+            _ => None,
+        }
+    }
+
+    /// `date` one interval later
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        let months = match self {
+            Self::Weekly => return date + Duration::days(7),
+            Self::Monthly => 1,
+            Self::Quarterly => 3,
+            Self::Yearly => 12,
+        };
+        date.checked_add_months(Months::new(months)).unwrap_or(date)
+    }
+
+    /// Occurrences per year
+    fn per_year(&self) -> u32 {
+        match self {
+            Self::Weekly => 52,
+            Self::Monthly => 12,
+            Self::Quarterly => 4,
+            Self::Yearly => 1,
+        }
+    }
+}
+
+/// A payment detected as recurring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringPayment {
+    pub payee: String,
+    pub account: String,
+    pub amount: String,
+    pub currency: String,
+    pub interval: RecurringInterval,
+    pub occurrences: usize,
+    /// Latest occurrence (YYYY-MM-DD)
+    pub last_date: String,
+    /// Predicted next occurrence (YYYY-MM-DD)
+    pub next_date: String,
+    /// The amount spread over a month, for comparing intervals
+    pub monthly_amount: String,
+}
+
+impl Ledger {
+    /// Recurring payments as of today, next due first
+    pub fn recurring_payments(&self) -> Vec<RecurringPayment> {
+        self.recurring_payments_as_of(chrono::Local::now().date_naive())
+    }
+
+    /// Recurring payments as of `today`, next due first
+    pub fn recurring_payments_as_of(&self, today: NaiveDate) -> Vec<RecurringPayment> {
+        let data = self.data.read().unwrap();
+        // (payee, account, currency, amount) -> dates, oldest first
+        let mut series: HashMap<(&str, &str, &str, Decimal), Vec<NaiveDate>> = HashMap::new();
+        for tx in &data.transactions {
+            let Ok(date) = NaiveDate::parse_from_str(&tx.date, "%Y-%m-%d") else { continue };
+            let payee = if tx.payee.trim().is_empty() { tx.narration.trim() } else { tx.payee.trim() };
+            if date > today || payee.is_empty() {
+                continue;
+            }
+            for posting in tx.postings.iter().filter(|p| self.options.is(&p.account, AccountType::Expenses)) {
+                let Some(amount) = posting.amount_decimal().filter(|a| *a > Decimal::ZERO) else { continue };
+                series.entry((payee, posting.account.as_str(), posting.currency.as_str(), amount.normalize()))
+                    .or_default()
+                    .push(date);
+            }
+        }
+
+        let mut payments: Vec<RecurringPayment> = series.into_iter()
+            .filter_map(|((payee, account, currency, amount), mut dates)| {
+                dates.sort();
+                dates.dedup();
+                if dates.len() < MIN_OCCURRENCES {
+                    return None;
+                }
+                let interval = RecurringInterval::from_gap((dates[1] - dates[0]).num_days())?;
+                let regular = dates.windows(2)
+                    .all(|pair| RecurringInterval::from_gap((pair[1] - pair[0]).num_days()) == Some(interval));
+                let last = *dates.last()?;
+                let next = interval.next(last);
+                if !regular || interval.next(next) < today {
+                    return None;
+                }
+                Some(RecurringPayment {
+                    payee: payee.to_string(),
+                    account: account.to_string(),
+                    amount: decimal_string(amount),
+                    currency: currency.to_string(),
+                    interval,
+                    occurrences: dates.len(),
+                    last_date: last.format("%Y-%m-%d").to_string(),
+                    next_date: next.format("%Y-%m-%d").to_string(),
+                    monthly_amount: decimal_string((amount * Decimal::from(interval.per_year()) / Decimal::from(12)).round_dp(2)),
+                })
+            })
+            .collect();
+        payments.sort_by(|a, b| a.next_date.cmp(&b.next_date).then_with(|| a.payee.cmp(&b.payee)));
+        payments
+    }
+}
@@ -0,0 +1,221 @@
+//! Canonical Beancount text for loaded directives
+//!
+//! The inverse of the parser: a [`Transaction`] (or an open, close, balance,
+//! pad, price or budget entry) is written back as Beancount text that parses
+//! to the same data. Output is canonical rather than a copy of the source:
+//! - Two-space indentation, two spaces between account and amount
+//! - Metadata keys sorted, values quoted unless they are numbers, dates,
+//!   accounts, currencies or booleans
+//! - Postings use the parsed units, cost and price; postings built outside
+//!   the parser fall back to their display amount, elided ones stay empty
+//!
+//! Comments and the original layout are lost; edits that must keep them go
+//! through [`crate::rewrite`] on the source file instead.
+
+use crate::budget::{Budget, BUDGET_TYPE};
+use crate::{Account, AccountStatus, BalanceEntry, Decimal, Ledger, LedgerOptions, PadEntry, Posting, PriceEntry, Transaction};
+
+/// Indentation of metadata and postings
+const INDENT: &str = "  ";
+
+/// Sort rank of transactions among the entries of one day in [`Ledger::render_ledger`]
+const TRANSACTION: u8 = 5;
+
+/// Double-quoted Beancount string
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Metadata values Beancount reads without quotes; accounts go by the roots
+/// of `options`
+fn is_bare_value(value: &str, options: &LedgerOptions) -> bool {
+    let is_date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok();
+    let is_account = value.contains(':')
+        && options.account_type(value).is_some()
+        && !value.contains(char::is_whitespace);
+    let is_currency = value.len() <= 24
+        && value.starts_with(|c: char| c.is_ascii_uppercase())
+        && value.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(c));
+    value == "TRUE" || value == "FALSE" || is_date || is_account || is_currency || value.parse::<Decimal>().is_ok()
+}
+
+/// `key: value` lines of a metadata object, keys sorted
+fn metadata_lines(metadata: &serde_json::Value, indent: &str, options: &LedgerOptions) -> String {
+    let Some(map) = metadata.as_object() else {
+        return String::new();
+    };
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            let value = match &map[key] {
+                serde_json::Value::String(s) if is_bare_value(s, options) => s.clone(),
+                serde_json::Value::String(s) => quote(s),
+                other => quote(&other.to_string()),
+            };
+            format!("{}{}: {}\n", indent, key, value)
+        })
+        .collect()
+}
+
+/// One posting line, without indentation or newline
+pub fn posting(posting: &Posting) -> String {
+    let amount = match &posting.units {
+        Some(units) => {
+            let mut amount = units.to_string();
+            if let Some(cost) = &posting.cost_spec {
+                match &cost.date {
+                    Some(date) => amount.push_str(&format!(" {{{}, {}}}", cost.per_unit, date)),
+                    None => amount.push_str(&format!(" {{{}}}", cost.per_unit)),
+                }
+            }
+            if let Some(price) = &posting.price_spec {
+                amount.push_str(&format!(" {} {}", if price.total { "@@" } else { "@" }, price.amount));
+            }
+            amount
+        }
+        None => posting.amount.trim().to_string(),
+    };
+    if amount.is_empty() {
+        posting.account.clone()
+    } else {
+        format!("{}  {}", posting.account, amount)
+    }
+}
+
+/// A transaction with its metadata and postings, ending in a newline;
+/// `options` tell account values in the metadata apart
+pub fn transaction(tx: &Transaction, options: &LedgerOptions) -> String {
+    let mut header = format!("{} {}", tx.date, tx.flag.as_deref().filter(|f| !f.is_empty()).unwrap_or("*"));
+    // A single string is the narration
+    if !tx.payee.is_empty() {
+        header.push_str(&format!(" {}", quote(&tx.payee)));
+    }
+    header.push_str(&format!(" {}", quote(&tx.narration)));
+    for tag in &tx.tags {
+        header.push_str(&format!(" #{}", tag));
+    }
+    for link in &tx.links {
+        header.push_str(&format!(" ^{}", link));
+    }
+
+    let mut out = header;
+    out.push('\n');
+    out.push_str(&metadata_lines(&tx.metadata, INDENT, options));
+    for p in &tx.postings {
+        out.push_str(&format!("{}{}\n", INDENT, posting(p)));
+        out.push_str(&metadata_lines(&p.metadata, &INDENT.repeat(2), options));
+    }
+    out
+}
+
+/// `open` directive, with the status line of a paused account (see [`crate::pause`]);
+/// None before the account has an open date
+pub fn open(account: &Account) -> Option<String> {
+    let date = account.open_date.as_deref()?;
+    let currency = account.currency.as_deref().map(|c| format!(" {}", c)).unwrap_or_default();
+    let mut out = format!("{} open {}{}\n", date, account.name, currency);
+    if account.status == AccountStatus::Paused {
+        out.push_str(&format!("{}{}: \"{}\"\n", INDENT, crate::pause::STATUS_KEY, crate::pause::PAUSED));
+    }
+    Some(out)
+}
+
+/// `close` directive of a closed account
+pub fn close(account: &Account) -> Option<String> {
+    let date = account.close_date.as_deref()?;
+    Some(format!("{} close {}\n", date, account.name))
+}
+
+/// `balance` directive
+pub fn balance(entry: &BalanceEntry) -> String {
+    let amount = if entry.units.is_zero() && !entry.amount.trim().is_empty() {
+        entry.amount.trim().to_string()
+    } else {
+        entry.units.to_string()
+    };
+    format!("{} balance {}  {} {}\n", entry.date, entry.account, amount, entry.currency)
+}
+
+/// `pad` directive
+pub fn pad(entry: &PadEntry) -> String {
+    format!("{} pad {} {}\n", entry.date, entry.account, entry.source_account)
+}
+
+/// `price` directive
+pub fn price(entry: &PriceEntry) -> String {
+    format!("{} price {}  {} {}\n", entry.date, entry.commodity, entry.amount, entry.currency)
+}
+
+/// `custom "budget"` directive
+pub fn budget(entry: &Budget) -> String {
+    format!("{} custom {} {} {} {} {}\n", entry.date, quote(BUDGET_TYPE), entry.account, quote(&entry.period.to_string()), entry.amount, entry.currency)
+}
+
+impl Ledger {
+    /// The whole loaded ledger as one canonical Beancount file, every entry in
+    /// date order (includes are resolved, so it loads on its own)
+    /// Transactions synthesized from `pad` directives are left out; the pad
+    /// itself is written and produces them again when loaded
+    pub fn render_ledger(&self) -> String {
+        let accounts = self.accounts();
+        let budgets = self.budgets();
+        let data = self.data.read().unwrap();
+
+        // (date, kind, text): on one day opens come first and closes last, like the parser expects
+        let mut entries: Vec<(String, u8, String)> = Vec::new();
+        for account in &accounts {
+            if let (Some(date), Some(text)) = (account.open_date.clone(), open(account)) {
+                entries.push((date, 0, text));
+            }
+            if let (Some(date), Some(text)) = (account.close_date.clone(), close(account)) {
+                entries.push((date, 9, text));
+            }
+        }
+        entries.extend(data.prices.entries().map(|p| (p.date.clone(), 1, price(&p))));
+        entries.extend(budgets.iter().map(|b| (b.date.clone(), 2, budget(b))));
+        entries.extend(data.balances.iter().map(|b| (b.date.clone(), 3, balance(b))));
+        entries.extend(data.pads.iter().map(|p| (p.date.clone(), 4, pad(p))));
+        entries.extend(data.transactions.iter()
+            .filter(|tx| !crate::integrity::is_pad_transaction(tx))
+            .map(|tx| (tx.date.clone(), TRANSACTION, transaction(tx, &self.options))));
+        drop(data);
+        // Stable: transactions of one day keep their ledger order
+        entries.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        // Transactions are set apart by blank lines, single-line directives stay together
+        let mut out = String::new();
+        let mut previous = None;
+        for (_, kind, text) in entries {
+            if previous.is_some_and(|p| p == TRANSACTION || kind == TRANSACTION) {
+                out.push('\n');
+            }
+            out.push_str(&text);
+            previous = Some(kind);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_and_bare_values() {
+        assert_eq!(quote(r#"Say "hi""#), r#""Say \"hi\"""#);
+        let options = LedgerOptions::default();
+        assert!(is_bare_value("2024-01-05", &options));
+        assert!(is_bare_value("12.50", &options));
+        assert!(is_bare_value("CNY", &options));
+        assert!(is_bare_value("Assets:Bank", &options));
+        assert!(!is_bare_value("2024-01-05 10:00:00", &options));
+        assert!(!is_bare_value("receipt.jpg", &options));
+        assert!(!is_bare_value("Assets: Bank", &options));
+
+        let mut renamed = LedgerOptions::default();
+        renamed.set("name_assets", "Vermoegen");
+        assert!(is_bare_value("Vermoegen:Bank", &renamed));
+        assert!(!is_bare_value("Vermoegen:Bank", &options));
+    }
+}
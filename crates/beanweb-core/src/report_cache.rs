@@ -0,0 +1,62 @@
+//! Computed reports kept until the ledger changes
+//!
+//! Balance, income-expense and net worth reports walk every transaction, and
+//! one dashboard view asks for the same report several times (summary cards,
+//! charts, fragments). Reports are cached per kind, conversion mode and time
+//! context:
+//! - The key also holds today's date, since the today horizon moves reports
+//!   at midnight without a reload
+//! - The whole cache is dropped whenever the ledger data is rebuilt (load,
+//!   file watcher reload, reload after a save)
+//! - At most [`CACHED_REPORTS`] are kept; when full the cache starts over
+//!
+//! Reports are built outside the lock, so two requests missing at once both
+//! compute the report and the second one is kept.
+
+use crate::Ledger;
+use chrono::Utc;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Reports kept at once
+const CACHED_REPORTS: usize = 64;
+
+/// See the module docs
+#[derive(Default)]
+pub(crate) struct ReportCache {
+    reports: HashMap<String, Arc<dyn Any + Send + Sync>>,
+}
+
+impl ReportCache {
+    pub(crate) fn clear(&mut self) {
+        self.reports.clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.reports.len()
+    }
+}
+
+impl Ledger {
+    /// The report cached under `key`, built with `build` on a miss
+    pub(crate) fn cached_report<R>(&self, key: String, build: impl FnOnce() -> R) -> R
+    where
+        R: Clone + Send + Sync + 'static,
+    {
+        let key = format!("{} today={}", key, Utc::now().date_naive());
+        let cached = self.report_cache.lock().unwrap().reports.get(&key).cloned();
+        if let Some(report) = cached.as_ref().and_then(|r| r.downcast_ref::<R>()) {
+            return report.clone();
+        }
+
+        let report = build();
+        let mut cache = self.report_cache.lock().unwrap();
+        if cache.reports.len() >= CACHED_REPORTS {
+            cache.clear();
+        }
+        cache.reports.insert(key, Arc::new(report.clone()));
+        report
+    }
+}
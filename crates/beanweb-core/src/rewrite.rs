@@ -0,0 +1,210 @@
+//! Span-aware directive rewriting
+//!
+//! Replacing a directive in a ledger file must not lose the user's `;`
+//! comments. A directive spans its header line plus the indented lines after
+//! it; when the new text is spliced in:
+//! - Unchanged lines keep their trailing comment (and original spacing)
+//! - A header or posting whose amount/narration changed keeps its comment,
+//!   matched by line kind or by the posting's account
+//! - Indented comment-only lines stay below the line they followed
+//!
+//! Comments written in the replacement text win over the original ones.
+//! Removing a directive drops its whole span, comments included.
+
+use crate::CoreError;
+
+/// Split a line into code, the whitespace before the comment, and the comment
+/// (starting at `;`); semicolons inside strings are not comments
+pub fn split_comment(line: &str) -> (&str, &str, Option<&str>) {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => {
+                let code = line[..i].trim_end();
+                return (code, &line[code.len()..i], Some(&line[i..]));
+            }
+            _ => {}
+        }
+    }
+    (line.trim_end(), "", None)
+}
+
+/// Index range of the directive starting at `start`: the header plus every
+/// following indented, non-blank line
+pub fn directive_span(lines: &[&str], start: usize) -> std::ops::Range<usize> {
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim().is_empty() || !line.starts_with([' ', '\t']))
+        .map(|offset| start + 1 + offset)
+        .unwrap_or(lines.len());
+    start..end
+}
+
+/// One original line of the directive being replaced
+struct OriginalLine<'a> {
+    code: &'a str,
+    gap: &'a str,
+    comment: Option<&'a str>,
+    /// Comment-only lines directly below this line
+    trailing: Vec<&'a str>,
+    used: bool,
+}
+
+fn normalized(code: &str) -> String {
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What identifies a line when its content changed: the header, or the
+/// first token of an indented line (posting account or metadata key)
+fn line_key(code: &str, index: usize) -> Option<String> {
+    if index == 0 {
+        return Some(String::from("<header>"));
+    }
+    code.starts_with([' ', '\t'])
+        .then(|| code.split_whitespace().next().map(|t| t.to_string()))
+        .flatten()
+}
+
+/// Carry inline and comment-only lines of `original` over to `replacement`
+pub fn merge_comments(original: &[&str], replacement: &[&str]) -> Vec<String> {
+    let mut lines: Vec<OriginalLine> = Vec::new();
+    for line in original {
+        let (code, gap, comment) = split_comment(line);
+        match lines.last_mut() {
+            // A comment-only line belongs to the code line above it
+            Some(previous) if code.trim().is_empty() && comment.is_some() => previous.trailing.push(line),
+            _ => lines.push(OriginalLine { code, gap, comment, trailing: Vec::new(), used: false }),
+        }
+    }
+
+    let mut out = Vec::new();
+    for (index, line) in replacement.iter().enumerate() {
+        let (code, _, comment) = split_comment(line);
+        if code.trim().is_empty() {
+            out.push(line.to_string());
+            continue;
+        }
+        let wanted = normalized(code);
+        let key = line_key(code, index);
+        let matched = lines.iter()
+            .position(|o| !o.used && normalized(o.code) == wanted)
+            .or_else(|| {
+                let key = key.as_ref()?;
+                lines.iter().enumerate().position(|(i, o)| !o.used && line_key(o.code, i).as_ref() == Some(key))
+            });
+        let Some(matched) = matched else {
+            out.push(line.to_string());
+            continue;
+        };
+
+        let original = &mut lines[matched];
+        original.used = true;
+        match (comment, original.comment) {
+            (None, Some(previous)) => {
+                let gap = if original.gap.is_empty() { "  " } else { original.gap };
+                out.push(format!("{}{}{}", code, gap, previous));
+            }
+            _ => out.push(line.to_string()),
+        }
+        out.extend(original.trailing.iter().map(|l| l.to_string()));
+    }
+    out
+}
+
+/// Replace the directive whose header is on `line` (1-based) in `source`
+pub fn rewrite_directive(source: &str, line: usize, replacement: &str) -> Result<String, CoreError> {
+    let lines: Vec<&str> = source.lines().collect();
+    let start = line.checked_sub(1).filter(|&i| i < lines.len()).ok_or_else(|| CoreError::ValidationError {
+        message: format!("Line {} is out of range", line),
+    })?;
+    if !lines[start].starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(CoreError::ValidationError { message: format!("No directive starts at line {}", line) });
+    }
+
+    let span = directive_span(&lines, start);
+    let replacement: Vec<&str> = replacement.trim_end().lines().collect();
+    let merged = merge_comments(&lines[span.clone()], &replacement);
+
+    let mut out: Vec<String> = lines[..span.start].iter().map(|l| l.to_string()).collect();
+    out.extend(merged);
+    out.extend(lines[span.end..].iter().map(|l| l.to_string()));
+    let mut text = out.join("\n");
+    if source.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Remove the directive starting at `line` (1-based) together with one of the
+/// blank lines around it, so the neighbours keep a single separating line
+pub fn remove_directive(source: &str, line: usize) -> Result<String, CoreError> {
+    let lines: Vec<&str> = source.lines().collect();
+    let start = line.checked_sub(1).filter(|&i| i < lines.len()).ok_or_else(|| CoreError::ValidationError {
+        message: format!("Line {} is out of range", line),
+    })?;
+    if !lines[start].starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(CoreError::ValidationError { message: format!("No directive starts at line {}", line) });
+    }
+
+    let mut span = directive_span(&lines, start);
+    let blank = |i: usize| lines.get(i).is_some_and(|l| l.trim().is_empty());
+    if blank(span.end) {
+        span.end += 1;
+    } else if span.start > 0 && blank(span.start - 1) {
+        span.start -= 1;
+    }
+
+    let mut out: Vec<&str> = lines[..span.start].to_vec();
+    out.extend(&lines[span.end..]);
+    let mut text = out.join("\n");
+    if source.ends_with('\n') && !text.is_empty() {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "2024-01-01 open Assets:Bank\n\n2024-01-05 * \"Shop\" \"Groceries; weekly\"  ; paid by card\n  ; receipt in drawer\n  Expenses:Food  30.00 CNY ; split later\n  Assets:Bank\n\n2024-01-06 * \"Next\"\n  Assets:Bank  1 CNY\n";
+
+    #[test]
+    fn test_split_comment() {
+        assert_eq!(split_comment("  Assets:Bank  1 CNY ; note"), ("  Assets:Bank  1 CNY", " ", Some("; note")));
+        assert_eq!(split_comment("2024-01-01 * \"a;b\""), ("2024-01-01 * \"a;b\"", "", None));
+    }
+
+    #[test]
+    fn test_rewrite_keeps_comments_when_amounts_change() {
+        let replacement = "2024-01-05 * \"Shop\" \"Groceries; weekly\"\n  Expenses:Food  35.00 CNY\n  Assets:Bank";
+        let result = rewrite_directive(SOURCE, 3, replacement).unwrap();
+        assert_eq!(
+            result,
+            "2024-01-01 open Assets:Bank\n\n2024-01-05 * \"Shop\" \"Groceries; weekly\"  ; paid by card\n  ; receipt in drawer\n  Expenses:Food  35.00 CNY ; split later\n  Assets:Bank\n\n2024-01-06 * \"Next\"\n  Assets:Bank  1 CNY\n"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_prefers_new_comments_and_drops_removed_lines() {
+        let replacement = "2024-01-05 * \"Shop\" \"Lunch\" ; edited\n  Expenses:Dining  30.00 CNY\n  Assets:Bank";
+        let result = rewrite_directive(SOURCE, 3, replacement).unwrap();
+        assert!(result.contains("\"Lunch\" ; edited\n  ; receipt in drawer\n  Expenses:Dining  30.00 CNY\n  Assets:Bank\n\n2024-01-06"));
+        assert!(!result.contains("split later"));
+        assert!(rewrite_directive(SOURCE, 2, replacement).is_err());
+    }
+
+    #[test]
+    fn test_remove_directive() {
+        assert_eq!(remove_directive(SOURCE, 3).unwrap(), "2024-01-01 open Assets:Bank\n\n2024-01-06 * \"Next\"\n  Assets:Bank  1 CNY\n");
+        assert_eq!(remove_directive(SOURCE, 8).unwrap(), SOURCE.split("\n\n2024-01-06").next().unwrap().to_string() + "\n");
+        assert!(remove_directive(SOURCE, 4).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Account balances rolled up the account hierarchy
+//!
+//! Balances are booked on the accounts named in postings, but the account
+//! tree also has parents: `Assets:Bank:Checking` makes `Assets:Bank` and
+//! `Assets` nodes whether or not they are opened themselves. Rolling up adds
+//! every account's balance to each of its ancestors:
+//! - Amounts stay per currency; nothing is converted, so a parent holding CNY
+//!   and USD shows both
+//! - A parent that has postings of its own includes them in its total
+//! - Signs are natural (as booked); callers apply the display sign convention
+//!
+//! The rollup is kept in the report cache, so expanding tree nodes reuses it
+//! until the next reload
+
+use crate::{links, Decimal, Ledger};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Amount per currency
+pub type CurrencyAmounts = BTreeMap<String, Decimal>;
+
+impl Ledger {
+    /// Balance of every account per currency up to `as_of` (None: everything)
+    /// Postings give the units; what they don't explain, the balance
+    /// directives without a pad, is counted in the account's own currency
+    pub fn account_holdings_as_of(&self, as_of: Option<NaiveDate>) -> HashMap<String, CurrencyAmounts> {
+        let totals = self.account_balances_as_of(as_of);
+        let operating_currency = &self.report_currency();
+        let data = self.data.read().unwrap();
+
+        let mut holdings: HashMap<String, CurrencyAmounts> = HashMap::new();
+        for tx in data.transactions.iter().filter(|t| !t.is_upcoming(as_of)) {
+            for ((account, currency), amount) in links::posting_units(tx) {
+                if !currency.is_empty() {
+                    *holdings.entry(account).or_default().entry(currency).or_default() += amount;
+                }
+            }
+        }
+        for account in data.accounts.iter().filter(|a| Self::parse_balance(&a.balance).is_some()) {
+            let total = totals.get(&account.name).copied().unwrap_or_default();
+            let amounts = holdings.entry(account.name.clone()).or_default();
+            let unexplained = total - amounts.values().sum::<Decimal>();
+            if !unexplained.is_zero() {
+                let currency = account.currency.clone().unwrap_or_else(|| operating_currency.clone());
+                *amounts.entry(currency).or_default() += unexplained;
+            }
+        }
+        for amounts in holdings.values_mut() {
+            amounts.retain(|_, amount| !amount.is_zero());
+        }
+        holdings.retain(|_, amounts| !amounts.is_empty());
+        holdings
+    }
+
+    /// Balance per currency of every account and every ancestor in its name,
+    /// each including all of its descendants, up to the today horizon
+    pub fn rolled_up_balances(&self) -> Arc<BTreeMap<String, CurrencyAmounts>> {
+        let as_of = self.as_of_date();
+        self.cached_report(format!("rolled_up as_of={:?}", as_of), || Arc::new(self.roll_up(as_of)))
+    }
+
+    fn roll_up(&self, as_of: Option<NaiveDate>) -> BTreeMap<String, CurrencyAmounts> {
+        let mut rolled: BTreeMap<String, CurrencyAmounts> = BTreeMap::new();
+        for (account, amounts) in self.account_holdings_as_of(as_of) {
+            let mut path = account.as_str();
+            loop {
+                let node = rolled.entry(path.to_string()).or_default();
+                for (currency, amount) in &amounts {
+                    *node.entry(currency.clone()).or_default() += amount;
+                }
+                match path.rfind(':') {
+                    Some(pos) => path = &path[..pos],
+                    None => break,
+                }
+            }
+        }
+        // Children that cancel out leave nothing to show on the parent
+        for amounts in rolled.values_mut() {
+            amounts.retain(|_, amount| !amount.is_zero());
+        }
+        rolled
+    }
+}
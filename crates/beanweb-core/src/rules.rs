@@ -0,0 +1,182 @@
+//! Rule-based categorization
+//!
+//! Most spending can be filed by the payee alone ("Starbucks" is coffee, "DB
+//! Fernverkehr" is travel). A rule says which account and tags a payment
+//! gets:
+//! - Conditions: payee and narration regexes (case-insensitive), a range for
+//!   the amount (its absolute value, so it reads the same for money in and
+//!   out) and metadata values, each a regex as well; all given conditions
+//!   must hold, and a rule needs at least one
+//! - The first matching rule in the file wins
+//!
+//! Rules are not part of the ledger. They live in [`RULES_FILE`] in the data
+//! directory, edited by hand; the CSV import applies them to rows without a
+//! counter account of their own, and the create form asks for the account of
+//! the payee typed in. The file is compiled with each load of the ledger, so
+//! edits take effect on reload.
+
+use crate::{CoreError, Decimal, Ledger};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+/// File holding the rules, relative to `data.path`
+pub const RULES_FILE: &str = "rules.yaml";
+
+/// Conditions and what they file a payment under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryRule {
+    pub name: String,
+    /// Regex on the payee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payee: Option<String>,
+    /// Regex on the narration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narration: Option<String>,
+    /// Smallest absolute amount, inclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_amount: Option<Decimal>,
+    /// Largest absolute amount, inclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<Decimal>,
+    /// Regex on the value, by metadata key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Account the payment is filed under
+    pub account: String,
+    /// Added to the transaction, without the leading `#`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// What a rule is matched against
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleSubject {
+    #[serde(default)]
+    pub payee: String,
+    #[serde(default)]
+    pub narration: String,
+    #[serde(default)]
+    pub amount: Option<Decimal>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+struct CompiledRule {
+    rule: CategoryRule,
+    payee: Option<Regex>,
+    narration: Option<Regex>,
+    metadata: Vec<(String, Regex)>,
+}
+
+impl CompiledRule {
+    fn matches(&self, subject: &RuleSubject) -> bool {
+        let amount = subject.amount.map(|a| a.abs());
+        self.payee.as_ref().is_none_or(|re| re.is_match(&subject.payee))
+            && self.narration.as_ref().is_none_or(|re| re.is_match(&subject.narration))
+            && self.rule.min_amount.is_none_or(|min| amount.is_some_and(|a| a >= min))
+            && self.rule.max_amount.is_none_or(|max| amount.is_some_and(|a| a <= max))
+            && self.metadata.iter().all(|(key, re)| subject.metadata.get(key).is_some_and(|v| re.is_match(v)))
+    }
+}
+
+/// Rules ready for matching, in file order
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+fn invalid(rule: &str, message: impl std::fmt::Display) -> CoreError {
+    CoreError::InvalidFormat { message: format!("{}: rule {}: {}", RULES_FILE, rule, message) }
+}
+
+impl RuleSet {
+    /// Compile `rules`; errors on a bad regex, a rule without account or
+    /// conditions, or a minimum above the maximum
+    pub fn new(rules: Vec<CategoryRule>) -> Result<Self, CoreError> {
+        let compile = |name: &str, pattern: &str| RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| invalid(name, e));
+        let mut compiled = Vec::new();
+        for mut rule in rules {
+            rule.account = rule.account.trim().to_string();
+            rule.tags = rule.tags.iter()
+                .map(|t| t.trim().trim_start_matches('#').to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if rule.account.is_empty() || rule.account.contains(char::is_whitespace) {
+                return Err(invalid(&rule.name, format!("invalid account `{}`", rule.account)));
+            }
+            let conditions = rule.payee.is_some() || rule.narration.is_some() || rule.min_amount.is_some()
+                || rule.max_amount.is_some() || !rule.metadata.is_empty();
+            if !conditions {
+                return Err(invalid(&rule.name, "no condition"));
+            }
+            if let (Some(min), Some(max)) = (rule.min_amount, rule.max_amount) {
+                if min > max {
+                    return Err(invalid(&rule.name, format!("min_amount {} is above max_amount {}", min, max)));
+                }
+            }
+            let payee = rule.payee.as_deref().map(|p| compile(&rule.name, p)).transpose()?;
+            let narration = rule.narration.as_deref().map(|p| compile(&rule.name, p)).transpose()?;
+            let metadata = rule.metadata.iter()
+                .map(|(key, p)| compile(&rule.name, p).map(|re| (key.clone(), re)))
+                .collect::<Result<_, _>>()?;
+            compiled.push(CompiledRule { rule, payee, narration, metadata });
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    /// Read and compile [`RULES_FILE`] in `data_dir`; empty when the file
+    /// doesn't exist
+    pub fn load(data_dir: &Path) -> Result<Self, CoreError> {
+        let path = data_dir.join(RULES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|_| CoreError::IoError)?;
+        let rules: Option<Vec<CategoryRule>> = serde_yaml::from_str(&content)
+            .map_err(|e| CoreError::InvalidFormat { message: format!("{}: {}", RULES_FILE, e) })?;
+        Self::new(rules.unwrap_or_default())
+    }
+
+    /// The rules, in file order
+    pub fn rules(&self) -> impl Iterator<Item = &CategoryRule> {
+        self.rules.iter().map(|r| &r.rule)
+    }
+
+    /// The first rule matching `subject`
+    pub fn matching(&self, subject: &RuleSubject) -> Option<&CategoryRule> {
+        self.rules.iter().find(|r| r.matches(subject)).map(|r| &r.rule)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Ledger {
+    /// Rules in file order, as compiled at the last load
+    pub fn categorization_rules(&self) -> Result<Vec<CategoryRule>, CoreError> {
+        Ok(self.rule_set()?.rules().cloned().collect())
+    }
+
+    /// The rules compiled at the last load; the error of the rules file if
+    /// it didn't compile
+    pub fn rule_set(&self) -> Result<Arc<RuleSet>, CoreError> {
+        self.rules.read().unwrap().clone().map_err(|message| CoreError::InvalidFormat { message })
+    }
+
+    /// Recompile the rules file, keeping the error message for
+    /// [`Ledger::rule_set`]
+    pub(crate) fn reload_rules(&self) {
+        let rules = RuleSet::load(&self.config.data.path).map(Arc::new).map_err(|e| match e {
+            CoreError::InvalidFormat { message } => message,
+            e => format!("{}: {}", RULES_FILE, e),
+        });
+        *self.rules.write().unwrap() = rules;
+    }
+}
@@ -0,0 +1,331 @@
+//! Stable facade over the ledger for embedding
+//!
+//! [`Ledger`] grew with the web UI: its methods mirror what the pages need,
+//! the data sits behind interior locks, and the web server keeps the whole
+//! ledger behind another lock for reloads. [`LedgerService`] is the interface
+//! for everything else (a CLI, another UI, integration tests):
+//! - Accounts, transactions and reports as the summaries defined here, never
+//!   the internal [`crate::Account`] or [`crate::Transaction`]
+//! - Mutations that write the ledger files, reload, and return the entry as
+//!   loaded from the file
+//! - Cheap to clone and safe to share between tasks; locking is handled
+//!   inside and never held across calls
+//!
+//! Stability: the methods of [`LedgerService`] follow semver. The summary
+//! types are `#[non_exhaustive]`, so fields can be added in a minor release
+//! but they cannot be built outside this crate. Methods of [`Ledger`] not
+//! reachable from here are internal to the workspace and may change in any
+//! release.
+
+use crate::{AccountStatus, BalanceReport, ConversionMode, CoreError, Decimal, IncomeExpenseReport, Ledger, ParserRef, TimeContext};
+use beanweb_config::{Config, TimeRange};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// An account as declared by its `open` and `close` directives
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct AccountSummary {
+    pub name: String,
+    pub open_date: Option<NaiveDate>,
+    pub close_date: Option<NaiveDate>,
+    /// The currency the account is constrained to, if any
+    pub currency: Option<String>,
+    pub closed: bool,
+}
+
+impl From<crate::Account> for AccountSummary {
+    fn from(account: crate::Account) -> Self {
+        let date = |d: Option<String>| d.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok());
+        Self {
+            closed: account.status == AccountStatus::Closed,
+            name: account.name,
+            open_date: date(account.open_date),
+            close_date: date(account.close_date),
+            currency: account.currency,
+        }
+    }
+}
+
+/// One leg of a transaction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct PostingSummary {
+    pub account: String,
+    /// None when left out, for the ledger to balance
+    pub units: Option<Decimal>,
+    pub currency: String,
+}
+
+/// A transaction and where it is written
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct TransactionSummary {
+    pub id: String,
+    pub date: NaiveDate,
+    pub flag: Option<String>,
+    pub payee: String,
+    pub narration: String,
+    pub tags: Vec<String>,
+    pub links: Vec<String>,
+    pub postings: Vec<PostingSummary>,
+    /// File and header line (1-based) of the directive
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl TransactionSummary {
+    fn new(tx: &crate::Transaction, ledger: &Ledger) -> Self {
+        Self {
+            date: tx.date_naive().unwrap_or_default(),
+            file: tx.source.as_deref().map(|s| ledger.source_path(s)),
+            line: tx.line.map(|l| l as usize),
+            postings: tx.postings.iter().map(|p| PostingSummary {
+                account: p.account.clone(),
+                units: p.units.as_ref().map(|u| u.number),
+                currency: p.currency.clone(),
+            }).collect(),
+            id: tx.id.clone(),
+            flag: tx.flag.clone(),
+            payee: tx.payee.clone(),
+            narration: tx.narration.clone(),
+            tags: tx.tags.clone(),
+            links: tx.links.clone(),
+        }
+    }
+}
+
+/// Totals of the balance sheet, in the report currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct BalanceSummary {
+    pub currency: String,
+    pub assets: Decimal,
+    pub liabilities: Decimal,
+    pub equity: Decimal,
+    pub net_worth: Decimal,
+    /// Balance per account
+    pub accounts: Vec<(String, Decimal)>,
+}
+
+impl From<BalanceReport> for BalanceSummary {
+    fn from(report: BalanceReport) -> Self {
+        Self {
+            assets: decimal(&report.total_assets),
+            liabilities: decimal(&report.total_liabilities),
+            equity: decimal(&report.total_equity),
+            net_worth: decimal(&report.net_worth),
+            accounts: report.entries.into_iter().map(|e| (e.account, decimal(&e.balance))).collect(),
+            currency: report.currency,
+        }
+    }
+}
+
+/// Income and expenses of a period, in the report currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct IncomeExpenseSummary {
+    pub currency: String,
+    pub income: Decimal,
+    pub expenses: Decimal,
+    pub net_income: Decimal,
+    /// Amount per income account
+    pub income_accounts: Vec<(String, Decimal)>,
+    /// Amount per expense account
+    pub expense_accounts: Vec<(String, Decimal)>,
+}
+
+impl From<IncomeExpenseReport> for IncomeExpenseSummary {
+    fn from(report: IncomeExpenseReport) -> Self {
+        let accounts = |entries: Vec<crate::IncomeExpenseEntry>| entries.into_iter().map(|e| (e.account, decimal(&e.amount))).collect();
+        Self {
+            income: decimal(&report.total_income),
+            expenses: decimal(&report.total_expenses),
+            net_income: decimal(&report.net_income),
+            income_accounts: accounts(report.income_entries),
+            expense_accounts: accounts(report.expense_entries),
+            currency: report.currency,
+        }
+    }
+}
+
+/// A transaction written, rewritten or removed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct WrittenTransaction {
+    pub file: PathBuf,
+    /// Header line of the directive (1-based)
+    pub line: usize,
+    /// The entry as loaded after the write; for a deletion, as it was
+    /// before
+    pub transaction: TransactionSummary,
+}
+
+fn decimal(text: &str) -> Decimal {
+    text.parse().unwrap_or_default()
+}
+
+/// Handle on a loaded ledger
+#[derive(Clone)]
+pub struct LedgerService {
+    ledger: Arc<RwLock<Ledger>>,
+}
+
+impl LedgerService {
+    /// Load the ledger at `config.ledger_path()`
+    pub async fn open(config: Config, parser: ParserRef) -> Result<Self, CoreError> {
+        let entry = config.ledger_path();
+        let mut ledger = Ledger::new(config, parser);
+        ledger.load(entry).await?;
+        Ok(Self::from_shared(Arc::new(RwLock::new(ledger))))
+    }
+
+    /// Wrap a ledger already shared with the web server, so both see the
+    /// same data and reloads
+    pub fn from_shared(ledger: Arc<RwLock<Ledger>>) -> Self {
+        Self { ledger }
+    }
+
+    /// Read the files again
+    pub async fn reload(&self) -> Result<(), CoreError> {
+        self.ledger.write().await.reload().await
+    }
+
+    // ==================== Accounts ====================
+
+    /// Every account, in declaration order
+    pub async fn accounts(&self) -> Vec<AccountSummary> {
+        self.ledger.read().await.accounts().into_iter().map(AccountSummary::from).collect()
+    }
+
+    /// The account called `name`
+    pub async fn account(&self, name: &str) -> Option<AccountSummary> {
+        self.ledger.read().await.account(name).map(AccountSummary::from)
+    }
+
+    // ==================== Transactions ====================
+
+    /// Number of transactions in the ledger, whatever the time range
+    pub async fn transaction_count(&self) -> usize {
+        self.ledger.read().await.transactions_count()
+    }
+
+    /// Up to `limit` transactions from `offset`, in file order
+    pub async fn transactions(&self, limit: usize, offset: usize) -> Vec<TransactionSummary> {
+        let ledger = self.ledger.read().await;
+        ledger.transactions(limit, offset).iter().map(|tx| TransactionSummary::new(tx, &ledger)).collect()
+    }
+
+    /// The transaction with `id`
+    pub async fn transaction(&self, id: &str) -> Option<TransactionSummary> {
+        let ledger = self.ledger.read().await;
+        ledger.transaction(id).map(|tx| TransactionSummary::new(&tx, &ledger))
+    }
+
+    /// Transactions whose payee, narration, tags, links or accounts match `query`
+    pub async fn search_transactions(&self, query: &str) -> Vec<TransactionSummary> {
+        let ledger = self.ledger.read().await;
+        let found = ledger.search_transactions(query);
+        found.iter().map(|tx| TransactionSummary::new(tx, &ledger)).collect()
+    }
+
+    // ==================== Time range and reports ====================
+
+    /// First and last day reports cover; None when open-ended
+    pub async fn period(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let context = self.ledger.read().await.time_context();
+        (context.start_date(), context.end_date())
+    }
+
+    /// Cover `range` in reports from now on
+    pub async fn set_time_range(&self, range: TimeRange) {
+        self.ledger.read().await.set_time_range(range)
+    }
+
+    /// Cover `start` to `end` (inclusive) in reports from now on
+    pub async fn set_custom_range(&self, start: NaiveDate, end: NaiveDate) {
+        self.ledger.read().await.set_custom_range(start, end)
+    }
+
+    /// Balance sheet of the current time range
+    pub async fn balance_report(&self, mode: ConversionMode) -> BalanceSummary {
+        self.ledger.read().await.balance_report_with(mode).into()
+    }
+
+    /// Income and expenses of the current time range
+    pub async fn income_expense_report(&self, mode: ConversionMode) -> IncomeExpenseSummary {
+        self.ledger.read().await.income_expense_report_with(mode).into()
+    }
+
+    /// Income and expenses from `start` to `end` (inclusive) instead of the
+    /// current time range, amounts converted
+    pub async fn income_expense_between(&self, start: NaiveDate, end: NaiveDate) -> IncomeExpenseSummary {
+        self.ledger.read().await.income_expense_report_in(&TimeContext::custom(start, end)).into()
+    }
+
+    // ==================== Mutations ====================
+
+    /// Append `text`, a single balanced transaction in Beancount syntax, to
+    /// [`Config::new_transaction_path`] and reload; postings to accounts not
+    /// open on its date are rejected
+    pub async fn create_transaction(&self, text: &str) -> Result<WrittenTransaction, CoreError> {
+        // Parse what gets written, so the header line counts from the trimmed text
+        let text = text.trim();
+        let mut ledger = self.ledger.write().await;
+        let parsed = ledger.validate_transaction_text(text).await?;
+        let accounts: Vec<String> = parsed.postings.iter().map(|p| p.account.clone()).collect();
+        let inactive = ledger.inactive_accounts_on(&parsed.date, &accounts);
+        if !inactive.is_empty() {
+            return Err(CoreError::ValidationError { message: inactive.join("; ") });
+        }
+
+        let path = ledger.config.new_transaction_path();
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        let current = current.trim_end();
+        // Header line of `text` in the file: after the current content and a
+        // blank line
+        let offset = if current.is_empty() { 0 } else { current.lines().count() + 1 };
+        let line = offset + parsed.line.map_or(1, |l| l as usize);
+        let content = if current.is_empty() {
+            format!("{}\n", text)
+        } else {
+            format!("{}\n\n{}\n", current, text)
+        };
+        ledger.write_document(&path.to_string_lossy(), &content)?;
+        ledger.reload().await?;
+        written(&ledger, &path, line)
+    }
+
+    /// Replace the transaction `id` with `text` and reload (see
+    /// [`Ledger::update_transaction`])
+    pub async fn update_transaction(&self, id: &str, text: &str) -> Result<WrittenTransaction, CoreError> {
+        let mut ledger = self.ledger.write().await;
+        let update = ledger.update_transaction(id, text).await?;
+        ledger.reload().await?;
+        written(&ledger, &update.file, update.line)
+    }
+
+    /// Remove the transaction `id` from its file and reload (see
+    /// [`Ledger::delete_transaction`])
+    pub async fn delete_transaction(&self, id: &str) -> Result<WrittenTransaction, CoreError> {
+        let mut ledger = self.ledger.write().await;
+        let deletion = ledger.delete_transaction(id).await?;
+        ledger.reload().await?;
+        Ok(WrittenTransaction {
+            transaction: TransactionSummary::new(&deletion.transaction, &ledger),
+            file: deletion.file,
+            line: deletion.line,
+        })
+    }
+}
+
+/// The entry loaded from `line` of `file` after a write
+fn written(ledger: &Ledger, file: &Path, line: usize) -> Result<WrittenTransaction, CoreError> {
+    let transaction = ledger.transaction_at(file, line).ok_or_else(|| CoreError::InternalError {
+        message: format!("No transaction at {}:{} after writing it", file.display(), line),
+    })?;
+    Ok(WrittenTransaction { transaction: TransactionSummary::new(&transaction, ledger), file: file.to_path_buf(), line })
+}
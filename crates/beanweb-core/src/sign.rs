@@ -0,0 +1,39 @@
+//! Display sign convention
+//!
+//! Beancount books credit-normal accounts (Income, Liabilities, Equity) with
+//! negative amounts. Every view converts booked amounts through
+//! [`display_amount`] so the whole UI follows one convention:
+//! - `natural`: earned income, owed liabilities and equity are positive;
+//!   a refund to an expense account or a payment against income is negative
+//! - `raw`: amounts exactly as booked
+//!
+//! Assets and Expenses are debit-normal and look the same under both.
+//!
+//! The rules take the account type resolved from the ledger's root names
+//! (see [`LedgerOptions::account_type`](crate::LedgerOptions::account_type)),
+//! so renamed roots behave like the roots they stand for.
+
+pub use beanweb_config::SignConvention;
+
+use crate::AccountType;
+
+/// Whether balances of this account type are negative in beancount under normal use
+pub fn is_credit_normal(account_type: AccountType) -> bool {
+    matches!(account_type, AccountType::Income | AccountType::Liabilities | AccountType::Equity)
+}
+
+/// Amount types the sign convention applies to (f64 for display, Decimal for sums)
+pub trait SignedAmount: Copy + PartialEq + Default + std::ops::Neg<Output = Self> {}
+
+impl SignedAmount for f64 {}
+impl SignedAmount for rust_decimal::Decimal {}
+
+/// Convert a booked amount into its display sign; `account_type` is None for
+/// an account outside the five roots, which is kept as booked
+pub fn display_amount<T: SignedAmount>(account_type: Option<AccountType>, amount: T, convention: SignConvention) -> T {
+    if convention == SignConvention::Natural && account_type.is_some_and(is_credit_normal) && amount != T::default() {
+        -amount
+    } else {
+        amount
+    }
+}
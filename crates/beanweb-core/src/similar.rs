@@ -0,0 +1,32 @@
+//! Near-identical transactions, for duplicate warnings on create
+//!
+//! Entering the same expense twice is the usual mistake when copying from a
+//! statement by hand. A transaction counts as similar to the one being
+//! entered when it has the same date, posts to the same set of accounts and
+//! its largest posting has the same size. The size ignores the sign and any
+//! elided posting, so "Expenses:Food 30 / Assets:Bank" and
+//! "Assets:Bank -30 / Expenses:Food" match each other.
+
+use crate::integrity::TOLERANCE;
+use crate::{Decimal, Ledger, Transaction};
+use std::collections::BTreeSet;
+
+/// Largest explicit posting amount, ignoring the sign
+fn size(tx: &Transaction) -> Option<Decimal> {
+    tx.postings.iter().filter_map(|p| p.amount_decimal()).map(|a| a.abs()).max()
+}
+
+impl Ledger {
+    /// Transactions on `date` of `amount` (either sign) posting to exactly `accounts`
+    pub fn find_similar_transactions(&self, date: &str, amount: Decimal, accounts: &[String]) -> Vec<Transaction> {
+        let wanted: BTreeSet<&str> = accounts.iter().map(String::as_str).collect();
+        let amount = amount.abs();
+        let data = self.data.read().unwrap();
+        data.transactions.iter()
+            .filter(|tx| tx.date == date)
+            .filter(|tx| size(tx).is_some_and(|s| (s - amount).abs() <= TOLERANCE))
+            .filter(|tx| tx.postings.iter().map(|p| p.account.as_str()).collect::<BTreeSet<_>>() == wanted)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+//! Reminders for accounts that haven't been reconciled lately
+//!
+//! An account is stale when its most recent `balance` assertion is older
+//! than `checks.stale_balance_days` for its type (the root of the account
+//! name, e.g. `Assets`). Only accounts that were reconciled at least once are
+//! listed, and closed accounts are left out.
+
+use crate::Ledger;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An account whose latest balance assertion is too old
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleBalance {
+    pub account: String,
+    /// Date of the latest `balance` directive
+    pub last_assertion: String,
+    /// Days since `last_assertion`
+    pub days: i64,
+    /// Configured limit for the account type
+    pub threshold: u32,
+}
+
+impl Ledger {
+    /// Stale accounts as of today, longest neglected first
+    pub fn stale_balances(&self) -> Vec<StaleBalance> {
+        self.stale_balances_as_of(chrono::Local::now().date_naive())
+    }
+
+    /// Stale accounts as of `today`, longest neglected first
+    pub fn stale_balances_as_of(&self, today: NaiveDate) -> Vec<StaleBalance> {
+        let limits = &self.config.checks.stale_balance_days;
+        let data = self.data.read().unwrap();
+        let mut latest: HashMap<&str, &str> = HashMap::new();
+        for balance in &data.balances {
+            let date = latest.entry(balance.account.as_str()).or_insert(balance.date.as_str());
+            if balance.date.as_str() > *date {
+                *date = balance.date.as_str();
+            }
+        }
+
+        let mut stale: Vec<StaleBalance> = data.accounts.iter()
+            .filter(|account| account.close_date.is_none())
+            .filter_map(|account| {
+                let threshold = *limits.get(account.name.split(':').next()?)?;
+                let last = latest.get(account.name.as_str())?;
+                let days = (today - NaiveDate::parse_from_str(last, "%Y-%m-%d").ok()?).num_days();
+                (days > i64::from(threshold)).then(|| StaleBalance {
+                    account: account.name.clone(),
+                    last_assertion: last.to_string(),
+                    days,
+                    threshold,
+                })
+            })
+            .collect();
+        stale.sort_by(|a, b| b.days.cmp(&a.days).then_with(|| a.account.cmp(&b.account)));
+        stale
+    }
+}
@@ -0,0 +1,124 @@
+//! Account suggestions for autocomplete
+//!
+//! Autocomplete fires on every keystroke, so the account list is not scanned
+//! and cloned per request. [`AccountSuggestions`] is built once per load with
+//! lowercased names and aliases, and shared behind an `Arc`:
+//! - Matches rank: name prefix, segment prefix ("food" → Expenses:Food:Dining),
+//!   substring, then alias; open accounts before closed ones
+//! - Paused accounts (see [`crate::pause`]) are not suggested at all
+//! - Given a date, only accounts open on that day are suggested (for a new
+//!   transaction on that date)
+//! - Results are memoized per query until the next reload replaces the index;
+//!   the memo lock is held while computing, so identical concurrent requests
+//!   are answered by a single scan
+
+use crate::{Account, AccountStatus};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Memoized queries kept per index before the memo is reset
+const MEMO_CAPACITY: usize = 256;
+
+/// Memoized results keyed by (lowercase query, limit, date)
+type Memo = HashMap<(String, usize, Option<String>), Arc<Vec<Suggestion>>>;
+
+/// One suggested account
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    pub name: String,
+    pub alias: Option<String>,
+    pub closed: bool,
+}
+
+struct Entry {
+    suggestion: Suggestion,
+    lower: String,
+    alias_lower: Option<String>,
+    account: Account,
+}
+
+impl Entry {
+    /// Match rank for a lowercase query, lower is better; None when it doesn't match
+    fn rank(&self, query: &str) -> Option<u8> {
+        if self.lower.starts_with(query) {
+            Some(0)
+        } else if self.lower.split(':').skip(1).any(|segment| segment.starts_with(query)) {
+            Some(1)
+        } else if self.lower.contains(query) {
+            Some(2)
+        } else if self.alias_lower.as_deref().is_some_and(|alias| alias.contains(query)) {
+            Some(3)
+        } else {
+            None
+        }
+    }
+}
+
+/// Pre-normalized account index for autocomplete, rebuilt on every load
+#[derive(Default)]
+pub struct AccountSuggestions {
+    entries: Vec<Entry>,
+    memo: Mutex<Memo>,
+}
+
+impl AccountSuggestions {
+    pub fn new(accounts: &[Account]) -> Self {
+        let mut entries: Vec<Entry> = accounts.iter()
+            .filter(|a| a.status != AccountStatus::Paused)
+            .map(|a| Entry {
+                lower: a.name.to_lowercase(),
+                alias_lower: a.alias.as_ref().map(|alias| alias.to_lowercase()),
+                suggestion: Suggestion {
+                    name: a.name.clone(),
+                    alias: a.alias.clone(),
+                    closed: a.status == AccountStatus::Closed,
+                },
+                account: a.clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.suggestion.name.cmp(&b.suggestion.name));
+        Self { entries, memo: Mutex::new(HashMap::new()) }
+    }
+
+    /// Number of indexed accounts
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Best `limit` accounts matching `query` (case-insensitive); empty query, no results
+    pub fn suggest(&self, query: &str, limit: usize) -> Arc<Vec<Suggestion>> {
+        self.suggest_on(query, limit, None)
+    }
+
+    /// [`Self::suggest`], keeping only accounts open on `date` (YYYY-MM-DD) when given
+    pub fn suggest_on(&self, query: &str, limit: usize, date: Option<&str>) -> Arc<Vec<Suggestion>> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() || limit == 0 {
+            return Arc::new(Vec::new());
+        }
+        let key = (query, limit, date.map(str::to_string));
+        let mut memo = self.memo.lock().unwrap();
+        if let Some(hit) = memo.get(&key) {
+            return hit.clone();
+        }
+
+        let mut matches: Vec<(u8, bool, usize, &Entry)> = self.entries.iter()
+            .filter(|e| date.is_none_or(|date| e.account.is_open_on(date)))
+            .filter_map(|e| e.rank(&key.0).map(|rank| (rank, e.suggestion.closed, e.lower.len(), e)))
+            .collect();
+        // Entries are sorted by name, so the stable sort keeps ties alphabetical
+        matches.sort_by_key(|&(rank, closed, len, _)| (rank, closed, len));
+        let result = Arc::new(matches.into_iter().take(limit).map(|(.., e)| e.suggestion.clone()).collect::<Vec<_>>());
+
+        if memo.len() >= MEMO_CAPACITY {
+            memo.clear();
+        }
+        memo.insert(key, result.clone());
+        result
+    }
+}
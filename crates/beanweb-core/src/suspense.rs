@@ -0,0 +1,119 @@
+//! Postings parked on placeholder accounts
+//!
+//! Imports and quick entries book what they can't classify to accounts such
+//! as `Expenses:Uncategorized` or `Equity:TODO` (`checks.suspense_accounts`,
+//! each an account filter). Those postings are listed for review, newest
+//! first, one per account and currency with elided amounts inferred.
+//!
+//! [`Ledger::recategorize_posting`] moves a transaction's postings from the
+//! placeholder to a real account through the edit layer, so the file is
+//! checked for staleness, comments are kept and a backup is written.
+
+use crate::account_filter::AccountFilter;
+use crate::edit::TransactionUpdate;
+use crate::{links, AccountStatus, CoreError, Ledger};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// Units a transaction posted to a suspense account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspensePosting {
+    pub transaction_id: String,
+    pub date: String,
+    pub payee: String,
+    pub narration: String,
+    pub account: String,
+    pub amount: f64,
+    pub currency: String,
+}
+
+/// `text` with the posting lines on exactly `from` moved to `to`, None when
+/// no posting is on `from`; indentation, amounts and comments stay as written
+fn replace_posting_account(text: &str, from: &str, to: &str) -> Option<String> {
+    let mut replaced = false;
+    let lines: Vec<String> = text.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let rest = line.trim_start();
+            let indent = &line[..line.len() - rest.len()];
+            match rest.strip_prefix(from) {
+                Some(after) if index > 0 && (after.is_empty() || after.starts_with([' ', '\t', ';'])) => {
+                    replaced = true;
+                    format!("{}{}{}", indent, to, after)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    replaced.then(|| lines.join("\n"))
+}
+
+impl Ledger {
+    /// Parsed `checks.suspense_accounts`; invalid patterns are skipped
+    fn suspense_filters(&self) -> Vec<AccountFilter> {
+        self.config.checks.suspense_accounts.iter()
+            .filter_map(|pattern| AccountFilter::parse(pattern).ok())
+            .collect()
+    }
+
+    /// Whether `account` matches one of `checks.suspense_accounts`
+    pub fn is_suspense_account(&self, account: &str) -> bool {
+        self.suspense_filters().iter().any(|f| f.matches(account))
+    }
+
+    /// Postings on suspense accounts, newest first
+    pub fn suspense_postings(&self) -> Vec<SuspensePosting> {
+        let filters = self.suspense_filters();
+        let data = self.data.read().unwrap();
+        let mut positions: Vec<usize> = data.accounts.iter()
+            .filter(|a| filters.iter().any(|f| f.matches(&a.name)))
+            .flat_map(|a| data.index.by_account(&a.name).iter().copied())
+            .collect();
+        positions.sort_unstable_by_key(|i| data.index.rank(*i));
+        positions.dedup();
+
+        let mut postings = Vec::new();
+        for tx in positions.into_iter().map(|i| &data.transactions[i]) {
+            for ((account, currency), amount) in links::posting_units(tx) {
+                if filters.iter().any(|f| f.matches(&account)) {
+                    postings.push(SuspensePosting {
+                        transaction_id: tx.id.clone(),
+                        date: tx.date.clone(),
+                        payee: tx.payee.clone(),
+                        narration: tx.narration.clone(),
+                        account,
+                        amount: amount.to_f64().unwrap_or(0.0),
+                        currency,
+                    });
+                }
+            }
+        }
+        postings
+    }
+
+    /// Number of transactions with a posting on a suspense account
+    pub fn suspense_count(&self) -> usize {
+        let mut ids: Vec<String> = self.suspense_postings().into_iter().map(|p| p.transaction_id).collect();
+        ids.dedup();
+        ids.len()
+    }
+
+    /// Move the postings of transaction `id` on the suspense account `from` to
+    /// the open account `to`; the caller reloads the ledger afterwards
+    pub async fn recategorize_posting(&mut self, id: &str, from: &str, to: &str) -> Result<TransactionUpdate, CoreError> {
+        let invalid = |message: String| CoreError::ValidationError { message };
+        if !self.is_suspense_account(from) {
+            return Err(invalid(format!("{} is not a suspense account", from)));
+        }
+        if self.is_suspense_account(to) {
+            return Err(invalid(format!("{} is a suspense account itself", to)));
+        }
+        if !self.account(to).is_some_and(|a| a.status == AccountStatus::Open) {
+            return Err(invalid(format!("{} is not an open account", to)));
+        }
+        let source = self.transaction_source(id).await?;
+        let text = replace_posting_account(&source, from, to)
+            .ok_or_else(|| invalid(format!("Transaction {} has no posting on {}", id, from)))?;
+        self.update_transaction(id, &text).await
+    }
+}
@@ -0,0 +1,65 @@
+//! Tags across the ledger
+//!
+//! `#tags` group transactions across accounts and months (a trip, a
+//! renovation). For each tag the tags page shows how many transactions carry
+//! it and what they moved, per currency:
+//! - `expenses`: units posted to Expenses accounts, refunds netted out
+//! - `income`: units posted to Income accounts, positive when earned
+//!
+//! Elided amounts count with their inferred value; upcoming transactions are
+//! left out until their date, as in the transactions list.
+
+use crate::amount::Amount;
+use crate::{links, AccountType, Decimal, Ledger};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One tag and what its transactions add up to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagSummary {
+    /// Without the `#`
+    pub tag: String,
+    pub count: usize,
+    pub expenses: Vec<Amount>,
+    pub income: Vec<Amount>,
+}
+
+fn amounts(sums: BTreeMap<String, Decimal>) -> Vec<Amount> {
+    sums.into_iter()
+        .filter(|(_, number)| !number.is_zero())
+        .map(|(currency, number)| Amount::new(number, currency))
+        .collect()
+}
+
+impl Ledger {
+    /// Every tag in use, most used first, then by name
+    pub fn all_tags(&self) -> Vec<TagSummary> {
+        let as_of = self.as_of_date();
+        let data = self.data.read().unwrap();
+        let mut tags: Vec<TagSummary> = data.index.tags()
+            .filter_map(|(tag, positions)| {
+                let mut count = 0;
+                let mut expenses: BTreeMap<String, Decimal> = BTreeMap::new();
+                let mut income: BTreeMap<String, Decimal> = BTreeMap::new();
+                for tx in positions.iter().map(|i| &data.transactions[*i]).filter(|tx| !tx.is_upcoming(as_of)) {
+                    count += 1;
+                    for ((account, currency), amount) in links::posting_units(tx) {
+                        match self.options.account_type(&account) {
+                            Some(AccountType::Expenses) => *expenses.entry(currency).or_default() += amount,
+                            Some(AccountType::Income) => *income.entry(currency).or_default() -= amount,
+                            _ => {}
+                        }
+                    }
+                }
+                (count > 0).then(|| TagSummary {
+                    tag: tag.to_string(),
+                    count,
+                    expenses: amounts(expenses),
+                    income: amounts(income),
+                })
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        tags
+    }
+}
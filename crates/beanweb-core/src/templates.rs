@@ -0,0 +1,146 @@
+//! Transaction templates for quick entry
+//!
+//! Most entries come in a handful of shapes (coffee, groceries, rent). A
+//! template keeps one of them by name and pre-fills the create form:
+//! - Payee, narration and tags
+//! - Postings: the account, and optionally an amount (left empty for the
+//!   amount that changes every time)
+//!
+//! Templates are not part of the ledger. They live in [`TEMPLATES_FILE`] in
+//! the data directory, sorted by name; saving a template under an existing
+//! name replaces it.
+
+use crate::{CoreError, Ledger};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// File holding the templates, relative to `data.path`
+pub const TEMPLATES_FILE: &str = "templates.yaml";
+
+/// A named transaction shape
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionTemplate {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub payee: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub narration: String,
+    /// Without the leading `#`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub postings: Vec<TemplatePosting>,
+}
+
+/// One posting of a template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplatePosting {
+    pub account: String,
+    /// As typed in the form ("35", "35 CNY"); empty to fill in each time
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub amount: String,
+}
+
+fn invalid(message: impl Into<String>) -> CoreError {
+    CoreError::ValidationError { message: message.into() }
+}
+
+impl TransactionTemplate {
+    /// Trimmed, tags without `#`, blank postings dropped; errors when there
+    /// is no name or no posting left
+    fn normalized(mut self) -> Result<Self, CoreError> {
+        self.name = self.name.trim().to_string();
+        self.payee = self.payee.trim().to_string();
+        self.narration = self.narration.trim().to_string();
+        self.tags = self.tags.iter()
+            .map(|t| t.trim().trim_start_matches('#').to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        self.postings.retain(|p| !p.account.trim().is_empty());
+        for posting in &mut self.postings {
+            posting.account = posting.account.trim().to_string();
+            posting.amount = posting.amount.trim().to_string();
+        }
+        if self.name.is_empty() {
+            return Err(invalid("template name is empty"));
+        }
+        if self.postings.is_empty() {
+            return Err(invalid(format!("template {} has no posting", self.name)));
+        }
+        if let Some(posting) = self.postings.iter().find(|p| p.account.contains(char::is_whitespace)) {
+            return Err(invalid(format!("invalid account name: {}", posting.account)));
+        }
+        Ok(self)
+    }
+}
+
+impl Ledger {
+    fn templates_path(&self) -> PathBuf {
+        self.config.data.path.join(TEMPLATES_FILE)
+    }
+
+    /// Saved templates, sorted by name; none when the file doesn't exist yet
+    pub fn transaction_templates(&self) -> Result<Vec<TransactionTemplate>, CoreError> {
+        let path = self.templates_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|_| CoreError::IoError)?;
+        let templates: Option<Vec<TransactionTemplate>> = serde_yaml::from_str(&content)
+            .map_err(|e| CoreError::InvalidFormat { message: format!("{}: {}", TEMPLATES_FILE, e) })?;
+        Ok(templates.unwrap_or_default())
+    }
+
+    /// The template called `name`
+    pub fn transaction_template(&self, name: &str) -> Result<Option<TransactionTemplate>, CoreError> {
+        Ok(self.transaction_templates()?.into_iter().find(|t| t.name == name))
+    }
+
+    /// Add `template`, or replace the one with the same name; returns it as
+    /// stored
+    pub fn save_transaction_template(&self, template: TransactionTemplate) -> Result<TransactionTemplate, CoreError> {
+        let template = template.normalized()?;
+        let mut templates = self.transaction_templates()?;
+        templates.retain(|t| t.name != template.name);
+        templates.push(template.clone());
+        self.write_templates(templates)?;
+        Ok(template)
+    }
+
+    /// Replace the template called `name` with `template`, renaming it when
+    /// the names differ; None when there is no `name`. A rename onto another
+    /// existing template is refused with [`CoreError::DuplicateEntry`].
+    pub fn replace_transaction_template(&self, name: &str, template: TransactionTemplate) -> Result<Option<TransactionTemplate>, CoreError> {
+        let template = template.normalized()?;
+        let mut templates = self.transaction_templates()?;
+        if !templates.iter().any(|t| t.name == name) {
+            return Ok(None);
+        }
+        if template.name != name && templates.iter().any(|t| t.name == template.name) {
+            return Err(CoreError::DuplicateEntry { entry: format!("template {}", template.name) });
+        }
+        templates.retain(|t| t.name != name);
+        templates.push(template.clone());
+        self.write_templates(templates)?;
+        Ok(Some(template))
+    }
+
+    /// Remove the template called `name`; false when there is none
+    pub fn delete_transaction_template(&self, name: &str) -> Result<bool, CoreError> {
+        let mut templates = self.transaction_templates()?;
+        let before = templates.len();
+        templates.retain(|t| t.name != name);
+        if templates.len() == before {
+            return Ok(false);
+        }
+        self.write_templates(templates)?;
+        Ok(true)
+    }
+
+    fn write_templates(&self, mut templates: Vec<TransactionTemplate>) -> Result<(), CoreError> {
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        let content = serde_yaml::to_string(&templates)
+            .map_err(|e| CoreError::InternalError { message: e.to_string() })?;
+        beanweb_utils::write_atomic(&self.templates_path(), content.as_bytes()).map_err(|_| CoreError::IoError)
+    }
+}
@@ -0,0 +1,103 @@
+//! Execution time of searches, reports and requests
+//!
+//! To tell whether lag comes from searching, report generation or rendering:
+//! - Query and report methods time themselves with [`QueryTimer`]; the API
+//!   records whole requests, rendering included, through [`Ledger::record_timing`]
+//! - Operations slower than `logging.slow_query_ms` are logged with their
+//!   parameters and result size
+//! - The slowest of them are kept in memory, across reloads, for
+//!   `/api/admin/slow`
+
+use crate::account_filter::AccountFilter;
+use crate::{Ledger, TimeContext};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Slow operations kept for [`Ledger::slow_queries`]
+const SLOWEST_KEPT: usize = 20;
+
+/// One timed operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryTiming {
+    /// Method name, or `GET /path` for requests
+    pub operation: String,
+    pub params: String,
+    pub millis: f64,
+    /// Items returned, or bytes for requests
+    pub results: usize,
+    /// When it finished (RFC 3339)
+    pub at: String,
+}
+
+/// The slowest operations seen, slowest first
+#[derive(Debug, Default)]
+pub(crate) struct SlowLog {
+    slowest: Vec<QueryTiming>,
+}
+
+impl SlowLog {
+    fn insert(&mut self, timing: QueryTiming) {
+        let position = self.slowest.partition_point(|t| t.millis >= timing.millis);
+        if position < SLOWEST_KEPT {
+            self.slowest.insert(position, timing);
+            self.slowest.truncate(SLOWEST_KEPT);
+        }
+    }
+}
+
+/// Started by a query method, finished with the size of its result
+pub(crate) struct QueryTimer {
+    operation: &'static str,
+    params: String,
+    started: Instant,
+}
+
+impl QueryTimer {
+    pub(crate) fn start(operation: &'static str, params: String) -> Self {
+        Self { operation, params, started: Instant::now() }
+    }
+
+    pub(crate) fn finish(self, ledger: &Ledger, results: usize) {
+        ledger.record_timing(self.operation, &self.params, self.started.elapsed(), results);
+    }
+}
+
+/// `start..end` of a report period, either side empty when open
+pub(crate) fn period(context: &TimeContext) -> String {
+    let date = |d: Option<chrono::NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+    format!("{}..{}", date(context.start_date()), date(context.end_date()))
+}
+
+/// Account filters as typed, comma separated
+pub(crate) fn filters(account_filters: &[AccountFilter]) -> String {
+    account_filters.iter().map(|f| f.to_string()).collect::<Vec<_>>().join(",")
+}
+
+impl Ledger {
+    /// Threshold from `logging.slow_query_ms`
+    pub fn slow_query_threshold(&self) -> Duration {
+        Duration::from_millis(self.config.logging.slow_query_ms)
+    }
+
+    /// Log and keep `operation` when it took longer than the threshold
+    pub fn record_timing(&self, operation: &str, params: &str, elapsed: Duration, results: usize) {
+        if elapsed < self.slow_query_threshold() {
+            return;
+        }
+        let millis = elapsed.as_secs_f64() * 1000.0;
+        tracing::warn!("Slow {} ({}): {:.1}ms, {} results", operation, params, millis, results);
+        self.timings.lock().unwrap().insert(QueryTiming {
+            operation: operation.to_string(),
+            params: params.to_string(),
+            millis,
+            results,
+            at: Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Slowest operations since startup, slowest first
+    pub fn slow_queries(&self) -> Vec<QueryTiming> {
+        self.timings.lock().unwrap().slowest.clone()
+    }
+}
@@ -0,0 +1,408 @@
+//! Bank transactions imported from CSV
+//!
+//! A bank export becomes transactions on one account, each balanced by an
+//! elided posting on a counter account (`Expenses:Uncategorized` unless
+//! chosen, so they show up for review; single rows can be given another one).
+//! By default each row is `date,amount,payee,narration,external_id,currency`;
+//! a header row is optional, as is every column after the amount, and an
+//! empty currency means the default one. Other layouts are described by a
+//! [`CsvColumns`] mapping, by position or by header:
+//! - The bank's transaction id is written as `external_id:` metadata
+//! - Rows whose external_id is already in the ledger (looked up in the
+//!   transaction index) or earlier in the CSV are skipped as duplicates, so
+//!   importing an overlapping date range again is safe
+//! - Rows without an external_id are duplicates when the ledger already has
+//!   the same amount on the account that day
+//! - Rows dated outside the time either account is open are invalid and
+//!   never written
+//! - Only the rows picked after the preview (`lines`) are written, when given
+//! - Rows without a counter account of their own get the account and tags of
+//!   the first matching categorization rule (see [`crate::rules`]), before
+//!   the import-wide one
+
+use crate::balance_import::{parse_number, split_csv_line, validate_target};
+use crate::bootstrap::{add_include, is_valid_currency};
+use crate::render::quote;
+use crate::rules::{RuleSet, RuleSubject};
+use crate::{CoreError, Ledger};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Metadata key holding the bank's transaction id
+pub const EXTERNAL_ID: &str = "external_id";
+
+/// File the transactions are written to unless another one is chosen
+pub const DEFAULT_IMPORT_FILE: &str = "imports.bean";
+
+/// Counter account unless another one is chosen
+pub const DEFAULT_COUNTER_ACCOUNT: &str = "Expenses:Uncategorized";
+
+/// Outcome of checking one CSV row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionRowStatus {
+    /// Becomes a transaction
+    Ok,
+    /// Its external_id is in the ledger, or an earlier row has it
+    Duplicate,
+    /// Can't be turned into a transaction
+    Invalid,
+}
+
+/// One checked CSV row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionImportRow {
+    /// 1-based line in the CSV
+    pub line: usize,
+    pub date: String,
+    /// Units posted to the imported account
+    pub amount: String,
+    pub currency: String,
+    pub payee: String,
+    pub narration: String,
+    pub external_id: Option<String>,
+    pub counter_account: String,
+    /// Tags of the categorization rule that chose the counter account
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Name of that rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub status: TransactionRowStatus,
+    pub message: Option<String>,
+}
+
+/// Checked CSV, before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionImportPreview {
+    pub rows: Vec<TransactionImportRow>,
+}
+
+impl TransactionImportPreview {
+    pub fn count(&self, status: TransactionRowStatus) -> usize {
+        self.rows.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Result of writing the transactions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionImportOutcome {
+    /// Written file, relative to the data directory
+    pub file: String,
+    pub written: usize,
+    /// Duplicate and invalid rows
+    pub skipped: usize,
+    /// Whether an `include` was added to the main file
+    pub include_added: bool,
+    pub preview: TransactionImportPreview,
+}
+
+/// A CSV column, by 0-based position or by its header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    Index(usize),
+    Header(String),
+}
+
+/// Where the fields are in a bank's CSV; the default is the
+/// `date,amount,payee,narration,external_id,currency` layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvColumns {
+    pub date: CsvColumn,
+    pub amount: CsvColumn,
+    #[serde(default)]
+    pub payee: Option<CsvColumn>,
+    #[serde(default)]
+    pub narration: Option<CsvColumn>,
+    #[serde(default)]
+    pub external_id: Option<CsvColumn>,
+    #[serde(default)]
+    pub currency: Option<CsvColumn>,
+    /// chrono format of the dates (`%d.%m.%Y`), when neither YYYY-MM-DD nor
+    /// YYYY/MM/DD
+    #[serde(default)]
+    pub date_format: Option<String>,
+    /// The bank shows money going out as positive
+    #[serde(default)]
+    pub negate: bool,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        Self {
+            date: CsvColumn::Index(0),
+            amount: CsvColumn::Index(1),
+            payee: Some(CsvColumn::Index(2)),
+            narration: Some(CsvColumn::Index(3)),
+            external_id: Some(CsvColumn::Index(4)),
+            currency: Some(CsvColumn::Index(5)),
+            date_format: None,
+            negate: false,
+        }
+    }
+}
+
+/// Positions of the mapped columns
+struct Positions {
+    date: usize,
+    amount: usize,
+    payee: Option<usize>,
+    narration: Option<usize>,
+    external_id: Option<usize>,
+    currency: Option<usize>,
+}
+
+impl CsvColumns {
+    fn columns(&self) -> [Option<&CsvColumn>; 6] {
+        [Some(&self.date), Some(&self.amount), self.payee.as_ref(), self.narration.as_ref(), self.external_id.as_ref(), self.currency.as_ref()]
+    }
+
+    /// Whether a column is named, so the first row has to be the header
+    fn by_header(&self) -> bool {
+        self.columns().iter().flatten().any(|c| matches!(c, CsvColumn::Header(_)))
+    }
+
+    /// Positions of the columns, headers looked up in `header`
+    fn positions(&self, header: &[String]) -> Result<Positions, CoreError> {
+        let position = |column: &CsvColumn| match column {
+            CsvColumn::Index(i) => Ok(*i),
+            CsvColumn::Header(name) => header.iter().position(|h| h.trim() == name.trim())
+                .ok_or_else(|| CoreError::ValidationError { message: format!("No column named {} in the CSV header", name) }),
+        };
+        let optional = |column: &Option<CsvColumn>| column.as_ref().map(position).transpose();
+        Ok(Positions {
+            date: position(&self.date)?,
+            amount: position(&self.amount)?,
+            payee: optional(&self.payee)?,
+            narration: optional(&self.narration)?,
+            external_id: optional(&self.external_id)?,
+            currency: optional(&self.currency)?,
+        })
+    }
+}
+
+/// Options of an import
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionImportOptions {
+    /// Account the export is for
+    pub account: String,
+    /// Account balancing each row, [`DEFAULT_COUNTER_ACCOUNT`] when None
+    #[serde(default)]
+    pub counter_account: Option<String>,
+    /// Counter account of single rows, by CSV line
+    #[serde(default)]
+    pub counter_accounts: HashMap<usize, String>,
+    /// Layout of the CSV, the default one when None
+    #[serde(default)]
+    pub columns: Option<CsvColumns>,
+    /// CSV lines to write; every new row when None
+    #[serde(default)]
+    pub lines: Option<Vec<usize>>,
+    /// Leave the categorization rules out
+    #[serde(default)]
+    pub skip_rules: bool,
+}
+
+impl TransactionImportOptions {
+    fn counter_account(&self) -> &str {
+        self.counter_account.as_deref().filter(|a| !a.is_empty()).unwrap_or(DEFAULT_COUNTER_ACCOUNT)
+    }
+
+    /// Counter account chosen for the row on `line`
+    fn chosen_counter_account(&self, line: usize) -> Option<&str> {
+        self.counter_accounts.get(&line).map(|a| a.trim()).filter(|a| !a.is_empty())
+    }
+}
+
+fn parse_date(text: &str, format: Option<&str>) -> Option<NaiveDate> {
+    format.and_then(|format| NaiveDate::parse_from_str(text, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y/%m/%d").ok())
+}
+
+impl Ledger {
+    /// Check CSV rows against the ledger without writing anything; fails when
+    /// either account doesn't exist
+    pub fn preview_transaction_import(&self, csv: &str, options: &TransactionImportOptions) -> Result<TransactionImportPreview, CoreError> {
+        let default_currency = self.report_currency();
+        let rules = if options.skip_rules { std::sync::Arc::new(RuleSet::default()) } else { self.rule_set()? };
+        let data = self.data.read().unwrap();
+        let find = |name: &str| data.accounts.iter().find(|a| a.name == name)
+            .ok_or_else(|| CoreError::ValidationError { message: format!("Unknown account: {}", name) });
+        let account = find(&options.account)?;
+        find(options.counter_account())?;
+        let columns = options.columns.clone().unwrap_or_default();
+        let mut positions = if columns.by_header() { None } else { Some(columns.positions(&[])?) };
+        let mut seen = HashSet::new();
+        let mut rows = Vec::new();
+
+        for (index, line) in csv.trim_start_matches('\u{feff}').lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = if line.contains('\t') {
+                line.split('\t').map(|f| f.trim().to_string()).collect()
+            } else {
+                split_csv_line(line)
+            };
+            let Some(at) = &positions else {
+                positions = Some(columns.positions(&fields)?);
+                continue;
+            };
+            let field = |i: Option<usize>| i.and_then(|i| fields.get(i)).cloned().unwrap_or_default();
+            // Header row: no digits where the first date would be
+            if index == 0 && !field(Some(at.date)).chars().any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let mut row = TransactionImportRow {
+                line: index + 1,
+                date: field(Some(at.date)),
+                amount: field(Some(at.amount)),
+                currency: Some(field(at.currency)).filter(|c| !c.is_empty()).unwrap_or_else(|| default_currency.clone()),
+                payee: field(at.payee),
+                narration: field(at.narration),
+                external_id: Some(field(at.external_id)).filter(|id| !id.is_empty()),
+                counter_account: options.counter_account().to_string(),
+                tags: Vec::new(),
+                rule: None,
+                status: TransactionRowStatus::Invalid,
+                message: None,
+            };
+            // A counter account picked for the row, else the first matching rule's
+            if let Some(chosen) = options.chosen_counter_account(row.line) {
+                row.counter_account = chosen.to_string();
+            } else if !rules.is_empty() {
+                let subject = RuleSubject {
+                    payee: row.payee.clone(),
+                    narration: row.narration.clone(),
+                    amount: parse_number(&row.amount).and_then(|a| a.parse().ok()),
+                    metadata: row.external_id.iter().map(|id| (EXTERNAL_ID.to_string(), id.clone())).collect(),
+                };
+                if let Some(rule) = rules.matching(&subject) {
+                    row.counter_account = rule.account.clone();
+                    row.tags = rule.tags.clone();
+                    row.rule = Some(rule.name.clone());
+                }
+            }
+
+            let Ok(counter) = find(&row.counter_account) else {
+                row.message = Some(format!("未知账户：{}", row.counter_account));
+                rows.push(row);
+                continue;
+            };
+            let Some(date) = parse_date(&row.date, columns.date_format.as_deref()) else {
+                row.message = Some(format!("日期无效：{}", row.date));
+                rows.push(row);
+                continue;
+            };
+            row.date = date.format("%Y-%m-%d").to_string();
+            let Some(amount) = parse_number(&row.amount) else {
+                row.message = Some(format!("金额无效：{}", row.amount));
+                rows.push(row);
+                continue;
+            };
+            row.amount = match amount.strip_prefix('-') {
+                Some(positive) if columns.negate => positive.to_string(),
+                None if columns.negate => format!("-{}", amount),
+                _ => amount,
+            };
+            if !is_valid_currency(&row.currency) {
+                row.message = Some(format!("货币无效：{}", row.currency));
+                rows.push(row);
+                continue;
+            }
+            if let Some(message) = [account, counter].iter().find_map(|a| a.inactive_on(&row.date)) {
+                row.message = Some(message);
+                rows.push(row);
+                continue;
+            }
+
+            match row.external_id.as_deref() {
+                Some(id) if data.index.by_external_id(id).is_some() => {
+                    row.status = TransactionRowStatus::Duplicate;
+                    row.message = Some("账本中已有该交易".to_string());
+                }
+                Some(id) if !seen.insert(id.to_string()) => {
+                    row.status = TransactionRowStatus::Duplicate;
+                    row.message = Some("CSV 中重复".to_string());
+                }
+                Some(_) => row.status = TransactionRowStatus::Ok,
+                None => {
+                    let units = row.amount.parse::<crate::Decimal>().ok();
+                    let booked = data.transactions.iter()
+                        .filter(|tx| tx.date == row.date)
+                        .flat_map(crate::links::posting_units)
+                        .any(|((account, currency), amount)| account == options.account && currency == row.currency && Some(amount) == units);
+                    if booked {
+                        row.status = TransactionRowStatus::Duplicate;
+                        row.message = Some("账本中已有当天相同金额的交易".to_string());
+                    } else {
+                        row.status = TransactionRowStatus::Ok;
+                    }
+                }
+            }
+            rows.push(row);
+        }
+
+        Ok(TransactionImportPreview { rows })
+    }
+
+    /// Append the new rows as transactions to `file` (relative to the data
+    /// directory), oldest first, and include it from the main file
+    pub fn import_transactions(&self, csv: &str, file: &str, options: &TransactionImportOptions) -> Result<TransactionImportOutcome, CoreError> {
+        let file = validate_target(file)?;
+        let preview = self.preview_transaction_import(csv, options)?;
+        let mut selected: Vec<&TransactionImportRow> = preview.rows.iter()
+            .filter(|r| r.status == TransactionRowStatus::Ok)
+            .filter(|r| options.lines.as_ref().is_none_or(|lines| lines.contains(&r.line)))
+            .collect();
+        selected.sort_by(|a, b| a.date.cmp(&b.date));
+        if selected.is_empty() {
+            return Err(CoreError::ValidationError { message: "No new transactions to import".to_string() });
+        }
+
+        let main_path = self.config.ledger_path();
+        let path = self.config.data.path.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| CoreError::IoError)?;
+        }
+        let mut body = format!(";; Imported transactions of {} ({})\n", options.account, chrono::Local::now().format("%Y-%m-%d"));
+        for row in &selected {
+            let tags: String = row.tags.iter().map(|t| format!(" #{}", t)).collect();
+            body.push_str(&format!("\n{} * {} {}{}\n", row.date, quote(&row.payee), quote(&row.narration), tags));
+            if let Some(id) = &row.external_id {
+                body.push_str(&format!("  {}: {}\n", EXTERNAL_ID, quote(id)));
+            }
+            body.push_str(&format!("  {}  {} {}\n", options.account, row.amount, row.currency));
+            body.push_str(&format!("  {}\n", row.counter_account));
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(current) if !current.trim().is_empty() => format!("{}\n\n{}", current.trim_end(), body),
+            _ => body,
+        };
+        self.write_document(&path.to_string_lossy(), &content)?;
+
+        let include_added = if path == main_path {
+            false
+        } else {
+            let main = std::fs::read_to_string(&main_path).unwrap_or_default();
+            match add_include(&main, file) {
+                Some(updated) => {
+                    self.write_document(&main_path.to_string_lossy(), &updated)?;
+                    true
+                }
+                None => false,
+            }
+        };
+
+        Ok(TransactionImportOutcome {
+            file: file.to_string(),
+            written: selected.len(),
+            skipped: preview.rows.len() - selected.len(),
+            include_added,
+            preview,
+        })
+    }
+}
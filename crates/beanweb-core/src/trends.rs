@@ -0,0 +1,237 @@
+//! Monthly trends per income/expense category and per account
+//!
+//! Backs the mini-charts in the report tables. Each series covers the months
+//! up to the end of the current time range (today for open-ended ranges),
+//! oldest first, with the report's natural signs: income earned and money
+//! spent are positive. Amounts without a price into the operating currency
+//! are left out, as in the report totals.
+//!
+//! [`Ledger::account_monthly`] is the per-account table on the account detail
+//! page: money in and out, net and ending balance per month, in the account's
+//! currency and with its displayed sign.
+//!
+//! [`Ledger::monthly_summary_report`] totals income and expenses per calendar
+//! month of one year, with the same conversion and signs as the income/expense
+//! report.
+
+use crate::{decimal_string, parse_decimal, Decimal, IncomeExpenseEntry, Ledger, MonthlySummary, MonthlySummaryReport, TimeContext};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Months shown in the report table charts
+pub const TREND_MONTHS: usize = 6;
+
+/// Months in the account detail table
+pub const ACCOUNT_MONTHS: usize = 12;
+
+/// Monthly totals per report entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryTrends {
+    /// YYYY-MM, oldest first
+    pub months: Vec<String>,
+    /// Income entry (account or report group) -> one value per month
+    pub income: HashMap<String, Vec<f64>>,
+    /// Expense entry (account or report group) -> one value per month
+    pub expenses: HashMap<String, Vec<f64>>,
+}
+
+impl CategoryTrends {
+    /// Series of `category`: the entry itself, or the sum of its sub-accounts
+    pub fn series(&self, category: &str) -> Option<Vec<f64>> {
+        let prefix = format!("{}:", category);
+        let mut total: Option<Vec<f64>> = None;
+        for (name, values) in self.income.iter().chain(&self.expenses) {
+            if name == category || name.starts_with(&prefix) {
+                let sum = total.get_or_insert_with(|| vec![0.0; self.months.len()]);
+                sum.iter_mut().zip(values).for_each(|(s, v)| *s += v);
+            }
+        }
+        total
+    }
+}
+
+/// One month of an account's activity
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountMonth {
+    /// YYYY-MM
+    pub month: String,
+    /// Postings that increased the displayed balance
+    pub inflow: f64,
+    /// Postings that decreased it, as a positive number
+    pub outflow: f64,
+    /// `inflow - outflow`
+    pub net: f64,
+    /// Balance at the end of the month
+    pub ending_balance: f64,
+    /// Postings left out for lack of a price into the account's currency
+    pub unconverted: usize,
+}
+
+/// Monthly activity of one account, oldest month first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountMonthly {
+    pub account: String,
+    pub currency: String,
+    pub months: Vec<AccountMonth>,
+}
+
+/// First day of each of the `months` months ending with the month of `end`, oldest first
+pub(crate) fn month_starts(end: NaiveDate, months: usize) -> Vec<NaiveDate> {
+    let Some(last) = NaiveDate::from_ymd_opt(end.year(), end.month(), 1) else {
+        return Vec::new();
+    };
+    (0..months)
+        .rev()
+        .filter_map(|back| last.checked_sub_months(Months::new(back as u32)))
+        .collect()
+}
+
+fn add_month(series: &mut HashMap<String, Vec<f64>>, entries: &[IncomeExpenseEntry], month: usize, months: usize) {
+    for entry in entries.iter().filter(|e| !e.unconverted) {
+        let values = series.entry(entry.account.clone()).or_insert_with(|| vec![0.0; months]);
+        values[month] += parse_decimal(&entry.amount).to_f64().unwrap_or(0.0);
+    }
+}
+
+impl Ledger {
+    /// The last `months` months of every income/expense entry; with `grouped`,
+    /// entries follow the configured report groups
+    pub fn category_trends(&self, months: usize, grouped: bool) -> CategoryTrends {
+        let context = self.time_context();
+        let today = Utc::now().date_naive();
+        let end = context.end_date().map_or(today, |end| end.min(today));
+        let Some(last) = NaiveDate::from_ymd_opt(end.year(), end.month(), 1) else {
+            return CategoryTrends::default();
+        };
+
+        let mut trends = CategoryTrends::default();
+        for month in 0..months {
+            let Some(start) = last.checked_sub_months(Months::new((months - 1 - month) as u32)) else {
+                continue;
+            };
+            let month_end = start.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()).unwrap_or(start);
+            let mut period = TimeContext::custom(start, month_end);
+            period.include_future = context.include_future;
+            let mut report = self.natural_income_expense_report_in(&period);
+            if grouped {
+                let groups = &self.config.reports;
+                report.income_entries = Self::group_report_entries(report.income_entries, parse_decimal(&report.total_income), groups);
+                report.expense_entries = Self::group_report_entries(report.expense_entries, parse_decimal(&report.total_expenses), groups);
+            }
+            trends.months.push(start.format("%Y-%m").to_string());
+            add_month(&mut trends.income, &report.income_entries, month, months);
+            add_month(&mut trends.expenses, &report.expense_entries, month, months);
+        }
+        trends
+    }
+
+    /// In, out, net and ending balance of `account` for the last `months`
+    /// months up to the end of the current time range (never past today)
+    /// Postings in another currency are converted at their date when a price
+    /// is known; sub-accounts are not included
+    pub fn account_monthly(&self, account: &str, months: usize) -> AccountMonthly {
+        let context = self.time_context();
+        let today = Utc::now().date_naive();
+        let end = context.end_date().map_or(today, |end| end.min(today));
+        let starts = month_starts(end, months);
+        let currency = self.account(account)
+            .and_then(|a| a.currency)
+            .unwrap_or_else(|| self.report_currency());
+
+        let mut result = AccountMonthly {
+            account: account.to_string(),
+            currency: currency.clone(),
+            months: starts.iter()
+                .map(|start| AccountMonth { month: start.format("%Y-%m").to_string(), ..Default::default() })
+                .collect(),
+        };
+        if starts.is_empty() {
+            return result;
+        }
+        let last_day = end.to_string();
+
+        let data = self.data.read().unwrap();
+        // Balance carried into the first month, then each month's postings
+        let mut opening = Decimal::ZERO;
+        let mut flows: Vec<(Decimal, Decimal, usize)> = vec![(Decimal::ZERO, Decimal::ZERO, 0); starts.len()];
+        for tx in data.transactions.iter().filter(|t| t.date.as_str() <= last_day.as_str()) {
+            for ((posting_account, posting_currency), units) in crate::links::posting_units(tx) {
+                if posting_account != account {
+                    continue;
+                }
+                let month = starts.iter().rposition(|start| start.to_string().as_str() <= tx.date.as_str());
+                let Some(value) = data.prices.convert(units, &posting_currency, &currency, &tx.date) else {
+                    if let Some(month) = month {
+                        flows[month].2 += 1;
+                    }
+                    continue;
+                };
+                let value = self.display_amount(account, value);
+                match month {
+                    Some(month) => {
+                        if value.is_sign_negative() {
+                            flows[month].1 -= value;
+                        } else {
+                            flows[month].0 += value;
+                        }
+                    }
+                    None => opening += value,
+                }
+            }
+        }
+        drop(data);
+
+        let mut balance = opening;
+        for (month, (inflow, outflow, unconverted)) in result.months.iter_mut().zip(flows) {
+            balance += inflow - outflow;
+            month.inflow = inflow.to_f64().unwrap_or(0.0);
+            month.outflow = outflow.to_f64().unwrap_or(0.0);
+            month.net = (inflow - outflow).to_f64().unwrap_or(0.0);
+            month.ending_balance = balance.to_f64().unwrap_or(0.0);
+            month.unconverted = unconverted;
+        }
+        result
+    }
+
+    /// Income, expenses and net income of each month of `year`, January first
+    /// Amounts follow [`Ledger::income_expense_report`]: converted into the
+    /// operating currency, unpriced amounts left out, income in the display sign
+    pub fn monthly_summary_report(&self, year: i32) -> MonthlySummaryReport {
+        let include_future = self.time_context().include_future;
+        let mut summaries = Vec::new();
+        let (mut total_income, mut total_expenses, mut total_net) = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        for month in 1..=12 {
+            let Some(start) = NaiveDate::from_ymd_opt(year, month, 1) else {
+                continue;
+            };
+            let end = start.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()).unwrap_or(start);
+            let mut period = TimeContext::custom(start, end);
+            period.include_future = include_future;
+            let report = self.income_expense_report_in(&period);
+            let (income, expenses, net) = (
+                parse_decimal(&report.total_income),
+                parse_decimal(&report.total_expenses),
+                parse_decimal(&report.net_income),
+            );
+            total_income += income;
+            total_expenses += expenses;
+            total_net += net;
+            summaries.push(MonthlySummary {
+                month: start.format("%Y-%m").to_string(),
+                income: decimal_string(income),
+                expenses: decimal_string(expenses),
+                net: decimal_string(net),
+            });
+        }
+        MonthlySummaryReport {
+            summaries,
+            year,
+            total_income: decimal_string(total_income),
+            total_expenses: decimal_string(total_expenses),
+            total_net: decimal_string(total_net),
+            currency: self.report_currency(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Spending velocity: how fast money goes out this month
+//!
+//! The dashboard's pace card compares the month so far with the month before:
+//! - `daily_average`: expenses of the month up to the reference day, divided
+//!   by the days elapsed (the reference day counts as elapsed)
+//! - `last_month_daily_average`: all of the previous month's expenses over
+//!   its number of days
+//! - `projected`: the daily average carried to the end of the month
+//!
+//! The reference day is the end of the selected range, or today when the
+//! range runs past it, so a past month shows its final pace. Expenses are
+//! converted and signed as in the income/expense report.
+
+use crate::{decimal_string, parse_decimal, percent_of, Decimal, Ledger, TimeContext};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Spending pace of the month holding the reference day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpendingVelocity {
+    /// Reference day (YYYY-MM-DD)
+    pub date: String,
+    pub days_elapsed: u32,
+    pub days_in_month: u32,
+    /// Expenses from the first of the month to the reference day
+    pub spent: String,
+    pub daily_average: String,
+    pub last_month_daily_average: String,
+    /// Change of the daily average against last month, in percent; None
+    /// when nothing was spent last month
+    pub change_percent: Option<f64>,
+    /// Expenses at month end at the current pace
+    pub projected: String,
+    pub currency: String,
+}
+
+/// First and last day of the month `date` is in
+fn month_bounds(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = date.with_day(1).unwrap_or(date);
+    let last = first.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()).unwrap_or(date);
+    (first, last)
+}
+
+impl Ledger {
+    /// Spending pace of the month at the end of `context` (see module docs)
+    pub fn spending_velocity(&self, context: &TimeContext) -> SpendingVelocity {
+        let today = Utc::now().date_naive();
+        let day = context.end_date().map_or(today, |end| end.min(today));
+        self.spending_velocity_on(day)
+    }
+
+    /// Spending pace of the month holding `day`, up to `day`
+    pub fn spending_velocity_on(&self, day: NaiveDate) -> SpendingVelocity {
+        let (first, last) = month_bounds(day);
+        let (previous_first, previous_last) = month_bounds(first.pred_opt().unwrap_or(first));
+        let expenses = |start, end| {
+            let report = self.natural_income_expense_report_in(&TimeContext::custom(start, end));
+            parse_decimal(&report.total_expenses)
+        };
+
+        let spent = expenses(first, day);
+        let days_elapsed = day.day();
+        let days_in_month = last.day();
+        let daily_average = spent / Decimal::from(days_elapsed);
+        let last_month_daily_average = expenses(previous_first, previous_last) / Decimal::from(previous_last.day());
+        let change_percent = (last_month_daily_average > Decimal::ZERO)
+            .then(|| percent_of(daily_average - last_month_daily_average, last_month_daily_average));
+        let projected = daily_average * Decimal::from(days_in_month);
+
+        SpendingVelocity {
+            date: day.format("%Y-%m-%d").to_string(),
+            days_elapsed,
+            days_in_month,
+            spent: decimal_string(spent),
+            daily_average: decimal_string(daily_average.round_dp(2)),
+            last_month_daily_average: decimal_string(last_month_daily_average.round_dp(2)),
+            change_percent,
+            projected: decimal_string(projected.round_dp(2)),
+            currency: self.report_currency(),
+        }
+    }
+}
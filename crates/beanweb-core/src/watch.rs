@@ -0,0 +1,107 @@
+//! Change detection for the ledger files
+//!
+//! A [`FileSnapshot`] records size and modification time of every ledger
+//! file (`.bean`, `.beancount`, `.bc`) under the data directory; comparing two snapshots tells which files
+//! were added, changed or removed. The watcher polls snapshots rather than
+//! relying on OS notifications, which also works on network mounts and in
+//! containers where inotify events don't arrive.
+
+use crate::includes::LEDGER_EXTENSIONS;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Directories never descended into (VCS metadata, editor state)
+const SKIPPED_DIRS: [&str; 3] = [".git", ".hg", "node_modules"];
+
+/// Size and modification time of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// State of all ledger files under a directory at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSnapshot {
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl FileSnapshot {
+    /// Scan `dir` recursively for ledger files; unreadable entries are skipped
+    pub fn scan(dir: &Path) -> Self {
+        let mut snapshot = Self::default();
+        snapshot.scan_dir(dir);
+        snapshot
+    }
+
+    fn scan_dir(&mut self, dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                let name = entry.file_name();
+                if !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                    self.scan_dir(&path);
+                }
+            } else if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| LEDGER_EXTENSIONS.contains(&ext)) {
+                self.files.insert(path, FileStamp { len: metadata.len(), modified: metadata.modified().ok() });
+            }
+        }
+    }
+
+    /// Number of files in the snapshot
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files added, modified or removed since `previous`, sorted
+    pub fn changed_since(&self, previous: &FileSnapshot) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self.files.iter()
+            .filter(|(path, stamp)| previous.files.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(previous.files.keys().filter(|path| !self.files.contains_key(*path)).cloned());
+        changed.sort();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_detects_changes() {
+        let dir = std::env::temp_dir().join(format!("beanweb-watch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("2024")).unwrap();
+        std::fs::write(dir.join("main.bean"), "include \"2024/*.bean\"\n").unwrap();
+        std::fs::write(dir.join("2024/jan.bean"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let before = FileSnapshot::scan(&dir);
+        assert_eq!(before.len(), 2);
+        assert!(FileSnapshot::scan(&dir).changed_since(&before).is_empty());
+
+        std::fs::write(dir.join("2024/jan.bean"), "2024-01-01 open Assets:Cash\n").unwrap();
+        std::fs::write(dir.join("2024/feb.bean"), "").unwrap();
+        std::fs::write(dir.join("2024/mar.beancount"), "").unwrap();
+        std::fs::remove_file(dir.join("main.bean")).unwrap();
+        let after = FileSnapshot::scan(&dir);
+        assert_eq!(
+            after.changed_since(&before),
+            vec![dir.join("2024/feb.bean"), dir.join("2024/jan.bean"), dir.join("2024/mar.beancount"), dir.join("main.bean")]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+//! Parser benchmarks on synthetic ledgers of 10k and 100k transactions
+//!
+//! - `parse`: [`SimpleBeancountParser::parse`] on one in-memory file
+//! - `parse_file`: the same ledger split into yearly include files, read
+//!   from disk by [`DefaultBeancountParser`]
+//!
+//! `cargo bench -p beanweb-parser -- --save-baseline main` on the base branch,
+//! then `-- --baseline main` on a change reports regressions against it.
+
+use beanweb_parser::{BeancountParserTrait, DefaultBeancountParser, SimpleBeancountParser};
+use beanweb_utils::synthetic::SyntheticLedger;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+
+fn ledger(transactions: usize) -> SyntheticLedger {
+    SyntheticLedger::new(transactions).end_year(2024)
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for size in SIZES {
+        let text = ledger(size).render();
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &text, |b, text| {
+            b.iter(|| SimpleBeancountParser::parse(text).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse_file(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let parser = DefaultBeancountParser;
+    let mut group = c.benchmark_group("parse_file");
+    group.sample_size(10);
+    for size in SIZES {
+        let dir = std::env::temp_dir().join(format!("beanweb-bench-includes-{}-{}", std::process::id(), size));
+        let main = ledger(size).write_to(&dir).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &main, |b, main| {
+            b.iter(|| runtime.block_on(parser.parse_file(main.clone())).unwrap())
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_parse_file);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+//! Parsed files kept between loads for incremental reloads
+//!
+//! A save in the UI changes one file, yet a reload used to parse every file
+//! the main file includes. [`ParseCache`] keeps each file's directives with
+//! what identified its content, so the next load only parses what changed:
+//! - Same modification time and size: reused without reading the file,
+//!   unless it was modified within [`RACY_WINDOW`] of being read (coarse
+//!   timestamps could hide a second write)
+//! - Otherwise the file is read and hashed; same hash: reused as well
+//! - Include directives stay in the cached directives and are resolved again
+//!   on every load, so new files matched by a glob are picked up
+//!
+//! Files the last load no longer reached are dropped from the cache.
+
+use crate::{FileParseStats, ParseError, SpannedDirective};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Files modified this close to being read are checked by content
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// One file as last parsed
+#[derive(Debug, Clone)]
+struct CachedFile {
+    modified: Option<SystemTime>,
+    size: u64,
+    hash: u64,
+    /// When the file was read for `hash`
+    read_at: SystemTime,
+    /// Directives in file order, include directives still in place
+    directives: Vec<SpannedDirective>,
+    /// Stats of the parse that produced `directives`
+    stats: FileParseStats,
+}
+
+/// Parsed files by path, see the module docs
+#[derive(Debug, Clone, Default)]
+pub struct ParseCache {
+    files: HashMap<PathBuf, CachedFile>,
+    /// Files reached by the load in progress or the last one
+    visited: HashSet<PathBuf>,
+    /// Files the last load actually parsed
+    parsed: Vec<PathBuf>,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ParseCache {
+    /// Number of files cached
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files the last load parsed; every other file it reached was reused
+    pub fn parsed(&self) -> &[PathBuf] {
+        &self.parsed
+    }
+
+    /// Forget every file, so the next load parses everything
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn begin(&mut self) {
+        self.visited.clear();
+        self.parsed.clear();
+    }
+
+    /// Drop the files the load that just ended did not reach
+    pub(crate) fn finish(&mut self) {
+        let visited = &self.visited;
+        self.files.retain(|path, _| visited.contains(path));
+    }
+
+    /// Directives and stats of `path`, parsed with `parse` only when the file changed
+    pub(crate) async fn get_or_parse(
+        &mut self,
+        path: &Path,
+        parse: impl FnOnce(&str) -> (Vec<SpannedDirective>, FileParseStats),
+    ) -> Result<(Vec<SpannedDirective>, FileParseStats), ParseError> {
+        self.visited.insert(path.to_path_buf());
+        let metadata = tokio::fs::metadata(path).await?;
+        let modified = metadata.modified().ok();
+        if let Some(cached) = self.files.get(path) {
+            let settled = modified.is_some_and(|m| m + RACY_WINDOW < cached.read_at);
+            if settled && cached.modified == modified && cached.size == metadata.len() {
+                return Ok((cached.directives.clone(), cached.stats.clone()));
+            }
+        }
+
+        let read_at = SystemTime::now();
+        let content = tokio::fs::read_to_string(path).await?;
+        let hash = content_hash(&content);
+        if let Some(cached) = self.files.get_mut(path) {
+            if cached.hash == hash {
+                // Touched but not changed
+                cached.modified = modified;
+                cached.read_at = read_at;
+                cached.size = content.len() as u64;
+                return Ok((cached.directives.clone(), cached.stats.clone()));
+            }
+        }
+
+        let (directives, stats) = parse(&content);
+        self.parsed.push(path.to_path_buf());
+        self.files.insert(path.to_path_buf(), CachedFile {
+            modified,
+            size: content.len() as u64,
+            hash,
+            read_at,
+            directives: directives.clone(),
+            stats: stats.clone(),
+        });
+        Ok((directives, stats))
+    }
+}
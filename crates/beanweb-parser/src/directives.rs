@@ -25,12 +25,34 @@ pub enum Directive {
     Price(PriceDirective),
     Event(EventDirective),
     Note(NoteDirective),
+    Query(QueryDirective),
     Option(OptionDirective),
     Include(IncludeDirective),
     Custom(CustomDirective),
     Comment(CommentDirective),
 }
 
+impl Directive {
+    /// Get the directive date (None for option, include and comment)
+    pub fn date(&self) -> Option<&Date> {
+        match self {
+            Directive::Transaction(d) => Some(&d.date),
+            Directive::Open(d) => Some(&d.date),
+            Directive::Close(d) => Some(&d.date),
+            Directive::Balance(d) => Some(&d.date),
+            Directive::Pad(d) => Some(&d.date),
+            Directive::Commodity(d) => Some(&d.date),
+            Directive::Document(d) => Some(&d.date),
+            Directive::Price(d) => Some(&d.date),
+            Directive::Event(d) => Some(&d.date),
+            Directive::Note(d) => Some(&d.date),
+            Directive::Query(d) => Some(&d.date),
+            Directive::Custom(d) => Some(&d.date),
+            Directive::Option(_) | Directive::Include(_) | Directive::Comment(_) => None,
+        }
+    }
+}
+
 /// Transaction directive
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
@@ -81,6 +103,9 @@ pub struct PadDirective {
 pub struct CommodityDirective {
     pub date: Date,
     pub name: String,
+    /// Metadata lines below the directive (e.g. `name:`, `asset-class:`)
+    #[serde(default)]
+    pub meta: Meta,
 }
 
 /// Document directive
@@ -115,6 +140,14 @@ pub struct NoteDirective {
     pub comment: String,
 }
 
+/// Query directive (a named BQL query)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryDirective {
+    pub date: Date,
+    pub name: String,
+    pub query: String,
+}
+
 /// Option directive
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionDirective {
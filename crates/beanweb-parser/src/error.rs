@@ -17,9 +17,54 @@ pub enum ParseError {
     #[error("Unsupported directive: {directive_type}")]
     UnsupportedDirective { directive_type: String },
 
-    #[error("IO error")]
+    #[error("IO error: {0}")]
     IoError(#[from] io::Error),
 
     #[error("Internal error")]
     InternalError,
 }
+
+/// How bad a [`DirectiveError`] is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The directive was skipped
+    #[default]
+    Error,
+    /// Nothing was skipped, but the file probably doesn't say what was meant
+    /// (an include of a file that doesn't exist)
+    Warning,
+}
+
+/// A problem found while parsing, usually a directive that was skipped
+///
+/// The parser records one of these and continues with the next directive,
+/// so a single malformed entry doesn't take the rest of the file with it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DirectiveError {
+    /// Source file path, as in `SpannedDirective::source`
+    pub source: Option<String>,
+    /// Line of the problem (1-based): the first line of the directive, or
+    /// the line within it that failed, such as a posting
+    pub line: usize,
+    /// Column on `line` where the problem starts (1-based, in characters)
+    #[serde(default = "first_column")]
+    pub column: usize,
+    /// Last line of the skipped directive (1-based, inclusive)
+    pub end_line: usize,
+    pub message: String,
+    /// The offending line, trimmed
+    pub text: String,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn first_column() -> usize {
+    1
+}
+
+impl std::fmt::Display for DirectiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.source.as_deref().unwrap_or("<input>"), self.line, self.column, self.message)
+    }
+}
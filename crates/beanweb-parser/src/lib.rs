@@ -1,17 +1,31 @@
 //! Beancount parser implementation
 //!
-//! A lightweight Beancount file parser using regex.
+//! A lightweight Beancount file parser using regex:
+//! - Strings may span lines and `;` comments may follow any directive or
+//!   posting; a string still open at the next dated line is an error there
+//! - Amounts in postings, balances and prices may be arithmetic expressions
+//!   (`(12.50 + 3) / 2`)
+//! - Malformed directives are skipped and reported with line and column
+//!
+//! Parsers plug in behind [`BeancountParserTrait`]: the ledger holds a
+//! [`ParserRef`] and never names [`DefaultBeancountParser`]. A parser built
+//! on another grammar (the `beancount-parser` crate, say) implements `parse`,
+//! `parse_file` and `parse_file_with_base` (stats and incremental parsing
+//! have defaults), is added to a [`ParserRegistry`] under a name, and is
+//! picked with `data.parser` in the config.
 
 use async_trait::async_trait;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+pub mod cache;
 pub mod error;
 pub mod types;
 pub mod directives;
 pub mod parser;
 
-pub use error::ParseError;
+pub use cache::ParseCache;
+pub use error::{DirectiveError, ParseError, Severity};
 pub use parser::{SimpleBeancountParser, extract_time_from_meta};
 
 // Re-export commonly used types
@@ -21,7 +35,7 @@ pub use types::{
 pub use directives::{
     SpannedDirective, Directive, Transaction, OpenDirective, CloseDirective,
     BalanceDirective, PadDirective, CommodityDirective, DocumentDirective,
-    PriceDirective, EventDirective, NoteDirective, OptionDirective, IncludeDirective,
+    PriceDirective, EventDirective, NoteDirective, QueryDirective, OptionDirective, IncludeDirective,
     CustomDirective, CommentDirective, Posting,
 };
 
@@ -52,17 +66,73 @@ pub fn generate_txn_id(source: Option<&str>, line: usize, content: &str) -> Stri
 /// Parser reference type
 pub type ParserRef = Arc<dyn BeancountParserTrait>;
 
+/// Directives parsed from a string, with a [`DirectiveError`] for every
+/// directive that was skipped
+#[derive(Debug, Clone, Default)]
+pub struct ParsedSource {
+    pub directives: Vec<SpannedDirective>,
+    pub errors: Vec<DirectiveError>,
+}
+
 /// Trait for Beancount parsers
 #[async_trait]
 pub trait BeancountParserTrait: Send + Sync {
-    /// Parse a Beancount file and return directives
-    async fn parse(&self, content: &str) -> Result<Vec<SpannedDirective>, ParseError>;
+    /// Parse Beancount text; malformed directives are skipped and reported
+    /// with their line and column instead of failing the whole text
+    async fn parse(&self, content: &str) -> Result<ParsedSource, ParseError>;
 
     /// Parse from a file path (recursive, handles includes)
     async fn parse_file(&self, path: PathBuf) -> Result<Vec<SpannedDirective>, ParseError>;
 
     /// Parse from a file path with base directory for resolving includes
     async fn parse_file_with_base(&self, path: PathBuf, base_dir: PathBuf) -> Result<Vec<SpannedDirective>, ParseError>;
+
+    /// Parse from a file path and report statistics for every file visited
+    /// Parsers that don't track stats return an empty list
+    async fn parse_file_with_stats(&self, path: PathBuf) -> Result<(Vec<SpannedDirective>, Vec<FileParseStats>), ParseError> {
+        Ok((self.parse_file(path).await?, Vec::new()))
+    }
+
+    /// As [`Self::parse_file_with_stats`], parsing only the files that changed
+    /// since `cache` was last filled; parsers without a cache parse everything
+    async fn parse_file_incremental(&self, path: PathBuf, _cache: &mut ParseCache) -> Result<(Vec<SpannedDirective>, Vec<FileParseStats>), ParseError> {
+        self.parse_file_with_stats(path).await
+    }
+}
+
+/// Statistics for a single parsed file (included files are reported separately)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FileParseStats {
+    /// File path as recorded in `SpannedDirective::source`
+    pub path: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Number of directives in this file, include directives excluded
+    pub directive_count: usize,
+    pub first_date: Option<chrono::NaiveDate>,
+    pub last_date: Option<chrono::NaiveDate>,
+    /// Time spent parsing this file, in milliseconds
+    pub parse_ms: f64,
+    /// Directives skipped because they could not be parsed
+    #[serde(default)]
+    pub errors: Vec<DirectiveError>,
+}
+
+impl FileParseStats {
+    fn collect(path: &str, size: u64, directives: &[SpannedDirective], errors: Vec<DirectiveError>, elapsed: std::time::Duration) -> Self {
+        let dates: Vec<chrono::NaiveDate> = directives.iter()
+            .filter_map(|d| d.data.date().map(|date| date.naive_date()))
+            .collect();
+        Self {
+            path: path.to_string(),
+            size,
+            directive_count: directives.iter().filter(|d| !matches!(d.data, Directive::Include(_))).count(),
+            first_date: dates.iter().min().copied(),
+            last_date: dates.iter().max().copied(),
+            parse_ms: elapsed.as_secs_f64() * 1000.0,
+            errors,
+        }
+    }
 }
 
 /// Default parser implementation
@@ -71,12 +141,9 @@ pub struct DefaultBeancountParser;
 
 #[async_trait]
 impl BeancountParserTrait for DefaultBeancountParser {
-    async fn parse(&self, content: &str) -> Result<Vec<SpannedDirective>, ParseError> {
-        SimpleBeancountParser::parse(content)
-            .map_err(|e| ParseError::SyntaxError {
-                location: "parse".to_string(),
-                message: e.to_string(),
-            })
+    async fn parse(&self, content: &str) -> Result<ParsedSource, ParseError> {
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(content, None);
+        Ok(ParsedSource { directives, errors })
     }
 
     async fn parse_file(&self, path: PathBuf) -> Result<Vec<SpannedDirective>, ParseError> {
@@ -85,22 +152,96 @@ impl BeancountParserTrait for DefaultBeancountParser {
     }
 
     async fn parse_file_with_base(&self, path: PathBuf, base_dir: PathBuf) -> Result<Vec<SpannedDirective>, ParseError> {
-        let content = tokio::fs::read_to_string(&path).await
-            .map_err(|e| ParseError::IoError(e))?;
+        let mut stats = Vec::new();
+        self.parse_file_collect(path, base_dir, &mut stats, None).await
+    }
+
+    async fn parse_file_with_stats(&self, path: PathBuf) -> Result<(Vec<SpannedDirective>, Vec<FileParseStats>), ParseError> {
+        let base_dir = path.parent().unwrap_or(&PathBuf::from(".")).to_path_buf();
+        let mut stats = Vec::new();
+        let directives = self.parse_file_collect(path, base_dir, &mut stats, None).await?;
+        Ok((directives, stats))
+    }
+
+    async fn parse_file_incremental(&self, path: PathBuf, cache: &mut ParseCache) -> Result<(Vec<SpannedDirective>, Vec<FileParseStats>), ParseError> {
+        let base_dir = path.parent().unwrap_or(&PathBuf::from(".")).to_path_buf();
+        let mut stats = Vec::new();
+        cache.begin();
+        let directives = self.parse_file_collect(path, base_dir, &mut stats, Some(&mut *cache)).await?;
+        cache.finish();
+        Ok((directives, stats))
+    }
+}
+
+// ==================== Parser Selection ====================
+
+/// Name of [`DefaultBeancountParser`] in a [`ParserRegistry`]
+pub const DEFAULT_PARSER: &str = "simple";
+
+/// Parsers by name, for `data.parser`
+///
+/// Holds [`DEFAULT_PARSER`] from the start; a build with another parser
+/// registers it before the config is read.
+pub struct ParserRegistry {
+    parsers: Vec<(String, fn() -> ParserRef)>,
+}
 
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        let mut registry = Self { parsers: Vec::new() };
+        registry.register(DEFAULT_PARSER, || Arc::new(DefaultBeancountParser));
+        registry
+    }
+}
+
+impl ParserRegistry {
+    /// Add `make` as `name`, replacing a parser registered under that name
+    pub fn register(&mut self, name: &str, make: fn() -> ParserRef) {
+        self.parsers.retain(|(registered, _)| registered != name);
+        self.parsers.push((name.to_string(), make));
+    }
+
+    /// A new parser of the kind registered as `name`
+    pub fn get(&self, name: &str) -> Option<ParserRef> {
+        self.parsers.iter().find(|(registered, _)| registered == name).map(|(_, make)| make())
+    }
+
+    /// Registered names, in the order they were added
+    pub fn names(&self) -> Vec<&str> {
+        self.parsers.iter().map(|(name, _)| name.as_str()).collect()
+    }
+}
+
+impl DefaultBeancountParser {
+    /// Parse a file and its includes, recording stats for each file visited
+    /// With a cache, unchanged files are taken from it instead of parsed
+    async fn parse_file_collect(&self, path: PathBuf, base_dir: PathBuf, stats: &mut Vec<FileParseStats>, mut cache: Option<&mut ParseCache>) -> Result<Vec<SpannedDirective>, ParseError> {
         // Get the relative path from the data directory for source tracking
         let source_path = path.to_string_lossy().to_string();
 
-        // First pass: parse and collect all directives
-        let all_directives = SimpleBeancountParser::parse_with_source(&content, Some(&source_path))
-            .map_err(|e| ParseError::SyntaxError {
-                location: source_path.clone(),
-                message: e.to_string(),
-            })?;
+        // First pass: parse and collect all directives; malformed ones are skipped
+        // and reported in the file's stats
+        let parse = |content: &str| {
+            let started = std::time::Instant::now();
+            let (directives, errors) = SimpleBeancountParser::parse_recovering(content, Some(&source_path));
+            let file_stats = FileParseStats::collect(&source_path, content.len() as u64, &directives, errors, started.elapsed());
+            (directives, file_stats)
+        };
+        let (all_directives, file_stats) = match cache.as_deref_mut() {
+            Some(cache) => cache.get_or_parse(&path, parse).await?,
+            None => {
+                let content = tokio::fs::read_to_string(&path).await
+                    .map_err(|e| ParseError::IoError(e))?;
+                parse(&content)
+            }
+        };
+        let stats_index = stats.len();
+        stats.push(file_stats);
 
         // Second pass: handle includes recursively
         let mut processed_directives = Vec::new();
         for directive in all_directives {
+            let include_line = directive.span.start;
             match directive.data {
                 Directive::Include(include) => {
                     let include_path = &include.path;
@@ -114,10 +255,12 @@ impl BeancountParserTrait for DefaultBeancountParser {
                         if let Ok(paths) = glob::glob(&pattern_str) {
                             for entry in paths.flatten() {
                                 if entry.is_file() {
-                                    let included_directives = self.parse_file_with_base(
+                                    let included_directives = Box::pin(self.parse_file_collect(
                                         entry.clone(),
-                                        entry.parent().unwrap_or(&base_dir).to_path_buf()
-                                    ).await
+                                        entry.parent().unwrap_or(&base_dir).to_path_buf(),
+                                        stats,
+                                        cache.as_deref_mut(),
+                                    )).await
                                     .map_err(|e| ParseError::SyntaxError {
                                         location: entry.to_string_lossy().to_string(),
                                         message: e.to_string(),
@@ -131,15 +274,19 @@ impl BeancountParserTrait for DefaultBeancountParser {
                         let included_path = base_dir.join(include_path);
                         if included_path.exists() {
                             // Recursively parse included file
-                            let included_directives = self.parse_file_with_base(
+                            let included_directives = Box::pin(self.parse_file_collect(
                                 included_path.clone(),
-                                included_path.parent().unwrap_or(&base_dir).to_path_buf()
-                            ).await
+                                included_path.parent().unwrap_or(&base_dir).to_path_buf(),
+                                stats,
+                                cache.as_deref_mut(),
+                            )).await
                             .map_err(|e| ParseError::SyntaxError {
                                 location: included_path.to_string_lossy().to_string(),
                                 message: e.to_string(),
                             })?;
                             processed_directives.extend(included_directives);
+                        } else {
+                            stats[stats_index].errors.push(missing_include(&source_path, include_line, include_path));
                         }
                     }
                 },
@@ -149,6 +296,74 @@ impl BeancountParserTrait for DefaultBeancountParser {
             }
         }
 
+        // Missing includes were added after the syntax errors
+        stats[stats_index].errors.sort_by_key(|e| (e.line, e.column));
+
         Ok(processed_directives)
     }
 }
+
+/// Warning for an `include` of a file that doesn't exist; the rest of the
+/// ledger loads without it
+fn missing_include(source: &str, line: usize, path: &str) -> DirectiveError {
+    let text = format!("include \"{}\"", path);
+    DirectiveError {
+        source: Some(source.to_string()),
+        line,
+        column: text.find('"').unwrap_or(0) + 2,
+        end_line: line,
+        message: format!("Included file not found: {}", path),
+        text,
+        severity: Severity::Warning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_parse_stats_collect() {
+        let content = r#"include "2024/*.bean"
+2024-01-01 open Assets:Cash CNY
+2024-03-05 * "Shop" "Lunch"
+  Expenses:Food  20.00 CNY
+  Assets:Cash
+2024-02-01 balance Assets:Cash 100.00 CNY
+"#;
+        let directives = SimpleBeancountParser::parse_with_source(content, Some("main.bean")).unwrap();
+        let stats = FileParseStats::collect("main.bean", content.len() as u64, &directives, Vec::new(), std::time::Duration::from_millis(3));
+        assert_eq!(stats.directive_count, 3);
+        assert_eq!(stats.first_date, chrono::NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(stats.last_date, chrono::NaiveDate::from_ymd_opt(2024, 3, 5));
+        assert_eq!(stats.parse_ms, 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_parser_registry() {
+        let mut registry = ParserRegistry::default();
+        assert_eq!(registry.names(), vec![DEFAULT_PARSER]);
+        assert!(registry.get("beancount").is_none());
+        registry.register("beancount", || Arc::new(DefaultBeancountParser));
+        assert_eq!(registry.names(), vec![DEFAULT_PARSER, "beancount"]);
+        let parser = registry.get("beancount").unwrap();
+        assert_eq!(parser.parse("2024-01-01 open Assets:Cash CNY\n").await.unwrap().directives.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics() {
+        let parsed = DefaultBeancountParser.parse("2024-01-01 open Assets:Cash CNY\n2024-01-02 opne Assets:Bank CNY\n").await.unwrap();
+        assert_eq!(parsed.directives.len(), 1);
+        assert_eq!(parsed.errors[0].to_string(), "<input>:2:12: Unknown directive: opne");
+
+        let dir = std::env::temp_dir().join(format!("beanweb-parser-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.bean"), "include \"missing.bean\"\n2024-01-01 open Assets:Cash CNY\n").unwrap();
+        let (directives, stats) = DefaultBeancountParser.parse_file_with_stats(dir.join("main.bean")).await.unwrap();
+        assert_eq!(directives.len(), 1);
+        let warning = &stats[0].errors[0];
+        assert_eq!((warning.line, warning.column, warning.severity), (1, 10, Severity::Warning));
+        assert_eq!(warning.message, "Included file not found: missing.bean");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -7,13 +7,107 @@ use std::sync::Arc;
 use crate::directives::{
     BalanceDirective, CloseDirective, CommentDirective, CommodityDirective,
     CustomDirective, DocumentDirective, EventDirective, IncludeDirective,
-    NoteDirective, OpenDirective, OptionDirective, PadDirective, Posting, PriceDirective,
+    NoteDirective, OpenDirective, OptionDirective, PadDirective, Posting, PriceDirective, QueryDirective,
     SpannedDirective, Transaction, Directive,
 };
 use crate::types::{Account, AccountType, Amount, Cost, Date, Meta, Price, SpanInfo, StringValue};
-use crate::error::ParseError;
+use crate::error::{DirectiveError, ParseError, Severity};
+
+/// Parentheses and signs an amount expression may nest
+const MAX_EXPRESSION_DEPTH: usize = 32;
+
+/// Recursive-descent evaluation of an amount expression, whitespace removed
+struct Expression<'a> {
+    text: &'a [u8],
+    pos: usize,
+    /// Parentheses and signs open at `pos`
+    depth: usize,
+}
+
+impl Expression<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    /// `product (('+' | '-') product)*`
+    fn sum(&mut self) -> Option<rust_decimal::Decimal> {
+        let mut value = self.product()?;
+        while let Some(op @ (b'+' | b'-')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = if op == b'+' { value.checked_add(rhs)? } else { value.checked_sub(rhs)? };
+        }
+        Some(value)
+    }
+
+    /// `factor (('*' | '/') factor)*`
+    fn product(&mut self) -> Option<rust_decimal::Decimal> {
+        let mut value = self.factor()?;
+        while let Some(op @ (b'*' | b'/')) = self.peek() {
+            self.pos += 1;
+            let rhs = self.factor()?;
+            value = if op == b'*' { value.checked_mul(rhs)? } else { value.checked_div(rhs)? };
+        }
+        Some(value)
+    }
+
+    /// `('-' | '+') factor | '(' sum ')' | number`, nested at most
+    /// [`MAX_EXPRESSION_DEPTH`] deep
+    fn factor(&mut self) -> Option<rust_decimal::Decimal> {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = self.nested();
+        self.depth -= 1;
+        value
+    }
+
+    fn nested(&mut self) -> Option<rust_decimal::Decimal> {
+        match self.peek()? {
+            b'-' => {
+                self.pos += 1;
+                Some(-self.factor()?)
+            }
+            b'+' => {
+                self.pos += 1;
+                self.factor()
+            }
+            b'(' => {
+                self.pos += 1;
+                let value = self.sum()?;
+                (self.peek()? == b')').then_some(())?;
+                self.pos += 1;
+                Some(value)
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == b'.') {
+                    self.pos += 1;
+                }
+                std::str::from_utf8(&self.text[start..self.pos]).ok()?.parse().ok()
+            }
+        }
+    }
+}
+
+/// A line of input after joining multi-line strings and cutting comments
+struct LogicalLine {
+    text: String,
+    /// First line of the file it covers (1-based)
+    line: usize,
+    /// Lines of the file it covers
+    count: usize,
+    /// Byte offset of its first line in the file
+    offset: usize,
+    /// Byte offset in `text` of a string literal never closed
+    unterminated: Option<usize>,
+}
 
 /// Simple line-based parser for Beancount files
+///
+/// Parsing is error-recovering: a malformed directive is skipped and recorded
+/// as a [`DirectiveError`], and parsing resumes at the next directive.
 pub struct SimpleBeancountParser;
 
 impl SimpleBeancountParser {
@@ -23,20 +117,26 @@ impl SimpleBeancountParser {
     }
 
     /// Parse a Beancount file content with source file path
+    /// Malformed directives are skipped; use [`Self::parse_recovering`] to get them
     pub fn parse_with_source(content: &str, source: Option<&str>) -> Result<Vec<SpannedDirective>, ParseError> {
+        Ok(Self::parse_recovering(content, source).0)
+    }
+
+    /// Parse a Beancount file content, returning the directives that parsed and
+    /// an error for every directive that was skipped
+    pub fn parse_recovering(content: &str, source: Option<&str>) -> (Vec<SpannedDirective>, Vec<DirectiveError>) {
         let mut directives = Vec::new();
-        let lines: Vec<&str> = content.lines().collect();
+        let mut errors = Vec::new();
+        let logical = Self::logical_lines(content);
+        let lines: Vec<&str> = logical.iter().map(|l| l.text.as_str()).collect();
         let mut i = 0;
-        let mut pos = 0usize;
 
         while i < lines.len() {
             let line = lines[i];
-            let line_start = pos;
             let trimmed = line.trim();
 
             // Skip empty lines and comments (;, #)
             if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
-                pos += line.len() + 1;
                 i += 1;
                 continue;
             }
@@ -49,117 +149,243 @@ impl SimpleBeancountParser {
                 static DATE_CHECK: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
                 let date_regex = DATE_CHECK.get_or_init(|| regex::Regex::new(r"^\d{4}-\d{2}-\d{2}").unwrap());
                 if !date_regex.is_match(trimmed) {
-                    pos += line.len() + 1;
                     i += 1;
                     continue;
                 }
             }
 
+            // A string never closed swallowed the rest of its directive: the
+            // directive is skipped and the error points at the opening quote
+            if let Some(quote) = logical[i].unterminated {
+                let before = &line[..quote];
+                let first = before.rfind('\n').map_or(0, |n| n + 1);
+                let end = line[quote..].find('\n').map_or(line.len(), |n| quote + n);
+                errors.push(DirectiveError {
+                    source: source.map(|s| s.to_string()),
+                    line: logical[i].line + before.matches('\n').count(),
+                    column: before[first..].chars().count() + 1,
+                    end_line: logical[i].line + logical[i].count - 1,
+                    message: format!("Unterminated string: {}", &line[quote..end]),
+                    text: line[first..end].trim().to_string(),
+                    severity: Severity::Error,
+                });
+                i += 1;
+                continue;
+            }
+
             // Check if this line starts a directive (has a date at start)
-            // Line number is 1-indexed (i starts from 0)
-            let line_number = i + 1;
-            if let Some((directive, lines_consumed)) = Self::parse_directive_block(&lines, i, line_start, line_number, source) {
-                directives.push(directive);
-                for j in 0..lines_consumed {
-                    if i + j < lines.len() {
-                        pos += lines[i + j].len() + 1;
+            // Line number is 1-indexed and counts lines of the file, not
+            // logical lines
+            let line_number = logical[i].line;
+            let line_start = logical[i].offset;
+            let (result, lines_consumed) = match Self::parse_directive_block(&lines, i, line_start, line_number, source) {
+                Some((result, lines_consumed)) => (Some(result), lines_consumed),
+                None => (Self::parse_line(line, line_start, line_number, source), 1),
+            };
+            let last = &logical[(i + lines_consumed).min(logical.len()) - 1];
+            match result {
+                Some(Ok(directive)) => directives.push(directive),
+                Some(Err(message)) => {
+                    let block = &lines[i..(i + lines_consumed).min(lines.len())];
+                    // `line_in_text`: lines of a multi-line string before the column
+                    let (offset, (line_in_text, column)) = Self::locate(block, &message);
+                    errors.push(DirectiveError {
+                        source: source.map(|s| s.to_string()),
+                        line: logical[i + offset].line + line_in_text,
+                        column,
+                        end_line: last.line + last.count - 1,
+                        message,
+                        text: block[offset].trim().to_string(),
+                        severity: Severity::Error,
+                    });
+                }
+                None => {}
+            }
+            i += lines_consumed;
+        }
+
+        (directives, errors)
+    }
+
+    /// The lines of `content` as the parser sees them: a string literal left
+    /// open at the end of a line continues on the next (`"first line` /
+    /// `second line"` is one logical line with a newline inside the string),
+    /// and `;` comments after other text are cut off. A string still open at
+    /// the next dated line or at the end of the file is marked unterminated.
+    fn logical_lines(content: &str) -> Vec<LogicalLine> {
+        static DATED: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
+        let dated = DATED.get_or_init(|| regex::Regex::new(r"^\d{4}-\d{2}-\d{2}\s").unwrap());
+        let mut logical: Vec<LogicalLine> = Vec::new();
+        let mut offset = 0;
+        let mut open_quote = None;
+        for (index, line) in content.lines().enumerate() {
+            match logical.last_mut() {
+                Some(last) if open_quote.is_some() && !dated.is_match(line) => {
+                    last.text.push('\n');
+                    last.text.push_str(line);
+                    last.count += 1;
+                }
+                last => {
+                    if let Some(last) = last {
+                        last.unterminated = open_quote;
                     }
+                    logical.push(LogicalLine { text: line.to_string(), line: index + 1, count: 1, offset, unterminated: None });
                 }
-                i += lines_consumed;
-            } else if let Some(directive) = Self::parse_line(trimmed, line_start, line_number, source) {
-                directives.push(directive);
-                pos += line.len() + 1;
-                i += 1;
-            } else {
-                pos += line.len() + 1;
-                i += 1;
             }
+            offset += line.len() + 1;
+            open_quote = Self::open_quote(&logical.last().unwrap().text);
+        }
+        if let Some(last) = logical.last_mut() {
+            last.unterminated = open_quote;
         }
+        for line in &mut logical {
+            if let Some(cut) = Self::comment_start(&line.text) {
+                // Lines that are only a comment stay as they are
+                if !line.text[..cut].trim().is_empty() {
+                    line.text.truncate(cut);
+                    line.text.truncate(line.text.trim_end().len());
+                }
+            }
+        }
+        logical
+    }
 
-        Ok(directives)
+    /// Byte offset of the quote opening a string literal that is still open
+    /// at the end of `text`
+    fn open_quote(text: &str) -> Option<usize> {
+        // Comment and org-mode header lines
+        if text.starts_with(['#', '*']) {
+            return None;
+        }
+        let mut open = None;
+        for (i, c) in text.char_indices() {
+            match c {
+                '"' => open = if open.is_some() { None } else { Some(i) },
+                ';' if open.is_none() => return None,
+                _ => {}
+            }
+        }
+        open
+    }
+
+    /// Byte offset of the `;` starting a comment, outside string literals
+    fn comment_start(text: &str) -> Option<usize> {
+        let mut open = false;
+        for (i, c) in text.char_indices() {
+            match c {
+                '"' => open = !open,
+                ';' if !open => return Some(i),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Where an error message points: the line (offset into `block`), the
+    /// lines of a multi-line string on it before the problem, and the 1-based
+    /// column. Messages end in the text that failed (`Invalid posting:
+    /// <line>`), which is looked up in the directive's lines; falls back to
+    /// the start of the first line.
+    fn locate(block: &[&str], message: &str) -> (usize, (usize, usize)) {
+        let column = |line: &str, byte: usize| {
+            let before = &line[..byte];
+            let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+            (before.matches('\n').count(), before[line_start..].chars().count() + 1)
+        };
+        let fragment = message.split_once(": ").map(|(_, f)| f.trim()).unwrap_or("");
+        if !fragment.is_empty() {
+            for (offset, line) in block.iter().enumerate() {
+                if let Some(byte) = line.find(fragment) {
+                    return (offset, column(line, byte));
+                }
+            }
+        }
+        let first = block.first().copied().unwrap_or("");
+        (0, column(first, first.len() - first.trim_start().len()))
+    }
+
+    /// Reject dates that match YYYY-MM-DD but don't exist (2024-02-30)
+    fn check_date(date_str: &str) -> Result<(), String> {
+        chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+            .map(|_| ())
+            .map_err(|_| format!("Invalid date: {}", date_str))
     }
 
-    /// Parse a directive that may span multiple lines (transactions, commodities with metadata)
+    /// Parse a directive that may span multiple lines (transactions, opens and commodities with metadata)
     /// line_number is 1-indexed for display purposes
-    fn parse_directive_block(lines: &[&str], start_idx: usize, byte_start: usize, line_number: usize, source: Option<&str>) -> Option<(SpannedDirective, usize)> {
+    /// Returns the parse result and the number of lines it covers, None for single-line directives
+    fn parse_directive_block(lines: &[&str], start_idx: usize, byte_start: usize, line_number: usize, source: Option<&str>) -> Option<(Result<SpannedDirective, String>, usize)> {
         let first_line = lines[start_idx];
         let trimmed = first_line.trim();
 
         // Match date pattern: YYYY-MM-DD
         static DATE_PATTERN: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
-        let date_regex = DATE_PATTERN.get_or_init(|| regex::Regex::new(r"^(\d{4}-\d{2}-\d{2})\s+(.+)$").unwrap());
+        let date_regex = DATE_PATTERN.get_or_init(|| regex::Regex::new(r"(?s)^(\d{4}-\d{2}-\d{2})\s+(.+)$").unwrap());
 
         let caps = date_regex.captures(trimmed)?;
         let date_str = caps.get(1).unwrap().as_str();
         let rest = caps.get(2).unwrap().as_str();
 
-        // Check if this is a transaction (flag: *, !, txn)
-        let is_transaction = rest.starts_with('*') || rest.starts_with('!') || rest.starts_with("txn ");
-
-        if is_transaction {
-            // Collect continuation lines (indented lines)
-            let mut continuation_lines = Vec::new();
-            let mut lines_consumed = 1;
-            let mut end_pos = byte_start + first_line.len();
-
-            for i in (start_idx + 1)..lines.len() {
-                let line = lines[i];
-                // Continuation lines are indented (start with whitespace)
-                if !line.is_empty() && (line.starts_with(' ') || line.starts_with('\t')) {
-                    continuation_lines.push(line);
-                    lines_consumed += 1;
-                    end_pos += line.len() + 1;
-                } else {
-                    break;
-                }
-            }
+        // Check if this is a transaction (flag: *, !, txn, or straight to the strings)
+        let is_transaction = rest.starts_with('*') || rest.starts_with('!') || rest.starts_with("txn ") || rest.starts_with('"');
+        if !is_transaction && !rest.starts_with("commodity ") && !rest.starts_with("open ") {
+            // Other directives - parse as single line
+            return None;
+        }
 
-            let directive = Self::parse_transaction_full(rest, date_str, &continuation_lines);
-            Some((
-                SpannedDirective {
-                    data: directive,
-                    // Use line_number instead of byte_start for transaction ID
-                    span: SpanInfo { start: line_number, end: end_pos },
-                    source: source.map(|s| s.to_string()),
-                },
-                lines_consumed,
-            ))
-        } else if rest.starts_with("commodity ") {
-            // Commodities can have metadata on continuation lines
-            let mut lines_consumed = 1;
-            let mut end_pos = byte_start + first_line.len();
-
-            for i in (start_idx + 1)..lines.len() {
-                let line = lines[i];
-                if !line.is_empty() && (line.starts_with(' ') || line.starts_with('\t')) {
-                    lines_consumed += 1;
-                    end_pos += line.len() + 1;
-                } else {
-                    break;
-                }
+        // Collect continuation lines (indented lines)
+        let mut continuation_lines = Vec::new();
+        let mut lines_consumed = 1;
+        let mut end_pos = byte_start + first_line.len();
+        for line in &lines[start_idx + 1..] {
+            // Continuation lines are indented (start with whitespace)
+            if !line.is_empty() && (line.starts_with(' ') || line.starts_with('\t')) {
+                continuation_lines.push(*line);
+                lines_consumed += 1;
+                end_pos += line.len() + 1;
+            } else {
+                break;
             }
-
-            let directive = Self::parse_commodity(rest, date_str);
-            Some((
-                SpannedDirective {
-                    data: directive,
-                    span: SpanInfo { start: line_number, end: end_pos },
-                    source: source.map(|s| s.to_string()),
-                },
-                lines_consumed,
-            ))
-        } else {
-            // Other directives - parse as single line
-            None
         }
+
+        let directive = Self::check_date(date_str).and_then(|_| {
+            if is_transaction {
+                Self::parse_transaction_full(rest, date_str, &continuation_lines)
+            } else if rest.starts_with("open ") {
+                Self::parse_open(rest, date_str).map(|directive| match directive {
+                    Directive::Open(open) => Directive::Open(OpenDirective {
+                        meta: Self::parse_meta_lines(&continuation_lines),
+                        ..open
+                    }),
+                    other => other,
+                })
+            } else {
+                Self::parse_commodity(rest, date_str).map(|directive| match directive {
+                    Directive::Commodity(commodity) => Directive::Commodity(CommodityDirective {
+                        meta: Self::parse_meta_lines(&continuation_lines),
+                        ..commodity
+                    }),
+                    other => other,
+                })
+            }
+        });
+        let result = directive.map(|data| SpannedDirective {
+            data,
+            // Use line_number instead of byte_start for transaction ID
+            span: SpanInfo { start: line_number, end: end_pos },
+            source: source.map(|s| s.to_string()),
+        });
+        Some((result, lines_consumed))
     }
 
     /// Parse a complete transaction with postings
-    fn parse_transaction_full(rest: &str, date_str: &str, continuation_lines: &[&str]) -> Directive {
+    /// Fails on a posting line that can't be parsed, rather than dropping it
+    /// and leaving an unbalanced transaction behind
+    fn parse_transaction_full(rest: &str, date_str: &str, continuation_lines: &[&str]) -> Result<Directive, String> {
         // Parse transaction header: FLAG "payee" "narration" #tags ^links
         static TXN_HEADER: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
         let header_regex = TXN_HEADER.get_or_init(|| {
-            regex::Regex::new(r#"^([*!]|txn)\s*(?:"([^"]*)")?\s*(?:"([^"]*)")?\s*(.*)$"#).unwrap()
+            regex::Regex::new(r#"^([*!]|txn)?\s*(?:"([^"]*)")?\s*(?:"([^"]*)")?\s*(.*)$"#).unwrap()
         });
 
         let mut flag = None;
@@ -196,25 +422,18 @@ impl SimpleBeancountParser {
                 continue;
             }
 
-            // Check if it's metadata (key: "value" or key: value)
-            if let Some(colon_pos) = trimmed.find(':') {
-                let before_colon = &trimmed[..colon_pos];
-                // Metadata keys don't contain spaces and aren't account names
-                if !before_colon.contains(' ') && !Self::is_account_name(before_colon) {
-                    let key = before_colon.trim();
-                    let value = trimmed[colon_pos + 1..].trim().trim_matches('"');
-                    meta.insert(key.to_string(), StringValue::Quote(value.to_string()));
-                    continue;
-                }
+            if let Some((key, value)) = Self::parse_meta_line(trimmed) {
+                meta.insert(key, value);
+                continue;
             }
 
-            // Try to parse as posting
-            if let Some(posting) = Self::parse_posting(trimmed) {
-                postings.push(posting);
+            match Self::parse_posting(trimmed) {
+                Some(posting) => postings.push(posting),
+                None => return Err(format!("Invalid posting: {}", trimmed)),
             }
         }
 
-        Directive::Transaction(Transaction {
+        Ok(Directive::Transaction(Transaction {
             date: Self::parse_date(date_str),
             flag,
             payee,
@@ -223,13 +442,72 @@ impl SimpleBeancountParser {
             links,
             postings,
             meta,
-        })
+        }))
+    }
+
+    /// Parse a metadata line (key: "value" or key: value)
+    fn parse_meta_line(trimmed: &str) -> Option<(String, StringValue)> {
+        let colon_pos = trimmed.find(':')?;
+        let before_colon = &trimmed[..colon_pos];
+        // Metadata keys don't contain spaces and aren't account names
+        let first_word = trimmed.split_whitespace().next().unwrap_or(trimmed);
+        if before_colon.contains(' ') || Self::is_account_name(first_word) {
+            return None;
+        }
+        let value = trimmed[colon_pos + 1..].trim().trim_matches('"');
+        Some((before_colon.trim().to_string(), StringValue::Quote(value.to_string())))
+    }
+
+    /// Metadata of a directive's continuation lines; other lines are ignored
+    fn parse_meta_lines(lines: &[&str]) -> Meta {
+        let mut meta = Meta::default();
+        for (key, value) in lines.iter().filter_map(|line| Self::parse_meta_line(line.trim())) {
+            meta.insert(key, value);
+        }
+        meta
     }
 
-    /// Check if a string looks like an account name (starts with known prefix)
+    /// A number, or an arithmetic expression of numbers with `+ - * /` and
+    /// parentheses as Beancount allows in amounts; commas group thousands.
+    /// None for anything else, including division by zero and expressions
+    /// nested deeper than [`MAX_EXPRESSION_DEPTH`]
+    pub fn parse_number(text: &str) -> Option<rust_decimal::Decimal> {
+        let text: String = text.chars().filter(|c| *c != ',' && !c.is_whitespace()).collect();
+        if let Ok(number) = text.parse() {
+            return Some(number);
+        }
+        let mut expression = Expression { text: text.as_bytes(), pos: 0, depth: 0 };
+        let value = expression.sum()?;
+        (expression.pos == text.len()).then_some(value)
+    }
+
+    /// `NUMBER CURRENCY` split on whitespace, where the number may be an
+    /// expression over several tokens (`2 * 35.00 USD`); a balance tolerance
+    /// (`~ 0.01`) before the currency is skipped. Errors with the number text.
+    fn parse_amount(tokens: &[&str]) -> Result<Amount, String> {
+        let (currency, number) = tokens.split_last().ok_or_else(String::new)?;
+        let number = match number.iter().position(|t| t.starts_with('~')) {
+            Some(tolerance) => &number[..tolerance],
+            None => number,
+        };
+        let expression = number.join(" ");
+        let amount = Self::parse_number(&expression).ok_or_else(|| expression.clone())?;
+        Ok(Amount { amount, currency: currency.to_string() })
+    }
+
+    /// Check if a string is an account name rather than a metadata key: at
+    /// least two components separated by `:`, the root (which `name_*`
+    /// options may rename) starting with a letter that isn't lowercase, the
+    /// others with such a letter or a digit, letters, digits and `-` after that
     fn is_account_name(s: &str) -> bool {
-        s.starts_with("Assets") || s.starts_with("Liabilities") ||
-        s.starts_with("Equity") || s.starts_with("Income") || s.starts_with("Expenses")
+        let mut components = s.split(':');
+        let valid = |component: &str, first: fn(char) -> bool| {
+            component.chars().next().is_some_and(first)
+                && component.chars().all(|c| c.is_alphanumeric() || c == '-')
+        };
+        components.next().is_some_and(|root| valid(root, |c| c.is_alphabetic() && !c.is_lowercase()))
+            && s.contains(':')
+            && components.all(|c| valid(c, |c| c.is_alphanumeric() && !c.is_lowercase()))
     }
 
     /// Parse a single posting line
@@ -239,10 +517,11 @@ impl SimpleBeancountParser {
             return None;
         }
 
-        // Posting format: [FLAG] ACCOUNT [AMOUNT CURRENCY] [{COST}] [@ PRICE]
+        // Posting format: [FLAG] ACCOUNT [AMOUNT CURRENCY] [{COST}] [@ PRICE | @@ TOTAL]
+        // Amounts may be arithmetic expressions (`(12.50 + 3) / 2`)
         static POSTING_PATTERN: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
         let posting_regex = POSTING_PATTERN.get_or_init(|| {
-            regex::Regex::new(r#"^([!*])?\s*((?:Assets|Liabilities|Equity|Income|Expenses):[^\s]+)\s*(-?[\d,]+(?:\.\d+)?)?\s*([A-Z][A-Z0-9]*)?(?:\s*\{([^}]*)\})?(?:\s*@\s*(-?[\d,]+(?:\.\d+)?)\s*([A-Z][A-Z0-9]*))?(?:\s*;.*)?$"#).unwrap()
+            regex::Regex::new(r#"^([!*])?\s*([^\P{L}\p{Ll}][^\s:]*:\S+)\s*([-+(]*[\d.][-+*/()\d., ]*?)?\s*([A-Z](?:[A-Z0-9'._-]*[A-Z0-9])?)?(?:\s*\{([^}]*)\})?(?:\s*(@@?)\s*([-+(]*[\d.][-+*/()\d., ]*?)\s*([A-Z](?:[A-Z0-9'._-]*[A-Z0-9])?))?(?:\s*;.*)?$"#).unwrap()
         });
 
         if let Some(caps) = posting_regex.captures(trimmed) {
@@ -251,8 +530,7 @@ impl SimpleBeancountParser {
             let (account_type, components) = Self::parse_account_name(account_name);
 
             let amount = if let (Some(amt), Some(curr)) = (caps.get(3), caps.get(4)) {
-                let amount_str = amt.as_str().replace(',', "");
-                let amount: rust_decimal::Decimal = amount_str.parse().ok()?;
+                let amount = Self::parse_number(amt.as_str())?;
                 let currency = curr.as_str().to_string();
                 Some(Amount { amount, currency })
             } else {
@@ -265,7 +543,7 @@ impl SimpleBeancountParser {
                 // Simple cost parsing: NUMBER CURRENCY
                 let parts: Vec<&str> = cost_str.split_whitespace().collect();
                 if parts.len() >= 2 {
-                    let amount: rust_decimal::Decimal = parts[0].replace(',', "").parse().ok()?;
+                    let amount = Self::parse_number(parts[0])?;
                     Some(Cost {
                         amount,
                         currency: parts[1].to_string(),
@@ -277,12 +555,10 @@ impl SimpleBeancountParser {
             });
 
             // Parse price if present
-            let price = if let (Some(price_amt), Some(price_curr)) = (caps.get(6), caps.get(7)) {
-                let amount: rust_decimal::Decimal = price_amt.as_str().replace(',', "").parse().ok()?;
-                Some(Price::Single(Amount {
-                    amount,
-                    currency: price_curr.as_str().to_string(),
-                }))
+            let price = if let (Some(kind), Some(price_amt), Some(price_curr)) = (caps.get(6), caps.get(7), caps.get(8)) {
+                let amount = Self::parse_number(price_amt.as_str())?;
+                let amount = Amount { amount, currency: price_curr.as_str().to_string() };
+                Some(if kind.as_str() == "@@" { Price::Total(amount) } else { Price::Single(amount) })
             } else {
                 None
             };
@@ -303,20 +579,37 @@ impl SimpleBeancountParser {
         }
     }
 
-    fn parse_line(line: &str, start: usize, line_number: usize, source: Option<&str>) -> Option<SpannedDirective> {
+    /// Parse a single-line directive; None for lines that carry no directive
+    /// (indented metadata of an `open`, `plugin`, tag stacks)
+    fn parse_line(line: &str, start: usize, line_number: usize, source: Option<&str>) -> Option<Result<SpannedDirective, String>> {
         // Match date pattern: YYYY-MM-DD
         static DATE_PATTERN: once_cell::sync::OnceCell<regex::Regex> =
             once_cell::sync::OnceCell::new();
         let date_regex = DATE_PATTERN.get_or_init(|| {
-            regex::Regex::new(r"^(\d{4}-\d{2}-\d{2})\s+(.+)$").unwrap()
+            regex::Regex::new(r"(?s)^(\d{4}-\d{2}-\d{2})\s+(.+)$").unwrap()
         });
 
-        let source_str = source.map(|s| s.to_string());
+        // Metadata lines below single-line directives are not parsed
+        if line.starts_with(' ') || line.starts_with('\t') {
+            return None;
+        }
+        let line = line.trim();
+        let spanned = |data: Directive| SpannedDirective {
+            data,
+            span: SpanInfo {
+                start: line_number,
+                end: start + line.len(),
+            },
+            source: source.map(|s| s.to_string()),
+        };
 
         // First check if line starts with date
         if let Some(caps) = date_regex.captures(line) {
             let date_str = caps.get(1).unwrap().as_str();
             let rest = caps.get(2).unwrap().as_str();
+            if let Err(message) = Self::check_date(date_str) {
+                return Some(Err(message));
+            }
 
             let directive = if rest.starts_with("open ") {
                 Self::parse_open(rest, date_str)
@@ -342,78 +635,38 @@ impl SimpleBeancountParser {
                 Self::parse_include(rest)
             } else if rest.starts_with("custom ") {
                 Self::parse_custom(rest, date_str)
+            } else if rest.starts_with("query ") {
+                Self::parse_query(rest, date_str)
             } else {
                 // Transactions are handled by parse_directive_block
-                Directive::Comment(CommentDirective {
-                    content: line.to_string(),
-                })
+                Err(format!("Unknown directive: {}", rest.split_whitespace().next().unwrap_or(rest)))
             };
 
-            Some(SpannedDirective {
-                data: directive,
-                span: SpanInfo {
-                    start: line_number,
-                    end: start + line.len(),
-                },
-                source: source_str,
-            })
-        } else {
+            Some(directive.map(spanned))
+        } else if line.starts_with("option ") {
             // Handle directives without dates (include, option, etc.)
-            let trimmed = line.trim();
-            if trimmed.starts_with("option ") {
-                Some(SpannedDirective {
-                    data: Self::parse_option(trimmed),
-                    span: SpanInfo {
-                        start: line_number,
-                        end: start + line.len(),
-                    },
-                    source: source_str.clone(),
-                })
-            } else if trimmed.starts_with("include ") {
-                Some(SpannedDirective {
-                    data: Self::parse_include(trimmed),
-                    span: SpanInfo {
-                        start: line_number,
-                        end: start + line.len(),
-                    },
-                    source: source_str.clone(),
-                })
-            } else if trimmed.starts_with("pushtag ") {
-                Some(SpannedDirective {
-                    data: Directive::Comment(CommentDirective {
-                        content: line.to_string(),
-                    }),
-                    span: SpanInfo {
-                        start: line_number,
-                        end: start + line.len(),
-                    },
-                    source: source_str.clone(),
-                })
-            } else if trimmed.starts_with("pophtag ") {
-                Some(SpannedDirective {
-                    data: Directive::Comment(CommentDirective {
-                        content: line.to_string(),
-                    }),
-                    span: SpanInfo {
-                        start: line_number,
-                        end: start + line.len(),
-                    },
-                    source: source_str.clone(),
-                })
-            } else {
-                None
-            }
+            Some(Self::parse_option(line).map(spanned))
+        } else if line.starts_with("include ") {
+            Some(Self::parse_include(line).map(spanned))
+        } else if line.starts_with("pushtag ") || line.starts_with("poptag ") {
+            Some(Ok(spanned(Directive::Comment(CommentDirective {
+                content: line.to_string(),
+            }))))
+        } else if line.starts_with("plugin ") || line.starts_with("pushmeta ") || line.starts_with("popmeta ") {
+            None
+        } else {
+            Some(Err(format!("Unrecognized line: {}", line)))
         }
     }
 
-    fn parse_open(rest: &str, date_str: &str) -> Directive {
+    fn parse_open(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 2 {
             let account_name = parts[1];
             let (account_type, components) = Self::parse_account_name(account_name);
             let currencies = parts[2..].iter().map(|s| s.to_string()).collect();
 
-            Directive::Open(OpenDirective {
+            Ok(Directive::Open(OpenDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
@@ -422,74 +675,67 @@ impl SimpleBeancountParser {
                 },
                 currencies,
                 meta: Meta::default(),
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed open directive: {}", rest))
         }
     }
 
-    fn parse_close(rest: &str, date_str: &str) -> Directive {
+    fn parse_close(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 2 {
             let account_name = parts[1];
             let (account_type, components) = Self::parse_account_name(account_name);
 
-            Directive::Close(CloseDirective {
+            Ok(Directive::Close(CloseDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
                     name: account_name.to_string(),
                     components,
                 },
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed close directive: {}", rest))
         }
     }
 
-    fn parse_balance(rest: &str, date_str: &str) -> Directive {
+    fn parse_balance(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 4 {
             let account_name = parts[1];
             let (account_type, components) = Self::parse_account_name(account_name);
-            let amount: rust_decimal::Decimal = parts[2].replace(',', "").parse().unwrap_or_default();
-            let currency = parts[3].to_string();
+            let amount = Self::parse_amount(&parts[2..])
+                .map_err(|expression| format!("Invalid balance amount: {}", expression))?;
 
-            Directive::Balance(BalanceDirective {
+            Ok(Directive::Balance(BalanceDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
                     name: account_name.to_string(),
                     components,
                 },
-                amount: Amount { amount, currency },
-            })
+                amount,
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed balance directive: {}", rest))
         }
     }
 
-    fn parse_commodity(rest: &str, date_str: &str) -> Directive {
+    fn parse_commodity(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 2 {
-            Directive::Commodity(CommodityDirective {
+            Ok(Directive::Commodity(CommodityDirective {
                 date: Self::parse_date(date_str),
                 name: parts[1].to_string(),
-            })
+                meta: Meta::default(),
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed commodity directive: {}", rest))
         }
     }
 
-    fn parse_pad(rest: &str, date_str: &str) -> Directive {
+    fn parse_pad(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 3 {
             let account_name = parts[1];
@@ -497,7 +743,7 @@ impl SimpleBeancountParser {
             let (account_type, components) = Self::parse_account_name(account_name);
             let (pad_type, pad_components) = Self::parse_account_name(pad_name);
 
-            Directive::Pad(PadDirective {
+            Ok(Directive::Pad(PadDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
@@ -509,22 +755,20 @@ impl SimpleBeancountParser {
                     name: pad_name.to_string(),
                     components: pad_components,
                 },
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed pad directive: {}", rest))
         }
     }
 
-    fn parse_document(rest: &str, date_str: &str) -> Directive {
+    fn parse_document(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 3 {
             let account_name = parts[1];
             let (account_type, components) = Self::parse_account_name(account_name);
-            let filename = parts[2..].join(" ");
+            let filename = Self::string_argument(&parts[2..].join(" "));
 
-            Directive::Document(DocumentDirective {
+            Ok(Directive::Document(DocumentDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
@@ -532,42 +776,36 @@ impl SimpleBeancountParser {
                     components,
                 },
                 filename,
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed document directive: {}", rest))
         }
     }
 
-    fn parse_price(rest: &str, date_str: &str) -> Directive {
+    fn parse_price(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
         if parts.len() >= 4 {
-            let amount: rust_decimal::Decimal = parts[2].replace(',', "").parse().unwrap_or_default();
+            let amount = Self::parse_amount(&parts[2..])
+                .map_err(|expression| format!("Invalid price amount: {}", expression))?;
 
-            Directive::Price(PriceDirective {
+            Ok(Directive::Price(PriceDirective {
                 date: Self::parse_date(date_str),
                 commodity: parts[1].to_string(),
-                amount: Amount {
-                    amount,
-                    currency: parts[3].to_string(),
-                },
-            })
+                amount,
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed price directive: {}", rest))
         }
     }
 
-    fn parse_note(rest: &str, date_str: &str) -> Directive {
+    fn parse_note(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.splitn(3, ' ').collect();
         if parts.len() >= 3 {
             let account_name = parts[1];
             let (account_type, components) = Self::parse_account_name(account_name);
-            let comment = parts[2..].join(" ");
+            let comment = Self::string_argument(&parts[2..].join(" "));
 
-            Directive::Note(NoteDirective {
+            Ok(Directive::Note(NoteDirective {
                 date: Self::parse_date(date_str),
                 account: Account {
                     account_type,
@@ -575,68 +813,88 @@ impl SimpleBeancountParser {
                     components,
                 },
                 comment,
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed note directive: {}", rest))
         }
     }
 
-    fn parse_event(rest: &str, date_str: &str) -> Directive {
-        let parts: Vec<&str> = rest.splitn(3, ' ').collect();
-        if parts.len() >= 3 {
-            Directive::Event(EventDirective {
+    fn parse_event(rest: &str, date_str: &str) -> Result<Directive, String> {
+        match Self::quoted_strings(rest).as_slice() {
+            [event_type, description, ..] => Ok(Directive::Event(EventDirective {
                 date: Self::parse_date(date_str),
-                event_type: parts[1].to_string(),
-                description: parts[2..].join(" "),
-            })
-        } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+                event_type: event_type.clone(),
+                description: description.clone(),
+            })),
+            _ => Err(format!("Malformed event directive: {}", rest)),
         }
     }
 
-    fn parse_option(rest: &str) -> Directive {
+    fn parse_query(rest: &str, date_str: &str) -> Result<Directive, String> {
+        match Self::quoted_strings(rest).as_slice() {
+            [name, query, ..] => Ok(Directive::Query(QueryDirective {
+                date: Self::parse_date(date_str),
+                name: name.clone(),
+                query: query.clone(),
+            })),
+            _ => Err(format!("Malformed query directive: {}", rest)),
+        }
+    }
+
+    /// The `"..."` strings in `text`, without the quotes; `\"` and `\\` are
+    /// unescaped
+    fn quoted_strings(text: &str) -> Vec<String> {
+        static QUOTED: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
+        static ESCAPED: once_cell::sync::OnceCell<regex::Regex> = once_cell::sync::OnceCell::new();
+        let quoted = QUOTED.get_or_init(|| regex::Regex::new(r#""((?:[^"\\]|\\.)*)""#).unwrap());
+        let escaped = ESCAPED.get_or_init(|| regex::Regex::new(r#"\\(["\\])"#).unwrap());
+        quoted.captures_iter(text).map(|caps| escaped.replace_all(&caps[1], "$1").into_owned()).collect()
+    }
+
+    /// The quoted string at the start of `text`, else `text` as written
+    fn string_argument(text: &str) -> String {
+        let text = text.trim();
+        if text.starts_with('"') {
+            if let Some(first) = Self::quoted_strings(text).into_iter().next() {
+                return first;
+            }
+        }
+        text.to_string()
+    }
+
+    fn parse_option(rest: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.splitn(3, ' ').collect();
         if parts.len() >= 3 {
-            Directive::Option(OptionDirective {
+            Ok(Directive::Option(OptionDirective {
                 key: parts[1].trim_matches('"').to_string(),
                 value: parts[2].trim_matches('"').to_string(),
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed option directive: {}", rest))
         }
     }
 
-    fn parse_include(rest: &str) -> Directive {
+    fn parse_include(rest: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.splitn(2, ' ').collect();
         if parts.len() >= 2 {
-            Directive::Include(IncludeDirective {
+            Ok(Directive::Include(IncludeDirective {
                 path: parts[1].trim_matches('"').to_string(),
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed include directive: {}", rest))
         }
     }
 
-    fn parse_custom(rest: &str, date_str: &str) -> Directive {
+    fn parse_custom(rest: &str, date_str: &str) -> Result<Directive, String> {
         let parts: Vec<&str> = rest.split_whitespace().collect();
-        if parts.len() >= 3 {
-            Directive::Custom(CustomDirective {
+        if parts.len() >= 2 {
+            Ok(Directive::Custom(CustomDirective {
                 date: Self::parse_date(date_str),
                 custom_type: parts[1].to_string(),
                 values: parts[2..].iter().map(|s| s.to_string()).collect(),
-            })
+            }))
         } else {
-            Directive::Comment(CommentDirective {
-                content: rest.to_string(),
-            })
+            Err(format!("Malformed custom directive: {}", rest))
         }
     }
 
@@ -711,6 +969,18 @@ mod tests {
         assert_eq!(directives.len(), 1);
     }
 
+    #[test]
+    fn test_parse_open_metadata() {
+        let input = "2023-01-01 open Assets:Cash CNY\n  status: \"paused\"\n2023-01-02 open Assets:Bank";
+        let directives = SimpleBeancountParser::parse(input).unwrap();
+        assert_eq!(directives.len(), 2);
+        match &directives[0].data {
+            Directive::Open(open) => assert_eq!(open.meta.get("status").map(|v| v.as_str()), Some("paused")),
+            other => panic!("expected open, got {:?}", other),
+        }
+        assert_eq!(directives[1].span.start, 3);
+    }
+
     #[test]
     fn test_parse_balance() {
         let input = "2023-01-15 balance Assets:Cash 100.00 CNY";
@@ -769,4 +1039,178 @@ mod tests {
             panic!("Expected Transaction directive, got {:?}", directives[0].data);
         }
     }
+
+    #[test]
+    fn test_parse_recovers_from_malformed_directives() {
+        let input = r#"2024-01-01 open Assets:Cash CNY
+2024-02-30 open Assets:Bank CNY
+2024-01-05 * "Shop" "Broken"
+  Expenses:Food  12,5.x CNY
+  Assets:Cash
+2024-01-06 * "Shop" "Fine"
+  Expenses:Food  10.00 CNY
+  Assets:Cash
+2024-01-07 balance Assets:Cash abc CNY
+2024-01-08 frobnicate Assets:Cash
+garbage line
+plugin "beancount.plugins.auto"
+2024-01-09 price VT.US 100 USD
+"#;
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(input, Some("main.bean"));
+        assert_eq!(directives.len(), 3);
+        let lines: Vec<(usize, usize)> = errors.iter().map(|e| (e.line, e.end_line)).collect();
+        assert_eq!(lines, vec![(2, 2), (4, 5), (9, 9), (10, 10), (11, 11)]);
+        assert!(errors[1].message.starts_with("Invalid posting"));
+        assert_eq!(errors[0].to_string(), "main.bean:2:1: Invalid date: 2024-02-30");
+        assert_eq!(errors[4].text, "garbage line");
+        // Columns point at the text that failed
+        assert_eq!((errors[1].column, errors[1].text.as_str()), (3, "Expenses:Food  12,5.x CNY"));
+        assert_eq!(errors[2].column, 32);
+        assert_eq!(errors[3].column, 12);
+        assert!(errors.iter().all(|e| e.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_parse_multiline_strings_and_comments() {
+        let input = r#"2024-01-05 * "Shop" "Lunch with
+the team" ; paid by card
+  Expenses:Food  60 CNY ; split three ways
+  Assets:Cash  -60 CNY
+2024-01-06 open Assets:Bank CNY ; opened online
+2024-01-07 note Assets:Bank "Fee; waived
+after a call"
+2024-01-08 * "Shop" "Broken
+  Expenses:Food  10 CNY
+  Assets:Cash
+2024-01-09 balance Assets:Cash 1,000.00 CNY ; counted
+# a "header
+2024-01-10 note Assets:Bank "Never closed
+"#;
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(input, None);
+        assert_eq!(directives.len(), 4);
+        let Directive::Transaction(txn) = &directives[0].data else { panic!("expected a transaction") };
+        assert_eq!(txn.narration.as_deref(), Some("Lunch with\nthe team"));
+        assert_eq!(txn.postings[0].amount.as_ref().unwrap().amount, rust_decimal::Decimal::from(60));
+        let Directive::Open(open) = &directives[1].data else { panic!("expected an open") };
+        assert_eq!(open.currencies, vec!["CNY"]);
+        let Directive::Note(note) = &directives[2].data else { panic!("expected a note") };
+        assert_eq!(note.comment, "Fee; waived\nafter a call");
+        // Line numbers count lines of the file
+        assert_eq!(directives[2].span.start, 6);
+        assert_eq!(directives[3].span.start, 11);
+
+        // A quote left open is an error at that line, up to the next dated
+        // line or the end of the file
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].line, errors[0].column, errors[0].end_line), (8, 21, 10));
+        assert_eq!(errors[0].message, "Unterminated string: \"Broken");
+        assert_eq!((errors[1].line, errors[1].column, errors[1].end_line), (13, 29, 13));
+    }
+
+    #[test]
+    fn test_parse_amount_expressions() {
+        let input = r#"2024-01-05 * "Shop" "Two tickets"
+  Expenses:Fun  2 * 35.00 USD
+  Assets:Cash  -(10.00 + 2.50) * 4 - 20 USD @ 7.1 CNY
+2024-01-06 balance Assets:Cash 2 * 35.00 USD
+2024-01-07 balance Assets:Bank (10.00 + 2.50) ~ 0.01 USD
+2024-01-08 price VT.US 100 / 4 USD
+2024-01-09 balance Assets:Bank 10 + USD
+"#;
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(input, None);
+        let number = |n: i64, scale: u32| rust_decimal::Decimal::new(n, scale);
+        let Directive::Transaction(txn) = &directives[0].data else { panic!("expected a transaction") };
+        assert_eq!(txn.postings[0].amount.as_ref().unwrap().amount, number(70, 0));
+        assert_eq!(txn.postings[1].amount.as_ref().unwrap().amount, number(-70, 0));
+        assert!(matches!(&txn.postings[1].price, Some(Price::Single(price)) if price.amount == number(71, 1)));
+        let Directive::Balance(balance) = &directives[1].data else { panic!("expected a balance") };
+        assert_eq!((balance.amount.amount, balance.amount.currency.as_str()), (number(70, 0), "USD"));
+        let Directive::Balance(balance) = &directives[2].data else { panic!("expected a balance") };
+        assert_eq!((balance.amount.amount, balance.amount.currency.as_str()), (number(125, 1), "USD"));
+        let Directive::Price(price) = &directives[3].data else { panic!("expected a price") };
+        assert_eq!(price.amount.amount, number(25, 0));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Invalid balance amount: 10 +");
+
+        assert_eq!(SimpleBeancountParser::parse_number("-(1 + 2) * 1,000 / 4"), Some(number(-750, 0)));
+        assert_eq!(SimpleBeancountParser::parse_number("1.5"), Some(number(15, 1)));
+        assert_eq!(SimpleBeancountParser::parse_number("10 / 0"), None);
+        assert_eq!(SimpleBeancountParser::parse_number("1 +"), None);
+        assert_eq!(SimpleBeancountParser::parse_number("(1"), None);
+        // Nesting is bounded instead of overflowing the stack
+        let deep = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert_eq!(SimpleBeancountParser::parse_number(&deep), None);
+        assert_eq!(SimpleBeancountParser::parse_number(&"-".repeat(10_000)), None);
+        assert_eq!(SimpleBeancountParser::parse_number("((-(2)))"), Some(number(-2, 0)));
+    }
+
+    #[test]
+    fn test_parse_renamed_roots() {
+        let input = r#"option "name_assets" "Vermoegen"
+2024-01-05 * "Markt" "Einkauf"
+  beleg: "A-17"
+  Ausgaben:Essen  40.00 EUR
+  ! Vermoegen:Bank
+"#;
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(input, None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Directive::Transaction(txn) = &directives[1].data else { panic!("expected a transaction") };
+        assert_eq!(txn.postings.len(), 2);
+        assert_eq!(txn.postings[0].account.name, "Ausgaben:Essen");
+        assert_eq!(txn.postings[1].account.name, "Vermoegen:Bank");
+        assert_eq!(txn.meta.get("beleg").map(|v| v.as_str()), Some("A-17"));
+    }
+
+    #[test]
+    fn test_is_account_name() {
+        assert!(SimpleBeancountParser::is_account_name("Vermoegen:Bank"));
+        assert!(SimpleBeancountParser::is_account_name("Assets:Cash:2024-Trip"));
+        assert!(SimpleBeancountParser::is_account_name("Активы:Банк"));
+        assert!(SimpleBeancountParser::is_account_name("Assets:DebitCard:中国银行:6295"));
+        assert!(!SimpleBeancountParser::is_account_name("beleg:"));
+        assert!(!SimpleBeancountParser::is_account_name("Beleg:"));
+        assert!(!SimpleBeancountParser::is_account_name("Assets"));
+        assert!(!SimpleBeancountParser::is_account_name("\"Assets:Bank\""));
+        assert!(!SimpleBeancountParser::is_account_name("2024:Assets"));
+        assert!(!SimpleBeancountParser::is_account_name("Assets:bank"));
+    }
+
+    #[test]
+    fn test_parse_total_price_and_dotted_currency() {
+        let input = r#"2024-01-05 * "Broker" "Buy"
+  Assets:Broker  2 VT.US @@ 200.00 USD
+  Assets:Cash
+"#;
+        let directives = SimpleBeancountParser::parse(input).unwrap();
+        let Directive::Transaction(txn) = &directives[0].data else {
+            panic!("Expected Transaction directive");
+        };
+        assert_eq!(txn.postings[0].amount.as_ref().unwrap().currency, "VT.US");
+        assert!(matches!(txn.postings[0].price, Some(Price::Total(_))));
+    }
+
+    #[test]
+    fn test_parse_notes_documents_events_queries() {
+        let input = r#"2024-01-02 note Assets:Bank "Called about the fee"
+2024-01-03 document Assets:Bank "statements/2024-01.pdf"
+2024-01-04 event "location" "Berlin, Germany"
+2024-01-05 query "food" "SELECT account, sum(position) WHERE account ~ 'Food'"
+2024-01-06 event location
+2024-01-07 note Assets:Bank "Asked about the \"fee\" again"
+"#;
+        let (directives, errors) = SimpleBeancountParser::parse_recovering(input, None);
+        assert_eq!(directives.len(), 5);
+        assert_eq!(errors.len(), 1);
+        let Directive::Note(note) = &directives[0].data else { panic!("expected a note") };
+        assert_eq!((note.account.name.as_str(), note.comment.as_str()), ("Assets:Bank", "Called about the fee"));
+        let Directive::Document(document) = &directives[1].data else { panic!("expected a document") };
+        assert_eq!(document.filename, "statements/2024-01.pdf");
+        let Directive::Event(event) = &directives[2].data else { panic!("expected an event") };
+        assert_eq!((event.event_type.as_str(), event.description.as_str()), ("location", "Berlin, Germany"));
+        let Directive::Query(query) = &directives[3].data else { panic!("expected a query") };
+        assert_eq!(query.name, "food");
+        assert_eq!(query.query, "SELECT account, sum(position) WHERE account ~ 'Food'");
+        let Directive::Note(note) = &directives[4].data else { panic!("expected a note") };
+        assert_eq!(note.comment, r#"Asked about the "fee" again"#);
+    }
 }
@@ -47,6 +47,16 @@ pub enum Date {
     DateTime(chrono::NaiveDateTime),
 }
 
+impl Date {
+    /// Get the calendar date (time part dropped)
+    pub fn naive_date(&self) -> chrono::NaiveDate {
+        match self {
+            Date::Date(d) => *d,
+            Date::DateTime(dt) => dt.date(),
+        }
+    }
+}
+
 /// String value (quoted or unquoted)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StringValue {
@@ -0,0 +1,715 @@
+//! Accounts: the tree, the account detail page and its fragments
+
+use crate::{attr_escape, html_escape};
+use beanweb_core::account_tree::AccountTreeNode;
+use beanweb_core::trends::AccountMonthly;
+use beanweb_core::{Account, AccountDocument, AccountNote, AccountStatus, Suggestion};
+use std::collections::HashMap;
+
+/// Name and amount cells of a tree row
+fn row_cells(node: &AccountTreeNode, depth: usize, has_children: bool) -> String {
+    let display_name = node.alias.as_ref().unwrap_or(&node.short_name);
+    let indent_html = if depth > 0 { format!(r#"<span class="inline-block" style="width: {}px"></span>"#, depth * 24) } else { String::new() };
+    let toggle_html = if has_children {
+        r#"<svg class="w-4 h-4 text-gray-400 mr-1 flex-shrink-0" fill="none" stroke="currentColor" viewBox="0 0 24 24"><path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M9 5l7 7-7 7"/></svg>"#
+    } else {
+        r#"<span class="w-4 h-4 mr-1 flex-shrink-0"></span>"#
+    };
+    let account_html = if node.is_real {
+        format!(r#"<a href="/accounts/{}" class="text-indigo-600 hover:text-indigo-800 font-medium">{}</a>"#, urlencoding::encode(&node.name), html_escape(display_name))
+    } else {
+        format!(r#"<span class="font-medium text-gray-700">{}</span>"#, html_escape(display_name))
+    };
+    let closed_badge = match node.account_status.as_str() {
+        "closed" => String::from(r#"<span class="ml-2 px-2 py-0.5 text-xs border rounded text-gray-500">Close</span>"#),
+        "paused" => String::from(r#"<span class="ml-2 px-2 py-0.5 text-xs border rounded text-amber-600 border-amber-300">暂停</span>"#),
+        _ => String::new(),
+    };
+
+    // Format multi-currency display
+    // Show the report currency in main column, Other currencies in separate part
+    let currency = &node.amount.calculated.currency;
+    let cny_amount = node.amount.detail.get(currency).cloned().unwrap_or_else(|| "0.00".to_string());
+    let cny_num: f64 = cny_amount.parse().unwrap_or(0.0);
+    let cny_class = if cny_num < 0.0 { "text-red-600" } else if cny_num == 0.0 { "text-gray-400" } else { "text-green-600" };
+    let cny_html = format!(r#"<span class="font-medium {}">{}</span>"#, cny_class, cny_amount);
+
+    // Check for other currencies
+    let other_currencies: Vec<_> = node.amount.detail.iter()
+        .filter(|(k, _)| *k != currency)
+        .filter(|(_, v)| {
+            if let Ok(n) = (*v).parse::<f64>() {
+                n != 0.0
+            } else {
+                !v.is_empty() && v.as_str() != "0.00"
+            }
+        })
+        .collect();
+
+    let other_html = if other_currencies.is_empty() {
+        String::new()
+    } else {
+        let other_text: Vec<String> = other_currencies.iter()
+            .map(|(currency, amount)| format!("{}: {}", currency, amount))
+            .collect();
+        format!(r#"<span class="font-medium text-blue-600 ml-2" title="Other currencies">({})</span>"#, other_text.join(", "))
+    };
+
+    // Parents show the roll-up of their subtree, own postings included
+    let rollup_html = if has_children {
+        r#"<span class="text-xs text-gray-400 mr-1" title="含子账户合计">合计</span>"#
+    } else {
+        ""
+    };
+
+    // Add currency label
+    let amount_html = format!(r#"{}{}{} <span class="text-gray-400 text-sm">{}</span>"#, rollup_html, cny_html, other_html, currency);
+
+    format!(r#"<div class="flex items-center flex-1 min-w-0">{}{}{}{}</div><div class="flex items-center gap-2 flex-shrink-0">{}{}</div>"#,
+        indent_html, toggle_html, account_html, closed_badge, amount_html, period_change(node.change))
+}
+
+/// Net change over the selected range, arrow and colour by direction
+fn period_change(change: Option<f64>) -> String {
+    let (class, text) = match change {
+        Some(change) if change >= 0.005 => ("text-green-600", format!("▲ {:.2}", change)),
+        Some(change) if change <= -0.005 => ("text-red-600", format!("▼ {:.2}", change.abs())),
+        _ => ("text-gray-300", "—".to_string()),
+    };
+    format!(r#"<span class="w-28 text-right text-sm {}" title="本期变动">{}</span>"#, class, text)
+}
+
+/// Which accounts the tree leaves out: closed ones on request, paused ones
+/// unless asked for (see [`beanweb_core::pause`])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeFilter {
+    pub hide_closed: bool,
+    pub show_paused: bool,
+}
+
+impl TreeFilter {
+    /// From `?hide_closed=true&show_paused=true`
+    pub fn from_query(query: Option<&HashMap<String, String>>) -> Self {
+        let flag = |name: &str| query.and_then(|q| q.get(name)).is_some_and(|s| s == "true");
+        Self { hide_closed: flag("hide_closed"), show_paused: flag("show_paused") }
+    }
+
+    pub fn hides(&self, node: &AccountTreeNode) -> bool {
+        match node.account_status.as_str() {
+            "closed" => self.hide_closed,
+            "paused" => !self.show_paused,
+            _ => false,
+        }
+    }
+
+    /// Query parameters carrying this filter to lazily loaded levels
+    fn query_params(&self) -> String {
+        format!("{}{}", if self.hide_closed { "&hide_closed=true" } else { "" }, if self.show_paused { "&show_paused=true" } else { "" })
+    }
+}
+
+fn node(node: &AccountTreeNode, depth: usize, search_term: &str, filter: TreeFilter) -> String {
+    if filter.hides(node) { return String::new(); }
+
+    let node_matches = if search_term.is_empty() {
+        true
+    } else {
+        let search_lower = search_term.to_lowercase();
+        node.name.to_lowercase().contains(&search_lower)
+            || node.alias.as_ref().is_some_and(|a| a.to_lowercase().contains(&search_lower))
+    };
+
+    fn has_matching_descendant(node: &AccountTreeNode, search_lower: &str, filter: TreeFilter) -> bool {
+        if filter.hides(node) { return false; }
+        if node.name.to_lowercase().contains(search_lower)
+            || node.alias.as_ref().is_some_and(|a| a.to_lowercase().contains(search_lower)) {
+            return true;
+        }
+        if let Some(children) = &node.children {
+            children.iter().any(|c| has_matching_descendant(c, search_lower, filter))
+        } else {
+            false
+        }
+    }
+
+    let visible_children: Vec<&AccountTreeNode> = node.children.as_ref()
+        .map(|children| {
+            if search_term.is_empty() {
+                children.iter().filter(|child| !filter.hides(child)).collect()
+            } else {
+                let search_lower = search_term.to_lowercase();
+                children.iter().filter(|child| {
+                    let child_visible = !filter.hides(child);
+                    let child_matches = child.name.to_lowercase().contains(&search_lower)
+                        || child.alias.as_ref().is_some_and(|a| a.to_lowercase().contains(&search_lower));
+                    child_visible && (child_matches || has_matching_descendant(child, &search_lower, filter))
+                }).collect()
+            }
+        }).unwrap_or_default();
+
+    let should_show = search_term.is_empty() || node_matches || !visible_children.is_empty();
+
+    if !should_show { return String::new(); }
+
+    let has_visible_children = !visible_children.is_empty();
+    let cells = row_cells(node, depth, has_visible_children);
+
+    let row = if has_visible_children && !node.is_real {
+        // Virtual node (category) with children - show as collapsible group
+        format!(r#"<details class="pl-4" open><summary class="flex items-center py-2 px-3 hover:bg-gray-50 cursor-pointer list-none" data-path="{}">{}</summary>"#,
+            node.path, cells)
+    } else if node.is_real {
+        // Real account (with its own transactions) - show as regular row
+        // If it also has children, they will be rendered below
+        format!(r#"<div class="flex items-center py-2 px-3 hover:bg-gray-50 border-b border-gray-100" data-path="{}">{}</div>"#,
+            node.path, cells)
+    } else {
+        // Real account without children - show as regular row
+        format!(r#"<div class="flex items-center py-2 px-3 hover:bg-gray-50 border-b border-gray-100" data-path="{}">{}</div>"#,
+            node.path, cells)
+    };
+
+    let mut html = row;
+    if has_visible_children {
+        for child in visible_children {
+            html.push_str(&self::node(child, depth + 1, search_term, filter));
+        }
+        // Close the details tag if this is a virtual node (category)
+        if !node.is_real && has_visible_children {
+            html.push_str("</details>");
+        }
+    }
+    html
+}
+
+/// The whole tree, only nodes matching `search_term` and their ancestors
+/// when it isn't empty
+pub fn tree(tree: &[AccountTreeNode], search_term: &str, filter: TreeFilter) -> String {
+    if tree.is_empty() { return String::from(r#"<div class="text-center py-12 text-gray-500"><p>暂无账户数据</p></div>"#); }
+    let mut html = String::new();
+    for node in tree { html.push_str(&self::node(node, 0, search_term, filter)); }
+    if html.is_empty() { return String::from(r#"<div class="text-center py-12 text-gray-500"><p>没有找到匹配的账户</p></div>"#); }
+    html
+}
+
+/// Render one level of the lazily loaded tree
+/// Nodes with children fetch them (`/accounts/list?parent=...`) when first expanded
+pub fn level(nodes: &[AccountTreeNode], filter: TreeFilter) -> String {
+    if nodes.is_empty() { return String::from(r#"<div class="text-center py-12 text-gray-500"><p>暂无账户数据</p></div>"#); }
+    let filter_params = filter.query_params();
+    let mut html = String::new();
+    for node in nodes.iter().filter(|n| !filter.hides(n)) {
+        let cells = row_cells(node, node.depth, node.has_children);
+        if node.has_children {
+            html.push_str(&format!(r#"<details data-path="{}"><summary class="flex items-center py-2 px-3 hover:bg-gray-50 border-b border-gray-100 cursor-pointer list-none">{}</summary><div hx-get="/accounts/list?parent={}{}" hx-trigger="toggle once from:closest details" hx-swap="outerHTML"><p class="py-2 px-3 text-sm text-gray-400">加载中...</p></div></details>"#,
+                node.path, cells, urlencoding::encode(&node.path), filter_params));
+        } else {
+            html.push_str(&format!(r#"<div class="flex items-center py-2 px-3 hover:bg-gray-50 border-b border-gray-100" data-path="{}">{}</div>"#,
+                node.path, cells));
+        }
+    }
+    html
+}
+
+/// Totals of the summary cards: assets, liabilities, income and expenses
+fn summary_totals(tree: &[AccountTreeNode]) -> (String, String, String, String) {
+    fn calculate_total(node: &AccountTreeNode, account_type: &str) -> f64 {
+        let is_type = node.name.starts_with(account_type);
+        let own_balance = node.amount.calculated.number.parse::<f64>().unwrap_or(0.0);
+        let children_total: f64 = node.children.as_ref()
+            .map(|children| children.iter().map(|c| calculate_total(c, account_type)).sum())
+            .unwrap_or(0.0);
+        if is_type { own_balance + children_total } else { children_total }
+    }
+    let total_assets = tree.iter().map(|n| calculate_total(n, "Assets")).sum();
+    let total_liabilities = tree.iter().map(|n| calculate_total(n, "Liabilities")).sum();
+    let total_income = tree.iter().map(|n| calculate_total(n, "Income")).sum();
+    let total_expenses = tree.iter().map(|n| calculate_total(n, "Expenses")).sum();
+    let fmt = |v: f64| if v == 0.0 { String::from("0.00") } else { format!("{:.2}", v) };
+    (fmt(total_assets), fmt(total_liabilities), fmt(total_income), fmt(total_expenses))
+}
+
+/// Accounts page content: summary cards over `roots`, the search form and
+/// the rendered tree (see [`tree`] and [`level`])
+pub fn page(roots: &[AccountTreeNode], tree_html: &str, search_term: &str, filter: TreeFilter) -> String {
+    let (total_assets, total_liabilities, total_income, total_expenses) = summary_totals(roots);
+    let header_html = r#"<div class="mb-6"><h2 class="text-2xl font-bold">账户</h2></div>"#;
+
+    let summary_html = format!(r#"<div class="grid grid-cols-1 lg:grid-cols-4 gap-4 mb-6">
+            <div class="bg-gradient-to-br from-green-50 to-green-100 p-4 rounded-xl border border-green-200">
+                <div class="flex items-center gap-2 mb-2">
+                    <svg class="w-5 h-5 text-green-600" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M12 8c-1.657 0-3 .895-3 2s1.343 2 3 2 3 .895 3 2-1.343 2-3 2m0-8c1.11 0 2.08.402 2.599 1M12 8V7m0 1v8m0 0v1m0-1c-1.11 0-2.08-.402-2.599-1M21 12a9 9 0 11-18 0 9 9 0 0118 0z"/>
+                    </svg>
+                    <p class="text-sm text-green-700 font-medium">总资产</p>
+                </div>
+                <p class="text-2xl font-bold text-green-800">{}</p>
+            </div>
+            <div class="bg-gradient-to-br from-red-50 to-red-100 p-4 rounded-xl border border-red-200">
+                <div class="flex items-center gap-2 mb-2">
+                    <svg class="w-5 h-5 text-red-600" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M9 12l2 2 4-4m6 2a9 9 0 11-18 0 9 9 0 0118 0z"/>
+                    </svg>
+                    <p class="text-sm text-red-700 font-medium">总负债</p>
+                </div>
+                <p class="text-2xl font-bold text-red-800">{}</p>
+            </div>
+            <div class="bg-gradient-to-br from-blue-50 to-blue-100 p-4 rounded-xl border border-blue-200">
+                <div class="flex items-center gap-2 mb-2">
+                    <svg class="w-5 h-5 text-blue-600" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M13 7h8m0 0v8m0-8l-8 8-4-4-6 6"/>
+                    </svg>
+                    <p class="text-sm text-blue-700 font-medium">总收入</p>
+                </div>
+                <p class="text-2xl font-bold text-blue-800">{}</p>
+            </div>
+            <div class="bg-gradient-to-br from-amber-50 to-amber-100 p-4 rounded-xl border border-amber-200">
+                <div class="flex items-center gap-2 mb-2">
+                    <svg class="w-5 h-5 text-amber-600" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M17 9V7a2 2 0 00-2-2H5a2 2 0 00-2 2v6a2 2 0 002 2h2m2 4h10a2 2 0 002-2v-6a2 2 0 00-2-2H9a2 2 0 00-2 2v6a2 2 0 002 2zm7-5a2 2 0 11-4 0 2 2 0 014 0z"/>
+                    </svg>
+                    <p class="text-sm text-amber-700 font-medium">总支出</p>
+                </div>
+                <p class="text-2xl font-bold text-amber-800">{}</p>
+            </div>
+        </div>"#, total_assets, total_liabilities, total_income, total_expenses);
+
+    let search_attr = if search_term.is_empty() { String::new() } else { format!(r#" value="{}""#, attr_escape(search_term)) };
+    let hide_closed_attr = if filter.hide_closed { " checked" } else { "" };
+    let show_paused_attr = if filter.show_paused { " checked" } else { "" };
+
+    let input_html = format!(r#"<input type="text" name="search" placeholder="搜索账户..."{} class="w-full pl-10 pr-4 py-2.5 border border-gray-300 rounded-lg focus:ring-2 focus:ring-indigo-500 focus:border-indigo-500">"#,
+        search_attr);
+
+    let mut filter_html = format!(r#"<div class="bg-white rounded-xl shadow-sm border border-gray-200 overflow-hidden">
+            <div class="p-4 border-b border-gray-200 bg-gray-50">
+                <div class="flex flex-col sm:flex-row sm:items-center gap-4">
+                    <form action="/accounts" method="get" class="flex-1 flex gap-4">
+                        <div class="relative flex-1">
+                            <svg class="absolute left-3 top-1/2 -translate-y-1/2 w-5 h-5 text-gray-400" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z"/>
+                            </svg>
+                            {}
+                        </div>
+                        <label class="flex items-center gap-2 cursor-pointer select-none">
+                            <input type="checkbox" name="hide_closed" value="true"{} class="w-4 h-4 text-indigo-600 rounded border-gray-300 focus:ring-indigo-500">
+                            <span class="text-sm text-gray-600">隐藏已关闭账户</span>
+                        </label>
+                        <label class="flex items-center gap-2 cursor-pointer select-none">
+                            <input type="checkbox" name="show_paused" value="true"{} class="w-4 h-4 text-indigo-600 rounded border-gray-300 focus:ring-indigo-500">
+                            <span class="text-sm text-gray-600">显示暂停账户</span>
+                        </label>
+                        <button type="submit" class="px-4 py-2.5 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700">搜索</button>
+                        <a href="/accounts" class="px-4 py-2.5 border border-gray-300 rounded-lg hover:bg-gray-50 text-gray-700">重置</a>
+                    </form>
+                </div>
+            </div>
+            <div class="divide-y divide-gray-100">"#,
+        input_html, hide_closed_attr, show_paused_attr);
+    filter_html.push_str(tree_html);
+    filter_html.push_str(r#"</div>
+        </div>"#);
+
+    format!("{}{}{}", header_html, summary_html, filter_html)
+}
+
+/// Paused badge and pause/resume button; closed accounts get neither
+pub fn pause_control(account: &Account) -> String {
+    let url = format!("/accounts/{}/paused", urlencoding::encode(&account.name));
+    match account.status {
+        AccountStatus::Paused => format!(
+            r#"<span class="px-2 py-0.5 text-xs border rounded text-amber-600 border-amber-300">暂停</span><button hx-post="{}?paused=false" hx-swap="afterend" class="text-sm text-indigo-600 hover:text-indigo-800">恢复账户</button>"#,
+            url
+        ),
+        AccountStatus::Open => format!(
+            r#"<button hx-post="{}?paused=true" hx-swap="afterend" hx-confirm="暂停后该账户不再出现在自动补全和默认账户列表中，可随时恢复。继续？" class="text-sm text-gray-500 hover:text-gray-700">暂停账户</button>"#,
+            url
+        ),
+        AccountStatus::Closed => String::new(),
+    }
+}
+
+/// Account detail page content: balance and pause control, the monthly
+/// table and transaction list loaded over HTMX, and the `records` (see
+/// [`records`]); `transaction_count` is shown above the list
+pub fn detail_page(account: &Account, balance_display: &str, transaction_count: usize, time_selector: &str, records: &str) -> String {
+    let encoded_name = urlencoding::encode(&account.name);
+    let tx_list_url = format!("/accounts/{}/transactions/list", encoded_name);
+
+    let header_back = r#"<div class="mb-6 flex items-center gap-4">
+        <a href="/accounts" class="text-gray-500 hover:text-gray-700 flex items-center gap-1">
+            <svg class="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M15 19l-7-7 7-7"/>
+            </svg>
+            返回账户
+        </a>
+    </div>"#;
+
+    let account_info = format!(r#"<div class="mb-6">
+        <h2 class="text-2xl font-bold break-all">{}</h2>
+        <p class="text-gray-500 mt-1 flex items-center gap-2">
+            <span class="px-2 py-0.5 bg-gray-100 rounded text-sm">{}</span>
+            <span>|</span>
+            <span>余额: <span class="font-medium text-green-600">{}</span></span>
+            {}
+        </p>
+    </div>"#, html_escape(&account.name), account.account_type, balance_display, pause_control(account));
+
+    let hx_get_attr = format!("hx-get=\"{}\"", tx_list_url);
+    let hx_target_attr = "hx-target=\"#account-tx-list\"".to_string();
+    let hx_trigger_input = "hx-trigger=\"keyup changed, delay:500ms\"".to_string();
+    let hx_trigger_select = "hx-trigger=\"change\"".to_string();
+
+    let filter_bar = format!(r#"<div class="mb-4 flex items-center justify-between gap-4 flex-wrap">
+        <h3 class="text-lg font-semibold">近期交易</h3>
+        <div class="flex items-center gap-2">
+            <input type="text" name="q" placeholder="搜索交易..."
+                {} {} {}
+                class="px-3 py-2 border rounded w-48">
+            <select name="limit" {} {} {}
+                class="px-3 py-2 border rounded" onchange="this.form.requestSubmit()">
+                <option value="10">10 条</option>
+                <option value="25">25 条</option>
+                <option value="50" selected>50 条</option>
+                <option value="100">100 条</option>
+            </select>
+        </div>
+    </div>
+    <div class="mb-4">
+        <span class="text-sm text-gray-500">共 {} 笔交易</span>
+    </div>
+    <div id="account-tx-list" {}="{}?limit=50" hx-trigger="load, time-range-changed from:body, ledger-reloaded from:body" class="bg-white rounded shadow-sm p-6">
+        <p class="text-gray-500 text-center py-8">加载中...</p>
+    </div>"#,
+        hx_get_attr, hx_target_attr, hx_trigger_input,
+        hx_get_attr, hx_target_attr, hx_trigger_select,
+        transaction_count,
+        hx_get_attr.split('=').next().unwrap_or("hx-get"), tx_list_url
+    );
+
+    let monthly = format!(
+        r#"<div id="account-monthly" hx-get="/accounts/{}/monthly" hx-trigger="load, time-range-changed from:body, ledger-reloaded from:body" class="mb-6"></div>"#,
+        encoded_name
+    );
+
+    format!("{}{}{}{}{}{}", time_selector, header_back, account_info, monthly, records, filter_bar)
+}
+
+/// Page content for an account the ledger doesn't have
+pub fn not_found(account_name: &str) -> String {
+    format!(r#"<div class="mb-6 flex items-center gap-4">
+        <a href="/accounts" class="text-gray-500 hover:text-gray-700 flex items-center gap-1">
+            <svg class="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M15 19l-7-7 7-7"/>
+            </svg>
+            返回账户
+        </a>
+    </div>
+    <div class="text-center py-12">
+        <div class="mb-4">
+            <svg class="w-16 h-16 mx-auto text-gray-300" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M9.172 16.172a4 4 0 015.656 0M9 10h.01M15 10h.01M21 12a9 9 0 11-18 0 9 9 0 0118 0z"/>
+            </svg>
+        </div>
+        <h2 class="text-xl font-bold text-gray-600">未找到账户</h2>
+        <p class="text-gray-400 mt-2">{}</p>
+    </div>"#, html_escape(account_name))
+}
+
+/// Notes and documents of the account detail page; nothing when it has neither.
+/// Documents inside the documents folder link to their file
+pub fn records(notes: &[AccountNote], documents: &[AccountDocument], documents_dir: &str) -> String {
+    if notes.is_empty() && documents.is_empty() {
+        return String::new();
+    }
+    let note_rows: String = notes.iter()
+        .map(|n| format!(
+            r#"<li class="py-2 border-b last:border-0"><span class="text-gray-500 mr-3">{}</span>{}<span class="text-xs text-gray-400 ml-2">{}</span></li>"#,
+            n.date, html_escape(&n.comment), html_escape(&n.account)
+        ))
+        .collect();
+    let document_rows: String = documents.iter()
+        .map(|d| {
+            let path = std::path::Path::new(&d.filename);
+            let linked = !documents_dir.is_empty()
+                && path.components().all(|c| matches!(c, std::path::Component::Normal(_)))
+                && path.starts_with(documents_dir);
+            let name = if linked {
+                format!(r#"<a href="{}" target="_blank" class="text-indigo-600 hover:underline">{}</a>"#, crate::document_url(&d.filename), html_escape(&d.filename))
+            } else {
+                format!(r#"<span class="font-mono">{}</span>"#, html_escape(&d.filename))
+            };
+            format!(
+                r#"<li class="py-2 border-b last:border-0"><span class="text-gray-500 mr-3">{}</span>{}<span class="text-xs text-gray-400 ml-2">{}</span></li>"#,
+                d.date, name, html_escape(&d.account)
+            )
+        })
+        .collect();
+    let section = |title: &str, count: usize, rows: &str| if count == 0 {
+        String::new()
+    } else {
+        format!(r#"<div class="bg-white rounded shadow-sm p-6"><h3 class="text-lg font-semibold mb-3">{} <span class="text-sm font-normal text-gray-500">({})</span></h3><ul class="text-sm">{}</ul></div>"#, title, count, rows)
+    };
+    format!(
+        r#"<div id="account-records" class="mb-6 grid grid-cols-1 md:grid-cols-2 gap-6">{}{}</div>"#,
+        section("备注", notes.len(), &note_rows),
+        section("文档", documents.len(), &document_rows)
+    )
+}
+
+/// Monthly totals table of the account detail page, newest month first
+/// Clicking a month shows only its transactions in the list below
+pub fn monthly(monthly: &AccountMonthly) -> String {
+    let encoded_name = urlencoding::encode(&monthly.account);
+    let rows: String = monthly.months.iter().rev()
+        .map(|m| {
+            let net_class = if m.net < 0.0 { "text-red-600" } else { "text-green-600" };
+            let note = if m.unconverted > 0 {
+                format!(r#" <span class="text-xs text-amber-600" title="缺少汇率，未计入">+{} 笔未换算</span>"#, m.unconverted)
+            } else {
+                String::new()
+            };
+            format!(
+                r#"<tr class='border-b hover:bg-gray-50'><td class='p-2'><button hx-get='/accounts/{}/transactions/list?limit=50&month={}' hx-target='#account-tx-list' class='text-indigo-600 hover:underline'>{}</button>{}</td><td class='p-2 text-right text-green-600'>{:.2}</td><td class='p-2 text-right text-red-600'>{:.2}</td><td class='p-2 text-right {}'>{:.2}</td><td class='p-2 text-right font-medium'>{:.2}</td></tr>"#,
+                encoded_name, m.month, m.month, note, m.inflow, m.outflow, net_class, m.net, m.ending_balance
+            )
+        })
+        .collect();
+    format!(
+        r#"<div class="bg-white rounded shadow-sm p-6"><h3 class="text-lg font-semibold mb-3">月度汇总 <span class="text-sm font-normal text-gray-500">({})</span></h3><table class="w-full text-sm"><thead><tr class="text-gray-500 border-b"><th class="p-2 text-left">月份</th><th class="p-2 text-right">流入</th><th class="p-2 text-right">流出</th><th class="p-2 text-right">净额</th><th class="p-2 text-right">期末余额</th></tr></thead><tbody>{}</tbody></table></div>"#,
+        monthly.currency, rows
+    )
+}
+
+/// Toggles the detail of a transaction in the account's list
+const TX_DETAIL_SCRIPT: &str = r#"<script>
+    function toggleAccountDetail(id) {
+        var el = document.getElementById(id);
+        if (el.style.display === 'none') {
+            el.style.display = 'block';
+            if (el.innerHTML === '') {
+                htmx.ajax('GET', '/transactions/' + id.replace('account-tx-detail-', '') + '/detail', {target: el});
+            }
+        } else {
+            el.style.display = 'none';
+            el.innerHTML = '';
+        }
+    }
+    </script>"#;
+
+/// What a line of the account's transaction list is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Balance directive, which sets the running balance
+    Balance,
+    /// Pad, to or from the account
+    Pad,
+    Transaction,
+}
+
+/// A line of the account's transaction list
+#[derive(Debug, Clone)]
+pub struct AccountEntry {
+    pub date: String,
+    /// Empty without a time
+    pub time: String,
+    pub kind: EntryKind,
+    /// Amount posted to the account
+    pub amount: f64,
+    /// Running balance after this entry, display-signed
+    pub balance: f64,
+    pub description: String,
+}
+
+/// One page of the account's transaction list, newest first, with the
+/// pager; `total_events` counts every page, `month` (YYYY-MM) is kept by
+/// the pager. Income and expense accounts colour outflows green
+pub fn transactions(entries: &[AccountEntry], total_events: usize, account_name: &str, limit: usize, offset: usize, month: Option<&str>, income_or_expense: bool) -> String {
+    let mut html = String::new();
+    if entries.is_empty() {
+        html.push_str(r#"<div class="text-center py-12 text-gray-500"><p>暂无交易记录</p></div>"#);
+    } else {
+        html.push_str(r#"<div class="space-y-2">"#);
+        for item in entries {
+            let datetime = if item.time.is_empty() {
+                item.date.clone()
+            } else {
+                format!("{} <span class='text-gray-400'>{}</span>", item.date, item.time)
+            };
+
+            match item.kind {
+                EntryKind::Balance => {
+                    // Balance entry - gray color
+                    html.push_str(&format!(r#"<div class="border border-l-4 rounded-r-lg p-3 hover:bg-gray-50 transition mb-2" style="border-left-color: #6B7280">
+                        <div class="flex items-center justify-between gap-2">
+                            <div class="flex items-center gap-3 flex-1 min-w-0">
+                                <span class="w-1 h-8 rounded flex-shrink-0" style="background:#6B7280"></span>
+                                <div class="flex-1 min-w-0">
+                                    <div class="text-sm text-gray-500">{}</div>
+                                    <div class="font-medium truncate">{}</div>
+                                </div>
+                            </div>
+                            <div class="flex flex-col items-end gap-1 flex-shrink-0">
+                                <span class="font-medium text-blue-600">余额重置</span>
+                                <span class="text-xs text-gray-400">余额: {:.2}</span>
+                            </div>
+                        </div>
+                    </div>"#, datetime, html_escape(&item.description), item.balance));
+                }
+                EntryKind::Pad => {
+                    // Pad entry - purple color
+                    // For Income/Expenses accounts: positive = expense (red), negative = income (green)
+                    // For Assets/Liabilities accounts: positive = increase (green), negative = decrease (red)
+                    let (amount_str, amount_class) = if item.amount < 0.0 {
+                        // Negative: for Income/Expenses = income (green), for Assets/Liabilities = decrease (red)
+                        if income_or_expense {
+                            (format!("{:.2}", item.amount.abs()), "text-green-600")
+                        } else {
+                            (format!("{:.2}", item.amount.abs()), "text-red-600")
+                        }
+                    } else if item.amount > 0.0 {
+                        // Positive: for Income/Expenses = expense (red), for Assets/Liabilities = increase (green)
+                        if income_or_expense {
+                            (format!("{:.2}", item.amount.abs()), "text-red-600")
+                        } else {
+                            (format!("{:.2}", item.amount), "text-green-600")
+                        }
+                    } else {
+                        ("0.00".to_string(), "text-gray-400")
+                    };
+
+                    html.push_str(&format!(r#"<div class="border border-l-4 rounded-r-lg p-3 hover:bg-gray-50 transition mb-2" style="border-left-color: #8B5CF6">
+                        <div class="flex items-center justify-between gap-2">
+                            <div class="flex items-center gap-3 flex-1 min-w-0">
+                                <span class="w-1 h-8 rounded flex-shrink-0" style="background:#8B5CF6"></span>
+                                <div class="flex-1 min-w-0">
+                                    <div class="text-sm text-gray-500">{}</div>
+                                    <div class="font-medium truncate">{}</div>
+                                </div>
+                            </div>
+                            <div class="flex flex-col items-end gap-1 flex-shrink-0">
+                                <span class="font-medium {}">{}</span>
+                                <span class="text-xs text-gray-400">余额: {:.2}</span>
+                            </div>
+                        </div>
+                    </div>"#, datetime, html_escape(&item.description), amount_class, amount_str, item.balance));
+                }
+                EntryKind::Transaction => {
+                    // Transaction entry - green color
+                    // For Income/Expenses accounts: positive = expense (red), negative = income (green)
+                    // For Assets/Liabilities accounts: positive = increase (green), negative = decrease (red)
+                    let (amount_str, amount_class) = if item.amount < 0.0 {
+                        // Negative: for Income/Expenses = income (green), for Assets/Liabilities = decrease (red)
+                        if income_or_expense {
+                            (format!("{:.2}", item.amount.abs()), "text-green-600")
+                        } else {
+                            (format!("{:.2}", item.amount.abs()), "text-red-600")
+                        }
+                    } else if item.amount > 0.0 {
+                        // Positive: for Income/Expenses = expense (red), for Assets/Liabilities = increase (green)
+                        if income_or_expense {
+                            (format!("{:.2}", item.amount.abs()), "text-red-600")
+                        } else {
+                            (format!("{:.2}", item.amount), "text-green-600")
+                        }
+                    } else {
+                        ("0.00".to_string(), "text-gray-400")
+                    };
+
+                    html.push_str(&format!(r#"<div class="border border-l-4 rounded-r-lg p-3 hover:bg-gray-50 transition mb-2" style="border-left-color: #10B981">
+                        <div class="flex items-center justify-between gap-2">
+                            <div class="flex items-center gap-3 flex-1 min-w-0">
+                                <span class="w-1 h-8 rounded flex-shrink-0" style="background:#10B981"></span>
+                                <div class="flex-1 min-w-0">
+                                    <div class="text-sm text-gray-500">{}</div>
+                                    <div class="font-medium truncate">{}</div>
+                                </div>
+                            </div>
+                            <div class="flex flex-col items-end gap-1 flex-shrink-0">
+                                <span class="font-medium {}">{}</span>
+                                <span class="text-xs text-gray-400">余额: {:.2}</span>
+                            </div>
+                        </div>
+                    </div>"#, datetime, html_escape(&item.description), amount_class, amount_str, item.balance));
+                }
+            }
+        }
+        html.push_str("</div>");
+
+        let current_page = offset.checked_div(limit).map_or(1, |page| page + 1);
+        let total_pages = if limit == 0 { 1 } else { total_events.div_ceil(limit) };
+        let prev_offset = offset.saturating_sub(limit);
+        let next_offset = offset + limit;
+        let last_offset = (total_pages.saturating_sub(1)) * limit;
+        // Pages keep the month filter
+        let list_url = match month {
+            Some(month) => format!("/accounts/{}/transactions/list?month={}&", urlencoding::encode(account_name), month),
+            None => format!("/accounts/{}/transactions/list?", urlencoding::encode(account_name)),
+        };
+
+        let target = "#account-tx-list";
+        html.push_str(&format!(
+            r#"<div class='mt-6 flex items-center justify-between flex-wrap gap-4'>
+            <span class='text-sm text-gray-500'>共 {} 条记录，第 {} / {} 页</span>
+            <div class='flex items-center gap-2'>
+                <button {} onclick='htmx.ajax("GET", "{}limit={}&offset=0", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>首页</button>
+                <button {} onclick='htmx.ajax("GET", "{}limit={}&offset={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>上一页</button>
+                <span class='text-sm text-gray-600'>第 <input type='number' id='account-page-jump-input' min='1' max='{}' value='{}' class='w-16 text-center border rounded px-2 py-1'> 页</span>
+                <button onclick='const p=document.getElementById("account-page-jump-input").value; htmx.ajax("GET", "{}limit={}&offset=" + (p-1)*{} + "", "{}")' class='px-3 py-1 border rounded bg-blue-50 hover:bg-blue-100 text-blue-600'>跳转</button>
+                <button {} onclick='htmx.ajax("GET", "{}limit={}&offset={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>下一页</button>
+                <button {} onclick='htmx.ajax("GET", "{}limit={}&offset={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>末页</button>
+            </div>
+        </div>"#,
+            total_events, current_page, total_pages,
+            if current_page == 1 { "disabled" } else { "" },
+            list_url, limit, target,
+            if current_page == 1 { "disabled" } else { "" },
+            list_url, limit, prev_offset, target,
+            total_pages, current_page,
+            list_url, limit, limit, target,
+            if current_page >= total_pages { "disabled" } else { "" },
+            list_url, limit, next_offset, target,
+            if current_page >= total_pages { "disabled" } else { "" },
+            list_url, limit, last_offset, target
+        ));
+        html.push_str(r#"<style>.disabled{cursor:not-allowed;opacity:0.5;pointer-events:none}</style>"#);
+        // Add toggle script for account transaction details
+        html.push_str(TX_DETAIL_SCRIPT);
+    }
+    html
+}
+
+/// Transaction list limited to `month`, with a button clearing the filter
+pub fn month_filter(account_name: &str, month: &str, limit: usize, list: &str) -> String {
+    format!(
+        r#"<div class='mb-4 flex items-center gap-2 text-sm'><span class='px-2 py-1 bg-indigo-50 text-indigo-700 rounded'>仅显示 {}</span><button hx-get='/accounts/{}/transactions/list?limit={}' hx-target='#account-tx-list' class='text-gray-500 hover:text-gray-700 underline'>清除</button></div>{}"#,
+        html_escape(month), urlencoding::encode(account_name), limit, list
+    )
+}
+
+/// Autocomplete list for the input whose suggestion box has id `target`
+pub fn suggestions(target: &str, matches: &[Suggestion]) -> String {
+    let target = attr_escape(target);
+    if matches.is_empty() {
+        return format!(r#"<div id='{}' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-40 overflow-auto hidden'></div>"#, target);
+    }
+
+    let options: Vec<String> = matches.iter().map(|a| {
+        let alias = a.alias.as_ref()
+            .map(|alias| format!("<div class='text-xs text-gray-500'>{}</div>", html_escape(alias)))
+            .unwrap_or_default();
+        format!(r#"<div class='px-3 py-2 hover:bg-indigo-50 cursor-pointer text-sm border-b last:border-0{}' data-account='{}' onclick="selectAccount(this, '{}')"><div class='font-medium'>{}</div>{}</div>"#,
+            if a.closed { " text-gray-400" } else { "" }, attr_escape(&a.name), target, html_escape(&a.name), alias)
+    }).collect();
+
+    format!(
+        r#"<div id='{}' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-40 overflow-auto'>{}</div>"#,
+        target,
+        options.join("")
+    )
+}
+
+/// Why pausing or resuming failed, in place of the button
+pub fn action_error(message: &str) -> String {
+    format!(r#"<span class='text-sm text-red-600'>{}</span>"#, html_escape(message))
+}
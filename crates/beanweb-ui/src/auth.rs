@@ -0,0 +1,169 @@
+//! Login pages, the session list and the two-factor section of the settings
+
+use crate::layout::base_html;
+use crate::{attr_escape, html_escape};
+use chrono::{DateTime, Utc};
+
+/// A logged-in device, for [`sessions`]
+pub struct SessionRow<'a> {
+    /// Public ID used to revoke the session
+    pub id: &'a str,
+    pub user_agent: &'a str,
+    pub address: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// The session of the request
+    pub current: bool,
+}
+
+fn error_line(error: Option<&str>, margin: &str) -> String {
+    error
+        .map(|e| format!("<p class='{} text-sm text-red-600'>{}</p>", margin, html_escape(e)))
+        .unwrap_or_default()
+}
+
+/// Login page: user name and password
+pub fn login_page(error: Option<&str>) -> String {
+    base_html("登录", &format!(
+        r#"<div class='min-h-screen flex items-center justify-center'>
+            <form method='post' action='/login' class='bg-white rounded-xl shadow-sm p-8 w-80'>
+                <h1 class='text-xl font-bold text-indigo-600 mb-6'>Beanweb</h1>
+                {}
+                <label class='block text-sm text-gray-600 mb-1'>用户名</label>
+                <input name='username' autocomplete='username' required class='w-full px-3 py-2 mb-4 border rounded-lg'>
+                <label class='block text-sm text-gray-600 mb-1'>密码</label>
+                <input name='password' type='password' autocomplete='current-password' required class='w-full px-3 py-2 mb-6 border rounded-lg'>
+                <button class='w-full px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>登录</button>
+            </form>
+        </div>"#,
+        error_line(error, "mb-4")
+    ))
+}
+
+/// Second step of the login: the authenticator or recovery code for the
+/// pending login `token`
+pub fn code_page(token: &str, error: Option<&str>) -> String {
+    base_html("登录", &format!(
+        r#"<div class='min-h-screen flex items-center justify-center'>
+            <form method='post' action='/login/verify' class='bg-white rounded-xl shadow-sm p-8 w-80'>
+                <h1 class='text-xl font-bold text-indigo-600 mb-6'>Beanweb</h1>
+                {}
+                <input type='hidden' name='token' value='{}'>
+                <label class='block text-sm text-gray-600 mb-1'>动态码或恢复码</label>
+                <input name='code' inputmode='numeric' autocomplete='one-time-code' autofocus required class='w-full px-3 py-2 mb-6 border rounded-lg'>
+                <button class='w-full px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>验证</button>
+            </form>
+        </div>"#,
+        error_line(error, "mb-4"), attr_escape(token)
+    ))
+}
+
+/// Settings section shown instead of `feature` while `server.auth` is unset
+pub fn auth_disabled(feature: &str) -> String {
+    format!("<p class='text-sm text-gray-500'>未启用登录认证（设置 server.auth 后{}）</p>", html_escape(feature))
+}
+
+/// Session list: one row per device, with a button to log the others out
+pub fn sessions(sessions: &[SessionRow]) -> String {
+    let rows: String = sessions.iter()
+        .map(|s| {
+            let action = if s.current {
+                "<span class='text-xs text-green-600'>当前设备</span>".to_string()
+            } else {
+                format!(
+                    "<button hx-delete='/api/sessions/{}' hx-swap='none' hx-on::after-request=\"htmx.ajax('GET', '/settings/sessions', '#sessions-list')\" class='text-xs text-red-600 hover:underline'>注销</button>",
+                    urlencoding::encode(s.id)
+                )
+            };
+            format!(
+                r#"<tr class='border-t'>
+                    <td class='py-2 pr-4 text-sm truncate max-w-xs' title='{ua_attr}'>{ua}</td>
+                    <td class='py-2 pr-4 text-sm text-gray-500'>{address}</td>
+                    <td class='py-2 pr-4 text-sm text-gray-500'>{created}</td>
+                    <td class='py-2 pr-4 text-sm text-gray-500'>{seen}</td>
+                    <td class='py-2 text-right'>{action}</td>
+                </tr>"#,
+                ua_attr = attr_escape(s.user_agent),
+                ua = html_escape(s.user_agent),
+                address = html_escape(s.address.unwrap_or("-")),
+                created = s.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                seen = s.last_seen.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                action = action,
+            )
+        })
+        .collect();
+    format!(
+        r#"<table class='w-full'>
+            <thead><tr class='text-left text-xs text-gray-500'><th class='pb-2'>设备</th><th class='pb-2'>地址</th><th class='pb-2'>登录时间</th><th class='pb-2'>最近活动</th><th></th></tr></thead>
+            <tbody>{}</tbody>
+        </table>
+        <div class='mt-4 text-right'>
+            <button hx-post='/logout' class='px-3 py-1.5 text-sm bg-gray-100 rounded-lg hover:bg-gray-200'>退出登录</button>
+        </div>"#,
+        rows
+    )
+}
+
+/// Password field of the forms that change the enrollment
+const PASSWORD_INPUT: &str = "<input name='password' type='password' autocomplete='current-password' placeholder='登录密码' required class='w-32 px-2 py-1 text-sm border rounded'>";
+/// Code field of the forms that need a current code
+const CODE_INPUT: &str = "<input name='code' inputmode='numeric' autocomplete='one-time-code' placeholder='动态码' required class='w-28 px-2 py-1 text-sm border rounded'>";
+
+/// Two-factor section: enrolled or not, with the forms that change it
+pub fn two_factor_status(enrolled: bool, recovery_codes_left: usize, error: Option<&str>) -> String {
+    let error = error_line(error, "mb-3");
+    if !enrolled {
+        return format!(
+            r#"{}<p class='text-sm text-gray-600 mb-3'>未启用。启用后登录时除密码外还需输入验证器应用中的动态码。</p>
+            <form hx-post='/settings/two-factor/setup' hx-target='#two-factor' class='flex items-center gap-2'>
+                {}
+                <button class='px-3 py-1.5 text-sm bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>启用两步验证</button>
+            </form>"#,
+            error, PASSWORD_INPUT
+        );
+    }
+    let code_form = |action: &str, label: &str, style: &str| format!(
+        r#"<form hx-post='/settings/two-factor/{}' hx-target='#two-factor' class='flex items-center gap-2'>
+            {}
+            {}
+            <button class='px-3 py-1 text-sm rounded-lg {}'>{}</button>
+        </form>"#,
+        action, PASSWORD_INPUT, CODE_INPUT, style, label
+    );
+    format!(
+        r#"{}<p class='text-sm text-green-700 mb-1'>已启用</p>
+        <p class='text-sm text-gray-500 mb-4'>剩余恢复码 {} 个</p>
+        <div class='flex flex-wrap gap-4'>{}{}</div>"#,
+        error,
+        recovery_codes_left,
+        code_form("recovery", "重新生成恢复码", "bg-gray-100 hover:bg-gray-200"),
+        code_form("disable", "停用", "bg-red-50 text-red-600 hover:bg-red-100"),
+    )
+}
+
+/// Enrollment: the `otpauth://` URI and base32 `secret` for the
+/// authenticator, and the form confirming a first code
+pub fn two_factor_setup(uri: &str, secret: &str) -> String {
+    let grouped: Vec<String> = secret.as_bytes().chunks(4).map(|c| String::from_utf8_lossy(c).into_owned()).collect();
+    format!(
+        r#"<p class='text-sm text-gray-600 mb-2'>在验证器应用中添加账户（<a href='{}' class='text-indigo-600 hover:underline'>打开</a>或手动输入密钥）：</p>
+        <p class='font-mono text-sm bg-gray-50 rounded px-3 py-2 mb-4 select-all' data-secret='{}'>{}</p>
+        <form hx-post='/settings/two-factor/enable' hx-target='#two-factor' class='flex items-center gap-2'>
+            {}
+            {}
+            <button class='px-3 py-1 text-sm bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>验证并启用</button>
+        </form>"#,
+        attr_escape(uri), attr_escape(secret), html_escape(&grouped.join(" ")), PASSWORD_INPUT, CODE_INPUT
+    )
+}
+
+/// Recovery codes in clear, shown once
+pub fn recovery_codes(codes: &[String]) -> String {
+    let items: String = codes.iter().map(|code| format!("<li class='font-mono'>{}</li>", html_escape(code))).collect();
+    format!(
+        r#"<p class='text-sm text-green-700 mb-2'>两步验证已启用。请保存以下恢复码，每个只能使用一次，之后不会再显示：</p>
+        <ul class='grid grid-cols-2 gap-1 text-sm bg-gray-50 rounded px-3 py-2 mb-4 recovery-codes'>{}</ul>
+        <button hx-get='/settings/two-factor' hx-target='#two-factor' class='px-3 py-1 text-sm bg-gray-100 rounded-lg hover:bg-gray-200'>已保存</button>"#,
+        items
+    )
+}
@@ -0,0 +1,186 @@
+//! Warnings shown above every page
+//!
+//! The server loads them into the status banner placeholder
+//! ([`crate::layout::STATUS_BANNER`]); each is empty when there is nothing
+//! to report.
+
+use crate::html_escape;
+
+/// The ledger failed to load; `loaded` tells whether older data is still
+/// shown, `retry_attempts` is set while the server keeps retrying
+pub fn load_error(error: &str, loaded: bool, retry_attempts: Option<u32>) -> String {
+    let detail = if loaded {
+        "显示的是上一次成功加载的数据"
+    } else {
+        "当前没有已加载的数据，所有页面显示为空"
+    };
+    let retrying = retry_attempts
+        .map(|attempts| format!("（自动重试中，已尝试 {} 次）", attempts))
+        .unwrap_or_default();
+    format!(
+        r#"<div class='mb-4 p-4 bg-red-50 border border-red-300 rounded-xl text-red-800'>
+            <div class='flex items-center justify-between gap-4'>
+                <div>
+                    <p class='font-semibold'>⚠️ 账本加载失败{}</p>
+                    <p class='text-sm'>{}</p>
+                </div>
+                <button onclick="fetch('/api/reload', {{method: 'POST'}}).then(() => window.location.reload())"
+                    class='px-3 py-1 text-sm bg-red-600 text-white rounded hover:bg-red-700 flex-shrink-0'>重新加载</button>
+            </div>
+            <pre class='mt-2 text-xs whitespace-pre-wrap bg-white border border-red-200 rounded p-2'>{}</pre>
+        </div>"#,
+        retrying,
+        detail,
+        html_escape(error)
+    )
+}
+
+/// Asset accounts below zero, with the transactions that took them there
+pub fn negative_balances(negative: &[beanweb_core::NegativeBalance]) -> String {
+    if negative.is_empty() {
+        return String::new();
+    }
+    let rows: Vec<String> = negative.iter()
+        .map(|n| {
+            let since = n.since.as_deref().map(|d| format!("，自 {} 起为负", d)).unwrap_or_default();
+            let causes: String = n.causes.iter()
+                .map(|c| format!(
+                    "<li><a href='/transactions/{}/edit' class='hover:underline'>{} {} {}</a> <span class='font-mono'>{:.2}</span></li>",
+                    urlencoding::encode(&c.transaction_id), c.date, html_escape(&c.payee), html_escape(&c.narration), c.amount
+                ))
+                .collect();
+            format!(
+                "<li class='py-1'><a href='/accounts/{}' class='font-mono hover:underline'>{}</a> 余额 {:.2} {}{}<ul class='ml-4 text-xs text-red-700'>{}</ul></li>",
+                urlencoding::encode(&n.account), html_escape(&n.account), n.balance, html_escape(&n.currency), since, causes
+            )
+        })
+        .collect();
+    format!(
+        r#"<details class='mb-4 p-4 bg-red-50 border border-red-300 rounded-xl text-red-800'>
+            <summary class='font-semibold cursor-pointer'>⚠️ {} 个资产账户余额为负，可能是金额符号录入错误</summary>
+            <ul class='mt-2 text-sm'>{}</ul>
+        </details>"#,
+        negative.len(),
+        rows.join("")
+    )
+}
+
+/// Every problem the parser found, grouped by file, each with line, column
+/// and the offending text; files link to the editor
+pub fn parse_errors(errors: &[beanweb_core::DirectiveError], data_dir: &std::path::Path) -> String {
+    if errors.is_empty() {
+        return String::new();
+    }
+    let mut files: Vec<(&str, Vec<&beanweb_core::DirectiveError>)> = Vec::new();
+    for error in errors {
+        let source = error.source.as_deref().unwrap_or("");
+        match files.iter_mut().find(|(s, _)| *s == source) {
+            Some((_, list)) => list.push(error),
+            None => files.push((source, vec![error])),
+        }
+    }
+    let sections: String = files.iter()
+        .map(|(source, list)| {
+            let file = std::path::Path::new(source).strip_prefix(data_dir).unwrap_or(std::path::Path::new(source)).display().to_string();
+            let rows: String = list.iter()
+                .map(|e| {
+                    let badge = match e.severity {
+                        beanweb_core::Severity::Error => "<span class='px-1 rounded bg-red-100 text-red-700'>错误</span>",
+                        beanweb_core::Severity::Warning => "<span class='px-1 rounded bg-amber-100 text-amber-700'>警告</span>",
+                    };
+                    format!(
+                        "<li class='py-1'>{} <span class='font-mono text-xs'>{}:{}</span> {}<pre class='mt-1 text-xs whitespace-pre-wrap bg-white border border-amber-200 rounded p-1'>{}</pre></li>",
+                        badge, e.line, e.column, html_escape(&e.message), html_escape(&e.text)
+                    )
+                })
+                .collect();
+            format!(
+                "<li class='mt-2'><a href='/files/{}' class='font-mono font-semibold hover:underline'>{}</a><ul class='ml-4'>{}</ul></li>",
+                urlencoding::encode(&file), html_escape(&file), rows
+            )
+        })
+        .collect();
+    let count = |severity: beanweb_core::Severity| errors.iter().filter(|e| e.severity == severity).count();
+    let summary = match (count(beanweb_core::Severity::Error), count(beanweb_core::Severity::Warning)) {
+        (0, warnings) => format!("{} 条警告", warnings),
+        (errors, 0) => format!("{} 条指令格式错误，已跳过，其余内容正常加载", errors),
+        (errors, warnings) => format!("{} 条指令格式错误（已跳过）和 {} 条警告", errors, warnings),
+    };
+    format!(
+        r#"<details class='mb-4 p-4 bg-amber-50 border border-amber-300 rounded-xl text-amber-800'>
+            <summary class='font-semibold cursor-pointer'>⚠️ {}</summary>
+            <ul class='mt-2 text-sm max-h-96 overflow-y-auto'>{}</ul>
+            <a href='/api/errors' target='_blank' class='text-xs underline'>JSON</a>
+        </details>"#,
+        summary, sections
+    )
+}
+
+/// Failed balance assertions, listed with file and line
+pub fn balance_failures(failed: &[beanweb_core::BalanceCheck], data_dir: &std::path::Path) -> String {
+    if failed.is_empty() {
+        return String::new();
+    }
+    const SHOWN: usize = 10;
+    let mut rows: Vec<String> = failed.iter()
+        .take(SHOWN)
+        .map(|c| {
+            let file = c.source.as_deref()
+                .map(|source| std::path::Path::new(source).strip_prefix(data_dir).unwrap_or(std::path::Path::new(source)).display().to_string())
+                .unwrap_or_default();
+            format!(
+                "<li class='py-1'><span class='font-mono text-xs text-red-600'>{}:{}</span> {} <span class='font-mono'>{}</span> 断言 {:.2} {currency}，实际 {:.2} {currency}（差额 {:.2}）</li>",
+                html_escape(&file), c.line.unwrap_or(0), c.date, html_escape(&c.account), c.expected, c.computed, c.difference,
+                currency = html_escape(&c.currency)
+            )
+        })
+        .collect();
+    if failed.len() > SHOWN {
+        rows.push(format!("<li class='py-1'>…… 另有 {} 条</li>", failed.len() - SHOWN));
+    }
+    format!(
+        r#"<details class='mb-4 p-4 bg-red-50 border border-red-300 rounded-xl text-red-800'>
+            <summary class='font-semibold cursor-pointer'>⚠️ {} 条余额断言失败</summary>
+            <ul class='mt-2 text-sm'>{}</ul>
+            <a href='/api/checks/balances?failed=true' target='_blank' class='text-xs underline'>全部详情</a>
+        </details>"#,
+        failed.len(),
+        rows.join("")
+    )
+}
+
+/// Integrity check badge: the issue count, what the last check added and a
+/// button to acknowledge them
+pub fn check_badge(issues: usize, added: &[beanweb_core::IntegrityIssue], resolved: usize) -> String {
+    let rows: String = added.iter()
+        .take(10)
+        .map(|issue| format!(
+            "<li class='py-1 border-b last:border-0'><span class='text-gray-400'>{}</span> <span class='font-mono'>{}</span><br>{}</li>",
+            issue.date, html_escape(&issue.account), html_escape(&issue.message)
+        ))
+        .collect();
+    let (title, class) = if issues == 0 {
+        ("✓ 完整性问题已全部修复".to_string(), "bg-green-50 text-green-700 border-green-200")
+    } else {
+        (format!("⚠️ 完整性检查：{} 个问题", issues), "bg-red-50 text-red-700 border-red-200")
+    };
+    let summary = match (added.len(), resolved) {
+        (0, 0) => String::new(),
+        (added, resolved) => format!("<p class='text-xs mt-1'>新增 {} 个，修复 {} 个</p>", added, resolved),
+    };
+    format!(
+        r#"<details class='m-2 p-2 border rounded-lg text-sm {class}'>
+            <summary class='cursor-pointer font-medium'>{title}</summary>
+            {summary}
+            <ul class='mt-2 max-h-64 overflow-auto text-xs'>{rows}</ul>
+            <div class='mt-2 flex gap-2 text-xs'>
+                <a href='/api/check/latest' target='_blank' class='underline'>全部详情</a>
+                <button hx-post='/check/seen' hx-target='#check-badge' class='ml-auto underline'>知道了</button>
+            </div>
+        </details>"#,
+        class = class,
+        title = title,
+        summary = summary,
+        rows = rows,
+    )
+}
@@ -0,0 +1,89 @@
+//! Budgets page: spent against budget per account
+
+use crate::html_escape;
+use beanweb_core::budget::{BudgetItem, BudgetPeriod, BudgetReport};
+
+/// Budgets page; the table loads once shown and again with the time range
+pub fn page(time_selector: &str) -> String {
+    format!(
+        r#"<div class='mb-6'>
+            <h2 class='text-2xl font-bold'>预算</h2>
+            <p class='text-gray-500 mt-1'>按 <code>custom "budget"</code> 指令统计所选时间段的预算执行情况</p>
+        </div>
+        {}
+        <div id='budgets-content' hx-get='/budgets/list' hx-trigger='load, time-range-changed from:body, ledger-reloaded from:body' class='bg-white rounded-xl shadow-sm p-6'>
+            <p class='text-gray-500 text-center'>加载中...</p>
+        </div>"#,
+        time_selector
+    )
+}
+
+/// In place of the table when budgets are turned off
+pub fn disabled() -> String {
+    r#"<div class='text-center py-12 text-gray-500'><p>预算功能未启用</p><p class='text-sm mt-1'>在配置中设置 features.budget_enable: true</p></div>"#.to_string()
+}
+
+/// Budget table of the time range, or how to add a budget when there is none
+pub fn list(report: &BudgetReport) -> String {
+    if report.items.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>暂无预算</p><p class='text-sm mt-1 font-mono'>2024-01-01 custom "budget" Expenses:Food "monthly" 1500.00 CNY</p></div>"#.to_string();
+    }
+    format!(
+        "<p class='text-sm text-gray-500 mb-4'>{} 至 {}</p><div class='space-y-4'>{}</div>",
+        report.start,
+        report.end,
+        report.items.iter().map(row).collect::<String>()
+    )
+}
+
+fn period_label(period: BudgetPeriod) -> &'static str {
+    match period {
+        BudgetPeriod::Weekly => "每周",
+        BudgetPeriod::Monthly => "每月",
+        BudgetPeriod::Quarterly => "每季度",
+        BudgetPeriod::Yearly => "每年",
+    }
+}
+
+/// One account: name, amounts and a progress bar
+fn row(item: &BudgetItem) -> String {
+    let (bar, text) = if item.percent > 100.0 {
+        ("bg-red-500", "text-red-600")
+    } else if item.percent >= 80.0 {
+        ("bg-amber-500", "text-amber-600")
+    } else {
+        ("bg-green-500", "text-green-600")
+    };
+    // Budget per period, and whether the range only covers part of one
+    let period = format!("{} {:.2}", period_label(item.period), item.period_amount);
+    let prorated = if item.prorated {
+        " <span class='text-xs px-1.5 py-0.5 bg-gray-100 text-gray-500 rounded' title='所选时间段与预算周期不一致，按天折算'>按天折算</span>"
+    } else {
+        ""
+    };
+    let remaining = if item.remaining < 0.0 {
+        format!("超支 {:.2}", -item.remaining)
+    } else {
+        format!("剩余 {:.2}", item.remaining)
+    };
+    format!(
+        r#"<div>
+            <div class='flex items-center justify-between mb-1'>
+                <div><a href='/accounts/{}' class='font-medium hover:text-indigo-600'>{}</a> <span class='text-xs text-gray-400'>{}</span>{}</div>
+                <div class='text-sm'><span class='font-medium'>{:.2}</span> / {:.2} {} <span class='{} ml-2'>{}</span></div>
+            </div>
+            <div class='w-full bg-gray-100 rounded-full h-2.5'><div class='{} h-2.5 rounded-full' style='width: {:.1}%'></div></div>
+        </div>"#,
+        urlencoding::encode(&item.account),
+        html_escape(&item.account),
+        period,
+        prorated,
+        item.spent,
+        item.budget,
+        html_escape(&item.currency),
+        text,
+        remaining,
+        bar,
+        item.percent.clamp(0.0, 100.0),
+    )
+}
@@ -0,0 +1,97 @@
+//! Commodities page: totals per currency or commodity across assets and liabilities
+
+use crate::html_escape;
+use beanweb_core::holdings::CommodityTotal;
+
+/// Commodities page, values in `report_currency`
+pub fn page(totals: &[CommodityTotal], report_currency: &str) -> String {
+    format!(
+        r#"<div class='mb-6'>
+            <h2 class='text-2xl font-bold'>货币/商品</h2>
+            <p class='text-gray-500 mt-1'>各类货币/商品在资产与负债账户中的总额（按 {} 估值）</p>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6'>
+            {}
+        </div>"#,
+        html_escape(report_currency),
+        table(totals)
+    )
+}
+
+/// Render commodity table, one expandable account breakdown per row
+fn table(totals: &[CommodityTotal]) -> String {
+    if totals.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>暂无货币/商品数据</p></div>"#.to_string();
+    }
+
+    let mut html = String::from(
+        r#"<div class='overflow-x-auto'>
+        <table class='w-full'>
+            <thead class='bg-gray-50'>
+                <tr>
+                    <th class='px-4 py-3 text-left text-sm font-medium text-gray-600'>货币/商品</th>
+                    <th class='px-4 py-3 text-right text-sm font-medium text-gray-600'>数量</th>
+                    <th class='px-4 py-3 text-right text-sm font-medium text-gray-600'>单价</th>
+                    <th class='px-4 py-3 text-right text-sm font-medium text-gray-600'>总价值</th>
+                    <th class='px-4 py-3 text-left text-sm font-medium text-gray-600'>账户</th>
+                </tr>
+            </thead>
+            <tbody class='divide-y divide-gray-100'>"#
+    );
+
+    for total in totals {
+        let price_display = total.price
+            .map(|p| format!("{:.2}", p))
+            .unwrap_or_else(|| String::from("-"));
+        let value_display = total.value
+            .filter(|v| *v != 0.0)
+            .map(|v| format!("{:.2}", v))
+            .unwrap_or_else(|| String::from("-"));
+
+        let mut name_display = if total.label != total.commodity {
+            format!("{} <span class='text-xs text-gray-400'>{}</span>", html_escape(&total.label), html_escape(&total.commodity))
+        } else {
+            html_escape(&total.commodity)
+        };
+        if let Some(asset_class) = &total.asset_class {
+            name_display.push_str(&format!(" <span class='ml-1 px-1.5 py-0.5 rounded bg-indigo-50 text-indigo-600 text-xs'>{}</span>", html_escape(asset_class)));
+        }
+        if !total.declared {
+            name_display.push_str(" <span class='ml-1 px-1.5 py-0.5 rounded bg-amber-50 text-amber-700 text-xs' title='没有 commodity 声明'>未声明</span>");
+        }
+
+        let accounts_display = if total.accounts.is_empty() {
+            String::from("<span class='text-gray-400'>-</span>")
+        } else {
+            let rows: String = total.accounts.iter()
+                .map(|(account, units)| format!(
+                    "<div class='flex justify-between gap-4'><a href='/accounts/{}' class='hover:text-indigo-600'>{}</a><span>{:.2}</span></div>",
+                    urlencoding::encode(account), html_escape(account), units
+                ))
+                .collect();
+            format!(
+                "<details><summary class='cursor-pointer text-sm text-gray-600'>{} 个账户</summary><div class='mt-2 space-y-1 text-sm'>{}</div></details>",
+                total.accounts.len(), rows
+            )
+        };
+
+        html.push_str(&format!(
+            r#"<tr class='hover:bg-gray-50 align-top'>
+                <td class='px-4 py-3 font-medium'>{}</td>
+                <td class='px-4 py-3 text-right {}'>{:.2}</td>
+                <td class='px-4 py-3 text-right text-gray-500'>{}</td>
+                <td class='px-4 py-3 text-right font-medium'>{}</td>
+                <td class='px-4 py-3'>{}</td>
+            </tr>"#,
+            name_display,
+            if total.units < 0.0 { "text-red-600" } else { "" },
+            total.units,
+            price_display,
+            value_display,
+            accounts_display
+        ));
+    }
+
+    html.push_str("</tbody></table></div>");
+    html
+}
@@ -0,0 +1,202 @@
+//! Dashboard: headline totals and cards
+//!
+//! [`page`] lays out the whole dashboard; the ranked cards can also be
+//! rendered on their own ([`top_card`]) when the user expands or collapses
+//! them.
+
+use crate::html_escape;
+use beanweb_core::stale::StaleBalance;
+use beanweb_core::{BalanceReport, IncomeExpenseReport, RecurringInterval, RecurringPayment, SpendingVelocity, TransactionStats};
+
+/// Everything shown on the dashboard
+#[derive(Debug)]
+pub struct Dashboard {
+    pub balance_report: BalanceReport,
+    pub income_expense: IncomeExpenseReport,
+    pub stats: TransactionStats,
+    pub top_assets: Vec<TopRow>,
+    pub top_expenses: Vec<TopRow>,
+    /// Rows shown on a collapsed ranked card
+    pub top_n: usize,
+    pub velocity: SpendingVelocity,
+    pub recurring: Vec<RecurringPayment>,
+    pub stale_balances: Vec<StaleBalance>,
+}
+
+/// One row of a ranked card
+#[derive(Debug, Clone)]
+pub struct TopRow {
+    pub label: String,
+    pub amount: f64,
+    /// Currency, or share of the expenses
+    pub detail: String,
+}
+
+/// Dashboard content (without the page shell)
+pub fn page(dashboard: &Dashboard) -> String {
+    let net_income_value: f64 = dashboard.income_expense.net_income.parse().unwrap_or(0.0);
+
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>仪表盘</h2></div>
+        <div class='grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-4 mb-6'>
+            <div class='bg-green-50 p-4 rounded-lg border border-green-200'><p class='text-sm text-green-600'>总资产</p><p class='text-2xl font-bold text-green-700'>{}</p></div>
+            <div class='bg-red-50 p-4 rounded-lg border border-red-200'><p class='text-sm text-red-600'>总负债</p><p class='text-2xl font-bold text-red-700'>{}</p></div>
+            <div class='bg-blue-50 p-4 rounded-lg border border-blue-200'><p class='text-sm text-blue-600'>总收入</p><p class='text-2xl font-bold text-blue-700'>{}</p></div>
+            <div class='bg-yellow-50 p-4 rounded-lg border border-yellow-200'><p class='text-sm text-yellow-600'>总支出</p><p class='text-2xl font-bold text-yellow-700'>{}</p></div>
+        </div>
+        <div class='grid grid-cols-1 lg:grid-cols-2 gap-6'>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>资产排名</h3>
+                <div id='top-assets'>{}</div>
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>支出分类排名</h3>
+                <div id='top-expenses'>{}</div>
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>本月统计</h3>
+                <div class='grid grid-cols-2 gap-4'>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>交易数</p><p class='text-xl font-bold'>{}</p></div>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>条目数</p><p class='text-xl font-bold'>{}</p></div>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>净资产</p><p class='text-xl font-bold text-indigo-600'>{}</p></div>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>收支结余</p><p class='text-xl font-bold {}'>{}</p></div>
+                </div>
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>支出速度</h3>
+                {}
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>订阅/周期支出</h3>
+                {}
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'>
+                <h3 class='text-lg font-semibold mb-4'>待对账账户</h3>
+                {}
+            </div>
+        </div>"#,
+        dashboard.balance_report.total_assets,
+        dashboard.balance_report.total_liabilities,
+        dashboard.income_expense.total_income,
+        dashboard.income_expense.total_expenses,
+        top_card(TopCard::Assets, &dashboard.top_assets, dashboard.top_n, false),
+        top_card(TopCard::Expenses, &dashboard.top_expenses, dashboard.top_n, false),
+        dashboard.stats.total_transactions,
+        dashboard.stats.total_postings,
+        dashboard.balance_report.net_worth,
+        if net_income_value < 0.0 { "text-red-600" } else { "text-green-600" },
+        dashboard.income_expense.net_income,
+        velocity(&dashboard.velocity),
+        recurring(&dashboard.recurring, dashboard.top_n),
+        stale_balances(&dashboard.stale_balances)
+    )
+}
+
+/// Dashboard card body: daily spend this month against last month, and where
+/// the month ends at that pace
+pub fn velocity(velocity: &SpendingVelocity) -> String {
+    let change = match velocity.change_percent {
+        Some(change) if change > 0.0 => format!("<span class='text-red-600'>↑ {:.1}%</span>", change),
+        Some(change) if change < 0.0 => format!("<span class='text-green-600'>↓ {:.1}%</span>", -change),
+        Some(_) => "<span class='text-gray-500'>持平</span>".to_string(),
+        None => "<span class='text-gray-400'>上月无支出</span>".to_string(),
+    };
+    format!(
+        r#"<div class='grid grid-cols-2 gap-4'>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>日均支出</p><p class='text-xl font-bold'>{} {}</p><p class='text-xs mt-1'>较上月日均 {} {}</p></div>
+                    <div class='text-center p-4 bg-gray-50 rounded-lg'><p class='text-sm text-gray-600'>预计月末支出</p><p class='text-xl font-bold text-amber-600'>{} {}</p><p class='text-xs text-gray-400 mt-1'>已支出 {}，第 {} / {} 天</p></div>
+                </div>"#,
+        velocity.daily_average,
+        html_escape(&velocity.currency),
+        velocity.last_month_daily_average,
+        change,
+        velocity.projected,
+        html_escape(&velocity.currency),
+        velocity.spent,
+        velocity.days_elapsed,
+        velocity.days_in_month,
+    )
+}
+
+/// Dashboard card body: the next `limit` recurring payments due
+pub fn recurring(payments: &[RecurringPayment], limit: usize) -> String {
+    if payments.is_empty() {
+        return "<p class='text-sm text-gray-500'>暂未发现周期性支出</p>".to_string();
+    }
+    let rows: String = payments.iter().take(limit)
+        .map(|p| {
+            let interval = match p.interval {
+                RecurringInterval::Weekly => "每周",
+                RecurringInterval::Monthly => "每月",
+                RecurringInterval::Quarterly => "每季度",
+                RecurringInterval::Yearly => "每年",
+            };
+            format!(
+                "<li class='flex items-center justify-between py-2 border-b last:border-0'><span><span class='font-medium'>{}</span><span class='ml-2 text-xs text-gray-400'>{} · {}</span></span><span class='text-right'><span class='text-sm font-medium'>{} {}</span><span class='block text-xs text-gray-500'>下次 {}</span></span></li>",
+                html_escape(&p.payee), interval, html_escape(&p.account), p.amount, html_escape(&p.currency), p.next_date
+            )
+        })
+        .collect();
+    format!("<ul>{}</ul>", rows)
+}
+
+/// Dashboard card body: accounts whose latest balance assertion is too old
+pub fn stale_balances(stale: &[StaleBalance]) -> String {
+    if stale.is_empty() {
+        return "<p class='text-sm text-gray-500'>所有账户近期都已对账</p>".to_string();
+    }
+    let rows: String = stale.iter()
+        .map(|s| format!(
+            "<li class='flex items-center justify-between py-2 border-b last:border-0'><a href='/accounts/{}' class='font-mono text-sm hover:text-indigo-600'>{}</a><span class='text-sm text-amber-600'>{} 天前（{}）</span></li>",
+            urlencoding::encode(&s.account), html_escape(&s.account), s.days, s.last_assertion
+        ))
+        .collect();
+    format!("<ul>{}</ul>", rows)
+}
+
+/// Ranked list shown on a dashboard card
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopCard {
+    /// Asset accounts by balance
+    Assets,
+    /// Expense categories by amount in the current period
+    Expenses,
+}
+
+/// Body of a ranked dashboard card: the first `top_n` rows, or all of them when
+/// `expanded`, plus a toggle that swaps in the other variant
+pub fn top_card(card: TopCard, rows: &[TopRow], top_n: usize, expanded: bool) -> String {
+    let mut rows: Vec<&TopRow> = rows.iter().collect();
+    rows.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap_or(std::cmp::Ordering::Equal));
+    if rows.is_empty() {
+        return "<p class='text-sm text-gray-500'>暂无数据</p>".to_string();
+    }
+
+    let total = rows.len();
+    let shown = if expanded { total } else { top_n.min(total) };
+    let mut html = String::from("<div class='space-y-1'>");
+    for (rank, row) in rows.into_iter().take(shown).enumerate() {
+        html.push_str(&format!(
+            "<div class='flex justify-between gap-2 py-2 border-b'><span class='w-6 text-gray-400'>{}</span><span class='flex-1 truncate'>{}</span><span class='text-xs text-gray-400'>{}</span><span class='font-medium'>{:.2}</span></div>",
+            rank + 1, row.label, row.detail, row.amount
+        ));
+    }
+    html.push_str("</div>");
+
+    let (path, target) = match card {
+        TopCard::Assets => ("/dashboard/top/assets", "#top-assets"),
+        TopCard::Expenses => ("/dashboard/top/expenses", "#top-expenses"),
+    };
+    if expanded && total > top_n {
+        html.push_str(&format!(
+            "<button hx-get='{}' hx-target='{}' class='mt-3 text-sm text-indigo-600 hover:underline'>收起</button>",
+            path, target
+        ));
+    } else if !expanded && total > shown {
+        html.push_str(&format!(
+            "<button hx-get='{}?all=1' hx-target='{}' class='mt-3 text-sm text-indigo-600 hover:underline'>展开全部 {} 项</button>",
+            path, target, total
+        ));
+    }
+    html
+}
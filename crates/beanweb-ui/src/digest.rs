@@ -0,0 +1,179 @@
+//! Period digest: a standalone HTML document for email or archiving
+//!
+//! Inline styles only: no htmx, no Tailwind, no external assets.
+
+use crate::html_escape;
+use beanweb_core::{CategoryReport, IncomeExpenseReport};
+use chrono::NaiveDate;
+
+/// Everything one digest shows
+pub struct Digest<'a> {
+    pub title: &'a str,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub report: &'a IncomeExpenseReport,
+    /// The period before, for the change next to income and expenses
+    pub previous: &'a IncomeExpenseReport,
+    pub categories: &'a CategoryReport,
+    /// Largest expense transactions, largest first
+    pub largest: &'a [LargestExpense],
+    /// Rows per section
+    pub top_n: usize,
+    /// When the digest was made, e.g. "2024-07-01 08:00"
+    pub generated: String,
+}
+
+/// A transaction with what it booked to expense accounts
+pub struct LargestExpense {
+    pub date: String,
+    /// Payee and narration
+    pub title: String,
+    pub total: f64,
+    pub currency: String,
+}
+
+/// "-1,234.50 CNY"; thousands separators on the integer part only
+fn amount(value: f64, currency: &str) -> String {
+    let text = format!("{:.2}", value.abs());
+    let (int, frac) = text.split_once('.').unwrap_or((&text, "00"));
+    let sign = if value <= -0.005 { "-" } else { "" };
+    format!("{}{}.{} {}", sign, beanweb_utils::format_number(int), frac, currency)
+}
+
+/// "+12.3%" change against the previous period, empty when there is nothing to compare
+fn change(current: f64, previous: f64) -> String {
+    if previous.abs() < 0.005 {
+        return String::new();
+    }
+    let pct = (current - previous) / previous.abs() * 100.0;
+    format!("<div style=\"font-size:12px;color:#6b7280\">较上期 {:+.1}%</div>", pct)
+}
+
+/// The digest document
+pub fn document(digest: &Digest) -> String {
+    let (report, previous_report, top_n) = (digest.report, digest.previous, digest.top_n);
+    let currency = &report.currency;
+
+    let value = |s: &str| s.parse::<f64>().unwrap_or(0.0);
+    let (income, expenses, net) = (value(&report.total_income), value(&report.total_expenses), value(&report.net_income));
+    let savings_rate = if income > 0.0 { format!("{:.1}%", net / income * 100.0) } else { "-".to_string() };
+
+    let card = |label: &str, text: String, color: &str, extra: String| {
+        format!(
+            "<td style=\"padding:12px;background:#f9fafb;border-radius:8px;width:25%\"><div style=\"font-size:12px;color:#6b7280\">{}</div><div style=\"font-size:18px;font-weight:bold;color:{}\">{}</div>{}</td>",
+            label, color, text, extra
+        )
+    };
+    let summary = format!(
+        "<table role=\"presentation\" width=\"100%\" cellspacing=\"8\" style=\"margin:0 -8px\"><tr>{}{}{}{}</tr></table>",
+        card("收入", amount(income, currency), "#047857", change(income, value(&previous_report.total_income))),
+        card("支出", amount(expenses, currency), "#b91c1c", change(expenses, value(&previous_report.total_expenses))),
+        card("结余", amount(net, currency), if net < 0.0 { "#b91c1c" } else { "#4338ca" }, String::new()),
+        card("储蓄率", savings_rate, "#111827", String::new()),
+    );
+
+    // Spending by category with inline bars
+    let mut category_rows = String::new();
+    for breakdown in digest.categories.breakdowns.iter().take(top_n) {
+        category_rows.push_str(&format!(
+            "<tr><td style=\"padding:6px 0\">{}</td><td style=\"padding:6px 8px;width:40%\"><div style=\"background:#fee2e2;border-radius:4px\"><div style=\"background:#ef4444;height:8px;border-radius:4px;width:{:.0}%\"></div></div></td><td style=\"padding:6px 0;text-align:right;white-space:nowrap\">{}</td><td style=\"padding:6px 0 6px 8px;text-align:right;color:#6b7280;font-size:12px\">{:.1}%</td></tr>",
+            html_escape(&breakdown.category),
+            breakdown.percentage.clamp(0.0, 100.0),
+            amount(value(&breakdown.amount), currency),
+            breakdown.percentage
+        ));
+    }
+
+    let body = format!(
+        "{summary}{categories}{income}{largest}{transfers}",
+        summary = summary,
+        categories = section("支出分类", &category_rows),
+        income = section("收入明细", &entry_rows(report, top_n)),
+        largest = section("最大支出", &largest_expense_rows(digest.largest, top_n)),
+        transfers = transfer_note(report),
+    );
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>账本摘要 · {title}</title>
+</head>
+<body style="margin:0;padding:24px;background:#f3f4f6;font-family:-apple-system,'PingFang SC','Microsoft YaHei',Helvetica,Arial,sans-serif;color:#111827;font-size:14px">
+<div style="max-width:640px;margin:0 auto;background:#ffffff;border-radius:12px;padding:24px">
+<h1 style="margin:0 0 4px;font-size:20px;color:#4f46e5">账本摘要 · {title}</h1>
+<p style="margin:0 0 16px;color:#6b7280;font-size:12px">{start} 至 {end}</p>
+{body}
+<p style="margin:24px 0 0;color:#9ca3af;font-size:11px">由 Beanweb 生成于 {generated}</p>
+</div>
+</body>
+</html>"#,
+        title = html_escape(digest.title),
+        start = digest.start,
+        end = digest.end,
+        body = body,
+        generated = digest.generated,
+    )
+}
+
+/// A titled table; empty sections say so instead of disappearing
+fn section(title: &str, rows: &str) -> String {
+    let rows = if rows.is_empty() {
+        "<tr><td style=\"padding:6px 0;color:#9ca3af\">本期无记录</td></tr>".to_string()
+    } else {
+        rows.to_string()
+    };
+    format!(
+        "<h2 style=\"margin:24px 0 8px;font-size:16px\">{}</h2><table role=\"presentation\" width=\"100%\" cellspacing=\"0\" style=\"border-collapse:collapse\">{}</table>",
+        title, rows
+    )
+}
+
+/// Income entries, largest first
+fn entry_rows(report: &IncomeExpenseReport, top_n: usize) -> String {
+    let mut entries: Vec<_> = report.income_entries.iter()
+        .filter(|e| e.amount.parse::<f64>().unwrap_or(0.0).abs() >= 0.005)
+        .collect();
+    entries.sort_by(|a, b| {
+        let (a, b) = (a.amount.parse::<f64>().unwrap_or(0.0), b.amount.parse::<f64>().unwrap_or(0.0));
+        b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    entries.iter()
+        .take(top_n)
+        .map(|entry| {
+            let currency = if entry.unconverted { &entry.currency } else { &report.currency };
+            format!(
+                "<tr><td style=\"padding:6px 0;border-bottom:1px solid #f3f4f6\">{}</td><td style=\"padding:6px 0;border-bottom:1px solid #f3f4f6;text-align:right;color:#047857\">{}</td></tr>",
+                html_escape(&entry.account),
+                amount(entry.amount.parse().unwrap_or(0.0), currency)
+            )
+        })
+        .collect()
+}
+
+/// Rows of the largest expenses, already sorted largest first
+fn largest_expense_rows(expenses: &[LargestExpense], top_n: usize) -> String {
+    expenses.iter()
+        .take(top_n)
+        .map(|expense| {
+            format!(
+                "<tr><td style=\"padding:6px 0;border-bottom:1px solid #f3f4f6;color:#6b7280;width:90px\">{}</td><td style=\"padding:6px 0;border-bottom:1px solid #f3f4f6\">{}</td><td style=\"padding:6px 0;border-bottom:1px solid #f3f4f6;text-align:right;color:#b91c1c;white-space:nowrap\">{}</td></tr>",
+                expense.date, html_escape(&expense.title), amount(expense.total, &expense.currency)
+            )
+        })
+        .collect()
+}
+
+/// One-line note on internal transfers, which the totals exclude
+fn transfer_note(report: &IncomeExpenseReport) -> String {
+    if report.transfers.count == 0 {
+        return String::new();
+    }
+    let volume: f64 = report.transfers.volume.parse().unwrap_or(0.0);
+    format!(
+        "<p style=\"margin:16px 0 0;color:#6b7280;font-size:12px\">另有内部转账 {} 笔，合计 {}，未计入收支。</p>",
+        report.transfers.count, amount(volume, &report.currency)
+    )
+}
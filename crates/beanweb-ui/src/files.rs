@@ -0,0 +1,304 @@
+//! Files page: the file list, the editor and the orphaned files panel
+
+use crate::{attr_escape, html_escape};
+use beanweb_core::{FileParseStats, OrphanedFile};
+
+/// A file under the data directory, for [`files_table`]
+pub struct FileRow<'a> {
+    /// Path relative to the data directory
+    pub name: &'a str,
+    pub modified: &'a str,
+    /// Size for display
+    pub size: &'a str,
+    pub bytes: u64,
+    /// Parse statistics; None for files the main file doesn't reach
+    pub stats: Option<&'a FileParseStats>,
+}
+
+/// Files page content: search box, the orphaned files panel when
+/// `detect_orphans` is set, and the file table loaded over HTMX
+pub fn page(detect_orphans: bool) -> String {
+    let orphans = if detect_orphans {
+        "<div id='orphaned-files' hx-get='/includes/orphans' hx-trigger='load, ledger-reloaded from:body'></div>"
+    } else {
+        ""
+    };
+
+    format!(r#"<div class='mb-6 flex items-center justify-between'>
+            <div><h2 class='text-2xl font-bold'>文件管理</h2><p class='text-gray-500 mt-1'>数据目录: ./data</p></div>
+            <input type='text' id='file-search' placeholder='搜索文件...' hx-get='/api/files' hx-trigger='keyup changed delay:300ms' hx-target='#files-list' name='filter[name]' class='px-4 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-indigo-500 focus:border-transparent w-64'>
+        </div>
+        {}
+        <div id='files-list' hx-get='/api/files' hx-trigger='load, ledger-reloaded from:body' class='bg-white rounded-xl shadow-sm overflow-hidden'>
+            <p class='text-gray-500 text-center py-12'>加载中...</p>
+        </div>"#, orphans)
+}
+
+/// Files table with size warnings and parse statistics; files over
+/// `warning_kb` (0 for no limit) are flagged
+pub fn files_table(files: &[FileRow], warning_kb: u64) -> String {
+    if files.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'>没有找到 Beancount 文件</div>"#.to_string();
+    }
+
+    let warning_bytes = warning_kb * 1024;
+    let oversized: Vec<&FileRow> = files.iter()
+        .filter(|f| warning_bytes > 0 && f.bytes > warning_bytes)
+        .collect();
+
+    let mut html = String::new();
+    if !oversized.is_empty() {
+        let names: Vec<String> = oversized.iter().map(|f| html_escape(f.name)).collect();
+        html.push_str(&format!(
+            r#"<div class='m-4 p-4 bg-amber-50 border border-amber-200 rounded-lg text-sm text-amber-800'>⚠️ {} 个文件超过 {} KB：{}。建议按月拆分（例如 <code>2024/2024-01.bean</code>），并在主文件中使用 <code>include "2024/*.bean"</code>。</div>"#,
+            oversized.len(),
+            warning_kb,
+            names.join("、")
+        ));
+    }
+
+    html.push_str(r#"<table class='w-full'><thead><tr><th class='text-left p-4 bg-gray-50'>文件名</th><th class='text-left p-4 bg-gray-50'>修改时间</th><th class='text-left p-4 bg-gray-50'>大小</th><th class='text-right p-4 bg-gray-50'>指令数</th><th class='text-left p-4 bg-gray-50'>日期范围</th><th class='text-right p-4 bg-gray-50'>解析耗时</th><th class='text-left p-4 bg-gray-50'>操作</th></tr></thead><tbody>"#);
+
+    for file in files {
+        let size_badge = if warning_bytes > 0 && file.bytes > warning_bytes {
+            r#" <span class='text-xs bg-amber-100 text-amber-800 px-2 py-1 rounded'>过大</span>"#
+        } else {
+            ""
+        };
+
+        // Files not reached from the main file have no parse stats
+        let (count, range, duration) = match file.stats {
+            Some(s) => {
+                let range = match (s.first_date, s.last_date) {
+                    (Some(first), Some(last)) if first == last => first.to_string(),
+                    (Some(first), Some(last)) => format!("{} ~ {}", first, last),
+                    _ => "-".to_string(),
+                };
+                (s.directive_count.to_string(), range, format!("{:.1} ms", s.parse_ms))
+            }
+            None => ("-".to_string(), "-".to_string(), "-".to_string()),
+        };
+
+        html.push_str(&format!(
+            r#"<tr class='border-b hover:bg-gray-50'><td class='p-4'><a href='/files/{}' class='text-indigo-600 hover:text-indigo-800 hover:underline'>{}</a></td><td class='p-4 text-gray-500'>{}</td><td class='p-4 text-gray-500'>{}{}</td><td class='p-4 text-right text-gray-500'>{}</td><td class='p-4 text-gray-500'>{}</td><td class='p-4 text-right text-gray-500'>{}</td><td class='p-4'><a href='/files/{}' class='px-3 py-1 bg-indigo-100 text-indigo-700 rounded hover:bg-indigo-200'>编辑</a></td></tr>"#,
+            urlencoding::encode(file.name),
+            html_escape(file.name),
+            file.modified,
+            file.size,
+            size_badge,
+            count,
+            range,
+            duration,
+            urlencoding::encode(file.name)
+        ));
+    }
+
+    html.push_str("</tbody></table>");
+    html
+}
+
+/// Editor page content for `file_path` holding `content` at `version` (its
+/// `ETag`); saves with a PUT to `/api/files/*path`, which is refused once the
+/// file changed elsewhere
+pub fn editor(file_path: &str, content: &str, version: &str) -> String {
+    format!(
+        r#"<div class='mb-4 flex items-center justify-between'>
+            <div class='flex items-center gap-4'>
+                <a href='/files' class='text-gray-500 hover:text-gray-700'>← 返回</a>
+                <h2 class='text-xl font-bold'>编辑文件 - {}</h2>
+            </div>
+            <div class='flex items-center gap-2'>
+                <span id='save-status' class='text-sm text-gray-500'></span>
+                <button onclick="saveFile(this, '{}')" class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存</button>
+            </div>
+        </div>
+        <div id='save-message'></div>
+        <div class='bg-white rounded-xl shadow-sm overflow-hidden'>
+            <textarea id='file-content' class='w-full p-4 font-mono text-sm resize-none focus:outline-none' style='min-height: calc(100vh - 300px);'>{}</textarea>
+        </div>
+        <div class='mt-4 text-sm text-gray-500'>行数: {}  字符数: {}  Ctrl+S 保存</div>
+        <script>
+            let fileVersion = '{}';
+            function saveFile(btn, path) {{
+                const content = document.getElementById('file-content').value;
+                btn.disabled = true;
+                btn.textContent = '保存中...';
+                fetch('/api/files/' + path, {{
+                    method: 'PUT',
+                    headers: {{'Content-Type': 'text/plain', 'If-Match': fileVersion}},
+                    body: content
+                }}).then(r => {{
+                    if (!r.ok && r.status !== 409) throw new Error('Save failed');
+                    fileVersion = r.headers.get('ETag') || fileVersion;
+                    return r.text();
+                }}).then(data => {{
+                    document.getElementById('save-message').innerHTML = data;
+                    btn.disabled = false;
+                    btn.textContent = '保存';
+                }}).catch(e => {{
+                    document.getElementById('save-message').innerHTML = '<div class="bg-red-50 border border-red-200 rounded-lg p-4"><span class="text-red-600">✗</span><span class="font-medium text-red-800">保存失败</span></div>';
+                    btn.disabled = false;
+                    btn.textContent = '保存';
+                }});
+            }}
+            document.addEventListener('keydown', e => {{
+                if ((e.ctrlKey || e.metaKey) && e.key === 's') {{
+                    e.preventDefault();
+                    const btn = document.querySelector('button[onclick*="saveFile"]');
+                    if (btn) btn.click();
+                }}
+            }});
+        </script>"#,
+        html_escape(file_path),
+        urlencoding::encode(file_path),
+        html_escape(content),
+        content.lines().count(),
+        content.len(),
+        version
+    )
+}
+
+/// Editor for a file too large to load whole: one window of lines at a
+/// time is fetched from `/api/files/*path` with a `Range` header and saved
+/// back with the matching `Content-Range`, leaving the other lines untouched
+pub fn window_editor(file_path: &str, size: u64, limit_kb: u64) -> String {
+    format!(
+        r#"<div class='mb-4 flex items-center justify-between'>
+            <div class='flex items-center gap-4'>
+                <a href='/files' class='text-gray-500 hover:text-gray-700'>← 返回</a>
+                <h2 class='text-xl font-bold'>编辑文件 - {}</h2>
+            </div>
+            <div class='flex items-center gap-2'>
+                <span id='window-status' class='text-sm text-gray-500'></span>
+                <button id='window-prev' onclick='loadWindow(windowStart - 500)' class='px-3 py-2 border rounded-lg hover:bg-gray-50' disabled>上一段</button>
+                <button id='window-next' onclick='loadWindow(windowStart + windowCount)' class='px-3 py-2 border rounded-lg hover:bg-gray-50' disabled>下一段</button>
+                <button id='window-save' onclick='saveWindow(this)' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700' disabled>保存</button>
+            </div>
+        </div>
+        <div class='mb-4 p-4 bg-amber-50 border border-amber-200 rounded-lg text-sm text-amber-800'>
+            文件大小 {:.1} MB，超过 {} KB，每次加载并保存 500 行。也可以按月拆分后在主文件中 <code>include</code>。
+        </div>
+        <div id='save-message'></div>
+        <div class='bg-white rounded-xl shadow-sm overflow-hidden'>
+            <textarea id='file-content' class='w-full p-4 font-mono text-sm resize-none focus:outline-none' style='min-height: calc(100vh - 340px);'></textarea>
+        </div>
+        <div class='mt-4 text-sm text-gray-500'>Ctrl+S 保存当前段</div>
+        <script>
+            const windowPath = '{}';
+            const area = document.getElementById('file-content');
+            let windowStart = 0, windowCount = 0, windowTotal = 0, windowDirty = false, windowVersion = '';
+            area.addEventListener('input', () => {{ windowDirty = true; }});
+            function loadWindow(offset) {{
+                if (windowDirty && !confirm('当前段有未保存的修改，确定离开？')) return;
+                offset = Math.max(0, offset);
+                fetch('/api/files/' + windowPath, {{headers: {{'Range': 'lines=' + offset + '-' + (offset + 499)}}}})
+                    .then(r => {{
+                        const range = (r.headers.get('Content-Range') || '').match(/lines (?:(\d+)-(\d+)|\*)\/(\d+)/);
+                        windowTotal = range ? parseInt(range[3]) : 0;
+                        windowStart = range && range[1] ? parseInt(range[1]) : windowTotal;
+                        windowCount = range && range[2] ? parseInt(range[2]) - windowStart + 1 : 0;
+                        windowVersion = r.headers.get('ETag') || '';
+                        return r.text();
+                    }})
+                    .then(text => {{
+                        area.value = text;
+                        windowDirty = false;
+                        document.getElementById('window-status').textContent = windowCount
+                            ? '第 ' + (windowStart + 1) + '-' + (windowStart + windowCount) + ' 行 / 共 ' + windowTotal + ' 行'
+                            : '共 ' + windowTotal + ' 行';
+                        document.getElementById('window-prev').disabled = windowStart === 0;
+                        document.getElementById('window-next').disabled = windowStart + windowCount >= windowTotal;
+                        document.getElementById('window-save').disabled = windowCount === 0;
+                    }})
+                    .catch(() => {{ document.getElementById('window-status').textContent = '加载失败'; }});
+            }}
+            function saveWindow(btn) {{
+                btn.disabled = true;
+                btn.textContent = '保存中...';
+                fetch('/api/files/' + windowPath, {{
+                    method: 'PUT',
+                    headers: {{
+                        'Content-Type': 'text/plain',
+                        'Content-Range': 'lines ' + windowStart + '-' + (windowStart + windowCount - 1) + '/' + windowTotal,
+                        'If-Match': windowVersion
+                    }},
+                    body: area.value
+                }}).then(r => {{
+                    if (!r.ok && r.status !== 409) throw new Error('Save failed');
+                    return r.text().then(data => ({{saved: r.ok, data}}));
+                }}).then(({{saved, data}}) => {{
+                    document.getElementById('save-message').innerHTML = data;
+                    btn.textContent = '保存';
+                    // A conflict keeps the edits in the textarea
+                    if (!saved) {{ btn.disabled = false; return; }}
+                    windowDirty = false;
+                    loadWindow(windowStart);
+                }}).catch(() => {{
+                    document.getElementById('save-message').innerHTML = '<div class="bg-red-50 border border-red-200 rounded-lg p-4"><span class="text-red-600">✗</span><span class="font-medium text-red-800">保存失败</span></div>';
+                    btn.disabled = false;
+                    btn.textContent = '保存';
+                }});
+            }}
+            document.addEventListener('keydown', e => {{
+                if ((e.ctrlKey || e.metaKey) && e.key === 's') {{
+                    e.preventDefault();
+                    const btn = document.getElementById('window-save');
+                    if (!btn.disabled) btn.click();
+                }}
+            }});
+            loadWindow(0);
+        </script>"#,
+        html_escape(file_path),
+        size as f64 / 1024.0 / 1024.0,
+        limit_kb,
+        urlencoding::encode(file_path),
+    )
+}
+
+/// Result of a save: saved (and reloaded) or why not, with the validation
+/// warnings either way
+pub fn save_result(result: Result<(), &str>, warnings: &[String]) -> String {
+    let warning_html = if !warnings.is_empty() {
+        let warnings: Vec<String> = warnings.iter().map(|w| format!("<li class='ml-4'>{}</li>", html_escape(w))).collect();
+        format!(r#"<div class='bg-yellow-50 border border-yellow-200 rounded-lg p-4 mt-2'><div class='flex items-center gap-2'><span class='text-yellow-600'>⚠</span><span class='font-medium text-yellow-800'>警告</span></div><ul class='mt-2 text-sm text-yellow-700'>{}</ul></div>"#, warnings.join(""))
+    } else {
+        String::new()
+    };
+    match result {
+        Ok(()) => format!(r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>保存成功！账本已重新加载</span></div>{}</div>"#, warning_html),
+        Err(e) => format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>保存失败: {}</span></div>{}</div>"#, html_escape(e), warning_html),
+    }
+}
+
+/// Orphaned files with one include button each, plus one per suggested glob
+pub fn orphaned_files(main_file: &str, orphans: &[OrphanedFile], error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<div class='mt-2 text-red-600'>{}</div>", html_escape(e)))
+        .unwrap_or_default();
+    if orphans.is_empty() {
+        return error;
+    }
+    let button = |include: &str, label: &str| format!(
+        "<button hx-post='/includes' hx-target='#orphaned-files' name='include' value='{}' class='px-3 py-1 bg-amber-100 text-amber-800 rounded hover:bg-amber-200'>{}</button>",
+        attr_escape(include), html_escape(label)
+    );
+
+    let mut globs: Vec<&str> = orphans.iter().filter_map(|o| o.glob.as_deref()).collect();
+    globs.dedup();
+    let rows: String = orphans.iter()
+        .map(|o| format!(
+            "<li class='flex items-center justify-between gap-4 py-2'><a href='/files/{}' class='font-mono hover:underline'>{}</a>{}</li>",
+            urlencoding::encode(&o.path), html_escape(&o.path), button(&o.path, &format!("添加 include 到 {}", main_file))
+        ))
+        .collect();
+    let suggestions: String = globs.iter()
+        .map(|glob| format!(
+            "<div class='flex items-center justify-between gap-4 pt-2'><span>或用 <code>include \"{}\"</code> 一次包含整个目录，以后新增的文件也会自动包含</span>{}</div>",
+            html_escape(glob), button(glob, "添加通配 include")
+        ))
+        .collect();
+    format!(
+        "<div class='mb-6 p-4 bg-amber-50 border border-amber-200 rounded-xl text-sm text-amber-800'><div class='font-medium'>⚠️ {} 个文件未被 {} 引用，其中的记录不会计入账本</div><ul class='mt-2 divide-y divide-amber-100'>{}</ul>{}{}</div>",
+        orphans.len(), html_escape(main_file), rows, suggestions, error
+    )
+}
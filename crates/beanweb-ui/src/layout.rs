@@ -0,0 +1,538 @@
+//! Page shell: HTML document, sidebar navigation and the time selector
+//!
+//! Full page loads get the document and sidebar around the content; HTMX
+//! requests get the content area only (see [`page`]).
+
+use beanweb_config::FeaturesConfig;
+
+/// Placeholder that loads the ledger load error banner on every page
+pub const STATUS_BANNER: &str = "<div id='status-banner' hx-get='/status/banner' hx-trigger='load, ledger-reloaded from:body'></div>";
+
+/// Sidebar heading; loads the ledger's `title` option, "Beanweb" without one
+pub const LEDGER_TITLE: &str = "<h1 class='text-xl font-bold text-indigo-600 truncate' hx-get='/status/title' hx-trigger='load, ledger-reloaded from:body'>Beanweb</h1>";
+
+/// Sidebar host for the integrity check badge; refreshed every 30 seconds
+pub const CHECK_BADGE: &str = "<div id='check-badge' hx-get='/check/badge' hx-trigger='load, every 30s, ledger-reloaded from:body'></div>";
+
+/// Sidebar privacy mode button; the label reflects the cookie and Alt+P toggles from anywhere
+pub const PRIVACY_TOGGLE: &str = r#"<div class='p-2 border-t'>
+    <button id='privacy-toggle' hx-post='/privacy/toggle' hx-swap='none' title='快捷键 Alt+P'
+        class='w-full flex items-center gap-2 px-3 py-2 rounded-lg text-sm text-gray-600 hover:bg-gray-50'>🙈<span>隐私模式</span><span class='privacy-state ml-auto text-xs text-gray-400'>关</span></button>
+</div>
+<script>
+(function () {
+    const on = document.cookie.split(';').some(c => c.trim() === 'beanweb_privacy=1');
+    const state = document.querySelector('#privacy-toggle .privacy-state');
+    if (state && on) { state.textContent = '开'; state.classList.add('text-indigo-600'); }
+    if (window.privacyShortcutBound) return;
+    window.privacyShortcutBound = true;
+    document.addEventListener('keydown', (e) => {
+        if (e.altKey && !e.ctrlKey && !e.metaKey && e.code === 'KeyP') {
+            e.preventDefault();
+            fetch('/privacy/toggle', {method: 'POST'}).then(() => window.location.reload());
+        }
+    });
+})();
+</script>"#;
+
+/// `inner_content` as a full page with the sidebar, or only the content
+/// area when `partial` (HTMX requests)
+pub fn page(title: &str, current_path: &str, inner_content: &str, partial: bool, features: &FeaturesConfig) -> String {
+    if partial {
+        // HTMX partial - just the content area (no sidebar for partial updates)
+        format!(r#"<div class='flex flex-col h-screen'>
+    <div class='flex flex-1 overflow-hidden'>
+        <main class='flex-1 overflow-auto bg-gray-50 p-6'>{}{}</main>
+    </div>
+</div>"#,
+            STATUS_BANNER, inner_content)
+    } else {
+        // Full page - wrap with base HTML and sidebar (without time selector)
+        base_html(title, &format!(r#"<div class='flex flex-col h-screen'>
+    <div class='flex flex-1 overflow-hidden'>
+        <aside class='w-64 flex-shrink-0'>{}</aside>
+        <main class='flex-1 overflow-auto bg-gray-50 p-6'>{}{}</main>
+    </div>
+</div>"#,
+            nav_sidebar(current_path, features), STATUS_BANNER, inner_content))
+    }
+}
+
+/// Base HTML template
+pub fn base_html(title: &str, content: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{} - Beanweb</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/tailwindcss@2.2.19/dist/tailwind.min.css">
+    <style>
+        .htmx-indicator {{ opacity: 0; transition: opacity 0.3s; }}
+        .htmx-request .htmx-indicator {{ opacity: 1; }}
+        .htmx-request.htmx-indicator {{ opacity: 1; }}
+    </style>
+</head>
+<body class="bg-gray-50 text-gray-900">
+    {}
+</body>
+</html>"#,
+        title, content
+    )
+}
+
+/// Navigation sidebar (without time selector - for backward compatibility);
+/// 预算 is only listed with the budget feature enabled
+pub fn nav_sidebar(current_path: &str, features: &FeaturesConfig) -> String {
+    let links = [
+        ("/", "仪表盘", "dashboard"),
+        ("/accounts", "账户", "accounts"),
+        ("/transactions", "流水", "transactions"),
+        ("/commodities", "货币", "commodities"),
+        ("/reports", "报表", "reports"),
+        ("/budgets", "预算", "budgets"),
+        ("/tags", "标签", "tags"),
+        ("/timeline", "时间线", "timeline"),
+        ("/files", "文件", "files"),
+        ("/settings", "设置", "settings"),
+    ];
+
+    let mut nav = format!("<div class='bg-white border-r h-screen flex flex-col'><div class='p-4 border-b'>{}</div><ul class='flex-1 py-2 space-y-1 px-2'>", LEDGER_TITLE);
+
+    for (path, label, id) in links.iter().filter(|(_, _, id)| *id != "budgets" || features.budget_enable) {
+        let is_active = if *path == "/" {
+            current_path == "/"
+        } else {
+            current_path.starts_with(path)
+        };
+        let active_class = if is_active { "bg-indigo-50 text-indigo-600" } else { "text-gray-600 hover:bg-gray-50" };
+        let icon = match *id {
+            "dashboard" => "📊",
+            "accounts" => "💰",
+            "transactions" => "📋",
+            "commodities" => "💱",
+            "reports" => "📈",
+            "budgets" => "🎯",
+            "tags" => "🏷️",
+            "timeline" => "🕒",
+            "files" => "📄",
+            "settings" => "⚙️",
+            _ => "📄",
+        };
+        // Transactions still on suspense accounts are counted next to 流水
+        let badge = if *id == "transactions" {
+            "<span hx-get='/transactions/suspense/badge' hx-trigger='load, ledger-reloaded from:body' class='absolute right-3 top-2'></span>"
+        } else {
+            ""
+        };
+        nav.push_str(&format!(
+            r#"<li class='relative'><a href='{}' class='flex items-center gap-2 px-3 py-2 rounded-lg {}'>{}<span>{}</span></a>{}</li>"#,
+            path, active_class, icon, label, badge
+        ));
+    }
+    nav.push_str("</ul>");
+    nav.push_str(CHECK_BADGE);
+    nav.push_str(PRIVACY_TOGGLE);
+    nav.push_str("</div>");
+    nav
+}
+
+/// Page-level time selector - For placing at top of pages
+/// With year/month dropdowns that can be combined, plus custom date picker with drill-down
+pub fn page_time_selector(current_range: &str, _start_date: &str, _end_date: &str) -> String {
+    // Parse current year and month from range string
+    let (selected_year, selected_month) = if current_range.starts_with("year:") {
+        let year = current_range.strip_prefix("year:").unwrap_or("");
+        if year.ends_with("-01") || year.ends_with("-02") || year.ends_with("-03") ||
+           year.ends_with("-04") || year.ends_with("-05") || year.ends_with("-06") ||
+           year.ends_with("-07") || year.ends_with("-08") || year.ends_with("-09") ||
+           year.ends_with("-10") || year.ends_with("-11") || year.ends_with("-12") {
+            // Format: year:2026-01 (year + month)
+            let parts: Vec<&str> = year.split('-').collect();
+            if parts.len() == 2 {
+                (parts[0], parts[1])
+            } else {
+                (year, "")
+            }
+        } else {
+            (year, "")
+        }
+    } else if current_range.starts_with("month:") {
+        let month = current_range.strip_prefix("month:").unwrap_or("");
+        ("", month)
+    } else {
+        ("", "")
+    };
+
+    // Parse custom range
+    let (custom_start, custom_end) = if current_range.starts_with("custom:") {
+        if let Some(custom_str) = current_range.strip_prefix("custom:") {
+            let parts: Vec<&str> = custom_str.split(',').collect();
+            if parts.len() == 2 {
+                (parts[0].to_string(), parts[1].to_string())
+            } else {
+                ("".to_string(), "".to_string())
+            }
+        } else {
+            ("".to_string(), "".to_string())
+        }
+    } else {
+        ("".to_string(), "".to_string())
+    };
+
+    format!(r#"
+        <div class='flex items-center gap-3 mb-4 p-3 bg-white rounded-lg border shadow-sm' id='page-time-selector'>
+            <span class='text-sm font-medium text-gray-600 flex-shrink-0'>时间:</span>
+            <!-- Year selector: 全部 or specific year -->
+            <select id='year-select' class='px-2 py-1.5 text-sm border rounded-lg bg-white min-w-[100px]' onchange='handleYearChange()'>
+                <option value='' {}>年份</option>
+                <option value='all' {}>全部</option>
+                <!-- Years loaded dynamically -->
+                <optgroup label='选择年份' id='year-options'>
+                    <option value='' disabled>加载中...</option>
+                </optgroup>
+            </select>
+            <span class='text-gray-400' id='month-separator' style='display: none;'>-</span>
+            <!-- Month selector: cascading, enabled only when year selected -->
+            <select id='month-select' class='px-2 py-1.5 text-sm border rounded-lg bg-white min-w-[90px]' onchange='handleMonthChange()' disabled style='background-color: #f9fafb;'>
+                <option value='all' {}>月份</option>
+                <option value='' {}>全年</option>
+                <option value='01' {}>1月</option>
+                <option value='02' {}>2月</option>
+                <option value='03' {}>3月</option>
+                <option value='04' {}>4月</option>
+                <option value='05' {}>5月</option>
+                <option value='06' {}>6月</option>
+                <option value='07' {}>7月</option>
+                <option value='08' {}>8月</option>
+                <option value='09' {}>9月</option>
+                <option value='10' {}>10月</option>
+                <option value='11' {}>11月</option>
+                <option value='12' {}>12月</option>
+            </select>
+            <div class='flex-1'></div>
+            <label class='flex items-center gap-1.5 text-sm text-gray-600 cursor-pointer select-none' title='未来日期的交易（如计划中的房租）默认不计入余额和报表'>
+                <input type='checkbox' id='include-future-toggle' class='w-4 h-4 rounded border-gray-300' onchange='toggleIncludeFuture(this.checked)'>
+                含未来交易
+            </label>
+            <button onclick='toggleTimeSelector()' class='px-3 py-1.5 text-sm border rounded-lg hover:bg-gray-50 flex items-center gap-1'>
+                <svg xmlns='http://www.w3.org/2000/svg' class='h-4 w-4' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
+                    <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M8 7V3m8 4V3m-9 8h10M5 21h14a2 2 0 002-2V7a2 2 0 00-2-2H5a2 2 0 00-2 2v12a2 2 0 002 2z'/>
+                </svg>
+                自定义
+            </button>
+        </div>
+        <!-- Time selector dropdown (hidden by default) -->
+        <div id='time-selector-dropdown' class='hidden mb-4 p-4 bg-white rounded-lg border shadow-lg' style='position: absolute; z-index: 50; min-width: 360px;'>
+            <div class='flex items-center justify-between mb-3'>
+                <h4 class='font-medium'>自定义日期范围</h4>
+                <button onclick='toggleTimeSelector()' class='text-gray-400 hover:text-gray-600'>
+                    <svg xmlns='http://www.w3.org/2000/svg' class='h-5 w-5' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M6 18L18 6M6 6l12 12'/>
+                    </svg>
+                </button>
+            </div>
+            <!-- Step 1: Select Year -->
+            <div id='custom-step1' class='space-y-3'>
+                <label class='text-sm font-medium text-gray-700'>选择年份</label>
+                <select id='custom-year-select' class='w-full px-3 py-2 border rounded-lg bg-white' onchange='customYearChanged()'>
+                    <option value=''>请选择年份</option>
+                    <optgroup label='可用年份' id='custom-year-options'>
+                        <option value='' disabled>加载中...</option>
+                    </optgroup>
+                </select>
+            </div>
+            <!-- Step 2: Select Month (hidden initially) -->
+            <div id='custom-step2' class='space-y-3' style='display: none;'>
+                <div class='flex items-center justify-between'>
+                    <label class='text-sm font-medium text-gray-700'>选择月份(从当年1月到所选月份)</label>
+                    <button onclick='backToYearSelect()' class='text-xs text-indigo-600 hover:underline'>返回年份</button>
+                </div>
+                <div class='grid grid-cols-4 gap-2'>
+                    <button onclick='selectCustomMonth("01")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>1月</button>
+                    <button onclick='selectCustomMonth("02")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>2月</button>
+                    <button onclick='selectCustomMonth("03")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>3月</button>
+                    <button onclick='selectCustomMonth("04")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>4月</button>
+                    <button onclick='selectCustomMonth("05")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>5月</button>
+                    <button onclick='selectCustomMonth("06")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>6月</button>
+                    <button onclick='selectCustomMonth("07")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>7月</button>
+                    <button onclick='selectCustomMonth("08")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>8月</button>
+                    <button onclick='selectCustomMonth("09")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>9月</button>
+                    <button onclick='selectCustomMonth("10")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>10月</button>
+                    <button onclick='selectCustomMonth("11")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>11月</button>
+                    <button onclick='selectCustomMonth("12")' class='px-2 py-2 text-sm border rounded hover:bg-gray-50'>12月</button>
+                </div>
+                <p id='custom-selected-date' class='text-sm text-gray-500 text-center'></p>
+            </div>
+            <!-- Direct date input (alternative) -->
+            <div class='mt-3 pt-3 border-t'>
+                <label class='text-xs text-gray-500 mb-1 block'>或者直接输入日期范围</label>
+                <div class='flex items-center gap-2 mb-2'>
+                    <input type='date' id='custom-start' value='{}' class='flex-1 px-2 py-1.5 text-sm border rounded'>
+                    <span class='text-gray-400'>至</span>
+                    <input type='date' id='custom-end' value='{}' class='flex-1 px-2 py-1.5 text-sm border rounded'>
+                </div>
+                <button onclick='applyCustomRange()' class='w-full px-3 py-2 bg-indigo-600 text-white text-sm rounded-lg hover:bg-indigo-700'>
+                    应用
+                </button>
+            </div>
+        </div>
+        <script>
+        let customSelectedYear = '';
+
+        // Fetch available years for custom picker
+        function loadCustomYearOptions() {{
+            fetch('/api/time-range/years')
+                .then(r => r.json())
+                .then(years => {{
+                    const yearGroup = document.getElementById('custom-year-options');
+                    if (yearGroup && years.length > 0) {{
+                        yearGroup.innerHTML = years.map(y =>
+                            `<option value="${{y}}">${{y}} 年</option>`
+                        ).join('');
+                    }}
+                }})
+                .catch(err => console.error('Failed to load years:', err));
+        }}
+
+        // Fetch available years and populate main selector
+        function loadYearOptions() {{
+            fetch('/api/time-range/years')
+                .then(r => r.json())
+                .then(years => {{
+                    const yearGroup = document.getElementById('year-options');
+                    if (yearGroup && years.length > 0) {{
+                        const currentYear = document.getElementById('year-select').value.split('-')[0];
+                        yearGroup.innerHTML = years.map(y =>
+                            `<option value="${{y}}" ${{y === currentYear ? 'selected' : ''}}>${{y}} 年</option>`
+                        ).join('');
+                        // If a year is already selected, trigger change to update month selector
+                        if (document.getElementById('year-select').value && document.getElementById('year-select').value !== 'all') {{
+                            handleYearChange();
+                        }}
+                    }}
+                }})
+                .catch(err => console.error('Failed to load years:', err));
+        }}
+
+        // Handle year selection change - enables month selector
+        function handleYearChange() {{
+            const yearSelect = document.getElementById('year-select');
+            const monthSelect = document.getElementById('month-select');
+            const separator = document.getElementById('month-separator');
+            const year = yearSelect.value;
+
+            if (year === '' || year === 'all') {{
+                // Reset to all - no time filter
+                monthSelect.value = '';
+                monthSelect.disabled = true;
+                monthSelect.style.backgroundColor = '#f9fafb';
+                separator.style.display = 'none';
+                setTimeRange('all');
+            }} else {{
+                // Year selected - enable month selector
+                monthSelect.disabled = false;
+                monthSelect.style.backgroundColor = '';
+                separator.style.display = 'inline';
+                // Default to full year, user can select specific month
+                monthSelect.value = 'all';
+            }}
+        }}
+
+        // Handle month selection change
+        function handleMonthChange() {{
+            const yearSelect = document.getElementById('year-select');
+            const monthSelect = document.getElementById('month-select');
+            const year = yearSelect.value;
+            const month = monthSelect.value;
+
+            if (!year || year === 'all') {{
+                // Should not happen due to disabled state, but handle gracefully
+                return;
+            }}
+
+            if (month === '' || month === 'all') {{
+                // Full year selected
+                setTimeRange('year:' + year);
+            }} else {{
+                // Specific month selected
+                setTimeRange('year:' + year + '-' + month);
+            }}
+        }}
+
+        function setTimeRange(range) {{
+            let apiRange = range;
+            if (range === 'all') {{
+                apiRange = 'all';
+            }} else if (range.match(/^\d{4}$/)) {{
+                // 4-digit year -> add year: prefix
+                apiRange = 'year:' + range;
+            }} else if (range.includes('-') && range.length === 7) {{
+                // year-month format (like 2025-03)
+                apiRange = 'year:' + range;
+            }}
+
+            console.log('[DEBUG] setTimeRange: original=' + range + ', api=' + apiRange);
+            htmx.ajax('POST', '/api/time-range?range=' + encodeURIComponent(apiRange),
+                {{ target: 'body', swap: 'none' }}).then(() => {{
+                    afterTimeRangeChange();
+                }}).catch(err => {{
+                    console.error('Failed to set time range:', err);
+                    window.location.reload();
+                }});
+        }}
+
+        // The POST response carries `HX-Trigger: time-range-changed`, so htmx fires
+        // the event on body and period-dependent fragments refetch themselves
+        // (hx-trigger='... time-range-changed from:body'). Pages without any
+        // subscribed fragment fall back to a full reload.
+        function afterTimeRangeChange() {{
+            if (!document.querySelector('[hx-trigger*="time-range-changed"]')) {{
+                window.location.reload();
+            }}
+        }}
+
+        // /api/events pushes `ledger-reloaded` after every reload (file watcher,
+        // saves, /api/reload); it is re-fired on body so subscribed fragments
+        // refetch (hx-trigger='... ledger-reloaded from:body')
+        if (window.EventSource && !window.ledgerEvents) {{
+            window.ledgerEvents = new EventSource('/api/events');
+            window.ledgerEvents.addEventListener('ledger-reloaded', () => htmx.trigger(document.body, 'ledger-reloaded'));
+        }}
+
+        function toggleIncludeFuture(checked) {{
+            htmx.ajax('POST', '/api/time-range?include_future=' + checked,
+                {{ target: 'body', swap: 'none' }}).then(() => {{
+                    afterTimeRangeChange();
+                }}).catch(err => {{
+                    console.error('Failed to toggle future transactions:', err);
+                    window.location.reload();
+                }});
+        }}
+
+        function toggleTimeSelector() {{
+            const dropdown = document.getElementById('time-selector-dropdown');
+            if (dropdown) {{
+                dropdown.classList.toggle('hidden');
+                if (!dropdown.classList.contains('hidden')) {{
+                    // Reset to step 1 when opening
+                    document.getElementById('custom-step1').style.display = 'block';
+                    document.getElementById('custom-step2').style.display = 'none';
+                    loadCustomYearOptions();
+                }}
+            }}
+        }}
+
+        // Custom date picker drill-down functions
+        function customYearChanged() {{
+            const yearSelect = document.getElementById('custom-year-select');
+            customSelectedYear = yearSelect.value;
+            if (customSelectedYear) {{
+                document.getElementById('custom-step1').style.display = 'none';
+                document.getElementById('custom-step2').style.display = 'block';
+                document.getElementById('custom-selected-date').textContent = '已选择: ' + customSelectedYear + ' 年';
+            }}
+        }}
+
+        function backToYearSelect() {{
+            document.getElementById('custom-step1').style.display = 'block';
+            document.getElementById('custom-step2').style.display = 'none';
+            customSelectedYear = '';
+        }}
+
+        function selectCustomMonth(month) {{
+            if (customSelectedYear) {{
+                const range = customSelectedYear + '-' + month;
+                setTimeRange(range);
+                toggleTimeSelector(); // Close dropdown
+            }}
+        }}
+
+        function applyCustomRange() {{
+            const start = document.getElementById('custom-start').value;
+            const end = document.getElementById('custom-end').value;
+            if (start && end) {{
+                htmx.ajax('POST', '/api/time-range?range=custom:' + start + ',' + end,
+                    {{ target: 'body', swap: 'none' }}).then(() => {{
+                        toggleTimeSelector(); // Close dropdown
+                        afterTimeRangeChange();
+                    }}).catch(err => {{
+                        console.error('Failed to set custom range:', err);
+                        window.location.reload();
+                    }});
+            }}
+        }}
+
+        // Initialize on load
+        document.addEventListener('DOMContentLoaded', function() {{
+            // Load year options first, then sync state
+            loadYearOptions();
+
+            fetch('/api/time-range')
+                .then(r => r.json())
+                .then(ctx => {{
+                    const toggle = document.getElementById('include-future-toggle');
+                    if (toggle) {{ toggle.checked = !!ctx.include_future; }}
+                }})
+                .catch(err => console.error('Failed to load time range:', err));
+
+            // If a year is already selected in the dropdown, enable month selector
+            const yearSelect = document.getElementById('year-select');
+            if (yearSelect && yearSelect.value && yearSelect.value !== 'all') {{
+                handleYearChange();
+            }} else {{
+                // Ensure month selector is disabled when no year selected
+                const monthSelect = document.getElementById('month-select');
+                const separator = document.getElementById('month-separator');
+                if (monthSelect) {{
+                    monthSelect.disabled = true;
+                    monthSelect.style.backgroundColor = '#f9fafb';
+                }}
+                if (separator) {{
+                    separator.style.display = 'none';
+                }}
+            }}
+        }});
+
+        // Close dropdown when clicking outside
+        document.addEventListener('click', function(e) {{
+            const dropdown = document.getElementById('time-selector-dropdown');
+            const toggle = document.querySelector('button[onclick="toggleTimeSelector()"]');
+            if (dropdown && !dropdown.classList.contains('hidden') && !dropdown.contains(e.target) && (!toggle || !toggle.contains(e.target))) {{
+                dropdown.classList.add('hidden');
+            }}
+        }});
+        </script>
+    "#,
+        if selected_year.is_empty() { "selected" } else { "" },
+        if selected_year == "all" { "selected" } else { "" },
+        if selected_month.is_empty() { "selected" } else { "" },
+        if selected_month == "all" { "selected" } else { "" },
+        if selected_month == "01" { "selected" } else { "" },
+        if selected_month == "02" { "selected" } else { "" },
+        if selected_month == "03" { "selected" } else { "" },
+        if selected_month == "04" { "selected" } else { "" },
+        if selected_month == "05" { "selected" } else { "" },
+        if selected_month == "06" { "selected" } else { "" },
+        if selected_month == "07" { "selected" } else { "" },
+        if selected_month == "08" { "selected" } else { "" },
+        if selected_month == "09" { "selected" } else { "" },
+        if selected_month == "10" { "selected" } else { "" },
+        if selected_month == "11" { "selected" } else { "" },
+        if selected_month == "12" { "selected" } else { "" },
+        custom_start,
+        custom_end
+    )
+}
+
+/// Header bar - Wrapper for main content area
+pub fn header_bar(current_path: &str, features: &FeaturesConfig) -> String {
+    format!(r#"<div class='flex flex-col h-screen'>
+    <div class='flex flex-1 overflow-hidden'>
+        <aside class='w-64 flex-shrink-0'>{}</aside>
+        <main class='flex-1 overflow-auto bg-gray-50 p-6'>"#,
+        nav_sidebar(current_path, features))
+}
@@ -0,0 +1,53 @@
+//! HTML rendering of pages and fragments
+//!
+//! Pure functions from ledger data to HTML: nothing here reads requests or
+//! touches the ledger, so pages can be tested on their own and another
+//! frontend can reuse the same data. The HTTP server (beanweb-api) fetches
+//! the data and hands it over.
+//!
+//! Modules:
+//! - layout: Page shell, sidebar navigation and time selector
+//! - banners: Load errors, parse errors, failed assertions, negative balances and the check badge
+//! - dashboard: Dashboard summary and cards
+//! - reports: Report tabs and their fragments
+//! - digest: Standalone period digest for email
+//! - accounts, picker: Account tree, account detail and the account picker
+//! - transactions, suspense: Transaction list, detail, forms and suspense review
+//! - budgets, commodities, tags, timeline: The other ledger pages
+//! - files, tools, rules, templates, settings, auth: Editing, tools and configuration pages
+
+pub mod accounts;
+pub mod auth;
+pub mod banners;
+pub mod budgets;
+pub mod commodities;
+pub mod dashboard;
+pub mod digest;
+pub mod files;
+pub mod layout;
+pub mod picker;
+pub mod reports;
+pub mod rules;
+pub mod settings;
+pub mod suspense;
+pub mod tags;
+pub mod templates;
+pub mod timeline;
+pub mod tools;
+pub mod transactions;
+
+/// Escape text for element content (quotes are left as they are)
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Escape text for an attribute value, quoted with `'` or `"`
+pub fn attr_escape(text: &str) -> String {
+    html_escape(text).replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+/// `/api/documents/` URL of a document path, each segment percent-encoded
+pub fn document_url(path: &str) -> String {
+    let segments: Vec<_> = path.split('/').map(urlencoding::encode).collect();
+    format!("/api/documents/{}", segments.join("/"))
+}
@@ -0,0 +1,196 @@
+//! Account picker - Reusable tree-based account selector
+//!
+//! Host forms embed [`picker_button`] next to a text input; the picker tree
+//! is loaded on demand and fills the named input on selection.
+//!
+//! Features:
+//! - Expand/collapse per node, type-ahead search that expands matches
+//! - Favorites on top (starred accounts, kept in the browser's localStorage)
+//! - Keyboard navigation: ↑/↓ move, →/← expand/collapse, Enter select, Esc close
+
+use crate::{attr_escape, html_escape};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Button + host element that opens the picker for the text input in the same
+/// `.picker-anchor` container. `account_type` limits the tree (e.g. "expenses").
+pub fn picker_button(account_type: Option<&str>) -> String {
+    let type_param = account_type.map(|t| format!("&type={}", urlencoding::encode(t))).unwrap_or_default();
+    format!(
+        r#"<button type='button' title='从账户树选择' tabindex='-1'
+            class='absolute right-2 top-2 px-1.5 py-0.5 text-gray-400 hover:text-indigo-600 rounded'
+            onclick="const a = this.closest('.picker-anchor'); const h = a.querySelector('[data-picker-host]'); if (h.innerHTML.trim()) {{ h.innerHTML = ''; }} else {{ htmx.ajax('GET', '/accounts/picker?target=' + encodeURIComponent(a.querySelector('input[type=text]').name) + '{}', {{target: h}}); }}">🌲</button>
+        <div data-picker-host class='absolute right-0 z-20 w-full min-w-[18rem] mt-1'></div>"#,
+        type_param
+    )
+}
+
+/// Render one tree level; intermediate (implicit) accounts expand but can't be selected
+fn render_nodes(parent: &str, children: &BTreeMap<String, BTreeSet<String>>, real: &HashSet<String>, html: &mut String) {
+    let Some(names) = children.get(parent) else {
+        return;
+    };
+    for name in names {
+        let short_name = name.rsplit(':').next().unwrap_or(name);
+        let has_children = children.contains_key(name);
+        let selectable = real.contains(name);
+        html.push_str(&format!(
+            r#"<li class='picker-node' data-account='{name}'>
+                <div class='picker-item flex items-center gap-1 px-2 py-1 rounded cursor-pointer hover:bg-gray-100' data-account='{name}' data-selectable='{sel}'>
+                    <span class='picker-toggle w-4 text-xs text-gray-400'>{caret}</span>
+                    <span class='flex-1 text-sm {label_class}'>{short}</span>
+                    {star}
+                </div>"#,
+            name = attr_escape(name),
+            sel = if selectable { "1" } else { "0" },
+            caret = if has_children { "▸" } else { "" },
+            label_class = if selectable { "" } else { "text-gray-500" },
+            short = html_escape(short_name),
+            star = if selectable {
+                "<button type='button' tabindex='-1' class='picker-star text-gray-300 hover:text-yellow-500' title='收藏'>☆</button>"
+            } else {
+                ""
+            },
+        ));
+        if has_children {
+            html.push_str("<ul class='picker-children ml-4 hidden'>");
+            render_nodes(name, children, real, html);
+            html.push_str("</ul>");
+        }
+        html.push_str("</li>");
+    }
+}
+
+/// Picker for the input named `target`: `children` maps each account (and
+/// `""` for the top) to its direct children, `real` holds the selectable ones
+pub fn tree(target: &str, children: &BTreeMap<String, BTreeSet<String>>, real: &HashSet<String>) -> String {
+    if real.is_empty() {
+        return r#"<div class='bg-white border rounded-lg shadow-lg p-4 text-sm text-gray-500'>没有可选的账户</div>"#.to_string();
+    }
+
+    let mut tree = String::new();
+    render_nodes("", children, real, &mut tree);
+    let picker_id = format!("picker-{}", target.replace(|c: char| !c.is_ascii_alphanumeric(), "-"));
+
+    format!(
+        r#"<div id='{id}' class='account-picker bg-white border rounded-lg shadow-lg p-2' data-target='{target}'>
+            <input type='search' class='picker-search w-full px-3 py-1.5 mb-2 border rounded text-sm' placeholder='筛选账户，↑↓ 选择，Enter 确认'>
+            <div class='picker-favorites'></div>
+            <ul class='picker-tree max-h-64 overflow-auto'>{tree}</ul>
+        </div>
+        <script>{script}
+        AccountPicker.init(document.getElementById('{id}'));</script>"#,
+        id = picker_id,
+        target = attr_escape(target),
+        tree = tree,
+        script = PICKER_SCRIPT,
+    )
+}
+
+/// Client-side behavior, defined once per page
+const PICKER_SCRIPT: &str = r#"
+window.AccountPicker = window.AccountPicker || (function () {
+    const KEY = 'beanweb_favorite_accounts';
+    const favorites = () => { try { return JSON.parse(localStorage.getItem(KEY)) || []; } catch (e) { return []; } };
+    const toggleFavorite = (name) => {
+        const list = favorites();
+        const next = list.includes(name) ? list.filter(n => n !== name) : list.concat([name]);
+        localStorage.setItem(KEY, JSON.stringify(next));
+    };
+    const visibleItems = (picker) => Array.from(picker.querySelectorAll('.picker-item')).filter(el => el.offsetParent !== null);
+    const setActive = (picker, el) => {
+        picker.querySelectorAll('.picker-active').forEach(e => e.classList.remove('picker-active', 'bg-indigo-50'));
+        if (el) { el.classList.add('picker-active', 'bg-indigo-50'); el.scrollIntoView({block: 'nearest'}); }
+    };
+    const setExpanded = (li, expanded) => {
+        const children = li && li.querySelector(':scope > .picker-children');
+        if (!children) return;
+        children.classList.toggle('hidden', !expanded);
+        li.querySelector(':scope > .picker-item .picker-toggle').textContent = expanded ? '▾' : '▸';
+    };
+    const renderFavorites = (picker) => {
+        const favs = favorites();
+        picker.querySelectorAll('.picker-star').forEach(star => {
+            const on = favs.includes(star.closest('.picker-item').dataset.account);
+            star.textContent = on ? '★' : '☆';
+            star.classList.toggle('text-yellow-500', on);
+        });
+        const rows = favs
+            .filter(name => picker.querySelector('.picker-tree .picker-item[data-selectable="1"][data-account="' + CSS.escape(name) + '"]'))
+            .map(name => "<div class='picker-item px-2 py-1 rounded cursor-pointer hover:bg-gray-100 text-sm' data-selectable='1' data-account='" + name + "'>★ " + name + "</div>");
+        picker.querySelector('.picker-favorites').innerHTML = rows.length
+            ? "<div class='text-xs text-gray-400 px-2'>收藏</div>" + rows.join('') + "<div class='border-b my-1'></div>"
+            : '';
+    };
+    const close = (picker) => {
+        const host = picker.closest('[data-picker-host]');
+        if (host) { host.innerHTML = ''; } else { picker.remove(); }
+    };
+    const select = (picker, name) => {
+        const input = document.querySelector('[name="' + picker.dataset.target + '"]');
+        if (input) {
+            input.value = name;
+            input.dispatchEvent(new Event('change', {bubbles: true}));
+            input.focus();
+        }
+        picker.dispatchEvent(new CustomEvent('account-picked', {bubbles: true, detail: {account: name}}));
+        close(picker);
+    };
+    const filter = (picker, q) => {
+        const nodes = Array.from(picker.querySelectorAll('.picker-tree li')).reverse();
+        nodes.forEach(li => {
+            if (!q) { li.classList.remove('hidden'); setExpanded(li, false); return; }
+            const self = li.dataset.account.toLowerCase().includes(q);
+            const child = li.querySelector(':scope > .picker-children > li:not(.hidden)');
+            li.classList.toggle('hidden', !self && !child);
+            setExpanded(li, !!child);
+        });
+        setActive(picker, visibleItems(picker).find(el => el.dataset.selectable === '1'));
+    };
+    const init = (picker) => {
+        if (!picker) return;
+        renderFavorites(picker);
+        const search = picker.querySelector('.picker-search');
+        search.addEventListener('input', () => filter(picker, search.value.trim().toLowerCase()));
+        picker.addEventListener('click', (e) => {
+            const item = e.target.closest('.picker-item');
+            if (!item) return;
+            if (e.target.closest('.picker-star')) {
+                toggleFavorite(item.dataset.account);
+                renderFavorites(picker);
+                return;
+            }
+            const li = item.closest('li');
+            const children = li && li.querySelector(':scope > .picker-children');
+            if (children && (e.target.closest('.picker-toggle') || item.dataset.selectable !== '1')) {
+                setExpanded(li, children.classList.contains('hidden'));
+                return;
+            }
+            select(picker, item.dataset.account);
+        });
+        picker.addEventListener('keydown', (e) => {
+            const items = visibleItems(picker);
+            const active = picker.querySelector('.picker-active');
+            const index = items.indexOf(active);
+            const li = active && active.closest('.picker-tree li');
+            if (e.key === 'ArrowDown') { setActive(picker, items[Math.min(index + 1, items.length - 1)]); }
+            else if (e.key === 'ArrowUp') { setActive(picker, items[Math.max(index - 1, 0)]); }
+            else if (e.key === 'ArrowRight' && li) { setExpanded(li, true); }
+            else if (e.key === 'ArrowLeft' && li) {
+                const children = li.querySelector(':scope > .picker-children');
+                if (children && !children.classList.contains('hidden')) { setExpanded(li, false); }
+                else { const parent = li.parentElement.closest('li'); if (parent) setActive(picker, parent.querySelector(':scope > .picker-item')); }
+            }
+            else if (e.key === 'Enter') {
+                if (active && active.dataset.selectable === '1') { select(picker, active.dataset.account); }
+                else if (li) { setExpanded(li, true); }
+            }
+            else if (e.key === 'Escape') { close(picker); }
+            else { return; }
+            e.preventDefault();
+        });
+        setActive(picker, visibleItems(picker)[0]);
+        search.focus();
+    };
+    return { init: init };
+})();
+"#;
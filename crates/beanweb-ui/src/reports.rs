@@ -0,0 +1,707 @@
+//! Reports: the page with its tabs, and the fragment of each tab
+//!
+//! The balance sheet, income-expense report and overview carry the
+//! original currencies / converted toggle ([`conversion_toggle`]); the
+//! server remembers the choice per report.
+
+use crate::html_escape;
+use beanweb_core::holdings::{HoldingsReport, UNCLASSIFIED};
+use beanweb_core::trends::CategoryTrends;
+use beanweb_core::{
+    AccountType, AllocationReport, Amount, Decimal, BalanceReport, BalanceReportEntry, ConversionMode, IncomeExpenseEntry,
+    IncomeExpenseReport, MonthlySummaryReport, PayeeSummary, PayeeTrends, TransferSummary,
+};
+
+/// Reports page content: the time selector, a button per tab and the tab
+/// content, loaded with the overview
+pub fn page(time_selector: &str) -> String {
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>报表</h2></div>
+        {}
+        <div class='mb-4 flex gap-2'>
+            <button hx-get='/reports/overview' hx-target='#reports-content' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>概览</button>
+            <button hx-get='/reports/balance' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>资产负债表</button>
+            <button hx-get='/reports/income-expense' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>收支报表</button>
+            <button hx-get='/reports/monthly' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>月度汇总</button>
+            <button hx-get='/reports/allocation' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>净收入去向</button>
+            <button hx-get='/reports/holdings' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>持仓分布</button>
+            <button hx-get='/reports/payees' hx-target='#reports-content' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>商户</button>
+        </div>
+        <div id='reports-content' hx-get='/reports/overview' hx-trigger='load, time-range-changed from:body, ledger-reloaded from:body' class='bg-white rounded-xl shadow-sm p-6'>
+            <p class='text-gray-500 text-center'>加载中...</p>
+        </div>"#,
+        time_selector
+    )
+}
+
+/// Toggle above a report: original currencies or converted into `currency`
+/// `path` is the report fragment, with any query it needs
+pub fn conversion_toggle(path: &str, mode: ConversionMode, currency: &str) -> String {
+    let separator = if path.contains('?') { '&' } else { '?' };
+    let button = |target: ConversionMode, label: String| {
+        let class = if target == mode { "bg-indigo-600 text-white" } else { "text-gray-600 hover:bg-gray-50" };
+        format!(
+            "<button hx-get='{}{}conversion={}' hx-target='#reports-content' class='px-3 py-1 {}'>{}</button>",
+            path, separator, target, class, label
+        )
+    };
+    format!(
+        "<div class='flex justify-end mb-4'><div class='inline-flex text-sm border rounded-lg overflow-hidden' title='金额显示方式'>{}{}</div></div>",
+        button(ConversionMode::Original, "原币种".to_string()),
+        button(ConversionMode::Converted, format!("折算为 {}", currency))
+    )
+}
+
+/// Overview tab: assets and liabilities next to income and expenses
+pub fn overview(balance_report: &BalanceReport, income_expense: &IncomeExpenseReport, trends: &CategoryTrends, mode: ConversionMode) -> String {
+
+    // Group balance entries by account type
+    let assets: Vec<_> = balance_report.entries.iter()
+        .filter(|e| matches!(e.account_type, AccountType::Assets))
+        .collect();
+    let liabilities: Vec<_> = balance_report.entries.iter()
+        .filter(|e| matches!(e.account_type, AccountType::Liabilities))
+        .collect();
+
+    let mut html = conversion_toggle("/reports/overview", mode, &balance_report.currency);
+    html.push_str(r#"<div class='grid grid-cols-1 md:grid-cols-2 gap-6'>"#);
+
+    // Assets section
+    html.push_str(r#"<div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4'>资产</h3><div class='space-y-2'>"#);
+    for entry in &assets {
+        html.push_str(&format!(r#"<div class='flex justify-between py-2 border-b'><span>{}</span>{}</div>"#, entry.account, render_balance_amount(entry, &balance_report.currency, mode)));
+    }
+    html.push_str("</div></div>");
+
+    // Liabilities section
+    html.push_str(r#"<div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4'>负债</h3><div class='space-y-2'>"#);
+    for entry in &liabilities {
+        html.push_str(&format!(r#"<div class='flex justify-between py-2 border-b'><span>{}</span>{}</div>"#, entry.account, render_balance_amount(entry, &balance_report.currency, mode)));
+    }
+    html.push_str("</div></div>");
+
+    // Income section
+    html.push_str(r#"<div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4 text-green-600'>收入</h3><div class='space-y-2'>"#);
+    html.push_str(&render_income_expense_rows(&income_expense.income_entries, &trends.months, &trends.income, &income_expense.currency, mode, "text-green-600", INCOME_CHART_COLOR));
+    html.push_str("</div></div>");
+
+    // Expenses section
+    html.push_str(r#"<div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4 text-red-600'>支出</h3><div class='space-y-2'>"#);
+    html.push_str(&render_income_expense_rows(&income_expense.expense_entries, &trends.months, &trends.expenses, &income_expense.currency, mode, "text-red-600", EXPENSE_CHART_COLOR));
+    html.push_str("</div></div></div>");
+
+    html
+}
+
+/// Balance sheet tab, with a column per other operating currency
+pub fn balance_sheet(balance_report: &BalanceReport, mode: ConversionMode) -> String {
+
+    // One more column per other operating currency
+    let others: Vec<&str> = balance_report.operating_totals.iter().map(|t| t.currency.as_str()).collect();
+    let other_headers: String = others.iter()
+        .map(|currency| format!(r#"<th class='px-4 py-2 text-right'>{}</th>"#, currency))
+        .collect();
+
+    let mut html = conversion_toggle("/reports/balance", mode, &balance_report.currency);
+    html.push_str(&format!(r#"<div class='overflow-x-auto'><table class='w-full'><thead class='bg-gray-50'><tr><th class='px-4 py-2 text-left'>账户</th><th class='px-4 py-2 text-right'>余额</th>{}</tr></thead><tbody>"#, other_headers));
+
+    // Group by account type
+    let types = [
+        (AccountType::Assets, "资产"),
+        (AccountType::Liabilities, "负债"),
+        (AccountType::Equity, "权益"),
+    ];
+
+    for (account_type, type_name) in &types {
+        let entries: Vec<_> = balance_report.entries.iter()
+            .filter(|e| &e.account_type == account_type)
+            .collect();
+
+        if !entries.is_empty() {
+            html.push_str(&format!(r#"<tr class='bg-gray-100'><td class='px-4 py-2 font-bold' colspan='{}'>{}</td></tr>"#, 2 + others.len(), type_name));
+            for entry in &entries {
+                let other_cells: String = others.iter()
+                    .map(|currency| {
+                        let amount = entry.operating.iter().find(|a| a.currency == *currency);
+                        format!(r#"<td class='px-4 py-2 text-right text-gray-600'>{}</td>"#, amount.map_or_else(|| "-".to_string(), |a| a.number.to_string()))
+                    })
+                    .collect();
+                html.push_str(&format!(r#"<tr class='border-b'><td class='px-4 py-2'>{}</td><td class='px-4 py-2 text-right'>{}</td>{}</tr>"#,
+                    entry.account, render_balance_amount(entry, &balance_report.currency, mode), other_cells));
+            }
+        }
+    }
+    html.push_str("</tbody>");
+
+    // Net worth in the operating currency, with what it is made of per currency
+    let by_currency: Vec<String> = balance_report.net_worth_by_currency.iter()
+        .map(|amount| amount.to_string())
+        .collect();
+    let net_worth = if mode == ConversionMode::Original && !by_currency.is_empty() {
+        by_currency.join(" · ")
+    } else if by_currency.len() > 1 {
+        format!(r#"{} {}<div class='text-xs font-normal text-gray-400'>{}</div>"#, balance_report.net_worth, balance_report.currency, by_currency.join(" · "))
+    } else {
+        format!("{} {}", balance_report.net_worth, balance_report.currency)
+    };
+    let other_totals: String = balance_report.operating_totals.iter()
+        .map(|t| format!(r#"<td class='px-4 py-2 text-right font-bold'>{} {}</td>"#, t.net_worth, t.currency))
+        .collect();
+    html.push_str(&format!(
+        r#"<tfoot><tr class='bg-gray-50'><td class='px-4 py-2 font-bold'>净资产</td><td class='px-4 py-2 text-right font-bold'>{}</td>{}</tr></tfoot>"#,
+        net_worth, other_totals
+    ));
+    html.push_str("</table></div>");
+    html
+}
+
+/// Balance of one account in the report currency; accounts holding several
+/// currencies list them below, and accounts without a price are flagged
+/// since they are left out of the totals. In original currencies the
+/// holdings are the balance
+fn render_balance_amount(entry: &BalanceReportEntry, report_currency: &str, mode: ConversionMode) -> String {
+    let holdings: Vec<String> = entry.holdings.iter()
+        .map(|amount| amount.to_string())
+        .collect();
+    if mode == ConversionMode::Original {
+        let balance = if holdings.is_empty() { format!("{} {}", entry.balance, entry.currency) } else { holdings.join(" · ") };
+        return format!(r#"<span class='text-right'><span class='font-medium'>{}</span></span>"#, balance);
+    }
+    let single_in_report_currency = entry.holdings.len() == 1 && entry.holdings[0].currency == report_currency;
+    let breakdown = if entry.unconverted || holdings.is_empty() || single_in_report_currency {
+        String::new()
+    } else {
+        format!(r#"<div class='text-xs text-gray-400'>{}</div>"#, holdings.join(" · "))
+    };
+    if entry.unconverted {
+        return format!(
+            r#"<span class='text-right'><span class='font-medium'>{}</span><span class='ml-2 text-xs bg-amber-100 text-amber-800 px-2 py-0.5 rounded' title='没有可用的价格，未计入合计'>未换算</span></span>"#,
+            holdings.join(" · ")
+        );
+    }
+    format!(r#"<span class='text-right'><span class='font-medium'>{} {}</span>{}</span>"#, entry.balance, entry.currency, breakdown)
+}
+
+/// Income/expense tab; `grouped` when the entries are rolled up by report
+/// group
+pub fn income_expense(income_expense: &IncomeExpenseReport, trends: &CategoryTrends, grouped: bool, mode: ConversionMode) -> String {
+    let path = if grouped { "/reports/income-expense?group_by=groups" } else { "/reports/income-expense" };
+    let mut html = conversion_toggle(path, mode, &income_expense.currency);
+    html.push_str(r#"<div class='grid grid-cols-1 md:grid-cols-2 gap-6'><div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4 text-green-600'>收入</h3><div class='space-y-2'>"#);
+
+    html.push_str(&render_income_expense_rows(&income_expense.income_entries, &trends.months, &trends.income, &income_expense.currency, mode, "text-green-600", INCOME_CHART_COLOR));
+    html.push_str(&render_currency_totals(&income_expense.income_by_currency, mode));
+    let income_totals: Vec<(String, String)> = income_expense.operating_totals.iter().map(|t| (t.total_income.clone(), t.currency.clone())).collect();
+    html.push_str(&render_operating_totals(&income_expense.total_income, &income_expense.currency, &income_totals));
+    html.push_str("</div></div><div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4 text-red-600'>支出</h3><div class='space-y-2'>");
+
+    html.push_str(&render_income_expense_rows(&income_expense.expense_entries, &trends.months, &trends.expenses, &income_expense.currency, mode, "text-red-600", EXPENSE_CHART_COLOR));
+    html.push_str(&render_currency_totals(&income_expense.expenses_by_currency, mode));
+    let expense_totals: Vec<(String, String)> = income_expense.operating_totals.iter().map(|t| (t.total_expenses.clone(), t.currency.clone())).collect();
+    html.push_str(&render_operating_totals(&income_expense.total_expenses, &income_expense.currency, &expense_totals));
+    html.push_str("</div></div></div>");
+    html.push_str(&render_transfers_section(&income_expense.transfers, &income_expense.currency));
+    html
+}
+
+/// Totals per currency below the rows, shown in original currencies where
+/// there is no single converted total
+fn render_currency_totals(totals: &[Amount], mode: ConversionMode) -> String {
+    if mode == ConversionMode::Converted || totals.is_empty() {
+        return String::new();
+    }
+    let totals: Vec<String> = totals.iter().map(|amount| amount.to_string()).collect();
+    format!(r#"<div class='flex justify-between pt-2 text-sm font-bold'><span>合计</span><span>{}</span></div>"#, totals.join(" · "))
+}
+
+/// Internal transfers, shown apart from income/expense since they don't change net worth
+fn render_transfers_section(transfers: &TransferSummary, currency: &str) -> String {
+    if transfers.count == 0 {
+        return String::new();
+    }
+    let volume: f64 = transfers.volume.parse().unwrap_or(0.0);
+    let mut html = format!(
+        r#"<div class='bg-white rounded-xl shadow-sm p-6 mt-6'>
+            <div class='flex items-center justify-between mb-4'>
+                <h3 class='text-lg font-bold text-blue-600'>内部转账</h3>
+                <span class='text-sm text-gray-500'>{} 笔，合计 <span class='font-medium text-gray-800'>{:.2} {}</span>，未计入收支</span>
+            </div>
+            <div class='space-y-2'>"#,
+        transfers.count, volume, currency
+    );
+    for entry in &transfers.entries {
+        let amount: f64 = entry.amount.parse().unwrap_or(0.0);
+        html.push_str(&format!(
+            r#"<div class='flex justify-between py-2 border-b'><span class='text-sm'>{} <span class='text-gray-400'>→</span> {} <span class='text-xs text-gray-400'>×{}</span></span><span class='font-medium text-blue-600'>{:.2} {}</span></div>"#,
+            entry.from_account, entry.to_account, entry.count, amount, currency
+        ));
+    }
+    html.push_str("</div></div>");
+    html
+}
+
+/// Mini-chart colors (Tailwind green-600 / red-600)
+const INCOME_CHART_COLOR: &str = "#16a34a";
+const EXPENSE_CHART_COLOR: &str = "#dc2626";
+
+/// Monthly bars of one entry, with the amounts in the tooltip; a flat line
+/// when it had no activity in those months
+fn trend_chart(months: &[String], values: Option<&Vec<f64>>, color: &str, currency: &str) -> String {
+    let values = values.cloned().unwrap_or_else(|| vec![0.0; months.len()]);
+    let tooltip: Vec<String> = months.iter().zip(&values)
+        .map(|(month, value)| html_escape(&format!("{}: {:.2} {}", month, value, currency)))
+        .collect();
+    format!(
+        "<span title='{}' class='shrink-0'>{}</span>",
+        tooltip.join("&#10;"),
+        beanweb_utils::svg::bars(&values, 48, 16, color)
+    )
+}
+
+/// One income/expense row; foreign-currency rows show the original amount,
+/// and rows without a price are flagged since they are left out of the totals.
+/// In original currencies every row is in its own currency
+fn render_income_expense_row(entry: &IncomeExpenseEntry, report_currency: &str, mode: ConversionMode, color: &str, chart: &str) -> String {
+    let detail = if entry.unconverted {
+        r#"<span class='ml-2 text-xs bg-amber-100 text-amber-800 px-2 py-0.5 rounded' title='没有可用的价格，未计入合计'>未换算</span>"#.to_string()
+    } else if entry.currency != report_currency && mode == ConversionMode::Converted {
+        format!(r#"<span class='ml-2 text-xs text-gray-400'>{} {}</span>"#, entry.original_amount, entry.currency)
+    } else {
+        String::new()
+    };
+    let currency = if entry.unconverted || mode == ConversionMode::Original { &entry.currency } else { report_currency };
+    format!(
+        r#"<div class='flex justify-between items-center gap-3 py-2 border-b'><a hx-get='/reports/category?category={}' hx-target='#reports-content' class='cursor-pointer hover:text-indigo-600'>{}</a><span class='flex items-center gap-3'>{}<span class='font-medium {}'>{} {}{}</span>{}</span></div>"#,
+        urlencoding::encode(&entry.account), entry.account, chart, color, entry.amount, currency, detail, render_operating_amounts(&entry.operating)
+    )
+}
+
+/// Amounts of a row in the other operating currencies, next to the main one
+fn render_operating_amounts(amounts: &[Amount]) -> String {
+    amounts.iter()
+        .map(|amount| format!(r#"<span class='w-28 text-right text-sm text-gray-500'>{}</span>"#, amount))
+        .collect()
+}
+
+/// Section total in the report currency and each other operating currency;
+/// only shown when there are other operating currencies
+fn render_operating_totals(total: &str, currency: &str, others: &[(String, String)]) -> String {
+    if others.is_empty() {
+        return String::new();
+    }
+    let others: String = others.iter()
+        .map(|(amount, currency)| format!(r#"<span class='w-28 text-right text-sm text-gray-500'>{} {}</span>"#, amount, currency))
+        .collect();
+    format!(r#"<div class='flex justify-between pt-2 font-bold'><span>合计</span><span class='flex items-center gap-3'><span>{} {}</span>{}</span></div>"#, total, currency, others)
+}
+
+/// Income or expense rows with their trend charts; the merged "Other" entry
+/// expands to its members, its chart summing theirs
+fn render_income_expense_rows(
+    entries: &[IncomeExpenseEntry],
+    months: &[String],
+    series: &std::collections::HashMap<String, Vec<f64>>,
+    report_currency: &str,
+    mode: ConversionMode,
+    color: &str,
+    chart_color: &str,
+) -> String {
+    let mut html = String::new();
+    for entry in entries {
+        if entry.members.is_empty() {
+            let chart = trend_chart(months, series.get(&entry.account), chart_color, report_currency);
+            html.push_str(&render_income_expense_row(entry, report_currency, mode, color, &chart));
+            continue;
+        }
+        let mut combined = vec![0.0; months.len()];
+        for values in entry.members.iter().filter_map(|m| series.get(&m.account)) {
+            combined.iter_mut().zip(values).for_each(|(sum, value)| *sum += value);
+        }
+        let chart = trend_chart(months, Some(&combined), chart_color, report_currency);
+        html.push_str(&format!(
+            r#"<details class='border-b'><summary class='flex justify-between items-center gap-3 py-2 cursor-pointer list-none'><span class='hover:text-indigo-600'>其他（{} 项）▸</span><span class='flex items-center gap-3'>{}<span class='font-medium {}'>{} {}</span>{}</span></summary><div class='pl-4 text-sm'>{}</div></details>"#,
+            entry.members.len(),
+            chart, color, entry.amount, report_currency, render_operating_amounts(&entry.operating),
+            render_income_expense_rows(&entry.members, months, series, report_currency, mode, color, chart_color)
+        ));
+    }
+    html
+}
+
+/// A transaction of the category detail, with what it booked to the
+/// category in the display sign
+#[derive(Debug, Clone)]
+pub struct CategoryTransaction {
+    pub date: String,
+    /// Payee, or the narration when there is none
+    pub title: String,
+    pub amount: f64,
+}
+
+/// Transactions of one category (newest first), their `total` and the
+/// category's year-long trend
+pub fn category(category: &str, transactions: &[CategoryTransaction], total: f64, trends: &CategoryTrends) -> String {
+    if category.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>请选择分类</p></div>"#.to_string();
+    }
+    let category_html = html_escape(category);
+
+    if transactions.is_empty() {
+        return format!(r#"<div class='text-center py-12 text-gray-500'><p>当前时间范围内暂无 {} 相关交易</p></div>"#, category_html);
+    }
+
+    // Year-long trend next to the title
+    let values = trends.series(category).unwrap_or_else(|| vec![0.0; trends.months.len()]);
+    let color = if category.starts_with("Income") { INCOME_CHART_COLOR } else { EXPENSE_CHART_COLOR };
+    let trend = format!(
+        "<span title='近 {} 个月（{} 至 {}）'>{}</span>",
+        trends.months.len(),
+        trends.months.first().map(|m| m.as_str()).unwrap_or(""),
+        trends.months.last().map(|m| m.as_str()).unwrap_or(""),
+        beanweb_utils::svg::sparkline(&values, 120, 28, color)
+    );
+
+    let mut html = format!(
+        r#"<div class='mb-4 flex items-center justify-between gap-4'>
+            <div><h3 class='text-lg font-bold'>{}</h3><p class='text-gray-500'>共 {} 笔交易，总额: {:.2}</p></div>
+            <div class='flex items-center gap-4'>
+                {}
+                <button hx-get='/reports/income-expense' hx-target='#reports-content' class='px-3 py-1.5 text-sm border rounded-lg hover:bg-gray-50'>返回收支报表</button>
+            </div>
+        </div>"#,
+        category_html, transactions.len(), total, trend
+    );
+
+    for tx in transactions.iter().take(20) {
+        html.push_str(&format!(
+            r#"<div class='border rounded-lg p-3 mb-2 hover:bg-gray-50'>
+                <div class='flex justify-between'>
+                    <span class='text-gray-500'>{}</span>
+                    <span class='font-medium {}'>{:.2}</span>
+                </div>
+                <div class='text-sm text-gray-700'>{}</div>
+            </div>"#,
+            tx.date,
+            if tx.amount < 0.0 { "text-red-600" } else { "text-green-600" },
+            tx.amount,
+            html_escape(&tx.title)
+        ));
+    }
+
+    if transactions.len() > 20 {
+        html.push_str(&format!(r#"<div class='text-center text-gray-500 mt-4'>显示前 20 笔，共 {} 笔</div>"#, transactions.len()));
+    }
+
+    html
+}
+
+/// Render net income allocation as a waterfall chart (inline SVG)
+/// First bar is net income, each following bar steps down by the amount an
+/// account absorbed, and the last bar is the unexplained residual. Changes
+/// in other currencies are listed but stay out of the chart.
+pub fn allocation(report: &AllocationReport) -> String {
+
+    if report.net_income.is_zero() && report.entries.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>当前时间范围内暂无收支数据</p></div>"#.to_string();
+    }
+
+    let to_f64 = |amount: Decimal| f64::try_from(amount).unwrap_or(0.0);
+    // (label, start, end, color)
+    let net_income = to_f64(report.net_income);
+    let mut bars: Vec<(String, f64, f64, &str)> = vec![("净收入".to_string(), 0.0, net_income, "#6366F1")];
+    let mut level = net_income;
+    for entry in report.entries.iter().filter(|e| e.currency == report.currency) {
+        let amount = to_f64(entry.amount);
+        let color = if amount >= 0.0 { "#10B981" } else { "#EF4444" };
+        bars.push((entry.account.clone(), level, level - amount, color));
+        level -= amount;
+    }
+    bars.push(("未解释差额".to_string(), level, 0.0, "#9CA3AF"));
+
+    let max = bars.iter().flat_map(|b| [b.1, b.2]).fold(0.0_f64, f64::max);
+    let min = bars.iter().flat_map(|b| [b.1, b.2]).fold(0.0_f64, f64::min);
+    let span = if (max - min).abs() < 0.001 { 1.0 } else { max - min };
+
+    let bar_width = 48.0;
+    let gap = 24.0;
+    let height = 240.0;
+    let width = bars.len() as f64 * (bar_width + gap) + gap;
+    let y_of = |v: f64| (max - v) / span * height;
+
+    let mut svg = format!(
+        r#"<svg viewBox='0 0 {:.0} {:.0}' class='w-full' style='max-height:320px'><line x1='0' x2='{:.0}' y1='{:.1}' y2='{:.1}' stroke='#D1D5DB'/>"#,
+        width, height + 60.0, width, y_of(0.0), y_of(0.0)
+    );
+    for (i, (label, start, end, color)) in bars.iter().enumerate() {
+        let x = gap + i as f64 * (bar_width + gap);
+        let top = y_of(start.max(*end));
+        let bar_height = (y_of(start.min(*end)) - top).max(1.0);
+        svg.push_str(&format!(
+            r#"<rect x='{:.1}' y='{:.1}' width='{:.0}' height='{:.1}' fill='{}' rx='3'><title>{}: {:.2}</title></rect><text x='{:.1}' y='{:.0}' font-size='10' text-anchor='end' fill='#6B7280' transform='rotate(-35 {:.1} {:.0})'>{}</text>"#,
+            x, top, bar_width, bar_height, color, label, end - start,
+            x + bar_width / 2.0, height + 14.0, x + bar_width / 2.0, height + 14.0,
+            label.rsplit(':').next().unwrap_or(label)
+        ));
+    }
+    svg.push_str("</svg>");
+
+    let mut rows = String::new();
+    for entry in &report.entries {
+        rows.push_str(&format!(
+            r#"<div class='flex justify-between py-2 border-b'><span>{}</span><span class='font-medium {}'>{:.2} {}</span></div>"#,
+            entry.account,
+            if entry.amount.is_sign_negative() { "text-red-600" } else { "text-green-600" },
+            entry.amount,
+            html_escape(&entry.currency)
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-6'>
+            <div class='grid grid-cols-3 gap-4'>
+                <div class='bg-indigo-50 rounded-lg p-4'><div class='text-sm text-gray-500'>净收入</div><div class='text-xl font-bold text-indigo-600'>{:.2}</div></div>
+                <div class='bg-green-50 rounded-lg p-4'><div class='text-sm text-gray-500'>已分配</div><div class='text-xl font-bold text-green-600'>{:.2}</div></div>
+                <div class='bg-gray-50 rounded-lg p-4'><div class='text-sm text-gray-500'>未解释差额</div><div class='text-xl font-bold text-gray-600'>{:.2}</div></div>
+            </div>
+            <div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4'>净收入去向</h3>{}</div>
+            <div class='bg-white rounded-xl shadow-sm p-6'><h3 class='text-lg font-bold mb-4'>账户变动</h3><div class='space-y-2'>{}</div></div>
+        </div>"#,
+        report.net_income, report.total_allocated, report.residual, svg, rows
+    )
+}
+
+/// Render holdings grouped by asset class, with commodity display names
+pub fn holdings(report: &HoldingsReport) -> String {
+
+    if report.groups.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>暂无持仓数据</p></div>"#.to_string();
+    }
+
+    let mut sections = String::new();
+    for group in &report.groups {
+        let class_label = if group.asset_class == UNCLASSIFIED {
+            "未分类".to_string()
+        } else {
+            html_escape(&group.asset_class)
+        };
+        let mut rows = String::new();
+        for holding in &group.holdings {
+            let name = if holding.label == holding.commodity {
+                holding.commodity.clone()
+            } else {
+                format!("{} <span class='text-xs text-gray-400'>{}</span>", html_escape(&holding.label), holding.commodity)
+            };
+            rows.push_str(&format!(
+                r#"<tr class='hover:bg-gray-50'>
+                    <td class='px-4 py-2'>{}</td>
+                    <td class='px-4 py-2 text-right'>{:.2}</td>
+                    <td class='px-4 py-2 text-right font-medium'>{}</td>
+                    <td class='px-4 py-2 text-sm text-gray-500'>{}</td>
+                </tr>"#,
+                name,
+                holding.units,
+                holding.value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "无价格".to_string()),
+                holding.accounts.join(", ")
+            ));
+        }
+        sections.push_str(&format!(
+            r#"<div class='bg-white rounded-xl shadow-sm p-6'>
+                <div class='flex justify-between items-center mb-2'>
+                    <h3 class='text-lg font-bold'>{}</h3>
+                    <span class='text-gray-600'>{:.2} {} · {:.1}%</span>
+                </div>
+                <div class='w-full bg-gray-100 rounded-full h-2 mb-4'><div class='bg-indigo-500 h-2 rounded-full' style='width: {:.1}%'></div></div>
+                <table class='w-full'>
+                    <thead class='bg-gray-50'><tr>
+                        <th class='px-4 py-2 text-left text-sm font-medium text-gray-600'>商品</th>
+                        <th class='px-4 py-2 text-right text-sm font-medium text-gray-600'>数量</th>
+                        <th class='px-4 py-2 text-right text-sm font-medium text-gray-600'>市值</th>
+                        <th class='px-4 py-2 text-left text-sm font-medium text-gray-600'>账户</th>
+                    </tr></thead>
+                    <tbody class='divide-y divide-gray-100'>{}</tbody>
+                </table>
+            </div>"#,
+            class_label, group.value, report.currency, group.percentage,
+            group.percentage.clamp(0.0, 100.0), rows
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-6'>
+            <div class='bg-indigo-50 rounded-lg p-4'><div class='text-sm text-gray-500'>持仓总市值</div><div class='text-xl font-bold text-indigo-600'>{:.2} {}</div></div>
+            {}
+        </div>"#,
+        report.total, report.currency, sections
+    )
+}
+
+/// Payees shown in the payee report
+const TOP_PAYEES: usize = 20;
+
+/// Biggest payees of the time range (biggest first), with their share and
+/// monthly trend
+pub fn payees(payees: &[PayeeSummary], trends: &PayeeTrends) -> String {
+    if payees.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>当前时间范围内暂无商户支出</p></div>"#.to_string();
+    }
+    let mut rows = String::new();
+    for payee in payees.iter().take(TOP_PAYEES) {
+        let unconverted = if payee.unconverted > 0 {
+            format!(r#" <span class='text-xs bg-amber-100 text-amber-800 px-2 py-0.5 rounded' title='没有可用的价格，未计入金额'>{} 笔未换算</span>"#, payee.unconverted)
+        } else {
+            String::new()
+        };
+        rows.push_str(&format!(
+            r#"<tr class='hover:bg-gray-50'>
+                <td class='px-4 py-2'><a href='/transactions?q={}' class='hover:text-indigo-600'>{}</a>{}</td>
+                <td class='px-4 py-2 text-right'>{}</td>
+                <td class='px-4 py-2 text-right text-sm text-gray-500'>{}</td>
+                <td class='px-4 py-2'>{}</td>
+                <td class='px-4 py-2 text-right font-medium'>{} {} <span class='text-xs text-gray-400'>{:.1}%</span></td>
+            </tr>"#,
+            urlencoding::encode(&payee.payee),
+            html_escape(&payee.payee),
+            unconverted,
+            payee.count,
+            payee.last_date,
+            trend_chart(&trends.months, trends.payees.get(&payee.payee), EXPENSE_CHART_COLOR, &payee.currency),
+            payee.amount,
+            payee.currency,
+            payee.percentage,
+        ));
+    }
+    let more = if payees.len() > TOP_PAYEES {
+        format!("<p class='text-sm text-gray-500 mt-3'>另有 {} 个商户，完整列表见 /api/payees</p>", payees.len() - TOP_PAYEES)
+    } else {
+        String::new()
+    };
+    format!(
+        r#"<div class='overflow-x-auto'><table class='w-full'>
+            <thead class='bg-gray-50'><tr>
+                <th class='px-4 py-2 text-left text-sm font-medium text-gray-600'>商户</th>
+                <th class='px-4 py-2 text-right text-sm font-medium text-gray-600'>交易</th>
+                <th class='px-4 py-2 text-right text-sm font-medium text-gray-600'>最近</th>
+                <th class='px-4 py-2 text-left text-sm font-medium text-gray-600'>近 {} 个月</th>
+                <th class='px-4 py-2 text-right text-sm font-medium text-gray-600'>支出</th>
+            </tr></thead>
+            <tbody class='divide-y divide-gray-100'>{}</tbody>
+        </table></div>{}"#,
+        trends.months.len(), rows, more
+    )
+}
+
+/// Monthly income and expense bars of one year, with a table of the figures
+/// Bars show magnitudes, so the income sign convention doesn't flip them
+pub fn monthly_summary(report: &MonthlySummaryReport) -> String {
+    let values: Vec<(f64, f64)> = report.summaries.iter()
+        .map(|m| (m.income.parse::<f64>().unwrap_or(0.0).abs(), m.expenses.parse::<f64>().unwrap_or(0.0).abs()))
+        .collect();
+    let max = values.iter().flat_map(|(income, expenses)| [*income, *expenses]).fold(0.0_f64, f64::max);
+    let scale = if max < 0.001 { 1.0 } else { max };
+
+    let bar_width = 14.0;
+    let gap = 16.0;
+    let height = 200.0;
+    let width = values.len() as f64 * (2.0 * bar_width + gap) + gap;
+    let mut svg = format!(
+        r#"<svg viewBox='0 0 {:.0} {:.0}' class='w-full' style='max-height:280px'><line x1='0' x2='{:.0}' y1='{:.0}' y2='{:.0}' stroke='#D1D5DB'/>"#,
+        width, height + 24.0, width, height, height
+    );
+    for (i, (summary, (income, expenses))) in report.summaries.iter().zip(&values).enumerate() {
+        let x = gap + i as f64 * (2.0 * bar_width + gap);
+        for (offset, value, color, label) in [(0.0, income, INCOME_CHART_COLOR, "收入"), (bar_width, expenses, EXPENSE_CHART_COLOR, "支出")] {
+            let bar_height = value / scale * height;
+            svg.push_str(&format!(
+                r#"<rect x='{:.1}' y='{:.1}' width='{:.0}' height='{:.1}' fill='{}' rx='2'><title>{} {}: {:.2} {}</title></rect>"#,
+                x + offset, height - bar_height, bar_width, bar_height, color, summary.month, label, value, report.currency
+            ));
+        }
+        svg.push_str(&format!(
+            r#"<text x='{:.1}' y='{:.0}' font-size='10' text-anchor='middle' fill='#6B7280'>{}</text>"#,
+            x + bar_width, height + 16.0, summary.month.get(5..).unwrap_or(&summary.month)
+        ));
+    }
+    svg.push_str("</svg>");
+
+    let mut rows = String::new();
+    for summary in &report.summaries {
+        let net: f64 = summary.net.parse().unwrap_or(0.0);
+        rows.push_str(&format!(
+            r#"<tr class='border-b'><td class='px-4 py-2'>{}</td><td class='px-4 py-2 text-right text-green-600'>{}</td><td class='px-4 py-2 text-right text-red-600'>{}</td><td class='px-4 py-2 text-right font-medium {}'>{}</td></tr>"#,
+            summary.month, summary.income, summary.expenses,
+            if net >= 0.0 { "text-indigo-600" } else { "text-red-600" }, summary.net
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-6'>
+            <div class='flex items-center justify-between'>
+                <button hx-get='/reports/monthly?year={prev}' hx-target='#reports-content' class='px-3 py-1 border rounded-lg hover:bg-gray-50'>‹ {prev}</button>
+                <h3 class='text-lg font-bold'>{year} 年月度收支</h3>
+                <button hx-get='/reports/monthly?year={next}' hx-target='#reports-content' class='px-3 py-1 border rounded-lg hover:bg-gray-50'>{next} ›</button>
+            </div>
+            <div class='grid grid-cols-3 gap-4'>
+                <div class='bg-green-50 rounded-lg p-4'><div class='text-sm text-gray-500'>收入</div><div class='text-xl font-bold text-green-600'>{income} {currency}</div></div>
+                <div class='bg-red-50 rounded-lg p-4'><div class='text-sm text-gray-500'>支出</div><div class='text-xl font-bold text-red-600'>{expenses} {currency}</div></div>
+                <div class='bg-indigo-50 rounded-lg p-4'><div class='text-sm text-gray-500'>净收入</div><div class='text-xl font-bold text-indigo-600'>{net} {currency}</div></div>
+            </div>
+            {svg}
+            <div class='overflow-x-auto'><table class='w-full'><thead class='bg-gray-50'><tr><th class='px-4 py-2 text-left'>月份</th><th class='px-4 py-2 text-right'>收入</th><th class='px-4 py-2 text-right'>支出</th><th class='px-4 py-2 text-right'>净收入</th></tr></thead><tbody>{rows}</tbody></table></div>
+        </div>"#,
+        prev = report.year - 1,
+        next = report.year + 1,
+        year = report.year,
+        income = report.total_income,
+        expenses = report.total_expenses,
+        net = report.total_net,
+        currency = report.currency,
+        svg = svg,
+        rows = rows,
+    )
+}
+
+/// One finished year of a report job, for [`job_progress`]
+pub struct JobYear<'a> {
+    pub year: i32,
+    pub income: &'a str,
+    pub expenses: &'a str,
+    pub net: &'a str,
+    pub currency: &'a str,
+}
+
+/// Report job in progress: a spinner with the years built so far, polling
+/// `/reports/jobs/{id}` until the job is done
+pub fn job_progress(id: &str, done: usize, total: usize, years: &[JobYear]) -> String {
+    let mut rows = String::new();
+    for year in years {
+        rows.push_str(&format!(
+            "<tr class='border-t'><td class='py-1'>{}</td><td class='py-1 text-right text-green-600'>{}</td><td class='py-1 text-right text-red-600'>{}</td><td class='py-1 text-right font-medium'>{} {}</td></tr>",
+            year.year, year.income, year.expenses, year.net, year.currency
+        ));
+    }
+    let table = if rows.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<table class='w-full text-sm mt-4'><thead><tr class='text-gray-500'><th class='text-left'>年份</th><th class='text-right'>收入</th><th class='text-right'>支出</th><th class='text-right'>净收入</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    };
+    format!(
+        r#"<div class='report-job bg-white rounded-xl shadow-sm p-6' hx-get='/reports/jobs/{}' hx-trigger='load delay:500ms' hx-swap='outerHTML'>
+            <div class='flex items-center gap-3 text-gray-600'>
+                <div class='animate-spin h-5 w-5 border-2 border-blue-500 border-t-transparent rounded-full'></div>
+                <span>正在生成报表（{}/{}）…</span>
+            </div>{}
+        </div>"#,
+        id, done, total, table
+    )
+}
+
+/// Report job that failed, with its error
+pub fn job_failed(error: &str) -> String {
+    format!(
+        "<div class='bg-red-50 border border-red-200 text-red-700 rounded-xl p-4'>报表生成失败：{}</div>",
+        html_escape(error)
+    )
+}
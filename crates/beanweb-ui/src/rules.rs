@@ -0,0 +1,87 @@
+//! Categorization rules page and the result of the rule tester
+
+use crate::html_escape;
+use beanweb_core::rules::RULES_FILE;
+use beanweb_core::CategoryRule;
+
+/// Conditions of `rule`, one per line
+fn conditions(rule: &CategoryRule) -> String {
+    let mut lines = Vec::new();
+    if let Some(payee) = &rule.payee {
+        lines.push(format!("交易对象 ~ {}", payee));
+    }
+    if let Some(narration) = &rule.narration {
+        lines.push(format!("摘要 ~ {}", narration));
+    }
+    match (rule.min_amount, rule.max_amount) {
+        (Some(min), Some(max)) => lines.push(format!("金额 {} – {}", min, max)),
+        (Some(min), None) => lines.push(format!("金额 ≥ {}", min)),
+        (None, Some(max)) => lines.push(format!("金额 ≤ {}", max)),
+        (None, None) => {}
+    }
+    for (key, pattern) in &rule.metadata {
+        lines.push(format!("{} ~ {}", key, pattern));
+    }
+    lines.iter().map(|l| html_escape(l)).collect::<Vec<_>>().join("<br>")
+}
+
+fn tags(rule: &CategoryRule) -> String {
+    rule.tags.iter().map(|t| format!("#{}", html_escape(t))).collect::<Vec<_>>().join(" ")
+}
+
+/// Rules page content: the rules, or why they couldn't be read, and a form
+/// to try them on sample text
+pub fn page(rules: Result<&[CategoryRule], &str>) -> String {
+    let list = match rules {
+        Ok([]) => format!(
+            "<p class='text-sm text-gray-500'>还没有规则。在数据目录的 <code>{}</code> 中添加，例如：</p><pre class='mt-2 p-3 bg-gray-50 rounded text-sm'>- name: Coffee\n  payee: starbucks|costa\n  max_amount: 100\n  account: Expenses:Food:Coffee\n  tags: [coffee]</pre>",
+            RULES_FILE
+        ),
+        Ok(rules) => {
+            let rows: String = rules.iter()
+                .map(|rule| format!(
+                    "<tr class='border-t align-top'><td class='py-2 pr-4 font-medium'>{}</td><td class='py-2 pr-4 text-sm font-mono text-gray-600'>{}</td><td class='py-2 pr-4 text-sm'>{}</td><td class='py-2 text-sm text-indigo-600'>{}</td></tr>",
+                    html_escape(&rule.name),
+                    conditions(rule),
+                    html_escape(&rule.account),
+                    tags(rule)
+                ))
+                .collect();
+            format!(
+                "<table class='w-full'><thead><tr class='text-left text-sm text-gray-500'><th class='pb-2'>名称</th><th class='pb-2'>条件</th><th class='pb-2'>账户</th><th class='pb-2'>标签</th></tr></thead><tbody>{}</tbody></table><p class='mt-3 text-xs text-gray-400'>按顺序匹配，第一条符合的规则生效；编辑 <code>{}</code> 修改</p>",
+                rows, RULES_FILE
+            )
+        }
+        Err(error) => format!("<div class='bg-red-50 border border-red-200 text-red-700 rounded-lg p-4'>{}</div>", html_escape(error)),
+    };
+
+    format!(
+        r#"<div class='mb-6'><a href='/settings' class='text-sm text-gray-500 hover:underline'>← 设置</a><h2 class='text-2xl font-bold'>分类规则</h2></div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>{}</div>
+        <div class='bg-white rounded-xl shadow-sm p-6'>
+            <h3 class='text-lg font-semibold mb-4'>测试规则</h3>
+            <form hx-get='/settings/rules/test' hx-target='#rule-test-result' class='grid grid-cols-1 md:grid-cols-3 gap-4'>
+                <input type='text' name='payee' placeholder='交易对象' class='px-3 py-2 border rounded-lg'>
+                <input type='text' name='narration' placeholder='摘要' class='px-3 py-2 border rounded-lg'>
+                <input type='text' name='amount' placeholder='金额' class='px-3 py-2 border rounded-lg'>
+                <textarea name='metadata' placeholder='元数据，每行 key: value' class='md:col-span-3 px-3 py-2 border rounded-lg font-mono text-sm' rows='2'></textarea>
+                <div><button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>测试</button></div>
+            </form>
+            <div id='rule-test-result' class='mt-4'></div>
+        </div>"#,
+        list
+    )
+}
+
+/// Rule tester result: the rule the sample matched and what it would set
+pub fn test_result(rule: Option<&CategoryRule>) -> String {
+    match rule {
+        Some(rule) => format!(
+            "<div class='bg-green-50 border border-green-200 rounded-lg p-4 text-sm'>匹配规则 <span class='font-medium'>{}</span> → <span class='font-mono'>{}</span> {}</div>",
+            html_escape(&rule.name),
+            html_escape(&rule.account),
+            tags(rule)
+        ),
+        None => "<div class='bg-gray-50 border rounded-lg p-4 text-sm text-gray-600'>没有匹配的规则</div>".to_string(),
+    }
+}
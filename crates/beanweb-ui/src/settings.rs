@@ -0,0 +1,70 @@
+//! Settings page
+
+use crate::html_escape;
+use beanweb_config::Config;
+
+/// Settings page content: the server, data, feature and pagination settings
+/// of `config`, with the sessions and two-factor sections loaded over HTMX
+pub fn page(config: &Config) -> String {
+    let enabled = |on: bool| if on { "启用" } else { "禁用" };
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>设置</h2></div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>服务器设置</h3>
+            <div class='grid grid-cols-2 gap-4 mb-4'>
+                <div><p class='text-sm text-gray-500'>主机地址</p><p class='font-medium'>{}</p></div>
+                <div><p class='text-sm text-gray-500'>端口</p><p class='font-medium'>{}</p></div>
+                <div><p class='text-sm text-gray-500'>认证</p><p class='font-medium'>{}</p></div>
+            </div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>数据设置</h3>
+            <div class='grid grid-cols-2 gap-4 mb-4'>
+                <div><p class='text-sm text-gray-500'>数据目录</p><p class='font-medium'>{}</p></div>
+                <div><p class='text-sm text-gray-500'>主文件</p><p class='font-medium'>{}</p></div>
+            </div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>功能开关</h3>
+            <div class='grid grid-cols-2 gap-4 mb-4'>
+                <div><p class='text-sm text-gray-500'>预算管理</p><p class='font-medium'>{}</p></div>
+                <div><p class='text-sm text-gray-500'>时间提取</p><p class='font-medium'>{}</p></div>
+            </div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>分页设置</h3>
+            <div><p class='text-sm text-gray-500'>每页记录数</p><p class='font-medium'>{}</p></div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <div class='flex items-center justify-between'>
+                <div><h3 class='text-lg font-semibold'>分类规则</h3><p class='text-sm text-gray-500'>按交易对象、金额和元数据自动选择账户和标签</p></div>
+                <a href='/settings/rules' class='text-indigo-600 hover:underline'>查看和测试</a>
+            </div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>导入与工具</h3>
+            <ul class='space-y-2'>
+                <li><a href='/tools/bootstrap' class='text-indigo-600 hover:underline'>账户模板</a><span class='text-sm text-gray-500'> · 为新账本按模板开立一整套账户</span></li>
+                <li><a href='/tools/accounts' class='text-indigo-600 hover:underline'>导入账户</a><span class='text-sm text-gray-500'> · 从 CSV 账户列表开立账户</span></li>
+                <li><a href='/tools/opening-balances' class='text-indigo-600 hover:underline'>期初余额</a><span class='text-sm text-gray-500'> · 记录开始记账时的账户余额</span></li>
+                <li><a href='/tools/balances' class='text-indigo-600 hover:underline'>导入余额断言</a><span class='text-sm text-gray-500'> · 从 CSV 导入历史余额</span></li>
+            </ul>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6 mb-6'>
+            <h3 class='text-lg font-semibold mb-4'>登录会话</h3>
+            <div id='sessions-list' hx-get='/settings/sessions' hx-trigger='load'></div>
+        </div>
+        <div class='bg-white rounded-xl shadow-sm p-6'>
+            <h3 class='text-lg font-semibold mb-4'>两步验证</h3>
+            <div id='two-factor' hx-get='/settings/two-factor' hx-trigger='load'></div>
+        </div>"#,
+        html_escape(&config.server.host),
+        config.server.port,
+        if config.server.auth.is_some() { "已设置" } else { "未设置" },
+        html_escape(&config.data.path.display().to_string()),
+        html_escape(&config.data.main_file),
+        enabled(config.features.budget_enable),
+        enabled(config.features.time_extraction),
+        config.pagination.records_per_page
+    )
+}
@@ -0,0 +1,70 @@
+//! Review of postings on suspense accounts
+
+use crate::{attr_escape, html_escape};
+use beanweb_core::SuspensePosting;
+
+/// Nav badge with the number of transactions to recategorize; empty at zero
+pub fn badge(count: usize) -> String {
+    match count {
+        0 => String::new(),
+        count => format!(
+            "<a href='/transactions/suspense' title='待分类的交易' class='px-1.5 text-xs bg-amber-100 text-amber-700 rounded-full'>{}</a>",
+            count
+        ),
+    }
+}
+
+/// Review page; the list loads once shown
+pub fn page() -> String {
+    r#"<div class='mb-6'>
+            <h2 class='text-2xl font-bold'>待分类</h2>
+            <p class='text-gray-500 mt-1'>记在占位账户（<code>checks.suspense_accounts</code>）上的分录，选择实际账户后改写原交易</p>
+        </div>
+        <div id='suspense-list' hx-get='/transactions/suspense/list' hx-trigger='load, ledger-reloaded from:body' class='bg-white rounded-xl shadow-sm p-6'>
+            <p class='text-gray-500 text-center'>加载中...</p>
+        </div>"#.to_string()
+}
+
+/// One row per posting, each with an account input and a recategorize button
+pub fn list(postings: &[SuspensePosting], targets: &[String], error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<div class='mb-4 text-sm text-red-600'>{}</div>", html_escape(e)))
+        .unwrap_or_default();
+    if postings.is_empty() {
+        return format!("{}<div class='text-center py-12 text-gray-500'><p>没有待分类的分录</p></div>", error);
+    }
+    let options: String = targets.iter().map(|a| format!("<option value='{}'>", attr_escape(a))).collect();
+    let rows: String = postings.iter()
+        .map(|p| {
+            let description = [p.payee.as_str(), p.narration.as_str()].iter()
+                .filter(|s| !s.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" · ");
+            format!(
+                r#"<li class='flex items-center justify-between gap-4 py-3'>
+                    <div class='min-w-0'>
+                        <p class='font-medium truncate'>{description}</p>
+                        <p class='text-sm text-gray-500'>{date} · <span class='font-mono'>{account}</span> · {amount:.2} {currency}</p>
+                    </div>
+                    <form hx-post='/transactions/suspense/recategorize' hx-target='#suspense-list' class='flex items-center gap-2 flex-shrink-0'>
+                        <input type='hidden' name='id' value='{id}'>
+                        <input type='hidden' name='from' value='{account}'>
+                        <input type='text' name='to' list='suspense-targets' required placeholder='实际账户' class='w-64 px-2 py-1 text-sm border rounded'>
+                        <button class='px-3 py-1 text-sm bg-indigo-600 text-white rounded hover:bg-indigo-700'>重新分类</button>
+                    </form>
+                </li>"#,
+                description = html_escape(&description),
+                date = html_escape(&p.date),
+                account = attr_escape(&p.account),
+                amount = p.amount,
+                currency = html_escape(&p.currency),
+                id = attr_escape(&p.transaction_id),
+            )
+        })
+        .collect();
+    format!(
+        "{}<ul class='divide-y'>{}</ul><datalist id='suspense-targets'>{}</datalist>",
+        error, rows, options
+    )
+}
@@ -0,0 +1,60 @@
+//! Tags page: transactions and amounts per tag
+
+use crate::html_escape;
+use beanweb_core::{Amount, TagSummary};
+
+/// Tags page; the table loads once shown
+pub fn page() -> String {
+    r#"<div class='mb-6'>
+            <h2 class='text-2xl font-bold'>标签</h2>
+            <p class='text-gray-500 mt-1'>交易上的 <code>#标签</code>，点击查看带该标签的流水</p>
+        </div>
+        <div id='tags-content' hx-get='/tags/list' hx-trigger='load, ledger-reloaded from:body' class='bg-white rounded-xl shadow-sm p-6'>
+            <p class='text-gray-500 text-center'>加载中...</p>
+        </div>"#.to_string()
+}
+
+/// Tag table, or an example when no transaction has a tag
+pub fn list(tags: &[TagSummary]) -> String {
+    if tags.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>暂无标签</p><p class='text-sm mt-1 font-mono'>2024-03-01 * "Airline" "Flight" #trip</p></div>"#.to_string();
+    }
+    format!(
+        r#"<table class='w-full'>
+            <thead class='bg-gray-50'><tr>
+                <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>标签</th>
+                <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>交易</th>
+                <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>支出</th>
+                <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>收入</th>
+            </tr></thead>
+            <tbody class='divide-y divide-gray-100'>{}</tbody>
+        </table>"#,
+        tags.iter().map(row).collect::<String>()
+    )
+}
+
+/// Amounts per currency, one per line
+fn amounts(amounts: &[Amount]) -> String {
+    if amounts.is_empty() {
+        return "<span class='text-gray-400'>-</span>".to_string();
+    }
+    amounts.iter()
+        .map(|a| format!("<div>{:.2} {}</div>", a.number, html_escape(&a.currency)))
+        .collect()
+}
+
+fn row(tag: &TagSummary) -> String {
+    format!(
+        r#"<tr>
+            <td class='px-3 py-2'><a href='/transactions?tag={}' class='px-2 py-0.5 bg-indigo-50 text-indigo-700 rounded hover:bg-indigo-100'>#{}</a></td>
+            <td class='px-3 py-2 text-right'>{}</td>
+            <td class='px-3 py-2 text-right text-red-600'>{}</td>
+            <td class='px-3 py-2 text-right text-green-600'>{}</td>
+        </tr>"#,
+        urlencoding::encode(&tag.tag),
+        html_escape(&tag.tag),
+        tag.count,
+        amounts(&tag.expenses),
+        amounts(&tag.income),
+    )
+}
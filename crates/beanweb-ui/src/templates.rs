@@ -0,0 +1,32 @@
+//! Transaction template picker of the create-transaction form
+
+use crate::{attr_escape, html_escape};
+use beanweb_core::TransactionTemplate;
+
+/// Template picker; `applyTemplate` in the form's script fills the fields
+/// from the chosen option. Empty without templates
+pub fn picker(templates: &[TransactionTemplate]) -> String {
+    if templates.is_empty() {
+        return String::new();
+    }
+    let options: String = templates.iter()
+        .map(|t| {
+            let json = serde_json::to_string(t).unwrap_or_default();
+            format!(
+                "<option value='{}' data-template='{}'>{}</option>",
+                attr_escape(&t.name),
+                attr_escape(&json),
+                html_escape(&t.name)
+            )
+        })
+        .collect();
+    format!(
+        r#"<div>
+                            <label class='block text-sm font-medium text-gray-700 mb-1'>模板</label>
+                            <select id='template-picker' onchange='applyTemplate(this)' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
+                                <option value=''>不使用模板</option>{}
+                            </select>
+                        </div>"#,
+        options
+    )
+}
@@ -0,0 +1,57 @@
+//! Timeline of `event` directives
+
+use crate::html_escape;
+use beanweb_core::LedgerEvent;
+
+/// Events newest first, grouped under their year
+pub fn events(events: &[LedgerEvent]) -> String {
+    if events.is_empty() {
+        return "<p class='text-gray-400 text-center py-8'>暂无事件</p>".to_string();
+    }
+    let mut html = String::new();
+    let mut year = "";
+    for event in events {
+        let event_year = event.date.get(..4).unwrap_or("");
+        if event_year != year {
+            if !year.is_empty() {
+                html.push_str("</ol>");
+            }
+            year = event_year;
+            html.push_str(&format!("<h3 class='text-lg font-semibold mt-4 mb-2'>{}</h3><ol class='border-l-2 border-indigo-100 ml-2'>", year));
+        }
+        html.push_str(&format!(
+            "<li class='relative pl-6 py-2'><span class='absolute -left-1.5 top-4 w-3 h-3 rounded-full bg-indigo-400'></span><span class='text-sm text-gray-500 mr-3'>{}</span><a href='/timeline?type={}' class='px-2 py-0.5 bg-indigo-50 text-indigo-700 rounded text-xs mr-2'>{}</a>{}</li>",
+            event.date,
+            urlencoding::encode(&event.event_type),
+            html_escape(&event.event_type),
+            html_escape(&event.description)
+        ));
+    }
+    html.push_str("</ol>");
+    html
+}
+
+/// Timeline page content: a chip per event type, `selected` highlighted,
+/// above the events
+pub fn page(events: &[LedgerEvent], event_types: &[String], selected: Option<&str>) -> String {
+    let mut filters = format!(
+        "<a href='/timeline' class='px-3 py-1 rounded-full text-sm {}'>全部</a>",
+        if selected.is_none() { "bg-indigo-600 text-white" } else { "bg-gray-100 text-gray-700" }
+    );
+    for t in event_types {
+        let active = selected == Some(t.as_str());
+        filters.push_str(&format!(
+            "<a href='/timeline?type={}' class='px-3 py-1 rounded-full text-sm {}'>{}</a>",
+            urlencoding::encode(t),
+            if active { "bg-indigo-600 text-white" } else { "bg-gray-100 text-gray-700" },
+            html_escape(t)
+        ));
+    }
+
+    format!(
+        "<div class='mb-6'><h2 class='text-2xl font-bold'>时间线</h2><p class='text-gray-500 mt-1'>账本中的 event 记录，共 {} 条</p></div><div class='mb-4 flex flex-wrap gap-2'>{}</div><div id='timeline' class='bg-white rounded-xl shadow-sm p-6'>{}</div>",
+        events.len(),
+        filters,
+        self::events(events)
+    )
+}
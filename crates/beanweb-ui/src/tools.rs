@@ -0,0 +1,402 @@
+//! Tool pages: the import forms, previews of the imports and the opening
+//! balances
+
+use crate::{attr_escape, html_escape};
+use beanweb_core::account_import::{AccountImportPreview, AccountRowStatus};
+use beanweb_core::bootstrap::AccountTemplate;
+use beanweb_core::balance_import::{BalanceImportPreview, BalanceRowStatus};
+use beanweb_core::opening::OpeningBalancesPlan;
+use beanweb_core::transaction_import::{TransactionImportPreview, TransactionRowStatus};
+
+/// Script of the tool forms. The tool endpoints take JSON, so the form is
+/// posted as an object of its named fields (checkboxes as booleans, empty
+/// fields left out, unless a page passes its own `build`): `toolPreview`
+/// shows the returned HTML in `target`, `toolImport` the message of the
+/// JSON API's outcome.
+const FORM_SCRIPT: &str = r#"<script>
+function toolBody(form) {
+    const body = {};
+    for (const field of form.elements) {
+        if (!field.name) continue;
+        if (field.type === 'checkbox') body[field.name] = field.checked;
+        else if (field.value.trim() !== '') body[field.name] = field.value;
+    }
+    return body;
+}
+function toolPost(url, body) {
+    return fetch(url, {method: 'POST', headers: {'Content-Type': 'application/json'}, body: JSON.stringify(body)});
+}
+function toolPreview(form, url, target, build) {
+    toolPost(url, (build || toolBody)(form)).then(r => r.text()).then(html => { document.getElementById(target).innerHTML = html; });
+}
+function toolImport(form, url, target, build) {
+    toolPost(url, (build || toolBody)(form)).then(r => r.json()).then(outcome => {
+        const el = document.getElementById(target);
+        el.textContent = outcome.message;
+        el.className = outcome.success ? 'text-green-700' : 'text-red-600';
+    });
+}
+function toolLoadFile(input, target) {
+    const file = input.files[0];
+    if (file) file.text().then(text => { document.getElementById(target).value = text; });
+}
+</script>"#;
+
+/// Form of a CSV import: the CSV pasted or loaded from a file, `options`
+/// (extra fields), the target file, and buttons to preview through `preview`
+/// and write through `import`
+fn csv_import_form(id: &str, columns: &str, options: &str, default_file: &str, preview: &str, import: &str) -> String {
+    format!(
+        r#"<form id='{id}' class='bg-white rounded-xl shadow-sm p-6 space-y-4' onsubmit='return false'>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>CSV（{columns}）</label>
+                <input type='file' accept='.csv,text/csv' onchange="toolLoadFile(this, '{id}-csv')" class='mb-2 text-sm'>
+                <textarea id='{id}-csv' name='csv' rows='8' class='w-full px-3 py-2 border rounded-lg font-mono text-sm'></textarea>
+            </div>
+            {options}
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>写入文件</label>
+                <input name='file' value='{file}' class='w-full px-3 py-2 border rounded-lg font-mono text-sm'>
+            </div>
+            <div class='flex items-center gap-3'>
+                <button type='button' onclick="toolPreview(this.form, '{preview}', '{id}-preview')" class='px-4 py-2 border rounded-lg hover:bg-gray-50'>预览</button>
+                <button type='button' onclick="toolImport(this.form, '{import}', '{id}-result')" class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>导入</button>
+                <span id='{id}-result'></span>
+            </div>
+        </form>
+        <div id='{id}-preview' class='bg-white rounded-xl shadow-sm p-6 mt-6'></div>
+        {script}"#,
+        id = id, columns = html_escape(columns), options = options, file = attr_escape(default_file),
+        preview = preview, import = import, script = FORM_SCRIPT
+    )
+}
+
+fn checkbox(name: &str, label: &str, checked: bool) -> String {
+    format!(
+        "<label class='flex items-center gap-2 text-sm'><input type='checkbox' name='{}'{}> {}</label>",
+        name, if checked { " checked" } else { "" }, html_escape(label)
+    )
+}
+
+/// Account bootstrap page: one of the shipped templates (its accounts
+/// listed under it), the currency and the opening date
+pub fn bootstrap_accounts_page(templates: &[AccountTemplate], default_currency: &str, today: &str) -> String {
+    let choices: String = templates.iter().enumerate().map(|(i, template)| format!(
+        r#"<label class='block border rounded-lg p-3'>
+                    <span class='flex items-center gap-2'><input type='radio' name='template' value='{}'{}> <span class='font-medium'>{}</span></span>
+                    <details class='mt-1 text-sm text-gray-500'><summary>{} 个账户</summary><div class='font-mono'>{}</div></details>
+                </label>"#,
+        attr_escape(&template.name), if i == 0 { " checked" } else { "" }, html_escape(&template.label),
+        template.accounts.len(),
+        template.accounts.iter().map(|a| html_escape(a)).collect::<Vec<_>>().join("<br>")
+    )).collect();
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>账户模板</h2>
+            <p class='text-sm text-gray-500'>为新账本按模板批量开立账户，写入 accounts.bean 并在主文件中 include</p></div>
+        <form id='bootstrap-accounts' class='bg-white rounded-xl shadow-sm p-6 space-y-4' onsubmit='return false'>
+            <div class='space-y-2'>{choices}</div>
+            <div class='flex gap-4'>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>货币</label>
+                    <input name='currency' value='{currency}' class='px-3 py-2 border rounded-lg'>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>开立日期</label>
+                    <input type='date' name='start_date' value='{today}' class='px-3 py-2 border rounded-lg'>
+                </div>
+            </div>
+            <div class='flex items-center gap-3'>
+                <button type='button' onclick="toolImport(this.form, '/api/tools/bootstrap-accounts', 'bootstrap-accounts-result', bootstrapBody)" class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>开立账户</button>
+                <span id='bootstrap-accounts-result'></span>
+            </div>
+        </form>
+        {script}
+        <script>
+        function bootstrapBody(form) {{
+            const body = toolBody(form);
+            body.template = form.querySelector("[name='template']:checked").value;
+            return body;
+        }}
+        </script>"#,
+        choices = choices, currency = attr_escape(default_currency), today = attr_escape(today), script = FORM_SCRIPT
+    )
+}
+
+/// Chart of accounts import page
+pub fn account_import_page(default_file: &str) -> String {
+    let options = "<div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>默认开立日期</label>
+                <input type='date' name='start_date' class='px-3 py-2 border rounded-lg'>
+                <p class='text-xs text-gray-500 mt-1'>用于没有日期的行，留空为今天</p>
+            </div>";
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>导入账户</h2>
+            <p class='text-sm text-gray-500'>从其他记账工具导出的账户列表，每行写成一条 open 指令</p></div>
+        {}"#,
+        csv_import_form("account-import", "账户,类型,货币,日期", options, default_file, "/tools/accounts/preview", "/api/tools/accounts/import")
+    )
+}
+
+/// Balance assertion import page; failing rows are left out unless the
+/// box is cleared
+pub fn balance_import_page(default_file: &str) -> String {
+    let options = format!(
+        "<div class='space-y-2'>{}{}</div>",
+        checkbox("end_of_day", "余额为当日结束时的金额（断言写在次日）", false),
+        checkbox("skip_failing", "跳过会失败的断言", true)
+    );
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>导入余额断言</h2>
+            <p class='text-sm text-gray-500'>按月的历史余额，先与账本计算的余额核对，再写成 balance 指令</p></div>
+        {}"#,
+        csv_import_form("balance-import", "日期,账户,金额,货币", &options, default_file, "/tools/balances/preview", "/api/tools/balances/import")
+    )
+}
+
+/// Opening balances wizard: the start date, one row per account and
+/// currency (`accounts` are offered for the account field), then the
+/// generated directives before they are written
+pub fn opening_balances_page(accounts: &[String], default_currency: &str, today: &str, default_file: &str) -> String {
+    let options: String = accounts.iter().map(|a| format!("<option value='{}'>", attr_escape(a))).collect();
+    let row = format!(
+        r#"<div class='opening-row grid grid-cols-12 gap-2'>
+                    <input data-field='account' list='opening-accounts' placeholder='Assets:Bank' class='col-span-6 px-3 py-2 border rounded-lg font-mono text-sm'>
+                    <input data-field='amount' placeholder='1,000.00' class='col-span-3 px-3 py-2 border rounded-lg text-right'>
+                    <input data-field='currency' placeholder='{}' class='col-span-2 px-3 py-2 border rounded-lg'>
+                    <button type='button' onclick='this.parentElement.remove()' class='col-span-1 text-gray-400 hover:text-red-600' title='删除'>×</button>
+                </div>"#,
+        attr_escape(default_currency)
+    );
+    format!(
+        r#"<div class='mb-6'><h2 class='text-2xl font-bold'>期初余额</h2>
+            <p class='text-sm text-gray-500'>开始记账时各账户的实际余额，写成一笔从 Equity:Opening-Balances 转入的交易</p></div>
+        <form id='opening-balances' class='bg-white rounded-xl shadow-sm p-6 space-y-4' onsubmit='return false'>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>1. 开始日期</label>
+                <input type='date' name='date' value='{today}' class='px-3 py-2 border rounded-lg'>
+            </div>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>2. 资产和负债余额（负债填负数）</label>
+                <datalist id='opening-accounts'>{options}</datalist>
+                <div id='opening-rows' class='space-y-2'>{row}</div>
+                <template id='opening-row'>{row}</template>
+                <button type='button' onclick="document.getElementById('opening-rows').append(document.getElementById('opening-row').content.cloneNode(true))" class='mt-2 text-sm text-indigo-600 hover:underline'>+ 添加一行</button>
+            </div>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>写入文件</label>
+                <input name='file' value='{file}' class='w-full px-3 py-2 border rounded-lg font-mono text-sm'>
+            </div>
+            <div class='flex items-center gap-3'>
+                <button type='button' onclick="toolPreview(this.form, '/tools/opening-balances/preview', 'opening-balances-preview', openingBody)" class='px-4 py-2 border rounded-lg hover:bg-gray-50'>3. 预览</button>
+                <button type='button' onclick="toolImport(this.form, '/api/tools/opening-balances', 'opening-balances-result', openingBody)" class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>4. 写入</button>
+                <span id='opening-balances-result'></span>
+            </div>
+        </form>
+        <div id='opening-balances-preview' class='bg-white rounded-xl shadow-sm p-6 mt-6'></div>
+        {script}
+        <script>
+        function openingBody(form) {{
+            const body = toolBody(form);
+            body.balances = [...form.querySelectorAll('.opening-row')].map(row => ({{
+                account: row.querySelector("[data-field='account']").value.trim(),
+                amount: row.querySelector("[data-field='amount']").value.trim(),
+                currency: row.querySelector("[data-field='currency']").value.trim() || null,
+            }})).filter(b => b.account && b.amount);
+            return body;
+        }}
+        </script>"#,
+        today = attr_escape(today), options = options, row = row, file = attr_escape(default_file), script = FORM_SCRIPT
+    )
+}
+
+/// Why a preview couldn't be made, in place of the preview
+pub fn error(message: &str) -> String {
+    format!("<div class='text-red-600'>{}</div>", html_escape(message))
+}
+
+/// Account import preview: one row per CSV line with what happens to it
+pub fn account_import_preview(preview: &AccountImportPreview) -> String {
+    if preview.rows.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>CSV 中没有数据行</p></div>"#.to_string();
+    }
+
+    let mut rows = String::new();
+    for row in &preview.rows {
+        let (class, label) = match row.status {
+            AccountRowStatus::Ok => ("", "开立"),
+            AccountRowStatus::Duplicate => ("text-gray-400", "已存在"),
+            AccountRowStatus::Invalid => ("bg-amber-50", "无效"),
+        };
+        rows.push_str(&format!(
+            r#"<tr class='{}'>
+                <td class='px-3 py-2 text-sm text-gray-500'>{}</td>
+                <td class='px-3 py-2 text-sm text-gray-500'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2 text-sm'>{}{}</td>
+            </tr>"#,
+            class, row.line,
+            html_escape(&row.source), html_escape(&row.account),
+            html_escape(&row.currencies.join(",")), html_escape(&row.date),
+            label,
+            row.message.as_deref().map(|m| format!(" · {}", html_escape(m))).unwrap_or_default()
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-4'>
+            <div class='text-sm text-gray-600'>开立 {} · 已存在 {} · 无效 {}</div>
+            <table class='w-full'>
+                <thead class='bg-gray-50'><tr>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>行</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>原名称</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>账户</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>货币</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>开立日期</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>状态</th>
+                </tr></thead>
+                <tbody class='divide-y divide-gray-100'>{}</tbody>
+            </table>
+        </div>"#,
+        preview.count(AccountRowStatus::Ok), preview.count(AccountRowStatus::Duplicate),
+        preview.count(AccountRowStatus::Invalid), rows
+    )
+}
+
+/// Balance import preview: a chip per month, red when an assertion fails,
+/// and one row per CSV line
+pub fn balance_import_preview(preview: &BalanceImportPreview) -> String {
+    if preview.rows.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>CSV 中没有数据行</p></div>"#.to_string();
+    }
+
+    let mut months = String::new();
+    for month in &preview.months {
+        let class = if month.failing > 0 { "bg-red-100 text-red-700" } else { "bg-green-100 text-green-700" };
+        months.push_str(&format!(
+            "<span class='px-2 py-1 rounded text-sm {}' title='{} 条，{} 条失败'>{}</span>",
+            class, month.rows, month.failing, month.month
+        ));
+    }
+
+    let mut rows = String::new();
+    for row in &preview.rows {
+        let (class, label) = match row.status {
+            BalanceRowStatus::Ok => ("", "通过"),
+            BalanceRowStatus::Fails => ("bg-red-50", "断言失败"),
+            BalanceRowStatus::Duplicate => ("text-gray-400", "已存在"),
+            BalanceRowStatus::Padded => ("text-gray-400", "由 pad 补齐"),
+            BalanceRowStatus::Invalid => ("bg-amber-50", "无效"),
+        };
+        rows.push_str(&format!(
+            r#"<tr class='{}'>
+                <td class='px-3 py-2 text-sm text-gray-500'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2 text-right'>{} {}</td>
+                <td class='px-3 py-2 text-right text-gray-500'>{}</td>
+                <td class='px-3 py-2 text-sm'>{}{}</td>
+            </tr>"#,
+            class, row.line,
+            html_escape(&row.date), html_escape(&row.account),
+            html_escape(&row.amount), html_escape(&row.currency),
+            row.computed.map(|c| format!("{:.2}", c)).unwrap_or_else(|| "-".to_string()),
+            label,
+            row.message.as_deref().map(|m| format!(" · {}", html_escape(m))).unwrap_or_default()
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-4'>
+            <div class='text-sm text-gray-600'>通过 {} · 失败 {} · 已存在 {} · 由 pad 补齐 {} · 无效 {}</div>
+            <div class='flex flex-wrap gap-2'>{}</div>
+            <table class='w-full'>
+                <thead class='bg-gray-50'><tr>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>行</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>日期</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>账户</th>
+                    <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>断言</th>
+                    <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>实际</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>状态</th>
+                </tr></thead>
+                <tbody class='divide-y divide-gray-100'>{}</tbody>
+            </table>
+        </div>"#,
+        preview.count(BalanceRowStatus::Ok), preview.count(BalanceRowStatus::Fails),
+        preview.count(BalanceRowStatus::Duplicate), preview.count(BalanceRowStatus::Padded),
+        preview.count(BalanceRowStatus::Invalid), months, rows
+    )
+}
+
+/// Opening balances preview: what gets opened and the generated directives
+pub fn opening_balances_preview(plan: &OpeningBalancesPlan) -> String {
+    let equity: Vec<String> = plan.equity.iter()
+        .map(|(amount, currency)| format!("{} {}", amount, currency))
+        .collect();
+    format!(
+        r#"<div class='space-y-4'>
+            <div class='text-sm text-gray-600'>新开账户 {} · 期初余额 {} 笔 · 权益 {}</div>
+            <pre class='bg-gray-50 rounded-lg p-4 text-sm font-mono overflow-x-auto'>{}</pre>
+        </div>"#,
+        plan.opens.len(), plan.postings.len(),
+        html_escape(&equity.join("，")),
+        html_escape(&plan.source)
+    )
+}
+
+/// Transaction import preview: one row per CSV line, duplicates greyed out
+pub fn transaction_import_preview(preview: &TransactionImportPreview) -> String {
+    if preview.rows.is_empty() {
+        return r#"<div class='text-center py-12 text-gray-500'><p>CSV 中没有数据行</p></div>"#.to_string();
+    }
+
+    let mut rows = String::new();
+    for row in &preview.rows {
+        let (class, label) = match row.status {
+            TransactionRowStatus::Ok => ("", "导入"),
+            TransactionRowStatus::Duplicate => ("text-gray-400", "已存在"),
+            TransactionRowStatus::Invalid => ("bg-amber-50", "无效"),
+        };
+        rows.push_str(&format!(
+            r#"<tr class='{}'>
+                <td class='px-3 py-2 text-sm text-gray-500'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2'>{}</td>
+                <td class='px-3 py-2 text-right'>{} {}</td>
+                <td class='px-3 py-2 text-sm'>{}{}</td>
+                <td class='px-3 py-2 text-sm font-mono text-gray-500'>{}</td>
+                <td class='px-3 py-2 text-sm'>{}{}</td>
+            </tr>"#,
+            class, row.line,
+            html_escape(&row.date),
+            html_escape([row.payee.as_str(), row.narration.as_str()].join(" ").trim()),
+            html_escape(&row.amount), html_escape(&row.currency),
+            html_escape(&row.counter_account),
+            row.rule.as_deref().map(|r| format!("<span class='ml-1 text-xs text-indigo-600' title='分类规则'>· {}</span>", html_escape(r))).unwrap_or_default(),
+            html_escape(row.external_id.as_deref().unwrap_or("-")),
+            label,
+            row.message.as_deref().map(|m| format!(" · {}", html_escape(m))).unwrap_or_default()
+        ));
+    }
+
+    format!(
+        r#"<div class='space-y-4'>
+            <div class='text-sm text-gray-600'>导入 {} · 已存在 {} · 无效 {}</div>
+            <table class='w-full'>
+                <thead class='bg-gray-50'><tr>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>行</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>日期</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>交易</th>
+                    <th class='px-3 py-2 text-right text-sm font-medium text-gray-600'>金额</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>对方账户</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>外部 ID</th>
+                    <th class='px-3 py-2 text-left text-sm font-medium text-gray-600'>状态</th>
+                </tr></thead>
+                <tbody class='divide-y divide-gray-100'>{}</tbody>
+            </table>
+        </div>"#,
+        preview.count(TransactionRowStatus::Ok), preview.count(TransactionRowStatus::Duplicate),
+        preview.count(TransactionRowStatus::Invalid), rows
+    )
+}
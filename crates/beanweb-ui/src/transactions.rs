@@ -0,0 +1,1264 @@
+//! Transactions list, detail, edit and create markup
+//!
+//! Rows of the streamed list come from [`row`] with the pager of
+//! [`list_footer`] after them; the create and edit forms post back to the
+//! server, which answers with [`created`], [`updated`] or a [`failure`] box.
+
+use crate::{attr_escape, html_escape};
+use beanweb_config::EditMode;
+use beanweb_core::{Account, Decimal, Flow, LinkGroup, PostingAmount, Transaction, TransactionHeadline, TransactionTemplate};
+
+/// Headline amount of a transaction for list rows (see [`beanweb_core::display`]):
+/// (amount signed per the sign convention, color class, currency); green for
+/// money in, red for money out
+pub fn headline_amount(headline: &TransactionHeadline) -> (String, String, String) {
+    let color = match headline.flow {
+        Flow::In => "text-green-600",
+        Flow::Out => "text-red-600",
+        Flow::Transfer => "text-gray-600",
+    };
+    let amount = Decimal::from_str_exact(&headline.display_amount).unwrap_or_default();
+    (format!("{:.2}", amount), color.to_string(), headline.currency.clone())
+}
+
+/// Opening of the streamed list; rows and then [`list_footer`] follow
+pub const LIST_START: &str = "<div id='tx-list-container' class='space-y-2'>";
+
+/// In place of the list when nothing matches
+pub fn list_empty() -> String {
+    r#"<div class='text-center py-12 text-gray-500'><p>暂无交易记录</p></div>"#.to_string()
+}
+
+/// In place of the list when the filters don't parse
+pub fn list_error(message: &str) -> String {
+    format!("<div class='text-center py-12 text-red-500'><p>{}</p></div>", html_escape(message))
+}
+
+/// Closes the list: the detail toggle and the pager. `total_count` is known
+/// for numbered pages and None when paging by cursor; `next` is the id the
+/// next page starts after; `query_param` is the encoded search followed by the
+/// other filters, kept on every page
+pub fn list_footer(limit: usize, offset: usize, total_count: Option<usize>, next: Option<&str>, query_param: &str) -> String {
+    let mut footer = String::from("</div>");
+
+    footer.push_str(r#"<script>
+    function toggleDetail(id) {
+        var el = document.getElementById(id);
+        if (el.style.display === 'none') {
+            el.style.display = 'block';
+            if (el.innerHTML === '') {
+                htmx.ajax('GET', '/transactions/' + id.replace('tx-detail-', '') + '/detail', {target: el});
+            }
+        } else {
+            el.style.display = 'none';
+            el.innerHTML = '';
+        }
+    }
+    </script>"#);
+
+    let target = "#transactions-content";
+    let next_button = format!(
+        r#"<button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&after={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>下一页</button>"#,
+        if next.is_none() { "disabled" } else { "" },
+        limit, urlencoding::encode(next.unwrap_or("")), query_param, target
+    );
+    match total_count {
+        Some(total_count) => {
+            let current_page = offset / limit + 1;
+            let total_pages = total_count.div_ceil(limit);
+            footer.push_str(&format!(
+                r#"<div class='mt-6 flex items-center justify-between flex-wrap gap-4'>
+            <span class='text-sm text-gray-500'>共 {} 条记录，第 {} / {} 页</span>
+            <div class='flex items-center gap-2'>
+                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset=0&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>首页</button>
+                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>上一页</button>
+                <span class='text-sm text-gray-600'>第 <input type='number' id='page-jump-input' min='1' max='{}' value='{}' class='w-16 text-center border rounded px-2 py-1'> 页</span>
+                <button onclick='const p=document.getElementById("page-jump-input").value; htmx.ajax("GET", "/transactions/list?limit={}&offset=" + (p-1)*{} + "&q={}", "{}")' class='px-3 py-1 border rounded bg-blue-50 hover:bg-blue-100 text-blue-600'>跳转</button>
+                {}
+                <button {} onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset={}&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>末页</button>
+            </div>
+        </div>"#,
+                total_count, current_page, total_pages,
+                if current_page == 1 { "disabled" } else { "" },
+                limit, query_param, target,
+                if current_page == 1 { "disabled" } else { "" },
+                limit, offset.saturating_sub(limit), query_param, target,
+                total_pages, current_page,
+                limit, limit, query_param, target,
+                next_button,
+                if current_page >= total_pages { "disabled" } else { "" },
+                limit, (total_pages.saturating_sub(1)) * limit, query_param, target
+            ));
+        }
+        None => {
+            footer.push_str(&format!(
+                r#"<div class='mt-6 flex items-center justify-end gap-2'>
+            <button onclick='htmx.ajax("GET", "/transactions/list?limit={}&offset=0&q={}", "{}")' class='px-3 py-1 border rounded hover:bg-gray-100'>首页</button>
+            {}
+        </div>"#,
+                limit, query_param, target, next_button
+            ));
+        }
+    }
+
+    footer.push_str(r#"<style>.disabled{cursor:not-allowed;opacity:0.5;pointer-events:none}</style>"#);
+
+    footer
+}
+
+/// One entry of the transactions list (summary row + lazy detail container)
+pub fn row(tx: &Transaction, headline: &TransactionHeadline) -> String {
+    let flag = tx.flag.as_deref().unwrap_or("");
+    let flag_color = match flag {
+        "*" => "#10B981",
+        "!" => "#F59E0B",
+        _ => "#6B7280",
+    };
+
+    // Smart parsing of payee and narration
+    // Handle cases where user entered "payee: narration" without quotes
+    let (desc, narration): (String, String) = if tx.payee.is_empty() && tx.narration.contains(": ") {
+        // Likely entered as "工资: 2026-01-30" without quotes
+        let parts: Vec<&str> = tx.narration.split(": ").collect();
+        if parts.len() >= 2 {
+            (parts[0].to_string(), parts[1..].join(": "))
+        } else {
+            (tx.narration.clone(), String::new())
+        }
+    } else if tx.payee.is_empty() {
+        // Only narration, no payee
+        (tx.narration.clone(), String::new())
+    } else if tx.narration.is_empty() {
+        // Only payee, no narration
+        (tx.payee.clone(), String::new())
+    } else {
+        // Both payee and narration present
+        (tx.payee.clone(), tx.narration.clone())
+    };
+
+    let narration_display = if narration.is_empty() {
+        String::new()
+    } else {
+        format!(" - {}", html_escape(&narration))
+    };
+
+    let (amount_display, amount_color, display_currency) = headline_amount(headline);
+
+    let currency_suffix = if display_currency.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", display_currency)
+    };
+
+    let tags_brief: Vec<String> = tx.tags.iter().take(2).map(|tag| format!("#{}", html_escape(tag))).collect();
+    let tags_brief_str = if tags_brief.is_empty() {
+        String::new()
+    } else {
+        format!(" <span class='text-gray-400 text-sm'>{}</span>", tags_brief.join(" "))
+    };
+
+    let flow = flow_chips(&tx.flow());
+
+    // Split transactions explain their headline amount on hover
+    let amount = format!("<span class='font-medium {}'>{}{}</span>", amount_color, amount_display, currency_suffix);
+    let amount = if tx.postings.len() > 2 {
+        format!(
+            r#"<span class='relative group' hx-get='/transactions/{}/breakdown' hx-trigger='mouseenter once' hx-target='find .tx-breakdown' onclick='event.stopPropagation()'>
+                        <span class='border-b border-dotted border-gray-400'>{}</span>
+                        <div class='tx-breakdown absolute right-0 top-full mt-1 z-20 hidden group-hover:block bg-white border rounded-lg shadow-lg p-3 w-72'></div>
+                    </span>"#,
+            urlencoding::encode(&tx.id), amount
+        )
+    } else {
+        amount
+    };
+
+    let detail_id = attr_escape(&format!("tx-detail-{}", tx.id));
+    let datetime = if tx.has_time() {
+        format!("{} <span class='text-gray-400'>{}</span>", tx.date, tx.time)
+    } else {
+        tx.date.clone()
+    };
+
+    format!(
+        r#"<div class='border border-l-4 rounded-r-lg p-3 hover:bg-gray-50 transition cursor-pointer' onclick='toggleDetail("{}")'>
+            <div class='flex items-center justify-between gap-2'>
+                <div class='flex items-center gap-3 flex-1 min-w-0'>
+                    <span class='w-1 h-8 rounded flex-shrink-0' style='background:{}'></span>
+                    <div class='flex-1 min-w-0'>
+                        <div class='text-sm text-gray-500'>{}</div>
+                        <div class='font-medium truncate'>{}{}</div>
+                        {}
+                    </div>
+                </div>
+                <div class='flex items-center gap-2 flex-shrink-0'>
+                    {}
+                    {}
+                </div>
+            </div>
+        </div>
+        <div id='{}' class='tx-detail-container' style='display:none'></div>"#,
+        detail_id, flag_color, datetime, html_escape(&desc), narration_display, flow, tags_brief_str, amount, detail_id
+    )
+}
+
+/// Account chips shown per side of a row's flow before "+N"
+const FLOW_CHIPS_PER_SIDE: usize = 2;
+
+/// `Cash → Food` under a row: last name segments, full names on hover
+fn flow_chips(flow: &beanweb_core::TransactionFlow) -> String {
+    let side = |accounts: &[String]| -> String {
+        let mut chips: Vec<String> = accounts.iter()
+            .take(FLOW_CHIPS_PER_SIDE)
+            .map(|account| format!(
+                "<span class='px-1.5 py-0.5 bg-gray-100 rounded truncate max-w-[8rem]' title='{}'>{}</span>",
+                html_escape(account),
+                html_escape(account.rsplit(':').next().unwrap_or(account))
+            ))
+            .collect();
+        if accounts.len() > FLOW_CHIPS_PER_SIDE {
+            let rest = &accounts[FLOW_CHIPS_PER_SIDE..];
+            chips.push(format!("<span class='text-gray-400' title='{}'>+{}</span>", html_escape(&rest.join(", ")), rest.len()));
+        }
+        chips.join("")
+    };
+    if flow.from.is_empty() && flow.to.is_empty() {
+        return String::new();
+    }
+    let arrow = if flow.from.is_empty() || flow.to.is_empty() { "" } else { "<span class='text-gray-400'>→</span>" };
+    format!(
+        "<div class='flex items-center gap-1 mt-1 text-xs text-gray-600 min-w-0'>{}{}{}</div>",
+        side(&flow.from), arrow, side(&flow.to)
+    )
+}
+
+/// Tooltip of a row with more than two postings: each posting with its
+/// amount as displayed
+pub fn breakdown(postings: &[(PostingAmount, Decimal)]) -> String {
+    let rows: String = postings.iter()
+        .map(|(p, display)| format!(
+            "<div class='flex justify-between gap-4'><span class='text-gray-600 truncate'>{}</span><span class='font-mono{}'>{} {}</span></div>",
+            html_escape(&p.account),
+            if p.inferred { " text-gray-400" } else { "" },
+            display,
+            html_escape(&p.currency)
+        ))
+        .collect();
+    format!("<div class='space-y-1 text-xs'>{}</div>", rows)
+}
+
+/// Future-dated transactions with their headlines, above the list
+pub fn upcoming(upcoming: &[(Transaction, TransactionHeadline)]) -> String {
+    let mut rows = String::new();
+    for (tx, headline) in upcoming {
+        let (amount_display, amount_color, currency) = headline_amount(headline);
+        let desc = if tx.payee.is_empty() { &tx.narration } else { &tx.payee };
+        rows.push_str(&format!(
+            r#"<div class='flex items-center justify-between py-2 border-b last:border-0'>
+                <div class='flex items-center gap-3 min-w-0'>
+                    <span class='text-sm text-gray-500 flex-shrink-0'>{}</span>
+                    <span class='truncate'>{}</span>
+                </div>
+                <span class='font-medium {}'>{} {}</span>
+            </div>"#,
+            tx.date, html_escape(desc), amount_color, amount_display, html_escape(&currency)
+        ));
+    }
+
+    format!(
+        r#"<div class='bg-amber-50 border border-amber-200 rounded-xl p-4 mb-4'>
+            <div class='flex items-center justify-between mb-2'>
+                <h3 class='font-semibold text-amber-800'>即将发生 ({})</h3>
+                <span class='text-xs text-amber-600'>未计入余额和报表</span>
+            </div>
+            {}
+        </div>"#,
+        upcoming.len(), rows
+    )
+}
+
+/// "N new since" banner; `watermark` is the date reviewed up to, if any
+pub fn review_banner(watermark: Option<&str>, new_count: usize) -> String {
+    let message = match watermark {
+        Some(date) => format!("自上次查看（{}）以来有 <strong>{}</strong> 笔新交易", html_escape(date), new_count),
+        None => format!("尚未标记查看进度，共 <strong>{}</strong> 笔交易", new_count),
+    };
+    format!(
+        r#"<div class='bg-blue-50 border border-blue-200 rounded-xl p-4 mb-4 flex items-center justify-between'>
+            <span class='text-blue-800'>{}</span>
+            <div class='flex gap-2'>
+                <button onclick="const f = document.querySelector('[name=reviewed]'); f.value = 'no'; htmx.trigger(f, 'change')"
+                    class='px-3 py-1 text-sm bg-white text-blue-700 border border-blue-200 rounded hover:bg-blue-100'>只看新交易</button>
+                <button hx-post='/transactions/review' hx-swap='none'
+                    class='px-3 py-1 text-sm bg-blue-600 text-white rounded hover:bg-blue-700'>全部标记为已查看</button>
+            </div>
+        </div>"#,
+        message
+    )
+}
+
+/// Search and label filters the list page opens with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListFilters<'a> {
+    pub search: &'a str,
+    /// Tag without its `#`, empty for none
+    pub tag: &'a str,
+    /// Link without its `^`, empty for none
+    pub link: &'a str,
+}
+
+/// Transactions page: toolbar, counts of the time range (`count`
+/// transactions with `postings` postings from `start` to `end`) and the
+/// list, loaded once shown
+pub fn page(time_selector: &str, filters: ListFilters, count: usize, postings: usize, start: &str, end: &str) -> String {
+    let filter_chips: String = [('#', filters.tag, "清除标签筛选"), ('^', filters.link, "清除关联筛选")].iter()
+        .filter(|(_, value, _)| !value.is_empty())
+        .map(|(sigil, value, title)| format!(
+            "<a href='/transactions' class='px-3 py-2 bg-indigo-50 text-indigo-700 rounded-lg hover:bg-indigo-100 flex items-center gap-1' title='{}'>{}{} <span>×</span></a>",
+            title, sigil, html_escape(value)
+        ))
+        .collect();
+    format!(
+        r#"<div class='relative'>
+            {}
+        </div>
+        <div class='flex items-center justify-between mb-4'>
+            <h2 class='text-2xl font-bold'>交易流水</h2>
+            <div class='flex gap-2'>
+                <button onclick='reloadLedger()' class='px-4 py-2 bg-gray-100 text-gray-700 rounded-lg hover:bg-gray-200 flex items-center gap-2' title='重新加载账本'>
+                    <svg xmlns='http://www.w3.org/2000/svg' class='h-5 w-5' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M4 4v5h.582m15.356 2A8.001 8.001 0 004.582 9m0 0H9m11 11v-5h-.581m0 0a8.003 8.003 0 01-15.357-2m15.357 2H15'/>
+                    </svg>
+                    Reload
+                </button>
+                <button onclick='exportTransactions()' class='px-4 py-2 bg-gray-100 text-gray-700 rounded-lg hover:bg-gray-200 flex items-center gap-2' title='按当前筛选导出 CSV'>
+                    <svg xmlns='http://www.w3.org/2000/svg' class='h-5 w-5' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M4 16v1a3 3 0 003 3h10a3 3 0 003-3v-1m-4-4l-4 4m0 0l-4-4m4 4V4'/>
+                    </svg>
+                    导出
+                </button>
+                <button hx-get='/transactions/create' hx-swap='beforeend' hx-target='body'
+                    class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700 flex items-center gap-2'>
+                    <svg xmlns='http://www.w3.org/2000/svg' class='h-5 w-5' fill='none' viewBox='0 0 24 24' stroke='currentColor'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M12 4v16m8-8H4'/>
+                    </svg>
+                    新建
+                </button>
+                <div class='relative picker-anchor'>
+                    <input type='text' name='q' value='{}' placeholder='搜索...'
+                        hx-get='/transactions/list' hx-target='#transactions-content' hx-trigger='keyup changed delay:500ms, change'
+                        hx-include="[name='account'], [name='tag'], [name='link'], [name='limit'], [name='reviewed']" class='px-4 py-2 pr-10 border rounded-lg w-56'>
+                    {}
+                </div>
+                <input type='text' name='account' placeholder='账户：Expenses:Food:* 或 ~Taxi|Uber'
+                    hx-get='/transactions/list' hx-target='#transactions-content' hx-trigger='keyup changed delay:500ms, change'
+                    hx-include="[name='q'], [name='tag'], [name='link'], [name='limit'], [name='reviewed']" class='px-4 py-2 border rounded-lg w-64'>
+                <input type='hidden' name='tag' value='{}'>
+                <input type='hidden' name='link' value='{}'>
+                {}
+                <select name='limit' hx-get='/transactions/list' hx-target='#transactions-content' hx-trigger='change'
+                    class='px-4 py-2 border rounded-lg' onchange='this.form.requestSubmit()'>
+                    <option value='10'>10 条</option>
+                    <option value='25'>25 条</option>
+                    <option value='50' selected>50 条</option>
+                    <option value='100'>100 条</option>
+                </select>
+                <select name='reviewed' hx-get='/transactions/list' hx-target='#transactions-content' hx-trigger='change'
+                    hx-include="[name='q'], [name='account'], [name='tag'], [name='link'], [name='limit']" class='px-4 py-2 border rounded-lg'>
+                    <option value=''>全部</option>
+                    <option value='no'>未查看</option>
+                    <option value='yes'>已查看</option>
+                </select>
+            </div>
+        </div>
+        <div id='tx-stats' hx-get='/transactions' hx-trigger='time-range-changed from:body, ledger-reloaded from:body' hx-select='#tx-stats' hx-swap='outerHTML' class='grid grid-cols-2 md:grid-cols-4 gap-3 mb-4'>
+            <div class='bg-indigo-50 p-3 rounded-lg border border-indigo-100'><p class='text-xs text-indigo-600'>交易数</p><p class='text-xl font-bold'>{}</p></div>
+            <div class='bg-purple-50 p-3 rounded-lg border border-purple-100'><p class='text-xs text-purple-600'>条目数</p><p class='text-xl font-bold'>{}</p></div>
+            <div class='bg-green-50 p-3 rounded-lg border border-green-100'><p class='text-xs text-green-600'>开始</p><p class='text-sm font-medium truncate'>{}</p></div>
+            <div class='bg-orange-50 p-3 rounded-lg border border-orange-100'><p class='text-xs text-orange-600'>结束</p><p class='text-sm font-medium truncate'>{}</p></div>
+        </div>
+        <div id='review-banner' hx-get='/transactions/review' hx-trigger='load, reviewed-updated from:body, ledger-reloaded from:body'></div>
+        <div id='upcoming-transactions' hx-get='/transactions/upcoming' hx-trigger='load, time-range-changed from:body, ledger-reloaded from:body'></div>
+        <div id='transactions-content' hx-get='/transactions/list' hx-trigger='load, time-range-changed from:body, ledger-reloaded from:body, reviewed-updated from:body' hx-include="[name='q'], [name='account'], [name='tag'], [name='link'], [name='limit'], [name='reviewed']" class='bg-white rounded-xl shadow-sm p-6'>
+            <p class='text-gray-500 text-center'>加载中...</p>
+        </div>
+        <script>
+        function reloadLedger() {{
+            fetch('/api/reload', {{method: 'POST'}})
+                .then(r => r.json())
+                .then(data => {{
+                    if (data.success) {{
+                        window.location.reload();
+                    }} else {{
+                        alert('重新加载失败: ' + data.message);
+                    }}
+                }})
+                .catch(e => alert('重新加载失败: ' + e));
+        }}
+        function exportTransactions() {{
+            const params = new URLSearchParams({{format: 'csv'}});
+            ['q', 'account', 'tag', 'link', 'reviewed'].forEach(name => {{
+                const input = document.querySelector(`[name='${{name}}']`);
+                if (input && input.value) params.set(name, input.value);
+            }});
+            window.location = '/api/transactions/export?' + params.toString();
+        }}
+        </script>"#,
+        time_selector,
+        attr_escape(filters.search),
+        crate::picker::picker_button(None),
+        attr_escape(filters.tag),
+        attr_escape(filters.link),
+        filter_chips,
+        count,
+        postings,
+        html_escape(start),
+        html_escape(end)
+    )
+}
+
+/// One transaction on its own page
+pub fn standalone(tx: &Transaction, linked: &[LinkGroup]) -> String {
+    format!(
+        r#"<div class='mb-6'>
+            <a href='/transactions' class='text-sm text-indigo-600 hover:underline'>← 交易流水</a>
+            <h2 class='text-2xl font-bold mt-2'>{} {}</h2>
+            <p class='text-gray-500 mt-1'>{}</p>
+        </div>
+        <div class='tx-detail-container bg-white rounded-xl shadow-sm p-6'>{}</div>"#,
+        tx.date, html_escape(&tx.payee), html_escape(&tx.narration),
+        detail(tx, linked)
+    )
+}
+
+/// In place of a transaction that doesn't exist
+pub fn not_found() -> String {
+    r#"<div class='text-center py-8 text-red-500'>未找到交易记录</div>"#.to_string()
+}
+
+/// Mode a modal opens in, and the label and mode of its toggle
+fn modes(edit_mode: &EditMode) -> (&'static str, &'static str, &'static str) {
+    match edit_mode {
+        EditMode::Form => ("form", "文本模式", "text"),
+        EditMode::Text => ("text", "表单模式", "form"),
+    }
+}
+
+/// Edit modal for a transaction that doesn't exist
+pub fn edit_modal_not_found(id: &str) -> String {
+    format!(
+        r#"<div id='edit-modal' class='fixed inset-0 bg-black bg-opacity-50 z-50 flex items-center justify-center' onclick='if(event.target.id === "edit-modal") closeEditModal()'>
+    <div class='bg-red-50 border border-red-200 rounded-lg p-6 text-center'>
+        <h3 class='text-lg font-medium text-red-800 mb-2'>未找到交易记录</h3>
+        <p class='text-red-600 mb-4'>交易 ID: {}</p>
+        <button onclick='closeEditModal()' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>关闭</button>
+    </div>
+</div>
+<script>function closeEditModal() {{ const m = document.getElementById('edit-modal'); if (m) m.remove(); }}</script>"#,
+        html_escape(id)
+    )
+}
+
+/// Modal editing transaction `id`, loading the form in `edit_mode` first
+pub fn edit_modal(id: &str, edit_mode: &EditMode) -> String {
+    let (initial_mode, toggle_label, toggle_mode) = modes(edit_mode);
+    format!(
+        r#"<!-- 编辑模态框 -->
+<div id='edit-modal' class='fixed inset-0 bg-black bg-opacity-50 z-50 flex items-center justify-center' onclick='if(event.target.id === "edit-modal") closeEditModal()'>
+    <div class='bg-white rounded-xl shadow-2xl w-full max-w-4xl max-h-[90vh] overflow-hidden' onclick='event.stopPropagation()'>
+        <div class='flex items-center justify-between px-6 py-4 border-b'>
+            <h2 class='text-xl font-bold'>编辑交易</h2>
+            <div class='flex items-center gap-2'>
+                <button id='edit-mode-toggle' onclick="switchEditMode('{toggle_mode}')" class='px-3 py-1.5 text-sm border rounded-lg hover:bg-gray-50'>
+                    切换到 {toggle_label}
+                </button>
+                <button onclick='closeEditModal()' class='text-gray-500 hover:text-gray-700 p-2'>
+                    <svg class='w-6 h-6' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M6 18L18 6M6 6l12 12'/>
+                    </svg>
+                </button>
+            </div>
+        </div>
+        <div class='p-6 overflow-y-auto max-h-[calc(90vh-140px)]' id='edit-form-container'>
+            <div hx-get='/transactions/{id}/edit/form?mode={initial_mode}' hx-trigger='load' hx-target='this' hx-swap='innerHTML'>
+                <div class='flex items-center justify-center py-12'>
+                    <div class='animate-spin rounded-full h-8 w-8 border-b-2 border-indigo-600'></div>
+                </div>
+            </div>
+        </div>
+    </div>
+</div>
+<script>
+function closeEditModal() {{
+    const modal = document.getElementById('edit-modal');
+    if (modal) {{ modal.remove(); }}
+}}
+function switchEditMode(mode) {{
+    const toggle = document.getElementById('edit-mode-toggle');
+    if (toggle) {{
+        const next = mode === 'form' ? 'text' : 'form';
+        toggle.setAttribute('onclick', "switchEditMode('" + next + "')");
+        toggle.textContent = '切换到 ' + (next === 'form' ? '表单模式' : '文本模式');
+    }}
+    const container = document.getElementById('edit-form-container');
+    if (container) {{
+        container.innerHTML = '<div class="flex items-center justify-center py-12"><div class="animate-spin rounded-full h-8 w-8 border-b-2 border-indigo-600"></div></div>';
+        htmx.ajax('GET', '/transactions/{id}/edit/form?mode=' + mode, {{target: container}});
+    }}
+}}
+document.addEventListener('keydown', function(e) {{
+    if (e.key === 'Escape') closeEditModal();
+}});
+</script>"#,
+        id = urlencoding::encode(id),
+        initial_mode = initial_mode,
+        toggle_mode = toggle_mode,
+        toggle_label = toggle_label,
+    )
+
+}
+
+/// Modal creating a transaction, loading the form in `edit_mode` first
+pub fn create_modal(edit_mode: &EditMode) -> String {
+    let (initial_mode, toggle_label, toggle_mode) = modes(edit_mode);
+    format!(
+        r#"<!-- 新建交易模态框 -->
+<div id='create-modal' class='fixed inset-0 bg-black bg-opacity-50 z-50 flex items-center justify-center' onclick='if(event.target.id === "create-modal") closeCreateModal()'>
+    <div class='bg-white rounded-xl shadow-2xl w-full max-w-4xl max-h-[90vh] overflow-hidden' onclick='event.stopPropagation()'>
+        <div class='flex items-center justify-between px-6 py-4 border-b'>
+            <h2 class='text-xl font-bold'>新建交易</h2>
+            <div class='flex items-center gap-2'>
+                <button onclick="switchCreateMode('{}')" class='px-3 py-1.5 text-sm border rounded-lg hover:bg-gray-50'>
+                    切换到 {}
+                </button>
+                <button onclick='closeCreateModal()' class='text-gray-500 hover:text-gray-700 p-2'>
+                    <svg class='w-6 h-6' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
+                        <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M6 18L18 6M6 6l12 12'/>
+                    </svg>
+                </button>
+            </div>
+        </div>
+        <div class='p-6 overflow-y-auto max-h-[calc(90vh-140px)]' id='create-form-container'>
+            <div hx-get='/transactions/create/form?mode={}' hx-trigger='load' hx-target='this' hx-swap='innerHTML'>
+                <div class='flex items-center justify-center py-12'>
+                    <div class='animate-spin rounded-full h-8 w-8 border-b-2 border-indigo-600'></div>
+                </div>
+            </div>
+        </div>
+    </div>
+</div>
+<script>
+function closeCreateModal() {{
+    const modal = document.getElementById('create-modal');
+    if (modal) {{ modal.remove(); }}
+}}
+function switchCreateMode(mode) {{
+    const container = document.getElementById('create-form-container');
+    if (container) {{
+        container.innerHTML = '<div class="flex items-center justify-center py-12"><div class="animate-spin rounded-full h-8 w-8 border-b-2 border-indigo-600"></div></div>';
+        htmx.ajax('GET', '/transactions/create/form?mode=' + mode, {{target: container}});
+    }}
+}}
+document.addEventListener('keydown', function(e) {{
+    if (e.key === 'Escape') closeCreateModal();
+}});
+</script>"#,
+        toggle_mode, toggle_label, initial_mode
+    )
+
+}
+
+/// Postings of a transaction, with the groups of its `^links`
+pub fn detail(tx: &Transaction, linked: &[LinkGroup]) -> String {
+    let mut known_total: f64 = 0.0;
+    let mut has_known_amount = false;
+    let mut common_currency = String::new();
+
+    for p in &tx.postings {
+        if !p.amount.is_empty() {
+            // Use total_value to handle @ price syntax
+            let (amount, currency) = p.total_value("CNY");
+            if amount != 0.0 {
+                known_total += amount;
+                has_known_amount = true;
+                if common_currency.is_empty() && !currency.is_empty() {
+                    common_currency = currency;
+                }
+            }
+        }
+    }
+
+    let missing_amount = if has_known_amount { -known_total } else { 0.0 };
+    // NOTE: "收起"按钮已移除 - 用户可以直接点击交易记录来展开/收起详情
+    // 如果需要单独收起，可以点击其他交易或再次点击当前交易
+
+    // Only transactions read from a file can be edited or deleted in place
+    let edit_button = if tx.source.is_some() {
+        let id = urlencoding::encode(&tx.id);
+        format!(
+            r#"<div class='flex items-center gap-2'>
+                <button hx-get='/transactions/{id}/edit' hx-target='body' hx-swap='beforeend' onclick='event.stopPropagation()' class='px-3 py-1 text-sm text-indigo-600 border border-indigo-200 rounded-lg hover:bg-indigo-50'>编辑</button>
+                <button hx-delete='/transactions/{id}' hx-confirm='确定删除这笔交易吗？将从源文件中移除（原文件备份为 .bak）' hx-target='closest .tx-detail-container' hx-swap='innerHTML' onclick='event.stopPropagation()' class='px-3 py-1 text-sm text-red-600 border border-red-200 rounded-lg hover:bg-red-50'>删除</button>
+            </div>"#,
+            id = id
+        )
+    } else {
+        String::new()
+    };
+
+    let mut html = format!(
+        r#"<div class='mt-2 p-4 bg-gray-50 rounded-lg border border-indigo-200'>
+        <div class='flex items-center justify-between gap-2 mb-3'>
+            <span class='text-sm text-gray-500'>点击交易查看详情</span>
+            {}
+        </div>
+        <div class='space-y-2'>"#,
+        edit_button
+    );
+
+    for posting in &tx.postings {
+        let (display_amount, amount_class) = if posting.amount.is_empty() || posting.amount == "0" || posting.amount == "-0" {
+            if has_known_amount {
+                let prefix = if missing_amount < 0.0 { "-" } else { "" };
+                let amount_val = missing_amount.abs();
+                let currency = if common_currency.is_empty() {
+                    posting.currency.clone()
+                } else {
+                    common_currency.clone()
+                };
+                let suffix = if currency.is_empty() { String::new() } else { format!(" {}", currency) };
+                (format!("{}{:.2}{}", prefix, amount_val, suffix), "text-indigo-600 font-medium")
+            } else {
+                ("-".to_string(), "text-gray-400")
+            }
+        } else {
+            // Use total_value to handle @ price syntax for display
+            let (amount_value, currency) = posting.total_value("CNY");
+            let color_class = if amount_value < 0.0 {
+                "text-red-600"
+            } else if amount_value > 0.0 {
+                "text-green-600"
+            } else {
+                "text-gray-400"
+            };
+            let prefix = if amount_value < 0.0 { "-" } else { "" };
+            let suffix = if currency.is_empty() { String::new() } else { format!(" {}", currency) };
+            // Keep the original price info if present
+            let original_price_suffix = match &posting.price {
+                Some(price) => format!(" {}", price),
+                None => String::new(),
+            };
+            (format!("{}{:.2}{}{}", prefix, amount_value.abs(), suffix, original_price_suffix), color_class)
+        };
+
+        let account_link = format!(r#"<a href="/accounts/{}" class='text-indigo-600 hover:underline truncate' title='{}'>{}</a>"#,
+            urlencoding::encode(&posting.account), attr_escape(&posting.account), html_escape(&posting.account));
+
+        html.push_str(&format!(
+            r#"<div class='flex items-center justify-between py-2 border-b border-gray-200 last:border-0'>
+                <div class='flex items-center gap-2 flex-1 min-w-0'>
+                    <span class='text-gray-400 text-sm flex-shrink-0'></span>
+                    <span class='truncate text-gray-700'>{}</span>
+                </div>
+                <span class='font-medium flex-shrink-0 {}'>{}</span>
+            </div>"#,
+            account_link,
+            amount_class,
+            display_amount
+        ));
+    }
+
+    if !tx.tags.is_empty() || !tx.links.is_empty() {
+        html.push_str(r#"<div class='mt-3 flex flex-wrap gap-2'>"#);
+        // Each chip opens the list of transactions sharing it
+        for tag in &tx.tags {
+            html.push_str(&format!(
+                r#"<a href='/transactions?tag={}' class='px-2 py-1 bg-blue-100 text-blue-700 rounded text-xs hover:bg-blue-200'>#{}</a>"#,
+                urlencoding::encode(tag), html_escape(tag)
+            ));
+        }
+        for link in &tx.links {
+            html.push_str(&format!(
+                r#"<a href='/transactions?link={}' class='px-2 py-1 bg-purple-100 text-purple-700 rounded text-xs hover:bg-purple-200' title='查看所有关联交易'>^{}</a>"#,
+                urlencoding::encode(link), html_escape(link)
+            ));
+        }
+        html.push_str("</div>");
+    }
+
+    for group in linked.iter().filter(|g| g.transactions.len() > 1) {
+        html.push_str(&link_group(group, &tx.id));
+    }
+
+    if tx.metadata.is_object() && !tx.metadata.as_object().unwrap().is_empty() {
+        html.push_str(r#"<div class='mt-3 pt-3 border-t border-gray-200'><h5 class='text-xs font-medium text-gray-500 mb-2'>元数据</h5><div class='text-xs text-gray-600 font-mono'>"#);
+        for (key, value) in tx.metadata.as_object().unwrap() {
+            html.push_str(&html_escape(&format!("{}: {}, ", key, value)));
+        }
+        html.push_str("</div></div>");
+    }
+
+    if let Some(document) = tx.metadata.get("document").and_then(|v| v.as_str()) {
+        html.push_str(&document_preview(document.trim_matches('"')));
+    }
+
+    html.push_str("</div></div>");
+    html
+}
+
+/// Lifecycle of one link: every transaction carrying it, and what is still open
+fn link_group(group: &LinkGroup, current_id: &str) -> String {
+    let status = if group.is_settled() {
+        "<span class='px-2 py-0.5 bg-green-100 text-green-700 rounded text-xs'>已结清</span>".to_string()
+    } else {
+        let open: Vec<String> = group.outstanding.iter()
+            .map(|b| format!("{} {} {}", b.account, b.amount, b.currency))
+            .collect();
+        format!("<span class='px-2 py-0.5 bg-amber-100 text-amber-700 rounded text-xs' title='{}'>未结清</span>", attr_escape(&open.join("; ")))
+    };
+
+    let mut rows = String::new();
+    for tx in &group.transactions {
+        let title = if tx.payee.is_empty() { tx.narration.clone() } else { format!("{} · {}", tx.payee, tx.narration) };
+        let current = tx.id == current_id;
+        rows.push_str(&format!(
+            r#"<div class='flex items-center gap-3 py-1 text-xs {}'>
+                <span class='text-gray-500 flex-shrink-0'>{}</span>
+                <span class='truncate'>{}</span>
+                {}
+            </div>"#,
+            if current { "font-medium text-indigo-700" } else { "text-gray-700" },
+            tx.date,
+            html_escape(&title),
+            if current { "<span class='text-gray-400 flex-shrink-0'>（当前）</span>" } else { "" },
+        ));
+    }
+    for balance in &group.outstanding {
+        rows.push_str(&format!(
+            r#"<div class='flex items-center justify-between py-1 text-xs text-amber-700 border-t border-gray-200'>
+                <span class='truncate'>{}</span>
+                <span class='flex-shrink-0'>{} {}</span>
+            </div>"#,
+            html_escape(&balance.account), balance.amount, html_escape(&balance.currency)
+        ));
+    }
+
+    format!(
+        r#"<div class='mt-3 pt-3 border-t border-gray-200'>
+            <div class='flex items-center gap-2 mb-2'>
+                <h5 class='text-xs font-medium text-gray-500'>关联交易 ^{}</h5>
+                {}
+            </div>
+            {}
+        </div>"#,
+        html_escape(&group.link), status, rows
+    )
+}
+
+/// Preview for the `document:` metadata attached on creation (image inline, PDF embedded)
+fn document_preview(document: &str) -> String {
+    let url = crate::document_url(document);
+    let is_pdf = document.to_lowercase().ends_with(".pdf");
+    let preview = if is_pdf {
+        format!(r#"<embed src='{}' type='application/pdf' class='w-full h-96 rounded border'>"#, url)
+    } else {
+        format!(r#"<img src='{}' alt='{}' loading='lazy' class='max-h-96 rounded border'>"#, url, attr_escape(document))
+    };
+    format!(
+        r#"<div class='mt-3 pt-3 border-t border-gray-200'>
+            <div class='flex items-center justify-between mb-2'>
+                <h5 class='text-xs font-medium text-gray-500'>附件</h5>
+                <a href='{url}' target='_blank' class='text-xs text-indigo-600 hover:underline'>{name} ↗</a>
+            </div>
+            {preview}
+        </div>"#,
+        url = url,
+        name = html_escape(document),
+        preview = preview,
+    )
+}
+
+/// Render form-based edit interface; amounts are edited as Beancount text so
+/// costs and prices survive (`10 AAPL {150.00 USD}`)
+fn edit_fields(tx: &Transaction, accounts: &[String]) -> String {
+    let flag = tx.flag.as_deref().unwrap_or("*");
+    let flag_options: String = [("*", "已确认 (*)"), ("!", "待确认 (!)"), ("", "无标记")].iter()
+        .map(|(value, label)| format!("<option value='{}'{}>{}</option>", value, if *value == flag { " selected" } else { "" }, label))
+        .collect();
+    let tags_value: Vec<String> = tx.tags.iter().map(|t| format!("#{}", t)).collect();
+    let links_value: Vec<String> = tx.links.iter().map(|l| format!("^{}", l)).collect();
+    let account_options: String = accounts.iter().map(|a| format!("<option value='{}'>", attr_escape(a))).collect();
+
+    let posting_row = |index: String, account: &str, amount: &str| format!(
+        r#"<div class='flex items-center gap-2 mb-2'>
+            <input type='text' name='posting_{index}_account' value='{account}' list='edit-accounts' class='flex-1 px-3 py-2.5 border rounded-lg' placeholder='账户'>
+            <input type='text' name='posting_{index}_amount' value='{amount}' class='w-56 px-3 py-2.5 border rounded-lg font-mono text-sm' placeholder='100.00 CNY（留空自动计算）'>
+            <button type='button' onclick='this.parentElement.remove()' class='px-3 py-2 text-red-500 hover:text-red-700 hover:bg-red-50 rounded-lg'>删除</button>
+        </div>"#,
+        index = index, account = attr_escape(account), amount = attr_escape(amount)
+    );
+    let postings_html: String = tx.postings.iter().enumerate()
+        .map(|(i, p)| posting_row(i.to_string(), &p.account, &p.amount))
+        .collect();
+    // Template for rows added in the browser; the index is filled in by addEditPosting()
+    let new_row = posting_row("NEW".to_string(), "", "").replace('`', "\\`");
+
+    format!(
+        r#"<div class='space-y-6'>
+            <div class='grid grid-cols-3 gap-4'>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>日期</label>
+                    <input type='date' name='date' value='{date}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>标记</label>
+                    <select name='flag' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>{flag_options}</select>
+                </div>
+            </div>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>交易对象</label>
+                <input type='text' name='payee' value='{payee}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易对象'>
+            </div>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-1'>摘要</label>
+                <input type='text' name='narration' value='{narration}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易摘要'>
+            </div>
+            <div>
+                <label class='block text-sm font-medium text-gray-700 mb-2'>分录</label>
+                <div class='border rounded-lg p-3 bg-gray-50' id='edit-postings-container'>{postings}</div>
+                <button type='button' onclick='addEditPosting()' class='mt-2 px-4 py-2 text-sm text-indigo-600 hover:text-indigo-800 hover:bg-indigo-50 rounded-lg border border-indigo-200'>+ 添加分录</button>
+                <p class='text-xs text-gray-400 mt-1'>元数据保持不变；如需修改请切换到文本模式</p>
+            </div>
+            <div class='grid grid-cols-2 gap-4'>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>标签 (Tags)</label>
+                    <input type='text' name='tags' value='{tags}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='#tag1 #tag2'>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>链接 (Links)</label>
+                    <input type='text' name='links' value='{links}' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='^link1 ^link2'>
+                </div>
+            </div>
+        </div>
+        <datalist id='edit-accounts'>{account_options}</datalist>
+        <script>
+            let editPostingCount = {count};
+            function addEditPosting() {{
+                const container = document.getElementById('edit-postings-container');
+                if (container) {{
+                    container.insertAdjacentHTML('beforeend', `{new_row}`.replaceAll('posting_NEW_', 'posting_' + editPostingCount + '_'));
+                    editPostingCount++;
+                }}
+            }}
+        </script>"#,
+        date = attr_escape(&tx.date),
+        flag_options = flag_options,
+        payee = attr_escape(&tx.payee),
+        narration = attr_escape(&tx.narration),
+        postings = postings_html,
+        tags = attr_escape(&tags_value.join(" ")),
+        links = attr_escape(&links_value.join(" ")),
+        account_options = account_options,
+        count = tx.postings.len(),
+        new_row = new_row,
+    )
+}
+
+/// Edit form of transaction `id` as Beancount text, `source` as written in its file
+pub fn edit_text_form(id: &str, source: &str) -> String {
+    format!(
+        r#"<form hx-put='/transactions/{}' hx-target='#edit-result' hx-swap='innerHTML'>
+            <input type='hidden' name='mode' value='text'>
+            <div class='mb-4'>
+                <label class='block text-sm font-medium text-gray-700 mb-2'>交易内容 (Beancount 格式)</label>
+                <textarea name="content" class="w-full h-64 font-mono text-sm p-4 border rounded-lg focus:ring-2 focus:ring-indigo-500" placeholder="输入交易记录...">{}</textarea>
+            </div>
+            <div class='flex items-center gap-4'>
+                <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存更改</button>
+                <button type='button' onclick='closeEditModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
+            </div>
+        </form>
+        <div id='edit-result' class='mt-4'></div>"#,
+        urlencoding::encode(id),
+        html_escape(source)
+    )
+}
+
+/// Edit form of `tx` with a field per part; `accounts` are offered for the postings
+pub fn edit_form(tx: &Transaction, accounts: &[String]) -> String {
+    format!(
+        r#"<form hx-put='/transactions/{}' hx-target='#edit-result' hx-swap='innerHTML'>
+            <input type='hidden' name='mode' value='form'>
+            {}
+            <div class='flex items-center gap-4 mt-6 pt-4 border-t'>
+                <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存更改</button>
+                <button type='button' onclick='closeEditModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
+            </div>
+        </form>
+        <div id='edit-result' class='mt-4'></div>"#,
+        urlencoding::encode(&tx.id),
+        edit_fields(tx, accounts)
+    )
+}
+
+/// Edit form for a transaction that doesn't exist
+pub fn edit_not_found() -> String {
+    r#"<div class='text-red-500 p-4'>未找到交易记录</div>"#.to_string()
+}
+
+/// Red box with `title`, e.g. "保存失败", and why
+pub fn failure(title: &str, message: &str) -> String {
+    format!(
+        r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>{}</span></div><p class='text-sm text-red-600 mt-1'>{}</p></div>"#,
+        html_escape(title), html_escape(message)
+    )
+}
+
+/// Saved edit: closes the modal and refreshes the list
+pub fn updated() -> String {
+    r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>交易已更新</span></div><p class='text-sm text-green-600 mt-1'>账本已重新加载</p><script>closeEditModal(); const txContent = document.getElementById('transactions-content'); if (txContent) { htmx.ajax('GET', '/transactions/list?limit=50', {target: txContent}); } else { setTimeout(() => window.location.reload(), 300); }</script></div>"#.to_string()
+}
+
+/// Deleted transaction: refreshes the list
+pub fn deleted() -> String {
+    r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>交易已删除</span></div><p class='text-sm text-green-600 mt-1'>原文件已备份为 .bak</p><script>const txContent = document.getElementById('transactions-content'); if (txContent) { htmx.ajax('GET', '/transactions/list?limit=50', {target: txContent}); } else { setTimeout(() => window.location.reload(), 300); }</script></div>"#.to_string()
+}
+
+/// Created transaction: closes the modal and refreshes the list
+pub fn created() -> String {
+    r#"<div class='bg-green-50 border border-green-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-green-600'>✓</span><span class='font-medium text-green-800'>交易已创建</span></div><p class='text-sm text-green-600 mt-1'>账本已重新加载</p><script>closeCreateModal(); const txContent = document.getElementById('transactions-content'); if (txContent) { htmx.ajax('GET', '/transactions/list?limit=50', {target: txContent}); } else { setTimeout(() => window.location.reload(), 300); }</script></div>"#.to_string()
+}
+
+/// Create form as Beancount text, prefilled with an example dated `today`;
+/// `idempotency_field` is the hidden key input of the form
+pub fn create_text_form(idempotency_field: &str, today: &str) -> String {
+    format!(
+        r#"<form hx-post='/transactions' hx-target='#create-result' hx-swap='innerHTML'>
+            {}
+            <div class='mb-4'>
+                <label class='block text-sm font-medium text-gray-700 mb-2'>交易内容 (Beancount 格式)</label>
+                <textarea name="content" class="w-full h-64 font-mono text-sm p-4 border rounded-lg focus:ring-2 focus:ring-indigo-500" placeholder="输入交易记录...">{}  * "交易对象" "摘要"
+    Assets:账户    -100.00 CNY
+    Expenses:类别    100.00 CNY</textarea>
+            </div>
+            <div class='flex items-center gap-4'>
+                <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存</button>
+                <button type='button' onclick='closeCreateModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
+            </div>
+        </form>
+        <div id='create-result' class='mt-4'></div>"#,
+        idempotency_field,
+        today
+    )
+}
+
+/// Create form with a field per part: `accounts` feed the account search,
+/// `currencies` the currency choices (the default first) and `templates` the
+/// template picker
+pub fn create_form(idempotency_field: &str, today: &str, accounts: &[Account], currencies: &[String], templates: &[TransactionTemplate]) -> String {
+    let accounts_json = serde_json::to_string(accounts).unwrap_or_default();
+    let currency_options: String = currencies.iter()
+        .map(|c| format!("<option value='{0}'>{0}</option>", attr_escape(c)))
+        .collect();
+    format!(
+        r#"<form hx-post='/transactions' hx-target='#create-result' hx-swap='innerHTML' hx-encoding='multipart/form-data'>
+            {idempotency}
+            <input type='hidden' id='allow-duplicate' name='allow_duplicate' value=''>
+            <div class='space-y-6'>
+                <div class='grid grid-cols-3 gap-4'>
+                    <div>
+                        <label class='block text-sm font-medium text-gray-700 mb-1'>日期</label>
+                        <input type='date' name='date' value='{}' onchange='recheckAccounts()' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
+                    </div>
+                    <div>
+                        <label class='block text-sm font-medium text-gray-700 mb-1'>时间 <span class='text-gray-400 font-normal'>(可选)</span></label>
+                        <input type='time' name='time' step='1' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' title='写入 time: 元数据，用于同一天内的排序' oninput='updatePreview()'>
+                    </div>
+                    <div>
+                        <label class='block text-sm font-medium text-gray-700 mb-1'>标记</label>
+                        <select name='flag' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500'>
+                            <option value='*'>已确认 (*)</option>
+                            <option value='!'>待确认 (!)</option>
+                            <option value=''>无标记</option>
+                        </select>
+                    </div>
+                </div>
+                {template_picker}
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>交易对象</label>
+                    <input type='text' name='payee' value='' onchange='applyRule()' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易对象'>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>摘要</label>
+                    <input type='text' name='narration' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='交易摘要'>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-2'>分录</label>
+                    <div class='border rounded-lg p-3 bg-gray-50' id='postings-container'>
+                        <div class='flex items-center gap-2 mb-2'>
+                            <div class='relative flex-1 picker-anchor'>
+                                <input type='text' name='posting_0_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8 pr-10' placeholder='搜索账户...'
+                                    oninput='filterAccount(this, "account-list-0")' onfocus='showAccountList(this, "account-list-0")' onblur='hideAccountListDelayed(this, "account-list-0")'>
+                                <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
+                                    <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
+                                </svg>
+                                <div id='account-list-0' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
+                                {picker}
+                            </div>
+                            <input type='text' name='posting_0_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额 / 12.5*3' onblur='evaluateAmount(this)'>
+                            <select name='posting_0_currency' class='w-24 px-2 py-2.5 border rounded-lg text-sm' onchange='updatePreview()'>{currency_options}</select>
+                        </div>
+                        <div class='flex items-center gap-2 mb-2'>
+                            <div class='relative flex-1 picker-anchor'>
+                                <input type='text' name='posting_1_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8 pr-10' placeholder='搜索账户...'
+                                    oninput='filterAccount(this, "account-list-1")' onfocus='showAccountList(this, "account-list-1")' onblur='hideAccountListDelayed(this, "account-list-1")'>
+                                <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
+                                    <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
+                                </svg>
+                                <div id='account-list-1' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
+                                {picker}
+                            </div>
+                            <input type='text' name='posting_1_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额 / 12.5*3' onblur='evaluateAmount(this)'>
+                            <select name='posting_1_currency' class='w-24 px-2 py-2.5 border rounded-lg text-sm' onchange='updatePreview()'>{currency_options}</select>
+                        </div>
+                    </div>
+                    <button type='button' onclick='addPosting()' class='mt-2 px-4 py-2 text-sm text-indigo-600 hover:text-indigo-800 hover:bg-indigo-50 rounded-lg border border-indigo-200'>+ 添加分录</button>
+                </div>
+                <div class='grid grid-cols-2 gap-4'>
+                    <div>
+                        <label class='block text-sm font-medium text-gray-700 mb-1'>标签 (Tags)</label>
+                        <input type='text' name='tags' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='#tag1 #tag2' oninput='updatePreview()'>
+                    </div>
+                    <div>
+                        <label class='block text-sm font-medium text-gray-700 mb-1'>链接 (Links)</label>
+                        <input type='text' name='links' value='' class='w-full px-3 py-2.5 border rounded-lg focus:ring-2 focus:ring-indigo-500' placeholder='^link1 ^link2' oninput='updatePreview()'>
+                    </div>
+                </div>
+                <div>
+                    <label class='block text-sm font-medium text-gray-700 mb-1'>附件 (收据图片 / PDF)</label>
+                    <input type='file' name='document' accept='image/*,application/pdf'
+                        class='w-full text-sm text-gray-600 file:mr-3 file:px-4 file:py-2 file:rounded-lg file:border-0 file:bg-indigo-50 file:text-indigo-700 hover:file:bg-indigo-100'>
+                    <p class='text-xs text-gray-400 mt-1'>保存到文档目录，并写入 document 元数据</p>
+                </div>
+            </div>
+            <div class='border-t pt-4'>
+                <label class='block text-sm font-medium text-gray-700 mb-2'>交易预览</label>
+                <pre id='tx-preview' class='bg-gray-900 text-green-400 p-4 rounded-lg text-sm font-mono overflow-x-auto'>2026-01-31 *
+    Assets:Account
+    Expenses:Category</pre>
+            </div>
+            <div class='flex items-center gap-4 mt-6 pt-4 border-t'>
+                <button type='submit' class='px-4 py-2 bg-indigo-600 text-white rounded-lg hover:bg-indigo-700'>保存</button>
+                <button type='button' onclick='closeCreateModal()' class='px-4 py-2 border rounded-lg hover:bg-gray-50'>取消</button>
+            </div>
+        </form>
+        <div id='create-result' class='mt-4'></div>
+        <div id='accounts-data' data-accounts='{}' class='hidden'></div>
+        <script>
+            let postingCount = 2;
+            function addPosting() {{
+                const container = document.getElementById('postings-container');
+                if (container) {{
+                    const html = `<div class='flex items-center gap-2 mb-2'>
+                        <div class='relative flex-1 picker-anchor'>
+                            <input type='text' name='posting_` + postingCount + `_account' value='' class='w-full px-3 py-2.5 border rounded-lg pl-8 pr-10' placeholder='搜索账户...'
+                                oninput='filterAccount(this, "account-list-` + postingCount + `")' onfocus='showAccountList(this, "account-list-` + postingCount + `")' onblur='hideAccountListDelayed(this, "account-list-` + postingCount + `")'>
+                            <svg class='w-4 h-4 absolute left-2.5 top-3 text-gray-400' fill='none' stroke='currentColor' viewBox='0 0 24 24'>
+                                <path stroke-linecap='round' stroke-linejoin='round' stroke-width='2' d='M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z'/>
+                            </svg>
+                            <div id='account-list-` + postingCount + `' class='absolute z-10 w-full bg-white border rounded-lg shadow-lg mt-1 max-h-48 overflow-auto hidden'></div>
+                                {picker}
+                        </div>
+                        <input type='text' name='posting_` + postingCount + `_amount' value='' class='w-32 px-3 py-2.5 border rounded-lg' placeholder='金额 / 12.5*3' onblur='evaluateAmount(this)'>
+                        <select name='posting_` + postingCount + `_currency' class='w-24 px-2 py-2.5 border rounded-lg text-sm' onchange='updatePreview()'>{currency_options}</select>
+                        <button type='button' onclick='this.parentElement.remove()' class='px-3 py-2 text-red-500 hover:text-red-700 hover:bg-red-50 rounded-lg'>删除</button>
+                    </div>`;
+                    container.insertAdjacentHTML('beforeend', html);
+                    postingCount++;
+                }}
+            }}
+            function showAccountList(input, listId) {{ filterAccount(input, listId); }}
+            function hideAccountListDelayed(input, listId) {{
+                setTimeout(() => {{
+                    const list = document.getElementById(listId);
+                    if (list) {{ list.classList.add('hidden'); }}
+                }}, 200);
+            }}
+            function filterAccount(input, listId) {{
+                const q = input.value.toLowerCase();
+                const list = document.getElementById(listId);
+                if (!list) return;
+                const dataDiv = document.getElementById('accounts-data');
+                let accounts = [];
+                if (dataDiv && dataDiv.dataset.accounts) {{
+                    try {{ accounts = JSON.parse(dataDiv.dataset.accounts); }} catch(e) {{ accounts = []; }}
+                }}
+                const date = transactionDate();
+                const filtered = accounts.filter(a => a.name.toLowerCase().includes(q) && isOpenOn(a, date));
+                if (filtered.length === 0 || q.length === 0) {{ list.classList.add('hidden'); return; }}
+                let html = '';
+                filtered.slice(0, 10).forEach(a => {{
+                    const parts = a.name.split(':');
+                    const shortName = parts[parts.length - 1];
+                    html += `<div class='px-3 py-2 hover:bg-indigo-50 cursor-pointer text-sm border-b last:border-0' data-account='${{a.name}}' onclick='selectAccount(this, "` + listId + `")'>
+                        <div class='font-medium'>${{shortName}}</div>
+                        <div class='text-xs text-gray-400'>${{a.name}}</div>
+                    </div>`;
+                }});
+                list.innerHTML = html;
+                list.classList.remove('hidden');
+            }}
+            function transactionDate() {{
+                const input = document.querySelector('input[name="date"]');
+                return input ? input.value : '';
+            }}
+            // Same rule as the server: opened on or before the date, closed after it
+            function isOpenOn(account, date) {{
+                if (!date) return true;
+                if (account.open_date && account.open_date > date) return false;
+                return !account.close_date || account.close_date > date;
+            }}
+            // Flags account fields already filled in when the date moves outside their open period
+            function recheckAccounts() {{
+                const dataDiv = document.getElementById('accounts-data');
+                let accounts = [];
+                if (dataDiv && dataDiv.dataset.accounts) {{
+                    try {{ accounts = JSON.parse(dataDiv.dataset.accounts); }} catch(e) {{ accounts = []; }}
+                }}
+                const date = transactionDate();
+                document.querySelectorAll('input[name$="_account"]').forEach(input => {{
+                    const account = accounts.find(a => a.name === input.value.trim());
+                    if (account && !isOpenOn(account, date)) {{
+                        input.classList.add('border-red-500');
+                        input.title = account.close_date && account.close_date <= date ? '账户已于 ' + account.close_date + ' 关闭' : '账户于 ' + account.open_date + ' 才开立';
+                    }} else {{
+                        input.classList.remove('border-red-500');
+                        input.title = '';
+                    }}
+                }});
+                updatePreview();
+            }}
+            document.addEventListener('account-picked', () => recheckAccounts());
+            // Fills payee, narration, tags and postings from the chosen template
+            function applyTemplate(select) {{
+                const option = select.selectedOptions[0];
+                if (!option || !option.dataset.template) return;
+                let template;
+                try {{ template = JSON.parse(option.dataset.template); }} catch(e) {{ return; }}
+                const form = select.form;
+                form.querySelector('input[name="payee"]').value = template.payee || '';
+                form.querySelector('input[name="narration"]').value = template.narration || '';
+                form.querySelector('input[name="tags"]').value = (template.tags || []).map(t => '#' + t).join(' ');
+                const postings = template.postings || [];
+                while (form.querySelectorAll('input[name$="_account"]').length < postings.length) {{ addPosting(); }}
+                form.querySelectorAll('input[name$="_account"]').forEach((input, i) => {{
+                    const amount = form.querySelector('input[name="' + input.name.replace('_account', '_amount') + '"]');
+                    input.value = postings[i] ? postings[i].account : '';
+                    if (amount) {{ amount.value = postings[i] ? (postings[i].amount || '') : ''; }}
+                }});
+                recheckAccounts();
+            }}
+            // Account and tags of the first categorization rule matching the payee; only fills what is still empty
+            function applyRule() {{
+                const form = document.querySelector('input[name="payee"]').form;
+                const payee = form.querySelector('input[name="payee"]').value.trim();
+                if (!payee) return;
+                const narration = form.querySelector('input[name="narration"]').value;
+                const amount = (form.querySelector('input[name$="_amount"]')?.value || '').replace(/[^\d.\-]/g, '');
+                fetch('/api/rules/match?payee=' + encodeURIComponent(payee) + '&narration=' + encodeURIComponent(narration) + '&amount=' + encodeURIComponent(amount))
+                    .then(r => r.ok ? r.json() : {{}})
+                    .then(result => {{
+                        const rule = result.rule;
+                        if (!rule) return;
+                        const empty = [...form.querySelectorAll('input[name$="_account"]')].find(input => !input.value.trim());
+                        if (empty) {{ empty.value = rule.account; }}
+                        const tags = form.querySelector('input[name="tags"]');
+                        const missing = (rule.tags || []).map(t => '#' + t).filter(t => !tags.value.split(/\s+/).includes(t));
+                        tags.value = [tags.value.trim(), ...missing].filter(Boolean).join(' ');
+                        recheckAccounts();
+                    }});
+            }}
+            // Expressions like "12.5*3" are evaluated by the server and replaced in place
+            function evaluateAmount(input) {{
+                const value = input.value.trim();
+                if (!/\d\s*[-+*/]|[()]/.test(value)) {{ input.classList.remove('border-red-500'); input.title = ''; updatePreview(); return; }}
+                fetch('/api/transactions/evaluate-amount?input=' + encodeURIComponent(value))
+                    .then(r => r.json())
+                    .then(result => {{
+                        if (result.ok) {{ input.value = result.value; input.classList.remove('border-red-500'); input.title = value; }}
+                        else {{ input.classList.add('border-red-500'); input.title = result.error; }}
+                        updatePreview();
+                    }});
+            }}
+            function selectAccount(item, listId) {{
+                const list = document.getElementById(listId);
+                const parent = list.parentElement;
+                const input = parent.querySelector('input[type="text"]');
+                const fullAccount = item.getAttribute('data-account');
+                if (input && fullAccount) {{ input.value = fullAccount; }}
+                list.classList.add('hidden');
+                updatePreview();
+            }}
+            function updatePreview() {{
+                const date = document.querySelector('input[name="date"]')?.value || new Date().toISOString().split('T')[0];
+                const flag = document.querySelector('select[name="flag"]')?.value || '*';
+                const flagStr = flag ? flag + ' ' : '';
+                const payee = document.querySelector('input[name="payee"]')?.value || '';
+                const narration = document.querySelector('input[name="narration"]')?.value || '';
+                const tags = document.querySelector('input[name="tags"]')?.value || '';
+                const links = document.querySelector('input[name="links"]')?.value || '';
+                let firstLine = date + ' ' + flagStr;
+                if (payee && narration) {{ firstLine += '"' + payee + '" "' + narration + '"'; }}
+                else if (payee) {{ firstLine += '"' + payee + '"'; }}
+                else if (narration) {{ firstLine += '"' + narration + '"'; }}
+                const extras = [];
+                tags.split(/\s+/).filter(t => t.startsWith('#')).forEach(t => extras.push(t));
+                links.split(/\s+/).filter(l => l.startsWith('^')).forEach(l => extras.push(l));
+                if (extras.length > 0) {{ firstLine += ' ' + extras.join(' '); }}
+                let postings = [];
+                document.querySelectorAll('[name^="posting_"][name$="_account"]').forEach(input => {{
+                    const name = input.name;
+                    const amountInput = document.querySelector('input[name="' + name.replace('_account', '_amount') + '"]');
+                    const account = input.value;
+                    const currencySelect = document.querySelector('select[name="' + name.replace('_account', '_currency') + '"]');
+                    let amount = amountInput?.value.trim() || '';
+                    if (amount && !/[A-Za-z]/.test(amount) && currencySelect?.value) {{ amount += ' ' + currencySelect.value; }}
+                    if (account) {{ postings.push('    ' + account + (amount ? ' ' + amount : '')); }}
+                }});
+                const time = document.querySelector('input[name="time"]')?.value || '';
+                if (time) {{ firstLine += '\\n    time: "' + (time.length === 5 ? time + ':00' : time) + '"'; }}
+                const preview = document.getElementById('tx-preview');
+                if (preview) {{ preview.textContent = firstLine + '\\n' + (postings.length > 0 ? postings.join('\\n') : '    ...'); }}
+            }}
+        </script>"#,
+        today, attr_escape(&accounts_json),
+        picker = crate::picker::picker_button(None),
+        currency_options = currency_options,
+        template_picker = crate::templates::picker(templates),
+        idempotency = idempotency_field
+    )
+}
+
+/// Accounts not open on `date` among the postings'
+pub fn inactive_accounts(date: &str, inactive: &[String]) -> String {
+    let items: Vec<String> = inactive.iter().map(|m| format!("<li>{}</li>", html_escape(m))).collect();
+    format!(r#"<div class='bg-red-50 border border-red-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-red-600'>✗</span><span class='font-medium text-red-800'>账户不可用</span></div><p class='text-sm text-red-600 mt-1'>{} 当天以下账户未开立或已关闭：</p><ul class='text-sm text-red-600 mt-1 list-disc list-inside'>{}</ul></div>"#, html_escape(date), items.join(""))
+}
+
+/// Warning listing near-identical transactions, with a button that submits
+/// the create form again with `allow_duplicate` set
+pub fn duplicate_warning(similar: &[Transaction]) -> String {
+    let items: String = similar.iter()
+        .map(|tx| format!(
+            "<li>{} {} {}</li>",
+            tx.date,
+            html_escape(&tx.payee),
+            html_escape(&tx.narration),
+        ))
+        .collect();
+    format!(
+        r#"<div class='bg-amber-50 border border-amber-200 rounded-lg p-4'><div class='flex items-center gap-2'><span class='text-amber-600'>⚠</span><span class='font-medium text-amber-800'>可能是重复交易</span></div><p class='text-sm text-amber-700 mt-1'>已有 {} 笔日期、金额和账户都相同的交易：</p><ul class='text-sm text-amber-700 mt-1 list-disc list-inside'>{}</ul><button type='button' onclick="const f = document.getElementById('allow-duplicate'); f.value = '1'; f.form.requestSubmit();" class='mt-3 px-3 py-1.5 bg-amber-600 text-white rounded-lg text-sm hover:bg-amber-700'>仍然保存</button></div>"#,
+        similar.len(),
+        items,
+    )
+}
@@ -0,0 +1,218 @@
+mod support;
+
+use beanweb_core::budget::{BudgetItem, BudgetPeriod, BudgetReport};
+use beanweb_core::stale::StaleBalance;
+use beanweb_core::{Amount, CategoryRule, Decimal, IntegrityIssue, IntegrityIssueKind, SuspensePosting, TagSummary, TemplatePosting, TransactionTemplate, ConversionMode, DirectiveError, MonthlySummary, MonthlySummaryReport, PayeeSummary, PayeeTrends, RecurringInterval, RecurringPayment, Severity, SpendingVelocity};
+use beanweb_config::FeaturesConfig;
+use beanweb_ui::dashboard::{self, TopCard, TopRow};
+use beanweb_ui::{banners, budgets, layout, reports, rules, suspense, tags, templates, transactions};
+use support::assert_snapshot;
+
+#[test]
+fn test_layout() {
+    let mut features = FeaturesConfig { budget_enable: true, ..FeaturesConfig::default() };
+    assert_snapshot("nav_sidebar", &layout::nav_sidebar("/reports/balance", &features));
+    // HTMX requests get the content area only
+    let partial = layout::page("报表", "/reports", "<p>content</p>", true, &features);
+    assert_snapshot("page_partial", &partial);
+    assert!(!partial.contains("<aside"));
+    assert!(layout::page("报表", "/reports", "<p>content</p>", false, &features).starts_with("<!DOCTYPE html>"));
+    // Budgets are left out of the sidebar while the feature is off
+    features.budget_enable = false;
+    let nav = layout::nav_sidebar("/reports/balance", &features);
+    assert!(!nav.contains("href='/budgets'"));
+    assert!(nav.contains("href='/reports'"));
+}
+
+#[test]
+fn test_banners() {
+    assert_snapshot("load_error", &banners::load_error("main.bean: <missing>", false, Some(3)));
+    assert!(banners::parse_errors(&[], std::path::Path::new("/data")).is_empty());
+    let error = |line: usize, column: usize, message: &str, text: &str, severity: Severity| DirectiveError {
+        source: Some("/data/2024.bean".to_string()),
+        line,
+        column,
+        end_line: line,
+        message: message.to_string(),
+        text: text.to_string(),
+        severity,
+    };
+    assert_snapshot("parse_errors", &banners::parse_errors(&[
+        error(3, 12, "Unknown directive: opne", "2024-01-02 opne Assets:Bank", Severity::Error),
+        error(9, 10, "Included file not found: <old>.bean", "include \"<old>.bean\"", Severity::Warning),
+    ], std::path::Path::new("/data")));
+    assert!(banners::negative_balances(&[]).is_empty());
+}
+
+#[test]
+fn test_dashboard_cards() {
+    let velocity = SpendingVelocity {
+        date: "2024-03-10".to_string(),
+        days_elapsed: 10,
+        days_in_month: 31,
+        spent: "300".to_string(),
+        daily_average: "30".to_string(),
+        last_month_daily_average: "25".to_string(),
+        change_percent: Some(20.0),
+        projected: "930".to_string(),
+        currency: "CNY".to_string(),
+    };
+    assert_snapshot("dashboard_velocity", &dashboard::velocity(&velocity));
+
+    let recurring = vec![RecurringPayment {
+        payee: "Netflix".to_string(),
+        account: "Expenses:Subscriptions".to_string(),
+        amount: "15.99".to_string(),
+        currency: "USD".to_string(),
+        interval: RecurringInterval::Monthly,
+        occurrences: 3,
+        last_date: "2024-03-15".to_string(),
+        next_date: "2024-04-15".to_string(),
+        monthly_amount: "15.99".to_string(),
+    }];
+    assert_snapshot("dashboard_recurring", &dashboard::recurring(&recurring, 5));
+
+    let stale = vec![StaleBalance { account: "Assets:Bank".to_string(), last_assertion: "2024-01-01".to_string(), days: 45, threshold: 30 }];
+    assert_snapshot("dashboard_stale_balances", &dashboard::stale_balances(&stale));
+}
+
+#[test]
+fn test_top_card() {
+    let rows: Vec<TopRow> = [("Assets:Cash", 50.0), ("Assets:Bank", 1000.0), ("Assets:Broker", 300.0)].iter()
+        .map(|(label, amount)| TopRow { label: label.to_string(), amount: *amount, detail: "CNY".to_string() })
+        .collect();
+    // Biggest first, with a button to show the rest
+    let collapsed = dashboard::top_card(TopCard::Assets, &rows, 2, false);
+    assert_snapshot("dashboard_top_collapsed", &collapsed);
+    assert!(collapsed.find("Assets:Bank") < collapsed.find("Assets:Broker"));
+    assert!(!collapsed.contains("Assets:Cash"));
+    assert!(dashboard::top_card(TopCard::Assets, &rows, 2, true).contains("收起"));
+    assert!(dashboard::top_card(TopCard::Expenses, &[], 2, false).contains("暂无数据"));
+}
+
+#[test]
+fn test_reports() {
+    assert_snapshot("conversion_toggle", &reports::conversion_toggle("/reports/income-expense?group_by=groups", ConversionMode::Original, "CNY"));
+
+    let monthly = MonthlySummaryReport {
+        summaries: vec![
+            MonthlySummary { month: "2024-01".to_string(), income: "1000".to_string(), expenses: "400".to_string(), net: "600".to_string() },
+            MonthlySummary { month: "2024-02".to_string(), income: "0".to_string(), expenses: "200".to_string(), net: "-200".to_string() },
+        ],
+        year: 2024,
+        total_income: "1000".to_string(),
+        total_expenses: "600".to_string(),
+        total_net: "400".to_string(),
+        currency: "CNY".to_string(),
+    };
+    assert_snapshot("monthly_summary", &reports::monthly_summary(&monthly));
+
+    let payees = vec![PayeeSummary {
+        payee: "Shop & Co".to_string(),
+        count: 2,
+        amount: "50".to_string(),
+        currency: "CNY".to_string(),
+        percentage: 100.0,
+        last_date: "2024-02-08".to_string(),
+        unconverted: 1,
+    }];
+    let trends = PayeeTrends {
+        months: vec!["2024-01".to_string(), "2024-02".to_string()],
+        payees: [("Shop & Co".to_string(), vec![0.0, 50.0])].into_iter().collect(),
+    };
+    let html = reports::payees(&payees, &trends);
+    assert_snapshot("payees", &html);
+    assert!(html.contains("/transactions?q=Shop%20%26%20Co"));
+    assert!(reports::payees(&[], &PayeeTrends::default()).contains("暂无商户支出"));
+}
+
+#[test]
+fn test_attr_escape() {
+    assert_eq!(beanweb_ui::attr_escape(r#"O'Brien & "Co" <x>"#), "O&#39;Brien &amp; &quot;Co&quot; &lt;x&gt;");
+    // Element content keeps its quotes
+    assert_eq!(beanweb_ui::html_escape(r#"O'Brien "Co""#), r#"O'Brien "Co""#);
+}
+
+#[test]
+fn test_check_badge() {
+    let issue = IntegrityIssue {
+        kind: IntegrityIssueKind::BalanceAssertion,
+        date: "2024-03-01".to_string(),
+        account: "Assets:Bank".to_string(),
+        transaction_id: None,
+        message: "expected 100.00 CNY, got <90.00>".to_string(),
+    };
+    assert_snapshot("check_badge", &banners::check_badge(1, &[issue], 2));
+    assert!(banners::check_badge(0, &[], 1).contains("已全部修复"));
+}
+
+#[test]
+fn test_suspense() {
+    assert!(suspense::badge(0).is_empty());
+    let posting = SuspensePosting {
+        transaction_id: "tx'1".to_string(),
+        date: "2024-03-02".to_string(),
+        payee: "Shop".to_string(),
+        narration: "Lunch".to_string(),
+        account: "Expenses:Uncategorized".to_string(),
+        amount: 35.0,
+        currency: "CNY".to_string(),
+    };
+    let html = suspense::list(&[posting], &["Expenses:Food".to_string()], Some("账户不存在"));
+    assert_snapshot("suspense_list", &html);
+    assert!(html.contains("value='tx&#39;1'"));
+}
+
+#[test]
+fn test_budgets_and_tags() {
+    let item = BudgetItem {
+        account: "Expenses:Food".to_string(),
+        period: BudgetPeriod::Monthly,
+        currency: "CNY".to_string(),
+        period_amount: 1500.0,
+        budget: 1500.0,
+        spent: 1650.0,
+        remaining: -150.0,
+        percent: 110.0,
+        prorated: false,
+    };
+    let report = BudgetReport { start: "2024-03-01".to_string(), end: "2024-03-31".to_string(), items: vec![item] };
+    assert_snapshot("budgets_list", &budgets::list(&report));
+    let tag = TagSummary {
+        tag: "trip".to_string(),
+        count: 2,
+        expenses: vec![Amount::new(Decimal::new(120050, 2), "CNY")],
+        income: vec![],
+    };
+    assert_snapshot("tags_list", &tags::list(&[tag]));
+}
+
+#[test]
+fn test_rules_and_templates() {
+    let rule = CategoryRule {
+        name: "Coffee <shops>".to_string(),
+        payee: Some("Starbucks|Costa".to_string()),
+        narration: None,
+        min_amount: None,
+        max_amount: Some(Decimal::new(100, 0)),
+        metadata: Default::default(),
+        account: "Expenses:Food:Coffee".to_string(),
+        tags: vec!["coffee".to_string()],
+    };
+    assert_snapshot("rules_page", &rules::page(Ok(std::slice::from_ref(&rule))));
+    assert!(rules::page(Err("bad regex")).contains("bad regex"));
+    let template = TransactionTemplate {
+        name: "Rent 'monthly'".to_string(),
+        payee: "Landlord".to_string(),
+        narration: String::new(),
+        tags: vec![],
+        postings: vec![TemplatePosting { account: "Expenses:Rent".to_string(), amount: "3000 CNY".to_string() }],
+    };
+    assert_snapshot("templates_picker", &templates::picker(&[template]));
+}
+
+#[test]
+fn test_transaction_messages() {
+    assert_snapshot("transaction_failure", &transactions::failure("保存失败", "余额 <不平>"));
+    assert!(transactions::list_error("bad & query").contains("bad &amp; query"));
+}
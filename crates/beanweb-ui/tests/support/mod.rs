@@ -0,0 +1,27 @@
+//! Snapshot assertions for rendered HTML
+//!
+//! [`assert_snapshot`] compares the output with `tests/snapshots/<name>.html`:
+//! - A missing or different snapshot fails the test
+//! - `UPDATE_SNAPSHOTS=1 cargo test -p beanweb-ui` writes new snapshots and
+//!   rewrites changed ones after an intended change in markup; they are
+//!   reviewed in the diff like any other change
+
+use std::path::PathBuf;
+
+/// Assert that `html` matches the stored snapshot `name`
+pub fn assert_snapshot(name: &str, html: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{}.html", name));
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+    if update {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, html).unwrap();
+        return;
+    }
+    assert!(path.exists(), "snapshot `{}` is missing, run with UPDATE_SNAPSHOTS=1 to write it", name);
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        expected == html,
+        "snapshot `{}` differs, rerun with UPDATE_SNAPSHOTS=1 if the change is intended\n--- expected\n{}\n--- actual\n{}",
+        name, expected, html
+    );
+}
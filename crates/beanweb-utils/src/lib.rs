@@ -1,5 +1,29 @@
 //! Utility functions and helpers
 
+pub mod lines;
+pub mod svg;
+pub mod synthetic;
+
+/// Replace the file at `path` with `content` so readers see either the old
+/// or the new file, never a partial one: the content goes to a temporary
+/// file next to it, which is then renamed over the original (keeping its
+/// permissions)
+pub fn write_atomic(path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+    let name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a file path", path.display())))?;
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+    let written = std::fs::write(&temp, content).and_then(|()| {
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        std::fs::rename(&temp, path)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
 /// Format a number with thousands separators
 pub fn format_number<T: ToString>(n: T) -> String {
     let s = n.to_string();
@@ -0,0 +1,166 @@
+//! Line-based windows into large text files
+//!
+//! The file editor pages through big files by line rather than by byte, so a
+//! window never splits a line or a UTF-8 character. Ranges use the
+//! `Range: lines=<first>-<last>` form (0-based, inclusive, the end optional)
+//! and are answered with `Content-Range: lines <first>-<last>/<total>`; a
+//! save of one window sends the same `Content-Range` back, with the file's
+//! `ETag` in `If-Match` so a file changed meanwhile is not overwritten.
+
+use std::io::BufRead;
+use std::path::Path;
+
+/// A slice of whole lines out of a larger text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineWindow {
+    /// The lines, each with its trailing newline
+    pub text: String,
+    /// Index of the first line
+    pub start: usize,
+    /// Number of lines in the window
+    pub count: usize,
+    /// Number of lines in the whole text
+    pub total: usize,
+}
+
+impl LineWindow {
+    /// Whether the window is the whole text
+    pub fn is_complete(&self) -> bool {
+        self.start == 0 && self.count == self.total
+    }
+
+    /// `Content-Range` value; `lines */<total>` for an empty window
+    pub fn content_range(&self) -> String {
+        if self.count == 0 {
+            format!("lines */{}", self.total)
+        } else {
+            format!("lines {}-{}/{}", self.start, self.start + self.count - 1, self.total)
+        }
+    }
+}
+
+/// Up to `limit` lines of `content` starting at line `offset`
+pub fn line_window(content: &str, offset: usize, limit: usize) -> LineWindow {
+    let mut total = 0;
+    let mut text = String::new();
+    for line in content.split_inclusive('\n') {
+        if total >= offset && total - offset < limit {
+            text.push_str(line);
+        }
+        total += 1;
+    }
+    let start = offset.min(total);
+    LineWindow { text, start, count: (total - start).min(limit), total }
+}
+
+/// [`line_window`] of the file at `path`, read line by line so only the
+/// window is held in memory
+pub fn read_line_window(path: &Path, offset: usize, limit: usize) -> std::io::Result<LineWindow> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut total = 0;
+    let mut text = String::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if total >= offset && total - offset < limit {
+            text.push_str(&String::from_utf8_lossy(&line));
+        }
+        total += 1;
+    }
+    let start = offset.min(total);
+    Ok(LineWindow { text, start, count: (total - start).min(limit), total })
+}
+
+/// `content` with the `count` lines from line `offset` replaced by
+/// `replacement`, which gets a trailing newline when lines follow it
+pub fn splice_lines(content: &str, offset: usize, count: usize, replacement: &str) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let start = offset.min(lines.len());
+    let end = (offset + count).min(lines.len());
+    let mut spliced: String = lines[..start].concat();
+    spliced.push_str(replacement);
+    if end < lines.len() && !replacement.is_empty() && !replacement.ends_with('\n') {
+        spliced.push('\n');
+    }
+    spliced.push_str(&lines[end..].concat());
+    spliced
+}
+
+/// Parse `lines <first>-<last>/<total>` into (offset, count, total)
+pub fn parse_content_range(header: &str) -> Option<(usize, usize, usize)> {
+    let (range, total) = header.trim().strip_prefix("lines ")?.split_once('/')?;
+    let (first, last) = range.split_once('-')?;
+    let (first, last): (usize, usize) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+    let total = total.trim().parse().ok()?;
+    let count = last.checked_sub(first)?.checked_add(1)?;
+    (last < total).then_some((first, count, total))
+}
+
+/// Parse `lines=<first>-<last>` or `lines=<first>-` into (offset, limit)
+pub fn parse_line_range(header: &str) -> Option<(usize, Option<usize>)> {
+    let (first, last) = header.trim().strip_prefix("lines=")?.split_once('-')?;
+    let first: usize = first.trim().parse().ok()?;
+    match last.trim() {
+        "" => Some((first, None)),
+        last => {
+            let last: usize = last.parse().ok()?;
+            Some((first, Some(last.checked_sub(first)?.checked_add(1)?)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_window() {
+        let content = "a\nb\nc\nd";
+        let window = line_window(content, 1, 2);
+        assert_eq!(window.text, "b\nc\n");
+        assert_eq!((window.start, window.count, window.total), (1, 2, 4));
+        assert_eq!(window.content_range(), "lines 1-2/4");
+        assert!(!window.is_complete());
+
+        assert!(line_window(content, 0, 10).is_complete());
+        assert_eq!(line_window(content, 3, 10).text, "d");
+        let past_end = line_window(content, 9, 10);
+        assert_eq!((past_end.text.as_str(), past_end.count), ("", 0));
+        assert_eq!(past_end.content_range(), "lines */4");
+    }
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("lines=0-499"), Some((0, Some(500))));
+        assert_eq!(parse_line_range("lines=500-"), Some((500, None)));
+        assert_eq!(parse_line_range("bytes=0-100"), None);
+        assert_eq!(parse_line_range("lines=10-5"), None);
+        assert_eq!(parse_line_range(&format!("lines=0-{}", usize::MAX)), None);
+    }
+
+    #[test]
+    fn test_read_line_window() {
+        let path = std::env::temp_dir().join(format!("beanweb-lines-{}.txt", std::process::id()));
+        std::fs::write(&path, "a\nb\nc\nd").unwrap();
+        let window = read_line_window(&path, 1, 2).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(window, line_window("a\nb\nc\nd", 1, 2));
+    }
+
+    #[test]
+    fn test_splice_lines() {
+        let content = "a\nb\nc\nd";
+        assert_eq!(splice_lines(content, 1, 2, "x\ny\nz\n"), "a\nx\ny\nz\nd");
+        assert_eq!(splice_lines(content, 1, 2, "x"), "a\nx\nd");
+        assert_eq!(splice_lines(content, 1, 2, ""), "a\nd");
+        assert_eq!(splice_lines(content, 3, 1, "e"), "a\nb\nc\ne");
+
+        assert_eq!(parse_content_range("lines 500-999/1200"), Some((500, 500, 1200)));
+        assert_eq!(parse_content_range("lines 0-10/5"), None);
+        assert_eq!(parse_content_range("lines */5"), None);
+        assert_eq!(parse_content_range(&format!("lines 0-{}/{}", usize::MAX, usize::MAX)), None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Inline SVG mini-charts for table rows
+//!
+//! Charts are plain `<svg>` strings sized in pixels, so HTMX fragments can
+//! embed them without any client-side chart library. Values are scaled to
+//! the chart height; an empty or all-zero series draws a flat baseline.
+
+/// Line chart of `values` scaled from zero (or the lowest value, if negative), with a dot on the last point
+pub fn sparkline(values: &[f64], width: u32, height: u32, color: &str) -> String {
+    let (w, h) = (width as f64, height as f64);
+    // Keep the stroke inside the box
+    let pad = 1.5;
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min).min(0.0);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max).max(0.0);
+    let span = if max - min > f64::EPSILON { max - min } else { 1.0 };
+    let step = if values.len() > 1 { (w - 2.0 * pad) / (values.len() - 1) as f64 } else { 0.0 };
+    let points: Vec<(f64, f64)> = values.iter().enumerate()
+        .map(|(i, v)| (pad + i as f64 * step, h - pad - (v - min) / span * (h - 2.0 * pad)))
+        .collect();
+
+    let mut svg = open_svg(width, height);
+    match points.as_slice() {
+        [] => svg.push_str(&baseline(w, h - pad, color)),
+        [(_, y)] => svg.push_str(&format!(
+            "<line x1='{:.1}' y1='{:.1}' x2='{:.1}' y2='{:.1}' stroke='{}' stroke-width='1.5'/>",
+            pad, y, w - pad, y, color
+        )),
+        _ => {
+            let path: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+            svg.push_str(&format!(
+                "<polyline points='{}' fill='none' stroke='{}' stroke-width='1.5' stroke-linejoin='round' stroke-linecap='round'/>",
+                path.join(" "), color
+            ));
+        }
+    }
+    if let Some((x, y)) = points.last() {
+        svg.push_str(&format!("<circle cx='{:.1}' cy='{:.1}' r='1.8' fill='{}'/>", x, y, color));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Bar chart of `values`; negative values hang below the zero line, zeros leave a gap and the last bar is drawn solid
+pub fn bars(values: &[f64], width: u32, height: u32, color: &str) -> String {
+    let (w, h) = (width as f64, height as f64);
+    let mut svg = open_svg(width, height);
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let min = values.iter().copied().fold(0.0, f64::min);
+    if values.is_empty() || max - min <= f64::EPSILON {
+        svg.push_str(&baseline(w, h - 0.5, color));
+        svg.push_str("</svg>");
+        return svg;
+    }
+
+    let zero = h * max / (max - min);
+    let slot = w / values.len() as f64;
+    let gap = (slot * 0.2).min(2.0);
+    for (i, value) in values.iter().enumerate().filter(|(_, v)| **v != 0.0) {
+        let bar = (value.abs() / (max - min) * h).max(1.0);
+        let y = if *value >= 0.0 { zero - bar } else { zero };
+        let opacity = if i + 1 == values.len() { "1" } else { "0.45" };
+        svg.push_str(&format!(
+            "<rect x='{:.1}' y='{:.1}' width='{:.1}' height='{:.1}' fill='{}' fill-opacity='{}'/>",
+            i as f64 * slot + gap / 2.0, y, slot - gap, bar, color, opacity
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn open_svg(width: u32, height: u32) -> String {
+    format!(
+        "<svg xmlns='http://www.w3.org/2000/svg' width='{0}' height='{1}' viewBox='0 0 {0} {1}' class='inline-block align-middle' aria-hidden='true'>",
+        width, height
+    )
+}
+
+fn baseline(w: f64, y: f64, color: &str) -> String {
+    format!("<line x1='0' y1='{:.1}' x2='{:.1}' y2='{:.1}' stroke='{}' stroke-opacity='0.3'/>", y, w, y, color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mini_charts() {
+        let line = sparkline(&[1.0, 3.0, 2.0], 60, 20, "#4f46e5");
+        assert!(line.starts_with("<svg") && line.ends_with("</svg>"));
+        assert!(line.contains("<polyline points='1.5,12.8 30.0,1.5 58.5,7.2'"));
+        assert!(line.contains("<circle cx='58.5'"));
+        assert!(sparkline(&[], 60, 20, "red").contains("<line"));
+
+        let chart = bars(&[10.0, 0.0, -5.0], 30, 15, "red");
+        assert_eq!(chart.matches("<rect").count(), 2);
+        // Tallest bar ends at the zero line, the negative one starts there
+        assert!(chart.contains("y='0.0' width='8.0' height='10.0'"));
+        assert!(chart.contains("y='10.0' width='8.0' height='5.0'"));
+        assert!(bars(&[0.0, 0.0], 30, 15, "red").contains("<line"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Synthetic Beancount ledgers for benchmarks and the demo mode
+//!
+//! The text depends only on the size, seed and end year, so a benchmark run
+//! always parses the same input. It is shaped like a household ledger rather
+//! than uniform noise, so profiles show the paths real files take:
+//! - Salary, rent and a cash withdrawal on the 1st, a credit card paid off on
+//!   the 15th, and daily spending over a dozen expense accounts
+//! - Salary scales with the spending, and nothing is paid from an account that
+//!   can't cover it (the card takes it instead), so no asset goes negative
+//! - A USD account bought into with `@` prices, with a `price` directive a month
+//! - Metadata, tags and links on a share of the transactions, and a `balance`
+//!   assertion on the checking account every January 1st
+//!
+//! [`SyntheticLedger::render`] gives one file; [`SyntheticLedger::files`] splits
+//! it into a main file including the accounts and one file per year.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const CHECKING: &str = "Assets:Bank:Checking";
+const CARD: &str = "Liabilities:CreditCard";
+const BROKER: &str = "Assets:Broker:USD";
+
+/// Accounts opened on the first day, with their currency
+const ACCOUNTS: [(&str, &str); 17] = [
+    (CHECKING, "CNY"),
+    ("Assets:Bank:Savings", "CNY"),
+    ("Assets:Cash", "CNY"),
+    (BROKER, "USD"),
+    (CARD, "CNY"),
+    ("Income:Salary", "CNY"),
+    ("Income:Interest", "CNY"),
+    ("Expenses:Food:Groceries", "CNY"),
+    ("Expenses:Food:Dining", "CNY"),
+    ("Expenses:Transport", "CNY"),
+    ("Expenses:Housing:Rent", "CNY"),
+    ("Expenses:Utilities", "CNY"),
+    ("Expenses:Shopping", "CNY"),
+    ("Expenses:Health", "CNY"),
+    ("Expenses:Entertainment", "CNY"),
+    ("Expenses:Travel", "CNY"),
+    ("Equity:Opening-Balances", "CNY"),
+];
+
+/// Everyday spending: (account, payees, largest amount in yuan)
+const SPENDING: [(&str, [&str; 3], u64); 8] = [
+    ("Expenses:Food:Groceries", ["Supermarket", "Fresh Market", "Bakery"], 300),
+    ("Expenses:Food:Dining", ["Noodle House", "Cafe", "Hotpot"], 200),
+    ("Expenses:Transport", ["Metro", "Taxi", "Gas Station"], 150),
+    ("Expenses:Utilities", ["Power Company", "Water Company", "ISP"], 400),
+    ("Expenses:Shopping", ["Online Store", "Mall", "Bookstore"], 800),
+    ("Expenses:Health", ["Pharmacy", "Clinic", "Gym"], 500),
+    ("Expenses:Entertainment", ["Cinema", "Concert Hall", "Game Store"], 300),
+    ("Expenses:Travel", ["Airline", "Hotel", "Rail"], 2000),
+];
+
+/// Most years a ledger spans; larger ledgers get busier days instead
+const MAX_YEARS: u64 = 10;
+
+/// Deterministic ledger generator
+#[derive(Debug, Clone)]
+pub struct SyntheticLedger {
+    transactions: usize,
+    seed: u64,
+    end_year: i32,
+}
+
+/// xorshift64*: small, fast and good enough to pick payees and amounts
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+}
+
+/// Transactions written so far and the running balances they imply, in cents
+#[derive(Default)]
+struct Entries {
+    count: usize,
+    balances: HashMap<&'static str, i64>,
+}
+
+impl Entries {
+    fn push(&mut self, out: &mut String, text: String, postings: &[(&'static str, i64)]) {
+        out.push_str(&text);
+        out.push('\n');
+        for (account, cents) in postings {
+            *self.balances.entry(account).or_default() += cents;
+        }
+        self.count += 1;
+    }
+
+    fn balance(&self, account: &str) -> i64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn yuan(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+impl SyntheticLedger {
+    /// A ledger of `transactions` transactions ending this year
+    pub fn new(transactions: usize) -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // Average Gregorian year; only the year is needed
+        let this_year = 1970 + (now / 31_556_952) as i32;
+        Self { transactions, seed: 0x5EED, end_year: this_year }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Last year with transactions; benchmarks fix it so the text never changes
+    pub fn end_year(mut self, year: i32) -> Self {
+        self.end_year = year;
+        self
+    }
+
+    /// Transactions per day and the first year, spreading the ledger over at most [`MAX_YEARS`]
+    fn layout(&self) -> (usize, i32) {
+        let per_day = (self.transactions as u64).div_ceil(MAX_YEARS * 365).max(3);
+        let years = (self.transactions as u64).div_ceil(per_day * 365).clamp(1, MAX_YEARS);
+        (per_day as usize, self.end_year - years as i32 + 1)
+    }
+
+    /// Account directives, then the dated entries of each year
+    fn generate(&self) -> (String, Vec<(i32, String)>) {
+        let (per_day, start_year) = self.layout();
+        let start = format!("{}-01-01", start_year);
+
+        let mut header = String::from("option \"title\" \"Synthetic ledger\"\noption \"operating_currency\" \"CNY\"\n\n");
+        header.push_str(&format!("{} commodity USD\n  name: \"US Dollar\"\n\n", start));
+        for (account, currency) in ACCOUNTS {
+            header.push_str(&format!("{} open {} {}\n", start, account, currency));
+        }
+
+        let mut rng = Rng(self.seed.max(1));
+        let mut ledger = Entries::default();
+        let mut years: Vec<(i32, String)> = Vec::new();
+        let mut usd_rate = 700;
+
+        let mut year = start_year;
+        'days: loop {
+            let mut out = String::new();
+            for month in 1..=12 {
+                for day in 1..=days_in_month(year, month) {
+                    if ledger.count >= self.transactions {
+                        years.push((year, out));
+                        break 'days;
+                    }
+                    let date = format!("{}-{:02}-{:02}", year, month, day);
+                    let day_start = ledger.count;
+
+                    if month == 1 && day == 1 {
+                        if year == start_year {
+                            ledger.push(&mut out, format!("{} * \"Opening balances\"\n  {}  50000.00 CNY\n  Equity:Opening-Balances\n", date, CHECKING),
+                                &[(CHECKING, 5_000_000)]);
+                        } else {
+                            out.push_str(&format!("{} balance {}  {} CNY\n\n", date, CHECKING, yuan(ledger.balance(CHECKING))));
+                        }
+                    }
+                    if day == 1 {
+                        usd_rate = (usd_rate + rng.below(21) as i64 - 10).clamp(600, 800);
+                        out.push_str(&format!("{} price USD  {} CNY\n\n", date, yuan(usd_rate)));
+                        let salary = per_day as i64 * 1_200_000;
+                        ledger.push(&mut out, format!("{} * \"Employer\" \"Salary\"\n  {}  {} CNY\n  Income:Salary  {} CNY\n", date, CHECKING, yuan(salary), yuan(-salary)),
+                            &[(CHECKING, salary)]);
+                        ledger.push(&mut out, format!("{} * \"Landlord\" \"Rent\" #housing\n  Expenses:Housing:Rent  5000.00 CNY\n  {}\n", date, CHECKING),
+                            &[(CHECKING, -500_000)]);
+                        let withdrawal = (per_day as i64 * 100_000).min(ledger.balance(CHECKING));
+                        if withdrawal > 0 {
+                            ledger.push(&mut out, format!("{} * \"ATM\" \"Cash withdrawal\"\n  Assets:Cash  {} CNY\n  {}\n", date, yuan(withdrawal), CHECKING),
+                                &[("Assets:Cash", withdrawal), (CHECKING, -withdrawal)]);
+                        }
+                    }
+                    if day == 15 {
+                        let owed = (-ledger.balance(CARD)).min(ledger.balance(CHECKING));
+                        ledger.push(&mut out, format!("{} * \"Card payment\" ^card-{}-{:02}\n  {}  {} CNY\n  {}\n", date, year, month, CARD, yuan(owed), CHECKING),
+                            &[(CARD, owed), (CHECKING, -owed)]);
+                        let dollars = 50 + rng.below(200) as i64;
+                        let cost = dollars * usd_rate;
+                        if cost <= ledger.balance(CHECKING) {
+                            ledger.push(&mut out, format!("{} * \"Broker\" \"Buy USD\"\n  {}  {}.00 USD @ {} CNY\n  {}  {} CNY\n", date, BROKER, dollars, yuan(usd_rate), CHECKING, yuan(-cost)),
+                                &[(CHECKING, -cost)]);
+                        }
+                    }
+
+                    while ledger.count - day_start < per_day && ledger.count < self.transactions {
+                        let (expense, payees, max) = SPENDING[rng.below(SPENDING.len() as u64) as usize];
+                        let payee = payees[rng.below(3) as usize];
+                        let cents = 100 + rng.below(max * 100) as i64;
+                        let from = match rng.below(10) {
+                            6 | 7 if ledger.balance("Assets:Cash") >= cents => "Assets:Cash",
+                            8 | 9 if ledger.balance(CHECKING) >= cents => CHECKING,
+                            _ => CARD,
+                        };
+                        let mut text = format!("{} * \"{}\" \"{}\"", date, payee, expense.rsplit(':').next().unwrap_or(expense));
+                        if ledger.count % 7 == 0 {
+                            text.push_str(&format!(" #trip-{}", year));
+                        }
+                        text.push('\n');
+                        if ledger.count % 10 == 0 {
+                            text.push_str(&format!("  receipt: \"r{}.jpg\"\n", ledger.count));
+                        }
+                        text.push_str(&format!("  {}  {} CNY\n  {}\n", expense, yuan(cents), from));
+                        ledger.push(&mut out, text, &[(from, -cents)]);
+                    }
+                }
+            }
+            years.push((year, out));
+            if year >= self.end_year {
+                break;
+            }
+            year += 1;
+        }
+        (header, years)
+    }
+
+    /// The whole ledger as one file
+    pub fn render(&self) -> String {
+        let (header, years) = self.generate();
+        let mut out = header;
+        for (_, text) in years {
+            out.push('\n');
+            out.push_str(&text);
+        }
+        out
+    }
+
+    /// (relative path, content) of `main.bean`, `accounts.bean` and `years/<year>.bean`
+    pub fn files(&self) -> Vec<(String, String)> {
+        let (header, years) = self.generate();
+        let mut main = String::from("include \"accounts.bean\"\n");
+        let mut files = vec![("accounts.bean".to_string(), header)];
+        for (year, text) in years {
+            main.push_str(&format!("include \"years/{}.bean\"\n", year));
+            files.push((format!("years/{}.bean", year), text));
+        }
+        files.insert(0, ("main.bean".to_string(), main));
+        files
+    }
+
+    /// Write [`Self::files`] under `dir`; returns the path of `main.bean`
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        for (name, content) in self.files() {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)?;
+        }
+        Ok(dir.join("main.bean"))
+    }
+}
@@ -1,14 +1,25 @@
 //! Beanweb main entry point
+//!
+//! `beanweb serve` (or no command) runs the web interface. The other
+//! commands load the ledger, act on it and exit:
+//! - `check`: parse errors and integrity issues, exit code 1 if there are any
+//! - `export --format csv|json`: every transaction, to stdout or `--output`
+//! - `fmt`: normalize indentation and align amounts in the ledger files
 
 use beanweb_api::start_server;
-use beanweb_config::Config;
-use beanweb_core::Ledger;
-use beanweb_parser::DefaultBeancountParser;
-use clap::Parser;
-use std::path::PathBuf;
+use beanweb_config::{Config, LogFormat, LoggingConfig, StartupMode};
+use beanweb_core::{export, format, Ledger, Severity};
+use beanweb_parser::{ParserRef, ParserRegistry};
+use beanweb_utils::synthetic::SyntheticLedger;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::runtime::Runtime;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser, Debug)]
 #[command(name = "beanweb")]
@@ -17,44 +28,281 @@ use tokio::runtime::Runtime;
 #[command(about = "A lightweight, high-performance Beancount web interface", long_about = None)]
 struct Args {
     /// Configuration file path
-    #[arg(short, long, default_value = "config.yaml")]
+    #[arg(short, long, default_value = "config.yaml", global = true)]
     config: PathBuf,
-}
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
+    /// Log level or RUST_LOG directives (`info,beanweb_api=debug`),
+    /// overriding RUST_LOG and `logging.level`
+    #[arg(long, value_name = "LEVEL", global = true, value_parser = beanweb_config::parse_log_filter)]
+    log_level: Option<String>,
 
-    let args = Args::parse();
-    let rt = Runtime::new()?;
+    /// Log format (text or json), overriding `logging.format`
+    #[arg(long, value_name = "FORMAT", global = true)]
+    log_format: Option<LogFormat>,
 
-    rt.block_on(async {
-        let config = Config::load(args.config.clone())
-            .expect("Failed to load configuration");
+    /// Without a command, beanweb serves
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve the web interface (the default)
+    Serve(ServeArgs),
+    #[command(flatten)]
+    Ledger(LedgerCommand),
+}
 
-        eprintln!("[INFO] Config loaded: data path={}, main_file={}",
-            config.data.path.to_string_lossy(), config.data.main_file);
+/// Commands that load the ledger, act on it and exit
+#[derive(Subcommand, Debug)]
+enum LedgerCommand {
+    /// Load the ledger and print parse errors and integrity issues; exits with 1 if there are any
+    Check,
+    /// Write every transaction of the ledger
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Normalize indentation and align posting amounts in the ledger files, in place
+    Fmt {
+        /// Only list the files that would change; exits with 1 if there are any
+        #[arg(long)]
+        check: bool,
+    },
+}
 
-        let parser = Arc::new(DefaultBeancountParser::default());
-        let ledger = Arc::new(RwLock::new(Ledger::new(config.clone(), parser)));
+#[derive(clap::Args, Debug, Default)]
+struct ServeArgs {
+    /// Serve a generated ledger of this many transactions instead of the configured one
+    #[arg(long, value_name = "TRANSACTIONS", num_args = 0..=1, default_missing_value = "5000")]
+    demo: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportFormat {
+    /// One row per posting, as `/api/transactions/export`
+    Csv,
+    /// The transactions as a JSON array
+    Json,
+}
 
-        // Try to load the ledger if the data directory exists
-        let data_path = config.data.path.join(&config.data.main_file);
-        eprintln!("[INFO] Looking for ledger file: {}", data_path.to_string_lossy());
+/// Log to stderr at `--log-level`, else RUST_LOG, else `logging.level`
+fn init_logging(logging: &LoggingConfig, args: &Args) {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&logging.level)),
+    };
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    match args.log_format.unwrap_or(logging.format) {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
 
-        if data_path.exists() {
-            eprintln!("[INFO] Ledger file found, loading...");
-            let mut ledger_guard = ledger.write().await;
-            let result = ledger_guard.load(data_path).await;
-            match result {
-                Ok(_) => eprintln!("[INFO] Ledger loaded successfully"),
-                Err(e) => eprintln!("[ERROR] Failed to load ledger: {:?}", e),
+/// Keep retrying the initial load with exponential backoff (1s, 2s, 4s ... capped at 60s)
+/// `max_attempts` counts the first attempt made before the server started; 0 means no limit
+async fn retry_load(ledger: Arc<RwLock<Ledger>>, path: PathBuf, max_attempts: u32) {
+    let mut delay = std::time::Duration::from_secs(1);
+    loop {
+        let status = ledger.read().await.load_status();
+        if status.loaded {
+            // Loaded in the meantime, e.g. by a manual reload
+            return;
+        }
+        let attempts = status.attempts;
+        if max_attempts > 0 && attempts >= max_attempts {
+            tracing::error!("Giving up loading ledger after {} attempts", attempts);
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        match ledger.write().await.load(path.clone()).await {
+            Ok(_) => {
+                tracing::info!("Ledger loaded successfully after {} attempts", attempts + 1);
+                return;
             }
-        } else {
-            eprintln!("[WARN] Ledger file not found: {}", data_path.display());
+            Err(e) => tracing::warn!("Ledger load attempt {} failed: {}", attempts + 1, e),
         }
+        delay = (delay * 2).min(std::time::Duration::from_secs(60));
+    }
+}
 
-        start_server(config, ledger).await
+/// `beanweb serve`: load the ledger as `server.startup_mode` says and serve it
+async fn serve(mut config: Config, args: ServeArgs) {
+    if let Some(transactions) = args.demo {
+        let dir = std::env::temp_dir().join("beanweb-demo");
+        let main = SyntheticLedger::new(transactions).write_to(&dir)
+            .expect("Failed to write demo ledger");
+        tracing::info!("Demo mode: {} generated transactions in {}", transactions, dir.display());
+        config.data.path = dir;
+        config.data.main_file = main.file_name().unwrap().to_string_lossy().into_owned();
+    }
+
+    tracing::info!("Config loaded: data path={}, main_file={}",
+        config.data.path.to_string_lossy(), config.data.main_file);
+
+    let parser = parser(&config).unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
     });
+    let ledger = Arc::new(RwLock::new(Ledger::new(config.clone(), parser)));
+
+    // Load the ledger; the outcome is kept in the ledger's load status
+    let data_path = config.data.path.join(&config.data.main_file);
+    tracing::debug!("Looking for ledger file: {}", data_path.to_string_lossy());
+
+    if !data_path.exists() {
+        tracing::warn!("Ledger file not found: {}", data_path.display());
+    }
+    let result = ledger.write().await.load(data_path.clone()).await;
+    match result {
+        Ok(_) => tracing::info!("Ledger loaded successfully"),
+        Err(e) => {
+            tracing::error!("Failed to load ledger: {:?}", e);
+            match config.server.startup_mode {
+                StartupMode::FailFast => {
+                    tracing::error!("startup_mode is fail_fast, exiting");
+                    std::process::exit(1);
+                }
+                StartupMode::ServeWithBanner => {}
+                StartupMode::Retry => {
+                    tokio::spawn(retry_load(ledger.clone(), data_path, config.server.startup_retry_max_attempts));
+                }
+            }
+        }
+    }
+
+    start_server(config, ledger).await
+}
+
+/// The parser named by `data.parser`
+fn parser(config: &Config) -> Result<ParserRef, String> {
+    let registry = ParserRegistry::default();
+    registry.get(&config.data.parser).ok_or_else(|| {
+        format!("Unknown parser `{}` in data.parser (available: {})", config.data.parser, registry.names().join(", "))
+    })
+}
 
-    Ok(())
+/// Load the configured ledger for a command that doesn't serve
+async fn load_ledger(config: &Config) -> Result<Ledger, Box<dyn std::error::Error>> {
+    let mut ledger = Ledger::new(config.clone(), parser(config)?);
+    ledger.load(config.data.path.join(&config.data.main_file)).await?;
+    Ok(ledger)
+}
+
+/// `beanweb check`: parse errors and warnings, then integrity issues
+fn check(ledger: &Ledger) -> ExitCode {
+    let parse_errors = ledger.parse_errors();
+    let warnings = parse_errors.iter().filter(|e| e.severity == Severity::Warning).count();
+    for error in &parse_errors {
+        let severity = match error.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        println!("{}: {}", severity, error);
+    }
+    let issues = ledger.integrity_check().issues;
+    for issue in &issues {
+        println!("error: {} {} ({}): {}", issue.date, issue.kind, issue.account, issue.message);
+    }
+
+    let errors = parse_errors.len() - warnings + issues.len();
+    println!("{} errors, {} warnings", errors, warnings);
+    if errors > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+/// `beanweb export`: every transaction to `output`, or stdout
+fn export(ledger: &Ledger, format: ExportFormat, output: Option<&Path>) -> std::io::Result<()> {
+    let transactions = ledger.all_transactions();
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match format {
+        ExportFormat::Csv => {
+            out.write_all(export::CSV_HEADER.as_bytes())?;
+            for tx in &transactions {
+                out.write_all(export::transaction_csv_rows(tx).as_bytes())?;
+            }
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &transactions)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()
+}
+
+/// `beanweb fmt`: rewrite every file of the ledger whose layout changes and
+/// print its path; with `check_only` nothing is written. Files are replaced
+/// as by the web editor, the previous content kept as `<file>.bak`; a file
+/// whose directives would read differently afterwards is left alone.
+async fn fmt(config: &Config, ledger: &Ledger, check_only: bool) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let parser = parser(config)?;
+    let mut changed = 0;
+    let mut refused = 0;
+    for stats in ledger.file_stats() {
+        let source = std::fs::read_to_string(&stats.path)?;
+        let formatted = match format::format_verified(parser.as_ref(), &source).await {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}: {}", stats.path, e);
+                refused += 1;
+                continue;
+            }
+        };
+        if formatted != source {
+            if !check_only {
+                ledger.write_document(&stats.path, &formatted)?;
+            }
+            println!("{}", stats.path);
+            changed += 1;
+        }
+    }
+    Ok(if refused > 0 || (check_only && changed > 0) { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
+
+/// Run a command on the loaded ledger
+async fn run(config: &Config, command: LedgerCommand) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let ledger = load_ledger(config).await?;
+    Ok(match command {
+        LedgerCommand::Check => check(&ledger),
+        LedgerCommand::Export { format, output } => {
+            export(&ledger, format, output.as_deref())?;
+            ExitCode::SUCCESS
+        }
+        LedgerCommand::Fmt { check } => fmt(config, &ledger, check).await?,
+    })
+}
+
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.serve.demo.is_some() && matches!(args.command, Some(Command::Ledger(_))) {
+        Args::command().error(ErrorKind::ArgumentConflict, "--demo only applies to serve").exit();
+    }
+    let rt = Runtime::new()?;
+
+    rt.block_on(async {
+        let config = Config::load(args.config.clone())
+            .expect("Failed to load configuration");
+        init_logging(&config.logging, &args);
+
+        match args.command {
+            None => serve(config, args.serve).await,
+            Some(Command::Serve(serve_args)) => serve(config, serve_args).await,
+            Some(Command::Ledger(command)) => {
+                return Ok(run(&config, command).await.unwrap_or_else(|e| {
+                    eprintln!("error: {}", e);
+                    ExitCode::FAILURE
+                }));
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    })
 }
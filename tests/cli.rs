@@ -0,0 +1,103 @@
+//! The commands that load the ledger and exit, run as a user would
+
+use beanweb_config::Config;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+const LEDGER: &str = "2024-01-01 open Assets:Bank CNY
+2024-01-01 open Expenses:Food CNY
+
+2024-01-05 * \"Shop\" \"Lunch\"
+    Expenses:Food    20.00 CNY
+  Assets:Bank
+";
+
+/// A data directory holding `main_bean` and a config.yaml pointing at it
+fn ledger_dir(main_bean: &str) -> PathBuf {
+    let id = DIRS.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("beanweb-cli-test-{}-{}", std::process::id(), id));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.bean"), main_bean).unwrap();
+    let mut config: Config = serde_yaml::from_str(Config::generate_default()).unwrap();
+    config.data.path = dir.clone();
+    config.data.main_file = "main.bean".to_string();
+    std::fs::write(dir.join("config.yaml"), serde_yaml::to_string(&config).unwrap()).unwrap();
+    dir
+}
+
+fn beanweb(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_beanweb"))
+        .arg("--config").arg(dir.join("config.yaml"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_check_exit_code() {
+    let clean = ledger_dir(LEDGER);
+    let output = beanweb(&clean, &["check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("0 errors, 0 warnings"));
+
+    let broken = ledger_dir(&format!("{}\n2024-01-06 balance Assets:Bank 5.00 CNY\n", LEDGER));
+    let output = beanweb(&broken, &["check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("1 errors"));
+
+    std::fs::remove_dir_all(clean).unwrap();
+    std::fs::remove_dir_all(broken).unwrap();
+}
+
+#[test]
+fn test_export() {
+    let dir = ledger_dir(LEDGER);
+    let csv = stdout(&beanweb(&dir, &["export"]));
+    assert_eq!(csv.lines().count(), 3, "header and one row per posting:\n{}", csv);
+    assert!(csv.contains("Expenses:Food") && csv.contains("Lunch"));
+
+    let output = dir.join("out.json");
+    let status = beanweb(&dir, &["export", "--format", "json", "--output", output.to_str().unwrap()]).status;
+    assert!(status.success());
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 1);
+    assert_eq!(json[0]["narration"], "Lunch");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_fmt() {
+    let dir = ledger_dir(LEDGER);
+    let output = beanweb(&dir, &["fmt", "--check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("main.bean"));
+    assert_eq!(std::fs::read_to_string(dir.join("main.bean")).unwrap(), LEDGER);
+
+    assert!(beanweb(&dir, &["fmt"]).status.success());
+    let formatted = std::fs::read_to_string(dir.join("main.bean")).unwrap();
+    assert!(formatted.contains("\n  Expenses:Food  20.00 CNY\n"));
+    assert_eq!(std::fs::read_to_string(dir.join("main.bean.bak")).unwrap(), LEDGER);
+    assert_eq!(beanweb(&dir, &["fmt", "--check"]).status.code(), Some(0));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_unknown_parser() {
+    let dir = ledger_dir(LEDGER);
+    let config = std::fs::read_to_string(dir.join("config.yaml")).unwrap();
+    std::fs::write(dir.join("config.yaml"), config.replace("parser: simple", "parser: nope")).unwrap();
+    let output = beanweb(&dir, &["check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown parser `nope` in data.parser (available: simple)"));
+    std::fs::remove_dir_all(dir).unwrap();
+}